@@ -0,0 +1,54 @@
+use crate::model::Problem;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The service answered with a problem document; `problem.code` tells
+    /// which, such as `BLOCK_NOT_FOUND` or `NOT_INDEXED`.
+    #[error("{} {}: {}", problem.status, problem.code, problem.detail)]
+    Api { problem: Box<Problem> },
+
+    /// An error status without a problem document, as a proxy in front of
+    /// the service might send.
+    #[error("HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+
+    #[error("JSON-RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
+
+    #[error("Proof checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error("Unexpected response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    /// The problem code of an API error.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Error::Api { problem } => Some(&problem.code),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of an error response.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api { problem } => Some(problem.status),
+            Error::Status { status, .. } => Some(*status),
+            Error::Http(e) => e.status().map(|status| status.as_u16()),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,714 @@
+//! Async client for the Raito proving service's HTTP API.
+//!
+//! ```no_run
+//! # async fn run() -> raito_client::Result<()> {
+//! use futures_util::TryStreamExt;
+//!
+//! let client = raito_client::Client::new("http://localhost:8080")?;
+//! let block = client.block("869123", true).await?;
+//! let verified: Vec<_> = client
+//!     .blocks_stream(raito_client::model::BlocksQuery {
+//!         verified: Some(true),
+//!         ..Default::default()
+//!     })
+//!     .try_collect()
+//!     .await?;
+//!
+//! let mut file = tokio::fs::File::create("proof.json").await?;
+//! client.download_proof(block.summary.height, &mut file).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+pub mod model;
+mod sse;
+
+pub use error::{Error, Result};
+
+use bytes::Bytes;
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+use model::*;
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    RequestBuilder, Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{future::Future, time::Duration};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Paths of the service's API this client has a method for, as the server's
+/// OpenAPI document lists them. `/v1/ws` is left out: `/v1/events` carries
+/// the same events.
+pub const ENDPOINTS: &[&str] = &[
+    "/v1/blocks",
+    "/v1/blocks/orphans",
+    "/v1/blocks/{identifier}",
+    "/v1/blocks/{identifier}/stats",
+    "/v1/blocks/{identifier}/txids",
+    "/v1/blocks/{height}/proof",
+    "/v1/tx",
+    "/v1/tx/{txid}",
+    "/v1/tx/{txid}/detail",
+    "/v1/tx/{txid}/raw",
+    "/v1/utxo/{txid}/{vout}",
+    "/v1/address/{address}/txs",
+    "/v1/address/{address}/utxos",
+    "/v1/header/{hash}",
+    "/v1/header/{hash}/raw",
+    "/v1/proofs",
+    "/v1/proofs/coverage",
+    "/v1/jobs/{id}",
+    "/v1/chain",
+    "/v1/version",
+    "/v1/stats",
+    "/v1/stats/proof-latency",
+    "/v1/usage",
+    "/v1/events",
+    "/v1/rpc",
+    "/healthz",
+    "/readyz",
+];
+
+const RESUME_TOKEN_HEADER: &str = "x-resume-token";
+const PROOF_CHECKSUM_HEADER: &str = "x-proof-checksum";
+
+/// How requests are retried when the service is throttling or briefly
+/// unavailable (429, 502, 503, 504) or cannot be reached.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 turns retrying off.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    /// Longest wait between attempts, also bounding `Retry-After`.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `attempt`, counted from 0: the server's
+    /// `Retry-After` when it sent one, doubling from `initial_backoff`
+    /// otherwise.
+    pub fn backoff(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after
+            .unwrap_or_else(|| {
+                self.initial_backoff
+                    .saturating_mul(2u32.saturating_pow(attempt))
+            })
+            .min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Where the service is served, without the `/v1` prefix.
+    pub base_url: String,
+    /// Sent as `Authorization: Bearer <key>`.
+    pub api_key: Option<String>,
+    /// Limit on each JSON request; proof downloads and event streams run as
+    /// long as they need.
+    pub timeout: Duration,
+    pub retry: RetryPolicy,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:8080".to_string(),
+            api_key: None,
+            timeout: Duration::from_secs(30),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// An event from `/v1/events`, or notice of the ones missed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notification {
+    /// `id` can be passed back as `last_event_id` to resume after it.
+    Event { id: u64, event: Event },
+    /// Events the client fell too far behind to receive.
+    Lagged { skipped: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    config: ClientConfig,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::with_config(ClientConfig {
+            base_url: base_url.into(),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_config(config: ClientConfig) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        if let Some(key) = &config.api_key {
+            let value = HeaderValue::from_str(&format!("Bearer {key}"))
+                .map_err(|_| Error::InvalidConfiguration("API key is not a valid header".into()))?;
+            headers.insert(header::AUTHORIZATION, value);
+        }
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("raito-client/", env!("CARGO_PKG_VERSION")))
+            .default_headers(headers)
+            .build()?;
+        Ok(Self { http, config })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.config.base_url.trim_end_matches('/'))
+    }
+
+    /// Sends the request `build` makes, retrying per the [`RetryPolicy`],
+    /// and turns error statuses into [`Error`]s.
+    async fn send(&self, build: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let retry = &self.config.retry;
+        let mut attempt = 0;
+        loop {
+            let retry_after = match build().send().await {
+                Ok(response) if is_retryable(response.status()) && attempt < retry.max_retries => {
+                    retry_after(&response)
+                }
+                Ok(response)
+                    if response.status().is_client_error()
+                        || response.status().is_server_error() =>
+                {
+                    return Err(error_from(response).await)
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if (e.is_connect() || e.is_timeout()) && attempt < retry.max_retries => None,
+                Err(e) => return Err(e.into()),
+            };
+            tokio::time::sleep(retry.backoff(attempt, retry_after)).await;
+            attempt += 1;
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &(impl Serialize + ?Sized),
+    ) -> Result<T> {
+        let url = self.url(path);
+        let response = self
+            .send(|| {
+                self.http
+                    .get(&url)
+                    .query(query)
+                    .timeout(self.config.timeout)
+            })
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    async fn get_bytes(&self, path: &str) -> Result<Bytes> {
+        let url = self.url(path);
+        let response = self
+            .send(|| self.http.get(&url).timeout(self.config.timeout))
+            .await?;
+        Ok(response.bytes().await?)
+    }
+
+    /// One page of blocks.
+    pub async fn blocks(&self, query: &BlocksQuery) -> Result<BlocksResponse> {
+        self.get_json("/v1/blocks", query).await
+    }
+
+    /// Every block from `query.cursor` on, fetching pages as the stream is
+    /// read. Pages are followed forward, so `direction` is ignored.
+    pub fn blocks_stream(
+        &self,
+        query: BlocksQuery,
+    ) -> impl Stream<Item = Result<BlockSummary>> + '_ {
+        paginate(query.cursor, move |cursor| {
+            let query = BlocksQuery {
+                cursor,
+                direction: None,
+                ..query.clone()
+            };
+            async move {
+                let page = self.blocks(&query).await?;
+                Ok((page.blocks, page.next_cursor.filter(|_| page.has_next)))
+            }
+        })
+    }
+
+    pub async fn orphans(
+        &self,
+        limit: Option<u32>,
+        cursor: Option<i64>,
+    ) -> Result<OrphansResponse> {
+        self.get_json(
+            "/v1/blocks/orphans",
+            &[("limit", limit.map(|l| l as i64)), ("cursor", cursor)],
+        )
+        .await
+    }
+
+    /// Every orphaned block, newest first.
+    pub fn orphans_stream(&self) -> impl Stream<Item = Result<BlockTombstone>> + '_ {
+        paginate(None, move |cursor| async move {
+            let page = self.orphans(None, cursor).await?;
+            Ok((page.orphans, page.next_cursor.filter(|_| page.has_next)))
+        })
+    }
+
+    /// A block by height or hash. Large blocks can leave `txids` out and page
+    /// through [`Client::block_txids_stream`] instead.
+    pub async fn block(&self, identifier: &str, include_txids: bool) -> Result<BlockDetail> {
+        self.get_json(
+            &format!("/v1/blocks/{identifier}"),
+            &[("include_txids", include_txids)],
+        )
+        .await
+    }
+
+    pub async fn block_stats(&self, identifier: &str) -> Result<BlockStats> {
+        self.get_json(&format!("/v1/blocks/{identifier}/stats"), &())
+            .await
+    }
+
+    pub async fn block_txids(
+        &self,
+        identifier: &str,
+        limit: Option<u32>,
+        cursor: Option<u32>,
+    ) -> Result<BlockTxidsResponse> {
+        self.get_json(
+            &format!("/v1/blocks/{identifier}/txids"),
+            &[("limit", limit), ("cursor", cursor)],
+        )
+        .await
+    }
+
+    /// Every txid of a block, in block order.
+    pub fn block_txids_stream<'a>(
+        &'a self,
+        identifier: &'a str,
+    ) -> impl Stream<Item = Result<String>> + 'a {
+        paginate(None, move |cursor| async move {
+            let page = self.block_txids(identifier, None, cursor).await?;
+            Ok((page.txids, page.next_cursor.filter(|_| page.has_next)))
+        })
+    }
+
+    /// The proof of a block as a stream of chunks, without resuming or
+    /// checking it; see [`Client::download_proof`] for both.
+    pub async fn proof_stream(&self, height: u32) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let url = self.url(&format!("/v1/blocks/{height}/proof"));
+        let response = self.send(|| self.http.get(&url)).await?;
+        Ok(response.bytes_stream().map_err(Error::from))
+    }
+
+    /// Streams the proof of a block into `writer`, picking up where it left
+    /// off when the connection drops, and checks it against the checksum
+    /// the service sent. Returns the number of bytes written.
+    pub async fn download_proof<W: AsyncWrite + Unpin>(
+        &self,
+        height: u32,
+        writer: &mut W,
+    ) -> Result<u64> {
+        let url = self.url(&format!("/v1/blocks/{height}/proof"));
+        let retry = &self.config.retry;
+        let mut written = 0u64;
+        let mut hasher = Sha256::new();
+        let mut resume_token: Option<String> = None;
+        let mut checksum: Option<String> = None;
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .send(|| {
+                    let request = self.http.get(&url);
+                    match &resume_token {
+                        Some(token) if written > 0 => request
+                            .header(header::RANGE, format!("bytes={written}-"))
+                            .header(RESUME_TOKEN_HEADER, token),
+                        _ => request,
+                    }
+                })
+                .await?;
+            if written > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+                return Err(Error::InvalidResponse(format!(
+                    "expected the rest of the proof from byte {written}, got status {}",
+                    response.status()
+                )));
+            }
+            let header = |name: &str| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            };
+            resume_token = header(RESUME_TOKEN_HEADER).or(resume_token);
+            checksum = checksum.or_else(|| {
+                header(PROOF_CHECKSUM_HEADER)
+                    .and_then(|value| value.strip_prefix("sha256=").map(str::to_string))
+            });
+
+            let mut body = response.bytes_stream();
+            let interrupted = loop {
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        hasher.update(&chunk);
+                        writer.write_all(&chunk).await?;
+                        written += chunk.len() as u64;
+                    }
+                    Some(Err(e)) => break Some(e),
+                    None => break None,
+                }
+            };
+            match interrupted {
+                None => break,
+                // Without a token the next attempt could not continue the file
+                Some(e) if resume_token.is_none() || attempt >= retry.max_retries => {
+                    return Err(e.into())
+                }
+                Some(_) => {
+                    tokio::time::sleep(retry.backoff(attempt, None)).await;
+                    attempt += 1;
+                }
+            }
+        }
+        writer.flush().await?;
+
+        if let Some(expected) = checksum {
+            let actual = hex::encode(hasher.finalize());
+            if actual != expected {
+                return Err(Error::ChecksumMismatch { expected, actual });
+            }
+        }
+        Ok(written)
+    }
+
+    pub async fn transaction_status(&self, txid: &str) -> Result<TransactionStatus> {
+        self.get_json(&format!("/v1/tx/{txid}"), &()).await
+    }
+
+    /// Relays a serialized transaction through the service's upstream node;
+    /// follow its inclusion with [`Client::transaction_status`].
+    pub async fn broadcast_transaction(&self, raw: &[u8]) -> Result<TransactionBroadcast> {
+        let url = self.url("/v1/tx");
+        let body = hex::encode(raw);
+        let response = self
+            .send(|| {
+                self.http
+                    .post(&url)
+                    .header(header::CONTENT_TYPE, "text/plain")
+                    .body(body.clone())
+                    .timeout(self.config.timeout)
+            })
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn transaction_detail(&self, txid: &str) -> Result<TransactionDetail> {
+        self.get_json(&format!("/v1/tx/{txid}/detail"), &()).await
+    }
+
+    /// The transaction's serialized bytes.
+    pub async fn raw_transaction(&self, txid: &str) -> Result<Bytes> {
+        self.get_bytes(&format!("/v1/tx/{txid}/raw")).await
+    }
+
+    pub async fn output_status(&self, txid: &str, vout: u32) -> Result<OutputStatus> {
+        self.get_json(&format!("/v1/utxo/{txid}/{vout}"), &()).await
+    }
+
+    pub async fn address_transactions(&self, address: &str) -> Result<AddressTransactionsResponse> {
+        self.get_json(&format!("/v1/address/{address}/txs"), &())
+            .await
+    }
+
+    pub async fn address_utxos(&self, address: &str) -> Result<AddressUtxosResponse> {
+        self.get_json(&format!("/v1/address/{address}/utxos"), &())
+            .await
+    }
+
+    pub async fn header_status(&self, hash: &str) -> Result<HeaderStatus> {
+        self.get_json(&format!("/v1/header/{hash}"), &()).await
+    }
+
+    /// The 80-byte block header.
+    pub async fn raw_header(&self, hash: &str) -> Result<Bytes> {
+        self.get_bytes(&format!("/v1/header/{hash}/raw")).await
+    }
+
+    pub async fn proofs(&self, query: &ProofsQuery) -> Result<ProofsResponse> {
+        self.get_json("/v1/proofs", query).await
+    }
+
+    /// Every proof matching `query`, from `query.cursor` on.
+    pub fn proofs_stream(
+        &self,
+        query: ProofsQuery,
+    ) -> impl Stream<Item = Result<ProofRecord>> + '_ {
+        paginate(query.cursor, move |cursor| {
+            let query = ProofsQuery {
+                cursor,
+                ..query.clone()
+            };
+            async move {
+                let page = self.proofs(&query).await?;
+                Ok((page.proofs, page.next_cursor.filter(|_| page.has_next)))
+            }
+        })
+    }
+
+    pub async fn proof_coverage(
+        &self,
+        limit: Option<u32>,
+        cursor: Option<u32>,
+    ) -> Result<ProofCoverageResponse> {
+        self.get_json(
+            "/v1/proofs/coverage",
+            &[("limit", limit), ("cursor", cursor)],
+        )
+        .await
+    }
+
+    /// Every range of indexed blocks without a proof.
+    pub fn proof_coverage_stream(&self) -> impl Stream<Item = Result<ProofGap>> + '_ {
+        paginate(None, move |cursor| async move {
+            let page = self.proof_coverage(None, cursor).await?;
+            Ok((page.gaps, page.next_cursor.filter(|_| page.has_next)))
+        })
+    }
+
+    pub async fn proof_job(&self, id: i64) -> Result<ProofJob> {
+        self.get_json(&format!("/v1/jobs/{id}"), &()).await
+    }
+
+    pub async fn chain(&self) -> Result<ChainInfo> {
+        self.get_json("/v1/chain", &()).await
+    }
+
+    pub async fn version(&self) -> Result<VersionInfo> {
+        self.get_json("/v1/version", &()).await
+    }
+
+    pub async fn network_stats(&self) -> Result<NetworkStats> {
+        self.get_json("/v1/stats", &()).await
+    }
+
+    /// Proof latency percentiles over the last `window` proofs.
+    pub async fn proof_latency(&self, window: Option<u32>) -> Result<ProofLatencyStats> {
+        self.get_json("/v1/stats/proof-latency", &[("window", window)])
+            .await
+    }
+
+    /// Usage and quotas of the configured API key.
+    pub async fn usage(&self) -> Result<UsageResponse> {
+        self.get_json("/v1/usage", &()).await
+    }
+
+    /// Index events as they happen. `kinds` picks event types, such as
+    /// `block_indexed`, all when empty; `last_event_id` replays the events
+    /// since that one first.
+    pub async fn events(
+        &self,
+        kinds: &[&str],
+        last_event_id: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Notification>>> {
+        let url = self.url("/v1/events");
+        let events = kinds.join(",");
+        let response = self
+            .send(|| {
+                let mut request = self
+                    .http
+                    .get(&url)
+                    .header(header::ACCEPT, "text/event-stream");
+                if !events.is_empty() {
+                    request = request.query(&[("events", &events)]);
+                }
+                if let Some(id) = last_event_id {
+                    request = request.header("last-event-id", id.to_string());
+                }
+                request
+            })
+            .await?;
+
+        let mut parser = sse::Parser::default();
+        Ok(response
+            .bytes_stream()
+            .map_err(Error::from)
+            .map_ok(move |chunk| stream::iter(parser.push(&chunk).into_iter().map(notification)))
+            .try_flatten())
+    }
+
+    /// Calls a method of the Bitcoin Core compatible JSON-RPC endpoint.
+    pub async fn rpc(&self, method: &str, params: Value) -> Result<Value> {
+        let url = self.url("/v1/rpc");
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let response = self
+            .send(|| {
+                self.http
+                    .post(&url)
+                    .json(&request)
+                    .timeout(self.config.timeout)
+            })
+            .await?;
+        let mut reply: Value = response.json().await?;
+        if let Some(error) = reply.get("error").filter(|error| !error.is_null()) {
+            return Err(Error::Rpc {
+                code: error["code"].as_i64().unwrap_or_default(),
+                message: error["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        Ok(reply["result"].take())
+    }
+
+    /// The service's health. Answered even while it is down, so not retried.
+    pub async fn health(&self) -> Result<HealthStatus> {
+        self.probe("/healthz").await
+    }
+
+    pub async fn readiness(&self) -> Result<ReadinessStatus> {
+        self.probe("/readyz").await
+    }
+
+    async fn probe<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self
+            .http
+            .get(self.url(path))
+            .timeout(self.config.timeout)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK | StatusCode::SERVICE_UNAVAILABLE => Ok(response.json().await?),
+            _ => Err(error_from(response).await),
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+async fn error_from(response: Response) -> Error {
+    let status = response.status().as_u16();
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(e) => return e.into(),
+    };
+    match serde_json::from_str::<Problem>(&body) {
+        Ok(problem) => Error::Api {
+            problem: Box::new(problem),
+        },
+        Err(_) => Error::Status { status, body },
+    }
+}
+
+fn notification(frame: sse::Frame) -> Result<Notification> {
+    let invalid =
+        |e: serde_json::Error| Error::InvalidResponse(format!("event {}: {e}", frame.data));
+    if frame.event.as_deref() == Some("lagged") {
+        let lagged: Value = serde_json::from_str(&frame.data).map_err(invalid)?;
+        return Ok(Notification::Lagged {
+            skipped: lagged["skipped"].as_u64().unwrap_or_default(),
+        });
+    }
+    let id = frame
+        .id
+        .as_deref()
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| Error::InvalidResponse("event without an id".to_string()))?;
+    let event = serde_json::from_str(&frame.data).map_err(invalid)?;
+    Ok(Notification::Event { id, event })
+}
+
+/// Fetches pages from `cursor` on as the stream is read, yielding their
+/// items in order. `fetch` returns a page's items and the cursor of the next
+/// page, if there is one.
+fn paginate<'a, C, T, Fut>(
+    cursor: Option<C>,
+    mut fetch: impl FnMut(Option<C>) -> Fut + 'a,
+) -> impl Stream<Item = Result<T>> + 'a
+where
+    C: 'a,
+    T: 'a,
+    Fut: Future<Output = Result<(Vec<T>, Option<C>)>> + 'a,
+{
+    // The outer `None` once the last page has been fetched
+    stream::try_unfold(Some(cursor), move |cursor| {
+        let page = cursor.map(&mut fetch);
+        async move {
+            let Some(page) = page else {
+                return Ok::<_, Error>(None);
+            };
+            let (items, next) = page.await?;
+            let items = stream::iter(items.into_iter().map(Ok));
+            Ok(Some((items, next.map(Some))))
+        }
+    })
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.backoff(0, None), Duration::from_millis(200));
+        assert_eq!(retry.backoff(2, None), Duration::from_millis(800));
+        assert_eq!(retry.backoff(10, None), Duration::from_secs(10));
+        assert_eq!(
+            retry.backoff(0, Some(Duration::from_secs(3))),
+            Duration::from_secs(3)
+        );
+        assert_eq!(
+            retry.backoff(0, Some(Duration::from_secs(60))),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginate() {
+        // Pages of two up to 5, the cursor being the last item
+        let items: Vec<u32> = paginate(Some(0), |cursor: Option<u32>| async move {
+            let start = cursor.unwrap_or_default();
+            let page: Vec<u32> = (start + 1..=(start + 2).min(5)).collect();
+            let next = page.last().copied().filter(|&last| last < 5);
+            Ok((page, next))
+        })
+        .try_collect()
+        .await
+        .unwrap();
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,491 @@
+//! Request and response types of the service's API. They mirror the
+//! server's `model` module field for field, with hashes as hex strings; the
+//! server's tests round-trip its responses through them to keep the two in
+//! step.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockSummary {
+    pub height: u32,
+    pub hash: String,
+    pub tx_count: u32,
+    /// Fees paid by the block's transactions, in BTC.
+    pub total_fees: f64,
+    pub timestamp: i64,
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockDetail {
+    #[serde(flatten)]
+    pub summary: BlockSummary,
+    pub prev_hash: String,
+    pub merkle_root: String,
+    pub witness_merkle_root: Option<String>,
+    pub witness_commitment: Option<String>,
+    pub version: i32,
+    pub bits: u32,
+    pub nonce: u32,
+    pub size: Option<u32>,
+    pub stripped_size: Option<u32>,
+    pub weight: Option<u32>,
+    pub difficulty: f64,
+    pub chainwork: Option<String>,
+    /// Empty when requested without txids, or for pruned blocks.
+    pub txids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wtxids: Vec<String>,
+    pub coinbase: Option<CoinbaseInfo>,
+    pub proof_url: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pruned: bool,
+    /// Displaced from the canonical chain by a reorg.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub orphaned: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoinbaseInfo {
+    pub txid: String,
+    /// In BTC, like `claimed_fees`.
+    pub subsidy: f64,
+    pub claimed_fees: Option<f64>,
+    pub fees_match: Option<bool>,
+    pub script_sig: Option<String>,
+    pub miner_tag: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlocksResponse {
+    pub blocks: Vec<BlockSummary>,
+    pub total: u32,
+    pub has_next: bool,
+    pub next_cursor: Option<u32>,
+    pub prev_cursor: Option<u32>,
+    /// The page was shrunk below the requested limit because the service is
+    /// under load.
+    #[serde(default)]
+    pub server_limited: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageDirection {
+    Next,
+    Prev,
+}
+
+/// Parameters of `GET /v1/blocks`; unset fields take the server's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlocksQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<SortOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<PageDirection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_timestamp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_timestamp: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockTombstone {
+    pub height: u32,
+    pub hash: String,
+    pub replaced_by: Option<String>,
+    pub reorg_depth: u32,
+    pub orphaned_at: i64,
+    pub block: BlockDetail,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrphansResponse {
+    pub orphans: Vec<BlockTombstone>,
+    pub has_next: bool,
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockStats {
+    pub height: u32,
+    pub size: u32,
+    pub weight: u32,
+    pub avg_fee_rate: f64,
+    pub median_fee_rate: f64,
+    pub input_count: u32,
+    pub output_count: u32,
+    /// In BTC.
+    pub subsidy: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockTxidsResponse {
+    pub height: u32,
+    pub txids: Vec<String>,
+    pub total: u32,
+    pub has_next: bool,
+    pub next_cursor: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionStatus {
+    pub included: bool,
+    pub block_height: Option<u32>,
+    pub block_hash: Option<String>,
+    pub confirmations: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_verified: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionBroadcast {
+    pub txid: String,
+    pub status_url: String,
+    pub status: TransactionStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionInput {
+    pub prev_txid: Option<String>,
+    pub prev_vout: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionOutput {
+    pub value: f64,
+    pub script_pubkey: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionDetail {
+    pub txid: String,
+    pub block_height: u32,
+    pub position_in_block: u32,
+    pub fee: Option<f64>,
+    pub size: Option<u32>,
+    pub vsize: Option<u32>,
+    pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptType {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    OpReturn,
+    Nonstandard,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputSpend {
+    pub txid: String,
+    pub input: u32,
+    pub block_height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputStatus {
+    pub txid: String,
+    pub vout: u32,
+    pub value: f64,
+    pub script_pubkey: String,
+    pub script_type: ScriptType,
+    pub block_height: u32,
+    pub unspent: bool,
+    pub spent_by: Option<OutputSpend>,
+    pub tip_height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressTransaction {
+    pub txid: String,
+    pub block_height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressTransactionsResponse {
+    pub address: String,
+    pub script_pubkey: String,
+    pub transactions: Vec<AddressTransaction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Utxo {
+    pub txid: String,
+    pub vout: u32,
+    pub value: f64,
+    pub block_height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressUtxosResponse {
+    pub address: String,
+    pub script_pubkey: String,
+    pub utxos: Vec<Utxo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeaderStatus {
+    pub block_hash: String,
+    pub in_chain: bool,
+    pub block_height: Option<u32>,
+    pub confirmations: Option<u32>,
+    pub verified: bool,
+    pub on_active_chain: bool,
+}
+
+/// Parameters of `GET /v1/proofs`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProofsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofRecord {
+    pub height: u32,
+    pub version: String,
+    pub size: Option<i64>,
+    pub verified: bool,
+    pub generated_at: i64,
+    pub proof_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofsResponse {
+    pub proofs: Vec<ProofRecord>,
+    pub has_next: bool,
+    pub next_cursor: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofGapReason {
+    NeverAttempted,
+    Failed,
+    Archived,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofGap {
+    pub start_height: u32,
+    pub end_height: u32,
+    pub count: u32,
+    pub reason: ProofGapReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofCoverageResponse {
+    pub gaps: Vec<ProofGap>,
+    pub has_next: bool,
+    pub next_cursor: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofJob {
+    pub id: i64,
+    pub height: u32,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkStats {
+    pub indexed_height: Option<u32>,
+    pub proved_height: Option<u32>,
+    pub proof_coverage: f64,
+    pub avg_proof_size: Option<f64>,
+    pub avg_proving_time_ms: Option<f64>,
+    pub queue_depth: u32,
+    #[serde(default)]
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofLatencyStats {
+    pub sample_count: u32,
+    pub p50_secs: Option<i64>,
+    pub p90_secs: Option<i64>,
+    pub p99_secs: Option<i64>,
+    pub max_secs: Option<i64>,
+    pub sla_secs: i64,
+    pub breaches: u32,
+    pub pending_over_sla: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainAnchor {
+    pub height: u32,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsensusParams {
+    pub halving_interval: u32,
+    pub halving_epoch: u32,
+    pub blocks_until_halving: u32,
+    /// In BTC.
+    pub subsidy: f64,
+    pub retarget_interval: u32,
+    pub blocks_until_retarget: u32,
+    pub target_block_time_secs: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainInfo {
+    pub network: String,
+    pub genesis_hash: String,
+    pub tip_height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pruned_below: Option<u32>,
+    pub consensus: ConsensusParams,
+    pub anchors: Vec<ChainAnchor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    pub git_commit: String,
+    pub built_at: Option<String>,
+    pub api_versions: Vec<String>,
+    pub proof_versions: Vec<String>,
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageCounts {
+    pub requests: u64,
+    pub proof_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageWindow {
+    pub used: UsageCounts,
+    pub request_limit: Option<u64>,
+    pub proof_bytes_limit: Option<u64>,
+    pub resets_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageResponse {
+    pub api_key_id: i64,
+    pub name: String,
+    pub daily: UsageWindow,
+    pub monthly: UsageWindow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    Up,
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub status: HealthState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthComponents {
+    pub database: ComponentHealth,
+    pub proof_storage: ComponentHealth,
+    pub prover: ComponentHealth,
+    pub sync: ComponentHealth,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthStatus {
+    pub status: HealthState,
+    pub timestamp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replica_lag_blocks: Option<u32>,
+    pub components: HealthComponents,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadinessStatus {
+    pub status: String,
+    pub circuit: CircuitState,
+    pub storage_available_bytes: Option<u64>,
+    pub timestamp: i64,
+}
+
+/// Something that changed in the index, as pushed on `/v1/events`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    BlockIndexed { height: u32, hash: String },
+    ProofGenerated { height: u32 },
+    ProofVerified { height: u32 },
+    Reorg { fork_height: u32, depth: u32 },
+}
+
+/// The `application/problem+json` document of an error response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// Stable machine-readable code, such as `BLOCK_NOT_FOUND`.
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    pub error: String,
+}
@@ -0,0 +1,88 @@
+//! Just enough of a Server-Sent Events parser for `/v1/events`.
+
+/// One dispatched event. Comments, such as keep-alives, and events without
+/// data are dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Parser {
+    /// Bytes after the last complete line; chunks may split lines and
+    /// characters.
+    pending: Vec<u8>,
+    id: Option<String>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl Parser {
+    /// Feeds a chunk of the stream, returning the events it completes.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Frame> {
+        self.pending.extend_from_slice(chunk);
+        let mut frames = Vec::new();
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if let Some(frame) = self.dispatch() {
+                    frames.push(frame);
+                }
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value).to_string();
+            match field {
+                "id" => self.id = Some(value),
+                "event" => self.event = Some(value),
+                "data" => self.data.push(value),
+                // Comments have an empty field name; unknown fields are ignored
+                _ => {}
+            }
+        }
+        frames
+    }
+
+    fn dispatch(&mut self) -> Option<Frame> {
+        let event = self.event.take();
+        let id = self.id.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = std::mem::take(&mut self.data).join("\n");
+        Some(Frame { id, event, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_split_chunks() {
+        let mut parser = Parser::default();
+        assert!(parser
+            .push(b": keep-alive\n\nid: 7\r\nevent: reo")
+            .is_empty());
+        let frames = parser.push(b"rg\ndata: {\"depth\":1}\n\nevent: lagged\ndata:{}\n\n");
+        assert_eq!(
+            frames,
+            [
+                Frame {
+                    id: Some("7".to_string()),
+                    event: Some("reorg".to_string()),
+                    data: "{\"depth\":1}".to_string(),
+                },
+                Frame {
+                    id: None,
+                    event: Some("lagged".to_string()),
+                    data: "{}".to_string(),
+                },
+            ]
+        );
+    }
+}
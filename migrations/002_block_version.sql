@@ -0,0 +1,2 @@
+-- Store the block header version so the 80-byte header can be reconstructed
+ALTER TABLE blocks ADD COLUMN version INTEGER NOT NULL DEFAULT 536870912;
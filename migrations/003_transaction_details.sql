@@ -0,0 +1,25 @@
+-- Per-transaction details for explorers: fee, size, inputs and outputs.
+-- Detail columns are nullable because transactions may be indexed by txid only.
+ALTER TABLE transactions ADD COLUMN fee REAL;
+ALTER TABLE transactions ADD COLUMN size INTEGER;
+ALTER TABLE transactions ADD COLUMN vsize INTEGER;
+
+-- Transaction inputs - previous outpoints spent by each transaction (NULL for coinbase)
+CREATE TABLE transaction_inputs (
+    txid TEXT NOT NULL,
+    input_index INTEGER NOT NULL,
+    prev_txid TEXT,
+    prev_vout INTEGER,
+    PRIMARY KEY (txid, input_index),
+    FOREIGN KEY (txid) REFERENCES transactions(txid) ON DELETE CASCADE
+);
+
+-- Transaction outputs - value and locking script of each output
+CREATE TABLE transaction_outputs (
+    txid TEXT NOT NULL,
+    output_index INTEGER NOT NULL,
+    value REAL NOT NULL,
+    script_pubkey TEXT NOT NULL,
+    PRIMARY KEY (txid, output_index),
+    FOREIGN KEY (txid) REFERENCES transactions(txid) ON DELETE CASCADE
+);
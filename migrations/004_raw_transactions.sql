@@ -0,0 +1,2 @@
+-- Consensus-encoded transaction bodies, stored when ingested alongside the txid
+ALTER TABLE transactions ADD COLUMN raw BLOB;
@@ -0,0 +1,14 @@
+-- Optional address index mapping script_pubkey to the transactions funding or spending it.
+-- Only populated when ADDRESS_INDEX_ENABLED is set, since it grows with every output.
+CREATE TABLE address_index (
+    script_pubkey TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    block_height INTEGER NOT NULL,
+    PRIMARY KEY (script_pubkey, txid),
+    FOREIGN KEY (txid) REFERENCES transactions(txid) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_address_index_height ON address_index(script_pubkey, block_height);
+
+-- Index for finding the input that spends a given output
+CREATE INDEX idx_transaction_inputs_prev ON transaction_inputs(prev_txid, prev_vout);
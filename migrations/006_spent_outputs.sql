@@ -0,0 +1,17 @@
+-- Spent outputs - maintained during ingestion so unspent checks are a single lookup
+CREATE TABLE spent_outputs (
+    txid TEXT NOT NULL,
+    vout INTEGER NOT NULL,
+    spending_txid TEXT NOT NULL,
+    spending_input INTEGER NOT NULL,
+    block_height INTEGER NOT NULL,
+    PRIMARY KEY (txid, vout),
+    FOREIGN KEY (spending_txid) REFERENCES transactions(txid) ON DELETE CASCADE
+);
+
+-- Backfill from inputs indexed before this table existed
+INSERT OR IGNORE INTO spent_outputs (txid, vout, spending_txid, spending_input, block_height)
+SELECT i.prev_txid, i.prev_vout, i.txid, i.input_index, t.block_height
+FROM transaction_inputs i
+JOIN transactions t ON t.txid = i.txid
+WHERE i.prev_txid IS NOT NULL;
@@ -0,0 +1,12 @@
+-- Per-block statistics computed once at ingest time from the block's transaction details
+CREATE TABLE block_stats (
+    block_height INTEGER PRIMARY KEY,
+    size INTEGER NOT NULL,
+    weight INTEGER NOT NULL,
+    avg_fee_rate REAL NOT NULL,
+    median_fee_rate REAL NOT NULL,
+    input_count INTEGER NOT NULL,
+    output_count INTEGER NOT NULL,
+    subsidy REAL NOT NULL,
+    FOREIGN KEY (block_height) REFERENCES blocks(height) ON DELETE CASCADE
+);
@@ -0,0 +1,9 @@
+-- Outcome of proving attempts that did not leave a servable proof, so coverage
+-- gaps can be explained. Blocks with neither a proof file nor a row here have
+-- never been attempted.
+CREATE TABLE proof_status (
+    block_height INTEGER PRIMARY KEY,
+    status TEXT NOT NULL CHECK (status IN ('failed', 'archived')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (block_height) REFERENCES blocks(height) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+-- Supports filtering the block list by verification status and time range
+CREATE INDEX idx_blocks_verified_timestamp ON blocks(verified, timestamp);
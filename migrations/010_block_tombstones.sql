@@ -0,0 +1,15 @@
+-- Blocks displaced by a reorg. The orphaned block is kept as a JSON snapshot
+-- (header fields and txids) so consumers can still see what was replaced,
+-- until it is purged after the retention window.
+CREATE TABLE block_tombstones (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    height INTEGER NOT NULL,
+    hash TEXT NOT NULL,
+    replaced_by TEXT,
+    reorg_depth INTEGER NOT NULL,
+    block_data TEXT NOT NULL,
+    orphaned_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_block_tombstones_hash ON block_tombstones(hash);
+CREATE INDEX idx_block_tombstones_orphaned_at ON block_tombstones(orphaned_at);
@@ -0,0 +1,36 @@
+-- Running block counts kept up to date by triggers, so paginated listings
+-- read a single row instead of counting the whole table per request.
+CREATE TABLE block_totals (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    block_count INTEGER NOT NULL,
+    verified_count INTEGER NOT NULL
+);
+
+INSERT INTO block_totals (id, block_count, verified_count)
+SELECT 1, COUNT(*), COALESCE(SUM(verified), 0) FROM blocks;
+
+-- Blocks are written with an upsert rather than INSERT OR REPLACE: the rows
+-- REPLACE deletes do not fire delete triggers, which would skew the counts.
+CREATE TRIGGER block_totals_insert
+    AFTER INSERT ON blocks
+    BEGIN
+        UPDATE block_totals
+        SET block_count = block_count + 1, verified_count = verified_count + NEW.verified
+        WHERE id = 1;
+    END;
+
+CREATE TRIGGER block_totals_delete
+    AFTER DELETE ON blocks
+    BEGIN
+        UPDATE block_totals
+        SET block_count = block_count - 1, verified_count = verified_count - OLD.verified
+        WHERE id = 1;
+    END;
+
+CREATE TRIGGER block_totals_update
+    AFTER UPDATE OF verified ON blocks
+    BEGIN
+        UPDATE block_totals
+        SET verified_count = verified_count - OLD.verified + NEW.verified
+        WHERE id = 1;
+    END;
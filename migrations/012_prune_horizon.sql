@@ -0,0 +1,8 @@
+-- Height below which transaction data has been pruned. Block rows (headers)
+-- and proof records are never pruned.
+CREATE TABLE prune_state (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    pruned_below INTEGER NOT NULL
+);
+
+INSERT INTO prune_state (id, pruned_below) VALUES (1, 0);
@@ -0,0 +1,9 @@
+-- Requests and proof bytes served per API key and UTC day. Monthly totals
+-- are summed from the days of the month.
+CREATE TABLE api_key_usage (
+    api_key_id INTEGER NOT NULL REFERENCES api_keys(id),
+    day TEXT NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0,
+    proof_bytes INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (api_key_id, day)
+);
@@ -0,0 +1,11 @@
+-- Block fees and subsidies become integer satoshis. REAL amounts round
+-- differently on every client that adds them up.
+ALTER TABLE blocks ADD COLUMN total_fees_sats INTEGER NOT NULL DEFAULT 0;
+UPDATE blocks SET total_fees_sats = CAST(ROUND(total_fees * 100000000) AS INTEGER);
+ALTER TABLE blocks DROP COLUMN total_fees;
+ALTER TABLE blocks RENAME COLUMN total_fees_sats TO total_fees;
+
+ALTER TABLE block_stats ADD COLUMN subsidy_sats INTEGER NOT NULL DEFAULT 0;
+UPDATE block_stats SET subsidy_sats = CAST(ROUND(subsidy * 100000000) AS INTEGER);
+ALTER TABLE block_stats DROP COLUMN subsidy;
+ALTER TABLE block_stats RENAME COLUMN subsidy_sats TO subsidy;
@@ -0,0 +1,11 @@
+-- Serialized size with and without witness data, and weight. Left NULL for
+-- blocks ingested without them or their transactions.
+ALTER TABLE blocks ADD COLUMN size INTEGER;
+ALTER TABLE blocks ADD COLUMN stripped_size INTEGER;
+ALTER TABLE blocks ADD COLUMN weight INTEGER;
+
+-- Blocks ingested with transaction details already have both in their stats
+UPDATE blocks SET
+    size = (SELECT s.size FROM block_stats s WHERE s.block_height = blocks.height),
+    weight = (SELECT s.weight FROM block_stats s WHERE s.block_height = blocks.height);
+UPDATE blocks SET stripped_size = (weight - size) / 3 WHERE weight IS NOT NULL;
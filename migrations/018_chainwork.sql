@@ -0,0 +1,5 @@
+-- Difficulty and cumulative chainwork (64 hex digits, so text order is
+-- numeric order). Filled when blocks are written; blocks ingested before
+-- this migration get them on `POST /admin/reindex`.
+ALTER TABLE blocks ADD COLUMN difficulty REAL;
+ALTER TABLE blocks ADD COLUMN chainwork TEXT;
@@ -0,0 +1,5 @@
+-- Segwit commitment data, known when a block was ingested with every
+-- transaction's raw bytes.
+ALTER TABLE blocks ADD COLUMN witness_merkle_root TEXT;
+ALTER TABLE blocks ADD COLUMN witness_commitment TEXT;
+ALTER TABLE transactions ADD COLUMN wtxid TEXT;
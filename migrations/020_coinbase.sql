@@ -0,0 +1,7 @@
+-- Coinbase details recorded at ingest. Blocks ingested before this
+-- migration get them on `POST /admin/reindex`.
+ALTER TABLE blocks ADD COLUMN coinbase_txid TEXT;
+ALTER TABLE blocks ADD COLUMN subsidy INTEGER;
+ALTER TABLE blocks ADD COLUMN claimed_fees INTEGER;
+ALTER TABLE blocks ADD COLUMN coinbase_script TEXT;
+ALTER TABLE blocks ADD COLUMN miner_tag TEXT;
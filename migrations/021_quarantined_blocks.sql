@@ -0,0 +1,13 @@
+-- Ingested blocks whose header failed validation, set aside with
+-- HEADER_VALIDATION=quarantine instead of being indexed. `document` is the
+-- block as it was received.
+CREATE TABLE quarantined_blocks (
+    hash TEXT PRIMARY KEY,
+    height INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    document TEXT NOT NULL,
+    quarantined_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_quarantined_blocks_height ON quarantined_blocks(height);
@@ -0,0 +1,9 @@
+-- Explicit parent links. `parent_height` is set when a block's `prev_hash`
+-- names the block indexed below it and cleared when that parent is removed,
+-- so a NULL above the lowest block marks a gap or a fork.
+ALTER TABLE blocks ADD COLUMN parent_height INTEGER REFERENCES blocks(height) ON DELETE SET NULL;
+
+UPDATE blocks SET parent_height = height - 1
+WHERE prev_hash = (SELECT parent.hash FROM blocks AS parent WHERE parent.height = blocks.height - 1);
+
+CREATE INDEX idx_blocks_parent_height ON blocks(parent_height);
@@ -0,0 +1,14 @@
+-- Inserts that arrived for a height already holding a different block, and
+-- what BLOCK_CONFLICT_POLICY did about them. `block_data` is the incoming
+-- block as received; a replaced block is kept in block_tombstones.
+CREATE TABLE block_conflicts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    height INTEGER NOT NULL,
+    existing_hash TEXT NOT NULL,
+    incoming_hash TEXT NOT NULL,
+    resolution TEXT NOT NULL,
+    block_data TEXT NOT NULL,
+    detected_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_block_conflicts_height ON block_conflicts(height);
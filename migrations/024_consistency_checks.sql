@@ -0,0 +1,23 @@
+-- Checksum of each proof as it was stored, so the consistency checker can
+-- tell a proof that changed on disk. NULL for proofs recorded before this
+-- migration or discovered on disk at ingest; those are checked by size.
+ALTER TABLE proof_files ADD COLUMN checksum TEXT;
+
+-- Runs of the consistency checker and what each found. Only the most recent
+-- runs are kept.
+CREATE TABLE consistency_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    checked_at INTEGER NOT NULL,
+    proofs_checked INTEGER NOT NULL,
+    blocks_checked INTEGER NOT NULL
+);
+
+CREATE TABLE consistency_findings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL REFERENCES consistency_runs(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    height INTEGER,
+    detail TEXT NOT NULL
+);
+
+CREATE INDEX idx_consistency_findings_run ON consistency_findings(run_id);
@@ -0,0 +1,17 @@
+-- Idempotency keys sent with uploads, per client, with a fingerprint of the
+-- request they were first used for and, once it succeeded, its response to
+-- replay to retries. `status` is NULL while the first request is running.
+-- Keys are forgotten after IDEMPOTENCY_KEY_TTL_SECS.
+CREATE TABLE idempotency_keys (
+    scope TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    fingerprint TEXT NOT NULL,
+    status INTEGER,
+    content_type TEXT,
+    location TEXT,
+    body BLOB,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (scope, idempotency_key)
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys(created_at);
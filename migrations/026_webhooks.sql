@@ -0,0 +1,12 @@
+-- Webhook subscriptions: where to send which events, and the secret their
+-- deliveries are signed with. `secret` is encrypted at rest when
+-- ENCRYPTION_KEYS is set; `events` is a comma-separated list of event types.
+CREATE TABLE webhooks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events TEXT NOT NULL,
+    active INTEGER NOT NULL DEFAULT 1,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,21 @@
+-- Webhook deliveries, queued per subscription when an event is published and
+-- kept as the subscription's delivery log. `status` is `pending` until the
+-- endpoint accepts the payload (`delivered`) or WEBHOOK_MAX_ATTEMPTS attempts
+-- have failed (`dead`). Finished deliveries are forgotten after
+-- WEBHOOK_DELIVERY_RETENTION_SECS.
+CREATE TABLE webhook_deliveries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    webhook_id INTEGER NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at INTEGER NOT NULL,
+    last_status_code INTEGER,
+    last_error TEXT,
+    created_at INTEGER NOT NULL,
+    finished_at INTEGER
+);
+
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(status, next_attempt_at);
+CREATE INDEX idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, id);
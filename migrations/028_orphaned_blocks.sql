@@ -0,0 +1,21 @@
+-- Blocks that left the canonical chain, one row per hash, served from
+-- GET /v1/blocks/orphans and by hash with `orphaned: true`. Tombstones are
+-- a reorg log purged after TOMBSTONE_RETENTION_DAYS; orphans have their own
+-- retention (ORPHAN_RETENTION_DAYS) so that purge does not take them along.
+-- A hash orphaned again is re-inserted, moving it to the front of the list.
+CREATE TABLE orphaned_blocks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    height INTEGER NOT NULL,
+    hash TEXT NOT NULL UNIQUE,
+    replaced_by TEXT,
+    reorg_depth INTEGER NOT NULL,
+    block_data TEXT NOT NULL,
+    orphaned_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_orphaned_blocks_orphaned_at ON orphaned_blocks(orphaned_at);
+
+INSERT OR REPLACE INTO orphaned_blocks (height, hash, replaced_by, reorg_depth, block_data, orphaned_at)
+SELECT height, hash, replaced_by, reorg_depth, block_data, orphaned_at
+FROM block_tombstones
+ORDER BY id;
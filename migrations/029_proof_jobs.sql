@@ -0,0 +1,13 @@
+-- Requests to prove a block, from `queued` through `running` to `completed`
+-- (the proof is registered and the block verified) or `failed`, with why.
+CREATE TABLE proof_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    block_height INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'queued',
+    error TEXT,
+    created_at INTEGER NOT NULL,
+    started_at INTEGER,
+    finished_at INTEGER
+);
+
+CREATE INDEX idx_proof_jobs_height ON proof_jobs(block_height);
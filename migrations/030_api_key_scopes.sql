@@ -0,0 +1,3 @@
+-- What each API key may do. Keys issued before scopes existed could already
+-- submit blocks and upload proofs, so they keep write access.
+ALTER TABLE api_keys ADD COLUMN scope TEXT NOT NULL DEFAULT 'write';
@@ -189,8 +189,8 @@ test_transactions() {
     print_section "💰 TRANSACTION VERIFICATION"
     
     # Test valid transaction from mock data
-    print_endpoint "GET /v1/tx/a1b2c3d4e5f67890123456789012345678901234567890123456789012345678" "Check transaction inclusion (valid tx from block 869123)"
-    response=$(curl -s "$BASE_URL/v1/tx/a1b2c3d4e5f67890123456789012345678901234567890123456789012345678")
+    print_endpoint "GET /v1/tx/56af67a85b54a7999b887844bcc7dade903d10c5167d9ca8072bf54cb02ffe6a" "Check transaction inclusion (valid tx from block 869123)"
+    response=$(curl -s "$BASE_URL/v1/tx/56af67a85b54a7999b887844bcc7dade903d10c5167d9ca8072bf54cb02ffe6a")
     print_response "$response"
     
     # Test non-existent transaction
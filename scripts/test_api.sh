@@ -37,7 +37,7 @@ echo "..."
 
 # Test 5: Transaction Status
 echo -e "\n\n${GREEN}5. Transaction Status${NC}"
-curl -s "$BASE_URL/v1/tx/a1b2c3d4e5f67890123456789012345678901234567890123456789012345678" | jq '.'
+curl -s "$BASE_URL/v1/tx/56af67a85b54a7999b887844bcc7dade903d10c5167d9ca8072bf54cb02ffe6a" | jq '.'
 
 # Test 6: Header Status  
 echo -e "\n${GREEN}6. Header Status${NC}"
@@ -0,0 +1,241 @@
+use crate::rate_limit::{client_ip, RateLimitConfig};
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use http_body::Body as _;
+use once_cell::sync::OnceCell;
+use serde_json::json;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+    time::Instant,
+};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+
+static ACCESS_LOG: OnceCell<AccessLog> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    Json,
+    /// Apache/NGINX combined log format.
+    Combined,
+}
+
+impl FromStr for AccessLogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "combined" => Ok(Self::Combined),
+            other => Err(format!("unknown access log format: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AccessLogConfig {
+    /// Access logging is off when unset.
+    pub format: Option<AccessLogFormat>,
+    /// Written to stdout when unset.
+    pub file: Option<PathBuf>,
+    /// Same setting as the rate limiter: log the last `X-Forwarded-For` hop
+    /// as the client.
+    pub trust_forwarded_for: bool,
+}
+
+impl AccessLogConfig {
+    pub fn from_env() -> Self {
+        Self {
+            format: std::env::var("ACCESS_LOG")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            file: std::env::var("ACCESS_LOG_FILE")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+            trust_forwarded_for: RateLimitConfig::from_env().trust_forwarded_for,
+        }
+    }
+
+    /// Starts writing access logs. The returned guard flushes pending lines
+    /// when dropped, so it must live as long as the server.
+    pub fn init(&self) -> io::Result<Option<WorkerGuard>> {
+        let Some(format) = self.format else {
+            return Ok(None);
+        };
+        let (writer, guard) = match &self.file {
+            Some(path) => {
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                tracing_appender::non_blocking(file)
+            }
+            None => tracing_appender::non_blocking(io::stdout()),
+        };
+        let _ = ACCESS_LOG.set(AccessLog {
+            format,
+            trust_forwarded_for: self.trust_forwarded_for,
+            writer,
+        });
+        Ok(Some(guard))
+    }
+}
+
+/// Sink for access log lines, kept apart from the tracing output.
+pub struct AccessLog {
+    format: AccessLogFormat,
+    trust_forwarded_for: bool,
+    writer: NonBlocking,
+}
+
+/// One served request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessRecord {
+    pub time: DateTime<Utc>,
+    pub client_ip: Option<String>,
+    pub method: String,
+    /// Path and query as requested.
+    pub target: String,
+    pub version: String,
+    pub status: u16,
+    /// Response body size, when known up front.
+    pub bytes: Option<u64>,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub duration_ms: u128,
+}
+
+impl AccessRecord {
+    pub fn format(&self, format: AccessLogFormat) -> String {
+        match format {
+            AccessLogFormat::Json => json!({
+                "time": self.time.to_rfc3339(),
+                "client_ip": self.client_ip,
+                "method": self.method,
+                "target": self.target,
+                "version": self.version,
+                "status": self.status,
+                "bytes": self.bytes,
+                "referrer": self.referrer,
+                "user_agent": self.user_agent,
+                "duration_ms": self.duration_ms,
+            })
+            .to_string(),
+            AccessLogFormat::Combined => format!(
+                "{} - - [{}] \"{} {} {}\" {} {} {} {}",
+                self.client_ip.as_deref().unwrap_or("-"),
+                self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+                self.method,
+                self.target,
+                self.version,
+                self.status,
+                self.bytes
+                    .map_or_else(|| "-".to_string(), |bytes| bytes.to_string()),
+                quoted(self.referrer.as_deref()),
+                quoted(self.user_agent.as_deref()),
+            ),
+        }
+    }
+}
+
+fn quoted(value: Option<&str>) -> String {
+    let value = value.unwrap_or("-");
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Writes one line per request once its response is ready, when access
+/// logging was started with [`AccessLogConfig::init`].
+pub async fn access_log_middleware(request: Request, next: Next) -> Response {
+    let Some(log) = ACCESS_LOG.get() else {
+        return next.run(request).await;
+    };
+
+    let start = Instant::now();
+    let time = Utc::now();
+    let client_ip = client_ip(&request, log.trust_forwarded_for);
+    let method = request.method().to_string();
+    let target = request
+        .uri()
+        .path_and_query()
+        .map_or_else(|| request.uri().path().to_string(), |pq| pq.to_string());
+    let version = format!("{:?}", request.version());
+    let referrer = header_value(request.headers(), header::REFERER);
+    let user_agent = header_value(request.headers(), header::USER_AGENT);
+
+    let response = next.run(request).await;
+
+    let bytes = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .or_else(|| response.body().size_hint().exact());
+    let record = AccessRecord {
+        time,
+        client_ip,
+        method,
+        target,
+        version,
+        status: response.status().as_u16(),
+        bytes,
+        referrer,
+        user_agent,
+        duration_ms: start.elapsed().as_millis(),
+    };
+    let mut line = record.format(log.format);
+    line.push('\n');
+    // Lines are handed to the writer thread; a full queue drops the line
+    // rather than stalling the request
+    let _ = log.writer.clone().write_all(line.as_bytes());
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_access_log_formats() {
+        let record = AccessRecord {
+            time: Utc.with_ymd_and_hms(2026, 10, 17, 5, 45, 7).unwrap(),
+            client_ip: Some("203.0.113.7".to_string()),
+            method: "GET".to_string(),
+            target: "/v1/blocks?limit=5".to_string(),
+            version: "HTTP/1.1".to_string(),
+            status: 200,
+            bytes: Some(1234),
+            referrer: None,
+            user_agent: Some("curl/8.5 \"test\"".to_string()),
+            duration_ms: 3,
+        };
+
+        assert_eq!(
+            record.format(AccessLogFormat::Combined),
+            r#"203.0.113.7 - - [17/Oct/2026:05:45:07 +0000] "GET /v1/blocks?limit=5 HTTP/1.1" 200 1234 "-" "curl/8.5 \"test\"""#
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&record.format(AccessLogFormat::Json)).unwrap();
+        assert_eq!(json["client_ip"], "203.0.113.7");
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["bytes"], 1234);
+        assert!(json["referrer"].is_null());
+        assert_eq!(json["user_agent"], "curl/8.5 \"test\"");
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{
+    error::{AppError, Result},
+    model::ScriptType,
+};
+use bech32::{hrp, segwit};
+
+/// Converts a mainnet address into the hex-encoded script_pubkey it locks to.
+///
+/// Supports segwit (bech32/bech32m), P2PKH and P2SH addresses.
+pub fn script_pubkey(address: &str) -> Result<String> {
+    if let Ok((hrp, version, program)) = segwit::decode(address) {
+        if hrp != hrp::BC {
+            return Err(AppError::InvalidAddress(format!(
+                "{address}: not a mainnet address"
+            )));
+        }
+        let version = version.to_u8();
+        let opcode = if version == 0 { 0x00 } else { 0x50 + version };
+        let mut script = vec![opcode, program.len() as u8];
+        script.extend_from_slice(&program);
+        return Ok(hex::encode(script));
+    }
+
+    let payload = bs58::decode(address)
+        .with_check(None)
+        .into_vec()
+        .map_err(|_| AppError::InvalidAddress(address.to_string()))?;
+
+    match payload.split_first() {
+        Some((0x00, hash)) if hash.len() == 20 => Ok(format!("76a914{}88ac", hex::encode(hash))),
+        Some((0x05, hash)) if hash.len() == 20 => Ok(format!("a914{}87", hex::encode(hash))),
+        _ => Err(AppError::InvalidAddress(format!(
+            "{address}: unsupported address type"
+        ))),
+    }
+}
+
+/// Classifies a hex-encoded script_pubkey by its standard template.
+pub fn script_type(script_pubkey: &str) -> ScriptType {
+    let Ok(script) = hex::decode(script_pubkey) else {
+        return ScriptType::Nonstandard;
+    };
+
+    match script.as_slice() {
+        [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20 => ScriptType::P2pkh,
+        [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20 => ScriptType::P2sh,
+        [0x00, 0x14, program @ ..] if program.len() == 20 => ScriptType::P2wpkh,
+        [0x00, 0x20, program @ ..] if program.len() == 32 => ScriptType::P2wsh,
+        [0x51, 0x20, program @ ..] if program.len() == 32 => ScriptType::P2tr,
+        [0x6a, ..] => ScriptType::OpReturn,
+        _ => ScriptType::Nonstandard,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segwit_address() {
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        assert_eq!(
+            script_pubkey(address).unwrap(),
+            "0014751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+    }
+
+    #[test]
+    fn test_legacy_addresses() {
+        assert_eq!(
+            script_pubkey("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2").unwrap(),
+            "76a91477bff20c60e522dfaa3350c39b030a5d004e839a88ac"
+        );
+        assert_eq!(
+            script_pubkey("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy").unwrap(),
+            "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"
+        );
+    }
+
+    #[test]
+    fn test_script_type() {
+        assert_eq!(
+            script_type("0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+            ScriptType::P2wpkh
+        );
+        assert_eq!(
+            script_type("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"),
+            ScriptType::P2sh
+        );
+        assert_eq!(script_type("6a0568656c6c6f"), ScriptType::OpReturn);
+        assert_eq!(script_type("51"), ScriptType::Nonstandard);
+    }
+
+    #[test]
+    fn test_rejects_testnet_and_garbage() {
+        assert!(script_pubkey("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").is_err());
+        assert!(script_pubkey("not-an-address").is_err());
+    }
+}
@@ -0,0 +1,503 @@
+use crate::{
+    audit::{self, Actor, ADMIN_TOKEN_ACTOR},
+    auth, config,
+    error::{AppError, Result},
+    jwt::{bearer_token, JwtValidator, Principal, Role},
+    maintenance::MaintenanceConfig,
+    model::{
+        ApiKey, ApiKeysResponse, AuditLogQuery, AuditLogResponse, BlockConflictsQuery,
+        BlockConflictsResponse, ConfigResponse, ConsistencyReport, ConsistencyReportsQuery,
+        ConsistencyReportsResponse, CreateApiKeyRequest, CreateWebhookRequest, CreatedApiKey,
+        CreatedWebhook, MaintenanceRequest, MaintenanceStatus, PruneRequest, PruneResponse,
+        ReindexRequest, ReindexResponse, ReloadResponse, RequeueResponse, ReseedResponse,
+        UpdateWebhookRequest, Webhook, WebhookDeliveriesQuery, WebhookDeliveriesResponse,
+        WebhooksResponse,
+    },
+    reload::Reloader,
+    store::BlockStore,
+    webhooks,
+};
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+    Extension, Json,
+};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use validator::Validate;
+
+static ADMIN_CONFIG: Lazy<AdminConfig> = Lazy::new(AdminConfig::from_env);
+
+#[derive(Debug, Clone, Default)]
+pub struct AdminConfig {
+    /// Bearer token required on `/admin` routes; they are disabled when unset.
+    pub token: Option<String>,
+}
+
+impl AdminConfig {
+    pub fn from_env() -> Self {
+        Self {
+            token: std::env::var("ADMIN_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &ADMIN_CONFIG
+    }
+
+    /// Checks the request's `Authorization: Bearer` token.
+    pub fn authorize(&self, headers: &HeaderMap) -> Result<()> {
+        let Some(expected) = &self.token else {
+            return Err(AppError::FeatureDisabled(
+                "admin API is disabled; set ADMIN_TOKEN to enable it".to_string(),
+            ));
+        };
+        let presented = bearer_token(headers)
+            .ok_or_else(|| AppError::Unauthorized("missing bearer token".to_string()))?;
+
+        // Comparing digests keeps the comparison time independent of how
+        // much of the token matches
+        if Sha256::digest(presented) != Sha256::digest(expected) {
+            return Err(AppError::Unauthorized("invalid bearer token".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Accepts the static token or, when JWTs are configured, a token with
+    /// the admin role. Returns the JWT's principal when that is what passed.
+    pub async fn authorize_request(
+        &self,
+        jwt: Option<&JwtValidator>,
+        headers: &HeaderMap,
+    ) -> Result<Option<Principal>> {
+        match (self.authorize(headers), jwt) {
+            (Ok(()), _) => Ok(None),
+            (Err(AppError::FeatureDisabled(_) | AppError::Unauthorized(_)), Some(jwt)) => {
+                let token = bearer_token(headers)
+                    .ok_or_else(|| AppError::Unauthorized("missing bearer token".to_string()))?;
+                Ok(Some(jwt.authorize(token, Role::Admin).await?))
+            }
+            (Err(e), _) => Err(e),
+        }
+    }
+}
+
+pub async fn admin_auth_middleware(mut request: Request, next: Next) -> Result<Response> {
+    let jwt = JwtValidator::global();
+    let principal = AdminConfig::global()
+        .authorize_request(jwt.as_deref(), request.headers())
+        .await?;
+    match principal {
+        Some(principal) => {
+            request.extensions_mut().insert(principal);
+        }
+        None => {
+            request
+                .extensions_mut()
+                .insert(Actor(ADMIN_TOKEN_ACTOR.to_string()));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+/// `POST /admin/prune`: prunes transaction data below `below_height`.
+pub async fn prune(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Json(request): Json<PruneRequest>,
+) -> Result<Json<PruneResponse>> {
+    let report = db.prune_below(request.below_height).await?;
+    let target = format!("height:{}", request.below_height);
+    audit::record(db.as_ref(), &actor, "prune", Some(&target)).await;
+    Ok(Json(report))
+}
+
+/// `POST /admin/reseed`: loads the bundled mock blocks again.
+pub async fn reseed(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+) -> Result<Json<ReseedResponse>> {
+    db.reseed().await?;
+    audit::record(db.as_ref(), &actor, "reseed", None).await;
+    Ok(Json(ReseedResponse {
+        tip_height: db.tip_height().await?,
+    }))
+}
+
+/// `POST /admin/reindex`: rebuilds statistics, spends and the address index
+/// of the blocks from `from_height` through `to_height`.
+pub async fn reindex(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Json(request): Json<ReindexRequest>,
+) -> Result<Json<ReindexResponse>> {
+    if request.from_height > request.to_height {
+        return Err(AppError::InvalidQueryParameter(
+            "from_height must not be above to_height".to_string(),
+        ));
+    }
+    let reindexed = db.reindex(request.from_height, request.to_height).await?;
+    let target = format!("heights:{}-{}", request.from_height, request.to_height);
+    audit::record(db.as_ref(), &actor, "reindex", Some(&target)).await;
+    Ok(Json(ReindexResponse { reindexed }))
+}
+
+/// `POST /admin/cache/invalidate`: drops cached blocks so the next reads
+/// come from the database.
+pub async fn invalidate_cache(State(db): State<Arc<dyn BlockStore>>, actor: Actor) -> StatusCode {
+    db.invalidate_cache();
+    audit::record(db.as_ref(), &actor, "cache.invalidate", None).await;
+    StatusCode::NO_CONTENT
+}
+
+/// `POST /admin/proofs/requeue`: puts proofs marked failed back in the
+/// backlog.
+pub async fn requeue_failed_proofs(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+) -> Result<Json<RequeueResponse>> {
+    let requeued = db.requeue_failed_proofs().await?;
+    audit::record(db.as_ref(), &actor, "proofs.requeue", None).await;
+    Ok(Json(RequeueResponse { requeued }))
+}
+
+/// `GET /admin/config`: the settings in effect, secrets redacted.
+pub async fn get_config() -> Json<ConfigResponse> {
+    Json(ConfigResponse {
+        config_file: config::config_path().map(|path| path.display().to_string()),
+        settings: config::effective_settings(&config::env),
+    })
+}
+
+/// `POST /admin/api-keys`: issues a key. The response is the only time the
+/// key is shown.
+pub async fn create_api_key(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedApiKey>)> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidQueryParameter(
+            "name must not be empty".to_string(),
+        ));
+    }
+    let created = auth::issue_key(db.as_ref(), name, request.scope).await?;
+    let target = format!("api_key:{}", created.api_key.id);
+    audit::record(db.as_ref(), &actor, "api_key.create", Some(&target)).await;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// `GET /admin/api-keys`: every key issued, revoked ones included.
+pub async fn list_api_keys(State(db): State<Arc<dyn BlockStore>>) -> Result<Json<ApiKeysResponse>> {
+    Ok(Json(ApiKeysResponse {
+        keys: db.list_api_keys().await?,
+    }))
+}
+
+/// `DELETE /admin/api-keys/:id`: revokes a key; it stops working at once.
+pub async fn revoke_api_key(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiKey>> {
+    let key = db.revoke_api_key(id).await?;
+    let target = format!("api_key:{id}");
+    audit::record(db.as_ref(), &actor, "api_key.revoke", Some(&target)).await;
+    Ok(Json(key))
+}
+
+/// `POST /admin/webhooks`: subscribes a URL to events. The response is the
+/// only time the signing secret is shown.
+pub async fn create_webhook(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedWebhook>)> {
+    let created = webhooks::create(db.as_ref(), &request).await?;
+    let target = format!("webhook:{}", created.webhook.id);
+    audit::record(db.as_ref(), &actor, "webhook.create", Some(&target)).await;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// `GET /admin/webhooks`: every subscription, inactive ones included.
+pub async fn list_webhooks(
+    State(db): State<Arc<dyn BlockStore>>,
+) -> Result<Json<WebhooksResponse>> {
+    Ok(Json(WebhooksResponse {
+        webhooks: db.list_webhooks().await?,
+    }))
+}
+
+/// `GET /admin/webhooks/:id`
+pub async fn get_webhook(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(id): Path<i64>,
+) -> Result<Json<Webhook>> {
+    Ok(Json(db.get_webhook(id).await?))
+}
+
+/// `GET /admin/webhooks/:id/deliveries`: the webhook's delivery log, newest
+/// first, with each delivery's attempts and last error.
+pub async fn webhook_deliveries(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(id): Path<i64>,
+    Query(query): Query<WebhookDeliveriesQuery>,
+) -> Result<Json<WebhookDeliveriesResponse>> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+
+    let deliveries = db
+        .list_webhook_deliveries(id, query.status, query.limit.unwrap_or(50), query.cursor)
+        .await?;
+    Ok(Json(deliveries))
+}
+
+/// `PATCH /admin/webhooks/:id`: changes the fields given, e.g. to rotate the
+/// secret or pause deliveries.
+pub async fn update_webhook(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Path(id): Path<i64>,
+    Json(request): Json<UpdateWebhookRequest>,
+) -> Result<Json<Webhook>> {
+    let changes = webhooks::validate_update(request)?;
+    let webhook = db.update_webhook(id, &changes).await?;
+    let target = format!("webhook:{id}");
+    audit::record(db.as_ref(), &actor, "webhook.update", Some(&target)).await;
+    Ok(Json(webhook))
+}
+
+/// `DELETE /admin/webhooks/:id`: unsubscribes a URL.
+pub async fn delete_webhook(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Path(id): Path<i64>,
+) -> Result<Json<Webhook>> {
+    let webhook = db.delete_webhook(id).await?;
+    let target = format!("webhook:{id}");
+    audit::record(db.as_ref(), &actor, "webhook.delete", Some(&target)).await;
+    Ok(Json(webhook))
+}
+
+/// `POST /admin/reload`: re-reads the configuration file and applies the
+/// settings that can change without a restart, as `SIGHUP` does.
+pub async fn reload_config(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Extension(reloader): Extension<Arc<Reloader>>,
+) -> Result<Json<ReloadResponse>> {
+    let response = reloader
+        .reload()
+        .map_err(|e| AppError::InvalidConfiguration(e.to_string()))?;
+    audit::record(
+        db.as_ref(),
+        &actor,
+        "config.reload",
+        response.config_file.as_deref(),
+    )
+    .await;
+    Ok(Json(response))
+}
+
+/// `GET /admin/maintenance`: the current maintenance mode.
+pub async fn get_maintenance(
+    Extension(reloader): Extension<Arc<Reloader>>,
+) -> Json<MaintenanceStatus> {
+    Json(reloader.maintenance.status())
+}
+
+/// `PUT /admin/maintenance`: switches the maintenance mode until it is set
+/// again or the configuration is reloaded.
+pub async fn set_maintenance(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    Extension(reloader): Extension<Arc<Reloader>>,
+    Json(request): Json<MaintenanceRequest>,
+) -> Result<Json<MaintenanceStatus>> {
+    let retry_after_secs = request
+        .retry_after_secs
+        .unwrap_or_else(|| MaintenanceConfig::from_env().retry_after_secs);
+    let message = request.message.filter(|message| !message.trim().is_empty());
+    reloader
+        .maintenance
+        .set(request.mode, retry_after_secs, message);
+
+    let status = reloader.maintenance.status();
+    let target = serde_json::to_value(status.mode)?;
+    audit::record(db.as_ref(), &actor, "maintenance.set", target.as_str()).await;
+    Ok(Json(status))
+}
+
+/// `GET /admin/audit-log`: recorded actions, newest first, filterable by
+/// `actor` and `action`.
+pub async fn audit_log(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<AuditLogResponse>> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+
+    let entries = db
+        .list_audit_log(
+            query.actor.as_deref(),
+            query.action.as_deref(),
+            query.limit.unwrap_or(50),
+            query.cursor,
+        )
+        .await?;
+    Ok(Json(entries))
+}
+
+/// `GET /admin/conflicts`: inserts that met a different block at their
+/// height and how `BLOCK_CONFLICT_POLICY` resolved them, newest first.
+pub async fn block_conflicts(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<BlockConflictsQuery>,
+) -> Result<Json<BlockConflictsResponse>> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+
+    let conflicts = db
+        .list_block_conflicts(query.limit.unwrap_or(50), query.cursor)
+        .await?;
+    Ok(Json(conflicts))
+}
+
+/// `GET /admin/consistency`: the most recent consistency check reports.
+pub async fn consistency_reports(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<ConsistencyReportsQuery>,
+) -> Result<Json<ConsistencyReportsResponse>> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+
+    let reports = db
+        .list_consistency_reports(query.limit.unwrap_or(1))
+        .await?;
+    Ok(Json(ConsistencyReportsResponse { reports }))
+}
+
+/// `POST /admin/consistency/run`: runs the consistency checks now.
+pub async fn run_consistency_check(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+) -> Result<Json<ConsistencyReport>> {
+    let report = db.run_consistency_check().await?;
+    let target = format!("run:{}", report.id);
+    audit::record(db.as_ref(), &actor, "consistency.run", Some(&target)).await;
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwt::JwtConfig;
+    use axum::http::{header, HeaderValue};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    #[test]
+    fn test_authorize() {
+        let bearer = |token: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+            );
+            headers
+        };
+
+        let disabled = AdminConfig::default();
+        assert!(matches!(
+            disabled.authorize(&bearer("secret")),
+            Err(AppError::FeatureDisabled(_))
+        ));
+
+        let config = AdminConfig {
+            token: Some("secret".to_string()),
+        };
+        assert!(config.authorize(&bearer("secret")).is_ok());
+        assert!(matches!(
+            config.authorize(&bearer("guess")),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            config.authorize(&HeaderMap::new()),
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_authorize_jwt() {
+        let jwt = JwtConfig {
+            hs256_secret: Some("jwt-secret".to_string()),
+            ..JwtConfig::default()
+        }
+        .validator()
+        .unwrap();
+        let bearer_jwt = |roles: &[&str]| {
+            let claims = json!({
+                "sub": "ops",
+                "exp": jsonwebtoken::get_current_timestamp() + 600,
+                "roles": roles,
+            });
+            let token = encode(
+                &Header::default(),
+                &claims,
+                &EncodingKey::from_secret(b"jwt-secret"),
+            )
+            .unwrap();
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+            );
+            headers
+        };
+
+        // JWTs work without ADMIN_TOKEN, but only with the admin role
+        let config = AdminConfig::default();
+        let principal = config
+            .authorize_request(Some(&jwt), &bearer_jwt(&["admin"]))
+            .await
+            .unwrap();
+        assert_eq!(principal.unwrap().subject, "ops");
+        assert!(matches!(
+            config
+                .authorize_request(Some(&jwt), &bearer_jwt(&["prover"]))
+                .await,
+            Err(AppError::Forbidden(_))
+        ));
+
+        // The static token still works alongside them
+        let config = AdminConfig {
+            token: Some("secret".to_string()),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        assert!(config
+            .authorize_request(Some(&jwt), &headers)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            config
+                .authorize_request(None, &bearer_jwt(&["admin"]))
+                .await,
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+}
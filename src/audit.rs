@@ -0,0 +1,48 @@
+use crate::{auth::ApiKeyIdentity, jwt::Principal, store::BlockStore};
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, Extensions},
+};
+use std::convert::Infallible;
+use tracing::error;
+
+/// Actor recorded for requests authenticated with `ADMIN_TOKEN`.
+pub const ADMIN_TOKEN_ACTOR: &str = "admin-token";
+
+/// Who made a request, as written to the audit log: `jwt:<subject>`,
+/// `key:<id>`, `admin-token`, or `anonymous` when nothing authenticated it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Actor(pub String);
+
+impl Actor {
+    pub fn from_extensions(extensions: &Extensions) -> Self {
+        if let Some(actor) = extensions.get::<Actor>() {
+            return actor.clone();
+        }
+        if let Some(principal) = extensions.get::<Principal>() {
+            return Actor(format!("jwt:{}", principal.subject));
+        }
+        if let Some(identity) = extensions.get::<ApiKeyIdentity>() {
+            return Actor(format!("key:{}", identity.id));
+        }
+        Actor("anonymous".to_string())
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Actor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        Ok(Actor::from_extensions(&parts.extensions))
+    }
+}
+
+/// Records an action that has already taken effect. A failed write is
+/// logged rather than returned, since the caller can't undo the action.
+pub async fn record(db: &dyn BlockStore, actor: &Actor, action: &str, target: Option<&str>) {
+    if let Err(e) = db.record_audit(&actor.0, action, target).await {
+        error!(actor = actor.0, action, target, error = %e, "Failed to write audit entry");
+    }
+}
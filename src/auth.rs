@@ -0,0 +1,186 @@
+use crate::{
+    error::{AppError, Result},
+    jwt::{bearer_token, JwtValidator, Principal, Role},
+    model::{CreatedApiKey, KeyScope},
+    store::BlockStore,
+};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Marks a value as one of this service's API keys.
+pub const KEY_PREFIX: &str = "raito_";
+
+/// Characters of a key kept in the clear so admins can tell keys apart.
+const DISPLAY_PREFIX_LEN: usize = KEY_PREFIX.len() + 8;
+
+static AUTH_CONFIG: Lazy<AuthConfig> = Lazy::new(AuthConfig::from_env);
+
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    /// Require an API key on read endpoints too, not only on writes.
+    pub require_for_reads: bool,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            require_for_reads: std::env::var("API_KEYS_REQUIRED_FOR_READS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.require_for_reads),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &AUTH_CONFIG
+    }
+
+    /// Authentication for the read endpoints under `/v1`.
+    pub fn reads(&self, store: Arc<dyn BlockStore>) -> ApiKeyAuth {
+        ApiKeyAuth {
+            required: self.require_for_reads,
+            ..ApiKeyAuth::optional(store)
+        }
+    }
+}
+
+/// The API key a request was authenticated with, added to the request's
+/// extensions by [`api_key_middleware`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyIdentity {
+    pub id: i64,
+    pub name: String,
+    pub scope: KeyScope,
+}
+
+/// State for [`api_key_middleware`]. Besides API keys it accepts JWTs
+/// carrying the reader role when JWT validation is configured.
+#[derive(Clone)]
+pub struct ApiKeyAuth {
+    store: Arc<dyn BlockStore>,
+    jwt: Option<Arc<JwtValidator>>,
+    required: bool,
+}
+
+impl ApiKeyAuth {
+    /// Lets anonymous requests through but still rejects invalid keys.
+    pub fn optional(store: Arc<dyn BlockStore>) -> Self {
+        Self {
+            store,
+            jwt: JwtValidator::global(),
+            required: false,
+        }
+    }
+}
+
+/// A new random key. Only its [`hash_key`] digest is stored.
+pub fn generate_key() -> String {
+    format!(
+        "{KEY_PREFIX}{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key))
+}
+
+/// Generates a key for `name` and stores it; the result is the only place
+/// the key itself ever appears.
+pub async fn issue_key(
+    store: &dyn BlockStore,
+    name: &str,
+    scope: KeyScope,
+) -> Result<CreatedApiKey> {
+    let key = generate_key();
+    let api_key = store
+        .create_api_key(name, scope, &key[..DISPLAY_PREFIX_LEN], &hash_key(&key))
+        .await?;
+    Ok(CreatedApiKey { key, api_key })
+}
+
+/// Authenticates `Authorization: Bearer <key>` and records the key as an
+/// [`ApiKeyIdentity`] extension, or a JWT as a
+/// [`Principal`](crate::jwt::Principal). A presented credential must be
+/// valid even where keys are optional.
+pub async fn api_key_middleware(
+    State(auth): State<ApiKeyAuth>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response> {
+    let Some(key) = bearer_token(request.headers()) else {
+        if auth.required {
+            return Err(AppError::Unauthorized("missing API key".to_string()));
+        }
+        return Ok(next.run(request).await);
+    };
+
+    if let (false, Some(jwt)) = (key.starts_with(KEY_PREFIX), &auth.jwt) {
+        let principal = jwt.authorize(key, Role::Reader).await?;
+        request.extensions_mut().insert(principal);
+        return Ok(next.run(request).await);
+    }
+
+    let api_key = auth
+        .store
+        .authenticate_api_key(&hash_key(key))
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("invalid or revoked API key".to_string()))?;
+    request.extensions_mut().insert(ApiKeyIdentity {
+        id: api_key.id,
+        name: api_key.name,
+        scope: api_key.scope,
+    });
+    Ok(next.run(request).await)
+}
+
+/// Admits requests that [`api_key_middleware`] authenticated with a write
+/// key or a JWT carrying the prover role; for the endpoints that change
+/// data.
+pub async fn require_write_middleware(request: Request, next: Next) -> Result<Response> {
+    let extensions = request.extensions();
+    if let Some(identity) = extensions.get::<ApiKeyIdentity>() {
+        if identity.scope != KeyScope::Write {
+            return Err(AppError::Forbidden(
+                "requires an API key with the write scope".to_string(),
+            ));
+        }
+    } else if let Some(principal) = extensions.get::<Principal>() {
+        if !principal.has_role(Role::Prover) {
+            return Err(AppError::Forbidden(format!(
+                "requires the {} role",
+                Role::Prover
+            )));
+        }
+    } else {
+        return Err(AppError::Unauthorized("missing API key".to_string()));
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_key() {
+        let key = generate_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + 64);
+        assert_ne!(key, generate_key());
+
+        let digest = hash_key(&key);
+        assert_eq!(digest.len(), 64);
+        assert_eq!(digest, hash_key(&key));
+        assert!(!digest.contains(&key[KEY_PREFIX.len()..]));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    error::{AppError, Result},
+    model::CircuitState,
+};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicI64, AtomicU32, Ordering},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+static BREAKER_CONFIG: Lazy<BreakerConfig> = Lazy::new(BreakerConfig::from_env);
+
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// Extra attempts made for a read that failed transiently.
+    pub read_retries: u32,
+    /// Delay before the first retry; doubled for each one after it.
+    pub retry_backoff_ms: u64,
+    /// Consecutive transient failures that open the breaker.
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before letting a request through.
+    pub open_secs: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            read_retries: 2,
+            retry_backoff_ms: 50,
+            failure_threshold: 5,
+            open_secs: 10,
+        }
+    }
+}
+
+impl BreakerConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            read_retries: std::env::var("DATABASE_READ_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.read_retries),
+            retry_backoff_ms: std::env::var("DATABASE_RETRY_BACKOFF_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.retry_backoff_ms),
+            failure_threshold: std::env::var("DATABASE_BREAKER_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&threshold| threshold > 0)
+                .unwrap_or(defaults.failure_threshold),
+            open_secs: std::env::var("DATABASE_BREAKER_OPEN_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.open_secs),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &BREAKER_CONFIG
+    }
+}
+
+/// Stops sending queries to a database that keeps failing transiently, so
+/// requests fail fast with 503 instead of piling up on an exhausted pool.
+///
+/// Closed until `failure_threshold` consecutive transient failures, then open
+/// for `open_secs`. After that it is half-open: the next request goes through
+/// and closes the breaker on success or reopens it on failure.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    consecutive_failures: AtomicU32,
+    /// Unix milliseconds when the breaker last opened; 0 while closed.
+    opened_at_ms: AtomicI64,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            consecutive_failures: AtomicU32::new(0),
+            opened_at_ms: AtomicI64::new(0),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match self.opened_at_ms.load(Ordering::Relaxed) {
+            0 => CircuitState::Closed,
+            opened_at if self.open_remaining_ms(opened_at) > 0 => CircuitState::Open,
+            _ => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Seconds a client should wait before retrying, at least 1.
+    pub fn retry_after_secs(&self) -> u64 {
+        let remaining_ms = self.open_remaining_ms(self.opened_at_ms.load(Ordering::Relaxed));
+        (remaining_ms.max(0) as u64).div_ceil(1000).max(1)
+    }
+
+    fn open_remaining_ms(&self, opened_at_ms: i64) -> i64 {
+        opened_at_ms + self.config.open_secs as i64 * 1000 - Utc::now().timestamp_millis()
+    }
+
+    /// Fails fast while the breaker is open.
+    pub fn check(&self) -> Result<()> {
+        if self.state() == CircuitState::Open {
+            return Err(AppError::StoreUnavailable {
+                reason: "circuit breaker open".to_string(),
+                retry_after_secs: self.retry_after_secs(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        if self.opened_at_ms.swap(0, Ordering::Relaxed) != 0 {
+            info!("Database circuit breaker closed");
+        }
+    }
+
+    pub fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        let half_open = self.state() == CircuitState::HalfOpen;
+        if failures >= self.config.failure_threshold || half_open {
+            self.opened_at_ms
+                .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+            warn!(
+                failures,
+                open_secs = self.config.open_secs,
+                "Database circuit breaker opened"
+            );
+        }
+    }
+
+    /// Runs an idempotent read, retrying transient failures with exponential
+    /// backoff. Other errors count as the database responding and are
+    /// returned as they are.
+    pub async fn retry<T, F, Fut>(&self, mut read: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut backoff = Duration::from_millis(self.config.retry_backoff_ms);
+        let mut attempt = 0;
+        loop {
+            self.check()?;
+            match read().await {
+                Err(AppError::StoreUnavailable { reason, .. }) => {
+                    self.record_failure();
+                    if attempt >= self.config.read_retries {
+                        return Err(AppError::StoreUnavailable {
+                            reason,
+                            retry_after_secs: self.retry_after_secs(),
+                        });
+                    }
+                    warn!(attempt, error = %reason, "Retrying database read");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => {
+                    self.record_success();
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unavailable() -> Result<()> {
+        Err(AppError::StoreUnavailable {
+            reason: "database is locked".to_string(),
+            retry_after_secs: 1,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_and_recovers() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            read_retries: 1,
+            retry_backoff_ms: 1,
+            failure_threshold: 3,
+            open_secs: 0,
+        });
+
+        // A transient failure is retried and the retry's result returned
+        let mut calls = 0;
+        let result = breaker
+            .retry(|| {
+                calls += 1;
+                async move {
+                    if calls == 1 {
+                        unavailable()
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        // Persistent failures open it once the threshold is reached
+        assert!(breaker.retry(|| async { unavailable() }).await.is_err());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.retry(|| async { unavailable() }).await.is_err());
+        assert_ne!(breaker.state(), CircuitState::Closed);
+
+        // With no open period left it is half-open; a success closes it
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.retry(|| async { Ok(()) }).await.unwrap();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_open_breaker_fails_fast() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 1,
+            open_secs: 30,
+            ..BreakerConfig::default()
+        });
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        match breaker.check() {
+            Err(AppError::StoreUnavailable {
+                retry_after_secs, ..
+            }) => assert!((29..=30).contains(&retry_after_secs)),
+            other => panic!("expected unavailable, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{
+    error::{AppError, Result},
+    hash::Txid,
+};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use tracing::warn;
+
+static BROADCASTER: Lazy<Option<Broadcaster>> =
+    Lazy::new(|| BroadcastConfig::from_env().upstream.map(Broadcaster::new));
+
+/// bitcoind RPC errors that mean the node refused the transaction itself:
+/// it did not decode, failed verification or was rejected from the mempool.
+const BITCOIND_REJECTIONS: &[i64] = &[-22, -25, -26];
+/// bitcoind's `RPC_VERIFY_ALREADY_IN_CHAIN`.
+const BITCOIND_ALREADY_IN_CHAIN: i64 = -27;
+
+/// Where transactions are relayed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Upstream {
+    /// A Bitcoin Core node, through `sendrawtransaction`.
+    Bitcoind {
+        url: String,
+        user: Option<String>,
+        password: Option<String>,
+    },
+    /// An Esplora API, through `POST /tx`.
+    Esplora { url: String },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BroadcastConfig {
+    /// Broadcasting is off when unset.
+    pub upstream: Option<Upstream>,
+}
+
+impl BroadcastConfig {
+    pub fn from_env() -> Self {
+        let url = std::env::var("BROADCAST_UPSTREAM_URL").ok();
+        let upstream = match (std::env::var("BROADCAST_UPSTREAM").as_deref(), url) {
+            (Err(_), _) => None,
+            (Ok(kind), None) => {
+                warn!(
+                    upstream = kind,
+                    "BROADCAST_UPSTREAM_URL is not set, broadcasting is off"
+                );
+                None
+            }
+            (Ok("bitcoind"), Some(url)) => Some(Upstream::Bitcoind {
+                url,
+                user: std::env::var("BROADCAST_RPC_USER").ok(),
+                password: std::env::var("BROADCAST_RPC_PASSWORD").ok(),
+            }),
+            (Ok("esplora"), Some(url)) => Some(Upstream::Esplora { url }),
+            (Ok(other), Some(_)) => {
+                warn!(
+                    upstream = other,
+                    "Unknown BROADCAST_UPSTREAM, broadcasting is off"
+                );
+                None
+            }
+        };
+        Self { upstream }
+    }
+}
+
+/// Relays raw transactions to the configured upstream.
+#[derive(Debug, Clone)]
+pub struct Broadcaster {
+    client: reqwest::Client,
+    upstream: Upstream,
+}
+
+impl Broadcaster {
+    pub fn new(upstream: Upstream) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            upstream,
+        }
+    }
+
+    /// The broadcaster configured by `BROADCAST_UPSTREAM`, if any.
+    pub fn global() -> Option<&'static Self> {
+        BROADCASTER.as_ref()
+    }
+
+    /// Relays the transaction serialized as `hex`, whose txid is `txid`.
+    /// Fails with [`AppError::TransactionRejected`] when the upstream refuses
+    /// it and [`AppError::UpstreamUnavailable`] when it cannot be asked.
+    pub async fn broadcast(&self, hex: &str, txid: &Txid) -> Result<()> {
+        let relayed = match &self.upstream {
+            Upstream::Bitcoind {
+                url,
+                user,
+                password,
+            } => self.send_bitcoind(url, user, password, hex).await?,
+            Upstream::Esplora { url } => self.send_esplora(url, hex).await?,
+        };
+        if relayed.as_ref() != Some(txid) {
+            warn!(%txid, relayed = ?relayed, "Upstream reported a different txid");
+        }
+        Ok(())
+    }
+
+    async fn send_bitcoind(
+        &self,
+        url: &str,
+        user: &Option<String>,
+        password: &Option<String>,
+        hex: &str,
+    ) -> Result<Option<Txid>> {
+        let mut request = self.client.post(url).json(&json!({
+            "jsonrpc": "1.0",
+            "id": "raito",
+            "method": "sendrawtransaction",
+            "params": [hex],
+        }));
+        if let Some(user) = user {
+            request = request.basic_auth(user, password.as_ref());
+        }
+        let response = request.send().await.map_err(unavailable)?;
+        let status = response.status();
+        // Older nodes answer RPC errors with a 500 and the error in the body
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| unavailable(format!("bitcoind answered {status}: {e}")))?;
+
+        if let Some(error) = body.get("error").filter(|error| !error.is_null()) {
+            let code = error["code"].as_i64().unwrap_or_default();
+            let message = error["message"].as_str().unwrap_or("unknown error");
+            if code == BITCOIND_ALREADY_IN_CHAIN {
+                return Ok(None);
+            }
+            if BITCOIND_REJECTIONS.contains(&code) {
+                return Err(AppError::TransactionRejected(message.to_string()));
+            }
+            return Err(unavailable(format!("bitcoind error {code}: {message}")));
+        }
+        Ok(body["result"].as_str().and_then(|txid| txid.parse().ok()))
+    }
+
+    async fn send_esplora(&self, url: &str, hex: &str) -> Result<Option<Txid>> {
+        let response = self
+            .client
+            .post(format!("{}/tx", url.trim_end_matches('/')))
+            .header(reqwest::header::CONTENT_TYPE, "text/plain")
+            .body(hex.to_string())
+            .send()
+            .await
+            .map_err(unavailable)?;
+        let status = response.status();
+        let body = response.text().await.map_err(unavailable)?;
+        match status {
+            status if status.is_success() => Ok(body.trim().parse().ok()),
+            StatusCode::BAD_REQUEST => Err(AppError::TransactionRejected(body.trim().to_string())),
+            status => Err(unavailable(format!("Esplora answered {status}"))),
+        }
+    }
+}
+
+fn unavailable(cause: impl ToString) -> AppError {
+    let cause = cause.to_string();
+    warn!(error = %cause, "Transaction broadcast failed");
+    AppError::UpstreamUnavailable(cause)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::post, Json, Router};
+
+    const TXID: &str = "e8151a2af31c368a35053ddd4bdb285a8595c769a3ad83e0fa02314a602d4609";
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{address}")
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_bitcoind() {
+        let app = Router::new().route(
+            "/",
+            post(|Json(request): Json<Value>| async move {
+                let error = |code: i64, message: &str| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({ "result": null, "error": { "code": code, "message": message } })),
+                    )
+                };
+                match request["params"][0].as_str().unwrap() {
+                    "00" => error(-26, "min relay fee not met"),
+                    "01" => error(-27, "Transaction already in block chain"),
+                    "02" => error(-28, "Loading block index..."),
+                    _ => (StatusCode::OK, Json(json!({ "result": TXID, "error": null }))),
+                }
+            }),
+        );
+        let broadcaster = Broadcaster::new(Upstream::Bitcoind {
+            url: serve(app).await,
+            user: Some("raito".to_string()),
+            password: Some("secret".to_string()),
+        });
+        let txid: Txid = TXID.parse().unwrap();
+
+        broadcaster.broadcast("ff", &txid).await.unwrap();
+        broadcaster.broadcast("01", &txid).await.unwrap();
+        assert!(matches!(
+            broadcaster.broadcast("00", &txid).await,
+            Err(AppError::TransactionRejected(message)) if message == "min relay fee not met"
+        ));
+        assert!(matches!(
+            broadcaster.broadcast("02", &txid).await,
+            Err(AppError::UpstreamUnavailable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_esplora() {
+        let app = Router::new().route(
+            "/api/tx",
+            post(|body: String| async move {
+                match body.as_str() {
+                    "00" => (
+                        StatusCode::BAD_REQUEST,
+                        "sendrawtransaction RPC error: bad-txns-inputs-missingorspent".to_string(),
+                    ),
+                    "01" => (StatusCode::SERVICE_UNAVAILABLE, String::new()),
+                    _ => (StatusCode::OK, TXID.to_string()),
+                }
+            }),
+        );
+        let broadcaster = Broadcaster::new(Upstream::Esplora {
+            url: format!("{}/api/", serve(app).await),
+        });
+        let txid: Txid = TXID.parse().unwrap();
+
+        broadcaster.broadcast("ff", &txid).await.unwrap();
+        assert!(matches!(
+            broadcaster.broadcast("00", &txid).await,
+            Err(AppError::TransactionRejected(message)) if message.contains("missingorspent")
+        ));
+        assert!(matches!(
+            broadcaster.broadcast("01", &txid).await,
+            Err(AppError::UpstreamUnavailable(_))
+        ));
+
+        let unreachable = Broadcaster::new(Upstream::Esplora {
+            url: "http://127.0.0.1:1".to_string(),
+        });
+        assert!(matches!(
+            unreachable.broadcast("ff", &txid).await,
+            Err(AppError::UpstreamUnavailable(_))
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{
+    error::Result,
+    hash::{BlockHash, Txid},
+    model::BlockDetail,
+    telemetry::Metrics,
+};
+use moka::future::Cache;
+use std::{future::Future, sync::Arc, time::Duration};
+
+#[derive(Debug, Clone)]
+pub struct BlockCacheConfig {
+    /// Approximate memory the cached blocks may take; 0 disables the cache.
+    pub max_bytes: u64,
+    /// How long a block stays cached after it was loaded.
+    pub ttl_secs: u64,
+}
+
+impl Default for BlockCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 64 * 1024 * 1024,
+            ttl_secs: 300,
+        }
+    }
+}
+
+impl BlockCacheConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_bytes: std::env::var("BLOCK_CACHE_MAX_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_bytes),
+            ttl_secs: std::env::var("BLOCK_CACHE_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.ttl_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum BlockKey {
+    Height(u32),
+    Hash(BlockHash),
+}
+
+/// LRU-evicted cache of block details in front of the store. Recent blocks
+/// are requested far more often than old ones, so a small cache absorbs
+/// most lookups.
+#[derive(Clone)]
+pub struct BlockCache {
+    blocks: Option<Cache<BlockKey, Arc<BlockDetail>>>,
+}
+
+impl std::fmt::Debug for BlockCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockCache")
+            .field("entries", &self.blocks.as_ref().map(Cache::entry_count))
+            .finish()
+    }
+}
+
+impl BlockCache {
+    pub fn new(config: &BlockCacheConfig) -> Self {
+        let blocks = (config.max_bytes > 0).then(|| {
+            Cache::builder()
+                .max_capacity(config.max_bytes)
+                .weigher(|_, block: &Arc<BlockDetail>| weight(block))
+                .time_to_live(Duration::from_secs(config.ttl_secs))
+                .build()
+        });
+        Self { blocks }
+    }
+
+    /// Returns the cached block for `key`, or loads it and caches it under
+    /// both its height and its hash. Errors are not cached.
+    pub async fn get_or_load<F>(&self, key: BlockKey, load: F) -> Result<BlockDetail>
+    where
+        F: Future<Output = Result<BlockDetail>>,
+    {
+        let Some(blocks) = &self.blocks else {
+            return load.await;
+        };
+        if let Some(block) = blocks.get(&key).await {
+            Metrics::global().record_block_cache(true);
+            return Ok(block.as_ref().clone());
+        }
+
+        Metrics::global().record_block_cache(false);
+        let block = load.await?;
+        let cached = Arc::new(block.clone());
+        blocks
+            .insert(BlockKey::Height(block.summary.height), cached.clone())
+            .await;
+        blocks
+            .insert(BlockKey::Hash(block.summary.hash), cached)
+            .await;
+        Ok(block)
+    }
+
+    /// Drops every cached block. Writes are rare next to reads, so any write
+    /// simply clears the cache rather than tracking which entries it touched.
+    pub fn clear(&self) {
+        if let Some(blocks) = &self.blocks {
+            blocks.invalidate_all();
+        }
+    }
+}
+
+/// Rough in-memory size of a block: fixed header fields plus its txids.
+fn weight(block: &BlockDetail) -> u32 {
+    let txids = block.txids.len() * std::mem::size_of::<Txid>();
+    (512 + txids).try_into().unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MockStore;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_cache_hits_by_height_and_hash() {
+        let store = MockStore::load_from_files().unwrap();
+        let block = store.get_block_by_height(869123).unwrap().clone();
+        let loads = AtomicU32::new(0);
+        let load = || async {
+            loads.fetch_add(1, Ordering::Relaxed);
+            Ok(block.clone())
+        };
+
+        let cache = BlockCache::new(&BlockCacheConfig::default());
+        cache
+            .get_or_load(BlockKey::Height(869123), load())
+            .await
+            .unwrap();
+        let by_hash = cache
+            .get_or_load(BlockKey::Hash(block.summary.hash), load())
+            .await
+            .unwrap();
+        assert_eq!(by_hash.summary.height, 869123);
+        assert_eq!(loads.load(Ordering::Relaxed), 1);
+
+        cache.clear();
+        cache
+            .get_or_load(BlockKey::Height(869123), load())
+            .await
+            .unwrap();
+        assert_eq!(loads.load(Ordering::Relaxed), 2);
+
+        let disabled = BlockCache::new(&BlockCacheConfig {
+            max_bytes: 0,
+            ..BlockCacheConfig::default()
+        });
+        disabled
+            .get_or_load(BlockKey::Height(869123), load())
+            .await
+            .unwrap();
+        disabled
+            .get_or_load(BlockKey::Height(869123), load())
+            .await
+            .unwrap();
+        assert_eq!(loads.load(Ordering::Relaxed), 4);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{model::BlockDetail, prune::PruneConfig};
+use axum::{
+    http::{header, HeaderValue},
+    response::Response,
+};
+use once_cell::sync::Lazy;
+
+static CACHE_CONTROL_CONFIG: Lazy<CacheControlConfig> = Lazy::new(CacheControlConfig::from_env);
+
+#[derive(Debug, Clone)]
+pub struct CacheControlConfig {
+    /// Confirmations after which a block and its proof are treated as final.
+    pub immutable_depth: u32,
+    /// `max-age` for final blocks and proofs.
+    pub immutable_max_age_secs: u64,
+    /// `max-age` for blocks and proofs near the tip, which a reorg may replace.
+    pub recent_max_age_secs: u64,
+    /// Whether the background pruner runs, which rewrites old block responses.
+    pub pruning: bool,
+}
+
+impl Default for CacheControlConfig {
+    fn default() -> Self {
+        Self {
+            immutable_depth: 6,
+            immutable_max_age_secs: 31_536_000,
+            recent_max_age_secs: 10,
+            pruning: false,
+        }
+    }
+}
+
+impl CacheControlConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            immutable_depth: std::env::var("CACHE_IMMUTABLE_DEPTH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&depth| depth > 0)
+                .unwrap_or(defaults.immutable_depth),
+            immutable_max_age_secs: std::env::var("CACHE_IMMUTABLE_MAX_AGE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.immutable_max_age_secs),
+            recent_max_age_secs: std::env::var("CACHE_RECENT_MAX_AGE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.recent_max_age_secs),
+            pruning: PruneConfig::from_env().keep_blocks.is_some(),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &CACHE_CONTROL_CONFIG
+    }
+
+    /// Whether `height` is buried deep enough below `tip_height` to outlive any
+    /// reorg the service expects.
+    pub fn is_settled(&self, height: u32, tip_height: Option<u32>) -> bool {
+        tip_height.is_some_and(|tip| tip >= height && tip - height + 1 >= self.immutable_depth)
+    }
+
+    /// Whether a block response can no longer change: settled, proved, and
+    /// either already pruned or never going to be. Orphans are not final,
+    /// since their tombstones expire.
+    pub fn is_block_final(&self, block: &BlockDetail, tip_height: Option<u32>) -> bool {
+        self.is_settled(block.summary.height, tip_height)
+            && block.summary.verified
+            && (block.pruned || !self.pruning)
+            && !block.orphaned
+    }
+
+    pub fn value(&self, immutable: bool) -> HeaderValue {
+        let value = if immutable {
+            format!("public, max-age={}, immutable", self.immutable_max_age_secs)
+        } else {
+            format!("public, max-age={}", self.recent_max_age_secs)
+        };
+        HeaderValue::from_str(&value).expect("cache directives are ASCII")
+    }
+
+    pub fn apply(&self, response: &mut Response, immutable: bool) {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, self.value(immutable));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settled_depth() {
+        let config = CacheControlConfig::default();
+        assert!(config.is_settled(100, Some(105)));
+        assert!(!config.is_settled(100, Some(104)));
+        assert!(!config.is_settled(100, Some(99)));
+        assert!(!config.is_settled(100, None));
+
+        assert_eq!(config.value(true), "public, max-age=31536000, immutable");
+        assert_eq!(config.value(false), "public, max-age=10");
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{
+    storage::{ProofStorage, StorageUsage},
+    telemetry::Metrics,
+};
+use once_cell::sync::Lazy;
+use std::{sync::Mutex, time::Duration};
+use tracing::warn;
+
+static CAPACITY_CONFIG: Lazy<CapacityConfig> = Lazy::new(CapacityConfig::from_env);
+
+/// Last usage read by the capacity monitor.
+static LAST_USAGE: Mutex<Option<StorageUsage>> = Mutex::new(None);
+
+#[derive(Debug, Clone)]
+pub struct CapacityConfig {
+    /// Readiness reports `degraded` once less than this is left on the proof
+    /// disk; checking is off when 0.
+    pub min_free_bytes: u64,
+    /// How often disk usage is measured.
+    pub interval_secs: u64,
+}
+
+impl Default for CapacityConfig {
+    fn default() -> Self {
+        Self {
+            min_free_bytes: 1024 * 1024 * 1024,
+            interval_secs: 60,
+        }
+    }
+}
+
+impl CapacityConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            min_free_bytes: std::env::var("STORAGE_MIN_FREE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.min_free_bytes),
+            interval_secs: std::env::var("STORAGE_CAPACITY_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.interval_secs),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &CAPACITY_CONFIG
+    }
+
+    pub fn is_low(&self, usage: &StorageUsage) -> bool {
+        usage.available_bytes < self.min_free_bytes
+    }
+}
+
+/// The most recent measurement of the proof disk, if one was taken.
+pub fn last_usage() -> Option<StorageUsage> {
+    *LAST_USAGE.lock().expect("capacity lock poisoned")
+}
+
+/// Measures `storage` and publishes the result to the gauges and readiness
+/// check. Running low is logged, since a proof write that hits a full disk
+/// leaves a truncated file behind.
+pub async fn check_capacity(
+    storage: &dyn ProofStorage,
+    config: &CapacityConfig,
+) -> std::io::Result<Option<StorageUsage>> {
+    let Some(usage) = storage.usage().await? else {
+        return Ok(None);
+    };
+    Metrics::global().set_storage_usage(&usage);
+    if config.is_low(&usage) {
+        warn!(
+            available_bytes = usage.available_bytes,
+            min_free_bytes = config.min_free_bytes,
+            storage = %storage.describe(),
+            "Proof storage is running out of disk space"
+        );
+    }
+    *LAST_USAGE.lock().expect("capacity lock poisoned") = Some(usage);
+    Ok(Some(usage))
+}
+
+/// Refreshes the storage capacity gauges every `interval_secs`.
+pub fn spawn_capacity_monitor(storage: &'static dyn ProofStorage, config: &'static CapacityConfig) {
+    let period = Duration::from_secs(config.interval_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_capacity(storage, config).await {
+                warn!(error = %e, "Failed to measure proof storage");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FsProofStorage;
+
+    #[tokio::test]
+    async fn test_storage_capacity() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FsProofStorage::new(dir.path().join("proofs"));
+
+        // Not written to yet: the parent's disk is measured instead
+        let empty = storage.usage().await.unwrap().unwrap();
+        assert_eq!(empty.proof_bytes, 0);
+        assert!(empty.available_bytes > 0);
+
+        storage.write(100, b"proof 100").await.unwrap();
+        storage.write(101, b"proof 101!").await.unwrap();
+        let usage = storage.usage().await.unwrap().unwrap();
+        assert_eq!(usage.proof_bytes, 19);
+
+        let config = CapacityConfig {
+            min_free_bytes: usage.available_bytes + 1,
+            ..Default::default()
+        };
+        assert!(config.is_low(&usage));
+        let config = CapacityConfig {
+            min_free_bytes: 0,
+            ..Default::default()
+        };
+        assert!(!config.is_low(&usage));
+    }
+}
@@ -0,0 +1,360 @@
+use crate::model::{ChainAnchor, ChainInfo, ConsensusParams};
+use crate::stats;
+use once_cell::sync::Lazy;
+use tracing::warn;
+
+pub const HALVING_INTERVAL: u32 = 210_000;
+pub const RETARGET_INTERVAL: u32 = 2016;
+pub const TARGET_BLOCK_TIME_SECS: u32 = 600;
+
+static NETWORK: Lazy<&'static NetworkParams> =
+    Lazy::new(|| match std::env::var("BITCOIN_NETWORK").as_deref() {
+        Ok("mainnet") | Err(_) => &MAINNET,
+        Ok("testnet") => &TESTNET,
+        Ok("signet") => &SIGNET,
+        Ok("regtest") => &REGTEST,
+        Ok(other) => {
+            warn!(network = other, "Unknown BITCOIN_NETWORK, using mainnet");
+            &MAINNET
+        }
+    });
+
+/// The consensus rules of a network that decide what difficulty a block must
+/// have, as Bitcoin Core's `chainparams` define them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkParams {
+    pub name: &'static str,
+    pub genesis_hash: &'static str,
+    /// Easiest target the network allows, in compact form.
+    pub pow_limit_bits: u32,
+    /// A block more than twice the target spacing after its parent may drop
+    /// to the proof-of-work limit (`fPowAllowMinDifficultyBlocks`).
+    pub allow_min_difficulty_blocks: bool,
+    /// Retarget heights keep the parent's target (`fPowNoRetargeting`).
+    pub no_retargeting: bool,
+}
+
+pub const MAINNET: NetworkParams = NetworkParams {
+    name: "mainnet",
+    genesis_hash: "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+    pow_limit_bits: 0x1d00ffff,
+    allow_min_difficulty_blocks: false,
+    no_retargeting: false,
+};
+
+/// Testnet3.
+pub const TESTNET: NetworkParams = NetworkParams {
+    name: "testnet",
+    genesis_hash: "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943",
+    pow_limit_bits: 0x1d00ffff,
+    allow_min_difficulty_blocks: true,
+    no_retargeting: false,
+};
+
+/// The default signet.
+pub const SIGNET: NetworkParams = NetworkParams {
+    name: "signet",
+    genesis_hash: "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6",
+    pow_limit_bits: 0x1e0377ae,
+    allow_min_difficulty_blocks: false,
+    no_retargeting: false,
+};
+
+pub const REGTEST: NetworkParams = NetworkParams {
+    name: "regtest",
+    genesis_hash: "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+    pow_limit_bits: 0x207fffff,
+    allow_min_difficulty_blocks: true,
+    no_retargeting: true,
+};
+
+/// The network configured through `BITCOIN_NETWORK`, mainnet by default.
+pub fn network() -> &'static NetworkParams {
+    &NETWORK
+}
+
+static ANCHORS: Lazy<Vec<ChainAnchor>> = Lazy::new(|| {
+    std::env::var("CHAIN_ANCHORS")
+        .map(|spec| parse_anchors(&spec))
+        .unwrap_or_default()
+});
+
+/// Parses `height:hash` pairs separated by commas, skipping malformed entries.
+pub fn parse_anchors(spec: &str) -> Vec<ChainAnchor> {
+    let mut anchors: Vec<ChainAnchor> = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let anchor = entry.split_once(':').and_then(|(height, hash)| {
+                Some(ChainAnchor {
+                    height: height.parse().ok()?,
+                    hash: hash.parse().ok()?,
+                })
+            });
+            if anchor.is_none() {
+                warn!(entry, "Ignoring malformed chain anchor");
+            }
+            anchor
+        })
+        .collect();
+    anchors.sort_by_key(|a| a.height);
+    anchors
+}
+
+/// Trusted `height:hash` checkpoints configured through `CHAIN_ANCHORS`.
+pub fn anchors() -> &'static [ChainAnchor] {
+    &ANCHORS
+}
+
+/// Difficulty of a block with compact target `bits`, relative to the
+/// difficulty-1 target, as `getblock` reports it.
+pub fn difficulty(bits: u32) -> f64 {
+    let mantissa = bits & 0x00ff_ffff;
+    if mantissa == 0 {
+        return 0.0;
+    }
+    let exponent = (bits >> 24) as i32;
+    f64::from(0xffff) / f64::from(mantissa) * 256f64.powi(29 - exponent)
+}
+
+/// Expected number of hashes needed to find a block with compact target
+/// `bits`: 2^256 / (target + 1), as Bitcoin Core counts chainwork. Zero for
+/// invalid targets; saturates for targets below 2^128, which no chain has
+/// come near.
+pub fn block_work(bits: u32) -> u128 {
+    let mantissa = u128::from(bits & 0x007f_ffff);
+    let exponent = bits >> 24;
+    if mantissa == 0 || bits & 0x0080_0000 != 0 {
+        return 0;
+    }
+    // target = mantissa * 2^shift
+    let Some(shift) = (exponent * 8).checked_sub(24) else {
+        return u128::MAX;
+    };
+    if shift >= 256 {
+        return 0;
+    }
+
+    // Long division of 2^(256 - shift) by the mantissa, one bit at a time;
+    // the remainder stays below the mantissa
+    let mut quotient: u128 = 0;
+    let mut remainder: u128 = 0;
+    for bit in 0..=(256 - shift) {
+        if quotient >> 127 != 0 {
+            return u128::MAX;
+        }
+        remainder = (remainder << 1) | u128::from(bit == 0);
+        quotient <<= 1;
+        if remainder >= mantissa {
+            remainder -= mantissa;
+            quotient |= 1;
+        }
+    }
+    // The `+ 1` only lowers the result when the division is exact
+    if remainder == 0 {
+        quotient -= 1;
+    }
+    quotient
+}
+
+/// The 256-bit target compact `bits` encode, big-endian, so a hash in display
+/// order meets it when it compares less or equal. `None` for negative, zero
+/// or overflowing targets, which Bitcoin Core rejects.
+pub fn target(bits: u32) -> Option<[u8; 32]> {
+    let mantissa = bits & 0x007f_ffff;
+    let exponent = (bits >> 24) as usize;
+    if mantissa == 0 || bits & 0x0080_0000 != 0 {
+        return None;
+    }
+    // target = mantissa * 256^(exponent - 3), laid out with room for the
+    // mantissa's bytes to spill past the top before the overflow check
+    let mut wide = [0u8; 35];
+    for (i, byte) in mantissa.to_be_bytes()[1..].iter().enumerate() {
+        let position = (35 + i).checked_sub(exponent)?;
+        if let Some(slot) = wide.get_mut(position) {
+            *slot = *byte;
+        }
+    }
+    if wide[..3].iter().any(|&byte| byte != 0) {
+        return None;
+    }
+    wide[3..].try_into().ok()
+}
+
+/// Compact target of the block opening a retarget period, as Bitcoin Core
+/// computes it: the previous target scaled by how long the closing period's
+/// blocks took (`timespan_secs`, clamped to a quarter to four times the two
+/// weeks intended), and no easier than the proof-of-work limit `pow_limit_bits`.
+pub fn next_bits(prev_bits: u32, timespan_secs: i64, pow_limit_bits: u32) -> u32 {
+    let intended = i64::from(RETARGET_INTERVAL * TARGET_BLOCK_TIME_SECS);
+    let timespan = timespan_secs.clamp(intended / 4, intended * 4);
+    let mantissa = u128::from(prev_bits & 0x007f_ffff);
+    let exponent = (prev_bits >> 24) as i32;
+
+    // The scaled target is `scaled * 256^(exponent - 3 - 8)`: the extra eight
+    // bytes keep the precision Core's 256-bit division has before encoding
+    let scaled = (mantissa << 64) * timespan as u128 / intended as u128;
+    if scaled == 0 {
+        return 0;
+    }
+    let size = (128 - scaled.leading_zeros()).div_ceil(8) as i32;
+    let mut compact_mantissa = (scaled >> ((size - 3) * 8)) as u32;
+    let mut compact_exponent = exponent - 8 + size - 3;
+    if compact_mantissa & 0x0080_0000 != 0 {
+        compact_mantissa >>= 8;
+        compact_exponent += 1;
+    }
+    if !(0..=0xff).contains(&compact_exponent) {
+        return pow_limit_bits;
+    }
+    let bits = (compact_exponent as u32) << 24 | compact_mantissa;
+    match (target(bits), target(pow_limit_bits)) {
+        (Some(next), Some(limit)) if next <= limit => bits,
+        _ => pow_limit_bits,
+    }
+}
+
+/// Chainwork of a block with compact target `bits` on top of `parent`:
+/// `None` when its parent isn't stored, so work is counted from this block,
+/// and `Some(None)` when the parent's chainwork is unknown.
+pub fn next_chainwork(parent: Option<Option<u128>>, bits: u32) -> Option<u128> {
+    match parent {
+        Some(parent) => parent.map(|parent| parent.saturating_add(block_work(bits))),
+        None => Some(block_work(bits)),
+    }
+}
+
+/// Formats chainwork as 64 hex digits, as `getblock` does. The fixed width
+/// makes stored values compare in numeric order.
+pub fn format_chainwork(work: u128) -> String {
+    format!("{work:064x}")
+}
+
+/// Parses hex chainwork, with or without leading zeros.
+pub fn parse_chainwork(hex: &str) -> Option<u128> {
+    let digits = hex.trim_start_matches('0');
+    if hex.is_empty() || digits.len() > 32 {
+        return None;
+    }
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u128::from_str_radix(digits, 16).ok()
+}
+
+/// Describes the network and the consensus parameters in effect at `tip_height`.
+pub fn chain_info(tip_height: Option<u32>, pruned_below: u32) -> ChainInfo {
+    let height = tip_height.unwrap_or(0);
+    ChainInfo {
+        network: network().name.to_string(),
+        genesis_hash: network().genesis_hash.to_string(),
+        tip_height,
+        pruned_below: (pruned_below > 0).then_some(pruned_below),
+        consensus: ConsensusParams {
+            halving_interval: HALVING_INTERVAL,
+            halving_epoch: height / HALVING_INTERVAL,
+            blocks_until_halving: HALVING_INTERVAL - height % HALVING_INTERVAL,
+            subsidy: stats::block_subsidy(height),
+            retarget_interval: RETARGET_INTERVAL,
+            blocks_until_retarget: RETARGET_INTERVAL - height % RETARGET_INTERVAL,
+            target_block_time_secs: TARGET_BLOCK_TIME_SECS,
+        },
+        anchors: anchors().to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_info_at_height() {
+        let info = chain_info(Some(869123), 0);
+        assert_eq!(info.consensus.halving_epoch, 4);
+        assert_eq!(info.consensus.blocks_until_halving, 1_050_000 - 869123);
+        assert_eq!(info.consensus.subsidy, 312_500_000);
+        assert_eq!(info.consensus.blocks_until_retarget, 2016 - 869123 % 2016);
+    }
+
+    #[test]
+    fn test_difficulty_and_work() {
+        // Genesis: difficulty 1, and the chainwork `getblock` reports for it
+        assert_eq!(difficulty(0x1d00ffff), 1.0);
+        assert_eq!(block_work(0x1d00ffff), 0x1_0001_0001);
+        assert_eq!(format_chainwork(0x1_0001_0001).len(), 64);
+
+        // A 2024 mainnet target
+        assert!((difficulty(0x17030ecd) - 92_049_594_548_485.47).abs() < 1.0);
+        let work = block_work(0x17030ecd);
+        assert_eq!(work, 395_356_030_850_084_270_690_399);
+
+        // Regtest's target is met by about every other hash
+        assert_eq!(block_work(0x207fffff), 2);
+        assert_eq!(block_work(0), 0);
+        assert_eq!(block_work(0x04800000), 0);
+
+        assert_eq!(parse_chainwork(&format_chainwork(work)), Some(work));
+        assert_eq!(parse_chainwork("00"), Some(0));
+        assert_eq!(parse_chainwork(""), None);
+        assert_eq!(parse_chainwork(&"f".repeat(33)), None);
+        assert_eq!(parse_chainwork("xyz"), None);
+    }
+
+    #[test]
+    fn test_next_bits() {
+        // Bitcoin Core's pow_tests: mainnet retargets at heights 32256,
+        // 2016, 68544 and 48384
+        let limit = MAINNET.pow_limit_bits;
+        assert_eq!(
+            next_bits(0x1d00ffff, 1262152739 - 1261130161, limit),
+            0x1d00d86a
+        );
+        assert_eq!(
+            next_bits(0x1d00ffff, 1233061996 - 1231006505, limit),
+            0x1d00ffff
+        );
+        assert_eq!(
+            next_bits(0x1c05a3f4, 1279297671 - 1279008237, limit),
+            0x1c0168fd
+        );
+        assert_eq!(
+            next_bits(0x1c387f6f, 1269211443 - 1263163443, limit),
+            0x1d00e1fd
+        );
+        // A period of exactly two weeks keeps the target
+        assert_eq!(next_bits(0x17030ecd, 1_209_600, limit), 0x17030ecd);
+        // Regtest's limit caps an easing period at its own target
+        assert_eq!(
+            next_bits(0x207fffff, i64::MAX, REGTEST.pow_limit_bits),
+            0x207fffff
+        );
+    }
+
+    #[test]
+    fn test_target() {
+        let hex = |bits| target(bits).map(hex::encode);
+        assert_eq!(
+            hex(0x1d00ffff).unwrap(),
+            format!("00000000ffff{}", "0".repeat(52))
+        );
+        assert_eq!(
+            hex(0x207fffff).unwrap(),
+            format!("7fffff{}", "0".repeat(58))
+        );
+        // Small exponents shift the mantissa right
+        assert_eq!(hex(0x01123456).unwrap(), format!("{}12", "0".repeat(62)));
+        assert_eq!(hex(0), None);
+        assert_eq!(hex(0x04923456), None);
+        assert_eq!(hex(0x22123456), None);
+    }
+
+    #[test]
+    fn test_parse_anchors() {
+        let hash = "00000000000000000264e1b06b0f6f8b0c7e9e5b8b8f9c9d8b7a6f5e4d3c2b1a";
+        let anchors = parse_anchors(&format!("869123:{hash}, bogus, 0:{}", MAINNET.genesis_hash));
+        assert_eq!(anchors.len(), 2);
+        assert_eq!(anchors[0].height, 0);
+        assert_eq!(anchors[1].hash.to_string(), hash);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    model::{parse_amount, CoinbaseInfo},
+    stats,
+};
+use serde_json::Value;
+
+/// Shortest run of printable characters taken as part of a miner tag, so
+/// that stray bytes of the height push and extranonce don't count.
+const MIN_TAG_RUN: usize = 4;
+
+/// Describes a block's coinbase from its document: the first txid, the
+/// subsidy at its height, and whatever its transaction details carry.
+pub fn coinbase_info(block: &Value) -> Option<CoinbaseInfo> {
+    let height = block["height"].as_u64()? as u32;
+    let txid = block["txids"].get(0)?.as_str()?.parse().ok()?;
+    let subsidy = stats::block_subsidy(height);
+    let coinbase = block["transactions"].as_array().and_then(|transactions| {
+        transactions
+            .iter()
+            .find(|tx| tx["txid"] == block["txids"][0])
+    });
+
+    let claimed_fees = coinbase
+        .and_then(|tx| tx["outputs"].as_array())
+        .and_then(|outputs| {
+            outputs
+                .iter()
+                .map(|output| parse_amount(&output["value"]))
+                .sum::<Option<i64>>()
+        })
+        .map(|claimed| claimed - subsidy);
+    let script = coinbase
+        .and_then(|tx| hex::decode(tx["raw"].as_str()?).ok())
+        .and_then(|raw| script_sig(&raw).map(<[u8]>::to_vec));
+
+    Some(CoinbaseInfo {
+        txid,
+        subsidy,
+        claimed_fees,
+        fees_match: claimed_fees
+            .zip(parse_amount(&block["total_fees"]))
+            .map(|(claimed, total)| claimed == total),
+        miner_tag: script.as_deref().and_then(miner_tag),
+        script_sig: script.map(hex::encode),
+    })
+}
+
+/// The script of a coinbase transaction's only input.
+fn script_sig(raw: &[u8]) -> Option<&[u8]> {
+    let mut rest = raw.get(4..)?;
+    // Segwit marker and flag
+    if rest.starts_with(&[0x00, 0x01]) {
+        rest = &rest[2..];
+    }
+    let (inputs, rest) = compact_size(rest)?;
+    if inputs == 0 {
+        return None;
+    }
+    // Null previous outpoint
+    let (len, rest) = compact_size(rest.get(36..)?)?;
+    rest.get(..usize::try_from(len).ok()?)
+}
+
+fn compact_size(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let (&first, rest) = bytes.split_first()?;
+    let width = match first {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        n => return Some((u64::from(n), rest)),
+    };
+    let mut value = [0u8; 8];
+    value[..width].copy_from_slice(rest.get(..width)?);
+    Some((u64::from_le_bytes(value), &rest[width..]))
+}
+
+/// Printable text pools write into the coinbase script, such as
+/// `/Foundry USA Pool/`, with runs of it joined by spaces.
+fn miner_tag(script: &[u8]) -> Option<String> {
+    let tag = script
+        .split(|byte| !(0x20..0x7f).contains(byte))
+        .filter(|run| run.len() >= MIN_TAG_RUN)
+        .map(|run| String::from_utf8_lossy(run).trim().to_string())
+        .filter(|run| !run.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!tag.is_empty()).then_some(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_coinbase_info() {
+        let blocks: Vec<Value> =
+            serde_json::from_str(include_str!("../data/mock_blocks.json")).unwrap();
+        let info = coinbase_info(&blocks[0]).unwrap();
+        assert_eq!(info.txid.to_string(), blocks[0]["txids"][0]);
+        assert_eq!(info.subsidy, 312_500_000);
+        assert_eq!(info.claimed_fees, Some(12_345_678));
+        assert_eq!(info.fees_match, Some(true));
+        assert_eq!(info.script_sig.as_deref(), Some("0303430d"));
+        assert_eq!(info.miner_tag, None);
+
+        // Transaction details missing: only what the header data gives
+        let info = coinbase_info(&blocks[2]).unwrap();
+        assert_eq!(info.claimed_fees, None);
+        assert_eq!(info.fees_match, None);
+        assert_eq!(info.script_sig, None);
+        assert!(coinbase_info(&json!({ "height": 1, "txids": [] })).is_none());
+    }
+
+    #[test]
+    fn test_miner_tag() {
+        let script = [
+            &[0x03, 0x43, 0x0d, 0x0d][..],
+            b"/Foundry USA Pool/",
+            &[0x01, 0xff],
+        ]
+        .concat();
+        assert_eq!(miner_tag(&script).as_deref(), Some("/Foundry USA Pool/"));
+        assert_eq!(miner_tag(&[0x03, 0x41, 0x42, 0x43]), None);
+
+        // Version, segwit marker, one input with a null outpoint, then the script
+        let raw = [
+            &[2, 0, 0, 0, 0, 1, 1][..],
+            &[0; 32],
+            &[0xff; 4],
+            &[script.len() as u8],
+            &script,
+        ]
+        .concat();
+        assert_eq!(script_sig(&raw), Some(&script[..]));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    database::{Database, ProofFileRecord},
+    error::{AppError, Result},
+    model::{BlockFilter, ConsistencyFinding, ConsistencyReport, FindingKind},
+    proof,
+    storage::ProofStorage,
+};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyConfig {
+    /// How often the checker runs; it is off when unset.
+    pub interval_secs: Option<u64>,
+}
+
+impl ConsistencyConfig {
+    pub fn from_env() -> Self {
+        Self {
+            interval_secs: std::env::var("CONSISTENCY_CHECK_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0),
+        }
+    }
+}
+
+/// Compares the proofs recorded in `records` with those held by `storage`:
+/// recorded proofs missing from storage, stored proofs nobody recorded, and
+/// stored proofs whose checksum (or size, when recorded without a checksum)
+/// changed since they were recorded.
+pub async fn check_proofs(
+    records: &[ProofFileRecord],
+    storage: &dyn ProofStorage,
+) -> Result<Vec<ConsistencyFinding>> {
+    let storage_error =
+        |e: std::io::Error| AppError::Store(anyhow::anyhow!("Failed to read proof storage: {}", e));
+    let stored: HashSet<u32> = storage
+        .list()
+        .await
+        .map_err(storage_error)?
+        .into_iter()
+        .collect();
+
+    let mut findings = Vec::new();
+    for record in records {
+        let data = if stored.contains(&record.height) {
+            storage.read(record.height).await.map_err(storage_error)?
+        } else {
+            None
+        };
+        let Some(data) = data else {
+            findings.push(ConsistencyFinding {
+                kind: FindingKind::MissingProofFile,
+                height: Some(record.height),
+                detail: format!("{} has no proof for this height", storage.describe()),
+            });
+            continue;
+        };
+        match &record.checksum {
+            Some(expected) => {
+                let actual = proof::checksum(&data);
+                if &actual != expected {
+                    findings.push(ConsistencyFinding {
+                        kind: FindingKind::ChecksumMismatch,
+                        height: Some(record.height),
+                        detail: format!("stored proof has sha256 {actual}, recorded {expected}"),
+                    });
+                }
+            }
+            None => match record.file_size {
+                Some(size) if data.len() as i64 != size => {
+                    findings.push(ConsistencyFinding {
+                        kind: FindingKind::SizeMismatch,
+                        height: Some(record.height),
+                        detail: format!("stored proof is {} bytes, recorded {size}", data.len()),
+                    });
+                }
+                _ => {}
+            },
+        }
+    }
+
+    let recorded: HashSet<u32> = records.iter().map(|record| record.height).collect();
+    let mut orphaned: Vec<u32> = stored.difference(&recorded).copied().collect();
+    orphaned.sort_unstable();
+    findings.extend(orphaned.into_iter().map(|height| ConsistencyFinding {
+        kind: FindingKind::OrphanedProofFile,
+        height: Some(height),
+        detail: format!(
+            "{} holds a proof that proof_files does not record",
+            storage.describe()
+        ),
+    }));
+
+    Ok(findings)
+}
+
+/// Runs every check once and stores the findings as a new report.
+pub async fn run(database: &Database, storage: &dyn ProofStorage) -> Result<ConsistencyReport> {
+    let records = database.proof_file_records().await?;
+    let mut findings = check_proofs(&records, storage).await?;
+    findings.extend(database.table_inconsistencies().await?);
+    let blocks_checked = database.count_blocks(&BlockFilter::default()).await?;
+
+    let report = database
+        .record_consistency_run(records.len() as u32, blocks_checked as u32, findings)
+        .await?;
+    if report.findings.is_empty() {
+        info!(run = report.id, "Consistency check found no problems");
+    } else {
+        warn!(
+            run = report.id,
+            findings = report.findings.len(),
+            "Consistency check found problems; see /admin/consistency"
+        );
+    }
+    Ok(report)
+}
+
+/// Periodically cross-checks proof storage against `proof_files` and the
+/// blocks and transactions tables against each other.
+pub fn spawn_consistency_checker(
+    database: Arc<Database>,
+    storage: &'static dyn ProofStorage,
+    config: ConsistencyConfig,
+) {
+    let Some(interval_secs) = config.interval_secs else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = run(&database, storage).await {
+                warn!(error = %e, "Failed to run consistency check");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FsProofStorage;
+
+    #[tokio::test]
+    async fn test_check_proofs() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FsProofStorage::new(dir.path());
+        storage.write(1, b"intact").await.unwrap();
+        storage.write(2, b"tampered").await.unwrap();
+        storage.write(3, b"resized").await.unwrap();
+        storage.write(5, b"unrecorded").await.unwrap();
+
+        let record = |height, data: &[u8], checksum: bool| ProofFileRecord {
+            height,
+            file_size: Some(data.len() as i64),
+            checksum: checksum.then(|| proof::checksum(data)),
+        };
+        let records = [
+            record(1, b"intact", true),
+            record(2, b"original", true),
+            record(3, b"original", false),
+            record(4, b"lost", true),
+        ];
+
+        let findings = check_proofs(&records, &storage).await.unwrap();
+        let kinds: Vec<_> = findings
+            .iter()
+            .map(|finding| (finding.kind, finding.height.unwrap()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (FindingKind::ChecksumMismatch, 2),
+                (FindingKind::SizeMismatch, 3),
+                (FindingKind::MissingProofFile, 4),
+                (FindingKind::OrphanedProofFile, 5),
+            ]
+        );
+    }
+}
@@ -1,14 +1,95 @@
 use crate::{
+    address,
+    breaker::{BreakerConfig, CircuitBreaker},
+    cache::{BlockCache, BlockCacheConfig, BlockKey},
+    chain, coinbase, consistency,
+    encryption::ColumnCipher,
     error::{AppError, Result},
-    model::{BlockDetail, BlockSummary, BlocksResponse, HeaderStatus, TransactionStatus},
+    events::{self, Event},
+    hash::{sha256d, BlockHash, BlockId, Txid, Wtxid},
+    idempotency::{IdempotencyClaim, IdempotencyConfig, StoredResponse},
+    ingest,
+    model::{
+        parse_amount, AddressTransaction, ApiKey, AuditEntry, AuditLogResponse, BlockConflict,
+        BlockConflictsResponse, BlockDetail, BlockFilter, BlockStats, BlockSummary, BlockTombstone,
+        BlockTxidsResponse, BlocksResponse, CoinbaseInfo, ConflictResolution, ConsistencyFinding,
+        ConsistencyReport, DeliveryStatus, FindingKind, HeaderStatus, JobStatus, KeyScope,
+        NetworkStats, OrphansResponse, OutputSpend, OutputStatus, PageDirection,
+        ProofCoverageResponse, ProofGap, ProofGapReason, ProofJob, ProofRecord, ProofsResponse,
+        PruneResponse, SortOrder, TransactionDetail, TransactionInput, TransactionOutput,
+        TransactionStatus, UpdateWebhookRequest, UsageCounts, Utxo, Webhook,
+        WebhookDeliveriesResponse, WebhookDelivery, WebhookEvent,
+    },
+    proof,
+    sla::{ProofMilestone, ProofSlaConfig},
+    stats, storage,
+    store::{BlockStore, ProofBacklog, ReplicaLag},
+    telemetry::Metrics,
+    validation::{self, Rejection, RetargetContext},
+    webhooks, witness,
 };
-use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
-use std::{path::Path, str::FromStr};
-use tracing::info;
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{
+    migrate::Migrator,
+    pool::PoolConnection,
+    query_builder::Separated,
+    sqlite::{SqliteConnectOptions, SqliteQueryResult},
+    QueryBuilder, Sqlite, SqliteConnection, SqliteExecutor, SqlitePool, Transaction,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    ops::RangeInclusive,
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{debug_span, info, warn, Instrument};
+
+const REPLICA_CHECK_INTERVAL_SECS: u64 = 5;
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Rows per multi-row insert, keeping bound parameters well below SQLite's limit.
+const BULK_INSERT_ROWS: usize = 500;
+
+/// Blocks rewritten per transaction by [`Database::reindex`].
+const REINDEX_BATCH_SIZE: usize = 100;
+
+/// Consistency checker runs kept for `/admin/consistency`.
+const CONSISTENCY_RUNS_KEPT: i64 = 20;
+
+const MIGRATION_LOCK_POLL_MS: u64 = 250;
+/// A lock older than this is assumed to belong to a replica that died
+/// mid-migration and is taken over.
+const MIGRATION_LOCK_STALE_SECS: i64 = 300;
+
+/// Columns holding secrets that are encrypted at rest with [`ColumnCipher`],
+/// as `(table, column)` pairs. Tables storing API key secrets, webhook signing
+/// secrets or admin credentials must register their secret columns here.
+pub const ENCRYPTED_COLUMNS: &[(&str, &str)] = &[("webhooks", "secret")];
 
 #[derive(Debug, Clone)]
 pub struct Database {
     pool: SqlitePool,
+    address_index: bool,
+    conflict_policy: ConflictPolicy,
+    /// Read-only copy that serves handler reads while it keeps up.
+    replica: Option<Box<Database>>,
+    replica_max_lag: u32,
+    /// Blocks the replica trails the primary by, as of the last check;
+    /// `u32::MAX` until the first check completes.
+    replica_lag: Arc<AtomicU32>,
+    breaker: Arc<CircuitBreaker>,
+    block_cache: Arc<BlockCache>,
+    /// Encrypts new values of [`ENCRYPTED_COLUMNS`]; `None` stores them as
+    /// plaintext.
+    cipher: Option<Arc<ColumnCipher>>,
 }
 
 #[derive(Debug, Clone)]
@@ -16,6 +97,52 @@ pub struct DatabaseConfig {
     pub database_url: String,
     pub max_connections: u32,
     pub run_migrations: bool,
+    pub address_index: bool,
+    /// Read-only replica for handler reads; ingest and jobs always use the primary.
+    pub replica_url: Option<String>,
+    /// Blocks the replica may trail the primary by before reads fall back to it.
+    pub replica_max_lag: u32,
+    pub conflict_policy: ConflictPolicy,
+}
+
+/// What an insert does when its height already holds a different block, from
+/// `BLOCK_CONFLICT_POLICY`. Every conflict is recorded in `block_conflicts`
+/// whatever the policy; reorgs through [`Database::apply_reorg`] are not
+/// conflicts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Tombstone the stored block and index the incoming one.
+    #[default]
+    Replace,
+    /// Keep the stored block and skip the incoming one.
+    Keep,
+    /// Fail the insert with `BLOCK_CONFLICT`.
+    Reject,
+}
+
+impl ConflictPolicy {
+    pub fn from_env() -> Self {
+        match std::env::var("BLOCK_CONFLICT_POLICY").as_deref() {
+            Ok("replace") | Err(_) => Self::Replace,
+            Ok("keep") => Self::Keep,
+            Ok("reject") => Self::Reject,
+            Ok(other) => {
+                warn!(
+                    policy = other,
+                    "Unknown BLOCK_CONFLICT_POLICY, replacing conflicting blocks"
+                );
+                Self::Replace
+            }
+        }
+    }
+
+    fn resolution(self) -> ConflictResolution {
+        match self {
+            ConflictPolicy::Replace => ConflictResolution::Replaced,
+            ConflictPolicy::Keep => ConflictResolution::Kept,
+            ConflictPolicy::Reject => ConflictResolution::Rejected,
+        }
+    }
 }
 
 impl Default for DatabaseConfig {
@@ -24,6 +151,10 @@ impl Default for DatabaseConfig {
             database_url: "sqlite:raito_data/raito.db".to_string(),
             max_connections: 10,
             run_migrations: true,
+            address_index: false,
+            replica_url: None,
+            replica_max_lag: 6,
+            conflict_policy: ConflictPolicy::default(),
         }
     }
 }
@@ -41,6 +172,16 @@ impl DatabaseConfig {
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
                 .unwrap_or(true),
+            address_index: std::env::var("ADDRESS_INDEX_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            replica_url: std::env::var("DATABASE_REPLICA_URL").ok(),
+            replica_max_lag: std::env::var("DATABASE_REPLICA_MAX_LAG")
+                .unwrap_or_else(|_| "6".to_string())
+                .parse()
+                .unwrap_or(6),
+            conflict_policy: ConflictPolicy::from_env(),
         }
     }
 
@@ -49,6 +190,10 @@ impl DatabaseConfig {
             database_url: "sqlite::memory:".to_string(),
             max_connections: 5,
             run_migrations: true,
+            address_index: true,
+            replica_url: None,
+            replica_max_lag: 6,
+            conflict_policy: ConflictPolicy::default(),
         }
     }
 }
@@ -70,38 +215,193 @@ impl Database {
         }
 
         let options = SqliteConnectOptions::from_str(&config.database_url)
-            .map_err(|e| AppError::Store(anyhow::anyhow!("Invalid database URL: {}", e)))?
+            .map_err(query_error("Invalid database URL"))?
             .create_if_missing(true)
             .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
             .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
             .pragma("cache_size", "1000")
             .pragma("temp_store", "memory");
 
-        let pool = SqlitePool::connect_with(options).await.map_err(|e| {
-            AppError::Store(anyhow::anyhow!("Failed to connect to database: {}", e))
-        })?;
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .map_err(query_error("Failed to connect to database"))?;
+
+        let replica = match &config.replica_url {
+            Some(url) => Some(Box::new(Self::connect_replica(url, &config).await?)),
+            None => None,
+        };
 
-        let db = Self { pool };
+        let db = Self {
+            pool,
+            address_index: config.address_index,
+            conflict_policy: config.conflict_policy,
+            replica,
+            replica_max_lag: config.replica_max_lag,
+            replica_lag: Arc::new(AtomicU32::new(u32::MAX)),
+            breaker: Arc::new(CircuitBreaker::new(BreakerConfig::global().clone())),
+            block_cache: Arc::new(BlockCache::new(&BlockCacheConfig::from_env())),
+            cipher: ColumnCipher::from_env()?.map(Arc::new),
+        };
 
         if config.run_migrations {
             db.run_migrations().await?;
         }
+        if db.replica.is_some() {
+            db.refresh_replica_lag().await?;
+        }
 
         info!("Database initialized successfully");
         Ok(db)
     }
 
+    async fn connect_replica(url: &str, config: &DatabaseConfig) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(url)
+            .map_err(query_error("Invalid replica URL"))?
+            .read_only(true);
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .map_err(query_error("Failed to connect to replica"))?;
+
+        info!("Read replica connected");
+        Ok(Self {
+            pool,
+            address_index: config.address_index,
+            conflict_policy: config.conflict_policy,
+            replica: None,
+            replica_max_lag: 0,
+            replica_lag: Arc::new(AtomicU32::new(0)),
+            breaker: Arc::new(CircuitBreaker::new(BreakerConfig::global().clone())),
+            block_cache: Arc::new(BlockCache::new(&BlockCacheConfig {
+                max_bytes: 0,
+                ..BlockCacheConfig::default()
+            })),
+            cipher: None,
+        })
+    }
+
+    /// Re-measures how many blocks the replica trails the primary by.
+    pub async fn refresh_replica_lag(&self) -> Result<Option<u32>> {
+        let Some(replica) = &self.replica else {
+            return Ok(None);
+        };
+        let primary_tip = self.tip_height().await?.unwrap_or_default();
+        let replica_tip = replica.tip_height().await?.unwrap_or_default();
+        let lag = primary_tip.saturating_sub(replica_tip);
+
+        self.replica_lag.store(lag, Ordering::Relaxed);
+        Ok(Some(lag))
+    }
+
+    /// How far the replica trailed the primary at the last check.
+    pub fn replica_lag(&self) -> Option<ReplicaLag> {
+        self.replica.as_ref().map(|_| ReplicaLag {
+            blocks: self.replica_lag.load(Ordering::Relaxed),
+            max_blocks: self.replica_max_lag,
+        })
+    }
+
+    /// The replica while it keeps up, else the primary.
+    fn reader(&self) -> &Database {
+        match (&self.replica, self.replica_lag()) {
+            (Some(replica), Some(lag)) if !lag.is_lagging() => replica,
+            _ => self,
+        }
+    }
+
+    /// Runs a handler read against [`Self::reader`] behind the circuit
+    /// breaker, retrying transient failures.
+    async fn read<'a, T, F, Fut>(&'a self, query: F) -> Result<T>
+    where
+        F: Fn(&'a Database) -> Fut,
+        Fut: Future<Output = Result<T>> + 'a,
+    {
+        self.breaker.retry(|| query(self.reader())).await
+    }
+
+    /// Applies pending migrations from `migrations/`. Applied versions are
+    /// tracked in `_sqlx_migrations`, and replicas sharing the database take
+    /// turns through [`Self::acquire_migration_lock`].
     pub async fn run_migrations(&self) -> Result<()> {
         info!("Running database migrations...");
 
-        let migration_sql = include_str!("../migrations/001_initial.sql");
+        let holder = self.acquire_migration_lock().await?;
+        let result = MIGRATOR.run(&self.pool).await;
+        self.release_migration_lock(&holder).await?;
+        result.map_err(|e| AppError::Store(anyhow::anyhow!("Migration failed: {}", e)))?;
+
+        info!("Database migrations completed successfully");
+        Ok(())
+    }
+
+    /// Versions recorded as applied, in order.
+    pub async fn applied_migrations(&self) -> Result<Vec<i64>> {
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success ORDER BY version")
+            .fetch_all(&self.pool)
+            .observe("applied_migrations")
+            .await
+            .map_err(query_error("Failed to list migrations"))
+    }
+
+    /// Advisory lock held while migrating: a single-row table that only one
+    /// process can insert into. Waits for the current holder, taking over the
+    /// lock once it is stale.
+    async fn acquire_migration_lock(&self) -> Result<String> {
+        let lock_err = |e: sqlx::Error| {
+            AppError::Store(anyhow::anyhow!("Failed to acquire migration lock: {}", e))
+        };
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS _migration_lock (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                holder TEXT NOT NULL,
+                acquired_at INTEGER NOT NULL
+            )",
+        )
+        .execute(&self.pool)
+        .observe("acquire_migration_lock")
+        .await
+        .map_err(lock_err)?;
 
-        sqlx::query(migration_sql)
+        let holder = format!("{}-{}", std::process::id(), Utc::now().timestamp_micros());
+        let mut waiting = false;
+        loop {
+            let now = Utc::now().timestamp();
+            sqlx::query("DELETE FROM _migration_lock WHERE acquired_at < ?")
+                .bind(now - MIGRATION_LOCK_STALE_SECS)
+                .execute(&self.pool)
+                .observe("acquire_migration_lock")
+                .await
+                .map_err(lock_err)?;
+            let acquired = sqlx::query(
+                "INSERT INTO _migration_lock (id, holder, acquired_at) VALUES (1, ?, ?)
+                 ON CONFLICT (id) DO NOTHING",
+            )
+            .bind(&holder)
+            .bind(now)
             .execute(&self.pool)
+            .observe("acquire_migration_lock")
             .await
-            .map_err(|e| AppError::Store(anyhow::anyhow!("Migration failed: {}", e)))?;
+            .map_err(lock_err)?
+            .rows_affected()
+                == 1;
+            if acquired {
+                return Ok(holder);
+            }
 
-        info!("Database migrations completed successfully");
+            if !waiting {
+                info!("Waiting for another instance to finish migrating");
+                waiting = true;
+            }
+            tokio::time::sleep(Duration::from_millis(MIGRATION_LOCK_POLL_MS)).await;
+        }
+    }
+
+    async fn release_migration_lock(&self, holder: &str) -> Result<()> {
+        sqlx::query("DELETE FROM _migration_lock WHERE holder = ?")
+            .bind(holder)
+            .execute(&self.pool)
+            .observe("release_migration_lock")
+            .await
+            .map_err(query_error("Failed to release migration lock"))?;
         Ok(())
     }
 
@@ -109,278 +409,3714 @@ impl Database {
         info!("Seeding database with mock data...");
 
         let mock_data = include_str!("../data/mock_blocks.json");
-        let blocks: Vec<serde_json::Value> = serde_json::from_str(mock_data)
+        let mut blocks: Vec<serde_json::Value> = serde_json::from_str(mock_data)
             .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to parse mock data: {}", e)))?;
 
+        // Ingest in chain order so spent outputs are known before their spenders
+        blocks.sort_by_key(|b| b["height"].as_u64());
+        self.insert_blocks(&blocks).await?;
+
+        info!("Database seeding completed successfully");
+        Ok(())
+    }
+
+    /// Ingests a block in the mock-data JSON shape. A different block already
+    /// stored at the same height is a conflict, resolved per
+    /// [`DatabaseConfig::conflict_policy`].
+    pub async fn insert_block(&self, block_data: &serde_json::Value) -> Result<()> {
+        self.insert_blocks(std::slice::from_ref(block_data)).await
+    }
+
+    /// Ingests `blocks` in order within a single transaction, so a batch is
+    /// either stored completely or not at all. Blocks conflicting with a
+    /// stored one are recorded and resolved per the conflict policy.
+    pub async fn insert_blocks(&self, blocks: &[serde_json::Value]) -> Result<()> {
+        let mut tx = self.begin().await?;
+        let mut milestones = Vec::new();
+        let mut written = Vec::with_capacity(blocks.len());
         for block_data in blocks {
-            self.insert_block(&block_data).await?;
+            let height = block_data["height"].as_u64().unwrap_or_default() as i64;
+            let incoming = block_data["hash"].as_str().unwrap_or_default();
+            let existing = Self::fetch_hash(&mut tx, height)
+                .await?
+                .filter(|existing| existing != incoming);
+            if let Some(existing) = existing {
+                let resolution = self.conflict_policy.resolution();
+                warn!(height, %existing, incoming, ?resolution, "Conflicting block insert");
+                if resolution == ConflictResolution::Rejected {
+                    // Recorded outside the batch, which is rolled back
+                    drop(tx);
+                    let mut conn = self.acquire().await?;
+                    Self::record_conflict(&mut conn, block_data, &existing, resolution).await?;
+                    return Err(AppError::BlockConflict(format!(
+                        "height {height} holds {existing}, not {incoming}"
+                    )));
+                }
+                Self::record_conflict(&mut tx, block_data, &existing, resolution).await?;
+                if resolution == ConflictResolution::Kept {
+                    continue;
+                }
+            }
+            milestones.extend(self.write_block(&mut tx, block_data).await?);
+            written.push(block_data);
         }
+        let now = Utc::now().timestamp();
+        for &milestone in &milestones {
+            Self::queue_webhook_deliveries(&mut tx, &milestone.into(), now).await?;
+        }
+        Self::commit(tx).await?;
+        self.block_cache.clear();
 
-        info!("Database seeding completed successfully");
+        Self::announce(written, milestones);
+        Ok(())
+    }
+
+    async fn fetch_hash(conn: &mut SqliteConnection, height: i64) -> Result<Option<String>> {
+        sqlx::query_scalar!("SELECT hash FROM blocks WHERE height = ?", height)
+            .fetch_optional(&mut *conn)
+            .observe("block_hash_at_height")
+            .await
+            .map_err(query_error("Failed to fetch block hash"))
+    }
+
+    async fn record_conflict(
+        conn: &mut SqliteConnection,
+        block_data: &serde_json::Value,
+        existing_hash: &str,
+        resolution: ConflictResolution,
+    ) -> Result<()> {
+        let height = block_data["height"].as_i64().unwrap_or_default();
+        let incoming_hash = block_data["hash"].as_str().unwrap_or_default();
+        let resolution = resolution.as_str();
+        let document = block_data.to_string();
+        let detected_at = Utc::now().timestamp();
+        sqlx::query!(
+            r#"
+            INSERT INTO block_conflicts (height, existing_hash, incoming_hash, resolution, block_data, detected_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            height,
+            existing_hash,
+            incoming_hash,
+            resolution,
+            document,
+            detected_at
+        )
+        .execute(&mut *conn)
+        .observe("record_conflict")
+        .await
+        .map_err(query_error("Failed to record block conflict"))?;
         Ok(())
     }
 
-    async fn insert_block(&self, block_data: &serde_json::Value) -> Result<()> {
+    /// Recorded block conflicts, newest first.
+    pub async fn list_block_conflicts(
+        &self,
+        limit: u32,
+        cursor: Option<i64>,
+    ) -> Result<BlockConflictsResponse> {
+        let limit = limit.min(100) as i64;
+        let fetch_limit = limit + 1;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id as "id!: i64", height as "height: u32",
+                   existing_hash as "existing_hash: BlockHash",
+                   incoming_hash as "incoming_hash: BlockHash", resolution, block_data, detected_at
+            FROM block_conflicts
+            WHERE (?1 IS NULL OR id < ?1)
+            ORDER BY id DESC
+            LIMIT ?2
+            "#,
+            cursor,
+            fetch_limit
+        )
+        .fetch_all(&self.pool)
+        .observe("list_block_conflicts")
+        .await
+        .map_err(query_error("Failed to fetch block conflicts"))?;
+
+        let has_next = rows.len() as i64 > limit;
+        let next_cursor = if has_next {
+            rows.get(limit as usize - 1).map(|row| row.id)
+        } else {
+            None
+        };
+
+        let conflicts = rows
+            .into_iter()
+            .take(limit as usize)
+            .map(|row| {
+                let resolution = serde_json::from_value(row.resolution.into()).map_err(|e| {
+                    AppError::Store(anyhow::anyhow!("Malformed conflict resolution: {}", e))
+                })?;
+                let block = serde_json::from_str(&row.block_data).map_err(|e| {
+                    AppError::Store(anyhow::anyhow!("Malformed conflicting block: {}", e))
+                })?;
+                Ok(BlockConflict {
+                    id: row.id,
+                    height: row.height,
+                    existing_hash: row.existing_hash,
+                    incoming_hash: row.incoming_hash,
+                    resolution,
+                    detected_at: row.detected_at,
+                    block,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(BlockConflictsResponse {
+            conflicts,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    /// Reports what a committed write of `blocks` achieved: milestones go to
+    /// the proof SLA, and everything to event subscribers.
+    fn announce<'a>(
+        blocks: impl IntoIterator<Item = &'a serde_json::Value>,
+        milestones: Vec<ProofMilestone>,
+    ) {
+        for block_data in blocks {
+            let height = block_data["height"].as_u64().unwrap() as u32;
+            if let Ok(hash) = block_data["hash"].as_str().unwrap_or_default().parse() {
+                events::publish(Event::BlockIndexed { height, hash });
+            }
+        }
+        for milestone in milestones {
+            ProofSlaConfig::global().observe(milestone);
+            events::publish(milestone.into());
+        }
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Sqlite>> {
+        self.pool
+            .begin()
+            .await
+            .map_err(query_error("Failed to begin transaction"))
+    }
+
+    async fn commit(tx: Transaction<'static, Sqlite>) -> Result<()> {
+        tx.commit()
+            .await
+            .map_err(query_error("Failed to commit transaction"))
+    }
+
+    /// Writes one block and everything derived from it on `conn`. Returns the
+    /// height and ingest-to-proof latency when a proof file was registered.
+    async fn write_block(
+        &self,
+        conn: &mut SqliteConnection,
+        block_data: &serde_json::Value,
+    ) -> Result<Vec<ProofMilestone>> {
         let height = block_data["height"].as_u64().unwrap() as i64;
         let hash = block_data["hash"].as_str().unwrap();
         let prev_hash = block_data["prev_hash"].as_str().unwrap();
         let merkle_root = block_data["merkle_root"].as_str().unwrap();
+        let version = block_data["version"].as_i64().unwrap();
         let bits = block_data["bits"].as_u64().unwrap() as i64;
         let nonce = block_data["nonce"].as_u64().unwrap() as i64;
         let tx_count = block_data["tx_count"].as_u64().unwrap() as i64;
-        let total_fees = block_data["total_fees"].as_f64().unwrap();
+        let total_fees = parse_amount(&block_data["total_fees"]).unwrap();
         let timestamp = block_data["timestamp"].as_i64().unwrap();
         let verified = block_data["verified"].as_bool().unwrap();
+        let size = stats::block_size(block_data);
+        let (block_size, stripped_size, weight) = (size.size, size.stripped_size, size.weight);
+        let difficulty = chain::difficulty(bits as u32);
+
+        // The block must extend what is indexed below it, not float free, at
+        // the difficulty the chain below it calls for
+        let parent = Self::fetch_parent(&mut *conn, height - 1, prev_hash).await?;
+        let parent_height = parent.is_some().then_some(height - 1);
+        let first = parent.is_none() && !Self::has_blocks_below(&mut *conn, height).await?;
+        validation::check_continuity(block_data, parent.is_some(), first, chain::anchors())
+            .map_err(|rejection| AppError::InvalidBlock(rejection.to_string()))?;
+        if let Some(parent) = &parent {
+            let network = chain::network();
+            let offset = height % i64::from(chain::RETARGET_INTERVAL);
+            let period_start = match offset {
+                0 => {
+                    Self::fetch_timestamp(&mut *conn, height - i64::from(chain::RETARGET_INTERVAL))
+                        .await?
+                }
+                _ => None,
+            };
+            let min_difficulty_parent = network.allow_min_difficulty_blocks
+                && offset != 0
+                && parent.bits == network.pow_limit_bits;
+            let period_bits = if min_difficulty_parent {
+                Self::fetch_bits(&mut *conn, height - offset).await?
+            } else {
+                None
+            };
+            let context = RetargetContext {
+                parent_bits: parent.bits,
+                parent_timestamp: parent.timestamp,
+                period_start,
+                period_bits,
+            };
+            validation::check_retarget(block_data, &context, network)
+                .map_err(|rejection| AppError::InvalidBlock(rejection.to_string()))?;
+        }
+
+        let chainwork = match block_data["chainwork"].as_str() {
+            Some(given) => chain::parse_chainwork(given),
+            None => chain::next_chainwork(parent.map(|parent| parent.chainwork), bits as u32),
+        }
+        .map(chain::format_chainwork);
+        let witness = witness::block_witness(block_data);
+        let witness_merkle_root = witness.as_ref().map(|w| w.witness_merkle_root.as_str());
+        let witness_commitment = witness
+            .as_ref()
+            .and_then(|w| w.witness_commitment.as_deref());
+        let coinbase = coinbase::coinbase_info(block_data);
+        if let Some(info) = coinbase
+            .as_ref()
+            .filter(|info| info.fees_match == Some(false))
+        {
+            warn!(
+                height,
+                claimed_fees = info.claimed_fees,
+                total_fees,
+                "Coinbase claims differ from the block's total fees"
+            );
+        }
+        let coinbase_txid = coinbase.as_ref().map(|info| info.txid);
+        let subsidy = coinbase.as_ref().map(|info| info.subsidy);
+        let claimed_fees = coinbase.as_ref().and_then(|info| info.claimed_fees);
+        let coinbase_script = coinbase
+            .as_ref()
+            .and_then(|info| info.script_sig.as_deref());
+        let miner_tag = coinbase.as_ref().and_then(|info| info.miner_tag.as_deref());
+
+        // A different block at this height is being displaced: keep a tombstone
+        // instead of letting the replace below silently drop it
+        let existing = sqlx::query!(
+            r#"
+            SELECT hash, verified as "verified: bool",
+                   CAST(strftime('%s', created_at) AS INTEGER) as "ingested_at!: i64"
+            FROM blocks WHERE height = ?
+            "#,
+            height
+        )
+        .fetch_optional(&mut *conn)
+        .observe("block_at_height")
+        .await
+        .map_err(query_error("Failed to fetch block"))?;
+        if existing
+            .as_ref()
+            .is_some_and(|existing| existing.hash != hash)
+        {
+            let tip_height = Self::fetch_tip_height(&mut *conn)
+                .await?
+                .unwrap_or_default();
+            let depth = tip_height.saturating_sub(height as u32) + 1;
+            Self::tombstone_block(conn, height as u32, Some(hash), depth).await?;
+        }
+
+        // A block seen before may only now arrive verified
+        let mut milestones = Vec::new();
+        let previous = existing.filter(|existing| existing.hash == hash);
+        if verified && !previous.as_ref().is_some_and(|previous| previous.verified) {
+            let secs = previous.map_or(0, |previous| Utc::now().timestamp() - previous.ingested_at);
+            milestones.push(ProofMilestone::Verified {
+                height: height as u32,
+                secs,
+            });
+        }
 
         // Insert block
         sqlx::query!(
             r#"
-            INSERT OR REPLACE INTO blocks 
-            (height, hash, prev_hash, merkle_root, bits, nonce, tx_count, total_fees, timestamp, verified)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO blocks 
+            (height, hash, prev_hash, merkle_root, version, bits, nonce, tx_count, total_fees, timestamp, verified,
+             size, stripped_size, weight, difficulty, chainwork, witness_merkle_root, witness_commitment,
+             coinbase_txid, subsidy, claimed_fees, coinbase_script, miner_tag, parent_height)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (height) DO UPDATE SET
+                hash = excluded.hash, prev_hash = excluded.prev_hash,
+                merkle_root = excluded.merkle_root, version = excluded.version,
+                bits = excluded.bits, nonce = excluded.nonce, tx_count = excluded.tx_count,
+                total_fees = excluded.total_fees, timestamp = excluded.timestamp,
+                verified = excluded.verified, size = excluded.size,
+                stripped_size = excluded.stripped_size, weight = excluded.weight,
+                difficulty = excluded.difficulty, chainwork = excluded.chainwork,
+                witness_merkle_root = excluded.witness_merkle_root,
+                witness_commitment = excluded.witness_commitment,
+                coinbase_txid = excluded.coinbase_txid, subsidy = excluded.subsidy,
+                claimed_fees = excluded.claimed_fees, coinbase_script = excluded.coinbase_script,
+                miner_tag = excluded.miner_tag, parent_height = excluded.parent_height
             "#,
-            height, hash, prev_hash, merkle_root, bits, nonce, tx_count, total_fees, timestamp, verified
+            height, hash, prev_hash, merkle_root, version, bits, nonce, tx_count, total_fees, timestamp, verified,
+            block_size, stripped_size, weight, difficulty, chainwork, witness_merkle_root, witness_commitment,
+            coinbase_txid, subsidy, claimed_fees, coinbase_script, miner_tag, parent_height
         )
-        .execute(&self.pool)
+        .execute(&mut *conn)
+        .observe("insert_block")
         .await
-        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to insert block: {}", e)))?;
-
-        // Insert transactions
-        if let Some(txids) = block_data["txids"].as_array() {
-            for (position, txid_value) in txids.iter().enumerate() {
-                if let Some(txid) = txid_value.as_str() {
-                    let position_i64 = position as i64;
-                    sqlx::query!(
-                        "INSERT OR REPLACE INTO transactions (txid, block_height, position_in_block) VALUES (?, ?, ?)",
-                        txid, height, position_i64
-                    )
-                    .execute(&self.pool)
-                    .await
-                    .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to insert transaction: {}", e)))?;
-                }
-            }
+        .map_err(query_error("Failed to insert block"))?;
+
+        // Insert transactions, with their details and derived block statistics when available
+        let txids: Vec<&str> = block_data["txids"]
+            .as_array()
+            .map(|txids| txids.iter().filter_map(|txid| txid.as_str()).collect())
+            .unwrap_or_default();
+        let transactions = block_data["transactions"].as_array();
+        self.write_transactions(
+            conn,
+            height,
+            &txids,
+            transactions.map(Vec::as_slice).unwrap_or_default(),
+        )
+        .await?;
+        if let Some(transactions) = transactions {
+            Self::write_block_stats(
+                conn,
+                &stats::compute_block_stats(height as u32, transactions),
+            )
+            .await?;
         }
 
         // Insert proof file record if it exists
         let proof_path = format!("data/proofs/{height}.json");
-        if Path::new(&proof_path).exists() {
-            sqlx::query!(
-                r#"
-                INSERT OR REPLACE INTO proof_files 
-                (block_height, file_path, proof_version, generated_at, execution_time_ms)
-                VALUES (?, ?, 'v1.0', ?, 45000)
-                "#,
-                height,
-                proof_path,
-                timestamp
+        if let Ok(data) = std::fs::read(Path::new(&proof_path)) {
+            let proved = Self::write_proof(
+                conn,
+                height as u32,
+                &proof_path,
+                data.len() as i64,
+                Some(&crate::proof::checksum(&data)),
+                timestamp,
+                Some(45000),
             )
-            .execute(&self.pool)
-            .await
-            .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to insert proof file: {}", e)))?;
+            .await?;
+            milestones.push(proved);
         }
 
-        Ok(())
+        Ok(milestones)
     }
 
-    pub async fn get_blocks(&self, limit: u32, cursor: Option<u32>) -> Result<BlocksResponse> {
-        let limit = limit.min(50) as i64;
+    /// What a child needs from the block `hash` at `height`, or `None` when no
+    /// such block is stored.
+    async fn fetch_parent(
+        conn: &mut SqliteConnection,
+        height: i64,
+        hash: &str,
+    ) -> Result<Option<ParentBlock>> {
+        let parent = sqlx::query!(
+            "SELECT chainwork, bits, timestamp FROM blocks WHERE height = ? AND hash = ?",
+            height,
+            hash
+        )
+        .fetch_optional(&mut *conn)
+        .observe("block_parent")
+        .await
+        .map_err(query_error("Failed to fetch parent block"))?;
+        Ok(parent.map(|parent| ParentBlock {
+            chainwork: parent.chainwork.as_deref().and_then(chain::parse_chainwork),
+            bits: parent.bits as u32,
+            timestamp: parent.timestamp,
+        }))
+    }
 
-        let blocks = if let Some(cursor_height) = cursor {
-            let cursor_i64 = cursor_height as i64;
-            sqlx::query_as!(
-                BlockSummary,
-                r#"
-                SELECT height as "height: u32", hash, tx_count as "tx_count: u32", 
-                       total_fees, timestamp, verified as "verified: bool"
-                FROM blocks 
-                WHERE height < ?
-                ORDER BY height DESC 
-                LIMIT ?
-                "#,
-                cursor_i64,
-                limit
-            )
-            .fetch_all(&self.pool)
+    async fn fetch_timestamp(conn: &mut SqliteConnection, height: i64) -> Result<Option<i64>> {
+        sqlx::query_scalar!("SELECT timestamp FROM blocks WHERE height = ?", height)
+            .fetch_optional(&mut *conn)
+            .observe("block_timestamp")
             .await
-        } else {
-            sqlx::query_as!(
-                BlockSummary,
-                r#"
-                SELECT height as "height: u32", hash, tx_count as "tx_count: u32", 
-                       total_fees, timestamp, verified as "verified: bool"
-                FROM blocks 
-                ORDER BY height DESC 
-                LIMIT ?
-                "#,
-                limit
-            )
-            .fetch_all(&self.pool)
+            .map_err(query_error("Failed to fetch block timestamp"))
+    }
+
+    async fn fetch_bits(conn: &mut SqliteConnection, height: i64) -> Result<Option<u32>> {
+        let bits = sqlx::query_scalar!("SELECT bits FROM blocks WHERE height = ?", height)
+            .fetch_optional(&mut *conn)
+            .observe("block_bits")
             .await
+            .map_err(query_error("Failed to fetch block bits"))?;
+        Ok(bits.map(|bits| bits as u32))
+    }
+
+    async fn has_blocks_below(conn: &mut SqliteConnection, height: i64) -> Result<bool> {
+        let below =
+            sqlx::query_scalar!("SELECT height FROM blocks WHERE height < ? LIMIT 1", height)
+                .fetch_optional(&mut *conn)
+                .observe("blocks_below")
+                .await
+                .map_err(query_error("Failed to fetch blocks"))?;
+        Ok(below.is_some())
+    }
+
+    /// Heights of indexed blocks whose parent is not indexed, other than the
+    /// lowest: each is a gap below it or a fork left by a replaced parent.
+    pub async fn chain_breaks(&self) -> Result<Vec<u32>> {
+        let heights = sqlx::query_scalar!(
+            r#"
+            SELECT height AS "height!" FROM blocks
+            WHERE parent_height IS NULL AND height > (SELECT MIN(height) FROM blocks)
+            ORDER BY height
+            "#
+        )
+        .fetch_all(&self.pool)
+        .observe("chain_breaks")
+        .await
+        .map_err(query_error("Failed to fetch chain breaks"))?;
+        Ok(heights.into_iter().map(|height| height as u32).collect())
+    }
+
+    /// Switches to the branch described by `new_blocks`, starting at its lowest
+    /// height. Canonical blocks the branch displaces are tombstoned with the
+    /// hash that replaced them and the depth of the reorg. Returns that depth.
+    ///
+    /// A branch with less chainwork than the current chain is ignored and 0 is
+    /// returned; when either chainwork is unknown the branch is followed.
+    pub async fn apply_reorg(&self, new_blocks: &[serde_json::Value]) -> Result<u32> {
+        let mut new_blocks = new_blocks.to_vec();
+        new_blocks.sort_by_key(|b| b["height"].as_u64());
+        let Some(fork_height) = new_blocks.first().and_then(|b| b["height"].as_i64()) else {
+            return Ok(0);
+        };
+
+        let mut tx = self.begin().await?;
+        let tip_work =
+            sqlx::query_scalar!("SELECT chainwork FROM blocks ORDER BY height DESC LIMIT 1")
+                .fetch_optional(&mut *tx)
+                .observe("tip_chainwork")
+                .await
+                .map_err(query_error("Failed to fetch chainwork"))?
+                .flatten()
+                .as_deref()
+                .and_then(chain::parse_chainwork);
+        let branch_work = match new_blocks.last().and_then(|b| b["chainwork"].as_str()) {
+            Some(given) => chain::parse_chainwork(given),
+            None => {
+                let prev_hash = new_blocks[0]["prev_hash"].as_str().unwrap_or_default();
+                Self::fetch_parent(&mut tx, fork_height - 1, prev_hash)
+                    .await?
+                    .and_then(|parent| parent.chainwork)
+                    .map(|parent| {
+                        new_blocks.iter().fold(parent, |work, b| {
+                            let bits = b["bits"].as_u64().unwrap_or_default() as u32;
+                            work.saturating_add(chain::block_work(bits))
+                        })
+                    })
+            }
+        };
+        if let (Some(branch_work), Some(tip_work)) = (branch_work, tip_work) {
+            if branch_work < tip_work {
+                warn!(
+                    fork_height,
+                    branch_work = %chain::format_chainwork(branch_work),
+                    tip_work = %chain::format_chainwork(tip_work),
+                    "Ignoring branch with less chainwork than the active chain"
+                );
+                return Ok(0);
+            }
         }
-        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to fetch blocks: {}", e)))?;
 
-        let total = sqlx::query_scalar!("SELECT COUNT(*) FROM blocks")
-            .fetch_one(&self.pool)
-            .await
-            .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to count blocks: {}", e)))?;
+        let existing = sqlx::query!(
+            "SELECT height, hash FROM blocks WHERE height >= ? ORDER BY height DESC",
+            fork_height
+        )
+        .fetch_all(&mut *tx)
+        .observe("reorged_blocks")
+        .await
+        .map_err(query_error("Failed to fetch reorged blocks"))?;
 
-        let has_next = blocks.len() as i64 == limit;
-        let next_cursor = if has_next {
-            blocks.last().map(|b| b.height)
-        } else {
-            None
+        let replacement = |height: i64| {
+            new_blocks
+                .iter()
+                .find(|b| b["height"].as_i64() == Some(height))
+                .and_then(|b| b["hash"].as_str())
         };
+        let orphaned: Vec<_> = existing
+            .into_iter()
+            .filter(|b| replacement(b.height) != Some(b.hash.as_str()))
+            .collect();
+        let depth = orphaned.len() as u32;
 
-        Ok(BlocksResponse {
-            blocks,
-            total: total as u32,
-            has_next,
-            next_cursor,
-        })
+        for block in &orphaned {
+            Self::tombstone_block(
+                &mut tx,
+                block.height as u32,
+                replacement(block.height),
+                depth,
+            )
+            .await?;
+        }
+        let mut milestones = Vec::new();
+        for block_data in &new_blocks {
+            milestones.extend(self.write_block(&mut tx, block_data).await?);
+        }
+        let reorg = (depth > 0).then_some(Event::Reorg {
+            fork_height: fork_height as u32,
+            depth,
+        });
+        let now = Utc::now().timestamp();
+        for event in reorg
+            .iter()
+            .cloned()
+            .chain(milestones.iter().map(|&m| m.into()))
+        {
+            Self::queue_webhook_deliveries(&mut tx, &event, now).await?;
+        }
+        Self::commit(tx).await?;
+        self.block_cache.clear();
+
+        if let Some(reorg) = reorg {
+            info!(fork_height, depth, "Applied chain reorg");
+            events::publish(reorg);
+        }
+        Self::announce(&new_blocks, milestones);
+        Ok(depth)
     }
 
-    pub async fn get_block_by_height(&self, height: u32) -> Result<BlockDetail> {
+    /// Records the block at `height` in `block_tombstones` and
+    /// `orphaned_blocks` and removes it (and
+    /// everything cascading from it) from the canonical tables.
+    async fn tombstone_block(
+        conn: &mut SqliteConnection,
+        height: u32,
+        replaced_by: Option<&str>,
+        depth: u32,
+    ) -> Result<()> {
+        let block = Self::fetch_block_by_height(&mut *conn, height).await?;
+        let block_data = serde_json::to_string(&block)
+            .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to snapshot block: {}", e)))?;
+
         let height_i64 = height as i64;
-        let block_row = sqlx::query!(
+        let depth = depth as i64;
+        let orphaned_at = Utc::now().timestamp();
+        sqlx::query!(
             r#"
-            SELECT height, hash, prev_hash, merkle_root, bits, nonce, 
-                   tx_count, total_fees, timestamp, verified
-            FROM blocks 
-            WHERE height = ?
+            INSERT INTO block_tombstones (height, hash, replaced_by, reorg_depth, block_data, orphaned_at)
+            VALUES (?, ?, ?, ?, ?, ?)
             "#,
-            height_i64
+            height_i64,
+            block.summary.hash,
+            replaced_by,
+            depth,
+            block_data,
+            orphaned_at
         )
-        .fetch_optional(&self.pool)
+        .execute(&mut *conn)
+        .observe("insert_tombstone")
         .await
-        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to fetch block: {}", e)))?
-        .ok_or_else(|| AppError::BlockNotFound(height.to_string()))?;
+        .map_err(query_error("Failed to insert tombstone"))?;
 
-        let txids: Vec<String> = sqlx::query_scalar!(
-            "SELECT txid FROM transactions WHERE block_height = ? ORDER BY position_in_block",
-            height_i64
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO orphaned_blocks (height, hash, replaced_by, reorg_depth, block_data, orphaned_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            height_i64,
+            block.summary.hash,
+            replaced_by,
+            depth,
+            block_data,
+            orphaned_at
         )
-        .fetch_all(&self.pool)
+        .execute(&mut *conn)
+        .observe("insert_orphan")
         .await
-        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to fetch transactions: {}", e)))?
-        .into_iter()
-        .flatten()
-        .collect();
+        .map_err(query_error("Failed to record orphaned block"))?;
 
-        Ok(BlockDetail {
-            summary: BlockSummary {
-                height: block_row.height as u32,
-                hash: block_row.hash,
-                tx_count: block_row.tx_count as u32,
-                total_fees: block_row.total_fees,
-                timestamp: block_row.timestamp,
-                verified: block_row.verified,
-            },
-            prev_hash: block_row.prev_hash,
-            merkle_root: block_row.merkle_root,
-            bits: block_row.bits as u32,
-            nonce: block_row.nonce as u32,
-            proof_url: format!("/v1/blocks/{height}/proof"),
-            txids,
-        })
+        sqlx::query!("DELETE FROM blocks WHERE height = ?", height_i64)
+            .execute(&mut *conn)
+            .observe("delete_orphaned_block")
+            .await
+            .map_err(query_error("Failed to remove orphaned block"))?;
+
+        Ok(())
     }
 
-    pub async fn get_block_by_hash(&self, hash: &str) -> Result<BlockDetail> {
-        let height = sqlx::query_scalar!(
-            "SELECT block_height FROM block_headers WHERE hash = ?",
-            hash
+    /// Lists orphaned blocks, most recently orphaned first.
+    pub async fn get_orphans(&self, limit: u32, cursor: Option<i64>) -> Result<OrphansResponse> {
+        let limit = limit.min(50) as i64;
+        let fetch_limit = limit + 1;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id as "id!: i64", height as "height: u32", hash as "hash: BlockHash",
+                   replaced_by as "replaced_by: BlockHash",
+                   reorg_depth as "reorg_depth: u32", block_data, orphaned_at
+            FROM orphaned_blocks
+            WHERE (?1 IS NULL OR id < ?1)
+            ORDER BY id DESC
+            LIMIT ?2
+            "#,
+            cursor,
+            fetch_limit
         )
-        .fetch_optional(&self.pool)
+        .fetch_all(&self.pool)
+        .observe("list_orphans")
         .await
-        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to fetch block by hash: {}", e)))?
-        .ok_or_else(|| AppError::BlockNotFound(hash.to_string()))?;
+        .map_err(query_error("Failed to fetch orphans"))?;
+
+        let has_next = rows.len() as i64 > limit;
+        let next_cursor = if has_next {
+            rows.get(limit as usize - 1).map(|row| row.id)
+        } else {
+            None
+        };
+
+        let orphans = rows
+            .into_iter()
+            .take(limit as usize)
+            .map(|row| {
+                let block = serde_json::from_str(&row.block_data).map_err(|e| {
+                    AppError::Store(anyhow::anyhow!("Malformed orphan snapshot: {}", e))
+                })?;
+                Ok(BlockTombstone {
+                    height: row.height,
+                    hash: row.hash,
+                    replaced_by: row.replaced_by,
+                    reorg_depth: row.reorg_depth,
+                    orphaned_at: row.orphaned_at,
+                    block,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(OrphansResponse {
+            orphans,
+            has_next,
+            next_cursor,
+        })
+    }
 
-        self.get_block_by_height(height as u32).await
+    /// Height below which transaction data has been pruned; 0 when nothing has.
+    pub async fn pruned_below(&self) -> Result<u32> {
+        Self::fetch_pruned_below(&self.pool).await
     }
 
-    pub async fn get_transaction_status(&self, txid: &str) -> Result<TransactionStatus> {
-        let result = sqlx::query!("SELECT block_height FROM transactions WHERE txid = ?", txid)
-            .fetch_optional(&self.pool)
+    async fn fetch_pruned_below<'c>(executor: impl SqliteExecutor<'c>) -> Result<u32> {
+        let pruned_below = sqlx::query_scalar!("SELECT pruned_below FROM prune_state")
+            .fetch_one(executor)
+            .observe_one("pruned_below")
             .await
-            .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to fetch transaction: {}", e)))?;
+            .map_err(query_error("Failed to fetch prune horizon"))?;
+        Ok(pruned_below as u32)
+    }
 
-        Ok(match result {
-            Some(row) => TransactionStatus {
+    /// Deletes the transactions of blocks below `height`, along with their
+    /// inputs, outputs and index entries. Block rows and proofs are kept. The
+    /// horizon only moves up: a lower `height` leaves everything as it is.
+    pub async fn prune_below(&self, height: u32) -> Result<PruneResponse> {
+        let mut tx = self.begin().await?;
+        let current = Self::fetch_pruned_below(&mut *tx).await?;
+        if height <= current {
+            return Ok(PruneResponse {
+                pruned_below: current,
+                transactions_removed: 0,
+            });
+        }
+
+        let height_i64 = height as i64;
+        let removed = sqlx::query!(
+            "DELETE FROM transactions WHERE block_height < ?",
+            height_i64
+        )
+        .execute(&mut *tx)
+        .observe("prune_transactions")
+        .await
+        .map_err(query_error("Failed to prune transactions"))?
+        .rows_affected();
+        sqlx::query!("UPDATE prune_state SET pruned_below = ?", height_i64)
+            .execute(&mut *tx)
+            .observe("update_prune_horizon")
+            .await
+            .map_err(query_error("Failed to update prune horizon"))?;
+        Self::commit(tx).await?;
+        self.block_cache.clear();
+
+        info!(pruned_below = height, removed, "Pruned transaction data");
+        Ok(PruneResponse {
+            pruned_below: height,
+            transactions_removed: removed,
+        })
+    }
+
+    /// Deletes tombstones created before `cutoff` (unix seconds). Orphaned
+    /// blocks live in their own table and are left alone.
+    pub async fn purge_tombstones(&self, cutoff: i64) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM block_tombstones WHERE orphaned_at < ?", cutoff)
+            .execute(&self.pool)
+            .observe("purge_tombstones")
+            .await
+            .map_err(query_error("Failed to purge tombstones"))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes orphaned blocks recorded before `cutoff` (unix seconds).
+    pub async fn purge_orphans(&self, cutoff: i64) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM orphaned_blocks WHERE orphaned_at < ?", cutoff)
+            .execute(&self.pool)
+            .observe("purge_orphans")
+            .await
+            .map_err(query_error("Failed to purge orphaned blocks"))?;
+
+        // Orphans are cached by hash like any block
+        if result.rows_affected() > 0 {
+            self.block_cache.clear();
+        }
+        Ok(result.rows_affected())
+    }
+
+    /// Rewrites the blocks from `from` through `to` from their stored
+    /// transactions, rebuilding statistics, spends and the address index.
+    /// Pruned blocks are skipped since their transactions are gone. Returns
+    /// how many blocks were rewritten.
+    pub async fn reindex(&self, from: u32, to: u32) -> Result<u32> {
+        let from = from.max(self.pruned_below().await?);
+        if from > to {
+            return Ok(0);
+        }
+        let heights = self.block_heights(from..=to).await?;
+        for batch in heights.chunks(REINDEX_BATCH_SIZE) {
+            let mut blocks = Vec::with_capacity(batch.len());
+            for &height in batch {
+                blocks.push(ingest::block_document(self, height).await?);
+            }
+            self.insert_blocks(&blocks).await?;
+        }
+        Ok(heights.len() as u32)
+    }
+
+    /// Clears the failed mark from proofs so they count as never attempted
+    /// and are picked up again. Returns how many were requeued.
+    pub async fn requeue_failed_proofs(&self) -> Result<u32> {
+        let failed = ProofGapReason::Failed.as_str();
+        let result = sqlx::query!("DELETE FROM proof_status WHERE status = ?", failed)
+            .execute(&self.pool)
+            .observe("requeue_failed_proofs")
+            .await
+            .map_err(query_error("Failed to requeue failed proofs"))?;
+
+        Ok(result.rows_affected() as u32)
+    }
+
+    /// Drops every cached block, here and on the replica.
+    pub fn invalidate_cache(&self) {
+        self.block_cache.clear();
+        if let Some(replica) = &self.replica {
+            replica.block_cache.clear();
+        }
+    }
+
+    pub async fn create_api_key(
+        &self,
+        name: &str,
+        scope: KeyScope,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Result<ApiKey> {
+        let created_at = Utc::now().timestamp();
+        let scope_str = scope.as_str();
+        let id = sqlx::query!(
+            "INSERT INTO api_keys (name, prefix, scope, key_hash, created_at) VALUES (?, ?, ?, ?, ?)",
+            name,
+            prefix,
+            scope_str,
+            key_hash,
+            created_at
+        )
+        .execute(&self.pool)
+        .observe("create_api_key")
+        .await
+        .map_err(query_error("Failed to create API key"))?
+        .last_insert_rowid();
+
+        Ok(ApiKey {
+            id,
+            name: name.to_string(),
+            prefix: prefix.to_string(),
+            scope,
+            created_at,
+            last_used_at: None,
+            revoked_at: None,
+        })
+    }
+
+    pub async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        let rows = sqlx::query_as!(
+            ApiKeyRow,
+            "SELECT id, name, prefix, scope, created_at, last_used_at, revoked_at
+             FROM api_keys ORDER BY id"
+        )
+        .fetch_all(&self.pool)
+        .observe("list_api_keys")
+        .await
+        .map_err(query_error("Failed to list API keys"))?;
+        rows.into_iter().map(ApiKeyRow::into_api_key).collect()
+    }
+
+    /// Revokes key `id`. Revoking an already revoked key keeps its original
+    /// revocation time.
+    pub async fn revoke_api_key(&self, id: i64) -> Result<ApiKey> {
+        let revoked_at = Utc::now().timestamp();
+        sqlx::query_as!(
+            ApiKeyRow,
+            r#"UPDATE api_keys SET revoked_at = COALESCE(revoked_at, ?) WHERE id = ?
+             RETURNING id as "id!", name, prefix, scope, created_at, last_used_at, revoked_at"#,
+            revoked_at,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .observe("revoke_api_key")
+        .await
+        .map_err(query_error("Failed to revoke API key"))?
+        .ok_or(AppError::ApiKeyNotFound(id))?
+        .into_api_key()
+    }
+
+    /// The unrevoked key whose digest is `key_hash`. Its `last_used_at` is
+    /// refreshed at most once a minute, so busy clients don't turn every read
+    /// into a write.
+    pub async fn authenticate_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>> {
+        let key = sqlx::query_as!(
+            ApiKeyRow,
+            r#"SELECT id as "id!", name, prefix, scope, created_at, last_used_at, revoked_at
+             FROM api_keys WHERE key_hash = ? AND revoked_at IS NULL"#,
+            key_hash
+        )
+        .fetch_optional(&self.pool)
+        .observe("authenticate_api_key")
+        .await
+        .map_err(query_error("Failed to look up API key"))?;
+
+        let Some(mut key) = key.map(ApiKeyRow::into_api_key).transpose()? else {
+            return Ok(None);
+        };
+        let now = Utc::now().timestamp();
+        if key.last_used_at.is_none_or(|used| now - used >= 60) {
+            sqlx::query!(
+                "UPDATE api_keys SET last_used_at = ? WHERE id = ?",
+                now,
+                key.id
+            )
+            .execute(&self.pool)
+            .observe("touch_api_key")
+            .await
+            .map_err(query_error("Failed to record API key use"))?;
+            key.last_used_at = Some(now);
+        }
+        Ok(Some(key))
+    }
+
+    pub async fn record_api_key_usage(&self, id: i64, day: &str, usage: UsageCounts) -> Result<()> {
+        let requests = usage.requests as i64;
+        let proof_bytes = usage.proof_bytes as i64;
+        sqlx::query!(
+            "INSERT INTO api_key_usage (api_key_id, day, requests, proof_bytes)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT (api_key_id, day) DO UPDATE SET
+                 requests = requests + excluded.requests,
+                 proof_bytes = proof_bytes + excluded.proof_bytes",
+            id,
+            day,
+            requests,
+            proof_bytes
+        )
+        .execute(&self.pool)
+        .observe("record_api_key_usage")
+        .await
+        .map_err(query_error("Failed to record API key usage"))?;
+        Ok(())
+    }
+
+    pub async fn get_api_key_usage(
+        &self,
+        id: i64,
+        day: &str,
+        month_start: &str,
+    ) -> Result<(UsageCounts, UsageCounts)> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(CASE WHEN day = ?2 THEN requests END), 0) as "day_requests!: i64",
+                   COALESCE(SUM(CASE WHEN day = ?2 THEN proof_bytes END), 0) as "day_proof_bytes!: i64",
+                   COALESCE(SUM(requests), 0) as "month_requests!: i64",
+                   COALESCE(SUM(proof_bytes), 0) as "month_proof_bytes!: i64"
+            FROM api_key_usage
+            WHERE api_key_id = ?1 AND day >= ?3 AND day <= ?2
+            "#,
+            id,
+            day,
+            month_start
+        )
+        .fetch_one(&self.pool)
+        .observe_one("api_key_usage")
+        .await
+        .map_err(query_error("Failed to fetch API key usage"))?;
+
+        Ok((
+            UsageCounts {
+                requests: row.day_requests as u64,
+                proof_bytes: row.day_proof_bytes as u64,
+            },
+            UsageCounts {
+                requests: row.month_requests as u64,
+                proof_bytes: row.month_proof_bytes as u64,
+            },
+        ))
+    }
+
+    /// Sets aside a block whose header failed validation, keeping the
+    /// document as received. Quarantining the same hash again replaces it.
+    pub async fn quarantine_block(
+        &self,
+        block_data: &serde_json::Value,
+        rejection: &Rejection,
+    ) -> Result<()> {
+        let hash = block_data["hash"].as_str().unwrap_or_default();
+        let height = block_data["height"].as_i64().unwrap_or_default();
+        let document = block_data.to_string();
+        let quarantined_at = Utc::now().timestamp();
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO quarantined_blocks (hash, height, reason, detail, document, quarantined_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            hash,
+            height,
+            rejection.reason,
+            rejection.detail,
+            document,
+            quarantined_at
+        )
+        .execute(&self.pool)
+        .observe("quarantine_block")
+        .await
+        .map_err(query_error("Failed to quarantine block"))?;
+        Ok(())
+    }
+
+    /// Hashes of quarantined blocks, lowest height first.
+    pub async fn quarantined_hashes(&self) -> Result<Vec<String>> {
+        sqlx::query_scalar!(
+            r#"SELECT hash AS "hash!" FROM quarantined_blocks ORDER BY height, hash"#
+        )
+        .fetch_all(&self.pool)
+        .observe("quarantined_hashes")
+        .await
+        .map_err(query_error("Failed to list quarantined blocks"))
+    }
+
+    pub async fn record_audit(
+        &self,
+        actor: &str,
+        action: &str,
+        target: Option<&str>,
+    ) -> Result<()> {
+        let created_at = Utc::now().timestamp();
+        sqlx::query!(
+            "INSERT INTO audit_log (actor, action, target, created_at) VALUES (?, ?, ?, ?)",
+            actor,
+            action,
+            target,
+            created_at
+        )
+        .execute(&self.pool)
+        .observe("record_audit")
+        .await
+        .map_err(query_error("Failed to record audit entry"))?;
+        Ok(())
+    }
+
+    /// Claims idempotency key `key` of client `scope` for the request with
+    /// `fingerprint`. Expired keys are forgotten first, and a claim left
+    /// pending past `config.pending_secs` is taken over.
+    pub async fn claim_idempotency_key(
+        &self,
+        scope: &str,
+        key: &str,
+        fingerprint: &str,
+        now: i64,
+        config: &IdempotencyConfig,
+    ) -> Result<IdempotencyClaim> {
+        let expired_before = now - config.ttl_secs;
+        let abandoned_before = now - config.pending_secs;
+        let mut tx = self.begin().await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE created_at < ?
+               OR (scope = ? AND idempotency_key = ? AND status IS NULL AND created_at < ?)
+            "#,
+            expired_before,
+            scope,
+            key,
+            abandoned_before
+        )
+        .execute(&mut *tx)
+        .observe("expire_idempotency_keys")
+        .await
+        .map_err(query_error("Failed to expire idempotency keys"))?;
+
+        let existing = sqlx::query!(
+            r#"
+            SELECT fingerprint, status, content_type, location, body
+            FROM idempotency_keys
+            WHERE scope = ? AND idempotency_key = ?
+            "#,
+            scope,
+            key
+        )
+        .fetch_optional(&mut *tx)
+        .observe("fetch_idempotency_key")
+        .await
+        .map_err(query_error("Failed to fetch idempotency key"))?;
+
+        let claim = match existing {
+            Some(row) if row.fingerprint != fingerprint => IdempotencyClaim::Mismatch,
+            Some(row) => match row.status {
+                Some(status) => IdempotencyClaim::Completed(StoredResponse {
+                    status: status as u16,
+                    content_type: row.content_type,
+                    location: row.location,
+                    body: row.body.unwrap_or_default(),
+                }),
+                None => IdempotencyClaim::InProgress,
+            },
+            None => {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO idempotency_keys (scope, idempotency_key, fingerprint, created_at)
+                    VALUES (?, ?, ?, ?)
+                    "#,
+                    scope,
+                    key,
+                    fingerprint,
+                    now
+                )
+                .execute(&mut *tx)
+                .observe("claim_idempotency_key")
+                .await
+                .map_err(query_error("Failed to claim idempotency key"))?;
+                IdempotencyClaim::Claimed
+            }
+        };
+        Self::commit(tx).await?;
+        Ok(claim)
+    }
+
+    pub async fn complete_idempotency_key(
+        &self,
+        scope: &str,
+        key: &str,
+        response: &StoredResponse,
+    ) -> Result<()> {
+        let status = i64::from(response.status);
+        sqlx::query!(
+            r#"
+            UPDATE idempotency_keys
+            SET status = ?, content_type = ?, location = ?, body = ?
+            WHERE scope = ? AND idempotency_key = ?
+            "#,
+            status,
+            response.content_type,
+            response.location,
+            response.body,
+            scope,
+            key
+        )
+        .execute(&self.pool)
+        .observe("complete_idempotency_key")
+        .await
+        .map_err(query_error("Failed to store idempotent response"))?;
+        Ok(())
+    }
+
+    pub async fn release_idempotency_key(&self, scope: &str, key: &str) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM idempotency_keys WHERE scope = ? AND idempotency_key = ? AND status IS NULL",
+            scope,
+            key
+        )
+        .execute(&self.pool)
+        .observe("release_idempotency_key")
+        .await
+        .map_err(query_error("Failed to release idempotency key"))?;
+        Ok(())
+    }
+
+    /// `secret` as stored: encrypted when a cipher is configured.
+    fn seal_secret(&self, secret: &str) -> Result<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(secret),
+            None => Ok(secret.to_string()),
+        }
+    }
+
+    pub async fn create_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        active: bool,
+    ) -> Result<Webhook> {
+        let secret = self.seal_secret(secret)?;
+        let events = join_events(events);
+        let now = Utc::now().timestamp();
+        let row = sqlx::query_as!(
+            WebhookRow,
+            r#"INSERT INTO webhooks (url, secret, events, active, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             RETURNING id as "id!", url, events, active as "active: bool", created_at, updated_at"#,
+            url,
+            secret,
+            events,
+            active,
+            now,
+            now
+        )
+        .fetch_one(&self.pool)
+        .observe_one("create_webhook")
+        .await
+        .map_err(query_error("Failed to create webhook"))?;
+        Ok(row.into_webhook())
+    }
+
+    pub async fn list_webhooks(&self) -> Result<Vec<Webhook>> {
+        let rows = sqlx::query_as!(
+            WebhookRow,
+            r#"SELECT id, url, events, active as "active: bool", created_at, updated_at
+             FROM webhooks ORDER BY id"#
+        )
+        .fetch_all(&self.pool)
+        .observe("list_webhooks")
+        .await
+        .map_err(query_error("Failed to list webhooks"))?;
+        Ok(rows.into_iter().map(WebhookRow::into_webhook).collect())
+    }
+
+    pub async fn get_webhook(&self, id: i64) -> Result<Webhook> {
+        sqlx::query_as!(
+            WebhookRow,
+            r#"SELECT id, url, events, active as "active: bool", created_at, updated_at
+             FROM webhooks WHERE id = ?"#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .observe("get_webhook")
+        .await
+        .map_err(query_error("Failed to look up webhook"))?
+        .map(WebhookRow::into_webhook)
+        .ok_or(AppError::WebhookNotFound(id))
+    }
+
+    /// Applies the fields set in `changes` to webhook `id`.
+    pub async fn update_webhook(&self, id: i64, changes: &UpdateWebhookRequest) -> Result<Webhook> {
+        let secret = changes
+            .secret
+            .as_deref()
+            .map(|secret| self.seal_secret(secret))
+            .transpose()?;
+        let events = changes.events.as_deref().map(join_events);
+        let now = Utc::now().timestamp();
+        sqlx::query_as!(
+            WebhookRow,
+            r#"UPDATE webhooks SET
+                url = COALESCE(?, url),
+                secret = COALESCE(?, secret),
+                events = COALESCE(?, events),
+                active = COALESCE(?, active),
+                updated_at = ?
+             WHERE id = ?
+             RETURNING id as "id!", url, events, active as "active: bool", created_at, updated_at"#,
+            changes.url,
+            secret,
+            events,
+            changes.active,
+            now,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .observe("update_webhook")
+        .await
+        .map_err(query_error("Failed to update webhook"))?
+        .map(WebhookRow::into_webhook)
+        .ok_or(AppError::WebhookNotFound(id))
+    }
+
+    /// Queues `event` for every active webhook subscribed to it; returns how
+    /// many deliveries were queued.
+    pub async fn enqueue_webhook_deliveries(&self, event: &Event, now: i64) -> Result<u64> {
+        let mut conn = self.acquire().await?;
+        Self::queue_webhook_deliveries(&mut conn, event, now).await
+    }
+
+    /// Queues `event` on `conn`. Writes announcing an event call this in the
+    /// transaction that records it, so webhooks hear of exactly the changes
+    /// that were committed, even if the process stops before publishing the
+    /// event or a subscriber falls behind the bus.
+    async fn queue_webhook_deliveries(
+        conn: &mut SqliteConnection,
+        event: &Event,
+        now: i64,
+    ) -> Result<u64> {
+        let Some(webhook_event) = webhooks::webhook_event(event) else {
+            return Ok(0);
+        };
+        let payload = serde_json::to_string(event)
+            .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to encode event: {}", e)))?;
+        let event = webhook_event.as_str();
+        let result = sqlx::query!(
+            r#"INSERT INTO webhook_deliveries (webhook_id, event, payload, next_attempt_at, created_at)
+             SELECT id, ?1, ?2, ?3, ?3 FROM webhooks
+             WHERE active = 1 AND instr(',' || events || ',', ',' || ?1 || ',') > 0"#,
+            event,
+            payload,
+            now
+        )
+        .execute(&mut *conn)
+        .observe("enqueue_webhook_deliveries")
+        .await
+        .map_err(query_error("Failed to queue webhook deliveries"))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Claims up to `limit` pending deliveries to active webhooks whose next
+    /// attempt is due at `now`, oldest first, with where to send them. Claimed
+    /// deliveries are leased until `lease_until`: no other caller gets them
+    /// before then, and they come due again if the attempt is never recorded.
+    pub async fn claim_webhook_deliveries(
+        &self,
+        now: i64,
+        lease_until: i64,
+        limit: u32,
+    ) -> Result<Vec<DueDelivery>> {
+        let limit = limit as i64;
+        // The outer due check makes the claim atomic: a row another caller
+        // leased in the meantime no longer matches
+        let rows = sqlx::query!(
+            r#"UPDATE webhook_deliveries SET next_attempt_at = ?1
+             WHERE id IN (
+                 SELECT d.id FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+                 WHERE d.status = 'pending' AND d.next_attempt_at <= ?2 AND w.active = 1
+                 ORDER BY d.next_attempt_at, d.id
+                 LIMIT ?3
+             ) AND status = 'pending' AND next_attempt_at <= ?2
+             RETURNING id as "id!", webhook_id, event, payload, attempts,
+                 (SELECT url FROM webhooks WHERE webhooks.id = webhook_id) as "url!: String",
+                 (SELECT secret FROM webhooks WHERE webhooks.id = webhook_id) as "secret!: String""#,
+            lease_until,
+            now,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .observe("claim_webhook_deliveries")
+        .await
+        .map_err(query_error("Failed to claim due webhook deliveries"))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(DueDelivery {
+                    id: row.id,
+                    webhook_id: row.webhook_id,
+                    event: row.event,
+                    payload: row.payload,
+                    attempts: row.attempts,
+                    url: row.url,
+                    secret: self.open_secret(&row.secret)?,
+                })
+            })
+            .collect()
+    }
+
+    /// `value` of an encrypted column as plaintext.
+    fn open_secret(&self, value: &str) -> Result<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(value),
+            None if ColumnCipher::is_encrypted(value) => Err(AppError::Encryption(
+                "secret is encrypted but ENCRYPTION_KEYS is not set".to_string(),
+            )),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    /// Records an attempt at delivery `id`: `status` is what it leaves the
+    /// delivery in, `next_attempt_at` when a pending one is tried again.
+    pub async fn record_webhook_attempt(
+        &self,
+        id: i64,
+        status: DeliveryStatus,
+        status_code: Option<u16>,
+        error: Option<&str>,
+        next_attempt_at: i64,
+        now: i64,
+    ) -> Result<()> {
+        let finished_at = (status != DeliveryStatus::Pending).then_some(now);
+        let status = status.as_str();
+        sqlx::query!(
+            r#"UPDATE webhook_deliveries SET
+                status = ?, attempts = attempts + 1, next_attempt_at = ?,
+                last_status_code = ?, last_error = ?, finished_at = ?
+             WHERE id = ?"#,
+            status,
+            next_attempt_at,
+            status_code,
+            error,
+            finished_at,
+            id
+        )
+        .execute(&self.pool)
+        .observe("record_webhook_attempt")
+        .await
+        .map_err(query_error("Failed to record webhook attempt"))?;
+        Ok(())
+    }
+
+    /// Forgets deliveries that finished before `before`; returns how many.
+    pub async fn purge_webhook_deliveries(&self, before: i64) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM webhook_deliveries WHERE status != 'pending' AND finished_at < ?",
+            before
+        )
+        .execute(&self.pool)
+        .observe("purge_webhook_deliveries")
+        .await
+        .map_err(query_error("Failed to purge webhook deliveries"))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Webhook `webhook_id`'s deliveries, newest first.
+    pub async fn list_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        status: Option<DeliveryStatus>,
+        limit: u32,
+        cursor: Option<i64>,
+    ) -> Result<WebhookDeliveriesResponse> {
+        // Tells a missing webhook apart from one without deliveries
+        self.get_webhook(webhook_id).await?;
+        let limit = limit.min(100) as i64;
+        let fetch_limit = limit + 1;
+        let status = status.map(DeliveryStatus::as_str);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id as "id!", webhook_id, event, payload, status, attempts, next_attempt_at,
+                   last_status_code, last_error, created_at, finished_at
+            FROM webhook_deliveries
+            WHERE webhook_id = ?1
+              AND (?2 IS NULL OR id < ?2)
+              AND (?3 IS NULL OR status = ?3)
+            ORDER BY id DESC
+            LIMIT ?4
+            "#,
+            webhook_id,
+            cursor,
+            status,
+            fetch_limit
+        )
+        .fetch_all(&self.pool)
+        .observe("list_webhook_deliveries")
+        .await
+        .map_err(query_error("Failed to fetch webhook deliveries"))?;
+
+        let mut deliveries = rows
+            .into_iter()
+            .map(|row| {
+                Ok(WebhookDelivery {
+                    id: row.id,
+                    webhook_id: row.webhook_id,
+                    event: WebhookEvent::parse(&row.event).ok_or_else(|| {
+                        AppError::Store(anyhow::anyhow!("Unknown webhook event: {}", row.event))
+                    })?,
+                    payload: serde_json::from_str(&row.payload).map_err(|e| {
+                        AppError::Store(anyhow::anyhow!("Invalid webhook payload: {}", e))
+                    })?,
+                    status: row.status.parse()?,
+                    attempts: row.attempts,
+                    next_attempt_at: row.next_attempt_at,
+                    last_status_code: row.last_status_code,
+                    last_error: row.last_error,
+                    created_at: row.created_at,
+                    finished_at: row.finished_at,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let has_next = deliveries.len() as i64 > limit;
+        deliveries.truncate(limit as usize);
+        let next_cursor = if has_next {
+            deliveries.last().map(|delivery| delivery.id)
+        } else {
+            None
+        };
+
+        Ok(WebhookDeliveriesResponse {
+            deliveries,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    /// Deletes webhook `id`, returning it as it was.
+    pub async fn delete_webhook(&self, id: i64) -> Result<Webhook> {
+        sqlx::query_as!(
+            WebhookRow,
+            r#"DELETE FROM webhooks WHERE id = ?
+             RETURNING id as "id!", url, events, active as "active: bool", created_at, updated_at"#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .observe("delete_webhook")
+        .await
+        .map_err(query_error("Failed to delete webhook"))?
+        .map(WebhookRow::into_webhook)
+        .ok_or(AppError::WebhookNotFound(id))
+    }
+
+    pub async fn list_audit_log(
+        &self,
+        actor: Option<&str>,
+        action: Option<&str>,
+        limit: u32,
+        cursor: Option<i64>,
+    ) -> Result<AuditLogResponse> {
+        let limit = limit.min(100) as i64;
+        let fetch_limit = limit + 1;
+
+        let mut entries = sqlx::query_as!(
+            AuditEntry,
+            r#"
+            SELECT id as "id!", actor, action, target, created_at
+            FROM audit_log
+            WHERE (?1 IS NULL OR id < ?1)
+              AND (?2 IS NULL OR actor = ?2)
+              AND (?3 IS NULL OR action = ?3)
+            ORDER BY id DESC
+            LIMIT ?4
+            "#,
+            cursor,
+            actor,
+            action,
+            fetch_limit
+        )
+        .fetch_all(&self.pool)
+        .observe("list_audit_log")
+        .await
+        .map_err(query_error("Failed to fetch audit log"))?;
+
+        let has_next = entries.len() as i64 > limit;
+        entries.truncate(limit as usize);
+        let next_cursor = if has_next {
+            entries.last().map(|entry| entry.id)
+        } else {
+            None
+        };
+
+        Ok(AuditLogResponse {
+            entries,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    /// Registers a proof as available for `height` and records how long it
+    /// took to appear after the block was ingested.
+    pub async fn record_proof(
+        &self,
+        height: u32,
+        file_path: &str,
+        file_size: i64,
+        checksum: Option<&str>,
+        generated_at: i64,
+        execution_time_ms: Option<i64>,
+    ) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        let proved = Self::write_proof(
+            &mut conn,
+            height,
+            file_path,
+            file_size,
+            checksum,
+            generated_at,
+            execution_time_ms,
+        )
+        .await?;
+        self.block_cache.clear();
+
+        ProofSlaConfig::global().observe(proved);
+        events::publish(proved.into());
+        Ok(())
+    }
+
+    async fn acquire(&self) -> Result<PoolConnection<Sqlite>> {
+        self.pool
+            .acquire()
+            .await
+            .map_err(query_error("Failed to acquire connection"))
+    }
+
+    /// Stores the proof record and returns how long after ingestion the proof
+    /// was generated and became available.
+    async fn write_proof(
+        conn: &mut SqliteConnection,
+        height: u32,
+        file_path: &str,
+        file_size: i64,
+        checksum: Option<&str>,
+        generated_at: i64,
+        execution_time_ms: Option<i64>,
+    ) -> Result<ProofMilestone> {
+        let height_i64 = height as i64;
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO proof_files 
+            (block_height, file_path, file_size, checksum, proof_version, generated_at, execution_time_ms)
+            VALUES (?, ?, ?, ?, 'v1.0', ?, ?)
+            "#,
+            height_i64,
+            file_path,
+            file_size,
+            checksum,
+            generated_at,
+            execution_time_ms
+        )
+        .execute(&mut *conn)
+        .observe("insert_proof_file")
+        .await
+        .map_err(query_error("Failed to insert proof file"))?;
+
+        sqlx::query!(
+            "DELETE FROM proof_status WHERE block_height = ?",
+            height_i64
+        )
+        .execute(&mut *conn)
+        .observe("clear_proof_status")
+        .await
+        .map_err(query_error("Failed to clear proof status"))?;
+
+        let latency = sqlx::query!(
+            r#"
+            SELECT CAST(strftime('%s', pf.created_at) AS INTEGER)
+                   - CAST(strftime('%s', b.created_at) AS INTEGER) as "available!: i64",
+                   pf.generated_at - CAST(strftime('%s', b.created_at) AS INTEGER) as "generated!: i64"
+            FROM proof_files pf
+            JOIN blocks b ON b.height = pf.block_height
+            WHERE pf.block_height = ?
+            "#,
+            height_i64
+        )
+        .fetch_one(&mut *conn)
+        .observe_one("proof_latency")
+        .await
+        .map_err(query_error("Failed to compute proof latency"))?;
+
+        Ok(ProofMilestone::Proved {
+            height,
+            generated_secs: latency.generated,
+            available_secs: latency.available,
+        })
+    }
+
+    /// Stores a block's transactions, inputs, outputs and index entries with
+    /// one multi-row insert per table rather than one statement per row.
+    async fn write_transactions(
+        &self,
+        conn: &mut SqliteConnection,
+        height: i64,
+        txids: &[&str],
+        details: &[serde_json::Value],
+    ) -> Result<()> {
+        let details: HashMap<&str, &serde_json::Value> = details
+            .iter()
+            .filter_map(|tx| Some((tx["txid"].as_str()?, tx)))
+            .collect();
+
+        let transactions = txids
+            .iter()
+            .enumerate()
+            .map(|(position, &txid)| {
+                let tx = details.get(txid);
+                let raw = tx
+                    .and_then(|tx| tx["raw"].as_str())
+                    .map(hex::decode)
+                    .transpose()
+                    .map_err(|e| {
+                        AppError::Store(anyhow::anyhow!("Invalid raw transaction hex: {}", e))
+                    })?;
+                Ok(TransactionRow {
+                    txid,
+                    wtxid: raw
+                        .as_deref()
+                        .map(|raw| Wtxid::from_internal_bytes(sha256d(raw))),
+                    position: position as i64,
+                    fee: tx.and_then(|tx| tx["fee"].as_f64()),
+                    size: tx.and_then(|tx| tx["size"].as_i64()),
+                    vsize: tx.and_then(|tx| tx["vsize"].as_i64()),
+                    raw,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        bulk_insert(
+            conn,
+            "INSERT OR REPLACE INTO transactions (txid, wtxid, block_height, position_in_block, fee, size, vsize, raw) ",
+            &transactions,
+            |mut row, tx| {
+                row.push_bind(tx.txid)
+                    .push_bind(tx.wtxid)
+                    .push_bind(height)
+                    .push_bind(tx.position)
+                    .push_bind(tx.fee)
+                    .push_bind(tx.size)
+                    .push_bind(tx.vsize)
+                    .push_bind(tx.raw.as_deref());
+            },
+        )
+        .observe("insert_transactions")
+        .await
+        .map_err(query_error("Failed to insert transactions"))?;
+
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for (txid, tx_data) in txids
+            .iter()
+            .filter_map(|txid| Some((*txid, details.get(txid)?)))
+        {
+            for (index, input) in tx_data["inputs"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                inputs.push((
+                    txid,
+                    index as i64,
+                    input["prev_txid"].as_str(),
+                    input["prev_vout"].as_i64(),
+                ));
+            }
+            for (index, output) in tx_data["outputs"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                outputs.push((
+                    txid,
+                    index as i64,
+                    output["value"].as_f64().unwrap(),
+                    output["script_pubkey"].as_str().unwrap(),
+                ));
+            }
+        }
+
+        bulk_insert(
+            conn,
+            "INSERT OR REPLACE INTO transaction_outputs (txid, output_index, value, script_pubkey) ",
+            &outputs,
+            |mut row, (txid, index, value, script_pubkey)| {
+                row.push_bind(*txid)
+                    .push_bind(*index)
+                    .push_bind(*value)
+                    .push_bind(*script_pubkey);
+            },
+        )
+        .observe("insert_transaction_outputs")
+        .await
+        .map_err(query_error("Failed to insert transaction outputs"))?;
+
+        bulk_insert(
+            conn,
+            "INSERT OR REPLACE INTO transaction_inputs (txid, input_index, prev_txid, prev_vout) ",
+            &inputs,
+            |mut row, (txid, index, prev_txid, prev_vout)| {
+                row.push_bind(*txid)
+                    .push_bind(*index)
+                    .push_bind(*prev_txid)
+                    .push_bind(*prev_vout);
+            },
+        )
+        .observe("insert_transaction_inputs")
+        .await
+        .map_err(query_error("Failed to insert transaction inputs"))?;
+
+        let spends: Vec<_> = inputs
+            .iter()
+            .filter_map(|(txid, index, prev_txid, prev_vout)| {
+                Some((*txid, *index, (*prev_txid)?, (*prev_vout)?))
+            })
+            .collect();
+        bulk_insert(
+            conn,
+            "INSERT OR REPLACE INTO spent_outputs (txid, vout, spending_txid, spending_input, block_height) ",
+            &spends,
+            |mut row, (txid, index, prev_txid, prev_vout)| {
+                row.push_bind(*prev_txid)
+                    .push_bind(*prev_vout)
+                    .push_bind(*txid)
+                    .push_bind(*index)
+                    .push_bind(height);
+            },
+        )
+        .observe("mark_spent_outputs")
+        .await
+        .map_err(query_error("Failed to record spent outputs"))?;
+
+        if self.address_index {
+            bulk_insert(
+                conn,
+                "INSERT OR IGNORE INTO address_index (script_pubkey, txid, block_height) ",
+                &outputs,
+                |mut row, (txid, _, _, script_pubkey)| {
+                    row.push_bind(*script_pubkey)
+                        .push_bind(*txid)
+                        .push_bind(height);
+                },
+            )
+            .observe("index_addresses")
+            .await
+            .map_err(query_error("Failed to index address"))?;
+
+            // Spending a coin also touches the address that owned it
+            sqlx::query!(
+                r#"
+                INSERT OR IGNORE INTO address_index (script_pubkey, txid, block_height)
+                SELECT o.script_pubkey, i.txid, t.block_height
+                FROM transaction_inputs i
+                JOIN transactions t ON t.txid = i.txid
+                JOIN transaction_outputs o ON o.txid = i.prev_txid AND o.output_index = i.prev_vout
+                WHERE t.block_height = ?
+                "#,
+                height
+            )
+            .execute(&mut *conn)
+            .observe("index_spent_addresses")
+            .await
+            .map_err(query_error("Failed to index spent addresses"))?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_block_stats(
+        conn: &mut SqliteConnection,
+        block_stats: &BlockStats,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO block_stats
+            (block_height, size, weight, avg_fee_rate, median_fee_rate, input_count, output_count, subsidy)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            block_stats.height,
+            block_stats.size,
+            block_stats.weight,
+            block_stats.avg_fee_rate,
+            block_stats.median_fee_rate,
+            block_stats.input_count,
+            block_stats.output_count,
+            block_stats.subsidy
+        )
+        .execute(&mut *conn)
+        .observe("insert_block_stats")
+        .await
+        .map_err(query_error("Failed to insert block stats"))?;
+
+        Ok(())
+    }
+
+    pub async fn get_blocks(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        direction: PageDirection,
+        order: SortOrder,
+        filter: &BlockFilter,
+    ) -> Result<BlocksResponse> {
+        let limit = limit.min(50);
+        let cursor_i64 = cursor.map(|c| c as i64);
+        let fetch = limit as i64 + 1;
+
+        // The cursor is exclusive in the direction of travel: newest-first
+        // pages continue below it, oldest-first pages above it
+        let blocks = match direction.travel(order) {
+            SortOrder::Desc => {
+                sqlx::query_as!(
+                    BlockSummary,
+                    r#"
+                    SELECT height as "height: u32", hash as "hash: BlockHash", tx_count as "tx_count: u32",
+                           total_fees, timestamp, verified as "verified: bool"
+                    FROM blocks 
+                    WHERE (?1 IS NULL OR height < ?1)
+                      AND (?2 IS NULL OR verified = ?2)
+                      AND (?3 IS NULL OR timestamp >= ?3)
+                      AND (?4 IS NULL OR timestamp <= ?4)
+                    ORDER BY height DESC 
+                    LIMIT ?5
+                    "#,
+                    cursor_i64,
+                    filter.verified,
+                    filter.from_timestamp,
+                    filter.to_timestamp,
+                    fetch
+                )
+                .fetch_all(&self.pool)
+                .observe("list_blocks")
+                .await
+            }
+            SortOrder::Asc => {
+                sqlx::query_as!(
+                    BlockSummary,
+                    r#"
+                    SELECT height as "height: u32", hash as "hash: BlockHash", tx_count as "tx_count: u32",
+                           total_fees, timestamp, verified as "verified: bool"
+                    FROM blocks 
+                    WHERE (?1 IS NULL OR height > ?1)
+                      AND (?2 IS NULL OR verified = ?2)
+                      AND (?3 IS NULL OR timestamp >= ?3)
+                      AND (?4 IS NULL OR timestamp <= ?4)
+                    ORDER BY height ASC 
+                    LIMIT ?5
+                    "#,
+                    cursor_i64,
+                    filter.verified,
+                    filter.from_timestamp,
+                    filter.to_timestamp,
+                    fetch
+                )
+                .fetch_all(&self.pool)
+                .observe("list_blocks")
+                .await
+            }
+        }
+        .map_err(query_error("Failed to fetch blocks"))?;
+
+        let total = self.count_blocks(filter).await?;
+
+        Ok(BlocksResponse::page(
+            blocks,
+            limit as usize,
+            cursor,
+            direction,
+            total as u32,
+        ))
+    }
+
+    /// Blocks matching `filter`. Without a time range the count comes from
+    /// the trigger-maintained `block_totals` row; a time range is counted
+    /// through the timestamp index.
+    pub async fn count_blocks(&self, filter: &BlockFilter) -> Result<i64> {
+        if filter.from_timestamp.is_some() || filter.to_timestamp.is_some() {
+            return sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) as "count!: i64" FROM blocks
+                WHERE (?1 IS NULL OR verified = ?1)
+                  AND (?2 IS NULL OR timestamp >= ?2)
+                  AND (?3 IS NULL OR timestamp <= ?3)
+                "#,
+                filter.verified,
+                filter.from_timestamp,
+                filter.to_timestamp
+            )
+            .fetch_one(&self.pool)
+            .observe_one("count_blocks")
+            .await
+            .map_err(query_error("Failed to count blocks"));
+        }
+
+        let totals = sqlx::query!("SELECT block_count, verified_count FROM block_totals")
+            .fetch_one(&self.pool)
+            .observe_one("block_totals")
+            .await
+            .map_err(query_error("Failed to read block totals"))?;
+        Ok(match filter.verified {
+            None => totals.block_count,
+            Some(true) => totals.verified_count,
+            Some(false) => totals.block_count - totals.verified_count,
+        })
+    }
+
+    pub async fn get_block_by_height(&self, height: u32) -> Result<BlockDetail> {
+        Self::fetch_block_by_height(&mut *self.acquire().await?, height).await
+    }
+
+    async fn fetch_block_by_height(
+        conn: &mut SqliteConnection,
+        height: u32,
+    ) -> Result<BlockDetail> {
+        let height_i64 = height as i64;
+        let block_row = sqlx::query!(
+            r#"
+            SELECT height, hash as "hash: BlockHash", prev_hash as "prev_hash: BlockHash",
+                   merkle_root, version, bits, nonce, tx_count, total_fees, timestamp, verified,
+                   size as "size: u32", stripped_size as "stripped_size: u32", weight as "weight: u32",
+                   difficulty, chainwork, witness_merkle_root, witness_commitment,
+                   coinbase_txid as "coinbase_txid: Txid", subsidy, claimed_fees, coinbase_script, miner_tag
+            FROM blocks 
+            WHERE height = ?
+            "#,
+            height_i64
+        )
+        .fetch_optional(&mut *conn)
+        .observe("block_by_height")
+        .await
+        .map_err(query_error("Failed to fetch block"))?
+        .ok_or_else(|| AppError::BlockNotFound(height.to_string()))?;
+
+        let transactions = sqlx::query!(
+            r#"
+            SELECT txid as "txid: Txid", wtxid as "wtxid: Wtxid"
+            FROM transactions WHERE block_height = ? ORDER BY position_in_block
+            "#,
+            height_i64
+        )
+        .fetch_all(&mut *conn)
+        .observe("block_transactions")
+        .await
+        .map_err(query_error("Failed to fetch transactions"))?;
+        let txids: Vec<Txid> = transactions.iter().filter_map(|tx| tx.txid).collect();
+        // Only a complete list is meaningful
+        let wtxids: Vec<Wtxid> = transactions
+            .iter()
+            .map(|tx| tx.wtxid)
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+
+        Ok(BlockDetail {
+            summary: BlockSummary {
+                height: block_row.height as u32,
+                hash: block_row.hash,
+                tx_count: block_row.tx_count as u32,
+                total_fees: block_row.total_fees,
+                timestamp: block_row.timestamp,
+                verified: block_row.verified,
+            },
+            prev_hash: block_row.prev_hash,
+            merkle_root: block_row.merkle_root,
+            witness_merkle_root: block_row.witness_merkle_root,
+            witness_commitment: block_row.witness_commitment,
+            version: block_row.version as i32,
+            bits: block_row.bits as u32,
+            nonce: block_row.nonce as u32,
+            size: block_row.size,
+            stripped_size: block_row.stripped_size,
+            weight: block_row.weight,
+            difficulty: block_row
+                .difficulty
+                .unwrap_or_else(|| chain::difficulty(block_row.bits as u32)),
+            chainwork: block_row.chainwork,
+            proof_url: format!("/v1/blocks/{height}/proof"),
+            txids,
+            wtxids,
+            coinbase: block_row.coinbase_txid.map(|txid| CoinbaseInfo {
+                txid,
+                subsidy: block_row
+                    .subsidy
+                    .unwrap_or_else(|| stats::block_subsidy(height)),
+                claimed_fees: block_row.claimed_fees,
+                fees_match: block_row
+                    .claimed_fees
+                    .map(|claimed| claimed == block_row.total_fees),
+                script_sig: block_row.coinbase_script,
+                miner_tag: block_row.miner_tag,
+            }),
+            pruned: height < Self::fetch_pruned_below(&mut *conn).await?,
+            orphaned: false,
+        })
+    }
+
+    pub async fn get_block_stats(&self, height: u32) -> Result<BlockStats> {
+        let height_i64 = height as i64;
+        let block_stats = sqlx::query_as!(
+            BlockStats,
+            r#"
+            SELECT block_height as "height: u32", size as "size: u32", weight as "weight: u32",
+                   avg_fee_rate, median_fee_rate, input_count as "input_count: u32",
+                   output_count as "output_count: u32", subsidy
+            FROM block_stats
+            WHERE block_height = ?
+            "#,
+            height_i64
+        )
+        .fetch_optional(&self.pool)
+        .observe("block_stats")
+        .await
+        .map_err(query_error("Failed to fetch block stats"))?;
+
+        match block_stats {
+            Some(block_stats) => Ok(block_stats),
+            None if self
+                .block_exists_by_identifier(&BlockId::Height(height))
+                .await? =>
+            {
+                Err(AppError::BlockStatsUnavailable(height.to_string()))
+            }
+            None => Err(AppError::BlockNotFound(height.to_string())),
+        }
+    }
+
+    pub async fn get_block_by_hash(&self, hash: &BlockHash) -> Result<BlockDetail> {
+        let height = sqlx::query_scalar!(
+            "SELECT block_height FROM block_headers WHERE hash = ?",
+            hash
+        )
+        .fetch_optional(&self.pool)
+        .observe("block_by_hash")
+        .await
+        .map_err(query_error("Failed to fetch block by hash"))?;
+
+        match height {
+            Some(height) => self.get_block_by_height(height as u32).await,
+            // A block displaced by a reorg is served from its tombstone, marked
+            // so it is not taken for the canonical one
+            None => self
+                .get_orphaned_block(hash)
+                .await?
+                .ok_or_else(|| AppError::BlockNotFound(hash.to_string())),
+        }
+    }
+
+    /// Block `hash` as it was when it was last orphaned.
+    async fn get_orphaned_block(&self, hash: &BlockHash) -> Result<Option<BlockDetail>> {
+        let block_data = sqlx::query_scalar!(
+            "SELECT block_data FROM orphaned_blocks WHERE hash = ?",
+            hash
+        )
+        .fetch_optional(&self.pool)
+        .observe("orphaned_block")
+        .await
+        .map_err(query_error("Failed to fetch orphaned block"))?;
+        let Some(block_data) = block_data else {
+            return Ok(None);
+        };
+        let mut block: BlockDetail = serde_json::from_str(&block_data)
+            .map_err(|e| AppError::Store(anyhow::anyhow!("Malformed orphan snapshot: {}", e)))?;
+        block.orphaned = true;
+        Ok(Some(block))
+    }
+
+    /// Pages through a block's txids in block order, continuing after the
+    /// `cursor` position.
+    pub async fn get_block_txids(
+        &self,
+        height: u32,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<BlockTxidsResponse> {
+        if !self
+            .block_exists_by_identifier(&BlockId::Height(height))
+            .await?
+        {
+            return Err(AppError::BlockNotFound(height.to_string()));
+        }
+
+        let height_i64 = height as i64;
+        let limit = limit.min(1000) as i64;
+        let fetch_limit = limit + 1;
+
+        let total = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM transactions WHERE block_height = ?",
+            height_i64
+        )
+        .fetch_one(&self.pool)
+        .observe_one("count_block_txids")
+        .await
+        .map_err(query_error("Failed to count transactions"))?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT txid as "txid!: Txid", position_in_block as "position: u32"
+            FROM transactions
+            WHERE block_height = ?1 AND (?2 IS NULL OR position_in_block > ?2)
+            ORDER BY position_in_block
+            LIMIT ?3
+            "#,
+            height_i64,
+            cursor,
+            fetch_limit
+        )
+        .fetch_all(&self.pool)
+        .observe("block_txids")
+        .await
+        .map_err(query_error("Failed to fetch transactions"))?;
+
+        let has_next = rows.len() as i64 > limit;
+        let next_cursor = if has_next {
+            rows.get(limit as usize - 1).map(|row| row.position)
+        } else {
+            None
+        };
+
+        Ok(BlockTxidsResponse {
+            height,
+            txids: rows
+                .into_iter()
+                .take(limit as usize)
+                .map(|row| row.txid)
+                .collect(),
+            total: total as u32,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    pub async fn get_transaction_status(&self, txid: &Txid) -> Result<TransactionStatus> {
+        let result = sqlx::query!(
+            r#"
+            SELECT t.block_height as "block_height: u32", b.hash as "hash: BlockHash", b.verified,
+                   (SELECT MAX(height) FROM blocks) - t.block_height + 1 as "confirmations!: u32",
+                   pf.block_height IS NOT NULL as "has_proof!: bool"
+            FROM transactions t
+            JOIN blocks b ON b.height = t.block_height
+            LEFT JOIN proof_files pf ON pf.block_height = t.block_height
+            WHERE t.txid = ?
+            "#,
+            txid
+        )
+        .fetch_optional(&self.pool)
+        .observe("transaction_status")
+        .await
+        .map_err(query_error("Failed to fetch transaction"))?;
+
+        Ok(match result {
+            Some(row) => TransactionStatus {
                 included: true,
-                block_height: Some(row.block_height as u32),
+                block_height: Some(row.block_height),
+                block_hash: Some(row.hash),
+                confirmations: Some(row.confirmations),
+                proof_url: row
+                    .has_proof
+                    .then(|| format!("/v1/blocks/{}/proof", row.block_height)),
+                proof_verified: row.has_proof.then_some(row.verified),
             },
             None => TransactionStatus {
                 included: false,
                 block_height: None,
+                block_hash: None,
+                confirmations: None,
+                proof_url: None,
+                proof_verified: None,
             },
         })
     }
 
-    pub async fn get_header_status(&self, hash: &str) -> Result<HeaderStatus> {
-        let result = sqlx::query!(
-            "SELECT block_height FROM block_headers WHERE hash = ?",
-            hash
+    pub async fn get_transaction_detail(&self, txid: &Txid) -> Result<TransactionDetail> {
+        let tx_row = sqlx::query!(
+            r#"
+            SELECT block_height, position_in_block, fee, size, vsize
+            FROM transactions
+            WHERE txid = ?
+            "#,
+            txid
+        )
+        .fetch_optional(&self.pool)
+        .observe("transaction_detail")
+        .await
+        .map_err(query_error("Failed to fetch transaction"))?
+        .ok_or_else(|| AppError::TransactionNotFound(txid.to_string()))?;
+
+        let inputs = sqlx::query_as!(
+            TransactionInput,
+            r#"
+            SELECT prev_txid as "prev_txid: Txid", prev_vout as "prev_vout: u32"
+            FROM transaction_inputs
+            WHERE txid = ?
+            ORDER BY input_index
+            "#,
+            txid
+        )
+        .fetch_all(&self.pool)
+        .observe("transaction_inputs")
+        .await
+        .map_err(query_error("Failed to fetch transaction inputs"))?;
+
+        let outputs = sqlx::query_as!(
+            TransactionOutput,
+            r#"
+            SELECT value, script_pubkey
+            FROM transaction_outputs
+            WHERE txid = ?
+            ORDER BY output_index
+            "#,
+            txid
+        )
+        .fetch_all(&self.pool)
+        .observe("transaction_outputs")
+        .await
+        .map_err(query_error("Failed to fetch transaction outputs"))?;
+
+        Ok(TransactionDetail {
+            txid: *txid,
+            block_height: tx_row.block_height as u32,
+            position_in_block: tx_row.position_in_block as u32,
+            fee: tx_row.fee,
+            size: tx_row.size.map(|s| s as u32),
+            vsize: tx_row.vsize.map(|s| s as u32),
+            inputs,
+            outputs,
+        })
+    }
+
+    pub async fn get_raw_transaction(&self, txid: &Txid) -> Result<Vec<u8>> {
+        sqlx::query_scalar!("SELECT raw FROM transactions WHERE txid = ?", txid)
+            .fetch_optional(&self.pool)
+            .observe("raw_transaction")
+            .await
+            .map_err(query_error("Failed to fetch raw transaction"))?
+            .ok_or_else(|| AppError::TransactionNotFound(txid.to_string()))?
+            .ok_or_else(|| AppError::RawTransactionUnavailable(txid.to_string()))
+    }
+
+    pub async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<OutputStatus> {
+        let vout_i64 = vout as i64;
+        let output = sqlx::query!(
+            r#"
+            SELECT o.value, o.script_pubkey, t.block_height
+            FROM transaction_outputs o
+            JOIN transactions t ON t.txid = o.txid
+            WHERE o.txid = ? AND o.output_index = ?
+            "#,
+            txid,
+            vout_i64
+        )
+        .fetch_optional(&self.pool)
+        .observe("output_status")
+        .await
+        .map_err(query_error("Failed to fetch output"))?
+        .ok_or_else(|| AppError::OutputNotFound(format!("{txid}:{vout}")))?;
+
+        let spent_by = sqlx::query_as!(
+            OutputSpend,
+            r#"
+            SELECT spending_txid as "txid: Txid", spending_input as "input: u32",
+                   block_height as "block_height: u32"
+            FROM spent_outputs
+            WHERE txid = ? AND vout = ?
+            "#,
+            txid,
+            vout_i64
+        )
+        .fetch_optional(&self.pool)
+        .observe("spending_input")
+        .await
+        .map_err(query_error("Failed to fetch spent output"))?;
+
+        let tip_height = self.tip_height().await?.unwrap_or_default();
+
+        Ok(OutputStatus {
+            txid: *txid,
+            vout,
+            value: output.value,
+            script_type: address::script_type(&output.script_pubkey),
+            script_pubkey: output.script_pubkey,
+            block_height: output.block_height as u32,
+            unspent: spent_by.is_none(),
+            spent_by,
+            tip_height,
+        })
+    }
+
+    pub fn address_index_enabled(&self) -> bool {
+        self.address_index
+    }
+
+    pub async fn get_address_transactions(
+        &self,
+        script_pubkey: &str,
+    ) -> Result<Vec<AddressTransaction>> {
+        sqlx::query_as!(
+            AddressTransaction,
+            r#"
+            SELECT txid as "txid: Txid", block_height as "block_height: u32"
+            FROM address_index
+            WHERE script_pubkey = ?
+            ORDER BY block_height DESC, txid
+            "#,
+            script_pubkey
+        )
+        .fetch_all(&self.pool)
+        .observe("address_transactions")
+        .await
+        .map_err(query_error("Failed to fetch address transactions"))
+    }
+
+    pub async fn get_address_utxos(&self, script_pubkey: &str) -> Result<Vec<Utxo>> {
+        sqlx::query_as!(
+            Utxo,
+            r#"
+            SELECT o.txid as "txid: Txid", o.output_index as "vout: u32", o.value,
+                   a.block_height as "block_height: u32"
+            FROM address_index a
+            JOIN transaction_outputs o
+              ON o.txid = a.txid AND o.script_pubkey = a.script_pubkey
+            WHERE a.script_pubkey = ?
+              AND NOT EXISTS (
+                  SELECT 1 FROM spent_outputs s
+                  WHERE s.txid = o.txid AND s.vout = o.output_index
+              )
+            ORDER BY a.block_height DESC, o.txid, o.output_index
+            "#,
+            script_pubkey
+        )
+        .fetch_all(&self.pool)
+        .observe("address_utxos")
+        .await
+        .map_err(query_error("Failed to fetch address UTXOs"))
+    }
+
+    pub async fn get_header_status(&self, hash: &BlockHash) -> Result<HeaderStatus> {
+        let active = sqlx::query!(
+            r#"
+            SELECT h.block_height as "block_height: u32",
+                   (SELECT MAX(height) FROM blocks) - h.block_height + 1 as "confirmations!: u32",
+                   b.verified AND pf.block_height IS NOT NULL as "verified!: bool"
+            FROM block_headers h
+            JOIN blocks b ON b.height = h.block_height
+            LEFT JOIN proof_files pf ON pf.block_height = h.block_height
+            WHERE h.hash = ?
+            "#,
+            hash
+        )
+        .fetch_optional(&self.pool)
+        .observe("header_status")
+        .await
+        .map_err(query_error("Failed to fetch header"))?;
+
+        if let Some(row) = active {
+            return Ok(HeaderStatus {
+                block_hash: *hash,
+                in_chain: true,
+                block_height: Some(row.block_height),
+                confirmations: Some(row.confirmations),
+                verified: row.verified,
+                on_active_chain: true,
+            });
+        }
+
+        let orphaned_height = sqlx::query_scalar!(
+            r#"SELECT height as "height: u32" FROM orphaned_blocks WHERE hash = ?"#,
+            hash
+        )
+        .fetch_optional(&self.pool)
+        .observe("orphaned_header")
+        .await
+        .map_err(query_error("Failed to fetch header"))?;
+
+        Ok(HeaderStatus {
+            block_hash: *hash,
+            in_chain: orphaned_height.is_some(),
+            block_height: orphaned_height,
+            confirmations: None,
+            verified: false,
+            on_active_chain: false,
+        })
+    }
+
+    /// Height of the highest indexed block, if any.
+    pub async fn tip_height(&self) -> Result<Option<u32>> {
+        Self::fetch_tip_height(&self.pool).await
+    }
+
+    async fn fetch_tip_height<'c>(executor: impl SqliteExecutor<'c>) -> Result<Option<u32>> {
+        let tip_height = sqlx::query_scalar!("SELECT MAX(height) FROM blocks")
+            .fetch_one(executor)
+            .observe_one("tip_height")
+            .await
+            .map_err(query_error("Failed to fetch tip height"))?;
+
+        Ok(tip_height.map(|h| h as u32))
+    }
+
+    /// Heights of the indexed blocks within `range`, lowest first.
+    pub async fn block_heights(&self, range: RangeInclusive<u32>) -> Result<Vec<u32>> {
+        let (from, to) = (*range.start() as i64, *range.end() as i64);
+        sqlx::query_scalar!(
+            r#"SELECT height as "height!: u32" FROM blocks WHERE height BETWEEN ? AND ? ORDER BY height"#,
+            from,
+            to
+        )
+        .fetch_all(&self.pool)
+        .observe("block_heights")
+        .await
+        .map_err(query_error("Failed to list block heights"))
+    }
+
+    pub async fn get_network_stats(&self) -> Result<NetworkStats> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                (SELECT MAX(height) FROM blocks) as "indexed_height: i64",
+                (SELECT MAX(block_height) FROM proof_files) as "proved_height: i64",
+                (SELECT block_count FROM block_totals) as "block_count!: i64",
+                (SELECT COUNT(*) FROM proof_files) as "proof_count!: i64",
+                (SELECT AVG(file_size) FROM proof_files) as "avg_proof_size: f64",
+                (SELECT AVG(execution_time_ms) FROM proof_files) as "avg_proving_time_ms: f64"
+            "#
+        )
+        .fetch_one(&self.pool)
+        .observe_one("network_stats")
+        .await
+        .map_err(query_error("Failed to fetch network stats"))?;
+
+        let proof_coverage = if row.block_count > 0 {
+            row.proof_count as f64 * 100.0 / row.block_count as f64
+        } else {
+            0.0
+        };
+
+        Ok(NetworkStats {
+            indexed_height: row.indexed_height.map(|h| h as u32),
+            proved_height: row.proved_height.map(|h| h as u32),
+            proof_coverage,
+            avg_proof_size: row.avg_proof_size,
+            avg_proving_time_ms: row.avg_proving_time_ms,
+            queue_depth: (row.block_count - row.proof_count).max(0) as u32,
+            stale: false,
+        })
+    }
+
+    /// Ingest-to-proof latencies in seconds of the `window` most recent
+    /// proofs, sorted ascending.
+    pub async fn get_proof_latencies(&self, window: u32) -> Result<Vec<i64>> {
+        let window = window as i64;
+        sqlx::query_scalar!(
+            r#"
+            SELECT latency as "latency!: i64" FROM (
+                SELECT CAST(strftime('%s', pf.created_at) AS INTEGER)
+                       - CAST(strftime('%s', b.created_at) AS INTEGER) AS latency
+                FROM proof_files pf
+                JOIN blocks b ON b.height = pf.block_height
+                ORDER BY pf.block_height DESC
+                LIMIT ?
+            )
+            ORDER BY latency
+            "#,
+            window
+        )
+        .fetch_all(&self.pool)
+        .observe("proof_latencies")
+        .await
+        .map_err(query_error("Failed to fetch proof latencies"))
+    }
+
+    /// Counts blocks still waiting for a proof that were ingested more than
+    /// `secs` seconds ago.
+    pub async fn count_unproved_older_than(&self, secs: i64) -> Result<u32> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM blocks b
+            LEFT JOIN proof_files pf ON pf.block_height = b.height
+            WHERE pf.block_height IS NULL
+              AND CAST(strftime('%s', 'now') AS INTEGER)
+                  - CAST(strftime('%s', b.created_at) AS INTEGER) > ?
+            "#,
+            secs
+        )
+        .fetch_one(&self.pool)
+        .observe_one("count_unproved_blocks")
+        .await
+        .map_err(query_error("Failed to count unproved blocks"))?;
+
+        Ok(count as u32)
+    }
+
+    pub async fn proof_backlog(&self) -> Result<ProofBacklog> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "blocks!: i64",
+                   MIN(CAST(strftime('%s', b.created_at) AS INTEGER)) as "oldest_ingested_at: i64"
+            FROM blocks b
+            LEFT JOIN proof_files pf ON pf.block_height = b.height
+            LEFT JOIN proof_status ps ON ps.block_height = b.height
+            WHERE pf.block_height IS NULL
+              AND (ps.status IS NULL OR ps.status != 'archived')
+            "#
+        )
+        .fetch_one(&self.pool)
+        .observe_one("proof_backlog")
+        .await
+        .map_err(query_error("Failed to read proof backlog"))?;
+
+        Ok(ProofBacklog {
+            blocks: row.blocks as u32,
+            oldest_ingested_at: row.oldest_ingested_at,
+        })
+    }
+
+    /// Lists ranges of indexed heights without a proof, starting at `cursor`,
+    /// grouping consecutive heights that share the same reason.
+    /// Lists available proofs in height order, continuing after `cursor`.
+    pub async fn get_proofs(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        verified: Option<bool>,
+        version: Option<&str>,
+    ) -> Result<ProofsResponse> {
+        let limit = limit.min(100) as i64;
+        let fetch_limit = limit + 1;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT pf.block_height as "height: u32", pf.proof_version, pf.file_size,
+                   b.verified, pf.generated_at
+            FROM proof_files pf
+            JOIN blocks b ON b.height = pf.block_height
+            WHERE (?1 IS NULL OR pf.block_height > ?1)
+              AND (?2 IS NULL OR b.verified = ?2)
+              AND (?3 IS NULL OR pf.proof_version = ?3)
+            ORDER BY pf.block_height
+            LIMIT ?4
+            "#,
+            cursor,
+            verified,
+            version,
+            fetch_limit
+        )
+        .fetch_all(&self.pool)
+        .observe("list_proofs")
+        .await
+        .map_err(query_error("Failed to fetch proofs"))?;
+
+        let has_next = rows.len() as i64 > limit;
+        let next_cursor = if has_next {
+            rows.get(limit as usize - 1).map(|row| row.height)
+        } else {
+            None
+        };
+
+        let proofs = rows
+            .into_iter()
+            .take(limit as usize)
+            .map(|row| ProofRecord {
+                height: row.height,
+                version: row.proof_version,
+                size: row.file_size,
+                verified: row.verified,
+                generated_at: row.generated_at,
+                proof_url: format!("/v1/blocks/{}/proof", row.height),
+            })
+            .collect();
+
+        Ok(ProofsResponse {
+            proofs,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    pub async fn get_proof_coverage(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<ProofCoverageResponse> {
+        let cursor = cursor.unwrap_or(0) as i64;
+        // Fetch one extra range to know whether another page follows
+        let fetch_limit = limit as i64 + 1;
+
+        let rows = sqlx::query!(
+            r#"
+            WITH missing AS (
+                SELECT b.height, COALESCE(ps.status, 'never_attempted') AS reason
+                FROM blocks b
+                LEFT JOIN proof_files pf ON pf.block_height = b.height
+                LEFT JOIN proof_status ps ON ps.block_height = b.height
+                WHERE pf.block_height IS NULL AND b.height >= ?
+            ),
+            islands AS (
+                SELECT height, reason,
+                       height - ROW_NUMBER() OVER (PARTITION BY reason ORDER BY height) AS grp
+                FROM missing
+            )
+            SELECT MIN(height) as "start_height!: i64", MAX(height) as "end_height!: i64",
+                   COUNT(*) as "count!: i64", reason as "reason!: String"
+            FROM islands
+            GROUP BY reason, grp
+            ORDER BY MIN(height)
+            LIMIT ?
+            "#,
+            cursor,
+            fetch_limit
+        )
+        .fetch_all(&self.pool)
+        .observe("proof_coverage")
+        .await
+        .map_err(query_error("Failed to fetch proof coverage"))?;
+
+        let mut gaps = rows
+            .into_iter()
+            .map(|row| {
+                Ok(ProofGap {
+                    start_height: row.start_height as u32,
+                    end_height: row.end_height as u32,
+                    count: row.count as u32,
+                    reason: row.reason.parse()?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let has_next = gaps.len() > limit as usize;
+        let next_cursor = if has_next {
+            gaps.pop().map(|gap| gap.start_height)
+        } else {
+            None
+        };
+
+        Ok(ProofCoverageResponse {
+            gaps,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    /// Records that proving `height` failed.
+    pub async fn mark_proof_failed(&self, height: u32) -> Result<()> {
+        let failed = Event::JobFailed { height };
+        let mut tx = self.begin().await?;
+        self.set_proof_status(&mut tx, height, ProofGapReason::Failed)
+            .await?;
+        Self::queue_webhook_deliveries(&mut tx, &failed, Utc::now().timestamp()).await?;
+        Self::commit(tx).await?;
+        events::publish(failed);
+        Ok(())
+    }
+
+    /// Drops the proof for `height` from serving and records it as archived.
+    pub async fn mark_proof_archived(&self, height: u32) -> Result<()> {
+        let height_i64 = height as i64;
+        sqlx::query!("DELETE FROM proof_files WHERE block_height = ?", height_i64)
+            .execute(&self.pool)
+            .observe("archive_proof")
+            .await
+            .map_err(query_error("Failed to archive proof"))?;
+        let mut conn = self.acquire().await?;
+        self.set_proof_status(&mut conn, height, ProofGapReason::Archived)
+            .await
+    }
+
+    /// Marks block `height` verified once its proof is in, reporting the
+    /// verification as ingest does for a block that arrives verified.
+    pub async fn mark_verified(&self, height: u32) -> Result<()> {
+        let height_i64 = height as i64;
+        let mut tx = self.begin().await?;
+        let secs = sqlx::query_scalar!(
+            r#"UPDATE blocks SET verified = 1 WHERE height = ? AND verified = 0
+             RETURNING CAST(strftime('%s', 'now') AS INTEGER)
+                 - CAST(strftime('%s', created_at) AS INTEGER) as "secs!: i64""#,
+            height_i64
+        )
+        .fetch_optional(&mut *tx)
+        .observe("mark_verified")
+        .await
+        .map_err(query_error("Failed to mark block verified"))?;
+        let verified = secs.map(|secs| ProofMilestone::Verified { height, secs });
+        if let Some(verified) = verified {
+            Self::queue_webhook_deliveries(&mut tx, &verified.into(), Utc::now().timestamp())
+                .await?;
+        }
+        Self::commit(tx).await?;
+
+        if let Some(verified) = verified {
+            self.block_cache.clear();
+            ProofSlaConfig::global().observe(verified);
+            events::publish(verified.into());
+        }
+        Ok(())
+    }
+
+    /// Queues a job to prove block `height`.
+    pub async fn create_proof_job(&self, height: u32, now: i64) -> Result<ProofJob> {
+        if !self
+            .block_exists_by_identifier(&BlockId::Height(height))
+            .await?
+        {
+            return Err(AppError::BlockNotFound(height.to_string()));
+        }
+        let height_i64 = height as i64;
+        let id = sqlx::query_scalar!(
+            r#"INSERT INTO proof_jobs (block_height, created_at) VALUES (?, ?) RETURNING id as "id!""#,
+            height_i64,
+            now
+        )
+        .fetch_one(&self.pool)
+        .observe_one("create_proof_job")
+        .await
+        .map_err(query_error("Failed to create proof job"))?;
+        self.get_proof_job(id).await
+    }
+
+    /// Moves job `id` to `status`, stamping when it started running or
+    /// finished.
+    pub async fn update_proof_job(
+        &self,
+        id: i64,
+        status: JobStatus,
+        error: Option<&str>,
+        now: i64,
+    ) -> Result<ProofJob> {
+        let started_at = (status == JobStatus::Running).then_some(now);
+        let finished_at = matches!(status, JobStatus::Completed | JobStatus::Failed).then_some(now);
+        let status = status.as_str();
+        let result = sqlx::query!(
+            r#"UPDATE proof_jobs SET
+                status = ?, error = ?,
+                started_at = COALESCE(?, started_at), finished_at = COALESCE(?, finished_at)
+             WHERE id = ?"#,
+            status,
+            error,
+            started_at,
+            finished_at,
+            id
+        )
+        .execute(&self.pool)
+        .observe("update_proof_job")
+        .await
+        .map_err(query_error("Failed to update proof job"))?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::JobNotFound(id));
+        }
+        self.get_proof_job(id).await
+    }
+
+    pub async fn get_proof_job(&self, id: i64) -> Result<ProofJob> {
+        let row = sqlx::query!(
+            r#"SELECT id as "id!", block_height as "height: u32", status, error,
+                   created_at, started_at, finished_at
+             FROM proof_jobs WHERE id = ?"#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .observe("get_proof_job")
+        .await
+        .map_err(query_error("Failed to look up proof job"))?
+        .ok_or(AppError::JobNotFound(id))?;
+        Ok(ProofJob {
+            id: row.id,
+            height: row.height,
+            status: row.status.parse()?,
+            error: row.error,
+            created_at: row.created_at,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+        })
+    }
+
+    async fn set_proof_status(
+        &self,
+        conn: &mut SqliteConnection,
+        height: u32,
+        status: ProofGapReason,
+    ) -> Result<()> {
+        if !self
+            .block_exists_by_identifier(&BlockId::Height(height))
+            .await?
+        {
+            return Err(AppError::BlockNotFound(height.to_string()));
+        }
+
+        let height_i64 = height as i64;
+        let status = status.as_str();
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO proof_status (block_height, status, updated_at)
+            VALUES (?, ?, datetime('now'))
+            "#,
+            height_i64,
+            status
+        )
+        .execute(&mut *conn)
+        .observe("set_proof_status")
+        .await
+        .map_err(query_error("Failed to record proof status"))?;
+
+        Ok(())
+    }
+
+    pub async fn proof_file_exists(&self, height: u32) -> Result<bool> {
+        let height_i64 = height as i64;
+        let exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM proof_files WHERE block_height = ?)",
+            height_i64
+        )
+        .fetch_one(&self.pool)
+        .observe_one("proof_file_exists")
+        .await
+        .map_err(query_error("Failed to check proof file"))?;
+
+        Ok(exists == Some(1))
+    }
+
+    /// Checksum recorded with the proof of block `height`, if it has one.
+    pub async fn proof_checksum(&self, height: u32) -> Result<Option<String>> {
+        let height_i64 = height as i64;
+        let checksum = sqlx::query_scalar!(
+            "SELECT checksum FROM proof_files WHERE block_height = ?",
+            height_i64
+        )
+        .fetch_optional(&self.pool)
+        .observe("proof_checksum")
+        .await
+        .map_err(query_error("Failed to fetch proof checksum"))?;
+        Ok(checksum.flatten())
+    }
+
+    /// Every recorded proof with the size and checksum it was stored with,
+    /// lowest height first.
+    pub async fn proof_file_records(&self) -> Result<Vec<ProofFileRecord>> {
+        sqlx::query_as!(
+            ProofFileRecord,
+            r#"
+            SELECT block_height as "height!: u32", file_size, checksum
+            FROM proof_files
+            ORDER BY block_height
+            "#
+        )
+        .fetch_all(&self.pool)
+        .observe("proof_file_records")
+        .await
+        .map_err(query_error("Failed to list proof files"))
+    }
+
+    /// Rows of `proof_files` and `transactions` that disagree with `blocks`.
+    pub async fn table_inconsistencies(&self) -> Result<Vec<ConsistencyFinding>> {
+        let mut findings = Vec::new();
+
+        let unbacked = sqlx::query_scalar!(
+            r#"
+            SELECT pf.block_height as "height!: u32"
+            FROM proof_files pf
+            LEFT JOIN blocks b ON b.height = pf.block_height
+            WHERE b.height IS NULL
+            ORDER BY pf.block_height
+            "#
+        )
+        .fetch_all(&self.pool)
+        .observe("proofs_without_block")
+        .await
+        .map_err(query_error("Failed to check proof records"))?;
+        findings.extend(unbacked.into_iter().map(|height| ConsistencyFinding {
+            kind: FindingKind::ProofWithoutBlock,
+            height: Some(height),
+            detail: "proof_files has a proof for a height with no block".to_string(),
+        }));
+
+        let dangling = sqlx::query!(
+            r#"
+            SELECT t.block_height as "height!: u32", COUNT(*) as "count!: i64"
+            FROM transactions t
+            LEFT JOIN blocks b ON b.height = t.block_height
+            WHERE b.height IS NULL
+            GROUP BY t.block_height
+            ORDER BY t.block_height
+            "#
+        )
+        .fetch_all(&self.pool)
+        .observe("dangling_transactions")
+        .await
+        .map_err(query_error("Failed to check transactions"))?;
+        findings.extend(dangling.into_iter().map(|row| ConsistencyFinding {
+            kind: FindingKind::DanglingTransactions,
+            height: Some(row.height),
+            detail: format!(
+                "{} transactions reference a height with no block",
+                row.count
+            ),
+        }));
+
+        let out_of_range = sqlx::query!(
+            r#"
+            SELECT b.height as "height!: u32", b.tx_count, COUNT(*) as "count!: i64"
+            FROM transactions t
+            JOIN blocks b ON b.height = t.block_height
+            WHERE t.position_in_block >= b.tx_count
+            GROUP BY b.height
+            ORDER BY b.height
+            "#
+        )
+        .fetch_all(&self.pool)
+        .observe("transactions_out_of_range")
+        .await
+        .map_err(query_error("Failed to check transaction positions"))?;
+        findings.extend(out_of_range.into_iter().map(|row| ConsistencyFinding {
+            kind: FindingKind::TransactionOutOfRange,
+            height: Some(row.height),
+            detail: format!(
+                "{} transactions are positioned past the block's tx_count of {}",
+                row.count, row.tx_count
+            ),
+        }));
+
+        Ok(findings)
+    }
+
+    /// Stores a consistency check's findings as a new run, dropping runs
+    /// older than the last [`CONSISTENCY_RUNS_KEPT`].
+    pub async fn record_consistency_run(
+        &self,
+        proofs_checked: u32,
+        blocks_checked: u32,
+        findings: Vec<ConsistencyFinding>,
+    ) -> Result<ConsistencyReport> {
+        let checked_at = Utc::now().timestamp();
+        let mut tx = self.begin().await?;
+        let id = sqlx::query!(
+            r#"
+            INSERT INTO consistency_runs (checked_at, proofs_checked, blocks_checked)
+            VALUES (?, ?, ?)
+            "#,
+            checked_at,
+            proofs_checked,
+            blocks_checked
         )
-        .fetch_optional(&self.pool)
+        .execute(&mut *tx)
+        .observe("record_consistency_run")
         .await
-        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to fetch header: {}", e)))?;
+        .map_err(query_error("Failed to record consistency run"))?
+        .last_insert_rowid();
 
-        Ok(match result {
-            Some(row) => HeaderStatus {
-                in_chain: true,
-                block_height: Some(row.block_height as u32),
-            },
-            None => HeaderStatus {
-                in_chain: false,
-                block_height: None,
-            },
+        for finding in &findings {
+            let kind = finding.kind.as_str();
+            sqlx::query!(
+                r#"
+                INSERT INTO consistency_findings (run_id, kind, height, detail)
+                VALUES (?, ?, ?, ?)
+                "#,
+                id,
+                kind,
+                finding.height,
+                finding.detail
+            )
+            .execute(&mut *tx)
+            .observe("record_consistency_finding")
+            .await
+            .map_err(query_error("Failed to record consistency finding"))?;
+        }
+
+        let oldest_kept = id - CONSISTENCY_RUNS_KEPT + 1;
+        sqlx::query!("DELETE FROM consistency_runs WHERE id < ?", oldest_kept)
+            .execute(&mut *tx)
+            .observe("trim_consistency_runs")
+            .await
+            .map_err(query_error("Failed to trim consistency runs"))?;
+        Self::commit(tx).await?;
+
+        Ok(ConsistencyReport {
+            id,
+            checked_at,
+            proofs_checked,
+            blocks_checked,
+            findings,
         })
     }
 
-    pub async fn proof_file_exists(&self, height: u32) -> Result<bool> {
-        let height_i64 = height as i64;
-        let exists = sqlx::query_scalar!(
-            "SELECT EXISTS(SELECT 1 FROM proof_files WHERE block_height = ?)",
-            height_i64
+    /// The `limit` most recent consistency runs with their findings, newest
+    /// first.
+    pub async fn list_consistency_reports(&self, limit: u32) -> Result<Vec<ConsistencyReport>> {
+        let limit = limit as i64;
+        let runs = sqlx::query!(
+            r#"
+            SELECT id as "id!: i64", checked_at, proofs_checked as "proofs_checked: u32",
+                   blocks_checked as "blocks_checked: u32"
+            FROM consistency_runs
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+            limit
         )
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
+        .observe("list_consistency_runs")
         .await
-        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to check proof file: {}", e)))?;
+        .map_err(query_error("Failed to fetch consistency runs"))?;
 
-        Ok(exists == Some(1))
+        let mut reports = Vec::with_capacity(runs.len());
+        for run in runs {
+            let findings = sqlx::query!(
+                r#"
+                SELECT kind, height as "height: u32", detail
+                FROM consistency_findings
+                WHERE run_id = ?
+                ORDER BY id
+                "#,
+                run.id
+            )
+            .fetch_all(&self.pool)
+            .observe("list_consistency_findings")
+            .await
+            .map_err(query_error("Failed to fetch consistency findings"))?
+            .into_iter()
+            .map(|row| {
+                let kind = serde_json::from_value(row.kind.into()).map_err(|e| {
+                    AppError::Store(anyhow::anyhow!("Malformed finding kind: {}", e))
+                })?;
+                Ok(ConsistencyFinding {
+                    kind,
+                    height: row.height,
+                    detail: row.detail,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+            reports.push(ConsistencyReport {
+                id: run.id,
+                checked_at: run.checked_at,
+                proofs_checked: run.proofs_checked,
+                blocks_checked: run.blocks_checked,
+                findings,
+            });
+        }
+        Ok(reports)
     }
 
-    pub async fn block_exists_by_identifier(&self, identifier: &str) -> Result<bool> {
-        if let Ok(height) = identifier.parse::<u32>() {
-            let height_i64 = height as i64;
-            let exists = sqlx::query_scalar!(
-                "SELECT EXISTS(SELECT 1 FROM blocks WHERE height = ?)",
-                height_i64
-            )
-            .fetch_one(&self.pool)
+    /// Heights of the `limit` highest blocks with a proof, highest first.
+    pub async fn recent_proof_heights(&self, limit: u32) -> Result<Vec<u32>> {
+        let limit = limit as i64;
+        sqlx::query_scalar!(
+            r#"SELECT block_height as "height!: u32" FROM proof_files ORDER BY block_height DESC LIMIT ?"#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .observe("recent_proof_heights")
+        .await
+        .map_err(query_error("Failed to list recent proofs"))
+    }
+
+    pub async fn block_exists_by_identifier(&self, id: &BlockId) -> Result<bool> {
+        match id {
+            BlockId::Height(height) => {
+                let height_i64 = *height as i64;
+                let exists = sqlx::query_scalar!(
+                    "SELECT EXISTS(SELECT 1 FROM blocks WHERE height = ?)",
+                    height_i64
+                )
+                .fetch_one(&self.pool)
+                .observe_one("block_exists")
+                .await
+                .map_err(query_error("Failed to check block existence"))?;
+                Ok(exists == Some(1))
+            }
+            BlockId::Hash(hash) => {
+                let exists = sqlx::query_scalar!(
+                    "SELECT EXISTS(SELECT 1 FROM block_headers WHERE hash = ?)",
+                    hash
+                )
+                .fetch_one(&self.pool)
+                .observe_one("block_exists")
+                .await
+                .map_err(query_error("Failed to check block existence"))?;
+                Ok(exists == Some(1))
+            }
+        }
+    }
+
+    /// Encrypts legacy plaintext rows and rotates rows encrypted under retired
+    /// keys to the active key, for every column in [`ENCRYPTED_COLUMNS`].
+    /// Returns the number of values rewritten.
+    pub async fn reencrypt_secrets(&self, cipher: &ColumnCipher) -> Result<u64> {
+        let mut tx = self
+            .pool
+            .begin()
             .await
-            .map_err(|e| {
-                AppError::Store(anyhow::anyhow!("Failed to check block existence: {}", e))
-            })?;
-            Ok(exists == Some(1))
-        } else {
-            let exists = sqlx::query_scalar!(
-                "SELECT EXISTS(SELECT 1 FROM block_headers WHERE hash = ?)",
-                identifier
-            )
-            .fetch_one(&self.pool)
+            .map_err(query_error("Failed to begin transaction"))?;
+        let mut updated = 0;
+
+        for (table, column) in ENCRYPTED_COLUMNS {
+            let rows: Vec<(i64, String)> = sqlx::query_as(&format!(
+                "SELECT rowid, {column} FROM {table} WHERE {column} IS NOT NULL"
+            ))
+            .fetch_all(&mut *tx)
+            .observe("reencrypt_read")
             .await
-            .map_err(|e| {
-                AppError::Store(anyhow::anyhow!("Failed to check block existence: {}", e))
-            })?;
-            Ok(exists == Some(1))
+            .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to read {}: {}", table, e)))?;
+
+            for (rowid, value) in rows {
+                if !cipher.needs_reencryption(&value) {
+                    continue;
+                }
+                sqlx::query(&format!("UPDATE {table} SET {column} = ? WHERE rowid = ?"))
+                    .bind(cipher.reencrypt(&value)?)
+                    .bind(rowid)
+                    .execute(&mut *tx)
+                    .observe("reencrypt_update")
+                    .await
+                    .map_err(|e| {
+                        AppError::Store(anyhow::anyhow!("Failed to update {}: {}", table, e))
+                    })?;
+                updated += 1;
+            }
         }
+
+        tx.commit().await.map_err(query_error("Failed to commit"))?;
+        Ok(updated)
+    }
+
+    /// Closes the connection pool; queries made afterwards fail.
+    pub async fn close(&self) {
+        self.pool.close().await;
     }
 
     pub async fn health_check(&self) -> Result<()> {
         sqlx::query("SELECT 1")
             .execute(&self.pool)
+            .observe("health_check")
             .await
-            .map_err(|e| AppError::Store(anyhow::anyhow!("Database health check failed: {}", e)))?;
+            .map_err(query_error("Database health check failed"))?;
+        Ok(())
+    }
+}
+
+/// Keeps the replica lag current so reads switch between replica and primary
+/// as it falls behind and catches up.
+pub fn spawn_replica_monitor(database: Arc<Database>) {
+    if database.replica.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(REPLICA_CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            match database.refresh_replica_lag().await {
+                Ok(Some(lag)) if lag > database.replica_max_lag => warn!(
+                    lag,
+                    max_lag = database.replica_max_lag,
+                    "Read replica lagging, serving reads from primary"
+                ),
+                Ok(_) => {}
+                Err(e) => warn!(error = %e, "Replica lag check failed"),
+            }
+        }
+    });
+}
+
+/// Rows a query returned or changed, as counted by [`ObserveQuery`].
+trait RowCount {
+    fn row_count(&self) -> u64;
+}
+
+impl<T> RowCount for Vec<T> {
+    fn row_count(&self) -> u64 {
+        self.len() as u64
+    }
+}
+
+impl<T> RowCount for Option<T> {
+    fn row_count(&self) -> u64 {
+        self.is_some() as u64
+    }
+}
+
+impl RowCount for SqliteQueryResult {
+    fn row_count(&self) -> u64 {
+        self.rows_affected()
+    }
+}
+
+impl RowCount for u64 {
+    fn row_count(&self) -> u64 {
+        *self
+    }
+}
+
+/// Runs a query inside a `db.query` span and records its duration, rows and
+/// failures under `name`, so slow queries show up per query in `/metrics`.
+trait ObserveQuery<T>: Future<Output = sqlx::Result<T>> + Sized {
+    fn observe(self, name: &'static str) -> impl Future<Output = sqlx::Result<T>>
+    where
+        T: RowCount,
+    {
+        observe_query(name, self, RowCount::row_count)
+    }
+
+    /// For `fetch_one`, whose row isn't a [`RowCount`].
+    fn observe_one(self, name: &'static str) -> impl Future<Output = sqlx::Result<T>> {
+        observe_query(name, self, |_| 1)
+    }
+}
+
+impl<F, T> ObserveQuery<T> for F where F: Future<Output = sqlx::Result<T>> {}
+
+async fn observe_query<T>(
+    name: &'static str,
+    query: impl Future<Output = sqlx::Result<T>>,
+    rows: impl FnOnce(&T) -> u64,
+) -> sqlx::Result<T> {
+    let start = Instant::now();
+    let result = query
+        .instrument(debug_span!("db.query", query = name))
+        .await;
+    Metrics::global().observe_db_query(
+        name,
+        start.elapsed(),
+        result.as_ref().map(rows).map_err(|_| ()),
+    );
+    result
+}
+
+/// SQLite result codes worth retrying: busy, locked, I/O error, can't open.
+const TRANSIENT_SQLITE_CODES: &[i64] = &[5, 6, 10, 14];
+
+/// Maps a failed query to [`AppError::StoreUnavailable`] when trying again
+/// may succeed, and to [`AppError::Store`] otherwise.
+fn query_error(context: &'static str) -> impl Fn(sqlx::Error) -> AppError {
+    move |e| {
+        let transient = match &e {
+            sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::WorkerCrashed => true,
+            // Extended result codes keep the primary code in the low byte
+            sqlx::Error::Database(db_error) => db_error
+                .code()
+                .and_then(|code| code.parse::<i64>().ok())
+                .is_some_and(|code| TRANSIENT_SQLITE_CODES.contains(&(code & 0xff))),
+            _ => false,
+        };
+        if transient {
+            AppError::StoreUnavailable {
+                reason: format!("{context}: {e}"),
+                retry_after_secs: 1,
+            }
+        } else {
+            AppError::Store(anyhow::anyhow!("{}: {}", context, e))
+        }
+    }
+}
+
+/// A proof as recorded in `proof_files`.
+#[derive(Debug, Clone)]
+pub struct ProofFileRecord {
+    pub height: u32,
+    pub file_size: Option<i64>,
+    /// `None` for proofs recorded without one.
+    pub checksum: Option<String>,
+}
+
+/// A pending webhook delivery whose next attempt is due.
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub payload: String,
+    /// Attempts made so far.
+    pub attempts: i64,
+    pub url: String,
+    /// The webhook's signing secret, decrypted.
+    pub secret: String,
+}
+
+/// The stored block a new one builds on.
+struct ParentBlock {
+    /// `None` when the parent was stored without chainwork.
+    chainwork: Option<u128>,
+    bits: u32,
+    timestamp: i64,
+}
+
+struct ApiKeyRow {
+    id: i64,
+    name: String,
+    prefix: String,
+    scope: String,
+    created_at: i64,
+    last_used_at: Option<i64>,
+    revoked_at: Option<i64>,
+}
+
+impl ApiKeyRow {
+    fn into_api_key(self) -> Result<ApiKey> {
+        Ok(ApiKey {
+            id: self.id,
+            name: self.name,
+            prefix: self.prefix,
+            scope: self.scope.parse()?,
+            created_at: self.created_at,
+            last_used_at: self.last_used_at,
+            revoked_at: self.revoked_at,
+        })
+    }
+}
+
+struct WebhookRow {
+    id: i64,
+    url: String,
+    /// Comma-separated event types.
+    events: String,
+    active: bool,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl WebhookRow {
+    fn into_webhook(self) -> Webhook {
+        Webhook {
+            id: self.id,
+            url: self.url,
+            // Types a later version no longer knows are dropped
+            events: self
+                .events
+                .split(',')
+                .filter_map(WebhookEvent::parse)
+                .collect(),
+            active: self.active,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+fn join_events(events: &[WebhookEvent]) -> String {
+    events
+        .iter()
+        .map(|event| event.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+struct TransactionRow<'a> {
+    txid: &'a str,
+    wtxid: Option<Wtxid>,
+    position: i64,
+    fee: Option<f64>,
+    size: Option<i64>,
+    vsize: Option<i64>,
+    raw: Option<Vec<u8>>,
+}
+
+/// Runs `statement` (an `INSERT ... ` prefix) with a `VALUES` list built from
+/// `rows`, in chunks of [`BULK_INSERT_ROWS`]. Returns the rows inserted.
+async fn bulk_insert<'a, T>(
+    conn: &mut SqliteConnection,
+    statement: &str,
+    rows: &'a [T],
+    mut bind: impl FnMut(Separated<'_, 'a, Sqlite, &'static str>, &'a T),
+) -> sqlx::Result<u64> {
+    let mut inserted = 0;
+    for chunk in rows.chunks(BULK_INSERT_ROWS) {
+        let mut builder = QueryBuilder::<Sqlite>::new(statement);
+        builder.push_values(chunk, &mut bind);
+        inserted += builder.build().execute(&mut *conn).await?.rows_affected();
+    }
+    Ok(inserted)
+}
+
+#[async_trait]
+impl BlockStore for Database {
+    async fn health_check(&self) -> Result<()> {
+        Database::health_check(self).await?;
+        if let Some(replica) = &self.replica {
+            Database::health_check(replica).await?;
+        }
         Ok(())
     }
+
+    fn replica_lag(&self) -> Option<ReplicaLag> {
+        Database::replica_lag(self)
+    }
+
+    fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        Some(&self.breaker)
+    }
+
+    async fn tip_height(&self) -> Result<Option<u32>> {
+        self.read(Database::tip_height).await
+    }
+
+    async fn get_blocks(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        direction: PageDirection,
+        order: SortOrder,
+        filter: &BlockFilter,
+    ) -> Result<BlocksResponse> {
+        self.read(|db| Database::get_blocks(db, limit, cursor, direction, order, filter))
+            .await
+    }
+
+    async fn get_orphans(&self, limit: u32, cursor: Option<i64>) -> Result<OrphansResponse> {
+        self.read(|db| Database::get_orphans(db, limit, cursor))
+            .await
+    }
+
+    async fn block_exists_by_identifier(&self, id: &BlockId) -> Result<bool> {
+        self.read(|db| Database::block_exists_by_identifier(db, id))
+            .await
+    }
+
+    async fn get_block_by_height(&self, height: u32) -> Result<BlockDetail> {
+        self.block_cache
+            .get_or_load(
+                BlockKey::Height(height),
+                self.read(|db| Database::get_block_by_height(db, height)),
+            )
+            .await
+    }
+
+    async fn get_block_by_hash(&self, hash: &BlockHash) -> Result<BlockDetail> {
+        self.block_cache
+            .get_or_load(
+                BlockKey::Hash(*hash),
+                self.read(|db| Database::get_block_by_hash(db, hash)),
+            )
+            .await
+    }
+
+    async fn get_block_stats(&self, height: u32) -> Result<BlockStats> {
+        self.read(|db| Database::get_block_stats(db, height)).await
+    }
+
+    async fn get_block_txids(
+        &self,
+        height: u32,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<BlockTxidsResponse> {
+        self.read(|db| Database::get_block_txids(db, height, limit, cursor))
+            .await
+    }
+
+    async fn get_transaction_status(&self, txid: &Txid) -> Result<TransactionStatus> {
+        self.read(|db| Database::get_transaction_status(db, txid))
+            .await
+    }
+
+    async fn get_transaction_detail(&self, txid: &Txid) -> Result<TransactionDetail> {
+        self.read(|db| Database::get_transaction_detail(db, txid))
+            .await
+    }
+
+    async fn get_raw_transaction(&self, txid: &Txid) -> Result<Vec<u8>> {
+        self.read(|db| Database::get_raw_transaction(db, txid))
+            .await
+    }
+
+    async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<OutputStatus> {
+        self.read(|db| Database::get_output_status(db, txid, vout))
+            .await
+    }
+
+    fn address_index_enabled(&self) -> bool {
+        Database::address_index_enabled(self)
+    }
+
+    async fn get_address_transactions(
+        &self,
+        script_pubkey: &str,
+    ) -> Result<Vec<AddressTransaction>> {
+        self.read(|db| Database::get_address_transactions(db, script_pubkey))
+            .await
+    }
+
+    async fn get_address_utxos(&self, script_pubkey: &str) -> Result<Vec<Utxo>> {
+        self.read(|db| Database::get_address_utxos(db, script_pubkey))
+            .await
+    }
+
+    async fn get_header_status(&self, hash: &BlockHash) -> Result<HeaderStatus> {
+        self.read(|db| Database::get_header_status(db, hash)).await
+    }
+
+    async fn get_network_stats(&self) -> Result<NetworkStats> {
+        self.read(Database::get_network_stats).await
+    }
+
+    async fn proof_file_exists(&self, height: u32) -> Result<bool> {
+        self.read(|db| Database::proof_file_exists(db, height))
+            .await
+    }
+
+    async fn proof_checksum(&self, height: u32) -> Result<Option<String>> {
+        self.read(|db| Database::proof_checksum(db, height)).await
+    }
+
+    async fn get_proofs(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        verified: Option<bool>,
+        version: Option<&str>,
+    ) -> Result<ProofsResponse> {
+        self.read(|db| Database::get_proofs(db, limit, cursor, verified, version))
+            .await
+    }
+
+    async fn get_proof_coverage(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<ProofCoverageResponse> {
+        self.read(|db| Database::get_proof_coverage(db, limit, cursor))
+            .await
+    }
+
+    async fn get_proof_latencies(&self, window: u32) -> Result<Vec<i64>> {
+        self.read(|db| Database::get_proof_latencies(db, window))
+            .await
+    }
+
+    async fn pruned_below(&self) -> Result<u32> {
+        self.read(Database::pruned_below).await
+    }
+
+    async fn prune_below(&self, height: u32) -> Result<PruneResponse> {
+        Database::prune_below(self, height).await
+    }
+
+    async fn reseed(&self) -> Result<()> {
+        self.seed_data().await
+    }
+
+    async fn reindex(&self, from: u32, to: u32) -> Result<u32> {
+        Database::reindex(self, from, to).await
+    }
+
+    async fn submit_block(&self, block: &serde_json::Value) -> Result<()> {
+        self.insert_block(block).await
+    }
+
+    async fn claim_idempotency_key(
+        &self,
+        scope: &str,
+        key: &str,
+        fingerprint: &str,
+        now: i64,
+        config: &IdempotencyConfig,
+    ) -> Result<IdempotencyClaim> {
+        Database::claim_idempotency_key(self, scope, key, fingerprint, now, config).await
+    }
+
+    async fn complete_idempotency_key(
+        &self,
+        scope: &str,
+        key: &str,
+        response: &StoredResponse,
+    ) -> Result<()> {
+        Database::complete_idempotency_key(self, scope, key, response).await
+    }
+
+    async fn release_idempotency_key(&self, scope: &str, key: &str) -> Result<()> {
+        Database::release_idempotency_key(self, scope, key).await
+    }
+
+    async fn create_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        active: bool,
+    ) -> Result<Webhook> {
+        Database::create_webhook(self, url, secret, events, active).await
+    }
+
+    async fn list_webhooks(&self) -> Result<Vec<Webhook>> {
+        Database::list_webhooks(self).await
+    }
+
+    async fn get_webhook(&self, id: i64) -> Result<Webhook> {
+        Database::get_webhook(self, id).await
+    }
+
+    async fn get_proof_job(&self, id: i64) -> Result<ProofJob> {
+        Database::get_proof_job(self, id).await
+    }
+
+    async fn update_webhook(&self, id: i64, changes: &UpdateWebhookRequest) -> Result<Webhook> {
+        Database::update_webhook(self, id, changes).await
+    }
+
+    async fn delete_webhook(&self, id: i64) -> Result<Webhook> {
+        Database::delete_webhook(self, id).await
+    }
+
+    async fn list_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        status: Option<DeliveryStatus>,
+        limit: u32,
+        cursor: Option<i64>,
+    ) -> Result<WebhookDeliveriesResponse> {
+        Database::list_webhook_deliveries(self, webhook_id, status, limit, cursor).await
+    }
+
+    async fn upload_proof(&self, height: u32, data: &[u8]) -> Result<()> {
+        let execution_time_ms = proof::execution_time_ms(data);
+        proof::register(self, storage::global(), height, data, execution_time_ms).await
+    }
+
+    fn invalidate_cache(&self) {
+        Database::invalidate_cache(self)
+    }
+
+    async fn requeue_failed_proofs(&self) -> Result<u32> {
+        Database::requeue_failed_proofs(self).await
+    }
+
+    async fn create_api_key(
+        &self,
+        name: &str,
+        scope: KeyScope,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Result<ApiKey> {
+        Database::create_api_key(self, name, scope, prefix, key_hash).await
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        Database::list_api_keys(self).await
+    }
+
+    async fn revoke_api_key(&self, id: i64) -> Result<ApiKey> {
+        Database::revoke_api_key(self, id).await
+    }
+
+    async fn authenticate_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>> {
+        Database::authenticate_api_key(self, key_hash).await
+    }
+
+    async fn record_audit(&self, actor: &str, action: &str, target: Option<&str>) -> Result<()> {
+        Database::record_audit(self, actor, action, target).await
+    }
+
+    async fn record_api_key_usage(&self, id: i64, day: &str, usage: UsageCounts) -> Result<()> {
+        Database::record_api_key_usage(self, id, day, usage).await
+    }
+
+    async fn get_api_key_usage(
+        &self,
+        id: i64,
+        day: &str,
+        month_start: &str,
+    ) -> Result<(UsageCounts, UsageCounts)> {
+        Database::get_api_key_usage(self, id, day, month_start).await
+    }
+
+    async fn list_audit_log(
+        &self,
+        actor: Option<&str>,
+        action: Option<&str>,
+        limit: u32,
+        cursor: Option<i64>,
+    ) -> Result<AuditLogResponse> {
+        Database::list_audit_log(self, actor, action, limit, cursor).await
+    }
+
+    async fn list_block_conflicts(
+        &self,
+        limit: u32,
+        cursor: Option<i64>,
+    ) -> Result<BlockConflictsResponse> {
+        Database::list_block_conflicts(self, limit, cursor).await
+    }
+
+    async fn list_consistency_reports(&self, limit: u32) -> Result<Vec<ConsistencyReport>> {
+        Database::list_consistency_reports(self, limit).await
+    }
+
+    async fn run_consistency_check(&self) -> Result<ConsistencyReport> {
+        consistency::run(self, storage::global()).await
+    }
+
+    async fn count_unproved_older_than(&self, secs: i64) -> Result<u32> {
+        self.read(|db| Database::count_unproved_older_than(db, secs))
+            .await
+    }
+
+    async fn proof_backlog(&self) -> Result<ProofBacklog> {
+        self.read(Database::proof_backlog).await
+    }
 }
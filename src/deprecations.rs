@@ -0,0 +1,385 @@
+use crate::telemetry::Metrics;
+use axum::{
+    extract::{MatchedPath, OriginalUri, Request, State},
+    http::{HeaderMap, HeaderValue, Uri},
+    middleware::Next,
+    response::Response,
+};
+use chrono::NaiveDate;
+use utoipa::openapi::{schema::Schema, Deprecated, OpenApi, RefOr};
+
+pub const DEPRECATION_HEADER: &str = "deprecation";
+pub const SUNSET_HEADER: &str = "sunset";
+
+/// What a deprecation applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// A whole endpoint, matched by method and axum route (`/v1/tx/:txid`).
+    Route {
+        method: &'static str,
+        path: &'static str,
+    },
+    /// A field of a response schema. Handlers report its use by attaching
+    /// [`Used`] to the response.
+    Field {
+        schema: &'static str,
+        field: &'static str,
+    },
+}
+
+/// Deprecation metadata for an endpoint or field.
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+    /// Stable name used in metrics and by handlers reporting field usage.
+    pub feature: &'static str,
+    pub target: Target,
+    /// Date (`YYYY-MM-DD`) the feature was deprecated.
+    pub deprecated_at: &'static str,
+    /// Date (`YYYY-MM-DD`) after which the feature may be removed.
+    pub sunset: Option<&'static str>,
+    /// Migration notes for clients.
+    pub link: Option<&'static str>,
+    /// Route replacing a deprecated route, with the same `:params`; linked
+    /// from its responses as the `successor-version`.
+    pub successor: Option<&'static str>,
+}
+
+/// A `/v1` route superseded by the same route under `/v2`, where its models
+/// changed.
+const fn superseded_by_v2(
+    feature: &'static str,
+    v1: &'static str,
+    v2: &'static str,
+) -> Deprecation {
+    Deprecation {
+        feature,
+        target: Target::Route {
+            method: "GET",
+            path: v1,
+        },
+        deprecated_at: "2026-10-17",
+        sunset: None,
+        link: None,
+        successor: Some(v2),
+    }
+}
+
+/// Every deprecated endpoint and field. Entries here get `Deprecation`/`Sunset`
+/// headers, are flagged in the OpenAPI spec and have their usage counted in
+/// `raito_deprecated_feature_requests_total`.
+pub const DEPRECATIONS: &[Deprecation] = &[
+    superseded_by_v2("v1_blocks", "/v1/blocks", "/v2/blocks"),
+    superseded_by_v2("v1_orphans", "/v1/blocks/orphans", "/v2/blocks/orphans"),
+    superseded_by_v2(
+        "v1_block",
+        "/v1/blocks/:identifier",
+        "/v2/blocks/:identifier",
+    ),
+    superseded_by_v2("v1_tx_status", "/v1/tx/:txid", "/v2/tx/:txid"),
+    superseded_by_v2("v1_tx_detail", "/v1/tx/:txid/detail", "/v2/tx/:txid/detail"),
+    superseded_by_v2(
+        "v1_output_status",
+        "/v1/utxo/:txid/:vout",
+        "/v2/utxo/:txid/:vout",
+    ),
+    superseded_by_v2(
+        "v1_address_utxos",
+        "/v1/address/:address/utxos",
+        "/v2/address/:address/utxos",
+    ),
+    superseded_by_v2("v1_proofs", "/v1/proofs", "/v2/proofs"),
+];
+
+/// Response extension set by handlers that served a deprecated field.
+#[derive(Debug, Clone, Copy)]
+pub struct Used(pub &'static str);
+
+fn parse_date(date: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
+
+impl Deprecation {
+    /// Sets the `Deprecation` (RFC 9745), `Sunset` (RFC 8594) and `Link`
+    /// headers describing this deprecation.
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        if let Some(deprecated_at) = parse_date(self.deprecated_at) {
+            if let Ok(value) = HeaderValue::from_str(&format!("@{}", deprecated_at.timestamp())) {
+                headers.insert(DEPRECATION_HEADER, value);
+            }
+        }
+        if let Some(sunset) = self.sunset.and_then(parse_date) {
+            let http_date = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            if let Ok(value) = HeaderValue::from_str(&http_date) {
+                headers.insert(SUNSET_HEADER, value);
+            }
+        }
+        if let Some(link) = self.link {
+            if let Ok(value) = HeaderValue::from_str(&format!("<{link}>; rel=\"deprecation\"")) {
+                headers.append(axum::http::header::LINK, value);
+            }
+        }
+    }
+
+    fn description(&self) -> String {
+        let mut description = format!("Deprecated since {}.", self.deprecated_at);
+        if let Some(sunset) = self.sunset {
+            description.push_str(&format!(" Scheduled for removal on {sunset}."));
+        }
+        if let Some(link) = self.link {
+            description.push_str(&format!(" See {link}."));
+        }
+        if let Some(successor) = self.successor {
+            description.push_str(&format!(" Use `{}` instead.", openapi_path(successor)));
+        }
+        description
+    }
+
+    /// Links the successor of a deprecated route from a response to `uri`,
+    /// carrying over its path parameters and query.
+    fn apply_successor(&self, uri: &Uri, headers: &mut HeaderMap) {
+        let (Target::Route { path: route, .. }, Some(successor)) = (self.target, self.successor)
+        else {
+            return;
+        };
+        let params: Vec<(&str, &str)> = route
+            .split('/')
+            .zip(uri.path().split('/'))
+            .filter_map(|(segment, value)| Some((segment.strip_prefix(':')?, value)))
+            .collect();
+        let mut target = successor
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(param) => params
+                    .iter()
+                    .find(|(name, _)| *name == param)
+                    .map_or(segment, |(_, value)| value),
+                None => segment,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if let Some(query) = uri.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+        if let Ok(value) = HeaderValue::from_str(&format!("<{target}>; rel=\"successor-version\""))
+        {
+            headers.append(axum::http::header::LINK, value);
+        }
+    }
+}
+
+fn find_route<'a>(
+    registry: &'a [Deprecation],
+    method: &str,
+    route: &str,
+) -> Option<&'a Deprecation> {
+    registry.iter().find(|d| match d.target {
+        Target::Route { method: m, path } => m.eq_ignore_ascii_case(method) && path == route,
+        Target::Field { .. } => false,
+    })
+}
+
+fn find_feature<'a>(registry: &'a [Deprecation], feature: &str) -> Option<&'a Deprecation> {
+    registry.iter().find(|d| d.feature == feature)
+}
+
+/// Adds deprecation headers to responses from deprecated routes, or from
+/// handlers that report a deprecated field via [`Used`], and counts each use.
+pub async fn deprecation_middleware(
+    State(registry): State<&'static [Deprecation]>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    // Nesting strips the version prefix from `request.uri()`
+    let uri = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| request.uri().clone(), |OriginalUri(uri)| uri.clone());
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+
+    let mut response = next.run(request).await;
+
+    let route_deprecation = route
+        .as_deref()
+        .and_then(|route| find_route(registry, &method, route));
+    let field_deprecation = response
+        .extensions()
+        .get::<Used>()
+        .and_then(|Used(feature)| find_feature(registry, feature));
+
+    for deprecation in [route_deprecation, field_deprecation].into_iter().flatten() {
+        deprecation.apply_headers(response.headers_mut());
+        Metrics::global().record_deprecated_use(deprecation.feature);
+    }
+    if let Some(deprecation) = route_deprecation {
+        deprecation.apply_successor(&uri, response.headers_mut());
+    }
+
+    response
+}
+
+fn openapi_path(route: &str) -> String {
+    route
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(param) => format!("{{{param}}}"),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Marks registered routes and fields as deprecated in the OpenAPI spec and
+/// documents their sunset dates.
+pub fn annotate_openapi(mut openapi: OpenApi, registry: &[Deprecation]) -> OpenApi {
+    for deprecation in registry {
+        match deprecation.target {
+            Target::Route { method, path } => {
+                let Some(item) = openapi.paths.paths.get_mut(&openapi_path(path)) else {
+                    continue;
+                };
+                for (item_type, operation) in item.operations.iter_mut() {
+                    let item_method = serde_json::to_value(item_type)
+                        .ok()
+                        .and_then(|v| v.as_str().map(str::to_string))
+                        .unwrap_or_default();
+                    if item_method.eq_ignore_ascii_case(method) {
+                        operation.deprecated = Some(Deprecated::True);
+                        let description = operation.description.get_or_insert_with(String::new);
+                        if !description.is_empty() {
+                            description.push_str("\n\n");
+                        }
+                        description.push_str(&deprecation.description());
+                    }
+                }
+            }
+            Target::Field { schema, field } => {
+                let property = openapi
+                    .components
+                    .as_mut()
+                    .and_then(|c| c.schemas.get_mut(schema))
+                    .and_then(|s| match s {
+                        RefOr::T(Schema::Object(object)) => object.properties.get_mut(field),
+                        _ => None,
+                    });
+                if let Some(RefOr::T(Schema::Object(property))) = property {
+                    property.deprecated = Some(Deprecated::True);
+                    property.description = Some(deprecation.description());
+                }
+            }
+        }
+    }
+    openapi
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::ApiDoc;
+    use axum::{middleware, routing::get, Extension, Router};
+    use axum_test::TestServer;
+    use utoipa::OpenApi as _;
+
+    const TEST_REGISTRY: &[Deprecation] = &[
+        Deprecation {
+            feature: "legacy_tx_status",
+            target: Target::Route {
+                method: "GET",
+                path: "/v1/tx/:txid",
+            },
+            deprecated_at: "2026-01-01",
+            sunset: Some("2026-07-01"),
+            link: Some("https://docs.example.com/migrate"),
+            successor: Some("/v2/tx/:txid"),
+        },
+        Deprecation {
+            feature: "block_total_fees",
+            target: Target::Field {
+                schema: "BlockSummary",
+                field: "total_fees",
+            },
+            deprecated_at: "2026-01-01",
+            sunset: None,
+            link: None,
+            successor: None,
+        },
+    ];
+
+    #[test]
+    fn test_registered_dates_are_valid() {
+        for deprecation in DEPRECATIONS {
+            assert!(parse_date(deprecation.deprecated_at).is_some());
+            assert!(deprecation.sunset.is_none_or(|s| parse_date(s).is_some()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_emits_headers() {
+        let app = Router::new()
+            .route("/v1/tx/:txid", get(|| async { "status" }))
+            .route(
+                "/v1/blocks",
+                get(|| async { (Extension(Used("block_total_fees")), "blocks") }),
+            )
+            .route("/v1/header/:hash", get(|| async { "header" }))
+            .layer(middleware::from_fn_with_state(
+                TEST_REGISTRY,
+                deprecation_middleware,
+            ));
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get("/v1/tx/abc").add_query_param("x", 1).await;
+        assert_eq!(response.header(DEPRECATION_HEADER), "@1767225600");
+        assert_eq!(
+            response.header(SUNSET_HEADER),
+            "Wed, 01 Jul 2026 00:00:00 GMT"
+        );
+        let links: Vec<_> = response
+            .headers()
+            .get_all("link")
+            .iter()
+            .map(|link| link.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            links,
+            [
+                "<https://docs.example.com/migrate>; rel=\"deprecation\"",
+                "</v2/tx/abc?x=1>; rel=\"successor-version\"",
+            ]
+        );
+
+        let response = server.get("/v1/blocks").await;
+        assert_eq!(response.header(DEPRECATION_HEADER), "@1767225600");
+        assert!(response.maybe_header(SUNSET_HEADER).is_none());
+
+        let response = server.get("/v1/header/abc").await;
+        assert!(response.maybe_header(DEPRECATION_HEADER).is_none());
+
+        let metrics = Metrics::global().render();
+        assert!(metrics
+            .contains("raito_deprecated_feature_requests_total{feature=\"legacy_tx_status\"}"));
+    }
+
+    #[test]
+    fn test_annotate_openapi() {
+        let openapi = annotate_openapi(ApiDoc::openapi(), TEST_REGISTRY);
+        let spec = serde_json::to_value(&openapi).unwrap();
+
+        let operation = &spec["paths"]["/v1/tx/{txid}"]["get"];
+        assert_eq!(operation["deprecated"], true);
+        assert!(operation["description"]
+            .as_str()
+            .unwrap()
+            .contains("Scheduled for removal on 2026-07-01. See https://docs.example.com/migrate. Use `/v2/tx/{txid}` instead."));
+
+        let property = &spec["components"]["schemas"]["BlockSummary"]["properties"]["total_fees"];
+        assert_eq!(property["deprecated"], true);
+    }
+}
@@ -0,0 +1,196 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+/// Formats a JSON response can be sent in, chosen by the request's `Accept`.
+/// The binary ones carry the same document and cost embedded clients less to
+/// transfer and parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseEncoding {
+    Json,
+    Cbor,
+    MessagePack,
+    /// Only for the resources in [`crate::proto`], which their handlers
+    /// encode themselves; other endpoints answer with JSON.
+    Protobuf,
+}
+
+impl ResponseEncoding {
+    /// The format the client ranks highest by `q`, the first listed on a tie,
+    /// and JSON when it names none of them.
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let mut best = (Self::Json, 0.0);
+        let accepted = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for entry in accepted {
+            let mut params = entry.split(';');
+            let media_type = params.next().unwrap_or_default().trim();
+            let Some(encoding) = Self::from_media_type(media_type) else {
+                continue;
+            };
+            let q = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if q > best.1 {
+                best = (encoding, q);
+            }
+        }
+        best.0
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type.to_ascii_lowercase().as_str() {
+            "application/json" | "application/*" | "*/*" => Some(Self::Json),
+            "application/cbor" => Some(Self::Cbor),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Self::MessagePack)
+            }
+            "application/x-protobuf" | "application/protobuf" => Some(Self::Protobuf),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Cbor => "application/cbor",
+            Self::MessagePack => "application/msgpack",
+            Self::Protobuf => crate::proto::PROTOBUF_CONTENT_TYPE,
+        }
+    }
+
+    /// Re-encodes a JSON document.
+    pub fn encode(self, json: &[u8]) -> Result<Vec<u8>, String> {
+        let value: serde_json::Value = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+        match self {
+            Self::Json | Self::Protobuf => Ok(json.to_vec()),
+            Self::Cbor => serde_cbor::to_vec(&value).map_err(|e| e.to_string()),
+            Self::MessagePack => rmp_serde::to_vec_named(&value).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Sends JSON responses as CBOR or MessagePack when `Accept` asks for them.
+/// Error bodies stay `application/problem+json`. Layer it inside compression
+/// so the binary body is what gets compressed.
+pub async fn response_encoding_middleware(request: Request, next: Next) -> Response {
+    let encoding = ResponseEncoding::negotiate(request.headers());
+    let mut response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+    if matches!(
+        encoding,
+        ResponseEncoding::Json | ResponseEncoding::Protobuf
+    ) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let encoded = match to_bytes(body, usize::MAX).await {
+        Ok(json) => encoding.encode(&json),
+        Err(e) => Err(e.to_string()),
+    };
+    let body = match encoded {
+        Ok(body) => body,
+        Err(e) => {
+            warn!(error = %e, encoding = ?encoding, "Failed to re-encode response");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(encoding.content_type()),
+    );
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+    // Another representation of the same document: equivalent, not identical
+    let weak = parts
+        .headers
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .and_then(|etag| HeaderValue::from_str(&format!("W/{etag}")).ok());
+    if let Some(weak) = weak {
+        parts.headers.insert(header::ETAG, weak);
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate() {
+        use ResponseEncoding::*;
+        assert_eq!(ResponseEncoding::negotiate(&HeaderMap::new()), Json);
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("application/cbor")),
+            Cbor
+        );
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("application/json, application/cbor")),
+            Json
+        );
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("application/json;q=0.5, application/x-msgpack")),
+            MessagePack
+        );
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("text/html, */*;q=0.1")),
+            Json
+        );
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("application/cbor;q=0")),
+            Json
+        );
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("application/x-protobuf")),
+            Protobuf
+        );
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let json = br#"{"height":869123,"hash":"00ab","fee":-1,"difficulty":1.5,"txids":["aa"],"pruned":null}"#;
+        let value: serde_json::Value = serde_json::from_slice(json).unwrap();
+
+        let cbor = ResponseEncoding::Cbor.encode(json).unwrap();
+        assert!(cbor.len() < json.len());
+        assert_eq!(
+            serde_cbor::from_slice::<serde_json::Value>(&cbor).unwrap(),
+            value
+        );
+
+        let msgpack = ResponseEncoding::MessagePack.encode(json).unwrap();
+        assert!(msgpack.len() < json.len());
+        assert_eq!(
+            rmp_serde::from_slice::<serde_json::Value>(&msgpack).unwrap(),
+            value
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::{AppError, Result};
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use std::collections::HashMap;
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+
+/// Encrypts sensitive column values (API key secrets, webhook signing secrets,
+/// admin credentials) with AES-256-GCM.
+///
+/// Encrypted values are stored as `enc:v1:<key_id>:<hex(nonce || ciphertext)>`
+/// so several master keys can coexist during rotation. Values without the
+/// prefix are treated as legacy plaintext until they are re-encrypted.
+pub struct ColumnCipher {
+    keys: HashMap<String, Aes256Gcm>,
+    active_key_id: String,
+}
+
+impl std::fmt::Debug for ColumnCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ColumnCipher")
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .field("active_key_id", &self.active_key_id)
+            .finish()
+    }
+}
+
+impl ColumnCipher {
+    pub fn new(keys: Vec<(String, [u8; 32])>, active_key_id: &str) -> Result<Self> {
+        let keys: HashMap<_, _> = keys
+            .into_iter()
+            .map(|(id, key)| (id, Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))))
+            .collect();
+
+        if !keys.contains_key(active_key_id) {
+            return Err(AppError::Encryption(format!(
+                "active key {active_key_id} is not configured"
+            )));
+        }
+
+        Ok(Self {
+            keys,
+            active_key_id: active_key_id.to_string(),
+        })
+    }
+
+    /// Loads master keys from `ENCRYPTION_KEYS` (`id=hex,id=hex`, 32-byte keys)
+    /// and the active key from `ENCRYPTION_ACTIVE_KEY_ID` (defaults to the
+    /// first key). Returns `None` when no keys are configured.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(spec) = std::env::var("ENCRYPTION_KEYS") else {
+            return Ok(None);
+        };
+
+        let keys = spec
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let (id, key) = entry.trim().split_once('=').ok_or_else(|| {
+                    AppError::Encryption(format!("malformed key entry for {entry}"))
+                })?;
+                let key: [u8; 32] = hex::decode(key)
+                    .ok()
+                    .and_then(|k| k.try_into().ok())
+                    .ok_or_else(|| {
+                        AppError::Encryption(format!("key {id} must be 32 bytes of hex"))
+                    })?;
+                Ok((id.to_string(), key))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let active_key_id = match std::env::var("ENCRYPTION_ACTIVE_KEY_ID") {
+            Ok(id) => id,
+            Err(_) => keys
+                .first()
+                .map(|(id, _)| id.clone())
+                .ok_or_else(|| AppError::Encryption("ENCRYPTION_KEYS is empty".to_string()))?,
+        };
+
+        Self::new(keys, &active_key_id).map(Some)
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let cipher = &self.keys[&self.active_key_id];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| AppError::Encryption("encryption failed".to_string()))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!(
+            "{PREFIX}{}:{}",
+            self.active_key_id,
+            hex::encode(payload)
+        ))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let Some(encrypted) = value.strip_prefix(PREFIX) else {
+            return Ok(value.to_string());
+        };
+
+        let (key_id, payload) = encrypted
+            .split_once(':')
+            .ok_or_else(|| AppError::Encryption("malformed encrypted value".to_string()))?;
+        let cipher = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| AppError::Encryption(format!("unknown key {key_id}")))?;
+        let payload = hex::decode(payload)
+            .map_err(|_| AppError::Encryption("malformed encrypted value".to_string()))?;
+        if payload.len() < NONCE_LEN {
+            return Err(AppError::Encryption(
+                "malformed encrypted value".to_string(),
+            ));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::Encryption(format!("decryption with key {key_id} failed")))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|_| AppError::Encryption("decrypted value is not UTF-8".to_string()))
+    }
+
+    /// True for values written by [`ColumnCipher::encrypt`].
+    pub fn is_encrypted(value: &str) -> bool {
+        value.starts_with(PREFIX)
+    }
+
+    /// True for plaintext values and values encrypted under a retired key.
+    pub fn needs_reencryption(&self, value: &str) -> bool {
+        match value.strip_prefix(PREFIX) {
+            Some(encrypted) => !encrypted.starts_with(&format!("{}:", self.active_key_id)),
+            None => true,
+        }
+    }
+
+    pub fn reencrypt(&self, value: &str) -> Result<String> {
+        self.encrypt(&self.decrypt(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(active: &str) -> ColumnCipher {
+        ColumnCipher::new(
+            vec![("old".to_string(), [1; 32]), ("new".to_string(), [2; 32])],
+            active,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let cipher = cipher("new");
+        let encrypted = cipher.encrypt("webhook-secret").unwrap();
+        assert!(encrypted.starts_with("enc:v1:new:"));
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "webhook-secret");
+    }
+
+    #[test]
+    fn test_rotation() {
+        let old = cipher("old").encrypt("api-key-secret").unwrap();
+        let cipher = cipher("new");
+
+        assert!(cipher.needs_reencryption(&old));
+        assert!(cipher.needs_reencryption("legacy plaintext"));
+
+        let rotated = cipher.reencrypt(&old).unwrap();
+        assert!(!cipher.needs_reencryption(&rotated));
+        assert_eq!(cipher.decrypt(&rotated).unwrap(), "api-key-secret");
+    }
+
+    #[test]
+    fn test_tampered_value_fails() {
+        let cipher = cipher("new");
+        let mut encrypted = cipher.encrypt("admin-password").unwrap();
+        let last = encrypted.pop().unwrap();
+        encrypted.push(if last == '0' { '1' } else { '0' });
+        assert!(cipher.decrypt(&encrypted).is_err());
+    }
+}
@@ -1,10 +1,15 @@
+use crate::{telemetry::TraceId, version::ApiVersion};
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use thiserror::Error;
+use utoipa::ToSchema;
+
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
 
 pub type Result<T> = std::result::Result<T, AppError>;
 
@@ -13,15 +18,33 @@ pub enum AppError {
     #[error("Block not found: {0}")]
     BlockNotFound(String),
 
+    #[error("Statistics not available for block: {0}")]
+    BlockStatsUnavailable(String),
+
     #[error("Proof not found for block: {0}")]
     ProofNotFound(String),
 
     #[error("Transaction not found: {0}")]
     TransactionNotFound(String),
 
+    #[error("Output not found: {0}")]
+    OutputNotFound(String),
+
+    #[error("Raw transaction not available: {0}")]
+    RawTransactionUnavailable(String),
+
     #[error("Header not found: {0}")]
     HeaderNotFound(String),
 
+    #[error("Not indexed yet: {what}")]
+    NotIndexed {
+        what: String,
+        tip_height: Option<u32>,
+    },
+
+    #[error("Block data pruned: {0}")]
+    BlockPruned(String),
+
     #[error("Invalid block identifier: {0}")]
     InvalidBlockIdentifier(String),
 
@@ -31,12 +54,114 @@ pub enum AppError {
     #[error("Invalid header hash: {0}")]
     InvalidHeaderHash(String),
 
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
     #[error("Invalid query parameter: {0}")]
     InvalidQueryParameter(String),
 
+    #[error("Invalid resume token: {0}")]
+    InvalidResumeToken(String),
+
+    #[error("Cannot resume download: {0}")]
+    ResumeConflict(String),
+
+    #[error("Range not satisfiable: {0}")]
+    RangeNotSatisfiable(String),
+
+    #[error("Feature disabled: {0}")]
+    FeatureDisabled(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Request body exceeds the limit of {0} bytes")]
+    PayloadTooLarge(usize),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("API key not found: {0}")]
+    ApiKeyNotFound(i64),
+
+    #[error("Webhook not found: {0}")]
+    WebhookNotFound(i64),
+
+    #[error("Job not found: {0}")]
+    JobNotFound(i64),
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+
+    #[error("Malformed block: {0}")]
+    MalformedBlock(String),
+
+    #[error("Invalid block: {0}")]
+    InvalidBlock(String),
+
+    #[error("Invalid proof: {0}")]
+    InvalidProof(String),
+
+    #[error("Malformed transaction: {0}")]
+    MalformedTransaction(String),
+
+    #[error("Transaction rejected: {0}")]
+    TransactionRejected(String),
+
+    #[error("Upstream unavailable: {0}")]
+    UpstreamUnavailable(String),
+
+    #[error("Invalid idempotency key: {0}")]
+    InvalidIdempotencyKey(String),
+
+    #[error("Idempotency key reused: {0}")]
+    IdempotencyKeyReused(String),
+
+    #[error("Request in progress: {0}")]
+    IdempotencyConflict(String),
+
+    #[error("Conflicting block: {0}")]
+    BlockConflict(String),
+
     #[error("Store error: {0}")]
     Store(#[from] anyhow::Error),
 
+    #[error("Store unavailable: {reason}")]
+    StoreUnavailable {
+        reason: String,
+        retry_after_secs: u64,
+    },
+
+    #[error("Service overloaded")]
+    Overloaded { retry_after_secs: u64 },
+
+    #[error("Service under maintenance: {message}")]
+    Maintenance {
+        message: String,
+        retry_after_secs: u64,
+    },
+
+    #[error("Too many concurrent {resource}")]
+    TooManyRequests {
+        resource: String,
+        retry_after_secs: u64,
+    },
+
+    #[error("Rate limit exceeded")]
+    RateLimited { retry_after_secs: u64 },
+
+    #[error("Quota exceeded: {quota}")]
+    QuotaExceeded {
+        quota: String,
+        retry_after_secs: u64,
+    },
+
+    #[error("Request timed out after {0}s")]
+    Timeout(u64),
+
     #[error("JSON serialization error: {0}")]
     Json(#[from] serde_json::Error),
 
@@ -47,18 +172,209 @@ pub enum AppError {
     Internal,
 }
 
+/// Variant of the [`AppError`] an error response was built from, attached to
+/// the response's extensions so metrics can count errors by kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorKind(pub &'static str);
+
+impl AppError {
+    /// Stable `snake_case` name of the variant, used as a metric label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::BlockNotFound(_) => "block_not_found",
+            AppError::BlockStatsUnavailable(_) => "block_stats_unavailable",
+            AppError::ProofNotFound(_) => "proof_not_found",
+            AppError::TransactionNotFound(_) => "transaction_not_found",
+            AppError::OutputNotFound(_) => "output_not_found",
+            AppError::RawTransactionUnavailable(_) => "raw_transaction_unavailable",
+            AppError::HeaderNotFound(_) => "header_not_found",
+            AppError::NotIndexed { .. } => "not_indexed",
+            AppError::BlockPruned(_) => "block_pruned",
+            AppError::InvalidBlockIdentifier(_) => "invalid_block_identifier",
+            AppError::InvalidTransactionId(_) => "invalid_transaction_id",
+            AppError::InvalidHeaderHash(_) => "invalid_header_hash",
+            AppError::InvalidAddress(_) => "invalid_address",
+            AppError::InvalidQueryParameter(_) => "invalid_query_parameter",
+            AppError::InvalidResumeToken(_) => "invalid_resume_token",
+            AppError::ResumeConflict(_) => "resume_conflict",
+            AppError::RangeNotSatisfiable(_) => "range_not_satisfiable",
+            AppError::FeatureDisabled(_) => "feature_disabled",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::ApiKeyNotFound(_) => "api_key_not_found",
+            AppError::WebhookNotFound(_) => "webhook_not_found",
+            AppError::JobNotFound(_) => "job_not_found",
+            AppError::Encryption(_) => "encryption",
+            AppError::InvalidConfiguration(_) => "invalid_configuration",
+            AppError::MalformedBlock(_) => "malformed_block",
+            AppError::InvalidBlock(_) => "invalid_block",
+            AppError::InvalidProof(_) => "invalid_proof",
+            AppError::MalformedTransaction(_) => "malformed_transaction",
+            AppError::TransactionRejected(_) => "transaction_rejected",
+            AppError::UpstreamUnavailable(_) => "upstream_unavailable",
+            AppError::InvalidIdempotencyKey(_) => "invalid_idempotency_key",
+            AppError::IdempotencyKeyReused(_) => "idempotency_key_reused",
+            AppError::IdempotencyConflict(_) => "idempotency_conflict",
+            AppError::BlockConflict(_) => "block_conflict",
+            AppError::Store(_) => "store",
+            AppError::StoreUnavailable { .. } => "store_unavailable",
+            AppError::Overloaded { .. } => "overloaded",
+            AppError::Maintenance { .. } => "maintenance",
+            AppError::TooManyRequests { .. } => "too_many_requests",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::QuotaExceeded { .. } => "quota_exceeded",
+            AppError::Timeout(_) => "timeout",
+            AppError::Json(_) => "json",
+            AppError::Io(_) => "io",
+            AppError::Internal => "internal",
+        }
+    }
+
+    /// Stable `SCREAMING_SNAKE_CASE` code for clients to branch on, such as
+    /// `NOT_INDEXED`.
+    pub fn code(&self) -> String {
+        self.kind().to_ascii_uppercase()
+    }
+
+    /// Machine-readable facts about the error beyond its message, if any.
+    fn details(&self) -> Option<Value> {
+        match self {
+            AppError::NotIndexed { tip_height, .. } => Some(json!({ "tip_height": tip_height })),
+            AppError::PayloadTooLarge(limit) => Some(json!({ "limit_bytes": limit })),
+            AppError::Timeout(secs) => Some(json!({ "timeout_secs": secs })),
+            AppError::StoreUnavailable {
+                retry_after_secs, ..
+            }
+            | AppError::Overloaded { retry_after_secs }
+            | AppError::Maintenance {
+                retry_after_secs, ..
+            }
+            | AppError::RateLimited { retry_after_secs }
+            | AppError::TooManyRequests {
+                retry_after_secs, ..
+            }
+            | AppError::QuotaExceeded {
+                retry_after_secs, ..
+            } => Some(json!({ "retry_after_secs": retry_after_secs })),
+            _ => None,
+        }
+    }
+}
+
+/// RFC 7807 problem document every error response carries, served as
+/// `application/problem+json`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    /// Always `about:blank`: `code` identifies the problem instead.
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Reason phrase of the HTTP status.
+    pub title: String,
+    pub status: u16,
+    /// Human-readable explanation; wording may change between releases.
+    pub detail: String,
+    /// Stable machine-readable code, such as `BLOCK_NOT_FOUND` or
+    /// `NOT_INDEXED`.
+    pub code: String,
+    /// Trace id of the request, as echoed in `X-Trace-Id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Facts specific to the code, such as `tip_height` for `NOT_INDEXED`
+    /// or `retry_after_secs` for throttling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<Value>,
+    /// Same as `detail`, kept for clients written before problem documents;
+    /// left out by `/v2`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let kind = ErrorKind(self.kind());
+        let retry_after = match &self {
+            AppError::StoreUnavailable {
+                retry_after_secs, ..
+            }
+            | AppError::Overloaded { retry_after_secs }
+            | AppError::Maintenance {
+                retry_after_secs, ..
+            }
+            | AppError::RateLimited { retry_after_secs }
+            | AppError::TooManyRequests {
+                retry_after_secs, ..
+            }
+            | AppError::QuotaExceeded {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
+        };
+        let details = self.details();
+        let code = self.code();
         let (status, error_message) = match self {
             AppError::BlockNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::BlockStatsUnavailable(_) => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::ProofNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::TransactionNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::OutputNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::RawTransactionUnavailable(_) => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::HeaderNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::NotIndexed { .. } => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::BlockPruned(_) => (StatusCode::GONE, self.to_string()),
             AppError::InvalidBlockIdentifier(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::InvalidTransactionId(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::InvalidHeaderHash(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::InvalidAddress(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::InvalidQueryParameter(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::InvalidResumeToken(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::ResumeConflict(_) => (StatusCode::CONFLICT, self.to_string()),
+            AppError::RangeNotSatisfiable(_) => {
+                (StatusCode::RANGE_NOT_SATISFIABLE, self.to_string())
+            }
+            AppError::FeatureDisabled(_) => (StatusCode::NOT_IMPLEMENTED, self.to_string()),
+            AppError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
+            AppError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()),
+            AppError::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::ApiKeyNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::WebhookNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::JobNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::Encryption(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Encryption error".to_string(),
+            ),
+            AppError::InvalidConfiguration(_) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, self.to_string())
+            }
+            AppError::MalformedBlock(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::InvalidBlock(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
+            AppError::InvalidProof(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
+            AppError::MalformedTransaction(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::TransactionRejected(_) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, self.to_string())
+            }
+            // The cause may name the upstream, which stays private
+            AppError::UpstreamUnavailable(_) => {
+                (StatusCode::BAD_GATEWAY, "Upstream unavailable".to_string())
+            }
+            AppError::InvalidIdempotencyKey(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::IdempotencyKeyReused(_) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, self.to_string())
+            }
+            AppError::IdempotencyConflict(_) => (StatusCode::CONFLICT, self.to_string()),
+            AppError::BlockConflict(_) => (StatusCode::CONFLICT, self.to_string()),
             AppError::Store(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Store error".to_string()),
+            AppError::StoreUnavailable { .. } => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Store temporarily unavailable".to_string(),
+            ),
+            AppError::Overloaded { .. } => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
+            AppError::Maintenance { .. } => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
+            AppError::TooManyRequests { .. } => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+            AppError::RateLimited { .. } => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+            AppError::QuotaExceeded { .. } => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+            AppError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, self.to_string()),
             AppError::Json(_) => (StatusCode::INTERNAL_SERVER_ERROR, "JSON error".to_string()),
             AppError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, "IO error".to_string()),
             AppError::Internal => (
@@ -67,11 +383,27 @@ impl IntoResponse for AppError {
             ),
         };
 
-        let body = Json(json!({
-            "error": error_message,
-            "status": status.as_u16()
-        }));
+        let body = ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: error_message.clone(),
+            code,
+            request_id: TraceId::current().map(|TraceId(trace_id)| trace_id),
+            details,
+            error: (ApiVersion::current() == ApiVersion::V1).then_some(error_message),
+        };
 
-        (status, body).into_response()
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(PROBLEM_JSON_CONTENT_TYPE),
+        );
+        if let Some(secs) = retry_after {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        let mut response = (status, headers, Json(body)).into_response();
+        response.extensions_mut().insert(kind);
+        response
     }
 }
@@ -0,0 +1,73 @@
+use crate::model::BlockDetail;
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// Strong ETag for a block response. The block hash pins the header and the
+/// txids; the flags cover the fields that can change for the same hash and
+/// the `include_txids` variant of the body.
+pub fn block_etag(block: &BlockDetail, include_txids: bool) -> String {
+    let mut tag = format!("\"{}", block.summary.hash);
+    if block.summary.verified {
+        tag.push_str("-v");
+    }
+    if block.pruned {
+        tag.push_str("-p");
+    }
+    if block.orphaned {
+        tag.push_str("-o");
+    }
+    if !include_txids {
+        tag.push_str("-n");
+    }
+    tag.push('"');
+    tag
+}
+
+/// Strong ETag for a proof file, from the SHA-256 of its content.
+pub fn proof_etag(checksum: &str) -> String {
+    format!("\"sha256-{checksum}\"")
+}
+
+/// Whether the request's `If-None-Match` names `etag`, so the client's copy
+/// is current. Uses the weak comparison RFC 9110 prescribes for this header.
+pub fn is_fresh(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// `304 Not Modified` carrying the validator the client matched.
+pub fn not_modified(etag: &str) -> Response {
+    let mut response = StatusCode::NOT_MODIFIED.into_response();
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_fresh() {
+        let etag = proof_etag("abc");
+        let request = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_NONE_MATCH, value.parse().unwrap());
+            headers
+        };
+
+        assert!(!is_fresh(&HeaderMap::new(), &etag));
+        assert!(is_fresh(&request("\"sha256-abc\""), &etag));
+        assert!(is_fresh(&request("\"other\", W/\"sha256-abc\""), &etag));
+        assert!(is_fresh(&request("*"), &etag));
+        assert!(!is_fresh(&request("\"sha256-abd\""), &etag));
+    }
+}
@@ -0,0 +1,527 @@
+use crate::{
+    error::{AppError, Result},
+    hash::BlockHash,
+    sla::ProofMilestone,
+};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query,
+    },
+    http::HeaderMap,
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        Response,
+    },
+};
+use futures_util::{stream, Stream, StreamExt};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::{BTreeSet, VecDeque},
+    convert::Infallible,
+    fmt,
+    str::FromStr,
+    sync::Mutex,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::debug;
+use utoipa::{IntoParams, ToSchema};
+
+/// Events held for subscribers that fall behind and for clients resuming
+/// with `Last-Event-ID`; those further behind are told how many they missed.
+const EVENT_BUFFER: usize = 1024;
+
+static EVENTS: Lazy<EventBus> = Lazy::new(EventBus::new);
+
+/// An event with its position in the feed. Ids count up from 1 since the
+/// service started, so they don't carry over a restart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Published {
+    pub id: u64,
+    pub event: Event,
+}
+
+struct EventBus {
+    sender: broadcast::Sender<Published>,
+    /// The last [`EVENT_BUFFER`] events, and the id the next one gets.
+    recent: Mutex<(VecDeque<Published>, u64)>,
+}
+
+impl EventBus {
+    fn new() -> Self {
+        Self {
+            sender: broadcast::channel(EVENT_BUFFER).0,
+            recent: Mutex::new((VecDeque::with_capacity(EVENT_BUFFER), 1)),
+        }
+    }
+}
+
+/// Something that changed in the index, pushed to subscribers once the write
+/// behind it has committed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A block was added to the canonical chain.
+    BlockIndexed { height: u32, hash: BlockHash },
+    /// A proof became available for the block.
+    ProofGenerated { height: u32 },
+    /// The block was marked verified.
+    ProofVerified { height: u32 },
+    /// Blocks from `fork_height` up were replaced by another branch; the new
+    /// ones follow as `block_indexed`.
+    Reorg { fork_height: u32, depth: u32 },
+    /// Proving the block failed.
+    JobFailed { height: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    BlockIndexed,
+    ProofGenerated,
+    ProofVerified,
+    Reorg,
+    JobFailed,
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::BlockIndexed { .. } => EventKind::BlockIndexed,
+            Event::ProofGenerated { .. } => EventKind::ProofGenerated,
+            Event::ProofVerified { .. } => EventKind::ProofVerified,
+            Event::Reorg { .. } => EventKind::Reorg,
+            Event::JobFailed { .. } => EventKind::JobFailed,
+        }
+    }
+}
+
+impl From<ProofMilestone> for Event {
+    fn from(milestone: ProofMilestone) -> Self {
+        match milestone {
+            ProofMilestone::Proved { height, .. } => Event::ProofGenerated { height },
+            ProofMilestone::Verified { height, .. } => Event::ProofVerified { height },
+        }
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "block_indexed" => Ok(EventKind::BlockIndexed),
+            "proof_generated" => Ok(EventKind::ProofGenerated),
+            "proof_verified" => Ok(EventKind::ProofVerified),
+            "reorg" => Ok(EventKind::Reorg),
+            "job_failed" => Ok(EventKind::JobFailed),
+            _ => Err(AppError::InvalidQueryParameter(format!(
+                "Unknown event type: {s}"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EventKind::BlockIndexed => "block_indexed",
+            EventKind::ProofGenerated => "proof_generated",
+            EventKind::ProofVerified => "proof_verified",
+            EventKind::Reorg => "reorg",
+            EventKind::JobFailed => "job_failed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Hands `event` to every current subscriber. Nobody listening is fine.
+pub fn publish(event: Event) {
+    debug!(?event, "Publishing event");
+    // Sending under the lock keeps ids in order and lets `resume` subscribe
+    // without a gap or overlap with the history it replays
+    let mut recent = EVENTS.recent.lock().expect("event lock poisoned");
+    let (history, next_id) = &mut *recent;
+    let published = Published {
+        id: *next_id,
+        event,
+    };
+    *next_id += 1;
+    if history.len() == EVENT_BUFFER {
+        history.pop_front();
+    }
+    history.push_back(published.clone());
+    let _ = EVENTS.sender.send(published);
+}
+
+/// A receiver of every event published from now on.
+pub fn subscribe() -> broadcast::Receiver<Published> {
+    EVENTS.sender.subscribe()
+}
+
+/// Where a client that last saw `last_id` picks up again.
+pub struct Resumed {
+    /// Events after `last_id` still held, oldest first.
+    pub missed: Vec<Published>,
+    /// Events after `last_id` no longer held.
+    pub skipped: u64,
+    /// Everything published after `missed`.
+    pub receiver: broadcast::Receiver<Published>,
+}
+
+/// Replays what was published after `last_id` and continues from there. An
+/// id this run never issued, as from before a restart, starts afresh.
+pub fn resume(last_id: u64) -> Resumed {
+    let recent = EVENTS.recent.lock().expect("event lock poisoned");
+    let (history, next_id) = &*recent;
+    let receiver = EVENTS.sender.subscribe();
+    if last_id >= *next_id {
+        return Resumed {
+            missed: Vec::new(),
+            skipped: 0,
+            receiver,
+        };
+    }
+    let oldest = history.front().map_or(*next_id, |published| published.id);
+    Resumed {
+        missed: history
+            .iter()
+            .filter(|published| published.id > last_id)
+            .cloned()
+            .collect(),
+        skipped: oldest.saturating_sub(last_id + 1),
+        receiver,
+    }
+}
+
+/// Event types a subscriber wants; empty means all of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter(BTreeSet<EventKind>);
+
+impl EventFilter {
+    pub fn new(kinds: impl IntoIterator<Item = EventKind>) -> Self {
+        Self(kinds.into_iter().collect())
+    }
+
+    /// Parses a comma-separated list such as `block_indexed,reorg`.
+    pub fn parse(list: &str) -> Result<Self> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|kind| !kind.is_empty())
+            .map(str::parse)
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    pub fn allows(&self, event: &Event) -> bool {
+        self.0.is_empty() || self.0.contains(&event.kind())
+    }
+
+    pub fn kinds(&self) -> Vec<EventKind> {
+        self.0.iter().copied().collect()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct EventsQuery {
+    /// Comma-separated event types to receive; all when omitted.
+    pub events: Option<String>,
+}
+
+/// Message a WebSocket client sends to replace its subscription.
+#[derive(Debug, Deserialize)]
+struct Subscribe {
+    subscribe: Vec<EventKind>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/ws",
+    tag = "events",
+    params(EventsQuery),
+    responses(
+        (status = 101, description = "WebSocket pushing each `Event` as a JSON text message. Send `{\"subscribe\": [...]}` to change which types arrive; it is acknowledged with `{\"type\": \"subscribed\"}`"),
+        (status = 400, description = "Unknown event type"),
+    )
+)]
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Query(query): Query<EventsQuery>,
+) -> Result<Response> {
+    let filter = EventFilter::parse(query.events.as_deref().unwrap_or_default())?;
+    // Subscribe before upgrading so nothing published meanwhile is missed
+    let events = subscribe();
+    Ok(ws.on_upgrade(move |socket| serve(socket, events, filter)))
+}
+
+async fn serve(
+    mut socket: WebSocket,
+    mut events: broadcast::Receiver<Published>,
+    mut filter: EventFilter,
+) {
+    loop {
+        let reply = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<Subscribe>(&text) {
+                    Ok(Subscribe { subscribe }) => {
+                        filter = EventFilter::new(subscribe);
+                        json!({ "type": "subscribed", "events": filter.kinds() })
+                    }
+                    Err(e) => json!({ "type": "error", "error": format!("Invalid message: {e}") }),
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by the socket itself
+                Some(Ok(_)) => continue,
+            },
+            event = events.recv() => match event {
+                Ok(published) if filter.allows(&published.event) => json!(published.event),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => json!({ "type": "lagged", "skipped": skipped }),
+                Err(RecvError::Closed) => break,
+            },
+        };
+        if socket.send(Message::Text(reply.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    tag = "events",
+    params(
+        EventsQuery,
+        ("Last-Event-ID" = Option<u64>, Header, description = "Id of the last event received; the events since are replayed first"),
+    ),
+    responses(
+        (status = 200, description = "`text/event-stream` of the same events as `/v1/ws`, each with its `id` and its type as the event name. Events no longer held for a resume are announced by a `lagged` event with the number `skipped`"),
+        (status = 400, description = "Unknown event type"),
+    )
+)]
+pub async fn sse_handler(
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = std::result::Result<SseEvent, Infallible>>>> {
+    let filter = EventFilter::parse(query.events.as_deref().unwrap_or_default())?;
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok());
+    let Resumed {
+        missed,
+        skipped,
+        receiver,
+    } = match last_id {
+        Some(last_id) => resume(last_id),
+        None => Resumed {
+            missed: Vec::new(),
+            skipped: 0,
+            receiver: subscribe(),
+        },
+    };
+
+    let replay: Vec<_> = (skipped > 0)
+        .then(|| lagged_event(skipped))
+        .into_iter()
+        .chain(
+            missed
+                .iter()
+                .filter(|published| filter.allows(&published.event))
+                .map(sse_event),
+        )
+        .map(Ok)
+        .collect();
+    let live = stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(published) if filter.allows(&published.event) => sse_event(&published),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => lagged_event(skipped),
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok(event), (receiver, filter)));
+        }
+    });
+    Ok(Sse::new(stream::iter(replay).chain(live)).keep_alive(KeepAlive::default()))
+}
+
+fn sse_event(published: &Published) -> SseEvent {
+    SseEvent::default()
+        .id(published.id.to_string())
+        .event(published.event.kind().to_string())
+        .data(json!(published.event).to_string())
+}
+
+fn lagged_event(skipped: u64) -> SseEvent {
+    SseEvent::default()
+        .event("lagged")
+        .data(json!({ "skipped": skipped }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::Value;
+    use tokio_tungstenite::tungstenite;
+
+    #[test]
+    fn test_event_filter() {
+        let block = Event::BlockIndexed {
+            height: 1,
+            hash: BlockHash::from_byte_array([1; 32]),
+        };
+        let reorg = Event::Reorg {
+            fork_height: 1,
+            depth: 1,
+        };
+        assert!(EventFilter::default().allows(&block));
+        let filter = EventFilter::parse("reorg, proof_verified").unwrap();
+        assert!(filter.allows(&reorg) && !filter.allows(&block));
+        assert!(matches!(
+            EventFilter::parse("reorg,blocks"),
+            Err(AppError::InvalidQueryParameter(_))
+        ));
+        assert_eq!(
+            serde_json::to_value(&reorg).unwrap(),
+            json!({ "type": "reorg", "fork_height": 1, "depth": 1 })
+        );
+    }
+
+    /// The next message that is not an event published by another test,
+    /// which all use heights far below these.
+    async fn next_message<S>(socket: &mut S) -> Value
+    where
+        S: futures_util::Stream<Item = tungstenite::Result<tungstenite::Message>> + Unpin,
+    {
+        loop {
+            if let tungstenite::Message::Text(text) = socket.next().await.unwrap().unwrap() {
+                let json: Value = serde_json::from_str(&text).unwrap();
+                if json["height"]
+                    .as_u64()
+                    .is_none_or(|height| height > 4_000_000)
+                {
+                    return json;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ws_subscription() {
+        let app = Router::new().route("/v1/ws", get(ws_handler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let url = format!("ws://{addr}/v1/ws?events=proof_generated");
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        publish(Event::ProofVerified { height: 4_000_001 });
+        publish(Event::ProofGenerated { height: 4_000_002 });
+        assert_eq!(
+            next_message(&mut socket).await,
+            json!({ "type": "proof_generated", "height": 4_000_002 })
+        );
+
+        let subscribe = json!({ "subscribe": ["proof_verified"] }).to_string();
+        socket
+            .send(tungstenite::Message::Text(subscribe))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_message(&mut socket).await,
+            json!({ "type": "subscribed", "events": ["proof_verified"] })
+        );
+        publish(Event::ProofGenerated { height: 4_000_003 });
+        publish(Event::ProofVerified { height: 4_000_004 });
+        assert_eq!(
+            next_message(&mut socket).await,
+            json!({ "type": "proof_verified", "height": 4_000_004 })
+        );
+
+        let response = reqwest::get(format!("http://{addr}/v1/ws?events=blocks"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    /// Publishes `events` and returns the ids they were given.
+    fn publish_all(events: &[Event]) -> Vec<u64> {
+        let mut receiver = subscribe();
+        events.iter().cloned().for_each(publish);
+        let mut ids = Vec::new();
+        while ids.len() < events.len() {
+            if let Ok(published) = receiver.try_recv() {
+                if events.contains(&published.event) {
+                    ids.push(published.id);
+                }
+            }
+        }
+        ids
+    }
+
+    #[test]
+    fn test_resume() {
+        let events = [
+            Event::ProofVerified { height: 5_000_001 },
+            Event::ProofGenerated { height: 5_000_002 },
+            Event::ProofVerified { height: 5_000_003 },
+        ];
+        let ids = publish_all(&events);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let resumed = resume(ids[0]);
+        let missed: Vec<_> = resumed
+            .missed
+            .iter()
+            .filter(|published| events.contains(&published.event))
+            .collect();
+        assert_eq!(missed.len(), 2);
+        assert_eq!(missed[0].id, ids[1]);
+        assert_eq!(missed[1].event, events[2]);
+
+        // Ids never issued, as after a restart, replay nothing
+        let resumed = resume(u64::MAX - 1);
+        assert!(resumed.missed.is_empty());
+        assert_eq!(resumed.skipped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sse_resume() {
+        let app = Router::new().route("/v1/events", get(sse_handler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let events = [
+            Event::ProofVerified { height: 6_000_001 },
+            Event::ProofGenerated { height: 6_000_002 },
+            Event::ProofVerified { height: 6_000_003 },
+        ];
+        let ids = publish_all(&events);
+        let mut response = reqwest::Client::new()
+            .get(format!("http://{addr}/v1/events?events=proof_verified"))
+            .header("Last-Event-ID", ids[0].to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let mut body = String::new();
+        while !body.contains("6000003") {
+            let chunk = response.chunk().await.unwrap().unwrap();
+            body.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        assert!(body.contains(&format!(
+            "id: {}\nevent: proof_verified\ndata: {}",
+            ids[2],
+            json!(events[2])
+        )));
+        assert!(!body.contains("6000001") && !body.contains("6000002"));
+    }
+}
@@ -1,13 +1,37 @@
 use crate::{
-    database::Database,
+    address,
+    audit::{self, Actor},
+    auth::ApiKeyIdentity,
+    broadcast::Broadcaster,
+    cache_control::CacheControlConfig,
+    capacity::{self, CapacityConfig},
+    chain, config,
     error::{AppError, Result},
-    model::{BlocksQuery, BlocksResponse, HeaderStatus, HealthStatus, TransactionStatus},
+    etag,
+    hash::{BlockHash, BlockId, Txid},
+    load::{self, LoadShedConfig},
+    model::{
+        AddressTransactionsResponse, AddressUtxosResponse, BlockDetail, BlockDetailQuery,
+        BlockStats, BlockTxidsQuery, BlocksQuery, BlocksResponse, ChainInfo, CircuitState,
+        ComponentHealth, CoverageQuery, HeaderStatus, HealthComponents, HealthState, HealthStatus,
+        NetworkStats, OrphansQuery, OrphansResponse, OutputStatus, ProofJob, ProofLatencyQuery,
+        ProofLatencyStats, ProofUpload, ProofsQuery, ProofsResponse, RawEncoding, RawQuery,
+        ReadinessStatus, TransactionBroadcast, TransactionDetail, UsageResponse, VersionInfo,
+    },
+    pagination, proof, proto,
+    raw_block::{self, RawBlock},
+    sla::{self, ProofSlaConfig},
+    staleness, storage,
+    store::{BlockStore, ProofBacklog},
+    telemetry::{self, Metrics},
+    usage, validation, version,
 };
 use axum::{
-    extract::{Path, Query, State},
-    http::{header, StatusCode},
+    body::{Body, Bytes},
+    extract::{OriginalUri, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use chrono::Utc;
 use std::sync::Arc;
@@ -19,20 +43,115 @@ use validator::Validate;
     paths(
         get_blocks,
         get_block_by_identifier,
+        submit_block,
+        get_block_stats,
+        get_block_txids,
         get_block_proof,
+        upload_block_proof,
         get_transaction_status,
+        broadcast_transaction,
+        get_transaction_detail,
+        get_raw_transaction,
+        get_output_status,
+        get_address_transactions,
+        get_address_utxos,
         get_header_status,
+        get_raw_header,
+        get_chain_info,
+        get_version,
+        get_orphans,
+        get_network_stats,
+        get_proof_latency,
+        get_proofs,
+        get_proof_coverage,
+        get_proof_job,
+        get_usage,
+        crate::events::ws_handler,
+        crate::events::sse_handler,
+        crate::rpc::rpc_handler,
+        crate::v2::get_blocks,
+        crate::v2::get_orphans,
+        crate::v2::get_block_by_identifier,
+        crate::v2::get_transaction_status,
+        crate::v2::get_transaction_detail,
+        crate::v2::get_output_status,
+        crate::v2::get_address_utxos,
+        crate::v2::get_proofs,
         health_check,
+        readiness_check,
     ),
     components(
         schemas(
+            crate::error::ProblemDetails,
+            crate::events::Event,
+            crate::events::EventKind,
+            crate::hash::BlockHash,
+            crate::hash::Txid,
+            crate::hash::Wtxid,
             crate::model::BlockSummary,
             crate::model::BlockDetail,
+            crate::model::CoinbaseInfo,
+            crate::model::BlockStats,
             crate::model::BlocksResponse,
+            crate::model::BlockTxidsResponse,
             crate::model::TransactionStatus,
+            crate::model::TransactionDetail,
+            crate::model::TransactionInput,
+            crate::model::TransactionOutput,
+            crate::model::ScriptType,
+            crate::model::OutputSpend,
+            crate::model::OutputStatus,
+            crate::model::AddressTransaction,
+            crate::model::AddressTransactionsResponse,
+            crate::model::Utxo,
+            crate::model::AddressUtxosResponse,
             crate::model::HeaderStatus,
+            crate::model::BlockTombstone,
+            crate::model::OrphansResponse,
+            crate::model::ChainInfo,
+            crate::model::ChainAnchor,
+            crate::model::ConsensusParams,
+            crate::model::VersionInfo,
+            crate::model::NetworkStats,
+            crate::model::LatencyPercentiles,
+            crate::model::ProofLatencyStats,
+            crate::model::ProofRecord,
+            crate::model::ProofsResponse,
+            crate::model::ProofUpload,
+            crate::model::TransactionBroadcast,
+            crate::model::ProofGap,
+            crate::model::ProofGapReason,
+            crate::model::ProofCoverageResponse,
+            crate::model::ProofJob,
+            crate::model::JobStatus,
             crate::model::HealthStatus,
+            crate::model::HealthState,
+            crate::model::HealthComponents,
+            crate::model::ComponentHealth,
+            crate::model::ReadinessStatus,
+            crate::model::CircuitState,
             crate::model::BlocksQuery,
+            crate::model::RawEncoding,
+            crate::model::SortOrder,
+            crate::model::PageDirection,
+            crate::model::UsageCounts,
+            crate::model::UsageWindow,
+            crate::model::UsageResponse,
+            crate::v2::PipelineStatus,
+            crate::v2::BlockSummaryV2,
+            crate::v2::BlockDetailV2,
+            crate::v2::CoinbaseInfoV2,
+            crate::v2::BlocksResponseV2,
+            crate::v2::BlockTombstoneV2,
+            crate::v2::OrphansResponseV2,
+            crate::v2::TransactionStatusV2,
+            crate::v2::TransactionOutputV2,
+            crate::v2::TransactionDetailV2,
+            crate::v2::OutputStatusV2,
+            crate::v2::UtxoV2,
+            crate::v2::AddressUtxosResponseV2,
+            crate::v2::ProofRecordV2,
+            crate::v2::ProofsResponseV2,
         )
     ),
     tags(
@@ -40,6 +159,11 @@ use validator::Validate;
         (name = "proofs", description = "STARK proof operations"),
         (name = "transactions", description = "Transaction verification"),
         (name = "headers", description = "Block header verification"),
+        (name = "addresses", description = "Address index lookups"),
+        (name = "usage", description = "API key usage and quotas"),
+        (name = "events", description = "Live index events"),
+        (name = "rpc", description = "Bitcoin Core compatible JSON-RPC"),
+        (name = "v2", description = "Resources with satoshi amounts, pipeline status and plain problem documents"),
         (name = "health", description = "Service health checks"),
     )
 )]
@@ -48,67 +172,387 @@ pub struct ApiDoc;
 #[utoipa::path(
     get,
     path = "/v1/blocks",
+    params(
+        ("limit" = Option<u32>, Query, description = "Number of blocks to return (1-50, default 20)"),
+        ("cursor" = Option<u32>, Query, description = "Continue after this height (below it for `desc`, above it for `asc`), from `next_cursor`"),
+        ("direction" = Option<PageDirection>, Query, description = "`next` (default) for the page after `cursor`, `prev` with `prev_cursor` for the page before it; `prev` without a cursor returns the last page"),
+        ("order" = Option<SortOrder>, Query, description = "`desc` (default) for newest first, `asc` for oldest first"),
+        ("verified" = Option<bool>, Query, description = "Only return blocks with this verification status"),
+        ("from_timestamp" = Option<i64>, Query, description = "Only return blocks at or after this unix timestamp"),
+        ("to_timestamp" = Option<i64>, Query, description = "Only return blocks at or before this unix timestamp"),
+    ),
     responses(
-        (status = 200, description = "List of blocks", body = BlocksResponse),
+        (status = 200, description = "List of blocks; may be smaller than requested under load (`server_limited`). `Accept: application/x-protobuf` returns a `raito.v1.BlockSummaryPage`", body = BlocksResponse),
         (status = 400, description = "Invalid query parameters"),
     )
 )]
 pub async fn get_blocks(
-    State(db): State<Arc<Database>>,
+    State(db): State<Arc<dyn BlockStore>>,
     Query(query): Query<BlocksQuery>,
-) -> Result<Json<BlocksResponse>> {
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let response = list_blocks(&*db, &query).await?;
+    let (next, prev) = (response.next_cursor, response.prev_cursor);
+    let body = if proto::is_requested(&headers) {
+        proto::response(&proto::BlockSummaryPage::from(&response))
+    } else {
+        Json(response).into_response()
+    };
+    Ok(pagination::with_links(body, &uri, next, prev))
+}
+
+/// The page of blocks `query` asks for, shrunk while the service is under
+/// load. Shared by every API version's block listing.
+pub(crate) async fn list_blocks(
+    db: &dyn BlockStore,
+    query: &BlocksQuery,
+) -> Result<BlocksResponse> {
     query
         .validate()
         .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+    if let (Some(from), Some(to)) = (query.from_timestamp, query.to_timestamp) {
+        if from > to {
+            return Err(AppError::InvalidQueryParameter(
+                "from_timestamp must not be after to_timestamp".to_string(),
+            ));
+        }
+    }
 
-    let limit = query.limit.unwrap_or(20);
-    let response = db.get_blocks(limit, query.cursor).await?;
+    let requested = query.limit.unwrap_or(20);
+    let load_shed = LoadShedConfig::global();
+    let limited = load_shed.limit_page(requested, load::in_flight_requests());
 
-    Ok(Json(response))
+    let mut response = db
+        .get_blocks(
+            limited.unwrap_or(requested),
+            query.cursor,
+            query.direction.unwrap_or_default(),
+            query.order.unwrap_or_default(),
+            &query.filter(),
+        )
+        .await?;
+    if limited.is_some() {
+        response.server_limited = true;
+        response.retry_after = Some(load_shed.retry_after_secs);
+    }
+    Ok(response)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/blocks/orphans",
+    tag = "blocks",
+    params(
+        ("limit" = Option<u32>, Query, description = "Number of orphans to return (1-50, default 20)"),
+        ("cursor" = Option<i64>, Query, description = "Resume after this position, from `next_cursor`"),
+    ),
+    responses(
+        (status = 200, description = "Blocks displaced by reorgs, most recent first", body = OrphansResponse),
+        (status = 400, description = "Invalid query parameters"),
+    )
+)]
+pub async fn get_orphans(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<OrphansQuery>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response> {
+    let orphans = list_orphans(&*db, &query).await?;
+    let next = orphans.next_cursor.filter(|_| orphans.has_next);
+    Ok(pagination::with_links(
+        Json(orphans),
+        &uri,
+        next,
+        None::<i64>,
+    ))
+}
+
+pub(crate) async fn list_orphans(
+    db: &dyn BlockStore,
+    query: &OrphansQuery,
+) -> Result<OrphansResponse> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+    db.get_orphans(query.limit.unwrap_or(20), query.cursor)
+        .await
 }
 
 #[utoipa::path(
     get,
     path = "/v1/blocks/{identifier}",
+    params(
+        ("include_txids" = Option<bool>, Query, description = "Set to `false` to omit `txids` (default true)"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy; answered with 304 while it is current"),
+    ),
     responses(
-        (status = 200, description = "Block details", body = crate::model::BlockDetail),
+        (status = 200, description = "Block details. `Accept: application/x-protobuf` returns a `raito.v1.BlockDetail`", body = crate::model::BlockDetail),
+        (status = 304, description = "The cached copy named by `If-None-Match` is current"),
         (status = 400, description = "Invalid block identifier"),
-        (status = 404, description = "Block not found"),
+        (status = 404, description = "Block not found, or past the indexed tip (`code` is `NOT_INDEXED`)"),
     )
 )]
 pub async fn get_block_by_identifier(
-    State(db): State<Arc<Database>>,
-    Path(identifier): Path<String>,
-) -> Result<Json<crate::model::BlockDetail>> {
-    let block = if let Ok(height) = identifier.parse::<u32>() {
-        db.get_block_by_height(height).await?
-    } else if identifier.len() == 64
-        && identifier
-            .chars()
-            .all(|c| c.is_ascii_hexdigit() || c == '0')
-    {
-        db.get_block_by_hash(&identifier).await?
+    State(db): State<Arc<dyn BlockStore>>,
+    id: BlockId,
+    Query(query): Query<BlockDetailQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let mut block = find_block(&*db, id).await?;
+    let include_txids = query.include_txids.unwrap_or(true);
+    let cache_control = CacheControlConfig::global();
+    let immutable = cache_control.is_block_final(&block, db.tip_height().await?);
+    let etag = etag::block_etag(&block, include_txids);
+
+    let mut response = if etag::is_fresh(&headers, &etag) {
+        etag::not_modified(&etag)
     } else {
-        return Err(AppError::InvalidBlockIdentifier(identifier));
+        if !include_txids {
+            block.txids.clear();
+            block.wtxids.clear();
+        }
+        if proto::is_requested(&headers) {
+            let mut response = proto::response(&proto::BlockDetail::from(&block));
+            if let Ok(etag) = HeaderValue::from_str(&format!("W/{etag}")) {
+                response.headers_mut().insert(header::ETAG, etag);
+            }
+            response
+        } else {
+            ([(header::ETAG, etag)], Json(block)).into_response()
+        }
     };
+    cache_control.apply(&mut response, immutable);
+    Ok(response)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/blocks",
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a retry with the same key and body gets the first response again"),
+    ),
+    request_body(
+        content = String,
+        description = "Serialized block, as hex text or `application/octet-stream` bytes",
+        content_type = "application/octet-stream",
+    ),
+    responses(
+        (status = 201, description = "Block indexed; `Location` names it", body = crate::model::BlockDetail),
+        (status = 200, description = "The block was already indexed", body = crate::model::BlockDetail),
+        (status = 400, description = "The body is not a serialized block"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 409, description = "Another block is indexed at its height and the conflict policy rejects it, or a request with the same `Idempotency-Key` is still running"),
+        (status = 413, description = "Body exceeds `BODY_LIMIT_BLOCK_BYTES`"),
+        (status = 422, description = "The block fails validation or does not extend the indexed chain, or the `Idempotency-Key` was used for another request"),
+    )
+)]
+pub async fn submit_block(
+    State(db): State<Arc<dyn BlockStore>>,
+    actor: Actor,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    let binary = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/octet-stream"));
+    let bytes = if binary {
+        body.to_vec()
+    } else {
+        let text = std::str::from_utf8(&body)
+            .map_err(|_| AppError::MalformedBlock("hex body is not text".to_string()))?;
+        hex::decode(text.trim())
+            .map_err(|e| AppError::MalformedBlock(format!("invalid hex: {e}")))?
+    };
+    let block = RawBlock::decode(&bytes).map_err(AppError::MalformedBlock)?;
+
+    let hash = block.hash();
+    match db.get_block_by_hash(&hash).await {
+        Ok(existing) if !existing.orphaned => return Ok(Json(existing).into_response()),
+        Ok(_) | Err(AppError::BlockNotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    // Placed on its parent when that is indexed, otherwise at the height its
+    // coinbase commits to
+    let height = match db.get_block_by_hash(&block.prev_hash()).await {
+        Ok(parent) => parent.summary.height + 1,
+        Err(AppError::BlockNotFound(_)) => block.coinbase_height().ok_or_else(|| {
+            AppError::InvalidBlock(format!(
+                "block {hash} builds on {}, which is not indexed, and its coinbase gives no height",
+                block.prev_hash()
+            ))
+        })?,
+        Err(e) => return Err(e),
+    };
+    let document = block.to_document(height);
+    validation::check_header(&document, None)
+        .map_err(|rejection| AppError::InvalidBlock(rejection.to_string()))?;
+    db.submit_block(&document).await?;
+    let target = format!("block:{hash}");
+    audit::record(db.as_ref(), &actor, "block.submit", Some(&target)).await;
+
+    let stored = db.get_block_by_hash(&hash).await?;
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/v1/blocks/{hash}"))],
+        Json(stored),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/blocks/{identifier}/stats",
+    responses(
+        (status = 200, description = "Block statistics", body = BlockStats),
+        (status = 400, description = "Invalid block identifier"),
+        (status = 404, description = "Block not found or statistics not available"),
+    )
+)]
+pub async fn get_block_stats(
+    State(db): State<Arc<dyn BlockStore>>,
+    id: BlockId,
+) -> Result<Json<BlockStats>> {
+    let height = resolve_block_height(&*db, id).await?;
+
+    let block_stats = db.get_block_stats(height).await?;
+
+    Ok(Json(block_stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/blocks/{identifier}/txids",
+    params(
+        ("limit" = Option<u32>, Query, description = "Number of txids to return (1-1000, default 100)"),
+        ("cursor" = Option<u32>, Query, description = "Continue after this position in the block, from `next_cursor`"),
+    ),
+    responses(
+        (status = 200, description = "Page of the block's transaction IDs in block order", body = BlockTxidsResponse),
+        (status = 400, description = "Invalid block identifier or query parameters"),
+        (status = 404, description = "Block not found"),
+        (status = 410, description = "The block's transactions have been pruned"),
+    )
+)]
+pub async fn get_block_txids(
+    State(db): State<Arc<dyn BlockStore>>,
+    id: BlockId,
+    Query(query): Query<BlockTxidsQuery>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+    let height = resolve_block_height(&*db, id).await?;
+    let pruned_below = db.pruned_below().await?;
+    if height < pruned_below {
+        return Err(AppError::BlockPruned(format!(
+            "transactions below height {pruned_below} are no longer kept"
+        )));
+    }
+
+    let txids = db
+        .get_block_txids(height, query.limit.unwrap_or(100), query.cursor)
+        .await?;
+
+    let next = txids.next_cursor.filter(|_| txids.has_next);
+    Ok(pagination::with_links(Json(txids), &uri, next, None::<u32>))
+}
+
+/// Looks a block up by height or hash, telling heights past the tip apart
+/// from missing blocks.
+pub(crate) async fn find_block(db: &dyn BlockStore, id: BlockId) -> Result<BlockDetail> {
+    match id {
+        BlockId::Height(height) => {
+            ensure_indexed(db, height).await?;
+            db.get_block_by_height(height).await
+        }
+        BlockId::Hash(hash) => {
+            let hash = normalize_block_hash(db, hash).await?;
+            db.get_block_by_hash(&hash).await
+        }
+    }
+}
+
+async fn resolve_block_height(db: &dyn BlockStore, id: BlockId) -> Result<u32> {
+    match id {
+        BlockId::Height(height) => {
+            ensure_indexed(db, height).await?;
+            Ok(height)
+        }
+        BlockId::Hash(hash) => {
+            let hash = normalize_block_hash(db, hash).await?;
+            let block = db.get_block_by_hash(&hash).await?;
+            // The height now holds another block, so an orphan has none
+            if block.orphaned {
+                return Err(AppError::BlockNotFound(hash.to_string()));
+            }
+            Ok(block.summary.height)
+        }
+    }
+}
 
-    Ok(Json(block))
+/// Rejects heights past the indexed tip as not indexed yet, so clients can
+/// tell a block that is still to come from one that is missing.
+async fn ensure_indexed(db: &dyn BlockStore, height: u32) -> Result<()> {
+    let tip_height = db.tip_height().await?;
+    if tip_height.is_none_or(|tip| height > tip) {
+        return Err(AppError::NotIndexed {
+            what: format!("block {height}"),
+            tip_height,
+        });
+    }
+    Ok(())
+}
+
+/// The byte order `hash` is indexed under. Hashes copied from raw block data
+/// are in internal order, so when only the reversed hash is known that one
+/// is looked up; unknown hashes are kept as given.
+async fn normalize_block_hash(db: &dyn BlockStore, hash: BlockHash) -> Result<BlockHash> {
+    let reversed = hash.reversed();
+    if reversed != hash
+        && !db.block_exists_by_identifier(&BlockId::Hash(hash)).await?
+        && db
+            .block_exists_by_identifier(&BlockId::Hash(reversed))
+            .await?
+    {
+        return Ok(reversed);
+    }
+    Ok(hash)
 }
 
 #[utoipa::path(
     get,
     path = "/v1/blocks/{height}/proof",
+    params(
+        ("Range" = Option<String>, Header, description = "Single byte range to resume a download"),
+        ("X-Resume-Token" = Option<String>, Header, description = "Resume token issued by a previous response"),
+        ("TE" = Option<String>, Header, description = "`trailers` to stream the proof with checksum trailers"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy; answered with 304 while it is current"),
+    ),
     responses(
         (status = 200, description = "STARK proof file"),
-        (status = 404, description = "Block or proof not found"),
+        (status = 206, description = "Requested byte range of the STARK proof file"),
+        (status = 304, description = "The cached copy named by `If-None-Match` is current"),
+        (status = 400, description = "Malformed resume token"),
+        (status = 404, description = "Block or proof not found, or block past the indexed tip (`code` is `NOT_INDEXED`)"),
+        (status = 409, description = "Resume token expired or proof superseded"),
+        (status = 416, description = "Requested range not satisfiable"),
+        (status = 429, description = "Too many proof downloads in progress; retry after `Retry-After`"),
     )
 )]
 pub async fn get_block_proof(
-    State(db): State<Arc<Database>>,
+    State(db): State<Arc<dyn BlockStore>>,
     Path(height): Path<u32>,
+    headers: HeaderMap,
 ) -> Result<Response> {
     // Check if block exists
-    if !db.block_exists_by_identifier(&height.to_string()).await? {
+    ensure_indexed(&*db, height).await?;
+    if !db
+        .block_exists_by_identifier(&BlockId::Height(height))
+        .await?
+    {
         return Err(AppError::BlockNotFound(height.to_string()));
     }
 
@@ -117,55 +561,405 @@ pub async fn get_block_proof(
         return Err(AppError::ProofNotFound(height.to_string()));
     }
 
-    // Load proof file from filesystem
-    let proof_path = format!("data/proofs/{height}.json");
-    let proof_data =
-        std::fs::read(&proof_path).map_err(|_| AppError::ProofNotFound(height.to_string()))?;
+    // A settled block's proof is final, so CDNs may keep it indefinitely
+    let cache_control = CacheControlConfig::global();
+    let immutable = cache_control.is_settled(height, db.tip_height().await?);
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
+    let storage = storage::global();
+    let builder = Response::builder()
+        .header(header::CACHE_CONTROL, cache_control.value(immutable))
         .header(header::CONTENT_TYPE, "application/json")
         .header(
             header::CONTENT_DISPOSITION,
             format!("attachment; filename=\"block_{height}_proof.json\""),
         )
-        .header(header::CONTENT_LENGTH, proof_data.len())
-        .body(proof_data.into())
-        .map_err(|_| AppError::Internal)?;
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    // Clients that accept trailers get the file streamed, with the checksum
+    // computed on the fly and sent after the last chunk. The ETag needs the
+    // checksum up front, so conditional requests take the buffered path.
+    let accepts_trailers = headers
+        .get(header::TE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|te| te.split(',').any(|t| t.trim() == "trailers"));
+    if accepts_trailers
+        && !headers.contains_key(header::RANGE)
+        && !headers.contains_key(header::IF_NONE_MATCH)
+    {
+        let reader = storage
+            .open(height)
+            .await
+            .map_err(|_| AppError::Internal)?
+            .ok_or_else(|| AppError::ProofNotFound(height.to_string()))?;
+        let body = proof::ChecksumBody::new(reader);
+        return builder
+            .status(StatusCode::OK)
+            .header(header::TRAILER, proof::ChecksumBody::TRAILERS)
+            .body(Body::new(body))
+            .map_err(|_| AppError::Internal);
+    }
+
+    // Map the proof from the configured backend; ranges are slices of it
+    let proof_data = storage
+        .map(height)
+        .await
+        .map_err(|_| AppError::Internal)?
+        .ok_or_else(|| AppError::ProofNotFound(height.to_string()))?;
+
+    // The recorded checksum spares hashing the whole proof on every
+    // download. Resumes also check the bytes mapped here, which may already
+    // be a replacement whose checksum is not recorded yet
+    let checksum = match db.proof_checksum(height).await? {
+        Some(checksum) => checksum,
+        None => proof::stored_checksum(height, proof_data.clone()).await?,
+    };
+    let etag = etag::proof_etag(&checksum);
+    if etag::is_fresh(&headers, &etag) {
+        let mut response = etag::not_modified(&etag);
+        cache_control.apply(&mut response, immutable);
+        return Ok(response);
+    }
+    if let Some(token) = headers.get(proof::RESUME_TOKEN_HEADER) {
+        let token = token
+            .to_str()
+            .map_err(|_| AppError::InvalidResumeToken("non-ASCII token".to_string()))?;
+        proof::verify_resume(token, &checksum, proof_data.clone()).await?;
+    }
+
+    let total_len = proof_data.len() as u64;
+    let range = match headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        Some(range) => proof::parse_range(range, total_len)?,
+        None => None,
+    };
+
+    let builder = builder
+        .header(header::ETAG, etag)
+        .header(proof::PROOF_CHECKSUM_HEADER, format!("sha256={checksum}"))
+        .header(
+            proof::RESUME_TOKEN_HEADER,
+            proof::issue_resume_token(&checksum),
+        );
+
+    let response = match range {
+        Some(range) => {
+            let (start, end) = (*range.start(), *range.end());
+            let body = proof_data.slice(start as usize..=end as usize);
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {start}-{end}/{total_len}"),
+                )
+                .header(header::CONTENT_LENGTH, body.len())
+                .body(body.into())
+        }
+        None => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, proof_data.len())
+            .body(proof_data.into()),
+    }
+    .map_err(|_| AppError::Internal)?;
 
     Ok(response)
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/blocks/{height}/proof",
+    params(
+        ("X-Proof-Checksum" = Option<String>, Header, description = "`sha256=<hex>` of the body, as downloads send it; the upload is refused when it differs"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a retry with the same key and body gets the first response again"),
+    ),
+    request_body(
+        content = String,
+        description = "Proof document generated for the block",
+        content_type = "application/json",
+    ),
+    responses(
+        (status = 201, description = "Proof stored; `Location` serves it", body = ProofUpload),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 404, description = "Block not found, or past the indexed tip (`code` is `NOT_INDEXED`)"),
+        (status = 409, description = "A request with the same `Idempotency-Key` is still running"),
+        (status = 413, description = "Body exceeds `BODY_LIMIT_PROOF_UPLOAD_BYTES`"),
+        (status = 422, description = "The proof does not name the indexed block, the body differs from `X-Proof-Checksum`, or the `Idempotency-Key` was used for another request"),
+    )
+)]
+pub async fn upload_block_proof(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(height): Path<u32>,
+    actor: Actor,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    ensure_indexed(&*db, height).await?;
+
+    // Catches a proof corrupted on its way from the prover
+    let checksum = proof::checksum(&body);
+    if let Some(declared) = headers.get(proof::PROOF_CHECKSUM_HEADER) {
+        let declared = declared.to_str().unwrap_or_default();
+        let expected = declared.strip_prefix("sha256=").unwrap_or(declared);
+        if !expected.eq_ignore_ascii_case(&checksum) {
+            return Err(AppError::InvalidProof(format!(
+                "body has sha256 {checksum}, {} declares {declared}",
+                proof::PROOF_CHECKSUM_HEADER
+            )));
+        }
+    }
+    db.upload_proof(height, &body).await?;
+    let target = format!("height:{height}");
+    audit::record(db.as_ref(), &actor, "proof.upload", Some(&target)).await;
+
+    let proof_url = format!("/v1/blocks/{height}/proof");
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, proof_url.clone())],
+        Json(ProofUpload {
+            height,
+            size: body.len() as i64,
+            checksum,
+            proof_url,
+        }),
+    )
+        .into_response())
+}
+
 #[utoipa::path(
     get,
     path = "/v1/tx/{txid}",
     responses(
-        (status = 200, description = "Transaction status", body = TransactionStatus),
+        (status = 200, description = "Transaction status. `Accept: application/x-protobuf` returns a `raito.v1.TransactionStatus`", body = TransactionStatus),
         (status = 400, description = "Invalid transaction ID"),
     )
 )]
 pub async fn get_transaction_status(
-    State(db): State<Arc<Database>>,
+    State(db): State<Arc<dyn BlockStore>>,
     Path(txid): Path<String>,
-) -> Result<Json<TransactionStatus>> {
-    if txid.len() != 64 {
-        return Err(AppError::InvalidTransactionId(format!(
-            "Invalid length: {}, expected 64",
-            txid.len()
-        )));
-    }
+    headers: HeaderMap,
+) -> Result<Response> {
+    let txid = resolve_txid(&*db, &txid).await?;
 
-    if !txid.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Err(AppError::InvalidTransactionId(format!(
-            "Contains non-hex characters: {txid}"
-        )));
+    let status = db.get_transaction_status(&txid).await?;
+
+    if proto::is_requested(&headers) {
+        return Ok(proto::response(&proto::TransactionStatus::from(&status)));
     }
+    Ok(Json(status).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/tx",
+    request_body(
+        content = String,
+        description = "Serialized transaction, as hex text or `application/octet-stream` bytes",
+        content_type = "text/plain",
+    ),
+    responses(
+        (status = 202, description = "Relayed upstream; `Location` tracks its inclusion", body = TransactionBroadcast),
+        (status = 400, description = "The body is not a serialized transaction"),
+        (status = 413, description = "Body exceeds `BODY_LIMIT_JOBS_BYTES`"),
+        (status = 422, description = "The upstream refused the transaction"),
+        (status = 501, description = "No broadcast upstream is configured"),
+        (status = 502, description = "The upstream could not be reached"),
+    )
+)]
+pub async fn broadcast_transaction(
+    State(db): State<Arc<dyn BlockStore>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    let broadcaster = Broadcaster::global().ok_or_else(|| {
+        AppError::FeatureDisabled("no broadcast upstream is configured".to_string())
+    })?;
+    let binary = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/octet-stream"));
+    let bytes = if binary {
+        body.to_vec()
+    } else {
+        let text = std::str::from_utf8(&body)
+            .map_err(|_| AppError::MalformedTransaction("hex body is not text".to_string()))?;
+        hex::decode(text.trim())
+            .map_err(|e| AppError::MalformedTransaction(format!("invalid hex: {e}")))?
+    };
+    let txid = raw_block::decode_transaction(&bytes).map_err(AppError::MalformedTransaction)?;
 
+    broadcaster.broadcast(&hex::encode(&bytes), &txid).await?;
+
+    let status_url = format!("/v1/tx/{txid}");
     let status = db.get_transaction_status(&txid).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, status_url.clone())],
+        Json(TransactionBroadcast {
+            txid,
+            status_url,
+            status,
+        }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/tx/{txid}/detail",
+    responses(
+        (status = 200, description = "Transaction details", body = TransactionDetail),
+        (status = 400, description = "Invalid transaction ID"),
+        (status = 404, description = "Transaction not found, or not indexed yet (`code` is `NOT_INDEXED`)"),
+    )
+)]
+pub async fn get_transaction_detail(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(txid): Path<String>,
+) -> Result<Json<TransactionDetail>> {
+    let txid = resolve_txid(&*db, &txid).await?;
+
+    let detail =
+        check_transaction_miss(&*db, &txid, db.get_transaction_detail(&txid).await).await?;
+
+    Ok(Json(detail))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/tx/{txid}/raw",
+    params(
+        ("format" = Option<RawEncoding>, Query, description = "`binary` (default) or `hex`"),
+    ),
+    responses(
+        (status = 200, description = "Consensus-encoded transaction"),
+        (status = 400, description = "Invalid transaction ID"),
+        (status = 404, description = "Transaction not indexed yet (`code` is `NOT_INDEXED`) or body not ingested"),
+    )
+)]
+pub async fn get_raw_transaction(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(txid): Path<String>,
+    Query(query): Query<RawQuery>,
+) -> Result<Response> {
+    let txid = resolve_txid(&*db, &txid).await?;
+
+    let raw_tx = check_transaction_miss(&*db, &txid, db.get_raw_transaction(&txid).await).await?;
+
+    Ok(raw_response(raw_tx, query.format.unwrap_or_default()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/utxo/{txid}/{vout}",
+    responses(
+        (status = 200, description = "Spent status of the output as of the indexed tip", body = OutputStatus),
+        (status = 400, description = "Invalid transaction ID"),
+        (status = 404, description = "Output not found, or its transaction not indexed yet (`code` is `NOT_INDEXED`)"),
+    )
+)]
+pub async fn get_output_status(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path((txid, vout)): Path<(String, u32)>,
+) -> Result<Json<OutputStatus>> {
+    let txid = resolve_txid(&*db, &txid).await?;
+
+    let status =
+        check_transaction_miss(&*db, &txid, db.get_output_status(&txid, vout).await).await?;
 
     Ok(Json(status))
 }
 
+/// Reports a lookup that missed a txid the index has never seen as not
+/// indexed yet, since it may confirm in a block past the tip. Misses on known
+/// transactions, such as details that were not ingested, are kept.
+pub(crate) async fn check_transaction_miss<T>(
+    db: &dyn BlockStore,
+    txid: &Txid,
+    result: Result<T>,
+) -> Result<T> {
+    if matches!(
+        result,
+        Err(AppError::TransactionNotFound(_) | AppError::OutputNotFound(_))
+    ) && !db.get_transaction_status(txid).await?.included
+    {
+        return Err(AppError::NotIndexed {
+            what: format!("transaction {txid}"),
+            tip_height: db.tip_height().await?,
+        });
+    }
+    result
+}
+
+/// Parses `txid` and picks the byte order it is indexed under, as
+/// [`normalize_block_hash`] does for blocks.
+pub(crate) async fn resolve_txid(db: &dyn BlockStore, txid: &str) -> Result<Txid> {
+    let txid: Txid = txid
+        .parse()
+        .map_err(|e: crate::hash::HashParseError| AppError::InvalidTransactionId(e.to_string()))?;
+    let reversed = txid.reversed();
+    if reversed != txid
+        && !db.get_transaction_status(&txid).await?.included
+        && db.get_transaction_status(&reversed).await?.included
+    {
+        return Ok(reversed);
+    }
+    Ok(txid)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/address/{address}/txs",
+    responses(
+        (status = 200, description = "Transactions funding or spending the address", body = AddressTransactionsResponse),
+        (status = 400, description = "Invalid address"),
+        (status = 501, description = "Address index is disabled"),
+    )
+)]
+pub async fn get_address_transactions(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(address): Path<String>,
+) -> Result<Json<AddressTransactionsResponse>> {
+    let script_pubkey = address_script_pubkey(&*db, &address)?;
+    let transactions = db.get_address_transactions(&script_pubkey).await?;
+
+    Ok(Json(AddressTransactionsResponse {
+        address,
+        script_pubkey,
+        transactions,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/address/{address}/utxos",
+    responses(
+        (status = 200, description = "Unspent outputs locked to the address", body = AddressUtxosResponse),
+        (status = 400, description = "Invalid address"),
+        (status = 501, description = "Address index is disabled"),
+    )
+)]
+pub async fn get_address_utxos(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(address): Path<String>,
+) -> Result<Json<AddressUtxosResponse>> {
+    let script_pubkey = address_script_pubkey(&*db, &address)?;
+    let utxos = db.get_address_utxos(&script_pubkey).await?;
+
+    Ok(Json(AddressUtxosResponse {
+        address,
+        script_pubkey,
+        utxos,
+    }))
+}
+
+pub(crate) fn address_script_pubkey(db: &dyn BlockStore, address: &str) -> Result<String> {
+    if !db.address_index_enabled() {
+        return Err(AppError::FeatureDisabled(
+            "address index (set ADDRESS_INDEX_ENABLED=true)".to_string(),
+        ));
+    }
+
+    address::script_pubkey(address)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/header/{hash}",
@@ -175,35 +969,368 @@ pub async fn get_transaction_status(
     )
 )]
 pub async fn get_header_status(
-    State(db): State<Arc<Database>>,
+    State(db): State<Arc<dyn BlockStore>>,
     Path(hash): Path<String>,
 ) -> Result<Json<HeaderStatus>> {
-    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit() || c == '0') {
-        return Err(AppError::InvalidHeaderHash(hash));
-    }
+    let hash = resolve_header_hash(&*db, hash).await?;
 
     let status = db.get_header_status(&hash).await?;
 
     Ok(Json(status))
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/header/{hash}/raw",
+    params(
+        ("format" = Option<RawEncoding>, Query, description = "`binary` (default) or `hex`"),
+    ),
+    responses(
+        (status = 200, description = "Serialized 80-byte block header"),
+        (status = 400, description = "Invalid header hash"),
+        (status = 404, description = "Header not found"),
+    )
+)]
+pub async fn get_raw_header(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(hash): Path<String>,
+    Query(query): Query<RawQuery>,
+) -> Result<Response> {
+    let hash = resolve_header_hash(&*db, hash).await?;
+
+    let block = db.get_block_by_hash(&hash).await.map_err(|e| match e {
+        AppError::BlockNotFound(_) => AppError::HeaderNotFound(hash.to_string()),
+        e => e,
+    })?;
+    let raw_header = block.raw_header()?;
+
+    Ok(raw_response(
+        raw_header.to_vec(),
+        query.format.unwrap_or_default(),
+    ))
+}
+
+async fn resolve_header_hash(db: &dyn BlockStore, hash: String) -> Result<BlockHash> {
+    let hash = hash
+        .parse()
+        .map_err(|_| AppError::InvalidHeaderHash(hash))?;
+    normalize_block_hash(db, hash).await
+}
+
+fn raw_response(bytes: Vec<u8>, encoding: RawEncoding) -> Response {
+    match encoding {
+        RawEncoding::Binary => {
+            ([(header::CONTENT_TYPE, "application/octet-stream")], bytes).into_response()
+        }
+        RawEncoding::Hex => {
+            ([(header::CONTENT_TYPE, "text/plain")], hex::encode(bytes)).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/proofs",
+    tag = "proofs",
+    params(
+        ("limit" = Option<u32>, Query, description = "Number of proofs to return (1-100, default 50)"),
+        ("cursor" = Option<u32>, Query, description = "Continue after this height, from `next_cursor`"),
+        ("verified" = Option<bool>, Query, description = "Only return proofs with this verification status"),
+        ("version" = Option<String>, Query, description = "Only return proofs produced by this prover version"),
+    ),
+    responses(
+        (status = 200, description = "Available proofs in ascending height order", body = ProofsResponse),
+        (status = 400, description = "Invalid query parameters"),
+    )
+)]
+pub async fn get_proofs(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<ProofsQuery>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response> {
+    let proofs = list_proofs(&*db, &query).await?;
+    let next = proofs.next_cursor.filter(|_| proofs.has_next);
+    Ok(pagination::with_links(
+        Json(proofs),
+        &uri,
+        next,
+        None::<u32>,
+    ))
+}
+
+pub(crate) async fn list_proofs(
+    db: &dyn BlockStore,
+    query: &ProofsQuery,
+) -> Result<ProofsResponse> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+    db.get_proofs(
+        query.limit.unwrap_or(50),
+        query.cursor,
+        query.verified,
+        query.version.as_deref(),
+    )
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/proofs/coverage",
+    tag = "proofs",
+    params(
+        ("limit" = Option<u32>, Query, description = "Number of ranges to return (1-100, default 50)"),
+        ("cursor" = Option<u32>, Query, description = "Height to resume from, from `next_cursor`"),
+    ),
+    responses(
+        (status = 200, description = "Height ranges lacking proofs", body = ProofCoverageResponse),
+        (status = 400, description = "Invalid query parameters"),
+    )
+)]
+pub async fn get_proof_coverage(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<CoverageQuery>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+
+    let coverage = db
+        .get_proof_coverage(query.limit.unwrap_or(50), query.cursor)
+        .await?;
+    let next = coverage.next_cursor.filter(|_| coverage.has_next);
+    Ok(pagination::with_links(
+        Json(coverage),
+        &uri,
+        next,
+        None::<u32>,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/jobs/{id}",
+    tag = "proofs",
+    params(("id" = i64, Path, description = "Proof job id")),
+    responses(
+        (status = 200, description = "Where the proof job stands", body = ProofJob),
+        (status = 404, description = "Job not found"),
+    )
+)]
+pub async fn get_proof_job(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(id): Path<i64>,
+) -> Result<Json<ProofJob>> {
+    Ok(Json(db.get_proof_job(id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stats/proof-latency",
+    tag = "proofs",
+    params(
+        ("window" = Option<u32>, Query, description = "Number of most recent proofs to include (1-10000, default 1000)"),
+    ),
+    responses(
+        (status = 200, description = "Ingest-to-proof latency percentiles and SLA status", body = ProofLatencyStats),
+        (status = 400, description = "Invalid query parameters"),
+    )
+)]
+pub async fn get_proof_latency(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<ProofLatencyQuery>,
+) -> Result<Json<ProofLatencyStats>> {
+    query
+        .validate()
+        .map_err(|e| AppError::InvalidQueryParameter(format!("Validation failed: {e}")))?;
+
+    let sla = ProofSlaConfig::global();
+    let latencies = db.get_proof_latencies(query.window.unwrap_or(1000)).await?;
+    let pending_over_sla = db.count_unproved_older_than(sla.target_secs).await?;
+
+    Ok(Json(ProofLatencyStats {
+        sample_count: latencies.len() as u32,
+        percentiles: sla::percentiles(&latencies),
+        sla_secs: sla.target_secs,
+        breaches: latencies.iter().filter(|&&l| l > sla.target_secs).count() as u32,
+        pending_over_sla,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/usage",
+    tag = "usage",
+    responses(
+        (status = 200, description = "Usage and quotas of the calling API key for the current UTC day and month", body = UsageResponse),
+        (status = 401, description = "Request was not made with an API key"),
+    )
+)]
+pub async fn get_usage(
+    State(db): State<Arc<dyn BlockStore>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+) -> Result<Json<UsageResponse>> {
+    let Some(Extension(identity)) = identity else {
+        return Err(AppError::Unauthorized(
+            "usage is tracked per API key".to_string(),
+        ));
+    };
+    Ok(Json(usage::current_usage(db.as_ref(), &identity).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/chain",
+    tag = "blocks",
+    responses(
+        (status = 200, description = "Network, consensus parameters and configured anchors", body = ChainInfo),
+    )
+)]
+pub async fn get_chain_info(State(db): State<Arc<dyn BlockStore>>) -> Result<Json<ChainInfo>> {
+    let tip_height = db.tip_height().await?;
+    let pruned_below = db.pruned_below().await?;
+    Ok(Json(chain::chain_info(tip_height, pruned_below)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/version",
+    tag = "health",
+    responses(
+        (status = 200, description = "Service version, build, supported API and proof format versions, and enabled features", body = VersionInfo),
+    )
+)]
+pub async fn get_version() -> Json<VersionInfo> {
+    Json(version::version_info(&config::env))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stats",
+    tag = "proofs",
+    responses(
+        (status = 200, description = "Network-wide proving statistics", body = NetworkStats),
+    )
+)]
+pub async fn get_network_stats(
+    State(db): State<Arc<dyn BlockStore>>,
+) -> Result<Json<NetworkStats>> {
+    let mut network_stats = db.get_network_stats().await?;
+    network_stats.stale = staleness::is_stale();
+    Ok(Json(network_stats))
+}
+
 #[utoipa::path(
     get,
     path = "/healthz",
     responses(
-        (status = 200, description = "Service is healthy", body = HealthStatus),
+        (status = 200, description = "Service is up or degraded", body = HealthStatus),
+        (status = 503, description = "A component is down", body = HealthStatus),
+    )
+)]
+pub async fn health_check(State(db): State<Arc<dyn BlockStore>>) -> impl IntoResponse {
+    let database = match db.health_check().await {
+        Ok(()) => ComponentHealth::up(),
+        Err(e) => ComponentHealth::down(e.to_string()),
+    };
+
+    let capacity = CapacityConfig::global();
+    let proof_storage = match capacity::last_usage() {
+        Some(usage) if capacity.is_low(&usage) => ComponentHealth::degraded(format!(
+            "{} bytes free, below {}",
+            usage.available_bytes, capacity.min_free_bytes
+        )),
+        _ => ComponentHealth::up(),
+    };
+
+    let sla = ProofSlaConfig::global();
+    let prover = match db.proof_backlog().await {
+        Ok(ProofBacklog {
+            blocks,
+            oldest_ingested_at: Some(ingested_at),
+        }) if Utc::now().timestamp() - ingested_at > sla.target_secs => {
+            ComponentHealth::degraded(format!(
+                "{blocks} blocks waiting, oldest for longer than the {}s SLA",
+                sla.target_secs
+            ))
+        }
+        Ok(_) => ComponentHealth::up(),
+        Err(e) => ComponentHealth::down(e.to_string()),
+    };
+
+    let replica_lag = db.replica_lag();
+    let sync = match replica_lag {
+        Some(lag) if lag.is_lagging() => ComponentHealth::degraded(format!(
+            "replica {} blocks behind, reading from the primary",
+            lag.blocks
+        )),
+        _ => ComponentHealth::up(),
+    };
+
+    let components = HealthComponents {
+        database,
+        proof_storage,
+        prover,
+        sync,
+    };
+    let status = components.overall();
+    let code = if status == HealthState::Down {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    (
+        code,
+        Json(HealthStatus {
+            status,
+            timestamp: Utc::now().timestamp(),
+            replica_lag_blocks: replica_lag.map(|lag| lag.blocks),
+            components,
+        }),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Service can take traffic", body = ReadinessStatus),
+        (status = 503, description = "Database unavailable or circuit breaker open"),
     )
 )]
-pub async fn health_check(State(db): State<Arc<Database>>) -> Result<Json<HealthStatus>> {
-    // Perform database health check
+pub async fn readiness_check(
+    State(db): State<Arc<dyn BlockStore>>,
+) -> Result<Json<ReadinessStatus>> {
+    // An open breaker takes the instance out of rotation without probing the
+    // database it is protecting
+    if let Some(breaker) = db.circuit_breaker() {
+        breaker.check()?;
+    }
     db.health_check().await?;
 
-    Ok(Json(HealthStatus {
-        status: "up".to_string(),
+    let circuit = db
+        .circuit_breaker()
+        .map_or(CircuitState::Closed, |breaker| breaker.state());
+    // Low disk space still lets reads through, so it degrades rather than
+    // failing readiness
+    let usage = capacity::last_usage();
+    let status = if usage.is_some_and(|usage| CapacityConfig::global().is_low(&usage)) {
+        "degraded"
+    } else {
+        "ready"
+    };
+    Ok(Json(ReadinessStatus {
+        status: status.to_string(),
+        circuit,
+        storage_available_bytes: usage.map(|usage| usage.available_bytes),
         timestamp: Utc::now().timestamp(),
     }))
 }
 
 pub async fn metrics_handler() -> impl IntoResponse {
-    "# Metrics will be implemented here\n"
+    (
+        [(header::CONTENT_TYPE, telemetry::OPENMETRICS_CONTENT_TYPE)],
+        Metrics::global().render(),
+    )
 }
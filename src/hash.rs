@@ -0,0 +1,286 @@
+use crate::error::AppError;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path},
+    http::request::Parts,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use sqlx::{
+    encode::IsNull, error::BoxDynError, sqlite::SqliteArgumentValue, Decode, Encode, Sqlite, Type,
+};
+use std::{borrow::Cow, fmt, str::FromStr};
+use utoipa::openapi::{ObjectBuilder, RefOr, Schema, SchemaType};
+
+/// Why a string is not a 32-byte hash.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HashParseError {
+    #[error("Invalid length: {0}, expected 64")]
+    Length(usize),
+    #[error("Contains non-hex characters: {0}")]
+    NotHex(String),
+}
+
+/// Defines a 32-byte hash shown as 64 lowercase hex digits in display
+/// (big-endian) order, the order used by block explorers and RPC. It is
+/// stored as that text, so SQL lookups compare hex strings. Parsing accepts
+/// either case and an optional `0x` prefix.
+macro_rules! hash_newtype {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name([u8; 32]);
+
+        impl $name {
+            /// Wraps bytes given in display order.
+            pub const fn from_byte_array(bytes: [u8; 32]) -> Self {
+                Self(bytes)
+            }
+
+            /// The bytes in display order.
+            pub const fn to_byte_array(self) -> [u8; 32] {
+                self.0
+            }
+
+            /// Wraps bytes given in internal order, as serialized in blocks
+            /// and transactions.
+            pub fn from_internal_bytes(mut bytes: [u8; 32]) -> Self {
+                bytes.reverse();
+                Self(bytes)
+            }
+
+            /// The bytes in internal order, reversed from display order.
+            pub fn to_internal_bytes(self) -> [u8; 32] {
+                let mut bytes = self.0;
+                bytes.reverse();
+                bytes
+            }
+
+            /// The same bytes read in the other byte order, for input that
+            /// may have been copied from raw block data.
+            pub fn reversed(self) -> Self {
+                Self::from_internal_bytes(self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = HashParseError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let s = s
+                    .strip_prefix("0x")
+                    .or_else(|| s.strip_prefix("0X"))
+                    .unwrap_or(s);
+                if s.len() != 64 {
+                    return Err(HashParseError::Length(s.len()));
+                }
+                let mut bytes = [0u8; 32];
+                hex::decode_to_slice(s, &mut bytes)
+                    .map_err(|_| HashParseError::NotHex(s.to_string()))?;
+                Ok(Self(bytes))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&hex::encode(self.0))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({self})", stringify!($name))
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                other.parse::<Self>().is_ok_and(|other| other == *self)
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                *self == **other
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = Cow::<str>::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl<'s> utoipa::ToSchema<'s> for $name {
+            fn schema() -> (&'s str, RefOr<Schema>) {
+                (
+                    stringify!($name),
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::String)
+                        .pattern(Some("^[0-9a-f]{64}$"))
+                        .description(Some("32-byte hash as hex, in display byte order"))
+                        .into(),
+                )
+            }
+        }
+
+        impl Type<Sqlite> for $name {
+            fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+                <str as Type<Sqlite>>::type_info()
+            }
+
+            fn compatible(ty: &<Sqlite as sqlx::Database>::TypeInfo) -> bool {
+                <str as Type<Sqlite>>::compatible(ty)
+            }
+        }
+
+        impl<'q> Encode<'q, Sqlite> for $name {
+            fn encode_by_ref(&self, args: &mut Vec<SqliteArgumentValue<'q>>) -> IsNull {
+                args.push(SqliteArgumentValue::Text(Cow::Owned(self.to_string())));
+                IsNull::No
+            }
+        }
+
+        impl<'r> Decode<'r, Sqlite> for $name {
+            fn decode(value: sqlx::sqlite::SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+                Ok(<&str as Decode<Sqlite>>::decode(value)?.parse()?)
+            }
+        }
+    };
+}
+
+hash_newtype! {
+    /// Double SHA-256 of a block header.
+    BlockHash
+}
+
+hash_newtype! {
+    /// Double SHA-256 of a transaction without its witness.
+    Txid
+}
+
+hash_newtype! {
+    /// Double SHA-256 of a transaction with its witness; the txid for
+    /// transactions that have none.
+    Wtxid
+}
+
+/// Bitcoin's double SHA-256, in internal byte order.
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// A block named by height or hash, as in `/v1/blocks/{identifier}`. As an
+/// extractor it reads the route's single path parameter and rejects anything
+/// else with 400.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockId {
+    Height(u32),
+    Hash(BlockHash),
+}
+
+impl FromStr for BlockId {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(height) = s.parse() {
+            Ok(Self::Height(height))
+        } else if let Ok(hash) = s.parse() {
+            Ok(Self::Hash(hash))
+        } else {
+            Err(AppError::InvalidBlockIdentifier(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Height(height) => height.fmt(f),
+            Self::Hash(hash) => hash.fmt(f),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for BlockId {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        let Path(identifier) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| AppError::InvalidBlockIdentifier(e.body_text()))?;
+        identifier.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+
+    #[test]
+    fn test_parse_and_display() {
+        let hash: BlockHash = HASH.parse().unwrap();
+        assert_eq!(hash.to_string(), HASH);
+        assert_eq!(hash.to_byte_array()[0], 0x00);
+        assert_eq!(hash.to_internal_bytes()[0], 0x6f);
+        assert_eq!(
+            BlockHash::from_internal_bytes(hash.to_internal_bytes()),
+            hash
+        );
+        assert_eq!(HASH.to_uppercase().parse::<BlockHash>().unwrap(), hash);
+        assert_eq!(format!("0x{HASH}").parse::<BlockHash>().unwrap(), hash);
+        assert_eq!(
+            format!("0X{}", HASH.to_uppercase())
+                .parse::<BlockHash>()
+                .unwrap(),
+            hash
+        );
+        assert_eq!(hash.reversed().to_byte_array(), hash.to_internal_bytes());
+        assert_eq!(hash.reversed().reversed(), hash);
+
+        assert_eq!(
+            "abc".parse::<Txid>().unwrap_err(),
+            HashParseError::Length(3)
+        );
+        assert!(matches!(
+            HASH.replace('f', "g").parse::<Txid>(),
+            Err(HashParseError::NotHex(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_block_id() {
+        assert_eq!(
+            "869123".parse::<BlockId>().unwrap(),
+            BlockId::Height(869123)
+        );
+        assert_eq!(
+            HASH.parse::<BlockId>().unwrap(),
+            BlockId::Hash(HASH.parse().unwrap())
+        );
+        assert_eq!(BlockId::Hash(HASH.parse().unwrap()).to_string(), HASH);
+        for invalid in ["", "-1", "4294967296", "abc", &HASH[1..]] {
+            assert!(matches!(
+                invalid.parse::<BlockId>(),
+                Err(AppError::InvalidBlockIdentifier(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let hash: Txid = serde_json::from_value(serde_json::json!(HASH)).unwrap();
+        assert_eq!(serde_json::to_value(hash).unwrap(), HASH);
+        assert!(serde_json::from_value::<Txid>(serde_json::json!("00ff")).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    auth::ApiKeyIdentity,
+    error::{AppError, Result},
+    jwt::Principal,
+    middleware::TimeoutConfig,
+    store::BlockStore,
+};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Set to `true` on a response replayed for a retried request.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest key accepted, as in the IETF Idempotency-Key draft's examples.
+const MAX_KEY_LEN: usize = 255;
+
+static IDEMPOTENCY_CONFIG: Lazy<IdempotencyConfig> = Lazy::new(IdempotencyConfig::from_env);
+
+#[derive(Debug, Clone)]
+pub struct IdempotencyConfig {
+    /// How long a key is remembered after its first use.
+    pub ttl_secs: i64,
+    /// How long a first request may stay unfinished before retries take
+    /// its key over, as after a crash. Requests cannot outlive their
+    /// timeout, so this is the submission budget.
+    pub pending_secs: i64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 24 * 60 * 60,
+            pending_secs: TimeoutConfig::default().submission_secs as i64,
+        }
+    }
+}
+
+impl IdempotencyConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            ttl_secs: std::env::var("IDEMPOTENCY_KEY_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.ttl_secs),
+            pending_secs: TimeoutConfig::from_env().submission_secs as i64,
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &IDEMPOTENCY_CONFIG
+    }
+}
+
+/// A successful response kept to replay to retries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub location: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// What an idempotency key already stands for when a request claims it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// The key is new, or expired, and now belongs to this request.
+    Claimed,
+    /// The key's first request is still running.
+    InProgress,
+    /// The key was first used for a different request.
+    Mismatch,
+    /// The key's first request succeeded with this response.
+    Completed(StoredResponse),
+}
+
+/// Whom a key belongs to: keys are only matched among one client's requests.
+fn scope(request: &Request) -> String {
+    if let Some(identity) = request.extensions().get::<ApiKeyIdentity>() {
+        format!("key:{}", identity.id)
+    } else if let Some(principal) = request.extensions().get::<Principal>() {
+        format!("jwt:{}", principal.subject)
+    } else {
+        "anonymous".to_string()
+    }
+}
+
+/// Digest of what makes two requests the same: method, path and body.
+pub fn fingerprint(method: &str, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update([0]);
+    hasher.update(path.as_bytes());
+    hasher.update([0]);
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// Checks a key is 1 to 255 visible ASCII characters.
+pub fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN || !key.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(AppError::InvalidIdempotencyKey(format!(
+            "expected 1 to {MAX_KEY_LEN} visible ASCII characters"
+        )));
+    }
+    Ok(())
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = (status, stored.body).into_response();
+    let headers = response.headers_mut();
+    let stored_headers = [
+        (header::CONTENT_TYPE, stored.content_type),
+        (header::LOCATION, stored.location),
+    ];
+    for (name, value) in stored_headers {
+        if let Some(value) = value.and_then(|v| HeaderValue::from_str(&v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Makes retries of a request sent with an `Idempotency-Key` safe: the first
+/// request with a key runs, and once it succeeds its response is replayed
+/// to every retry with the same key instead of running it again. A retry
+/// while the first request runs gets `409`, reuse of the key for another
+/// request `422`. Failed requests release their key so they can be retried.
+/// Runs after authentication, since keys belong to the client using them.
+pub async fn idempotency_middleware(
+    State((store, config)): State<(Arc<dyn BlockStore>, &'static IdempotencyConfig)>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.run(request).await);
+    };
+    let key = key
+        .to_str()
+        .map_err(|_| AppError::InvalidIdempotencyKey("not visible ASCII".to_string()))?
+        .to_string();
+    validate_key(&key)?;
+
+    let scope = scope(&request);
+    let (parts, body) = request.into_parts();
+    // The body limit layer has already buffered it
+    let body = axum::body::to_bytes(body, usize::MAX).await.map_err(|e| {
+        AppError::Io(std::io::Error::other(format!(
+            "failed to read request body: {e}"
+        )))
+    })?;
+    let fingerprint = fingerprint(parts.method.as_str(), parts.uri.path(), &body);
+
+    let now = Utc::now().timestamp();
+    match store
+        .claim_idempotency_key(&scope, &key, &fingerprint, now, config)
+        .await?
+    {
+        IdempotencyClaim::Claimed => {}
+        IdempotencyClaim::Completed(stored) => return Ok(replay(stored)),
+        IdempotencyClaim::InProgress => {
+            return Err(AppError::IdempotencyConflict(format!(
+                "a request with key {key} is still being processed"
+            )))
+        }
+        IdempotencyClaim::Mismatch => {
+            return Err(AppError::IdempotencyKeyReused(format!(
+                "key {key} was used for a different request"
+            )))
+        }
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if !response.status().is_success() {
+        store.release_idempotency_key(&scope, &key).await?;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX).await.map_err(|e| {
+        AppError::Io(std::io::Error::other(format!(
+            "failed to read response body: {e}"
+        )))
+    })?;
+    let header_value = |name| {
+        parts
+            .headers
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let stored = StoredResponse {
+        status: parts.status.as_u16(),
+        content_type: header_value(header::CONTENT_TYPE),
+        location: header_value(header::LOCATION),
+        body: body.to_vec(),
+    };
+    store
+        .complete_idempotency_key(&scope, &key, &stored)
+        .await?;
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        let upload = fingerprint("POST", "/v1/blocks/1/proof", b"{}");
+        assert_eq!(upload, fingerprint("POST", "/v1/blocks/1/proof", b"{}"));
+        assert_ne!(upload, fingerprint("POST", "/v1/blocks/2/proof", b"{}"));
+        assert_ne!(upload, fingerprint("POST", "/v1/blocks/1/proof", b"{ }"));
+        // Fields are delimited, so bytes cannot move between them
+        assert_ne!(
+            fingerprint("POST", "/a", b"b"),
+            fingerprint("POST", "/ab", b"")
+        );
+    }
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("8e03978e-40d5-43e8-bc93-6894a57f9324").is_ok());
+        assert!(validate_key(&"k".repeat(255)).is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key(&"k".repeat(256)).is_err());
+        assert!(validate_key("two words").is_err());
+    }
+}
@@ -0,0 +1,562 @@
+use crate::{
+    chain,
+    database::Database,
+    error::{AppError, Result},
+    hash::{BlockHash, Txid},
+    model::parse_amount,
+    validation::{check_header, AssumeValid, HeaderValidation, Rejection},
+};
+use serde_json::{json, Value};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+pub struct IngestConfig {
+    /// Directory of block files to sync from at startup; sync is off when unset.
+    pub dir: Option<PathBuf>,
+    /// Blocks written per database transaction.
+    pub batch_size: usize,
+    /// Items each pipeline stage may run ahead of the next.
+    pub channel_capacity: usize,
+    /// What to do with blocks whose header fails proof-of-work validation.
+    pub header_validation: HeaderValidation,
+    /// Block up to which proof of work and merkle roots are not checked.
+    pub assume_valid: Option<AssumeValid>,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            batch_size: 100,
+            channel_capacity: 64,
+            header_validation: HeaderValidation::default(),
+            assume_valid: None,
+        }
+    }
+}
+
+impl IngestConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            dir: std::env::var("INGEST_DIR").ok().map(PathBuf::from),
+            batch_size: std::env::var("INGEST_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&size| size > 0)
+                .unwrap_or(defaults.batch_size),
+            channel_capacity: std::env::var("INGEST_CHANNEL_CAPACITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&capacity| capacity > 0)
+                .unwrap_or(defaults.channel_capacity),
+            header_validation: HeaderValidation::from_env(),
+            assume_valid: AssumeValid::from_env(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestReport {
+    pub blocks: usize,
+    pub batches: usize,
+    /// Blocks set aside in `quarantined_blocks` instead of being indexed.
+    pub quarantined: usize,
+    /// Indexed blocks whose header checks were skipped under
+    /// [`IngestConfig::assume_valid`].
+    pub assumed_valid: usize,
+}
+
+type FieldCheck = (&'static str, fn(&Value) -> bool);
+
+/// Integer satoshis, or BTC in documents exported before amounts were
+/// integers.
+fn is_amount(value: &Value) -> bool {
+    parse_amount(value).is_some_and(|sats| sats >= 0)
+}
+
+fn is_block_hash(value: &Value) -> bool {
+    value
+        .as_str()
+        .is_some_and(|s| s.parse::<BlockHash>().is_ok())
+}
+
+/// Rewrites the block's hashes and txids in their stored form, lowercase
+/// without a `0x` prefix, so that lookups compare equal text. Only call on a
+/// block whose fields have been checked.
+fn normalize_hashes(block: &mut Value) {
+    for field in ["hash", "prev_hash"] {
+        if let Some(hash) = block[field]
+            .as_str()
+            .and_then(|s| s.parse::<BlockHash>().ok())
+        {
+            block[field] = Value::String(hash.to_string());
+        }
+    }
+    if let Some(txids) = block["txids"].as_array_mut() {
+        for txid in txids {
+            if let Some(parsed) = txid.as_str().and_then(|s| s.parse::<Txid>().ok()) {
+                *txid = Value::String(parsed.to_string());
+            }
+        }
+    }
+}
+
+/// Fields every block needs before it can be written, with their JSON types.
+const REQUIRED_FIELDS: &[FieldCheck] = &[
+    ("height", Value::is_u64),
+    ("hash", is_block_hash),
+    ("prev_hash", is_block_hash),
+    ("merkle_root", Value::is_string),
+    ("version", Value::is_i64),
+    ("bits", Value::is_u64),
+    ("nonce", Value::is_u64),
+    ("tx_count", Value::is_u64),
+    ("total_fees", is_amount),
+    ("timestamp", Value::is_i64),
+    ("verified", Value::is_boolean),
+];
+
+/// Fields a block may leave out, but must give as unsigned integers if set.
+const OPTIONAL_SIZE_FIELDS: &[&str] = &["size", "stripped_size", "weight"];
+
+/// Ingests every `*.json` file in `dir`, each holding one block or an array
+/// of blocks in the mock-data shape. Files are taken in height order of
+/// their names (`869123.json`), so name them after the block they start at.
+///
+/// Reading, parsing and writing run as separate stages joined by bounded
+/// channels: while one batch is being committed the next files are already
+/// being read and parsed. The first error stops the pipeline; batches
+/// committed before it are kept.
+///
+/// Each block's proof of work and merkle root are checked per
+/// [`IngestConfig::header_validation`]: a failure is an error like a
+/// malformed file, or with [`HeaderValidation::Quarantine`] the block is set
+/// aside and skipped. Blocks up to [`IngestConfig::assume_valid`] skip both.
+pub async fn ingest_dir(
+    database: &Database,
+    dir: &Path,
+    config: &IngestConfig,
+) -> Result<IngestReport> {
+    let files = block_files(dir)?;
+    let (raw_tx, mut raw_rx) = mpsc::channel::<Result<(PathBuf, Vec<u8>)>>(config.channel_capacity);
+    let (block_tx, mut block_rx) =
+        mpsc::channel::<Result<(Value, Option<Rejection>)>>(config.channel_capacity);
+    let validation = config.header_validation;
+    let assume_valid = config.assume_valid;
+
+    // Fetch: read files from disk. A closed channel means the writer gave up.
+    let fetch = tokio::spawn(async move {
+        for path in files {
+            let read = tokio::fs::read(&path).await.map_err(|e| {
+                AppError::Store(anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+            });
+            let failed = read.is_err();
+            if raw_tx.send(read.map(|bytes| (path, bytes))).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    // Parse: decode and check blocks so the writer only sees well-formed ones
+    let parse = tokio::spawn(async move {
+        while let Some(raw) = raw_rx.recv().await {
+            let blocks = raw.and_then(|(path, bytes)| parse_blocks(&path, &bytes));
+            match blocks {
+                Ok(blocks) => {
+                    for block in blocks {
+                        let rejection = match validation {
+                            HeaderValidation::Off => None,
+                            _ => check_header(&block, assume_valid.as_ref()).err(),
+                        };
+                        let item = match rejection {
+                            Some(rejection) if validation == HeaderValidation::Reject => Err(
+                                AppError::Store(anyhow::anyhow!("Invalid block: {}", rejection)),
+                            ),
+                            rejection => Ok((block, rejection)),
+                        };
+                        let failed = item.is_err();
+                        if block_tx.send(item).await.is_err() || failed {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    let _ = block_tx.send(Err(e)).await;
+                    return;
+                }
+            }
+        }
+    });
+
+    // Write: commit whatever has arrived, up to a batch, in one transaction
+    let mut report = IngestReport::default();
+    let mut batch = Vec::with_capacity(config.batch_size);
+    let mut quarantine = Vec::new();
+    let result = loop {
+        let Some(first) = block_rx.recv().await else {
+            break Ok(report);
+        };
+        let mut failure = sort_block(first, &mut batch, &mut quarantine);
+        while failure.is_none() && batch.len() < config.batch_size {
+            match block_rx.try_recv() {
+                Ok(next) => failure = sort_block(next, &mut batch, &mut quarantine),
+                Err(_) => break,
+            }
+        }
+
+        for (block, rejection) in quarantine.drain(..) {
+            warn!(%rejection, "Quarantining block");
+            if let Err(e) = database.quarantine_block(&block, &rejection).await {
+                failure = Some(e);
+                batch.clear();
+                break;
+            }
+            report.quarantined += 1;
+        }
+        if !batch.is_empty() {
+            if let Err(e) = database.insert_blocks(&batch).await {
+                break Err(e);
+            }
+            report.blocks += batch.len();
+            report.batches += 1;
+            if let Some(trusted) = &config.assume_valid {
+                report.assumed_valid += batch.iter().filter(|b| trusted.covers(b)).count();
+            }
+            batch.clear();
+        }
+        if let Some(e) = failure {
+            break Err(e);
+        }
+    };
+
+    // Dropping the receiver unblocks the upstream stages if we stopped early
+    drop(block_rx);
+    let _ = tokio::join!(fetch, parse);
+    result
+}
+
+/// Files a parsed block into the batch to write or, if its header was
+/// refused, the blocks to quarantine; passes on a pipeline error.
+fn sort_block(
+    item: Result<(Value, Option<Rejection>)>,
+    batch: &mut Vec<Value>,
+    quarantine: &mut Vec<(Value, Rejection)>,
+) -> Option<AppError> {
+    item.map(|(block, rejection)| match rejection {
+        Some(rejection) => quarantine.push((block, rejection)),
+        None => batch.push(block),
+    })
+    .err()
+}
+
+/// Syncs from [`IngestConfig::dir`] in the background, when one is set.
+pub fn spawn_initial_sync(database: Arc<Database>, config: IngestConfig) {
+    let Some(dir) = config.dir.clone() else {
+        return;
+    };
+    tokio::spawn(async move {
+        info!(dir = %dir.display(), "Starting initial sync");
+        match ingest_dir(&database, &dir, &config).await {
+            Ok(report) => info!(
+                blocks = report.blocks,
+                batches = report.batches,
+                "Initial sync completed"
+            ),
+            Err(e) => warn!(error = %e, "Initial sync failed"),
+        }
+    });
+}
+
+/// Writes every indexed block to `dir` as `<height>.json`, in the mock-data
+/// shape [`ingest_dir`] reads, and returns how many were written.
+/// Transactions that were only indexed by txid are exported without details.
+pub async fn export_dir(database: &Database, dir: &Path) -> Result<usize> {
+    let write_error = |path: &Path, e: std::io::Error| {
+        AppError::Store(anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    };
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| write_error(dir, e))?;
+
+    let heights = database.block_heights(0..=u32::MAX).await?;
+    for &height in &heights {
+        let path = dir.join(format!("{height}.json"));
+        let document = block_document(database, height).await?;
+        let bytes = serde_json::to_vec_pretty(&document).expect("block JSON serializes");
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|e| write_error(&path, e))?;
+    }
+    Ok(heights.len())
+}
+
+/// Block `height` as stored, in the mock-data shape [`Database::insert_blocks`]
+/// takes. Transactions only indexed by txid are left without details.
+pub async fn block_document(database: &Database, height: u32) -> Result<Value> {
+    let block = database.get_block_by_height(height).await?;
+    let mut transactions = Vec::new();
+    for txid in &block.txids {
+        let detail = database.get_transaction_detail(txid).await?;
+        let raw = match database.get_raw_transaction(txid).await {
+            Ok(raw) => Some(hex::encode(raw)),
+            Err(AppError::RawTransactionUnavailable(_)) => None,
+            Err(e) => return Err(e),
+        };
+        if detail.size.is_none() && raw.is_none() && detail.outputs.is_empty() {
+            continue;
+        }
+        transactions.push(json!({
+            "txid": detail.txid,
+            "fee": detail.fee,
+            "size": detail.size,
+            "vsize": detail.vsize,
+            "inputs": detail.inputs,
+            "outputs": detail.outputs,
+            "raw": raw,
+        }));
+    }
+
+    let mut value = json!({
+        "height": height,
+        "hash": block.summary.hash,
+        "prev_hash": block.prev_hash,
+        "merkle_root": block.merkle_root,
+        "version": block.version,
+        "bits": block.bits,
+        "nonce": block.nonce,
+        "tx_count": block.summary.tx_count,
+        "total_fees": block.summary.total_fees,
+        "timestamp": block.summary.timestamp,
+        "verified": block.summary.verified,
+        "size": block.size,
+        "stripped_size": block.stripped_size,
+        "weight": block.weight,
+        "chainwork": block.chainwork,
+        "txids": block.txids,
+    });
+    if !transactions.is_empty() {
+        value["transactions"] = Value::Array(transactions);
+    }
+    Ok(value)
+}
+
+fn block_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to list {}: {}", dir.display(), e)))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+
+    let height = |path: &PathBuf| {
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u64>().ok())
+    };
+    files.sort_by(|a, b| height(a).cmp(&height(b)).then_with(|| a.cmp(b)));
+    Ok(files)
+}
+
+fn parse_blocks(path: &Path, bytes: &[u8]) -> Result<Vec<Value>> {
+    let invalid = |reason: String| {
+        AppError::Store(anyhow::anyhow!(
+            "Invalid block file {}: {}",
+            path.display(),
+            reason
+        ))
+    };
+    let mut blocks = match serde_json::from_slice(bytes).map_err(|e| invalid(e.to_string()))? {
+        Value::Array(blocks) => blocks,
+        block => vec![block],
+    };
+
+    for block in &blocks {
+        if let Some((field, _)) = REQUIRED_FIELDS
+            .iter()
+            .find(|(field, is_valid)| !is_valid(&block[*field]))
+        {
+            return Err(invalid(format!("missing or malformed `{field}`")));
+        }
+        if let Some(field) = OPTIONAL_SIZE_FIELDS
+            .iter()
+            .find(|field| !(block[**field].is_null() || block[**field].is_u64()))
+        {
+            return Err(invalid(format!("malformed `{field}`")));
+        }
+        let chainwork = &block["chainwork"];
+        if !(chainwork.is_null()
+            || chainwork
+                .as_str()
+                .and_then(chain::parse_chainwork)
+                .is_some())
+        {
+            return Err(invalid("malformed `chainwork`".to_string()));
+        }
+        let txids_valid = match &block["txids"] {
+            Value::Null => true,
+            Value::Array(txids) => txids
+                .iter()
+                .all(|txid| txid.as_str().is_some_and(|s| s.parse::<Txid>().is_ok())),
+            _ => false,
+        };
+        if !txids_valid {
+            return Err(invalid("malformed `txids`".to_string()));
+        }
+    }
+    blocks.iter_mut().for_each(normalize_hashes);
+    // Spent outputs must be known before their spenders
+    blocks.sort_by_key(|b| b["height"].as_u64());
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_ingest_dir_in_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut blocks: Vec<Value> =
+            serde_json::from_str(include_str!("../data/mock_blocks.json")).unwrap();
+        // Stored lowercase and unprefixed whatever form the file uses
+        let hash = blocks[0]["hash"].as_str().unwrap().to_string();
+        blocks[0]["hash"] = json!(format!("0x{}", hash.to_uppercase()));
+        for block in &blocks {
+            let path = dir.path().join(format!("{}.json", block["height"]));
+            std::fs::write(path, serde_json::to_vec(block).unwrap()).unwrap();
+        }
+        let config = IngestConfig {
+            dir: None,
+            batch_size: 2,
+            channel_capacity: 1,
+            header_validation: HeaderValidation::Off,
+            assume_valid: None,
+        };
+
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        let report = ingest_dir(&database, dir.path(), &config).await.unwrap();
+        assert_eq!(report.blocks, 5);
+        assert!(report.batches >= 3);
+        assert_eq!(database.tip_height().await.unwrap(), Some(869123));
+        let tx = database
+            .get_transaction_detail(&blocks[0]["txids"][0].as_str().unwrap().parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(tx.block_height, 869123);
+        let block = database
+            .get_block_by_hash(&hash.parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(block.summary.hash.to_string(), hash);
+
+        // A malformed file fails the sync instead of panicking the writer
+        std::fs::write(dir.path().join("869124.json"), r#"{"height": 869124}"#).unwrap();
+        let err = ingest_dir(&database, dir.path(), &config)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("malformed `hash`"));
+        assert_eq!(database.tip_height().await.unwrap(), Some(869123));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_dir_validates_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let blocks: Vec<Value> =
+            serde_json::from_str(include_str!("../data/mock_blocks.json")).unwrap();
+        std::fs::write(
+            dir.path().join("869119.json"),
+            serde_json::to_vec(&blocks).unwrap(),
+        )
+        .unwrap();
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+
+        // The mock hashes are synthetic, so none of them meets its target
+        let err = ingest_dir(&database, dir.path(), &IngestConfig::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("hash_mismatch"));
+        assert_eq!(database.tip_height().await.unwrap(), None);
+
+        let config = IngestConfig {
+            header_validation: HeaderValidation::Quarantine,
+            ..IngestConfig::default()
+        };
+        let report = ingest_dir(&database, dir.path(), &config).await.unwrap();
+        assert_eq!((report.blocks, report.quarantined), (0, 5));
+        assert_eq!(database.tip_height().await.unwrap(), None);
+        let quarantined = database.quarantined_hashes().await.unwrap();
+        assert_eq!(quarantined.len(), 5);
+        assert!(quarantined.contains(&blocks[0]["hash"].as_str().unwrap().to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_dir_assume_valid() {
+        let dir = tempfile::tempdir().unwrap();
+        let blocks: Vec<Value> =
+            serde_json::from_str(include_str!("../data/mock_blocks.json")).unwrap();
+        std::fs::write(
+            dir.path().join("869119.json"),
+            serde_json::to_vec(&blocks).unwrap(),
+        )
+        .unwrap();
+        let trusted = blocks.iter().find(|b| b["height"] == 869122).unwrap();
+        let config = IngestConfig {
+            header_validation: HeaderValidation::Quarantine,
+            assume_valid: AssumeValid::parse(&format!(
+                "869122:{}",
+                trusted["hash"].as_str().unwrap()
+            )),
+            ..IngestConfig::default()
+        };
+
+        // Trusted up to 869122; the synthetic 869123 is still checked above it
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        let report = ingest_dir(&database, dir.path(), &config).await.unwrap();
+        assert_eq!(
+            (report.blocks, report.assumed_valid, report.quarantined),
+            (4, 4, 1)
+        );
+        assert_eq!(database.tip_height().await.unwrap(), Some(869122));
+    }
+
+    #[tokio::test]
+    async fn test_export_round_trip() {
+        let source = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        source.seed_data().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(export_dir(&source, dir.path()).await.unwrap(), 5);
+
+        let target = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        let config = IngestConfig {
+            header_validation: HeaderValidation::Off,
+            ..IngestConfig::default()
+        };
+        let report = ingest_dir(&target, dir.path(), &config).await.unwrap();
+        assert_eq!(report.blocks, 5);
+
+        for height in source.block_heights(0..=u32::MAX).await.unwrap() {
+            let exported = source.get_block_by_height(height).await.unwrap();
+            let imported = target.get_block_by_height(height).await.unwrap();
+            assert_eq!(
+                serde_json::to_value(&imported).unwrap(),
+                serde_json::to_value(&exported).unwrap()
+            );
+        }
+        let txid = source.get_block_by_height(869123).await.unwrap().txids[0];
+        assert_eq!(
+            serde_json::to_value(target.get_transaction_detail(&txid).await.unwrap()).unwrap(),
+            serde_json::to_value(source.get_transaction_detail(&txid).await.unwrap()).unwrap()
+        );
+        assert_eq!(
+            target.get_raw_transaction(&txid).await.unwrap(),
+            source.get_raw_transaction(&txid).await.unwrap()
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    database::Database,
+    error::Result,
+    model::{BlockDetail, JobStatus, ProofJob},
+    proof,
+    storage::ProofStorage,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::warn;
+
+/// Produces the proof of a block.
+#[async_trait]
+pub trait Prover: Send + Sync {
+    /// The proof document of `block`, or why it could not be produced.
+    async fn prove(&self, block: &BlockDetail) -> std::result::Result<Vec<u8>, String>;
+}
+
+/// Proves block `height` as a job: queued, running, then completed once the
+/// proof is registered, read back and verified against the block, which is
+/// then marked verified; or failed with the reason it was not. A failed job
+/// marks the proof failed, which publishes `job_failed`; a completed one
+/// publishes `proof_generated` and `proof_verified`.
+pub async fn run(
+    database: &Database,
+    storage: &dyn ProofStorage,
+    prover: &dyn Prover,
+    height: u32,
+) -> Result<ProofJob> {
+    let job = database
+        .create_proof_job(height, Utc::now().timestamp())
+        .await?;
+    database
+        .update_proof_job(job.id, JobStatus::Running, None, Utc::now().timestamp())
+        .await?;
+
+    let block = database.get_block_by_height(height).await?;
+    let outcome = match prover.prove(&block).await {
+        Ok(data) => {
+            let execution_time_ms = proof::execution_time_ms(&data);
+            async {
+                proof::register(database, storage, height, &data, execution_time_ms).await?;
+                proof::verify(database, storage, height).await
+            }
+            .await
+            .map_err(|e| e.to_string())
+        }
+        Err(error) => Err(error),
+    };
+
+    match outcome {
+        Ok(_) => {
+            database.mark_verified(height).await?;
+            database
+                .update_proof_job(job.id, JobStatus::Completed, None, Utc::now().timestamp())
+                .await
+        }
+        Err(error) => {
+            warn!(job = job.id, height, error = %error, "Proof job failed");
+            let job = database
+                .update_proof_job(
+                    job.id,
+                    JobStatus::Failed,
+                    Some(&error),
+                    Utc::now().timestamp(),
+                )
+                .await?;
+            database.mark_proof_failed(height).await?;
+            Ok(job)
+        }
+    }
+}
@@ -0,0 +1,867 @@
+use crate::{
+    chain,
+    error::{AppError, Result},
+    events::{self, Event},
+    hash::{BlockHash, BlockId, Txid},
+    model::{
+        AddressTransaction, BlockDetail, BlockFilter, BlockStats, BlockTxidsResponse,
+        BlocksResponse, HeaderStatus, NetworkStats, OrphansResponse, OutputStatus, PageDirection,
+        ProofCoverageResponse, ProofGap, ProofGapReason, ProofRecord, ProofsResponse, SortOrder,
+        TransactionDetail, TransactionStatus, Utxo,
+    },
+    proof,
+    sla::{ProofMilestone, ProofSlaConfig},
+    store::{self, BlockStore, ProofBacklog},
+    validation::{self, RetargetContext},
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use redb::{ReadTransaction, ReadableTable, ReadableTableMetadata, TableDefinition};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+// Values are JSON so records can grow fields without a migration step
+const BLOCKS: TableDefinition<u32, &[u8]> = TableDefinition::new("blocks");
+const HEADERS: TableDefinition<&str, u32> = TableDefinition::new("headers");
+const TRANSACTIONS: TableDefinition<&str, (u32, u32)> = TableDefinition::new("transactions");
+const PROOFS: TableDefinition<u32, &[u8]> = TableDefinition::new("proofs");
+
+#[derive(Debug, Clone)]
+pub struct KvConfig {
+    pub path: PathBuf,
+}
+
+impl Default for KvConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("data/raito.redb"),
+        }
+    }
+}
+
+impl KvConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            path: std::env::var("KV_STORE_PATH")
+                .map(PathBuf::from)
+                .unwrap_or(defaults.path),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredBlock {
+    block: BlockDetail,
+    ingested_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredProof {
+    file_path: String,
+    file_size: i64,
+    version: String,
+    generated_at: i64,
+    execution_time_ms: Option<i64>,
+    recorded_at: i64,
+}
+
+fn kv_error<E: Into<redb::Error>>(context: &'static str) -> impl FnOnce(E) -> AppError {
+    move |e| AppError::Store(anyhow::anyhow!("{context}: {}", e.into()))
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| AppError::Store(anyhow::anyhow!("Malformed key-value record: {}", e)))
+}
+
+/// Append-only block and proof metadata in an embedded redb file, for
+/// deployments that want no SQL engine. Ingestion writes a whole batch in a
+/// single transaction.
+///
+/// Only what the mock-data ingest carries is kept: blocks, txids, headers and
+/// proofs. Transaction details, block statistics, the address index, proof
+/// failure tracking and reorg tombstones need the SQLite backend; those
+/// lookups answer as if that data was never ingested.
+pub struct KvStore {
+    db: redb::Database,
+}
+
+impl KvStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let db =
+            redb::Database::create(path).map_err(kv_error("Failed to open key-value store"))?;
+
+        // Create every table up front so readers never see a missing one
+        let txn = db
+            .begin_write()
+            .map_err(kv_error("Failed to open key-value store"))?;
+        txn.open_table(BLOCKS)
+            .map_err(kv_error("Failed to create tables"))?;
+        txn.open_table(HEADERS)
+            .map_err(kv_error("Failed to create tables"))?;
+        txn.open_table(TRANSACTIONS)
+            .map_err(kv_error("Failed to create tables"))?;
+        txn.open_table(PROOFS)
+            .map_err(kv_error("Failed to create tables"))?;
+        txn.commit().map_err(kv_error("Failed to create tables"))?;
+
+        info!(path = %path.display(), "Opened key-value store");
+        Ok(Self { db })
+    }
+
+    pub fn seed_data(&self) -> Result<()> {
+        let mock_data = include_str!("../data/mock_blocks.json");
+        let mut blocks: Vec<serde_json::Value> = serde_json::from_str(mock_data)
+            .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to parse mock data: {}", e)))?;
+        blocks.sort_by_key(|b| b["height"].as_u64());
+
+        self.insert_blocks(&blocks)?;
+        info!(
+            count = blocks.len(),
+            "Key-value store seeded with mock data"
+        );
+        Ok(())
+    }
+
+    /// Ingests blocks in the mock-data JSON shape in one write transaction.
+    /// A different block already stored at the same height is replaced.
+    pub fn insert_blocks(&self, blocks: &[serde_json::Value]) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let mut milestones = Vec::new();
+        let mut indexed = Vec::new();
+
+        let txn = self
+            .db
+            .begin_write()
+            .map_err(kv_error("Failed to begin ingest"))?;
+        {
+            let mut block_table = txn
+                .open_table(BLOCKS)
+                .map_err(kv_error("Failed to open blocks"))?;
+            let mut headers = txn
+                .open_table(HEADERS)
+                .map_err(kv_error("Failed to open headers"))?;
+            let mut transactions = txn
+                .open_table(TRANSACTIONS)
+                .map_err(kv_error("Failed to open transactions"))?;
+            let mut proofs = txn
+                .open_table(PROOFS)
+                .map_err(kv_error("Failed to open proofs"))?;
+
+            for block_data in blocks {
+                let height = block_data["height"].as_u64().unwrap() as u32;
+                let parent = match height.checked_sub(1) {
+                    Some(parent_height) => block_table
+                        .get(parent_height)
+                        .map_err(kv_error("Failed to read block"))?
+                        .map(|parent| decode::<StoredBlock>(parent.value()))
+                        .transpose()?
+                        .map(|parent| parent.block)
+                        .filter(|parent| {
+                            block_data["prev_hash"] == parent.summary.hash.to_string()
+                        }),
+                    None => None,
+                };
+                let first = parent.is_none()
+                    && block_table
+                        .range(..height)
+                        .map_err(kv_error("Failed to read blocks"))?
+                        .next()
+                        .is_none();
+                validation::check_continuity(block_data, parent.is_some(), first, chain::anchors())
+                    .map_err(|rejection| AppError::InvalidBlock(rejection.to_string()))?;
+                if let Some(parent) = &parent {
+                    let network = chain::network();
+                    let offset = height % chain::RETARGET_INTERVAL;
+                    let stored = |height| {
+                        block_table
+                            .get(height)
+                            .map_err(kv_error("Failed to read block"))?
+                            .map(|block| decode::<StoredBlock>(block.value()))
+                            .transpose()
+                    };
+                    let period_start = match offset {
+                        0 => stored(height - chain::RETARGET_INTERVAL)?
+                            .map(|start| start.block.summary.timestamp),
+                        _ => None,
+                    };
+                    let min_difficulty_parent = network.allow_min_difficulty_blocks
+                        && offset != 0
+                        && parent.bits == network.pow_limit_bits;
+                    let period_bits = if min_difficulty_parent {
+                        stored(height - offset)?.map(|start| start.block.bits)
+                    } else {
+                        None
+                    };
+                    let context = RetargetContext {
+                        parent_bits: parent.bits,
+                        parent_timestamp: parent.summary.timestamp,
+                        period_start,
+                        period_bits,
+                    };
+                    validation::check_retarget(block_data, &context, network)
+                        .map_err(|rejection| AppError::InvalidBlock(rejection.to_string()))?;
+                }
+                let block = store::parse_block(block_data, parent.as_ref());
+
+                let replaced = block_table
+                    .get(height)
+                    .map_err(kv_error("Failed to read block"))?
+                    .map(|existing| decode::<StoredBlock>(existing.value()))
+                    .transpose()?;
+                if let Some(replaced) = &replaced {
+                    if replaced.block.summary.hash != block.summary.hash {
+                        headers
+                            .remove(replaced.block.summary.hash.to_string().as_str())
+                            .map_err(kv_error("Failed to drop header"))?;
+                        for txid in &replaced.block.txids {
+                            transactions
+                                .remove(txid.to_string().as_str())
+                                .map_err(kv_error("Failed to drop transaction"))?;
+                        }
+                        proofs
+                            .remove(height)
+                            .map_err(kv_error("Failed to drop proof"))?;
+                    }
+                }
+
+                // Re-ingesting the same block keeps its original ingestion
+                // time, and it may only now arrive verified
+                let previous =
+                    replaced.filter(|replaced| replaced.block.summary.hash == block.summary.hash);
+                let ingested_at = previous
+                    .as_ref()
+                    .map_or(now, |previous| previous.ingested_at);
+                if block.summary.verified
+                    && !previous.is_some_and(|previous| previous.block.summary.verified)
+                {
+                    milestones.push(ProofMilestone::Verified {
+                        height,
+                        secs: now - ingested_at,
+                    });
+                }
+
+                for (position, txid) in block.txids.iter().enumerate() {
+                    transactions
+                        .insert(txid.to_string().as_str(), (height, position as u32))
+                        .map_err(kv_error("Failed to insert transaction"))?;
+                }
+                headers
+                    .insert(block.summary.hash.to_string().as_str(), height)
+                    .map_err(kv_error("Failed to insert header"))?;
+
+                // Same proof discovery as the SQLite ingest
+                let proof_path = format!("data/proofs/{height}.json");
+                if let Ok(metadata) = std::fs::metadata(&proof_path) {
+                    let proof = StoredProof {
+                        file_path: proof_path,
+                        file_size: metadata.len() as i64,
+                        version: proof::PROOF_VERSION.to_string(),
+                        generated_at: block.summary.timestamp,
+                        execution_time_ms: Some(45000),
+                        recorded_at: now,
+                    };
+                    let reproved = proofs
+                        .insert(height, serde_json::to_vec(&proof)?.as_slice())
+                        .map_err(kv_error("Failed to insert proof"))?
+                        .is_some();
+                    if !reproved {
+                        milestones.push(ProofMilestone::Proved {
+                            height,
+                            generated_secs: proof.generated_at - ingested_at,
+                            available_secs: now - ingested_at,
+                        });
+                    }
+                }
+
+                indexed.push(Event::BlockIndexed {
+                    height,
+                    hash: block.summary.hash,
+                });
+                let stored = StoredBlock { block, ingested_at };
+                block_table
+                    .insert(height, serde_json::to_vec(&stored)?.as_slice())
+                    .map_err(kv_error("Failed to insert block"))?;
+            }
+        }
+        txn.commit().map_err(kv_error("Failed to commit ingest"))?;
+
+        indexed.into_iter().for_each(events::publish);
+        for milestone in milestones {
+            ProofSlaConfig::global().observe(milestone);
+            events::publish(milestone.into());
+        }
+        Ok(())
+    }
+
+    /// Registers a proof as available for an ingested block.
+    pub fn record_proof(
+        &self,
+        height: u32,
+        file_path: &str,
+        file_size: i64,
+        generated_at: i64,
+        execution_time_ms: Option<i64>,
+    ) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let txn = self
+            .db
+            .begin_write()
+            .map_err(kv_error("Failed to begin proof write"))?;
+        let ingested_at = {
+            let blocks = txn
+                .open_table(BLOCKS)
+                .map_err(kv_error("Failed to open blocks"))?;
+            let stored = blocks
+                .get(height)
+                .map_err(kv_error("Failed to read block"))?
+                .ok_or_else(|| AppError::BlockNotFound(height.to_string()))?;
+            decode::<StoredBlock>(stored.value())?.ingested_at
+        };
+        {
+            let mut proofs = txn
+                .open_table(PROOFS)
+                .map_err(kv_error("Failed to open proofs"))?;
+            let proof = StoredProof {
+                file_path: file_path.to_string(),
+                file_size,
+                version: "v1.0".to_string(),
+                generated_at,
+                execution_time_ms,
+                recorded_at: now,
+            };
+            proofs
+                .insert(height, serde_json::to_vec(&proof)?.as_slice())
+                .map_err(kv_error("Failed to insert proof"))?;
+        }
+        txn.commit().map_err(kv_error("Failed to commit proof"))?;
+
+        let proved = ProofMilestone::Proved {
+            height,
+            generated_secs: generated_at - ingested_at,
+            available_secs: now - ingested_at,
+        };
+        ProofSlaConfig::global().observe(proved);
+        events::publish(proved.into());
+        Ok(())
+    }
+
+    fn read(&self) -> Result<ReadTransaction> {
+        self.db
+            .begin_read()
+            .map_err(kv_error("Failed to begin read"))
+    }
+
+    fn block(&self, txn: &ReadTransaction, height: u32) -> Result<Option<StoredBlock>> {
+        let blocks = txn
+            .open_table(BLOCKS)
+            .map_err(kv_error("Failed to open blocks"))?;
+        let stored = blocks
+            .get(height)
+            .map_err(kv_error("Failed to read block"))?;
+        stored.map(|s| decode(s.value())).transpose()
+    }
+
+    fn blocks(&self, txn: &ReadTransaction) -> Result<Vec<StoredBlock>> {
+        let blocks = txn
+            .open_table(BLOCKS)
+            .map_err(kv_error("Failed to open blocks"))?;
+        let iter = blocks.iter().map_err(kv_error("Failed to scan blocks"))?;
+        iter.map(|entry| {
+            let (_, value) = entry.map_err(kv_error("Failed to scan blocks"))?;
+            decode(value.value())
+        })
+        .collect()
+    }
+
+    fn proofs(&self, txn: &ReadTransaction) -> Result<Vec<(u32, StoredProof)>> {
+        let proofs = txn
+            .open_table(PROOFS)
+            .map_err(kv_error("Failed to open proofs"))?;
+        let iter = proofs.iter().map_err(kv_error("Failed to scan proofs"))?;
+        iter.map(|entry| {
+            let (key, value) = entry.map_err(kv_error("Failed to scan proofs"))?;
+            Ok((key.value(), decode(value.value())?))
+        })
+        .collect()
+    }
+
+    fn has_proof(&self, txn: &ReadTransaction, height: u32) -> Result<bool> {
+        let proofs = txn
+            .open_table(PROOFS)
+            .map_err(kv_error("Failed to open proofs"))?;
+        let exists = proofs
+            .get(height)
+            .map_err(kv_error("Failed to read proof"))?
+            .is_some();
+        Ok(exists)
+    }
+
+    fn tip(&self, txn: &ReadTransaction) -> Result<Option<u32>> {
+        let blocks = txn
+            .open_table(BLOCKS)
+            .map_err(kv_error("Failed to open blocks"))?;
+        let last = blocks.last().map_err(kv_error("Failed to read tip"))?;
+        Ok(last.map(|(key, _)| key.value()))
+    }
+
+    fn height_by_hash(&self, txn: &ReadTransaction, hash: &BlockHash) -> Result<Option<u32>> {
+        let headers = txn
+            .open_table(HEADERS)
+            .map_err(kv_error("Failed to open headers"))?;
+        let height = headers
+            .get(hash.to_string().as_str())
+            .map_err(kv_error("Failed to read header"))?;
+        Ok(height.map(|h| h.value()))
+    }
+
+    fn locate_transaction(&self, txn: &ReadTransaction, txid: &Txid) -> Result<Option<u32>> {
+        let transactions = txn
+            .open_table(TRANSACTIONS)
+            .map_err(kv_error("Failed to open transactions"))?;
+        let location = transactions
+            .get(txid.to_string().as_str())
+            .map_err(kv_error("Failed to read transaction"))?;
+        Ok(location.map(|l| l.value().0))
+    }
+}
+
+#[async_trait]
+impl BlockStore for KvStore {
+    async fn health_check(&self) -> Result<()> {
+        self.read().map(drop)
+    }
+
+    async fn tip_height(&self) -> Result<Option<u32>> {
+        self.tip(&self.read()?)
+    }
+
+    async fn get_blocks(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        direction: PageDirection,
+        order: SortOrder,
+        filter: &BlockFilter,
+    ) -> Result<BlocksResponse> {
+        let limit = limit.min(50) as usize;
+        let mut blocks: Vec<_> = self
+            .blocks(&self.read()?)?
+            .into_iter()
+            .map(|stored| stored.block.summary)
+            .filter(|b| filter.verified.is_none_or(|v| b.verified == v))
+            .filter(|b| filter.from_timestamp.is_none_or(|t| b.timestamp >= t))
+            .filter(|b| filter.to_timestamp.is_none_or(|t| b.timestamp <= t))
+            .collect();
+        let total = blocks.len() as u32;
+        let travel = direction.travel(order);
+        if travel == SortOrder::Desc {
+            blocks.reverse();
+        }
+
+        let blocks: Vec<_> = blocks
+            .into_iter()
+            .filter(|b| match (travel, cursor) {
+                (_, None) => true,
+                (SortOrder::Desc, Some(c)) => b.height < c,
+                (SortOrder::Asc, Some(c)) => b.height > c,
+            })
+            .take(limit + 1)
+            .collect();
+
+        Ok(BlocksResponse::page(
+            blocks, limit, cursor, direction, total,
+        ))
+    }
+
+    async fn get_orphans(&self, _limit: u32, _cursor: Option<i64>) -> Result<OrphansResponse> {
+        Ok(OrphansResponse {
+            orphans: Vec::new(),
+            has_next: false,
+            next_cursor: None,
+        })
+    }
+
+    async fn block_exists_by_identifier(&self, id: &BlockId) -> Result<bool> {
+        let txn = self.read()?;
+        match id {
+            BlockId::Height(height) => Ok(self.block(&txn, *height)?.is_some()),
+            BlockId::Hash(hash) => Ok(self.height_by_hash(&txn, hash)?.is_some()),
+        }
+    }
+
+    async fn get_block_by_height(&self, height: u32) -> Result<BlockDetail> {
+        self.block(&self.read()?, height)?
+            .map(|stored| stored.block)
+            .ok_or_else(|| AppError::BlockNotFound(height.to_string()))
+    }
+
+    async fn get_block_by_hash(&self, hash: &BlockHash) -> Result<BlockDetail> {
+        let txn = self.read()?;
+        self.height_by_hash(&txn, hash)?
+            .map(|height| self.block(&txn, height))
+            .transpose()?
+            .flatten()
+            .map(|stored| stored.block)
+            .ok_or_else(|| AppError::BlockNotFound(hash.to_string()))
+    }
+
+    async fn get_block_stats(&self, height: u32) -> Result<BlockStats> {
+        self.get_block_by_height(height).await?;
+        Err(AppError::BlockStatsUnavailable(height.to_string()))
+    }
+
+    async fn get_block_txids(
+        &self,
+        height: u32,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<BlockTxidsResponse> {
+        let block = self.get_block_by_height(height).await?;
+        let start = cursor.map_or(0, |c| c as usize + 1);
+        let txids: Vec<Txid> = block
+            .txids
+            .iter()
+            .skip(start)
+            .take(limit.min(1000) as usize)
+            .copied()
+            .collect();
+        let end = start + txids.len();
+        let has_next = end < block.txids.len();
+
+        Ok(BlockTxidsResponse {
+            height,
+            txids,
+            total: block.txids.len() as u32,
+            has_next,
+            next_cursor: has_next.then(|| end as u32 - 1),
+        })
+    }
+
+    async fn get_transaction_status(&self, txid: &Txid) -> Result<TransactionStatus> {
+        let txn = self.read()?;
+        let Some(height) = self.locate_transaction(&txn, txid)? else {
+            return Ok(TransactionStatus {
+                included: false,
+                block_height: None,
+                block_hash: None,
+                confirmations: None,
+                proof_url: None,
+                proof_verified: None,
+            });
+        };
+        let block = self
+            .block(&txn, height)?
+            .ok_or_else(|| AppError::BlockNotFound(height.to_string()))?
+            .block;
+        let tip = self.tip(&txn)?.unwrap_or(height);
+        let has_proof = self.has_proof(&txn, height)?;
+
+        Ok(TransactionStatus {
+            included: true,
+            block_height: Some(height),
+            block_hash: Some(block.summary.hash),
+            confirmations: Some(tip - height + 1),
+            proof_url: has_proof.then_some(block.proof_url),
+            proof_verified: has_proof.then_some(block.summary.verified),
+        })
+    }
+
+    async fn get_transaction_detail(&self, txid: &Txid) -> Result<TransactionDetail> {
+        Err(AppError::TransactionNotFound(txid.to_string()))
+    }
+
+    async fn get_raw_transaction(&self, txid: &Txid) -> Result<Vec<u8>> {
+        if self.locate_transaction(&self.read()?, txid)?.is_some() {
+            Err(AppError::RawTransactionUnavailable(txid.to_string()))
+        } else {
+            Err(AppError::TransactionNotFound(txid.to_string()))
+        }
+    }
+
+    async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<OutputStatus> {
+        Err(AppError::OutputNotFound(format!("{txid}:{vout}")))
+    }
+
+    fn address_index_enabled(&self) -> bool {
+        false
+    }
+
+    async fn get_address_transactions(
+        &self,
+        _script_pubkey: &str,
+    ) -> Result<Vec<AddressTransaction>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_address_utxos(&self, _script_pubkey: &str) -> Result<Vec<Utxo>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_header_status(&self, hash: &BlockHash) -> Result<HeaderStatus> {
+        let txn = self.read()?;
+        let Some(height) = self.height_by_hash(&txn, hash)? else {
+            return Ok(HeaderStatus {
+                block_hash: *hash,
+                in_chain: false,
+                block_height: None,
+                confirmations: None,
+                verified: false,
+                on_active_chain: false,
+            });
+        };
+        let verified = self
+            .block(&txn, height)?
+            .is_some_and(|stored| stored.block.summary.verified)
+            && self.has_proof(&txn, height)?;
+        let tip = self.tip(&txn)?.unwrap_or(height);
+
+        Ok(HeaderStatus {
+            block_hash: *hash,
+            in_chain: true,
+            block_height: Some(height),
+            confirmations: Some(tip - height + 1),
+            verified,
+            on_active_chain: true,
+        })
+    }
+
+    async fn get_network_stats(&self) -> Result<NetworkStats> {
+        let txn = self.read()?;
+        let block_count = txn
+            .open_table(BLOCKS)
+            .map_err(kv_error("Failed to open blocks"))?
+            .len()
+            .map_err(kv_error("Failed to count blocks"))?;
+        let proofs = self.proofs(&txn)?;
+
+        let average = |values: Vec<i64>| {
+            (!values.is_empty()).then(|| values.iter().sum::<i64>() as f64 / values.len() as f64)
+        };
+
+        Ok(NetworkStats {
+            indexed_height: self.tip(&txn)?,
+            proved_height: proofs.last().map(|(height, _)| *height),
+            proof_coverage: if block_count > 0 {
+                proofs.len() as f64 * 100.0 / block_count as f64
+            } else {
+                0.0
+            },
+            avg_proof_size: average(proofs.iter().map(|(_, p)| p.file_size).collect()),
+            avg_proving_time_ms: average(
+                proofs
+                    .iter()
+                    .filter_map(|(_, p)| p.execution_time_ms)
+                    .collect(),
+            ),
+            queue_depth: block_count.saturating_sub(proofs.len() as u64) as u32,
+            stale: false,
+        })
+    }
+
+    async fn proof_file_exists(&self, height: u32) -> Result<bool> {
+        self.has_proof(&self.read()?, height)
+    }
+
+    async fn get_proofs(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        verified: Option<bool>,
+        version: Option<&str>,
+    ) -> Result<ProofsResponse> {
+        let limit = limit.min(100) as usize;
+        let txn = self.read()?;
+
+        let mut records = Vec::new();
+        for (height, proof) in self.proofs(&txn)? {
+            if cursor.is_some_and(|c| height <= c) || version.is_some_and(|v| v != proof.version) {
+                continue;
+            }
+            let Some(stored) = self.block(&txn, height)? else {
+                continue;
+            };
+            if verified.is_some_and(|v| stored.block.summary.verified != v) {
+                continue;
+            }
+            records.push(ProofRecord {
+                height,
+                version: proof.version,
+                size: Some(proof.file_size),
+                verified: stored.block.summary.verified,
+                generated_at: proof.generated_at,
+                proof_url: stored.block.proof_url,
+            });
+            if records.len() > limit {
+                break;
+            }
+        }
+
+        let has_next = records.len() > limit;
+        records.truncate(limit);
+        let next_cursor = if has_next {
+            records.last().map(|r| r.height)
+        } else {
+            None
+        };
+
+        Ok(ProofsResponse {
+            proofs: records,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    async fn get_proof_coverage(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<ProofCoverageResponse> {
+        let txn = self.read()?;
+        let proved: Vec<u32> = self.proofs(&txn)?.into_iter().map(|(h, _)| h).collect();
+
+        // Failures and archival aren't tracked here, so every gap is unattempted
+        let mut gaps: Vec<ProofGap> = Vec::new();
+        for stored in self.blocks(&txn)? {
+            let height = stored.block.summary.height;
+            if height < cursor.unwrap_or(0) || proved.binary_search(&height).is_ok() {
+                continue;
+            }
+            match gaps.last_mut() {
+                Some(gap) if gap.end_height + 1 == height => {
+                    gap.end_height = height;
+                    gap.count += 1;
+                }
+                _ => gaps.push(ProofGap {
+                    start_height: height,
+                    end_height: height,
+                    count: 1,
+                    reason: ProofGapReason::NeverAttempted,
+                }),
+            }
+        }
+
+        gaps.truncate(limit as usize + 1);
+        let has_next = gaps.len() > limit as usize;
+        let next_cursor = if has_next {
+            gaps.pop().map(|gap| gap.start_height)
+        } else {
+            None
+        };
+
+        Ok(ProofCoverageResponse {
+            gaps,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    async fn get_proof_latencies(&self, window: u32) -> Result<Vec<i64>> {
+        let txn = self.read()?;
+        let mut proofs = self.proofs(&txn)?;
+        proofs.sort_by_key(|(_, p)| std::cmp::Reverse(p.recorded_at));
+
+        let mut latencies = Vec::new();
+        for (height, proof) in proofs.into_iter().take(window as usize) {
+            if let Some(stored) = self.block(&txn, height)? {
+                latencies.push(proof.recorded_at - stored.ingested_at);
+            }
+        }
+        latencies.sort_unstable();
+        Ok(latencies)
+    }
+
+    async fn count_unproved_older_than(&self, secs: i64) -> Result<u32> {
+        let txn = self.read()?;
+        let cutoff = Utc::now().timestamp() - secs;
+        let mut count = 0;
+        for stored in self.blocks(&txn)? {
+            if stored.ingested_at < cutoff && !self.has_proof(&txn, stored.block.summary.height)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn proof_backlog(&self) -> Result<ProofBacklog> {
+        let txn = self.read()?;
+        let mut backlog = ProofBacklog::default();
+        for stored in self.blocks(&txn)? {
+            if !self.has_proof(&txn, stored.block.summary.height)? {
+                backlog.blocks += 1;
+                backlog.oldest_ingested_at = Some(
+                    backlog
+                        .oldest_ingested_at
+                        .map_or(stored.ingested_at, |oldest| oldest.min(stored.ingested_at)),
+                );
+            }
+        }
+        Ok(backlog)
+    }
+
+    async fn submit_block(&self, block: &serde_json::Value) -> Result<()> {
+        self.insert_blocks(std::slice::from_ref(block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ingest_and_replace() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KvStore::open(&dir.path().join("raito.redb")).unwrap();
+        store.seed_data().unwrap();
+
+        assert_eq!(store.tip_height().await.unwrap(), Some(869123));
+        let tip = store.get_block_by_height(869123).await.unwrap();
+        assert_eq!(
+            store
+                .get_block_by_hash(&tip.summary.hash)
+                .await
+                .unwrap()
+                .summary
+                .height,
+            869123
+        );
+        let status = store.get_transaction_status(&tip.txids[0]).await.unwrap();
+        assert_eq!(status.confirmations, Some(1));
+        assert!(store.proof_file_exists(869123).await.unwrap());
+
+        let replacement = serde_json::json!({
+            "height": 869123,
+            "hash": "1".repeat(64),
+            "prev_hash": tip.prev_hash,
+            "merkle_root": tip.merkle_root,
+            "version": tip.version,
+            "bits": tip.bits,
+            "nonce": 7,
+            "tx_count": 0,
+            "total_fees": 0.0,
+            "timestamp": tip.summary.timestamp,
+            "verified": false,
+            "txids": []
+        });
+        store.insert_blocks(&[replacement]).unwrap();
+
+        let status = store.get_transaction_status(&tip.txids[0]).await.unwrap();
+        assert!(!status.included);
+        let header = store.get_header_status(&tip.summary.hash).await.unwrap();
+        assert!(!header.in_chain);
+        let header = store
+            .get_header_status(&"1".repeat(64).parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(header.block_height, Some(869123));
+
+        assert!(!store.proof_file_exists(869121).await.unwrap());
+        store.record_proof(869121, "p.json", 10, 0, None).unwrap();
+        assert!(store.proof_file_exists(869121).await.unwrap());
+    }
+}
@@ -1,11 +1,64 @@
+pub mod access_log;
+pub mod address;
+pub mod admin;
+pub mod audit;
+pub mod auth;
+pub mod breaker;
+pub mod broadcast;
+pub mod cache;
+pub mod cache_control;
+pub mod capacity;
+pub mod chain;
+pub mod coinbase;
+pub mod config;
+pub mod consistency;
 pub mod database;
+pub mod deprecations;
+pub mod encoding;
+pub mod encryption;
 pub mod error;
+pub mod etag;
+pub mod events;
 pub mod handlers;
+pub mod hash;
+pub mod idempotency;
+pub mod ingest;
+pub mod jobs;
+pub mod jwt;
+pub mod kv;
+pub mod load;
+pub mod logging;
+pub mod maintenance;
 pub mod middleware;
 pub mod model;
+pub mod pagination;
+pub mod proof;
+pub mod proto;
+pub mod prune;
+pub mod rate_limit;
+pub mod raw_block;
+pub mod reload;
+pub mod reorg;
+pub mod rpc;
+pub mod sandbox;
+pub mod secrets;
+pub mod sla;
+pub mod smoke;
+pub mod staleness;
+pub mod stats;
+pub mod storage;
 pub mod store;
+pub mod telemetry;
+pub mod tls;
+pub mod usage;
+pub mod v2;
+pub mod validation;
+pub mod version;
+pub mod warm;
+pub mod webhooks;
+pub mod witness;
 
 pub use database::{Database, DatabaseConfig};
 pub use error::{AppError, Result};
 pub use model::{BlockDetail, BlockSummary, HeaderStatus, TransactionStatus};
-pub use store::MockStore;
+pub use store::{BlockStore, MockStore};
@@ -0,0 +1,363 @@
+use crate::{
+    config::{self, Lookup},
+    error::AppError,
+    telemetry::Metrics,
+};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use http_body::{Frame, SizeHint};
+use once_cell::sync::Lazy;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+static LOAD_SHED_CONFIG: Lazy<LoadShedConfig> = Lazy::new(LoadShedConfig::from_env);
+
+#[derive(Debug, Clone)]
+pub struct LoadShedConfig {
+    /// In-flight requests above which list endpoints start shrinking pages.
+    pub threshold: usize,
+    /// Largest page served while the service is under load.
+    pub page_limit: u32,
+    /// Seconds clients are asked to wait before requesting a full page again.
+    pub retry_after_secs: u32,
+}
+
+impl Default for LoadShedConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 64,
+            page_limit: 5,
+            retry_after_secs: 2,
+        }
+    }
+}
+
+impl LoadShedConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            threshold: std::env::var("LOAD_SHED_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.threshold),
+            page_limit: std::env::var("LOAD_SHED_PAGE_LIMIT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.page_limit),
+            retry_after_secs: std::env::var("LOAD_SHED_RETRY_AFTER_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.retry_after_secs),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &LOAD_SHED_CONFIG
+    }
+
+    /// Returns the reduced page size to serve instead of `requested`, if the
+    /// service is under load and the request asks for more than it allows.
+    pub fn limit_page(&self, requested: u32, in_flight: usize) -> Option<u32> {
+        (in_flight > self.threshold && requested > self.page_limit).then_some(self.page_limit)
+    }
+}
+
+pub fn in_flight_requests() -> usize {
+    IN_FLIGHT.load(Ordering::Relaxed)
+}
+
+struct InFlightGuard;
+
+impl InFlightGuard {
+    fn new() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub async fn in_flight_middleware(request: Request, next: Next) -> Response {
+    let _guard = InFlightGuard::new();
+    next.run(request).await
+}
+
+#[derive(Debug, Clone)]
+pub struct ConcurrencyConfig {
+    /// API requests served at once before new ones get 503; 0 disables the limit.
+    pub max_requests: usize,
+    /// Proof downloads streamed at once before new ones get 429; 0 disables the limit.
+    pub max_proof_downloads: usize,
+    /// `Retry-After` sent with a rejected request.
+    pub retry_after_secs: u64,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_requests: 512,
+            max_proof_downloads: 32,
+            retry_after_secs: 1,
+        }
+    }
+}
+
+impl ConcurrencyConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(&config::env)
+    }
+
+    pub fn from_lookup(lookup: Lookup) -> Self {
+        let defaults = Self::default();
+        Self {
+            max_requests: lookup("MAX_CONCURRENT_REQUESTS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_requests),
+            max_proof_downloads: lookup("MAX_CONCURRENT_PROOF_DOWNLOADS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_proof_downloads),
+            retry_after_secs: lookup("CONCURRENCY_RETRY_AFTER_SECS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.retry_after_secs),
+        }
+    }
+
+    /// Limit shared by every API route; overflowing it means the service as
+    /// a whole is overloaded.
+    pub fn global_limit(&self) -> ConcurrencyLimit {
+        ConcurrencyLimit::new("global", None, self.max_requests, self.retry_after_secs)
+    }
+
+    /// Limit on proof downloads alone, which hold disk bandwidth for as long
+    /// as the client takes to read them.
+    pub fn proof_download_limit(&self) -> ConcurrencyLimit {
+        ConcurrencyLimit::new(
+            "proof_downloads",
+            Some("proof downloads"),
+            self.max_proof_downloads,
+            self.retry_after_secs,
+        )
+    }
+}
+
+/// A pool of slots that requests take on arrival and give back once their
+/// response body has been sent. Requests that find no free slot are shed
+/// immediately rather than queued.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit {
+    name: &'static str,
+    /// What a per-route limit guards, reported with its 429; global limits
+    /// answer 503 instead.
+    resource: Option<&'static str>,
+    permits: Arc<Semaphore>,
+    /// Current limit; 0 disables it.
+    max: Arc<AtomicUsize>,
+    /// Slots the semaphore was last sized to, which `max` leaves alone while
+    /// the limit is disabled.
+    capacity: Arc<Mutex<usize>>,
+    retry_after_secs: u64,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(
+        name: &'static str,
+        resource: Option<&'static str>,
+        max: usize,
+        retry_after_secs: u64,
+    ) -> Self {
+        Self {
+            name,
+            resource,
+            permits: Arc::new(Semaphore::new(max)),
+            max: Arc::new(AtomicUsize::new(max)),
+            capacity: Arc::new(Mutex::new(max)),
+            retry_after_secs,
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max.load(Ordering::Relaxed)
+    }
+
+    /// Changes the limit in place. Requests holding a slot keep it: slots
+    /// beyond a lowered limit are retired as those requests finish, so
+    /// nothing in flight is cut off.
+    pub fn resize(&self, max: usize) {
+        self.max.store(max, Ordering::Relaxed);
+        if max == 0 {
+            return;
+        }
+        let mut capacity = self
+            .capacity
+            .lock()
+            .expect("concurrency limit lock poisoned");
+        if max > *capacity {
+            self.permits.add_permits(max - *capacity);
+        } else if max < *capacity {
+            let surplus = (*capacity - max) as u32;
+            let permits = self.permits.clone();
+            tokio::spawn(async move {
+                if let Ok(retired) = permits.acquire_many_owned(surplus).await {
+                    retired.forget();
+                }
+            });
+        }
+        *capacity = max;
+    }
+
+    fn rejection(&self) -> AppError {
+        match self.resource {
+            Some(resource) => AppError::TooManyRequests {
+                resource: resource.to_string(),
+                retry_after_secs: self.retry_after_secs,
+            },
+            None => AppError::Overloaded {
+                retry_after_secs: self.retry_after_secs,
+            },
+        }
+    }
+}
+
+pub async fn concurrency_limit_middleware(
+    State(limit): State<ConcurrencyLimit>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if limit.max() == 0 {
+        return next.run(request).await;
+    }
+    let Ok(permit) = limit.permits.clone().try_acquire_owned() else {
+        Metrics::global().record_shed_request(limit.name);
+        return limit.rejection().into_response();
+    };
+
+    let (parts, body) = next.run(request).await.into_parts();
+    Response::from_parts(
+        parts,
+        Body::new(PermitBody {
+            body,
+            _permit: permit,
+        }),
+    )
+}
+
+/// Holds a concurrency slot until the response body, which may be a
+/// long-running proof stream, is finished or dropped.
+struct PermitBody {
+    body: Body,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl http_body::Body for PermitBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        Pin::new(&mut self.body).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_limit_page_only_under_load() {
+        let config = LoadShedConfig::default();
+        assert_eq!(config.limit_page(20, 0), None);
+        assert_eq!(config.limit_page(20, config.threshold + 1), Some(5));
+        assert_eq!(config.limit_page(3, config.threshold + 1), None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_sheds_until_body_sent() {
+        let config = ConcurrencyConfig {
+            max_requests: 1,
+            max_proof_downloads: 1,
+            retry_after_secs: 3,
+        };
+        let request = || Request::get("/").body(Body::empty()).unwrap();
+
+        for (limit, status) in [
+            (config.global_limit(), StatusCode::SERVICE_UNAVAILABLE),
+            (config.proof_download_limit(), StatusCode::TOO_MANY_REQUESTS),
+        ] {
+            let app = Router::new().route("/", get(|| async { "proof" })).layer(
+                middleware::from_fn_with_state(limit, concurrency_limit_middleware),
+            );
+
+            // The slot stays taken while the first body is unsent
+            let first = app.clone().oneshot(request()).await.unwrap();
+            let rejected = app.clone().oneshot(request()).await.unwrap();
+            assert_eq!(rejected.status(), status);
+            assert_eq!(rejected.headers()["retry-after"], "3");
+
+            drop(first);
+            let next = app.oneshot(request()).await.unwrap();
+            assert_eq!(next.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_resize() {
+        let limit = ConcurrencyLimit::new("test", Some("test"), 1, 1);
+        let app = Router::new().route("/", get(|| async { "proof" })).layer(
+            middleware::from_fn_with_state(limit.clone(), concurrency_limit_middleware),
+        );
+        let status = || async {
+            let request = Request::get("/").body(Body::empty()).unwrap();
+            app.clone().oneshot(request).await.unwrap()
+        };
+
+        let first = status().await;
+        assert_eq!(status().await.status(), StatusCode::TOO_MANY_REQUESTS);
+        limit.resize(2);
+        let second = status().await;
+        assert_eq!(second.status(), StatusCode::OK);
+
+        // Lowering the limit leaves both in-flight bodies alone
+        limit.resize(1);
+        assert_eq!(status().await.status(), StatusCode::TOO_MANY_REQUESTS);
+        drop((first, second));
+        tokio::task::yield_now().await;
+        let only = status().await;
+        assert_eq!(only.status(), StatusCode::OK);
+        assert_eq!(status().await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        drop(only);
+        limit.resize(0);
+        let (a, b) = (status().await, status().await);
+        assert_eq!((a.status(), b.status()), (StatusCode::OK, StatusCode::OK));
+    }
+}
@@ -0,0 +1,197 @@
+use crate::config::{self, Lookup};
+use once_cell::sync::OnceCell;
+use std::{io, path::PathBuf, str::FromStr};
+use tracing::Subscriber;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{self, Rotation},
+};
+use tracing_subscriber::{
+    fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan, reload, util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+static FILTER: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+
+const DEFAULT_DIRECTIVES: &str = "info,raito_proving_service=debug,tower_http=debug,sqlx=info";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// One JSON object per event, for log shippers.
+    #[default]
+    Json,
+    /// Multi-line, human-readable output for local development.
+    Pretty,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "pretty" => Ok(Self::Pretty),
+            other => Err(format!("unknown log format: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "minutely" => Ok(Self::Minutely),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            "never" => Ok(Self::Never),
+            other => Err(format!("unknown log rotation: {other}")),
+        }
+    }
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Filter directives replacing the built-in defaults; `RUST_LOG` still
+    /// takes precedence when set.
+    pub level: Option<String>,
+    /// Also write logs to this file, rotated by `rotation`. Rotated files get
+    /// a date suffix.
+    pub file: Option<PathBuf>,
+    pub rotation: LogRotation,
+    /// Rotated files kept before the oldest is deleted; all by default.
+    pub max_files: Option<usize>,
+}
+
+impl LogConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(&config::env)
+    }
+
+    pub fn from_lookup(lookup: Lookup) -> Self {
+        let defaults = Self::default();
+        Self {
+            format: lookup("LOG_FORMAT")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.format),
+            level: lookup("LOG_LEVEL").filter(|v| !v.is_empty()),
+            file: lookup("LOG_FILE")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+            rotation: lookup("LOG_ROTATION")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.rotation),
+            max_files: lookup("LOG_MAX_FILES")
+                .and_then(|v| v.parse().ok())
+                .filter(|&files| files > 0),
+        }
+    }
+
+    fn filter(&self) -> EnvFilter {
+        EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new(self.level.as_deref().unwrap_or(DEFAULT_DIRECTIVES)))
+    }
+
+    /// Installs the global subscriber. The returned guard flushes the log
+    /// file when dropped, so it must live as long as the process logs.
+    pub fn init(&self) -> io::Result<Option<WorkerGuard>> {
+        let mut layers = vec![format_layer(self.format, io::stdout, true)];
+
+        let mut guard = None;
+        if let Some(path) = &self.file {
+            let directory = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or_else(|| std::path::Path::new("."));
+            let file_name = path.file_name().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "LOG_FILE has no file name")
+            })?;
+
+            let mut appender = rolling::Builder::new()
+                .rotation(self.rotation.into())
+                .filename_prefix(file_name.to_string_lossy());
+            if let Some(max_files) = self.max_files {
+                appender = appender.max_log_files(max_files);
+            }
+            let appender = appender
+                .build(directory)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+
+            let (writer, file_guard) = tracing_appender::non_blocking(appender);
+            layers.push(format_layer(self.format, writer, false));
+            guard = Some(file_guard);
+        }
+
+        let (filter, handle) = reload::Layer::new(self.filter());
+        let _ = FILTER.set(handle);
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(layers)
+            .init();
+        Ok(guard)
+    }
+
+    /// Swaps the installed filter for this config's level without
+    /// restarting; the format and file stay as they were at startup. Returns
+    /// whether there was a subscriber to update.
+    pub fn reload_filter(&self) -> io::Result<bool> {
+        let Some(handle) = FILTER.get() else {
+            return Ok(false);
+        };
+        handle.reload(self.filter()).map_err(io::Error::other)?;
+        Ok(true)
+    }
+}
+
+fn format_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_target(true)
+        .with_thread_ids(true)
+        .with_line_number(true);
+    match format {
+        LogFormat::Json => layer.json().boxed(),
+        LogFormat::Pretty => layer.pretty().with_ansi(ansi).boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_settings() {
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("pretty".parse::<LogFormat>(), Ok(LogFormat::Pretty));
+        assert!("xml".parse::<LogFormat>().is_err());
+
+        assert_eq!("hourly".parse::<LogRotation>(), Ok(LogRotation::Hourly));
+        assert_eq!("Never".parse::<LogRotation>(), Ok(LogRotation::Never));
+        assert!("weekly".parse::<LogRotation>().is_err());
+    }
+}
@@ -1,114 +1,754 @@
-use axum::{middleware, routing::get, Router};
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post},
+    Extension, Router,
+};
+use clap::{Args, Parser, Subcommand};
 use raito_proving_service::{
-    database::{Database, DatabaseConfig},
+    access_log::{access_log_middleware, AccessLogConfig},
+    admin::{self, admin_auth_middleware},
+    auth::{api_key_middleware, require_write_middleware, AuthConfig},
+    capacity::{self, CapacityConfig},
+    config,
+    consistency::{self, ConsistencyConfig},
+    database::{self, Database, DatabaseConfig},
+    deprecations::{self, deprecation_middleware, DEPRECATIONS},
+    encoding::response_encoding_middleware,
+    encryption::ColumnCipher,
+    events,
     handlers::{
-        get_block_by_identifier, get_block_proof, get_blocks, get_header_status,
-        get_transaction_status, health_check, metrics_handler, ApiDoc,
+        broadcast_transaction, get_address_transactions, get_address_utxos,
+        get_block_by_identifier, get_block_proof, get_block_stats, get_block_txids, get_blocks,
+        get_chain_info, get_header_status, get_network_stats, get_orphans, get_output_status,
+        get_proof_coverage, get_proof_job, get_proof_latency, get_proofs, get_raw_header,
+        get_raw_transaction, get_transaction_detail, get_transaction_status, get_usage,
+        get_version, health_check, metrics_handler, readiness_check, submit_block,
+        upload_block_proof, ApiDoc,
+    },
+    idempotency::{idempotency_middleware, IdempotencyConfig},
+    ingest::{self, IngestConfig},
+    kv::{KvConfig, KvStore},
+    load::{concurrency_limit_middleware, in_flight_middleware},
+    logging::LogConfig,
+    maintenance::maintenance_middleware,
+    middleware::{
+        body_limit_middleware, compression_layer, cors_middleware, metrics_middleware,
+        security_headers_middleware, timeout_middleware, weaken_compressed_etag, BodyLimitConfig,
+        CompressionConfig, TimeoutConfig,
     },
-    middleware::{cors_layer, metrics_middleware, security_headers_middleware},
+    proof,
+    prune::{self, PruneConfig},
+    rate_limit::rate_limit_middleware,
+    reload::{self, Reloader},
+    reorg, rpc,
+    sandbox::{self, SandboxConfig},
+    sla::{self, ProofSlaConfig},
+    smoke,
+    staleness::{self, staleness_middleware, StalenessConfig},
+    storage::{self, DualReadStorage, FsProofStorage, ProofStorage},
+    store::StoreBackend,
+    telemetry::trace_context_middleware,
+    tls::{self, CertificateReloader, TlsConfig},
+    usage::usage_middleware,
+    v2,
+    version::v2_scope_middleware,
+    warm::{self, ProofWarmConfig},
+    webhooks::{self, WebhookConfig},
+    BlockStore,
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
 };
-use std::{net::SocketAddr, sync::Arc};
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
 use tracing::info;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_appender::non_blocking::WorkerGuard;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+/// Serves Bitcoin blocks with their STARK proofs, and runs the operational
+/// tasks around the same database and proof storage.
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Serve arguments also work without `serve`, which is the default.
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Debug, Args)]
+struct ServeArgs {
+    /// Serve a throwaway in-memory chain that grows by one proved block at a time
+    #[arg(long)]
+    sandbox: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run the HTTP server (the default)
+    Serve(ServeArgs),
+    /// Apply pending database migrations
+    Migrate,
+    /// Load the bundled mock blocks into the database
+    Seed,
+    /// Ingest a directory of `<height>.json` block files
+    Import { dir: PathBuf },
+    /// Write every block to a directory as `<height>.json`, readable by `import`
+    Export { dir: PathBuf },
+    /// Store the prover's proof of block `height` and start serving it
+    Prove {
+        height: u32,
+        /// Proof file written by the prover
+        #[arg(long)]
+        proof: PathBuf,
+    },
+    /// Check the stored proof of block `height` against the indexed block
+    Verify { height: u32 },
+    /// Run end-to-end contract checks against a deployment
+    Smoke {
+        #[arg(long, default_value = "http://localhost:8080")]
+        base_url: String,
+    },
+    /// Encrypt secret columns with the current ENCRYPTION_KEYS
+    EncryptSecrets,
+    /// Copy and checksum-verify every proof into a new storage directory
+    MigrateProofs {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    init_tracing();
+    let cli = Cli::parse();
 
-    info!("Starting Raito Proving Service");
+    // Settings from the config file must be in place before anything reads
+    // the environment
+    let config_file = match config::load().await {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
 
-    // Initialize database
-    let db_config = DatabaseConfig::from_env();
-    let database = Database::new(db_config).await?;
+    let result = match cli.command.unwrap_or(Command::Serve(cli.serve)) {
+        Command::Serve(args) => return serve(args, config_file).await,
+        Command::Migrate => migrate().await,
+        Command::Seed => seed().await,
+        Command::Import { dir } => import(&dir).await,
+        Command::Export { dir } => export(&dir).await,
+        Command::Prove { height, proof } => prove(height, &proof).await,
+        Command::Verify { height } => verify(height).await,
+        Command::Smoke { base_url } => run_smoke(&base_url).await,
+        Command::EncryptSecrets => encrypt_secrets().await,
+        Command::MigrateProofs { from, to } => migrate_proofs(&from, &to).await,
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+    Ok(())
+}
 
-    // Seed database with mock data if it's empty
-    let should_seed = std::env::var("DATABASE_SEED")
-        .unwrap_or_else(|_| "true".to_string())
-        .parse()
-        .unwrap_or(true);
+async fn serve(
+    args: ServeArgs,
+    config_file: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _log_guard = init_tracing()?;
+    let _access_log_guard = AccessLogConfig::from_env().init()?;
 
-    if should_seed {
-        database.seed_data().await?;
-        info!("Database seeded with mock data");
+    if let Some(path) = config_file {
+        info!(path = %path.display(), "Loaded configuration file");
     }
 
-    let db = Arc::new(database);
+    let sandbox = args.sandbox;
+    // Sandbox mode drives the SQLite ingest path directly
+    let backend = if sandbox {
+        StoreBackend::Sqlite
+    } else {
+        StoreBackend::from_env()
+    };
+    info!(sandbox, ?backend, "Starting Raito Proving Service");
+
+    // Seed database with mock data if it's empty
+    let should_seed = sandbox
+        || std::env::var("DATABASE_SEED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse()
+            .unwrap_or(true);
+
+    let store: Arc<dyn BlockStore> = match backend {
+        StoreBackend::Sqlite => start_database(sandbox, should_seed).await?,
+        StoreBackend::Redb => {
+            let kv = KvStore::open(&KvConfig::from_env().path)?;
+            if should_seed {
+                kv.seed_data()?;
+            }
+            Arc::new(kv)
+        }
+    };
 
     let port = std::env::var("PORT")
         .unwrap_or_else(|_| "8080".to_string())
         .parse::<u16>()
         .unwrap_or(8080);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let host = std::env::var("HOST")
+        .ok()
+        .and_then(|v| v.parse::<IpAddr>().ok())
+        .unwrap_or(IpAddr::from([0, 0, 0, 0]));
+    let addr = SocketAddr::new(host, port);
     info!("Server will listen on {}", addr);
 
-    let app = create_app(db);
+    sla::spawn_backlog_monitor(store.clone(), ProofSlaConfig::global());
+    capacity::spawn_capacity_monitor(storage::global(), CapacityConfig::global());
+    staleness::spawn_staleness_monitor(store.clone(), StalenessConfig::global());
+    let reloader = Arc::new(Reloader::from_env());
+    let tls = TlsConfig::from_env();
+    let certificate = CertificateReloader::new(tls.clone())?;
+    if let Some(certificate) = &certificate {
+        let _ = reloader.certificate.set(certificate.clone());
+        tls::spawn_certificate_watcher(certificate.clone());
+    }
+    reload::spawn_sighup_handler(reloader.clone())?;
+    let app = build_app(store, reloader);
 
     let listener = TcpListener::bind(addr).await?;
+    if let Some(certificate) = certificate {
+        info!(
+            client_certs = tls.requires_client_cert(),
+            "Server listening on {} with TLS", addr
+        );
+        axum_server::from_tcp_rustls(listener.into_std()?, certificate.rustls_config())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await?;
+        return Ok(());
+    }
     info!("Server listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
 
-fn create_app(database: Arc<Database>) -> Router {
-    let api_routes = Router::new()
+/// Opens the SQLite database and starts the jobs that maintain it.
+async fn start_database(
+    sandbox: bool,
+    should_seed: bool,
+) -> Result<Arc<Database>, Box<dyn std::error::Error>> {
+    // Initialize database
+    let mut db_config = DatabaseConfig::from_env();
+    let sandbox_config = SandboxConfig::from_env();
+    if sandbox {
+        // Sandbox state is throwaway: an in-memory chain, and dummy proofs
+        // written to a scratch directory while the bundled proofs stay readable
+        db_config.database_url = "sqlite::memory:".to_string();
+        storage::set_global(Box::new(DualReadStorage::new(
+            Box::new(FsProofStorage::new(&sandbox_config.proof_dir)),
+            storage::from_env(),
+        )));
+    }
+    let database = Database::new(db_config).await?;
+
+    if should_seed {
+        database.seed_data().await?;
+        info!("Database seeded with mock data");
+    }
+
+    let db = Arc::new(database);
+    reorg::spawn_tombstone_gc(db.clone());
+    database::spawn_replica_monitor(db.clone());
+    ingest::spawn_initial_sync(db.clone(), IngestConfig::from_env());
+    prune::spawn_pruner(db.clone(), PruneConfig::from_env());
+    warm::spawn_proof_warmer(db.clone(), storage::global(), ProofWarmConfig::from_env());
+    consistency::spawn_consistency_checker(
+        db.clone(),
+        storage::global(),
+        ConsistencyConfig::from_env(),
+    );
+    webhooks::spawn_webhook_dispatcher(db.clone(), WebhookConfig::from_env())?;
+    if sandbox {
+        sandbox::spawn_block_generator(db.clone(), storage::global(), &sandbox_config);
+    }
+
+    Ok(db)
+}
+
+#[cfg(test)]
+fn create_app(store: Arc<dyn BlockStore>) -> Router {
+    build_app(store, Arc::new(Reloader::from_env()))
+}
+
+/// The app with its reloadable limits and CORS policy held by `reloader`.
+fn build_app(store: Arc<dyn BlockStore>, reloader: Arc<Reloader>) -> Router {
+    // JSON responses are compressed; proof downloads keep their byte ranges
+    let compression = CompressionConfig::from_env();
+    let timeouts = TimeoutConfig::from_env();
+    let body_limits = BodyLimitConfig::from_env();
+    let json_layers = ServiceBuilder::new()
+        .layer(middleware::from_fn_with_state(
+            reloader.api_limiter.clone(),
+            rate_limit_middleware,
+        ))
+        .layer(middleware::map_response(weaken_compressed_etag))
+        .layer(compression_layer(&compression))
+        .layer(middleware::from_fn(response_encoding_middleware))
+        .layer(middleware::from_fn_with_state(
+            timeouts.lookup(),
+            timeout_middleware,
+        ));
+    let api_layers = ServiceBuilder::new()
+        .layer(middleware::from_fn(in_flight_middleware))
+        .layer(middleware::from_fn(metrics_middleware))
+        .layer(middleware::from_fn_with_state(
+            reloader.global_limit.clone(),
+            concurrency_limit_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            DEPRECATIONS,
+            deprecation_middleware,
+        ))
+        .layer(middleware::from_fn(security_headers_middleware))
+        .layer(middleware::from_fn_with_state(
+            StalenessConfig::global(),
+            staleness_middleware,
+        ))
+        // Ahead of the rate limiters, which count keyed clients by key
+        .layer(middleware::from_fn_with_state(
+            AuthConfig::global().reads(store.clone()),
+            api_key_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            store.clone(),
+            usage_middleware,
+        ));
+
+    let json_routes = Router::new()
         .route("/blocks", get(get_blocks))
+        .route("/blocks/orphans", get(get_orphans))
         .route("/blocks/:identifier", get(get_block_by_identifier))
-        .route("/blocks/:height/proof", get(get_block_proof))
+        .route("/blocks/:identifier/stats", get(get_block_stats))
+        .route("/blocks/:identifier/txids", get(get_block_txids))
+        .route(
+            "/tx",
+            post(broadcast_transaction)
+                .route_layer(DefaultBodyLimit::max(body_limits.jobs_bytes))
+                .route_layer(middleware::from_fn_with_state(
+                    body_limits.jobs_bytes,
+                    body_limit_middleware,
+                )),
+        )
         .route("/tx/:txid", get(get_transaction_status))
+        .route("/tx/:txid/detail", get(get_transaction_detail))
+        .route("/tx/:txid/raw", get(get_raw_transaction))
+        .route("/utxo/:txid/:vout", get(get_output_status))
+        .route("/address/:address/txs", get(get_address_transactions))
+        .route("/address/:address/utxos", get(get_address_utxos))
         .route("/header/:hash", get(get_header_status))
-        .with_state(database.clone())
-        .layer(
-            ServiceBuilder::new()
-                .layer(middleware::from_fn(metrics_middleware))
-                .layer(middleware::from_fn(security_headers_middleware)),
-        );
+        .route("/header/:hash/raw", get(get_raw_header))
+        .route("/proofs", get(get_proofs))
+        .route("/proofs/coverage", get(get_proof_coverage))
+        .route("/jobs/:id", get(get_proof_job))
+        .route("/chain", get(get_chain_info))
+        .route("/version", get(get_version))
+        .route("/stats", get(get_network_stats))
+        .route("/stats/proof-latency", get(get_proof_latency))
+        .route("/usage", get(get_usage))
+        .route(
+            "/rpc",
+            post(rpc::rpc_handler)
+                .route_layer(DefaultBodyLimit::max(body_limits.jobs_bytes))
+                .route_layer(middleware::from_fn_with_state(
+                    body_limits.jobs_bytes,
+                    body_limit_middleware,
+                )),
+        )
+        .layer(json_layers.clone());
+
+    let api_routes = json_routes
+        .route("/ws", get(events::ws_handler))
+        .route("/events", get(events::sse_handler))
+        .route(
+            "/blocks",
+            post(submit_block)
+                .route_layer(middleware::from_fn_with_state(
+                    (store.clone(), IdempotencyConfig::global()),
+                    idempotency_middleware,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    timeouts.submission(),
+                    timeout_middleware,
+                ))
+                .route_layer(middleware::from_fn(require_write_middleware))
+                .route_layer(DefaultBodyLimit::max(body_limits.block_bytes))
+                .route_layer(middleware::from_fn_with_state(
+                    body_limits.block_bytes,
+                    body_limit_middleware,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    reloader.api_limiter.clone(),
+                    rate_limit_middleware,
+                )),
+        )
+        .route(
+            "/blocks/:height/proof",
+            get(get_block_proof)
+                .route_layer(middleware::from_fn_with_state(
+                    timeouts.proof_download(),
+                    timeout_middleware,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    reloader.proof_download_limit.clone(),
+                    concurrency_limit_middleware,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    reloader.proofs_limiter.clone(),
+                    rate_limit_middleware,
+                )),
+        )
+        .route(
+            "/blocks/:height/proof",
+            post(upload_block_proof)
+                .route_layer(middleware::from_fn_with_state(
+                    (store.clone(), IdempotencyConfig::global()),
+                    idempotency_middleware,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    timeouts.submission(),
+                    timeout_middleware,
+                ))
+                .route_layer(middleware::from_fn(require_write_middleware))
+                .route_layer(DefaultBodyLimit::max(body_limits.proof_upload_bytes))
+                .route_layer(middleware::from_fn_with_state(
+                    body_limits.proof_upload_bytes,
+                    body_limit_middleware,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    reloader.api_limiter.clone(),
+                    rate_limit_middleware,
+                )),
+        )
+        .with_state(store.clone())
+        .layer(api_layers.clone());
+
+    // Resources whose models changed; the rest are only served under /v1
+    let v2_routes = Router::new()
+        .route("/blocks", get(v2::get_blocks))
+        .route("/blocks/orphans", get(v2::get_orphans))
+        .route("/blocks/:identifier", get(v2::get_block_by_identifier))
+        .route("/tx/:txid", get(v2::get_transaction_status))
+        .route("/tx/:txid/detail", get(v2::get_transaction_detail))
+        .route("/utxo/:txid/:vout", get(v2::get_output_status))
+        .route("/address/:address/utxos", get(v2::get_address_utxos))
+        .route("/proofs", get(v2::get_proofs))
+        .layer(json_layers)
+        .with_state(store.clone())
+        .layer(api_layers)
+        // Outermost, so errors from the layers above are v2 problems too
+        .layer(middleware::from_fn(v2_scope_middleware));
+
+    let admin_routes = Router::new()
+        .route("/prune", post(admin::prune))
+        .route("/reseed", post(admin::reseed))
+        .route("/reindex", post(admin::reindex))
+        .route("/cache/invalidate", post(admin::invalidate_cache))
+        .route("/proofs/requeue", post(admin::requeue_failed_proofs))
+        .route("/config", get(admin::get_config))
+        .route(
+            "/api-keys",
+            post(admin::create_api_key).get(admin::list_api_keys),
+        )
+        .route("/api-keys/:id", delete(admin::revoke_api_key))
+        .route(
+            "/webhooks",
+            post(admin::create_webhook).get(admin::list_webhooks),
+        )
+        .route(
+            "/webhooks/:id",
+            get(admin::get_webhook)
+                .patch(admin::update_webhook)
+                .delete(admin::delete_webhook),
+        )
+        .route("/webhooks/:id/deliveries", get(admin::webhook_deliveries))
+        .route("/audit-log", get(admin::audit_log))
+        .route("/conflicts", get(admin::block_conflicts))
+        .route("/consistency", get(admin::consistency_reports))
+        .route("/consistency/run", post(admin::run_consistency_check))
+        .route("/reload", post(admin::reload_config))
+        .route(
+            "/maintenance",
+            get(admin::get_maintenance).put(admin::set_maintenance),
+        )
+        .layer(Extension(reloader.clone()))
+        .with_state(store.clone())
+        .layer(middleware::from_fn_with_state(
+            timeouts.submission(),
+            timeout_middleware,
+        ))
+        .layer(middleware::from_fn(admin_auth_middleware))
+        .layer(DefaultBodyLimit::max(body_limits.admin_bytes))
+        .layer(middleware::from_fn_with_state(
+            body_limits.admin_bytes,
+            body_limit_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            reloader.admin_limiter.clone(),
+            rate_limit_middleware,
+        ));
 
     Router::new()
-        .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .merge(SwaggerUi::new("/docs").url(
+            "/api-docs/openapi.json",
+            deprecations::annotate_openapi(ApiDoc::openapi(), DEPRECATIONS),
+        ))
         .route("/healthz", get(health_check))
+        .route("/readyz", get(readiness_check))
         .route("/metrics", get(metrics_handler))
         .nest("/v1", api_routes)
-        .with_state(database)
+        .nest("/v2", v2_routes)
+        .nest("/admin", admin_routes)
+        .with_state(store)
         .layer(
             ServiceBuilder::new()
+                .layer(middleware::from_fn(access_log_middleware))
+                .layer(middleware::from_fn(trace_context_middleware))
                 .layer(TraceLayer::new_for_http())
-                .layer(cors_layer()),
+                .layer(middleware::from_fn_with_state(
+                    reloader.cors.clone(),
+                    cors_middleware,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    reloader.maintenance.clone(),
+                    maintenance_middleware,
+                )),
         )
 }
 
-fn init_tracing() {
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        EnvFilter::new("info,raito_proving_service=debug,tower_http=debug,sqlx=info")
-    });
+async fn encrypt_secrets() -> Result<(), Box<dyn std::error::Error>> {
+    let cipher = ColumnCipher::from_env()?.ok_or("ENCRYPTION_KEYS is not set")?;
+    let database = Database::new(DatabaseConfig::from_env()).await?;
+
+    let updated = database.reencrypt_secrets(&cipher).await?;
+    println!("Re-encrypted {updated} secret values");
+    Ok(())
+}
+
+async fn migrate() -> Result<(), Box<dyn std::error::Error>> {
+    let database = Database::new(DatabaseConfig {
+        run_migrations: true,
+        ..DatabaseConfig::from_env()
+    })
+    .await?;
+
+    let applied = database.applied_migrations().await?;
+    match applied.last() {
+        Some(version) => println!("{} migrations applied, at version {version}", applied.len()),
+        None => println!("No migrations applied"),
+    }
+    Ok(())
+}
+
+async fn seed() -> Result<(), Box<dyn std::error::Error>> {
+    match StoreBackend::from_env() {
+        StoreBackend::Sqlite => {
+            Database::new(DatabaseConfig::from_env())
+                .await?
+                .seed_data()
+                .await?
+        }
+        StoreBackend::Redb => KvStore::open(&KvConfig::from_env().path)?.seed_data()?,
+    }
+    println!("Seeded the database with mock data");
+    Ok(())
+}
+
+async fn import(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let database = Database::new(DatabaseConfig::from_env()).await?;
+    let report = ingest::ingest_dir(&database, dir, &IngestConfig::from_env()).await?;
+    println!(
+        "Imported {} blocks from {} in {} batches",
+        report.blocks,
+        dir.display(),
+        report.batches
+    );
+    if report.assumed_valid > 0 {
+        println!(
+            "Skipped header checks of {} blocks up to the assumed-valid block",
+            report.assumed_valid
+        );
+    }
+    if report.quarantined > 0 {
+        println!(
+            "Quarantined {} blocks that failed validation",
+            report.quarantined
+        );
+    }
+    Ok(())
+}
+
+async fn export(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let database = Database::new(DatabaseConfig::from_env()).await?;
+    let count = ingest::export_dir(&database, dir).await?;
+    println!("Exported {count} blocks to {}", dir.display());
+    Ok(())
+}
+
+async fn prove(height: u32, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let data =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let database = Database::new(DatabaseConfig::from_env()).await?;
+    let storage = storage::global();
+    proof::register(&database, storage, height, &data, None).await?;
+    println!(
+        "Stored proof of block {height} in {} (sha256 {})",
+        storage.describe(),
+        proof::checksum(&data)
+    );
+    Ok(())
+}
+
+async fn verify(height: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let database = Database::new(DatabaseConfig::from_env()).await?;
+    let checksum = proof::verify(&database, storage::global(), height).await?;
+    println!("Proof of block {height} matches the indexed block (sha256 {checksum})");
+    Ok(())
+}
+
+async fn migrate_proofs(from: &str, to: &str) -> ! {
+    let source = FsProofStorage::new(from);
+    let target = FsProofStorage::new(to);
+    println!(
+        "Migrating proofs from {} to {}",
+        source.describe(),
+        target.describe()
+    );
+
+    let result = storage::migrate(&source, &target, |height, progress| {
+        println!(
+            "  [{}/{}] block {height} (copied {}, skipped {}, failed {})",
+            progress.processed(),
+            progress.total,
+            progress.copied,
+            progress.skipped,
+            progress.failed.len()
+        );
+    })
+    .await;
+
+    let progress = match result {
+        Ok(progress) => progress,
+        Err(e) => {
+            eprintln!("Failed to list proofs in {}: {e}", source.describe());
+            std::process::exit(1);
+        }
+    };
+
+    for (height, error) in &progress.failed {
+        println!("  FAIL  block {height}: {error}");
+    }
+    if progress.is_complete() {
+        println!(
+            "All {} proofs verified in {}",
+            progress.total,
+            target.describe()
+        );
+        println!(
+            "Cut over by setting PROOF_STORAGE_DIR={to} and unsetting PROOF_STORAGE_FALLBACK_DIR"
+        );
+        std::process::exit(0);
+    }
+    println!(
+        "{} proofs failed to migrate; re-run to retry",
+        progress.failed.len()
+    );
+    std::process::exit(1);
+}
+
+async fn run_smoke(base_url: &str) -> ! {
+    println!("Running smoke checks against {base_url}");
 
-    let format_layer = tracing_subscriber::fmt::layer()
-        .with_target(true)
-        .with_thread_ids(true)
-        .with_line_number(true)
-        .json();
+    let checks = smoke::run(base_url).await;
+    for check in &checks {
+        match &check.result {
+            Ok(()) => println!("  PASS  {}", check.name),
+            Err(e) => println!("  FAIL  {}: {e:#}", check.name),
+        }
+    }
 
-    tracing_subscriber::registry()
-        .with(env_filter)
-        .with(format_layer)
-        .init();
+    let failed = checks.iter().filter(|c| !c.passed()).count();
+    println!("{} passed, {failed} failed", checks.len() - failed);
+    std::process::exit(if failed == 0 { 0 } else { 1 });
+}
 
-    info!("Tracing initialized");
+/// Installs logging as configured by `LOG_*`; keep the returned guard alive
+/// so buffered file output is flushed on exit.
+fn init_tracing() -> std::io::Result<Option<WorkerGuard>> {
+    let config = LogConfig::from_env();
+    let guard = config.init()?;
+    info!(format = ?config.format, file = ?config.file, "Tracing initialized");
+    Ok(guard)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use axum_test::TestServer;
-    use raito_proving_service::database::DatabaseConfig;
-    use serde_json::Value;
+    use raito_proving_service::{chain, database::DatabaseConfig, model::KeyScope, MockStore};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_cli_arguments() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+
+        let serve = |args: &[&str]| match Cli::try_parse_from(args).unwrap() {
+            Cli {
+                command: None,
+                serve,
+            } => serve.sandbox,
+            Cli {
+                command: Some(Command::Serve(serve)),
+                ..
+            } => serve.sandbox,
+            other => panic!("not a serve command: {other:?}"),
+        };
+        assert!(!serve(&["raito"]));
+        assert!(serve(&["raito", "--sandbox"]));
+        assert!(serve(&["raito", "serve", "--sandbox"]));
+
+        let cli = Cli::try_parse_from(["raito", "prove", "869121", "--proof", "p.json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Prove { height: 869121, ref proof }) if proof == Path::new("p.json")
+        ));
+        let cli = Cli::try_parse_from(["raito", "smoke"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Smoke { ref base_url }) if base_url == "http://localhost:8080"
+        ));
+        let cli = Cli::try_parse_from(["raito", "migrate-proofs", "--from", "a", "--to", "b"]);
+        assert!(matches!(
+            cli.unwrap().command,
+            Some(Command::MigrateProofs { ref from, ref to }) if from == "a" && to == "b"
+        ));
+
+        assert!(Cli::try_parse_from(["raito", "verify", "tip"]).is_err());
+        assert!(Cli::try_parse_from(["raito", "prove", "869121"]).is_err());
+        assert!(Cli::try_parse_from(["raito", "--sandbox", "migrate"]).is_err());
+    }
 
     async fn create_test_database() -> Arc<Database> {
         let config = DatabaseConfig::test_config();
@@ -119,10 +759,193 @@ mod tests {
         Arc::new(db)
     }
 
+    #[tokio::test]
+    async fn test_backends_agree() {
+        let db = TestServer::new(create_app(create_test_database().await)).unwrap();
+        let mock = TestServer::new(create_app(Arc::new(
+            MockStore::load_from_files().expect("Failed to load mock store"),
+        )))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let kv_store = KvStore::open(&dir.path().join("raito.redb")).unwrap();
+        kv_store.seed_data().unwrap();
+        let kv = TestServer::new(create_app(Arc::new(kv_store))).unwrap();
+
+        for path in [
+            "/v1/blocks",
+            "/v1/blocks/869123",
+            "/v1/blocks/869122/txids",
+            "/v1/header/00000000000000000264e1b06b0f6f8b0c7e9e5b8b8f9c9d8b7a6f5e4d3c2b1a",
+            "/v1/proofs/coverage",
+            "/v1/chain",
+        ] {
+            let expected: Value = db.get(path).await.json();
+            for backend in [&mock, &kv] {
+                let actual: Value = backend.get(path).await.json();
+                assert_eq!(actual, expected, "{path}");
+            }
+        }
+
+        let prev_page = |server: &TestServer| {
+            server
+                .get("/v1/blocks")
+                .add_query_param("limit", 2)
+                .add_query_param("cursor", 869120)
+                .add_query_param("direction", "prev")
+        };
+        let expected: Value = prev_page(&db).await.json();
+        for backend in [&mock, &kv] {
+            assert_eq!(prev_page(backend).await.json::<Value>(), expected);
+            backend
+                .get("/v1/blocks/869123/proof")
+                .await
+                .assert_status_ok();
+            let proofs: Value = backend.get("/v1/proofs").await.json();
+            assert_eq!(proofs["proofs"].as_array().unwrap().len(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_versioned_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = || DatabaseConfig {
+            database_url: format!("sqlite:{}", dir.path().join("raito.db").display()),
+            ..DatabaseConfig::test_config()
+        };
+
+        // Two instances starting together take turns on the lock
+        let (a, b) = tokio::join!(Database::new(config()), Database::new(config()));
+        a.unwrap();
+        b.unwrap();
+
+        // A restart finds every version applied and runs nothing again
+        let db = Database::new(config()).await.unwrap();
+        let applied = db.applied_migrations().await.unwrap();
+        assert_eq!(applied, (1..=30).collect::<Vec<i64>>());
+        db.seed_data().await.unwrap();
+        assert_eq!(db.tip_height().await.unwrap(), Some(869123));
+    }
+
+    #[tokio::test]
+    async fn test_amounts_migrate_to_satoshis() {
+        use sqlx::migrate::Migrator;
+
+        let dir = tempfile::tempdir().unwrap();
+        let database_url = format!("sqlite:{}", dir.path().join("raito.db").display());
+
+        // A database last migrated while amounts were REAL BTC
+        let options: sqlx::sqlite::SqliteConnectOptions = database_url.parse().unwrap();
+        let pool = sqlx::SqlitePool::connect_with(options.create_if_missing(true))
+            .await
+            .unwrap();
+        let mut migrator = Migrator::new(std::path::Path::new("migrations"))
+            .await
+            .unwrap();
+        migrator.migrations = migrator
+            .migrations
+            .iter()
+            .filter(|migration| migration.version < 16)
+            .cloned()
+            .collect::<Vec<_>>()
+            .into();
+        migrator.run(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO blocks (height, hash, prev_hash, merkle_root, bits, nonce, tx_count, total_fees, timestamp)
+             VALUES (1, ?, ?, 'm', 0, 0, 1, 0.12345678, 0)",
+        )
+        .bind("11".repeat(32))
+        .bind("00".repeat(32))
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+
+        let db = Database::new(DatabaseConfig {
+            database_url,
+            ..DatabaseConfig::test_config()
+        })
+        .await
+        .unwrap();
+        let block = db.get_block_by_height(1).await.unwrap();
+        assert_eq!(block.summary.total_fees, 12_345_678);
+    }
+
+    #[tokio::test]
+    async fn test_insert_blocks_is_atomic() {
+        let db = create_test_database().await;
+        let tip = db.get_block_by_height(869123).await.unwrap();
+        let first = sandbox::next_block(&tip);
+        let mut second = first.clone();
+        second["height"] = json!(869125);
+        second["hash"] = json!("00".repeat(32));
+        second["txids"] = json!(["11".repeat(32)]);
+        second["transactions"] = json!([{ "txid": "11".repeat(32), "raw": "not hex" }]);
+
+        // A bad block anywhere in the batch leaves nothing behind
+        assert!(db.insert_blocks(&[first.clone(), second]).await.is_err());
+        assert_eq!(db.tip_height().await.unwrap(), Some(869123));
+
+        db.insert_blocks(&[first]).await.unwrap();
+        assert_eq!(db.tip_height().await.unwrap(), Some(869124));
+        let txids = db.get_block_txids(869124, 10, None).await.unwrap();
+        assert_eq!(txids.total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_replica_lag() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = |name: &str| format!("sqlite:{}", dir.path().join(name).display());
+
+        // Stands in for replication: the replica is seeded but never sees new blocks
+        let replica = Database::new(DatabaseConfig {
+            database_url: url("replica.db"),
+            ..DatabaseConfig::test_config()
+        })
+        .await
+        .unwrap();
+        replica.seed_data().await.unwrap();
+
+        let db = Database::new(DatabaseConfig {
+            database_url: url("primary.db"),
+            replica_url: Some(url("replica.db")),
+            replica_max_lag: 1,
+            ..DatabaseConfig::test_config()
+        })
+        .await
+        .unwrap();
+        db.seed_data().await.unwrap();
+        let db = Arc::new(db);
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+
+        let advance = || async {
+            let tip = db.get_block_by_height(db.tip_height().await.unwrap().unwrap());
+            let block = sandbox::next_block(&tip.await.unwrap());
+            db.insert_block(&block).await.unwrap();
+            db.refresh_replica_lag().await.unwrap()
+        };
+
+        // Within the allowed lag, reads come from the replica
+        assert_eq!(advance().await, Some(1));
+        let chain: Value = server.get("/v1/chain").await.json();
+        assert_eq!(chain["tip_height"], 869123);
+        let health: Value = server.get("/healthz").await.json();
+        assert_eq!(health["status"], "up");
+        assert_eq!(health["replica_lag_blocks"], 1);
+
+        // Past it, reads fall back to the primary
+        assert_eq!(advance().await, Some(2));
+        let chain: Value = server.get("/v1/chain").await.json();
+        assert_eq!(chain["tip_height"], 869125);
+        let health: Value = server.get("/healthz").await.json();
+        assert_eq!(health["status"], "degraded");
+        assert_eq!(health["components"]["sync"]["status"], "degraded");
+        assert_eq!(health["components"]["database"]["status"], "up");
+    }
+
     #[tokio::test]
     async fn test_health_check() {
         let db = create_test_database().await;
-        let app = create_app(db);
+        let app = create_app(db.clone());
         let server = TestServer::new(app).unwrap();
 
         let response = server.get("/healthz").await;
@@ -130,6 +953,224 @@ mod tests {
 
         let json: Value = response.json();
         assert_eq!(json["status"], "up");
+        for component in ["database", "proof_storage", "prover", "sync"] {
+            assert_eq!(json["components"][component]["status"], "up");
+        }
+
+        // A failed component takes the whole verdict down, still reporting
+        // the rest
+        db.close().await;
+        let response = server.get("/healthz").await;
+        response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        let json: Value = response.json();
+        assert_eq!(json["status"], "down");
+        assert_eq!(json["components"]["database"]["status"], "down");
+        assert!(json["components"]["database"]["detail"].is_string());
+        assert_eq!(json["components"]["sync"]["status"], "up");
+    }
+
+    #[tokio::test]
+    async fn test_database_unavailable() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+        let ready: Value = server.get("/readyz").await.json();
+        assert_eq!(ready["circuit"], "closed");
+
+        // Failing reads are retried, then reported as 503 rather than 500
+        db.close().await;
+        let response = server.get("/v1/blocks/869123").await;
+        response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers().contains_key("retry-after"));
+
+        // Enough of them trip the breaker, taking the instance out of rotation
+        server.get("/v1/blocks/869123").await;
+        let response = server.get("/readyz").await;
+        response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        let retry_after: u64 = response.headers()["retry-after"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_block_totals_follow_writes() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+        let total = |verified: Option<&'static str>| {
+            let server = &server;
+            async move {
+                let mut request = server.get("/v1/blocks");
+                if let Some(verified) = verified {
+                    request = request.add_query_param("verified", verified);
+                }
+                request.await.json::<Value>()["total"].as_u64().unwrap()
+            }
+        };
+        assert_eq!((total(None).await, total(Some("false")).await), (5, 0));
+
+        // Re-ingesting the same blocks updates them in place
+        db.seed_data().await.unwrap();
+        assert_eq!(total(None).await, 5);
+
+        let tip = db.get_block_by_height(869123).await.unwrap();
+        let mut block = sandbox::next_block(&tip);
+        block["verified"] = json!(false);
+        db.insert_block(&block).await.unwrap();
+        assert_eq!((total(None).await, total(Some("false")).await), (6, 1));
+
+        // A reorg tombstones the old block and counts its replacement
+        let mut replacement = block.clone();
+        replacement["hash"] = json!("00".repeat(32));
+        replacement["verified"] = json!(true);
+        db.apply_reorg(&[replacement]).await.unwrap();
+        assert_eq!((total(None).await, total(Some("true")).await), (6, 6));
+    }
+
+    #[tokio::test]
+    async fn test_block_cache_invalidated_by_writes() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+
+        let tip = db.get_block_by_height(869123).await.unwrap();
+        let block = sandbox::next_block(&tip);
+        db.insert_block(&block).await.unwrap();
+        let first = server.get("/v1/blocks/869124").await.json::<Value>();
+        let cached = server.get("/v1/blocks/869124").await.json::<Value>();
+        assert_eq!(first, cached);
+
+        // A reorg replaces the cached block, by height and by its old hash,
+        // which is now served as an orphan
+        let mut replacement = block.clone();
+        replacement["hash"] = json!("00".repeat(32));
+        db.apply_reorg(&[replacement]).await.unwrap();
+        let response = server.get("/v1/blocks/869124").await.json::<Value>();
+        assert_eq!(response["hash"], json!("00".repeat(32)));
+        let orphaned = server
+            .get(&format!("/v1/blocks/{}", first["hash"].as_str().unwrap()))
+            .await
+            .json::<Value>();
+        assert_eq!(orphaned["orphaned"], true);
+
+        let metrics = server.get("/metrics").await.text();
+        assert!(metrics.contains("raito_block_cache_requests_total{result=\"hit\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_admin_operations() {
+        use raito_proving_service::model::ProofGapReason;
+
+        let db = create_test_database().await;
+        let store: Arc<dyn BlockStore> = db.clone();
+        let stats = db.get_block_stats(869123).await.unwrap();
+
+        // Reindexing rewrites the range in place
+        assert_eq!(store.reindex(869120, 869123).await.unwrap(), 4);
+        assert_eq!(store.reindex(0, 10).await.unwrap(), 0);
+        assert_eq!(
+            serde_json::to_value(db.get_block_stats(869123).await.unwrap()).unwrap(),
+            serde_json::to_value(&stats).unwrap()
+        );
+        db.prune_below(869122).await.unwrap();
+        assert_eq!(store.reindex(869119, 869123).await.unwrap(), 2);
+
+        db.mark_proof_failed(869121).await.unwrap();
+        let gap_reason = || async {
+            db.get_proof_coverage(10, None)
+                .await
+                .unwrap()
+                .gaps
+                .iter()
+                .find(|gap| (gap.start_height..=gap.end_height).contains(&869121))
+                .map(|gap| gap.reason)
+        };
+        assert_eq!(gap_reason().await, Some(ProofGapReason::Failed));
+        assert_eq!(store.requeue_failed_proofs().await.unwrap(), 1);
+        assert_eq!(gap_reason().await, Some(ProofGapReason::NeverAttempted));
+        assert_eq!(store.requeue_failed_proofs().await.unwrap(), 0);
+
+        store.invalidate_cache();
+        store.reseed().await.unwrap();
+        assert_eq!(store.tip_height().await.unwrap(), Some(869123));
+
+        let mock: Arc<dyn BlockStore> = Arc::new(MockStore::load_from_files().unwrap());
+        assert!(matches!(
+            mock.reseed().await,
+            Err(raito_proving_service::AppError::FeatureDisabled(_))
+        ));
+
+        // Like the rest of the admin API, these are off unless ADMIN_TOKEN is set
+        let server = TestServer::new(create_app(db)).unwrap();
+        for path in [
+            "/admin/reseed",
+            "/admin/cache/invalidate",
+            "/admin/proofs/requeue",
+        ] {
+            server
+                .post(path)
+                .await
+                .assert_status(axum::http::StatusCode::NOT_IMPLEMENTED);
+        }
+        server
+            .get("/admin/config")
+            .await
+            .assert_status(axum::http::StatusCode::NOT_IMPLEMENTED);
+    }
+
+    #[tokio::test]
+    async fn test_pruning() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+        let proved_before = db.proof_file_exists(869121).await.unwrap();
+
+        let report = db.prune_below(869122).await.unwrap();
+        assert_eq!(report.pruned_below, 869122);
+        assert!(report.transactions_removed > 0);
+        let chain: Value = server.get("/v1/chain").await.json();
+        assert_eq!(chain["pruned_below"], 869122);
+
+        // Headers and proofs survive; the transactions are reported as pruned
+        let block: Value = server.get("/v1/blocks/869121").await.json();
+        assert_eq!(block["pruned"], true);
+        assert_eq!(block["txids"], json!([]));
+        let response = server.get("/v1/blocks/869121/txids").await;
+        response.assert_status(axum::http::StatusCode::GONE);
+        let header: Value = server
+            .get(&format!("/v1/header/{}", block["hash"].as_str().unwrap()))
+            .await
+            .json();
+        assert_eq!(header["in_chain"], true);
+        assert_eq!(db.proof_file_exists(869121).await.unwrap(), proved_before);
+
+        let block: Value = server.get("/v1/blocks/869122").await.json();
+        assert!(block.get("pruned").is_none());
+        server
+            .get("/v1/blocks/869122/txids")
+            .await
+            .assert_status_ok();
+
+        // The horizon never moves down
+        let report = db.prune_below(869000).await.unwrap();
+        assert_eq!(
+            (report.pruned_below, report.transactions_removed),
+            (869122, 0)
+        );
+
+        // The admin API is off unless ADMIN_TOKEN is set
+        let response = server
+            .post("/admin/prune")
+            .json(&json!({ "below_height": 869123 }))
+            .await;
+        response.assert_status(axum::http::StatusCode::NOT_IMPLEMENTED);
+
+        // Oversized bodies are turned away before anything else looks at them
+        let response = server
+            .post("/admin/prune")
+            .json(&json!({ "padding": "x".repeat(100_000) }))
+            .await;
+        response.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.json::<Value>()["status"], 413);
     }
 
     #[tokio::test]
@@ -146,6 +1187,126 @@ mod tests {
         assert!(json["total"].is_number());
     }
 
+    #[tokio::test]
+    async fn test_blocks_ascending_order() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server
+            .get("/v1/blocks")
+            .add_query_param("order", "asc")
+            .add_query_param("limit", 2)
+            .await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["blocks"][0]["height"], 869119);
+        assert_eq!(json["blocks"][1]["height"], 869120);
+        assert_eq!(json["next_cursor"], 869120);
+
+        let json: Value = server
+            .get("/v1/blocks")
+            .add_query_param("order", "asc")
+            .add_query_param("limit", 2)
+            .add_query_param("cursor", 869120)
+            .await
+            .json();
+        assert_eq!(json["blocks"][0]["height"], 869121);
+        assert_eq!(json["blocks"][1]["height"], 869122);
+
+        server
+            .get("/v1/blocks")
+            .add_query_param("order", "sideways")
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_blocks_backward_pagination() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+        let page = |cursor: Option<u64>, direction: &'static str| {
+            let mut request = server
+                .get("/v1/blocks")
+                .add_query_param("limit", 2)
+                .add_query_param("direction", direction);
+            if let Some(cursor) = cursor {
+                request = request.add_query_param("cursor", cursor);
+            }
+            async move { request.await.json::<Value>() }
+        };
+        let heights = |json: &Value| -> Vec<u64> {
+            json["blocks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|b| b["height"].as_u64().unwrap())
+                .collect()
+        };
+
+        // Forward from the tip, then back again without refetching it
+        let first = page(None, "next").await;
+        assert_eq!(heights(&first), [869123, 869122]);
+        assert_eq!(first["prev_cursor"], Value::Null);
+        let second = page(first["next_cursor"].as_u64(), "next").await;
+        assert_eq!(heights(&second), [869121, 869120]);
+        assert_eq!(second["prev_cursor"], 869121);
+
+        let back = page(second["prev_cursor"].as_u64(), "prev").await;
+        assert_eq!(heights(&back), [869123, 869122]);
+        assert_eq!(back["prev_cursor"], Value::Null);
+        assert_eq!(back["next_cursor"], 869122);
+        assert_eq!(back["has_next"], true);
+
+        // Without a cursor `prev` starts from the far end
+        let last = page(None, "prev").await;
+        assert_eq!(heights(&last), [869120, 869119]);
+        assert_eq!(last["has_next"], false);
+        assert_eq!(last["prev_cursor"], 869120);
+
+        server
+            .get("/v1/blocks")
+            .add_query_param("direction", "up")
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_blocks_filters() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server
+            .get("/v1/blocks")
+            .add_query_param("from_timestamp", 1704065400)
+            .add_query_param("to_timestamp", 1704066600)
+            .add_query_param("limit", 2)
+            .await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["total"], 3);
+        assert_eq!(json["blocks"][0]["height"], 869122);
+        assert_eq!(json["blocks"][1]["height"], 869121);
+        assert_eq!(json["next_cursor"], 869121);
+
+        let response = server
+            .get("/v1/blocks")
+            .add_query_param("verified", false)
+            .await;
+        let json: Value = response.json();
+        assert_eq!(json["total"], 0);
+        assert_eq!(json["blocks"].as_array().unwrap().len(), 0);
+
+        server
+            .get("/v1/blocks")
+            .add_query_param("from_timestamp", 1704066600)
+            .add_query_param("to_timestamp", 1704065400)
+            .await
+            .assert_status_bad_request();
+    }
+
     #[tokio::test]
     async fn test_block_by_height() {
         let db = create_test_database().await;
@@ -159,6 +1320,2346 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_block_total_fees() {
+        let server = TestServer::new(create_app(create_test_database().await)).unwrap();
+
+        // Stored as satoshis, reported in BTC under /v1
+        let json: Value = server.get("/v1/blocks/869123").await.json();
+        assert_eq!(json["total_fees"], 0.12345678);
+        let json: Value = server.get("/v2/blocks/869123").await.json();
+        assert_eq!(json["total_fees"], 12345678);
+    }
+
+    #[tokio::test]
+    async fn test_block_invalid_identifier() {
+        let server = TestServer::new(create_app(create_test_database().await)).unwrap();
+
+        for path in ["/v1/blocks/not-a-block", "/v1/blocks/abc/stats"] {
+            let response = server.get(path).await;
+            assert_eq!(response.status_code(), 400);
+            assert!(response.json::<Value>()["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("Invalid block identifier"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_size_and_weight() {
+        let server = TestServer::new(create_app(create_test_database().await)).unwrap();
+
+        // Derived from the block's transaction details
+        let json: Value = server.get("/v1/blocks/869123").await.json();
+        assert_eq!(json["size"], 81 + 86 + 82 + 113);
+        assert_eq!(json["weight"], 362 * 4);
+        assert_eq!(json["stripped_size"], 362);
+
+        // Given in the block document
+        let json: Value = server.get("/v1/blocks/869121").await.json();
+        assert_eq!(json["size"], 1534021);
+        assert_eq!(json["stripped_size"], 812345);
+        assert_eq!(json["weight"], 3971056);
+        let json: Value = server.get("/v1/blocks/869120").await.json();
+        assert!(json["size"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_block_difficulty_and_chainwork() {
+        let server = TestServer::new(create_app(create_test_database().await)).unwrap();
+
+        // Five blocks indexed from 869119, all at the same target
+        let json: Value = server.get("/v1/blocks/869123").await.json();
+        assert!(
+            (json["difficulty"].as_f64().unwrap() - chain::difficulty(0x17094226)).abs() < 1e-3
+        );
+        assert_eq!(
+            json["chainwork"],
+            chain::format_chainwork(5 * chain::block_work(0x17094226))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_witness_fields() {
+        let server = TestServer::new(create_app(create_test_database().await)).unwrap();
+
+        // Every transaction was ingested raw; none has a witness
+        let json: Value = server.get("/v1/blocks/869123").await.json();
+        assert_eq!(json["wtxids"], json["txids"]);
+        assert_eq!(json["witness_merkle_root"].as_str().unwrap().len(), 64);
+        assert!(json["witness_commitment"].is_null());
+
+        let json: Value = server.get("/v1/blocks/869120").await.json();
+        assert!(json.get("wtxids").is_none());
+        assert!(json["witness_merkle_root"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_block_coinbase() {
+        let server = TestServer::new(create_app(create_test_database().await)).unwrap();
+
+        // Coinbase outputs pay the subsidy plus exactly the block's fees
+        let json: Value = server.get("/v1/blocks/869123").await.json();
+        assert_eq!(json["coinbase"]["txid"], json["txids"][0]);
+        assert_eq!(json["coinbase"]["subsidy"], 3.125);
+        assert_eq!(json["coinbase"]["claimed_fees"], 0.12345678);
+        assert_eq!(json["coinbase"]["fees_match"], true);
+        assert_eq!(json["coinbase"]["script_sig"], "0303430d");
+    }
+
+    #[tokio::test]
+    async fn test_block_not_indexed() {
+        let server = TestServer::new(create_app(create_test_database().await)).unwrap();
+
+        // Past the tip the block may still come, below the first indexed one it won't
+        for path in [
+            "/v1/blocks/869124",
+            "/v1/blocks/869124/stats",
+            "/v1/blocks/869124/proof",
+        ] {
+            let response = server.get(path).await;
+            response.assert_status_not_found();
+            let json: Value = response.json();
+            assert_eq!(json["code"], "NOT_INDEXED", "{path}");
+            assert_eq!(json["details"]["tip_height"], 869123);
+        }
+        let response = server.get("/v1/blocks/1").await;
+        response.assert_status_not_found();
+        assert_eq!(response.json::<Value>()["code"], "BLOCK_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_problem_details() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        let response = server.get("/v1/blocks/not-a-block").await;
+        response.assert_status_bad_request();
+        assert_eq!(response.header("content-type"), "application/problem+json");
+        let json: Value = response.json();
+        assert_eq!(json["type"], "about:blank");
+        assert_eq!(json["title"], "Bad Request");
+        assert_eq!(json["status"], 400);
+        assert_eq!(json["code"], "INVALID_BLOCK_IDENTIFIER");
+        assert_eq!(json["detail"], json["error"]);
+        assert_eq!(
+            json["request_id"],
+            response.header("x-trace-id").to_str().unwrap()
+        );
+        assert!(json.get("details").is_none());
+
+        let json: Value = server.get("/v1/blocks/869124").await.json();
+        assert_eq!(json["code"], "NOT_INDEXED");
+        assert_eq!(json["details"], json!({ "tip_height": 869123 }));
+    }
+
+    #[tokio::test]
+    async fn test_binary_encodings() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db)).unwrap();
+        let json = server.get("/v1/blocks/869123").await;
+        assert_eq!(json.header("vary"), "accept");
+        let json: Value = json.json();
+
+        let cbor = server
+            .get("/v1/blocks/869123")
+            .add_header(
+                "accept".parse().unwrap(),
+                "application/cbor".parse().unwrap(),
+            )
+            .await;
+        cbor.assert_status_ok();
+        assert_eq!(cbor.header("content-type"), "application/cbor");
+        assert!(cbor.header("etag").to_str().unwrap().starts_with("W/"));
+        assert_eq!(
+            serde_cbor::from_slice::<Value>(cbor.as_bytes()).unwrap(),
+            json
+        );
+
+        let msgpack = server
+            .get("/v1/blocks")
+            .add_header(
+                "accept".parse().unwrap(),
+                "application/msgpack".parse().unwrap(),
+            )
+            .await;
+        assert_eq!(msgpack.header("content-type"), "application/msgpack");
+        let blocks: Value = rmp_serde::from_slice(msgpack.as_bytes()).unwrap();
+        assert_eq!(blocks["blocks"][0]["height"], 869123);
+
+        // Errors stay problem documents
+        let missing = server
+            .get("/v1/blocks/1")
+            .add_header(
+                "accept".parse().unwrap(),
+                "application/cbor".parse().unwrap(),
+            )
+            .await;
+        assert_eq!(missing.header("content-type"), "application/problem+json");
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_resources() {
+        use prost::Message;
+        use raito_proving_service::proto;
+
+        let db = create_test_database().await;
+        let block = db.get_block_by_height(869123).await.unwrap();
+        let txid = block.txids[0];
+        let server = TestServer::new(create_app(db)).unwrap();
+        let get = |path: String| {
+            server.get(&path).add_header(
+                "accept".parse().unwrap(),
+                "application/x-protobuf".parse().unwrap(),
+            )
+        };
+
+        let response = get("/v1/blocks/869123".to_string()).await;
+        response.assert_status_ok();
+        assert_eq!(response.header("content-type"), "application/x-protobuf");
+        assert!(response.header("etag").to_str().unwrap().starts_with("W/"));
+        let detail = proto::BlockDetail::decode(response.as_bytes().as_ref()).unwrap();
+        assert_eq!(detail.summary.unwrap().hash, block.summary.hash.to_string());
+        assert_eq!(detail.txids.len(), block.txids.len());
+
+        let response = get("/v1/blocks".to_string()).await;
+        let page = proto::BlockSummaryPage::decode(response.as_bytes().as_ref()).unwrap();
+        assert_eq!(page.blocks[0].height, 869123);
+
+        let response = get(format!("/v1/tx/{txid}")).await;
+        let status = proto::TransactionStatus::decode(response.as_bytes().as_ref()).unwrap();
+        assert!(status.included);
+        assert_eq!(status.block_height, Some(869123));
+
+        // Endpoints without a message stay JSON
+        let response = get("/v1/blocks/869123/stats".to_string()).await;
+        assert!(response
+            .header("content-type")
+            .to_str()
+            .unwrap()
+            .starts_with("application/json"));
+    }
+
+    #[tokio::test]
+    async fn test_pagination_links() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        let first = server.get("/v1/blocks").add_query_param("limit", 2).await;
+        assert_eq!(
+            first.header("link"),
+            "</v1/blocks?limit=2&cursor=869122>; rel=\"next\""
+        );
+        let second = server
+            .get("/v1/blocks")
+            .add_query_param("limit", 2)
+            .add_query_param("cursor", 869122)
+            .await;
+        let json: Value = second.json();
+        assert_eq!(json["blocks"][0]["height"], 869121);
+        assert_eq!(
+            second.header("link"),
+            "</v1/blocks?limit=2&cursor=869120>; rel=\"next\", \
+             </v1/blocks?limit=2&cursor=869121&direction=prev>; rel=\"prev\""
+        );
+
+        let proofs = server.get("/v1/proofs").add_query_param("limit", 1).await;
+        let json: Value = proofs.json();
+        assert_eq!(
+            proofs.header("link"),
+            format!(
+                "</v1/proofs?limit=1&cursor={}>; rel=\"next\"",
+                json["next_cursor"]
+            )
+        );
+        let txids = server.get("/v1/blocks/869123/txids").await;
+        assert!(txids.maybe_header("link").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_api_v2() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db)).unwrap();
+        let txid = "a88b4f5db1b57361982f83a1ac960faeac7488e2813b1cf45670d504c2086042";
+
+        // Booleans become a pipeline status
+        let v1: Value = server.get("/v1/blocks/869123").await.json();
+        let response = server.get("/v2/blocks/869123").await;
+        response.assert_status_ok();
+        let v2: Value = response.json();
+        assert!(v2.get("verified").is_none());
+        let expected = if v1["verified"] == true {
+            "verified"
+        } else {
+            "indexed"
+        };
+        assert_eq!(v2["status"], expected);
+        assert_eq!(
+            v2["total_fees"],
+            raito_proving_service::model::btc_to_sats(v1["total_fees"].as_f64().unwrap())
+        );
+        assert_eq!(v2["coinbase"]["subsidy"], 312_500_000);
+        assert_eq!(v2["txids"], v1["txids"]);
+
+        let page = server.get("/v2/blocks").add_query_param("limit", 2).await;
+        let json: Value = page.json();
+        assert_eq!(json["blocks"][0]["height"], 869123);
+        assert!(json["blocks"][0]["status"].is_string());
+        assert_eq!(
+            page.header("link"),
+            "</v2/blocks?limit=2&cursor=869122>; rel=\"next\""
+        );
+
+        let v1: Value = server.get(&format!("/v1/tx/{txid}")).await.json();
+        let v2: Value = server.get(&format!("/v2/tx/{txid}")).await.json();
+        assert!(v2.get("included").is_none());
+        let expected = match v1["proof_verified"].as_bool() {
+            None => "indexed",
+            Some(false) => "proved",
+            Some(true) => "verified",
+        };
+        assert_eq!(v2["status"], expected);
+        let unknown: Value = server
+            .get("/v2/tx/0000000000000000000000000000000000000000000000000000000000000001")
+            .await
+            .json();
+        assert_eq!(unknown["status"], "pending");
+
+        let proofs: Value = server.get("/v2/proofs").await.json();
+        for proof in proofs["proofs"].as_array().unwrap() {
+            assert!(proof["status"] == "proved" || proof["status"] == "verified");
+        }
+
+        // Amounts are integer satoshis
+        let v1: Value = server.get(&format!("/v1/tx/{txid}/detail")).await.json();
+        let v2: Value = server.get(&format!("/v2/tx/{txid}/detail")).await.json();
+        let sats = |btc: &Value| (btc.as_f64().unwrap() * 1e8).round() as i64;
+        assert_eq!(v2["outputs"][0]["value"], sats(&v1["outputs"][0]["value"]));
+        assert!(v2["outputs"][0]["value"].is_i64());
+
+        // Problem documents without the legacy field
+        let missing = server.get("/v2/blocks/999999").await;
+        missing.assert_status_not_found();
+        assert_eq!(missing.header("content-type"), "application/problem+json");
+        let problem: Value = missing.json();
+        assert_eq!(problem["code"], "NOT_INDEXED");
+        assert!(problem.get("error").is_none());
+        let problem: Value = server.get("/v1/blocks/999999").await.json();
+        assert_eq!(problem["error"], problem["detail"]);
+
+        // Superseded v1 routes point at their successor
+        let v1 = server
+            .get("/v1/blocks/869123")
+            .add_query_param("include_txids", false)
+            .await;
+        assert!(v1.maybe_header("deprecation").is_some());
+        let links: Vec<_> = v1.headers().get_all("link").iter().cloned().collect();
+        assert_eq!(
+            links,
+            ["</v2/blocks/869123?include_txids=false>; rel=\"successor-version\""]
+        );
+        let unchanged = server.get("/v1/blocks/869123/stats").await;
+        assert!(unchanged.maybe_header("deprecation").is_none());
+        assert!(server
+            .get("/v2/blocks/869123")
+            .await
+            .maybe_header("deprecation")
+            .is_none());
+
+        let spec = serde_json::to_value(deprecations::annotate_openapi(
+            ApiDoc::openapi(),
+            DEPRECATIONS,
+        ))
+        .unwrap();
+        assert_eq!(
+            spec["paths"]["/v1/blocks/{identifier}"]["get"]["deprecated"],
+            true
+        );
+        assert!(spec["paths"]["/v2/blocks/{identifier}"]["get"]
+            .get("deprecated")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc() {
+        let db = create_test_database().await;
+        let block = db.get_block_by_height(869123).await.unwrap();
+        let hash = block.summary.hash.to_string();
+        let txid = block.txids[0].to_string();
+        let server = TestServer::new(create_app(db)).unwrap();
+        let call = |method: &str, params: Value| {
+            server.post("/v1/rpc").json(&json!({
+                "jsonrpc": "1.0",
+                "id": "test",
+                "method": method,
+                "params": params,
+            }))
+        };
+
+        let response = call("getblockhash", json!([869123])).await;
+        response.assert_status_ok();
+        let reply: Value = response.json();
+        assert_eq!(reply, json!({"result": hash, "error": null, "id": "test"}));
+        let count: Value = call("getblockcount", json!([])).await.json();
+        assert_eq!(count["result"], 869123);
+
+        let header: Value = call("getblockheader", json!([hash])).await.json();
+        assert_eq!(header["result"]["height"], 869123);
+        assert_eq!(header["result"]["confirmations"], 1);
+        assert_eq!(
+            header["result"]["previousblockhash"],
+            block.prev_hash.to_string()
+        );
+        assert!(header["result"].get("nextblockhash").is_none());
+        let raw: Value = call("getblockheader", json!([hash, false])).await.json();
+        let header = server.get(&format!("/v1/header/{hash}/raw")).await;
+        assert_eq!(raw["result"], ::hex::encode(header.as_bytes()));
+
+        let verbose: Value = call("getblock", json!({"blockhash": hash})).await.json();
+        assert_eq!(verbose["result"]["tx"][0], txid);
+        assert_eq!(verbose["result"]["size"], block.size.unwrap());
+        // Every transaction of this block was ingested raw
+        let raw: Value = call("getblock", json!([hash, 0])).await.json();
+        let raw = ::hex::decode(raw["result"].as_str().unwrap()).unwrap();
+        assert_eq!(raw.len(), block.size.unwrap() as usize);
+        assert_eq!(&raw[..80], header.as_bytes());
+
+        let tx: Value = call("getrawtransaction", json!([txid, true, hash]))
+            .await
+            .json();
+        assert_eq!(tx["result"]["blockhash"], hash);
+        assert_eq!(tx["result"]["confirmations"], 1);
+        let other = block.prev_hash.to_string();
+        let response = call("getrawtransaction", json!([txid, false, other])).await;
+        response.assert_status(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.json::<Value>()["error"]["code"], -5);
+
+        // Core's error codes and statuses
+        let response = call("getblockhash", json!([1])).await;
+        response.assert_status(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.json::<Value>()["error"]["code"], -8);
+        let response = call("sendrawtransaction", json!(["00"])).await;
+        response.assert_status_not_found();
+        assert_eq!(response.json::<Value>()["error"]["code"], -32601);
+
+        // 2.0 errors answer 200, and batches a reply per call
+        let response = server
+            .post("/v1/rpc")
+            .json(&json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "getblockhash", "params": [869122]},
+                {"jsonrpc": "2.0", "id": 2, "method": "getblockhash", "params": ["x"]},
+            ]))
+            .await;
+        response.assert_status_ok();
+        let replies: Value = response.json();
+        assert_eq!(replies[0]["id"], 1);
+        assert!(replies[0].get("error").is_none());
+        assert_eq!(replies[1]["error"]["code"], -3);
+        assert!(replies[1].get("result").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hashes_in_any_form() {
+        let db = create_test_database().await;
+        let block = db.get_block_by_height(869123).await.unwrap();
+        let txid = block.txids[0];
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        let hash = block.summary.hash;
+        for form in [
+            format!("0x{hash}"),
+            hash.to_string().to_uppercase(),
+            hash.reversed().to_string(),
+        ] {
+            let response = server.get(&format!("/v1/blocks/{form}")).await;
+            assert_eq!(response.status_code(), 200, "{form}");
+            assert_eq!(response.json::<Value>()["height"], 869123);
+            let json: Value = server.get(&format!("/v1/header/{form}")).await.json();
+            assert_eq!(json["block_hash"], hash.to_string());
+            assert_eq!(json["in_chain"], true);
+        }
+
+        for form in [format!("0X{txid}"), txid.reversed().to_string()] {
+            let json: Value = server.get(&format!("/v1/tx/{form}")).await.json();
+            assert_eq!(json["included"], true, "{form}");
+            assert_eq!(json["block_height"], 869123);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_txids_pagination() {
+        let db = create_test_database().await;
+        let block = db.get_block_by_height(869123).await.unwrap();
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        let json: Value = server
+            .get("/v1/blocks/869123/txids")
+            .add_query_param("limit", 2)
+            .await
+            .json();
+        assert_eq!(json["total"], block.txids.len());
+        assert_eq!(json["txids"], serde_json::json!(block.txids[..2]));
+        assert_eq!(json["has_next"], true);
+
+        let json: Value = server
+            .get(&format!("/v1/blocks/{}/txids", block.summary.hash))
+            .add_query_param("limit", 2)
+            .add_query_param("cursor", json["next_cursor"].as_u64().unwrap())
+            .await
+            .json();
+        assert_eq!(json["txids"], serde_json::json!(block.txids[2..]));
+        assert_eq!(json["has_next"], false);
+
+        let json: Value = server
+            .get("/v1/blocks/869123")
+            .add_query_param("include_txids", false)
+            .await
+            .json();
+        assert!(json["txids"].as_array().unwrap().is_empty());
+
+        server
+            .get("/v1/blocks/1/txids")
+            .await
+            .assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_proof_range_resume() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let full = server.get("/v1/blocks/869123/proof").await;
+        full.assert_status_ok();
+        let token = full.header("x-resume-token");
+
+        let partial = server
+            .get("/v1/blocks/869123/proof")
+            .add_header("range".parse().unwrap(), "bytes=10-".parse().unwrap())
+            .add_header("x-resume-token".parse().unwrap(), token)
+            .await;
+        partial.assert_status(axum::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(partial.as_bytes().as_ref(), &full.as_bytes()[10..]);
+    }
+
+    #[tokio::test]
+    async fn test_proof_resume_token_bound_to_recorded_checksum() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+
+        let full = server.get("/v1/blocks/869123/proof").await;
+        let recorded = db.proof_checksum(869123).await.unwrap().unwrap();
+        assert_eq!(recorded, proof::checksum(full.as_bytes()));
+        assert_eq!(
+            full.header("x-proof-checksum"),
+            format!("sha256={recorded}")
+        );
+        let token = full.header("x-resume-token");
+
+        // Superseding the record invalidates outstanding tokens, whatever the
+        // file on disk still holds
+        let superseding = proof::checksum(b"superseding proof");
+        db.record_proof(
+            869123,
+            "data/proofs/869123.json",
+            full.as_bytes().len() as i64,
+            Some(&superseding),
+            chrono::Utc::now().timestamp(),
+            None,
+        )
+        .await
+        .unwrap();
+        let response = server
+            .get("/v1/blocks/869123/proof")
+            .add_header("range".parse().unwrap(), "bytes=10-".parse().unwrap())
+            .add_header("x-resume-token".parse().unwrap(), token)
+            .await;
+        response.assert_status(axum::http::StatusCode::CONFLICT);
+
+        let response = server.get("/v1/blocks/869123/proof").await;
+        assert_eq!(
+            response.header("x-proof-checksum"),
+            format!("sha256={superseding}")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_proof_resume_after_supersession_conflicts() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let stale_token = raito_proving_service::proof::issue_resume_token(
+            &raito_proving_service::proof::checksum(b"superseded proof"),
+        );
+        let response = server
+            .get("/v1/blocks/869123/proof")
+            .add_header("range".parse().unwrap(), "bytes=10-".parse().unwrap())
+            .add_header(
+                "x-resume-token".parse().unwrap(),
+                stale_token.parse().unwrap(),
+            )
+            .await;
+        response.assert_status(axum::http::StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_proof_streaming_with_trailers() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server
+            .get("/v1/blocks/869123/proof")
+            .add_header("te".parse().unwrap(), "trailers".parse().unwrap())
+            .await;
+        response.assert_status_ok();
+        assert_eq!(
+            response.header("trailer"),
+            "x-proof-checksum, x-resume-token"
+        );
+        assert!(!response.headers().contains_key("content-length"));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_requests() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+        let conditional = |path: &'static str, etag: &str| {
+            server
+                .get(path)
+                .add_header("if-none-match".parse().unwrap(), etag.parse().unwrap())
+        };
+
+        for path in ["/v1/blocks/869123", "/v1/blocks/869123/proof"] {
+            let response = server.get(path).await;
+            response.assert_status_ok();
+            let etag = response.header("etag");
+            let etag = etag.to_str().unwrap();
+            assert!(etag.starts_with('"'));
+
+            let response = conditional(path, etag).await;
+            response.assert_status(axum::http::StatusCode::NOT_MODIFIED);
+            assert_eq!(response.header("etag"), etag);
+            assert!(response.as_bytes().is_empty());
+
+            conditional(path, "\"stale\"").await.assert_status_ok();
+        }
+
+        // Omitting txids is a different representation with its own tag
+        let full = server.get("/v1/blocks/869123").await.header("etag");
+        let response = server
+            .get("/v1/blocks/869123")
+            .add_query_param("include_txids", false)
+            .add_header("if-none-match".parse().unwrap(), full)
+            .await;
+        response.assert_status_ok();
+        assert!(response.json::<Value>()["txids"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_control() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+        let cache_control = |path: &'static str| {
+            let server = &server;
+            async move {
+                let response = server.get(path).await;
+                response.assert_status_ok();
+                response
+                    .header("cache-control")
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            }
+        };
+
+        // At the tip a reorg may still replace the block
+        assert_eq!(
+            cache_control("/v1/blocks/869123").await,
+            "public, max-age=10"
+        );
+        assert_eq!(
+            cache_control("/v1/blocks/869123/proof").await,
+            "public, max-age=10"
+        );
+
+        let mut tip = db.get_block_by_height(869123).await.unwrap();
+        for _ in 0..5 {
+            db.insert_block(&sandbox::next_block(&tip)).await.unwrap();
+            tip = db
+                .get_block_by_height(tip.summary.height + 1)
+                .await
+                .unwrap();
+        }
+        let immutable = "public, max-age=31536000, immutable";
+        assert_eq!(cache_control("/v1/blocks/869123").await, immutable);
+        assert_eq!(cache_control("/v1/blocks/869123/proof").await, immutable);
+    }
+
+    #[tokio::test]
+    async fn test_response_compression() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+        let get = |path: &'static str, encoding: &'static str| {
+            server.get(path).add_header(
+                "accept-encoding".parse().unwrap(),
+                encoding.parse().unwrap(),
+            )
+        };
+
+        let plain = server.get("/v1/blocks").await;
+        for encoding in ["gzip", "br", "zstd"] {
+            let response = get("/v1/blocks", encoding).await;
+            response.assert_status_ok();
+            assert_eq!(response.header("content-encoding"), encoding);
+            assert!(response.as_bytes().len() < plain.as_bytes().len());
+        }
+
+        // A compressed body no longer matches the strong validator byte for byte
+        let response = get("/v1/blocks/869123", "gzip").await;
+        let etag = response.header("etag");
+        assert!(etag.to_str().unwrap().starts_with("W/\""));
+        get("/v1/blocks/869123", "gzip")
+            .add_header("if-none-match".parse().unwrap(), etag)
+            .await
+            .assert_status(axum::http::StatusCode::NOT_MODIFIED);
+
+        // Proof downloads stay byte-addressable for range requests
+        let response = get("/v1/blocks/869123/proof", "gzip").await;
+        response.assert_status_ok();
+        assert!(!response.headers().contains_key("content-encoding"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiting() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get("/v1/blocks").await;
+        assert_eq!(response.header("ratelimit-limit"), "120");
+        assert_eq!(response.header("ratelimit-remaining"), "119");
+
+        // Proof downloads have a smaller budget of their own
+        for remaining in (0..10).rev() {
+            let response = server.get("/v1/blocks/869123/proof").await;
+            response.assert_status_ok();
+            assert_eq!(
+                response.header("ratelimit-remaining"),
+                remaining.to_string()
+            );
+        }
+        let response = server.get("/v1/blocks/869123/proof").await;
+        response.assert_status(axum::http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.header("retry-after"), "2");
+        assert_eq!(response.header("ratelimit-reset"), "2");
+        assert_eq!(response.json::<Value>()["error"], "Rate limit exceeded");
+
+        server.get("/v1/blocks").await.assert_status_ok();
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let db = create_test_database().await;
+        let created =
+            raito_proving_service::auth::issue_key(db.as_ref(), "explorer", KeyScope::Read)
+                .await
+                .unwrap();
+        assert!(created.key.starts_with(&created.api_key.prefix));
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+
+        // Reads stay open by default, and a valid key is counted on its own
+        server.get("/v1/blocks").await.assert_status_ok();
+        let response = server
+            .get("/v1/blocks")
+            .add_header(
+                "authorization".parse().unwrap(),
+                format!("Bearer {}", created.key).parse().unwrap(),
+            )
+            .await;
+        response.assert_status_ok();
+        assert_eq!(response.header("ratelimit-remaining"), "119");
+        let keys = db.list_api_keys().await.unwrap();
+        assert!(keys[0].last_used_at.is_some());
+
+        server
+            .get("/v1/blocks")
+            .add_header(
+                "authorization".parse().unwrap(),
+                "Bearer raito_guess".parse().unwrap(),
+            )
+            .await
+            .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+        let revoked = db.revoke_api_key(created.api_key.id).await.unwrap();
+        assert!(revoked.revoked_at.is_some());
+        let response = server
+            .get("/v1/blocks")
+            .add_header(
+                "authorization".parse().unwrap(),
+                format!("Bearer {}", created.key).parse().unwrap(),
+            )
+            .await;
+        response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.json::<Value>()["error"],
+            "Unauthorized: invalid or revoked API key"
+        );
+
+        assert!(matches!(
+            db.revoke_api_key(999).await,
+            Err(raito_proving_service::AppError::ApiKeyNotFound(999))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_webhooks() {
+        use raito_proving_service::{
+            model::{CreateWebhookRequest, UpdateWebhookRequest, WebhookEvent},
+            webhooks, AppError,
+        };
+
+        let db = create_test_database().await;
+        let request = CreateWebhookRequest {
+            url: "https://hooks.example.com/raito".to_string(),
+            secret: None,
+            events: vec![WebhookEvent::Reorg, WebhookEvent::Reorg],
+            active: None,
+        };
+        let created = webhooks::create(db.as_ref(), &request).await.unwrap();
+        assert!(created.secret.starts_with("whsec_"));
+        assert_eq!(created.webhook.events, [WebhookEvent::Reorg]);
+        assert!(created.webhook.active);
+        assert_eq!(
+            db.list_webhooks().await.unwrap(),
+            std::slice::from_ref(&created.webhook)
+        );
+
+        let invalid = CreateWebhookRequest {
+            url: "file:///etc/passwd".to_string(),
+            ..request
+        };
+        assert!(matches!(
+            webhooks::create(db.as_ref(), &invalid).await,
+            Err(AppError::InvalidQueryParameter(_))
+        ));
+
+        // Omitted fields are left as they are
+        let changes = webhooks::validate_update(UpdateWebhookRequest {
+            events: Some(vec![WebhookEvent::ProofVerified, WebhookEvent::JobFailed]),
+            active: Some(false),
+            ..UpdateWebhookRequest::default()
+        })
+        .unwrap();
+        let id = created.webhook.id;
+        let updated = db.update_webhook(id, &changes).await.unwrap();
+        assert_eq!(updated.url, created.webhook.url);
+        assert_eq!(
+            updated.events,
+            [WebhookEvent::ProofVerified, WebhookEvent::JobFailed]
+        );
+        assert!(!updated.active);
+        assert_eq!(db.get_webhook(id).await.unwrap(), updated);
+        assert!(webhooks::validate_update(UpdateWebhookRequest {
+            events: Some(vec![]),
+            ..UpdateWebhookRequest::default()
+        })
+        .is_err());
+
+        assert_eq!(db.delete_webhook(id).await.unwrap(), updated);
+        assert!(matches!(
+            db.get_webhook(id).await,
+            Err(AppError::WebhookNotFound(_))
+        ));
+        assert!(matches!(
+            db.delete_webhook(id).await,
+            Err(AppError::WebhookNotFound(_))
+        ));
+
+        // Like the rest of the admin API, off unless ADMIN_TOKEN is set
+        let server = TestServer::new(create_app(db)).unwrap();
+        server
+            .get("/admin/webhooks")
+            .await
+            .assert_status(axum::http::StatusCode::NOT_IMPLEMENTED);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_usage() {
+        let db = create_test_database().await;
+        let created =
+            raito_proving_service::auth::issue_key(db.as_ref(), "explorer", KeyScope::Read)
+                .await
+                .unwrap();
+        let server = TestServer::new(create_app(db)).unwrap();
+        let bearer = format!("Bearer {}", created.key);
+
+        // Usage is reported per key
+        server
+            .get("/v1/usage")
+            .await
+            .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+        server
+            .get("/v1/blocks")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .await
+            .assert_status_ok();
+        let proof = server
+            .get("/v1/blocks/869123/proof")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .await;
+        proof.assert_status_ok();
+        // Streamed downloads have no length up front; the bytes sent count
+        let streamed = server
+            .get("/v1/blocks/869123/proof")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .add_header("te".parse().unwrap(), "trailers".parse().unwrap())
+            .await;
+        streamed.assert_status_ok();
+        assert!(streamed.maybe_header("content-length").is_none());
+        // Anonymous requests aren't attributed to any key
+        server.get("/v1/blocks").await.assert_status_ok();
+
+        let response = server
+            .get("/v1/usage")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .await;
+        response.assert_status_ok();
+        let usage: Value = response.json();
+        assert_eq!(usage["api_key_id"], created.api_key.id);
+        assert_eq!(usage["name"], "explorer");
+        for window in ["daily", "monthly"] {
+            assert_eq!(usage[window]["used"]["requests"], 3);
+            assert_eq!(
+                usage[window]["used"]["proof_bytes"],
+                (proof.as_bytes().len() + streamed.as_bytes().len()) as u64
+            );
+            assert!(usage[window]["request_limit"].is_null());
+        }
+        assert!(usage["daily"]["resets_at"].as_i64() <= usage["monthly"]["resets_at"].as_i64());
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        use axum::extract::{Path, Query, State};
+        use raito_proving_service::{
+            audit::Actor,
+            model::{AuditLogQuery, CreateApiKeyRequest, PruneRequest},
+        };
+
+        let store: Arc<dyn BlockStore> = create_test_database().await;
+        let ops = Actor("jwt:ops".to_string());
+
+        let (_, created) = admin::create_api_key(
+            State(store.clone()),
+            ops.clone(),
+            axum::Json(CreateApiKeyRequest {
+                name: "explorer".to_string(),
+                scope: KeyScope::Read,
+            }),
+        )
+        .await
+        .unwrap();
+        let revoked = admin::revoke_api_key(
+            State(store.clone()),
+            Actor("admin-token".to_string()),
+            Path(created.api_key.id),
+        )
+        .await
+        .unwrap();
+        assert!(revoked.revoked_at.is_some());
+        let report = admin::prune(
+            State(store.clone()),
+            ops,
+            axum::Json(PruneRequest {
+                below_height: 869120,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.pruned_below, 869120);
+
+        let query = |actor: Option<&str>, limit: Option<u32>, cursor: Option<i64>| {
+            Query(AuditLogQuery {
+                actor: actor.map(str::to_string),
+                action: None,
+                limit,
+                cursor,
+            })
+        };
+        let log = admin::audit_log(State(store.clone()), query(None, None, None))
+            .await
+            .unwrap()
+            .0;
+        let actions: Vec<_> = log.entries.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, ["prune", "api_key.revoke", "api_key.create"]);
+        assert_eq!(log.entries[0].target.as_deref(), Some("height:869120"));
+        assert_eq!(log.entries[1].actor, "admin-token");
+
+        let page = admin::audit_log(State(store.clone()), query(Some("jwt:ops"), Some(1), None))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(page.entries[0].action, "prune");
+        assert!(page.has_next);
+        let page = admin::audit_log(
+            State(store.clone()),
+            query(Some("jwt:ops"), Some(1), page.next_cursor),
+        )
+        .await
+        .unwrap()
+        .0;
+        assert_eq!(page.entries[0].action, "api_key.create");
+        assert!(!page.has_next);
+    }
+
+    #[tokio::test]
+    async fn test_raw_header() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let hash = "00000000000000000264e1b06b0f6f8b0c7e9e5b8b8f9c9d8b7a6f5e4d3c2b1a";
+        let binary = server.get(&format!("/v1/header/{hash}/raw")).await;
+        binary.assert_status_ok();
+        assert_eq!(binary.as_bytes().len(), 80);
+
+        let hex = server
+            .get(&format!("/v1/header/{hash}/raw"))
+            .add_query_param("format", "hex")
+            .await;
+        hex.assert_status_ok();
+        assert_eq!(hex.text(), ::hex::encode(binary.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_transaction_confirmations() {
+        let db = create_test_database().await;
+        let block = db.get_block_by_height(869121).await.unwrap();
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        let json: Value = server
+            .get(&format!("/v1/tx/{}", block.txids[0]))
+            .await
+            .json();
+        assert_eq!(json["included"], true);
+        assert_eq!(json["block_hash"], block.summary.hash.to_string());
+        assert_eq!(json["confirmations"], 3);
+        // 869121 ships without a proof file
+        assert!(json.get("proof_url").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_proof_link() {
+        let db = create_test_database().await;
+        let block = db.get_block_by_height(869123).await.unwrap();
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        let json: Value = server
+            .get(&format!("/v1/tx/{}", block.txids[0]))
+            .await
+            .json();
+        assert_eq!(json["proof_url"], block.proof_url);
+        assert_eq!(json["proof_verified"], block.summary.verified);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_detail() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server
+            .get("/v1/tx/a88b4f5db1b57361982f83a1ac960faeac7488e2813b1cf45670d504c2086042/detail")
+            .await;
+        response.assert_status_ok();
+
+        let json: Value = response.json();
+        assert_eq!(json["block_height"], 869123);
+        assert_eq!(json["vsize"], 82);
+        assert_eq!(json["inputs"].as_array().unwrap().len(), 1);
+        assert_eq!(json["outputs"].as_array().unwrap().len(), 1);
+
+        let missing = server
+            .get("/v1/tx/0000000000000000000000000000000000000000000000000000000000000000/detail")
+            .await;
+        missing.assert_status_not_found();
+        let json: Value = missing.json();
+        assert_eq!(json["code"], "NOT_INDEXED");
+        assert_eq!(json["details"]["tip_height"], 869123);
+    }
+
+    #[tokio::test]
+    async fn test_smoke_checks_pass_against_live_server() {
+        let db = create_test_database().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, create_app(db)).await });
+
+        let checks = smoke::run(&format!("http://{addr}")).await;
+        for check in &checks {
+            assert!(check.passed(), "{}: {:?}", check.name, check.result);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_sdk() {
+        use futures_util::{StreamExt, TryStreamExt};
+        use raito_client::{model::BlocksQuery, Client, Notification};
+        use raito_proving_service::{events::Event, hash::BlockHash};
+
+        let db = create_test_database().await;
+        let block = db.get_block_by_height(869123).await.unwrap();
+        let txid = block.txids[1].to_string();
+        let hash = block.summary.hash.to_string();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, create_app(db)).await });
+        let client = Client::new(format!("http://{addr}")).unwrap();
+
+        // A method for every documented endpoint
+        let documented: std::collections::BTreeSet<_> = ApiDoc::openapi()
+            .paths
+            .paths
+            .into_keys()
+            // The client speaks v1, whose superseded routes keep working
+            .filter(|path| path != "/v1/ws" && !path.starts_with("/v2/"))
+            .collect();
+        let covered = raito_client::ENDPOINTS
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        assert_eq!(documented, covered);
+
+        // Its models read every field the server writes
+        let raw = |path: String| async move {
+            reqwest::get(format!("http://{addr}{path}"))
+                .await
+                .unwrap()
+                .json::<Value>()
+                .await
+                .unwrap()
+        };
+        let pairs = [
+            (
+                json!(client.block("869123", true).await.unwrap()),
+                raw("/v1/blocks/869123".to_string()).await,
+            ),
+            (
+                json!(client.blocks(&BlocksQuery::default()).await.unwrap()),
+                raw("/v1/blocks".to_string()).await,
+            ),
+            (
+                json!(client.block_stats("869123").await.unwrap()),
+                raw("/v1/blocks/869123/stats".to_string()).await,
+            ),
+            (
+                json!(client.block_txids("869123", None, None).await.unwrap()),
+                raw("/v1/blocks/869123/txids".to_string()).await,
+            ),
+            (
+                json!(client.transaction_status(&txid).await.unwrap()),
+                raw(format!("/v1/tx/{txid}")).await,
+            ),
+            (
+                json!(client.transaction_detail(&txid).await.unwrap()),
+                raw(format!("/v1/tx/{txid}/detail")).await,
+            ),
+            (
+                json!(client.header_status(&hash).await.unwrap()),
+                raw(format!("/v1/header/{hash}")).await,
+            ),
+            (
+                json!(client.proofs(&Default::default()).await.unwrap()),
+                raw("/v1/proofs".to_string()).await,
+            ),
+            (
+                json!(client.proof_coverage(None, None).await.unwrap()),
+                raw("/v1/proofs/coverage".to_string()).await,
+            ),
+            (
+                json!(client.chain().await.unwrap()),
+                raw("/v1/chain".to_string()).await,
+            ),
+            (
+                json!(client.version().await.unwrap()),
+                raw("/v1/version".to_string()).await,
+            ),
+            (
+                json!(client.network_stats().await.unwrap()),
+                raw("/v1/stats".to_string()).await,
+            ),
+            (
+                json!(client.proof_latency(None).await.unwrap()),
+                raw("/v1/stats/proof-latency".to_string()).await,
+            ),
+            (
+                json!(client.orphans(None, None).await.unwrap()),
+                raw("/v1/blocks/orphans".to_string()).await,
+            ),
+        ];
+        for (typed, raw) in pairs {
+            assert_eq!(typed, raw);
+        }
+
+        // Pagination helpers walk every page
+        let heights: Vec<u32> = client
+            .blocks_stream(BlocksQuery {
+                limit: Some(2),
+                ..Default::default()
+            })
+            .map_ok(|block| block.height)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(heights, (869119..=869123).rev().collect::<Vec<_>>());
+        let txids: Vec<String> = client
+            .block_txids_stream("869123")
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(txids.len(), block.txids.len());
+
+        // Proof downloads are checked against the served checksum
+        let mut proof = Vec::new();
+        let written = client.download_proof(869123, &mut proof).await.unwrap();
+        assert_eq!(written, proof.len() as u64);
+        let served = reqwest::get(format!("http://{addr}/v1/blocks/869123/proof"))
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(proof, served);
+
+        let error = client.block("1", true).await.unwrap_err();
+        assert_eq!(error.code(), Some("BLOCK_NOT_FOUND"));
+        assert_eq!(error.status(), Some(404));
+        assert_eq!(
+            client.rpc("getblockcount", json!([])).await.unwrap(),
+            869123
+        );
+        assert!(client.health().await.is_ok());
+        let broadcast = client.broadcast_transaction(&[1, 0]).await.unwrap_err();
+        assert_eq!(broadcast.status(), Some(501));
+
+        let mut events = Box::pin(client.events(&["block_indexed"], None).await.unwrap());
+        let event = Event::BlockIndexed {
+            height: 4_300_000,
+            hash: BlockHash::from_internal_bytes([3; 32]),
+        };
+        events::publish(event);
+        loop {
+            let notification = events.next().await.unwrap().unwrap();
+            if matches!(
+                notification,
+                Notification::Event {
+                    event: raito_client::model::Event::BlockIndexed {
+                        height: 4_300_000,
+                        ..
+                    },
+                    ..
+                }
+            ) {
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_raw_transaction_hashes_to_txid() {
+        use sha2::{Digest, Sha256};
+
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let txid = "a88b4f5db1b57361982f83a1ac960faeac7488e2813b1cf45670d504c2086042";
+        let response = server.get(&format!("/v1/tx/{txid}/raw")).await;
+        response.assert_status_ok();
+
+        let mut hash = Sha256::digest(Sha256::digest(response.as_bytes())).to_vec();
+        hash.reverse();
+        assert_eq!(::hex::encode(hash), txid);
+
+        let hex = server
+            .get(&format!("/v1/tx/{txid}/raw"))
+            .add_query_param("format", "hex")
+            .await;
+        assert_eq!(hex.text(), ::hex::encode(response.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_utxo_status() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        // Output 0 of this 869122 transaction is spent in 869123, output 1 is not
+        let txid = "7673b0584632ba2a6ffa91dbabc9286f0c98b3f8742945c337add728f04263a0";
+
+        let spent = server.get(&format!("/v1/utxo/{txid}/0")).await;
+        spent.assert_status_ok();
+        let json: Value = spent.json();
+        assert_eq!(json["unspent"], false);
+        assert_eq!(json["script_type"], "p2wpkh");
+        assert_eq!(json["spent_by"]["block_height"], 869123);
+
+        let unspent = server.get(&format!("/v1/utxo/{txid}/1")).await;
+        let json: Value = unspent.json();
+        assert_eq!(json["unspent"], true);
+        assert_eq!(json["tip_height"], 869123);
+
+        let missing = server.get(&format!("/v1/utxo/{txid}/7")).await;
+        missing.assert_status_not_found();
+        // The transaction is indexed; the output just doesn't exist
+        assert_eq!(missing.json::<Value>()["code"], "OUTPUT_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_address_lookups() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        // P2WPKH address for the change output of a transaction later spent in 869123
+        let address =
+            bech32::segwit::encode(bech32::hrp::BC, bech32::segwit::VERSION_0, &[0x02; 20])
+                .unwrap();
+
+        let txs = server.get(&format!("/v1/address/{address}/txs")).await;
+        txs.assert_status_ok();
+        let json: Value = txs.json();
+        assert_eq!(json["transactions"].as_array().unwrap().len(), 2);
+
+        let utxos = server.get(&format!("/v1/address/{address}/utxos")).await;
+        utxos.assert_status_ok();
+        let json: Value = utxos.json();
+        assert!(json["utxos"].as_array().unwrap().is_empty());
+
+        let invalid = server.get("/v1/address/not-an-address/txs").await;
+        invalid.assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_metrics_exemplars_link_traces() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let trace_id = "0af7651916cd43dd8448eb211c80319c";
+        let response = server
+            .get("/v1/blocks")
+            .add_header(
+                "traceparent".parse().unwrap(),
+                format!("00-{trace_id}-b7ad6b7169203331-01")
+                    .parse()
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(response.header("x-trace-id"), trace_id);
+
+        server
+            .get("/v1/blocks/999999")
+            .await
+            .assert_status_not_found();
+
+        let metrics = server.get("/metrics").await;
+        metrics.assert_status_ok();
+        let body = metrics.text();
+        assert!(body.contains(
+            "raito_http_request_duration_seconds_count{method=\"GET\",route=\"/v1/blocks\",status_class=\"2xx\"}"
+        ));
+        assert!(
+            body.contains("raito_http_requests_in_flight{method=\"GET\",route=\"/v1/blocks\"} 0")
+        );
+        assert!(body.contains(
+            "raito_http_requests_total{method=\"GET\",route=\"/v1/blocks\",status=\"200\"}"
+        ));
+        assert!(body.contains(
+            "raito_http_errors_total{method=\"GET\",route=\"/v1/blocks/:identifier\",error=\"not_indexed\"}"
+        ));
+        assert!(body.contains("raito_db_query_duration_seconds_count{query=\"list_blocks\"}"));
+        assert!(body.contains("raito_db_query_rows_total{query=\"list_blocks\"}"));
+        assert!(body.contains(&format!("trace_id=\"{trace_id}\"")));
+    }
+
+    #[tokio::test]
+    async fn test_block_stats() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get("/v1/blocks/869123/stats").await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["subsidy"], 3.125);
+        assert_eq!(json["input_count"], 3);
+        assert_eq!(json["output_count"], 4);
+
+        let by_hash = server
+            .get(
+                "/v1/blocks/00000000000000000264e1b06b0f6f8b0c7e9e5b8b8f9c9d8b7a6f5e4d3c2b1a/stats",
+            )
+            .await;
+        assert_eq!(by_hash.json::<Value>(), json);
+
+        // Blocks ingested without transaction details have no stats
+        let unavailable = server.get("/v1/blocks/869119/stats").await;
+        unavailable.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_header_status() {
+        let db = create_test_database().await;
+        let tip = db.get_block_by_height(869123).await.unwrap();
+        let unproved = db.get_block_by_height(869121).await.unwrap();
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        let json: Value = server
+            .get(&format!("/v1/header/{}", tip.summary.hash))
+            .await
+            .json();
+        assert_eq!(json["block_hash"], tip.summary.hash.to_string());
+        assert_eq!(json["on_active_chain"], true);
+        assert_eq!(json["confirmations"], 1);
+        assert_eq!(json["verified"], tip.summary.verified);
+
+        let json: Value = server
+            .get(&format!("/v1/header/{}", unproved.summary.hash))
+            .await
+            .json();
+        assert_eq!(json["confirmations"], 3);
+        assert_eq!(json["verified"], false);
+
+        let unknown = "f".repeat(64);
+        let json: Value = server.get(&format!("/v1/header/{unknown}")).await.json();
+        assert_eq!(json["in_chain"], false);
+        assert_eq!(json["block_hash"], unknown);
+    }
+
+    #[tokio::test]
+    async fn test_reorg_tombstones() {
+        let db = create_test_database().await;
+        let block = |height: u32, hash: &str, prev_hash: &str| {
+            serde_json::json!({
+                "height": height,
+                "hash": hash,
+                "prev_hash": prev_hash,
+                "merkle_root": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+                "version": 536870912,
+                "bits": 0x17094226,
+                "nonce": 1,
+                "tx_count": 0,
+                "total_fees": 0,
+                "timestamp": 1704067800,
+                "verified": false,
+                "txids": []
+            })
+        };
+        let old_tip = db.get_block_by_height(869123).await.unwrap();
+        let parent = db.get_block_by_height(869122).await.unwrap();
+        let webhook = webhooks::create(
+            db.as_ref(),
+            &raito_proving_service::model::CreateWebhookRequest {
+                url: "https://example.com/hook".to_string(),
+                secret: None,
+                events: vec![raito_proving_service::model::WebhookEvent::Reorg],
+                active: None,
+            },
+        )
+        .await
+        .unwrap();
+        let deliveries = || db.list_webhook_deliveries(webhook.webhook.id, None, 10, None);
+
+        // A shorter branch at the same target has less chainwork and is ignored
+        let mut shorter = block(869122, &"3".repeat(64), &parent.prev_hash.to_string());
+        shorter["bits"] = json!(parent.bits);
+        let depth = db.apply_reorg(&[shorter]).await.unwrap();
+        assert_eq!(depth, 0);
+        let tip = db.get_block_by_height(869123).await.unwrap();
+        assert_eq!(tip.summary.hash, old_tip.summary.hash);
+
+        let replacement = "1".repeat(64);
+        let depth = db
+            .apply_reorg(&[
+                block(869123, &replacement, &parent.summary.hash.to_string()),
+                block(869124, &"2".repeat(64), &replacement),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(depth, 1);
+
+        // Webhooks hear of the reorg that was applied, queued with it
+        let queued = deliveries().await.unwrap().deliveries;
+        assert_eq!(queued.len(), 1);
+        assert_eq!(
+            queued[0].payload,
+            json!({ "type": "reorg", "fork_height": 869123, "depth": 1 })
+        );
+
+        let app = create_app(db.clone());
+        let server = TestServer::new(app).unwrap();
+
+        let canonical: Value = server.get("/v1/blocks/869123").await.json();
+        assert_eq!(canonical["hash"], replacement);
+
+        let response = server.get("/v1/blocks/orphans").await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["orphans"].as_array().unwrap().len(), 1);
+        let orphan = &json["orphans"][0];
+        assert_eq!(orphan["hash"], old_tip.summary.hash.to_string());
+        assert_eq!(orphan["replaced_by"], replacement);
+        assert_eq!(orphan["reorg_depth"], 1);
+        assert_eq!(orphan["block"]["txids"], serde_json::json!(old_tip.txids));
+
+        // Transactions of the orphaned block are no longer canonical
+        let status: Value = server
+            .get(&format!("/v1/tx/{}", old_tip.txids[1]))
+            .await
+            .json();
+        assert_eq!(status["included"], false);
+
+        let header: Value = server
+            .get(&format!("/v1/header/{}", old_tip.summary.hash))
+            .await
+            .json();
+        assert_eq!(header["in_chain"], true);
+        assert_eq!(header["on_active_chain"], false);
+        assert_eq!(header["block_height"], 869123);
+        assert!(header["confirmations"].is_null());
+
+        let header: Value = server
+            .get(&format!("/v1/header/{replacement}"))
+            .await
+            .json();
+        assert_eq!(header["on_active_chain"], true);
+        assert_eq!(header["confirmations"], 2);
+
+        // Fetched by hash, the orphan is served but marked as such
+        let orphaned: Value = server
+            .get(&format!("/v1/blocks/{}", old_tip.summary.hash))
+            .await
+            .json();
+        assert_eq!(orphaned["orphaned"], true);
+        assert_eq!(orphaned["height"], 869123);
+        assert!(canonical.get("orphaned").is_none());
+        server
+            .get(&format!("/v1/blocks/{}/txids", old_tip.summary.hash))
+            .await
+            .assert_status_not_found();
+
+        // Purging tombstones leaves orphans served; they have their own retention
+        assert_eq!(db.purge_tombstones(i64::MAX).await.unwrap(), 1);
+        let json: Value = server.get("/v1/blocks/orphans").await.json();
+        assert_eq!(json["orphans"].as_array().unwrap().len(), 1);
+        let orphaned: Value = server
+            .get(&format!("/v1/blocks/{}", old_tip.summary.hash))
+            .await
+            .json();
+        assert_eq!(orphaned["orphaned"], true);
+
+        assert_eq!(db.purge_orphans(i64::MAX).await.unwrap(), 1);
+        let json: Value = server.get("/v1/blocks/orphans").await.json();
+        assert!(json["orphans"].as_array().unwrap().is_empty());
+        server
+            .get(&format!("/v1/blocks/{}", old_tip.summary.hash))
+            .await
+            .assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_block_conflicts() {
+        use axum::extract::{Query, State};
+        use raito_proving_service::{
+            database::ConflictPolicy,
+            model::{BlockConflictsQuery, ConflictResolution},
+        };
+
+        let database = |conflict_policy| async move {
+            let db = Database::new(DatabaseConfig {
+                conflict_policy,
+                ..DatabaseConfig::test_config()
+            })
+            .await
+            .unwrap();
+            db.seed_data().await.unwrap();
+            db
+        };
+        let stored = create_test_database()
+            .await
+            .get_block_by_height(869122)
+            .await
+            .unwrap();
+        let mut incoming = serde_json::to_value(&stored).unwrap();
+        incoming["hash"] = json!("7".repeat(64));
+        incoming["txids"] = json!([]);
+        incoming["total_fees"] = json!(0);
+
+        // The stored block stays, and the incoming one is only recorded
+        let db = database(ConflictPolicy::Keep).await;
+        db.insert_block(&incoming).await.unwrap();
+        let block = db.get_block_by_height(869122).await.unwrap();
+        assert_eq!(block.summary.hash, stored.summary.hash);
+        let report = db.list_block_conflicts(50, None).await.unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        let conflict = &report.conflicts[0];
+        assert_eq!(conflict.resolution, ConflictResolution::Kept);
+        assert_eq!(conflict.existing_hash, stored.summary.hash);
+        assert_eq!(conflict.block["hash"], incoming["hash"]);
+
+        // Re-inserting the stored block is no conflict
+        db.insert_block(&serde_json::to_value(&block).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.list_block_conflicts(50, None)
+                .await
+                .unwrap()
+                .conflicts
+                .len(),
+            1
+        );
+
+        // A rejected insert rolls back but its conflict is still recorded
+        let db = database(ConflictPolicy::Reject).await;
+        let err = db.insert_block(&incoming).await.unwrap_err();
+        assert!(matches!(
+            err,
+            raito_proving_service::AppError::BlockConflict(_)
+        ));
+        let block = db.get_block_by_height(869122).await.unwrap();
+        assert_eq!(block.summary.hash, stored.summary.hash);
+        let report = db.list_block_conflicts(50, None).await.unwrap();
+        assert_eq!(report.conflicts[0].resolution, ConflictResolution::Rejected);
+
+        // By default the incoming block wins and the stored one is tombstoned
+        let db = create_test_database().await;
+        db.insert_block(&incoming).await.unwrap();
+        let orphans = db.get_orphans(50, None).await.unwrap().orphans;
+        assert_eq!(orphans[0].hash, stored.summary.hash);
+        let query = |limit, cursor| {
+            Query(BlockConflictsQuery {
+                limit: Some(limit),
+                cursor,
+            })
+        };
+        let store: Arc<dyn BlockStore> = db;
+        let page = admin::block_conflicts(State(store), query(1, None))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(page.conflicts[0].resolution, ConflictResolution::Replaced);
+        assert_eq!(page.conflicts[0].height, 869122);
+        assert!(!page.has_next);
+    }
+
+    #[tokio::test]
+    async fn test_consistency_check() {
+        use axum::extract::{Query, State};
+        use raito_proving_service::model::{ConsistencyReportsQuery, FindingKind};
+
+        let db = create_test_database().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FsProofStorage::new(dir.path());
+
+        // 869122's proof is intact, 869123's never reached this storage, and
+        // 869120's was written without being recorded
+        let proof = std::fs::read("data/proofs/869122.json").unwrap();
+        storage.write(869122, &proof).await.unwrap();
+        storage.write(869120, b"{}").await.unwrap();
+        // A block claiming fewer transactions than it holds
+        let tip = db.get_block_by_height(869123).await.unwrap();
+        let mut block = sandbox::next_block(&tip);
+        block["txids"] = json!(["11".repeat(32), "22".repeat(32)]);
+        db.insert_block(&block).await.unwrap();
+
+        let report = consistency::run(&db, &storage).await.unwrap();
+        assert_eq!(report.proofs_checked, 2);
+        assert_eq!(report.blocks_checked, 6);
+        let findings: Vec<_> = report
+            .findings
+            .iter()
+            .map(|finding| (finding.kind, finding.height))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (FindingKind::MissingProofFile, Some(869123)),
+                (FindingKind::OrphanedProofFile, Some(869120)),
+                (FindingKind::TransactionOutOfRange, Some(869124)),
+            ]
+        );
+
+        // A proof registered through the service is checked by checksum
+        let registered = json!({
+            "block_height": 869124,
+            "block_hash": block["hash"],
+        })
+        .to_string();
+        proof::register(&db, &storage, 869124, registered.as_bytes(), None)
+            .await
+            .unwrap();
+        storage
+            .write(869124, b"{\"tampered\": true}")
+            .await
+            .unwrap();
+        let report = consistency::run(&db, &storage).await.unwrap();
+        assert!(report
+            .findings
+            .iter()
+            .any(|finding| finding.kind == FindingKind::ChecksumMismatch
+                && finding.height == Some(869124)));
+
+        let store: Arc<dyn BlockStore> = db;
+        let query = |limit| Query(ConsistencyReportsQuery { limit });
+        let latest = admin::consistency_reports(State(store.clone()), query(None))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(latest.reports.len(), 1);
+        assert_eq!(latest.reports[0].id, report.id);
+        assert_eq!(latest.reports[0].findings, report.findings);
+        let all = admin::consistency_reports(State(store), query(Some(20)))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(all.reports.len(), 2);
+        assert_eq!(all.reports[1].findings.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_header_continuity() {
+        let db = create_test_database().await;
+        let tip = db.get_block_by_height(869123).await.unwrap();
+        let mut block = serde_json::to_value(&tip).unwrap();
+        block["txids"] = json!([]);
+        block["total_fees"] = json!(0);
+        assert!(db.chain_breaks().await.unwrap().is_empty());
+
+        // A block whose parent is not indexed would leave a gap
+        block["height"] = json!(869125);
+        block["hash"] = json!("5".repeat(64));
+        block["prev_hash"] = json!("4".repeat(64));
+        let err = db.insert_block(&block).await.unwrap_err();
+        assert!(matches!(
+            err,
+            raito_proving_service::AppError::InvalidBlock(_)
+        ));
+        assert!(err.to_string().contains("unlinked_parent"));
+        assert!(db.get_block_by_height(869125).await.is_err());
+
+        // So would one changing difficulty away from a retarget height
+        block["height"] = json!(869124);
+        block["prev_hash"] = json!(tip.summary.hash.to_string());
+        block["bits"] = json!(0x17034219);
+        let err = db.insert_block(&block).await.unwrap_err();
+        assert!(err.to_string().contains("unexpected_retarget"));
+        block["bits"] = json!(tip.bits);
+
+        // Replacing a parent leaves its child unlinked, which is reported
+        let parent = db.get_block_by_height(869121).await.unwrap();
+        block["height"] = json!(869122);
+        block["prev_hash"] = json!(parent.summary.hash.to_string());
+        db.insert_block(&block).await.unwrap();
+        assert_eq!(db.chain_breaks().await.unwrap(), vec![869123]);
+    }
+
+    #[tokio::test]
+    async fn test_chain_info() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get("/v1/chain").await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["network"], "mainnet");
+        assert_eq!(
+            json["genesis_hash"],
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(json["tip_height"], 869123);
+        assert_eq!(json["consensus"]["subsidy"], 3.125);
+        assert_eq!(json["consensus"]["retarget_interval"], 2016);
+        assert!(json["anchors"].is_array());
+    }
+
+    #[tokio::test]
+    async fn test_version_info() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get("/v1/version").await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!json["git_commit"].as_str().unwrap().is_empty());
+        assert!(json["built_at"].as_str().is_some());
+        assert_eq!(json["api_versions"], serde_json::json!(["v1", "v2"]));
+        assert_eq!(json["proof_versions"], serde_json::json!(["v1.0"]));
+        assert!(json["features"].is_array());
+    }
+
+    #[tokio::test]
+    async fn test_network_stats() {
+        let db = create_test_database().await;
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get("/v1/stats").await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["indexed_height"], 869123);
+        assert_eq!(json["proved_height"], 869123);
+        assert_eq!(json["proof_coverage"], 40.0);
+        assert_eq!(json["queue_depth"], 3);
+        assert_eq!(json["avg_proving_time_ms"], 45000.0);
+        assert!(json["avg_proof_size"].as_f64().unwrap() > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_proof_latency() {
+        let db = create_test_database().await;
+        let backlog = db.proof_backlog().await.unwrap();
+        let stats = db.get_network_stats().await.unwrap();
+        assert!(backlog.blocks > 0);
+        assert_eq!(backlog.blocks, stats.queue_depth);
+        assert!(backlog.oldest_ingested_at.is_some());
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get("/v1/stats/proof-latency").await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["sample_count"], 2);
+        assert_eq!(json["sla_secs"], 3600);
+        assert_eq!(json["breaches"], 0);
+        assert_eq!(json["pending_over_sla"], 0);
+        assert!(json["p50_secs"].as_i64().unwrap() <= json["max_secs"].as_i64().unwrap());
+
+        let response = server
+            .get("/v1/stats/proof-latency")
+            .add_query_param("window", 1)
+            .await;
+        assert_eq!(response.json::<Value>()["sample_count"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_proof_listing() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        let json: Value = server
+            .get("/v1/proofs")
+            .add_query_param("limit", 1)
+            .await
+            .json();
+        assert_eq!(json["proofs"][0]["height"], 869122);
+        assert_eq!(json["proofs"][0]["proof_url"], "/v1/blocks/869122/proof");
+        assert_eq!(json["has_next"], true);
+
+        let json: Value = server
+            .get("/v1/proofs")
+            .add_query_param("cursor", json["next_cursor"].as_u64().unwrap())
+            .await
+            .json();
+        assert_eq!(json["proofs"].as_array().unwrap().len(), 1);
+        assert_eq!(json["proofs"][0]["height"], 869123);
+        assert_eq!(json["has_next"], false);
+
+        let json: Value = server
+            .get("/v1/proofs")
+            .add_query_param("version", "v9.9")
+            .await
+            .json();
+        assert!(json["proofs"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_proof_coverage() {
+        let db = create_test_database().await;
+        db.mark_proof_failed(869120).await.unwrap();
+        db.mark_proof_archived(869123).await.unwrap();
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server
+            .get("/v1/proofs/coverage")
+            .add_query_param("limit", 2)
+            .await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["gaps"][0]["start_height"], 869119);
+        assert_eq!(json["gaps"][0]["reason"], "never_attempted");
+        assert_eq!(json["gaps"][1]["start_height"], 869120);
+        assert_eq!(json["gaps"][1]["reason"], "failed");
+        assert_eq!(json["has_next"], true);
+
+        let cursor = json["next_cursor"].as_u64().unwrap();
+        let response = server
+            .get("/v1/proofs/coverage")
+            .add_query_param("limit", 2)
+            .add_query_param("cursor", cursor)
+            .await;
+        let json: Value = response.json();
+        assert_eq!(json["gaps"][0]["start_height"], 869121);
+        assert_eq!(json["gaps"][0]["reason"], "never_attempted");
+        assert_eq!(json["gaps"][1]["start_height"], 869123);
+        assert_eq!(json["gaps"][1]["reason"], "archived");
+        assert_eq!(json["has_next"], false);
+
+        server
+            .get("/v1/proofs/coverage")
+            .add_query_param("limit", 0)
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_proof_job() {
+        let db = create_test_database().await;
+        let job = db.create_proof_job(869123, 1_700_000_000).await.unwrap();
+        let app = create_app(db);
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get(&format!("/v1/jobs/{}", job.id)).await;
+        response.assert_status_ok();
+        let json: Value = response.json();
+        assert_eq!(json["height"], 869123);
+        assert_eq!(json["status"], "queued");
+        assert_eq!(json["created_at"], 1_700_000_000);
+        assert!(json["finished_at"].is_null());
+
+        let response = server.get(&format!("/v1/jobs/{}", job.id + 1)).await;
+        response.assert_status_not_found();
+        assert_eq!(response.json::<Value>()["code"], "JOB_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_reload_runtime_settings() {
+        let db = create_test_database().await;
+        let reloader = Arc::new(Reloader::from_lookup(&|_| None));
+        let server = TestServer::new(build_app(db, reloader.clone())).unwrap();
+        let origin = |origin: &'static str| {
+            let request = server.get("/v1/chain");
+            async move {
+                request
+                    .add_header("origin".parse().unwrap(), origin.parse().unwrap())
+                    .await
+            }
+        };
+
+        let response = origin("https://b.example").await;
+        assert_eq!(response.header("ratelimit-limit"), "120");
+        assert_eq!(response.header("access-control-allow-origin"), "*");
+
+        let settings = std::collections::HashMap::from([
+            ("RATE_LIMIT_API_BURST", "1"),
+            ("CORS_ALLOWED_ORIGINS", "https://a.example"),
+            ("MAX_CONCURRENT_PROOF_DOWNLOADS", "4"),
+        ]);
+        let reloaded = reloader.apply(&|name| settings.get(name).map(|v| v.to_string()));
+        assert_eq!(
+            reloaded,
+            ["rate_limits", "concurrency", "cors", "maintenance"]
+        );
+        assert_eq!(reloader.proof_download_limit.max(), 4);
+
+        // The same app picks the new settings up for the next request
+        let response = origin("https://a.example").await;
+        assert_eq!(response.header("ratelimit-limit"), "1");
+        assert_eq!(
+            response.header("access-control-allow-origin"),
+            "https://a.example"
+        );
+        let response = origin("https://b.example").await;
+        response.assert_status(axum::http::StatusCode::TOO_MANY_REQUESTS);
+        assert!(!response
+            .headers()
+            .contains_key("access-control-allow-origin"));
+
+        // Reloading goes through the admin API, which is off without a token
+        server
+            .post("/admin/reload")
+            .await
+            .assert_status(axum::http::StatusCode::NOT_IMPLEMENTED);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode() {
+        use raito_proving_service::model::MaintenanceMode;
+
+        let db = create_test_database().await;
+        let reloader = Arc::new(Reloader::from_lookup(&|_| None));
+        let server = TestServer::new(build_app(db, reloader.clone())).unwrap();
+        let maintenance = &reloader.maintenance;
+        assert_eq!(maintenance.status().mode, MaintenanceMode::Off);
+
+        // Read-only: reads are served, writes get 503 before reaching auth
+        maintenance.set(MaintenanceMode::ReadOnly, 120, None);
+        server.get("/v1/chain").await.assert_status_ok();
+        let response = server
+            .post("/admin/prune")
+            .json(&json!({"below_height": 1}))
+            .await;
+        response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.header("retry-after"), "120");
+        // The JSON-RPC facade only reads
+        server
+            .post("/v1/rpc")
+            .json(&json!({"method": "getblockcount"}))
+            .await
+            .assert_status_ok();
+        assert!(response.json::<Value>()["error"]
+            .as_str()
+            .unwrap()
+            .contains("read-only"));
+
+        maintenance.set(
+            MaintenanceMode::Full,
+            60,
+            Some("Storage migration".to_string()),
+        );
+        let since = maintenance.status().since.unwrap();
+        let response = server.get("/v1/chain").await;
+        response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.header("retry-after"), "60");
+        assert!(response.json::<Value>()["error"]
+            .as_str()
+            .unwrap()
+            .contains("Storage migration"));
+
+        // Probes and the controls to end maintenance stay reachable
+        server.get("/healthz").await.assert_status_ok();
+        server.get("/metrics").await.assert_status_ok();
+        server
+            .put("/admin/maintenance")
+            .json(&json!({"mode": "off"}))
+            .await
+            .assert_status(axum::http::StatusCode::NOT_IMPLEMENTED);
+
+        // Reloading applies the configured mode
+        let settings = std::collections::HashMap::from([("MAINTENANCE_MODE", "full")]);
+        reloader.apply(&|name| settings.get(name).map(|v| v.to_string()));
+        let status = maintenance.status();
+        assert_eq!(status.since, Some(since));
+        assert_eq!(status.retry_after_secs, 300);
+        assert_eq!(status.message.as_deref(), Some("Storage migration"));
+        reloader.apply(&|_| None);
+        assert_eq!(maintenance.status().mode, MaintenanceMode::Off);
+        assert!(maintenance.status().since.is_none());
+        server.get("/v1/chain").await.assert_status_ok();
+    }
+
+    /// A one-transaction block on `prev` at regtest difficulty, whose
+    /// coinbase has `script_sig` and pays 50 BTC.
+    fn regtest_block(version: i32, prev: [u8; 32], script_sig: &[u8]) -> Vec<u8> {
+        use raito_proving_service::hash::sha256d;
+
+        let mut coinbase = 1i32.to_le_bytes().to_vec();
+        coinbase.push(1);
+        coinbase.extend([0; 32]);
+        coinbase.extend(u32::MAX.to_le_bytes());
+        coinbase.push(script_sig.len() as u8);
+        coinbase.extend(script_sig);
+        coinbase.extend(u32::MAX.to_le_bytes());
+        coinbase.push(1);
+        coinbase.extend(5_000_000_000u64.to_le_bytes());
+        coinbase.extend([1, 0x51]);
+        coinbase.extend([0; 4]);
+
+        let mut header = version.to_le_bytes().to_vec();
+        header.extend(prev);
+        header.extend(sha256d(&coinbase));
+        header.extend(1_700_000_000u32.to_le_bytes());
+        header.extend(0x207fffffu32.to_le_bytes());
+        header.extend([0; 4]);
+        for nonce in 0u32.. {
+            header[76..80].copy_from_slice(&nonce.to_le_bytes());
+            if sha256d(&header)[31] < 0x7f {
+                break;
+            }
+        }
+        [header, vec![1], coinbase].concat()
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_transaction_without_upstream() {
+        let db = create_test_database().await;
+        let server = TestServer::new(create_app(db)).unwrap();
+
+        // Off unless BROADCAST_UPSTREAM is set, which tests leave alone
+        let response = server.post("/v1/tx").text("0100").await;
+        response.assert_status(axum::http::StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(response.json::<Value>()["code"], "FEATURE_DISABLED");
+    }
+
+    #[tokio::test]
+    async fn test_submit_block() {
+        use raito_proving_service::hash::sha256d;
+
+        let db = Arc::new(
+            Database::new(DatabaseConfig::test_config())
+                .await
+                .expect("Failed to create test database"),
+        );
+        let created =
+            raito_proving_service::auth::issue_key(db.as_ref(), "pipeline", KeyScope::Write)
+                .await
+                .unwrap();
+        let bearer = format!("Bearer {}", created.key);
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+
+        // The first block is placed at the height its coinbase pushes
+        let first = regtest_block(2, [0x11; 32], &[0x02, 0xc8, 0x00]);
+        let hex_body = hex::encode(&first);
+        server
+            .post("/v1/blocks")
+            .text(hex_body.clone())
+            .await
+            .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+        // A read key is known but may not write
+        let reader =
+            raito_proving_service::auth::issue_key(db.as_ref(), "explorer", KeyScope::Read)
+                .await
+                .unwrap();
+        let response = server
+            .post("/v1/blocks")
+            .add_header(
+                "authorization".parse().unwrap(),
+                format!("Bearer {}", reader.key).parse().unwrap(),
+            )
+            .text(hex_body.clone())
+            .await;
+        response.assert_status(axum::http::StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.json::<Value>()["error"],
+            "Forbidden: requires an API key with the write scope"
+        );
+        let response = server
+            .post("/v1/blocks")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .text(hex_body.clone())
+            .await;
+        response.assert_status(axum::http::StatusCode::CREATED);
+        let block = response.json::<Value>();
+        assert_eq!(block["height"], 200);
+        assert_eq!(block["tx_count"], 1);
+        assert_eq!(block["total_fees"], 0.0);
+        assert_eq!(
+            response.header("location"),
+            format!("/v1/blocks/{}", block["hash"].as_str().unwrap())
+        );
+        server.get("/v1/blocks/200").await.assert_status_ok();
+
+        // Submitting it again changes nothing
+        server
+            .post("/v1/blocks")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .text(hex_body)
+            .await
+            .assert_status_ok();
+
+        // A child without a BIP 34 height is placed on its parent
+        let child = regtest_block(1, sha256d(&first[..80]), &[0x00]);
+        let response = server
+            .post("/v1/blocks")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .content_type("application/octet-stream")
+            .bytes(child.into())
+            .await;
+        response.assert_status(axum::http::StatusCode::CREATED);
+        assert_eq!(response.json::<Value>()["height"], 201);
+
+        // Bytes that are no block, and a block whose header lies
+        let response = server
+            .post("/v1/blocks")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .text("00ff")
+            .await;
+        response.assert_status_bad_request();
+        assert_eq!(response.json::<Value>()["code"], "MALFORMED_BLOCK");
+        let mut tampered = regtest_block(2, [0x22; 32], &[0x02, 0xd0, 0x07]);
+        tampered[36] ^= 1;
+        server
+            .post("/v1/blocks")
+            .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+            .text(hex::encode(tampered))
+            .await
+            .assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(db.get_block_by_height(2000).await.is_err());
+
+        // Only the blocks that were indexed are audited, under the key
+        let log = db.list_audit_log(None, None, 50, None).await.unwrap();
+        let actions: Vec<_> = log.entries.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, ["block.submit", "block.submit"]);
+        assert_eq!(
+            log.entries[1].target,
+            Some(format!("block:{}", block["hash"].as_str().unwrap()))
+        );
+        assert_eq!(log.entries[1].actor, format!("key:{}", created.api_key.id));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_keys() {
+        use raito_proving_service::hash::sha256d;
+
+        let db = Arc::new(
+            Database::new(DatabaseConfig::test_config())
+                .await
+                .expect("Failed to create test database"),
+        );
+        let mut bearers = Vec::new();
+        for name in ["pipeline", "backfill"] {
+            let created =
+                raito_proving_service::auth::issue_key(db.as_ref(), name, KeyScope::Write)
+                    .await
+                    .unwrap();
+            bearers.push(format!("Bearer {}", created.key));
+        }
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+        let submit = |bearer: &str, key: &str, block: &[u8]| {
+            server
+                .post("/v1/blocks")
+                .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+                .add_header("idempotency-key".parse().unwrap(), key.parse().unwrap())
+                .text(hex::encode(block))
+        };
+        let first = regtest_block(2, [0x11; 32], &[0x02, 0xc8, 0x00]);
+        let child = regtest_block(1, sha256d(&first[..80]), &[0x00]);
+
+        let response = submit(&bearers[0], "retry-1", &first).await;
+        response.assert_status(axum::http::StatusCode::CREATED);
+        assert!(response.maybe_header("idempotent-replayed").is_none());
+        let created = response.json::<Value>();
+
+        // A retry gets the first response again, not a fresh 200
+        let response = submit(&bearers[0], "retry-1", &first).await;
+        response.assert_status(axum::http::StatusCode::CREATED);
+        assert_eq!(response.header("idempotent-replayed"), "true");
+        assert_eq!(
+            response.header("location"),
+            format!("/v1/blocks/{}", created["hash"].as_str().unwrap())
+        );
+        assert_eq!(response.json::<Value>(), created);
+
+        // Keys are per client, and bound to the request they were first used for
+        submit(&bearers[1], "retry-1", &first)
+            .await
+            .assert_status_ok();
+        let response = submit(&bearers[0], "retry-1", &child).await;
+        response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.json::<Value>()["code"], "IDEMPOTENCY_KEY_REUSED");
+
+        // A failed request does not hold its key
+        submit(&bearers[0], "retry-2", b"\x00")
+            .await
+            .assert_status_bad_request();
+        submit(&bearers[0], "retry-2", &child)
+            .await
+            .assert_status(axum::http::StatusCode::CREATED);
+
+        let response = submit(&bearers[0], "two words", &child).await;
+        response.assert_status_bad_request();
+        assert_eq!(response.json::<Value>()["code"], "INVALID_IDEMPOTENCY_KEY");
+    }
+
+    #[tokio::test]
+    async fn test_upload_block_proof() {
+        let db = create_test_database().await;
+        let created =
+            raito_proving_service::auth::issue_key(db.as_ref(), "prover", KeyScope::Write)
+                .await
+                .unwrap();
+        let bearer = format!("Bearer {}", created.key);
+        let server = TestServer::new(create_app(db.clone())).unwrap();
+        let upload = |height: u32, body: Vec<u8>| {
+            server
+                .post(&format!("/v1/blocks/{height}/proof"))
+                .add_header("authorization".parse().unwrap(), bearer.parse().unwrap())
+                .content_type("application/json")
+                .bytes(body.into())
+        };
+
+        let proof = std::fs::read("data/proofs/869122.json").unwrap();
+        let seeded = db.get_proofs(100, None, None, None).await.unwrap().proofs;
+        let seeded_at = seeded
+            .iter()
+            .find(|record| record.height == 869122)
+            .unwrap()
+            .generated_at;
+        server
+            .post("/v1/blocks/869122/proof")
+            .bytes(proof.clone().into())
+            .await
+            .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+        // A proof filed under another block, a corrupted upload, and a block
+        // the index has not reached are refused
+        let response = upload(869121, proof.clone()).await;
+        response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.json::<Value>()["code"], "INVALID_PROOF");
+        let response = upload(869122, proof.clone())
+            .add_header(
+                "x-proof-checksum".parse().unwrap(),
+                format!("sha256={}", "00".repeat(32)).parse().unwrap(),
+            )
+            .await;
+        response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(response.json::<Value>()["detail"]
+            .as_str()
+            .unwrap()
+            .contains("x-proof-checksum"));
+        upload(900000, proof.clone())
+            .await
+            .assert_status_not_found();
+
+        // A matching checksum lets the proof through to the block check; the
+        // bundled proof names a malformed hash, so nothing is stored
+        let response = upload(869122, proof.clone())
+            .add_header(
+                "x-proof-checksum".parse().unwrap(),
+                format!("sha256={}", proof::checksum(&proof))
+                    .parse()
+                    .unwrap(),
+            )
+            .await;
+        response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(response.json::<Value>()["detail"]
+            .as_str()
+            .unwrap()
+            .contains("block_hash"));
+        let proofs = db.get_proofs(100, None, None, None).await.unwrap().proofs;
+        let record = proofs.iter().find(|record| record.height == 869122);
+        assert_eq!(record.unwrap().generated_at, seeded_at);
+        let log = db.list_audit_log(None, None, 50, None).await.unwrap();
+        assert!(log.entries.is_empty());
+
+        // Stored uploads are audited under the key that made them. The
+        // bundled proof goes back byte for byte, leaving the shared proof
+        // directory as other tests expect it
+        let bundled = std::fs::read("data/proofs/869123.json").unwrap();
+        let response = upload(869123, bundled.clone()).await;
+        response.assert_status(axum::http::StatusCode::CREATED);
+        assert_eq!(std::fs::read("data/proofs/869123.json").unwrap(), bundled);
+        let log = db.list_audit_log(None, None, 50, None).await.unwrap();
+        assert_eq!(log.entries.len(), 1);
+        assert_eq!(log.entries[0].action, "proof.upload");
+        assert_eq!(log.entries[0].target.as_deref(), Some("height:869123"));
+        assert_eq!(log.entries[0].actor, format!("key:{}", created.api_key.id));
+    }
+
     #[tokio::test]
     async fn test_cors_headers() {
         let db = create_test_database().await;
@@ -0,0 +1,143 @@
+use crate::{
+    config::{self, Lookup},
+    error::{AppError, Result},
+    model::{MaintenanceMode, MaintenanceStatus},
+};
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use std::sync::{Arc, RwLock};
+
+/// Paths served in every mode: probes, metrics, and the admin endpoints
+/// needed to end maintenance.
+const ALWAYS_ALLOWED: &[&str] = &[
+    "/healthz",
+    "/readyz",
+    "/metrics",
+    "/admin/maintenance",
+    "/admin/reload",
+];
+
+/// POST endpoints that only read, served in read-only mode.
+const READ_ONLY_POSTS: &[&str] = &["/v1/rpc"];
+
+#[derive(Debug, Clone)]
+pub struct MaintenanceConfig {
+    pub mode: MaintenanceMode,
+    /// `Retry-After` sent with rejected requests.
+    pub retry_after_secs: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            mode: MaintenanceMode::Off,
+            retry_after_secs: 300,
+        }
+    }
+}
+
+impl MaintenanceConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(&config::env)
+    }
+
+    pub fn from_lookup(lookup: Lookup) -> Self {
+        let defaults = Self::default();
+        Self {
+            mode: lookup("MAINTENANCE_MODE")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.mode),
+            retry_after_secs: lookup("MAINTENANCE_RETRY_AFTER_SECS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.retry_after_secs),
+        }
+    }
+}
+
+/// The current maintenance mode, switched by config reloads and the admin
+/// endpoint; whichever set it last wins.
+#[derive(Debug)]
+pub struct Maintenance {
+    status: RwLock<MaintenanceStatus>,
+}
+
+impl Maintenance {
+    pub fn new(config: &MaintenanceConfig) -> Self {
+        let maintenance = Self {
+            status: RwLock::new(MaintenanceStatus {
+                mode: MaintenanceMode::Off,
+                retry_after_secs: config.retry_after_secs,
+                message: None,
+                since: None,
+            }),
+        };
+        maintenance.set(config.mode, config.retry_after_secs, None);
+        maintenance
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        self.status
+            .read()
+            .expect("maintenance lock poisoned")
+            .clone()
+    }
+
+    /// Switches to `mode`. `since` is kept when only the retry hint or
+    /// message changes.
+    pub fn set(&self, mode: MaintenanceMode, retry_after_secs: u64, message: Option<String>) {
+        let mut status = self.status.write().expect("maintenance lock poisoned");
+        let since = match mode {
+            MaintenanceMode::Off => None,
+            _ if mode == status.mode => status.since,
+            _ => Some(Utc::now().timestamp()),
+        };
+        *status = MaintenanceStatus {
+            mode,
+            retry_after_secs,
+            message,
+            since,
+        };
+    }
+
+    /// Rejects the request when the current mode doesn't serve it.
+    pub fn check(&self, method: &Method, path: &str) -> Result<()> {
+        let status = self.status.read().expect("maintenance lock poisoned");
+        let rejected = match status.mode {
+            MaintenanceMode::Off => false,
+            MaintenanceMode::ReadOnly => {
+                !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+                    && !(*method == Method::POST && READ_ONLY_POSTS.contains(&path))
+            }
+            MaintenanceMode::Full => true,
+        };
+        if !rejected || ALWAYS_ALLOWED.contains(&path) {
+            return Ok(());
+        }
+        let message = status.message.clone().unwrap_or_else(|| match status.mode {
+            MaintenanceMode::ReadOnly => "the service is read-only".to_string(),
+            _ => "the service is down for maintenance".to_string(),
+        });
+        Err(AppError::Maintenance {
+            message,
+            retry_after_secs: status.retry_after_secs,
+        })
+    }
+}
+
+pub type SharedMaintenance = Arc<Maintenance>;
+
+/// Answers 503 with `Retry-After` for requests the maintenance mode holds
+/// back.
+pub async fn maintenance_middleware(
+    State(maintenance): State<SharedMaintenance>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    maintenance.check(request.method(), request.uri().path())?;
+    Ok(next.run(request).await)
+}
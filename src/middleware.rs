@@ -1,16 +1,213 @@
+use crate::{
+    config::{self, Lookup},
+    error::{AppError, ErrorKind},
+    telemetry::{Metrics, RouteLabels},
+};
 use axum::{
-    extract::Request,
-    http::{HeaderValue, Method},
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue, Method},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
+};
+use http_body_util::LengthLimitError;
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
-use std::time::Instant;
-use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
-use tracing::info;
+use tower::{Layer, ServiceExt};
+use tower_http::{
+    compression::{
+        predicate::{NotForContentType, Predicate, SizeAbove},
+        CompressionLayer,
+    },
+    cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer},
+};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+pub struct TimeoutConfig {
+    /// Budget for block, transaction and other lookups.
+    pub lookup_secs: u64,
+    /// Budget for a proof download to start sending; streaming it may take longer.
+    pub proof_download_secs: u64,
+    /// Budget for submissions that do heavy work, such as admin jobs.
+    pub submission_secs: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            lookup_secs: 10,
+            proof_download_secs: 60,
+            submission_secs: 300,
+        }
+    }
+}
+
+impl TimeoutConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            lookup_secs: std::env::var("LOOKUP_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.lookup_secs),
+            proof_download_secs: std::env::var("PROOF_DOWNLOAD_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.proof_download_secs),
+            submission_secs: std::env::var("SUBMISSION_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.submission_secs),
+        }
+    }
+
+    pub fn lookup(&self) -> Duration {
+        Duration::from_secs(self.lookup_secs)
+    }
+
+    pub fn proof_download(&self) -> Duration {
+        Duration::from_secs(self.proof_download_secs)
+    }
+
+    pub fn submission(&self) -> Duration {
+        Duration::from_secs(self.submission_secs)
+    }
+}
+
+/// Largest request bodies accepted, per route group. Routes without a body
+/// get none: axum's default applies to them.
+#[derive(Debug, Clone)]
+pub struct BodyLimitConfig {
+    /// Admin requests, which are small JSON documents.
+    pub admin_bytes: usize,
+    /// Prove job submissions, batch queries and JSON-RPC calls.
+    pub jobs_bytes: usize,
+    /// Block submissions; the default fits a 4 MB block sent as hex.
+    pub block_bytes: usize,
+    /// Proof uploads.
+    pub proof_upload_bytes: usize,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        Self {
+            admin_bytes: 64 * 1024,
+            jobs_bytes: 1024 * 1024,
+            block_bytes: 8 * 1024 * 1024,
+            proof_upload_bytes: 512 * 1024 * 1024,
+        }
+    }
+}
+
+impl BodyLimitConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            admin_bytes: std::env::var("BODY_LIMIT_ADMIN_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.admin_bytes),
+            jobs_bytes: std::env::var("BODY_LIMIT_JOBS_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.jobs_bytes),
+            block_bytes: std::env::var("BODY_LIMIT_BLOCK_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.block_bytes),
+            proof_upload_bytes: std::env::var("BODY_LIMIT_PROOF_UPLOAD_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.proof_upload_bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// Responses smaller than this are sent uncompressed.
+    pub min_bytes: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_bytes: 512,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("COMPRESSION_ENABLED")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.enabled),
+            min_bytes: std::env::var("COMPRESSION_MIN_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.min_bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    /// Origins browsers may call the API from; any origin when empty.
+    pub allowed_origins: Vec<HeaderValue>,
+}
+
+impl CorsConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(&config::env)
+    }
 
-pub fn cors_layer() -> CorsLayer {
+    pub fn from_lookup(lookup: Lookup) -> Self {
+        Self {
+            allowed_origins: lookup("CORS_ALLOWED_ORIGINS")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|origin| !origin.is_empty() && *origin != "*")
+                        .filter_map(|origin| HeaderValue::from_str(origin).ok())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// CORS policy that can be swapped while the server runs.
+pub type SharedCors = Arc<RwLock<CorsLayer>>;
+
+/// Applies whichever CORS policy is current when the request arrives.
+pub async fn cors_middleware(
+    State(cors): State<SharedCors>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let cors = cors.read().expect("CORS lock poisoned").clone();
+    match cors.layer(next).oneshot(request).await {
+        Ok(response) => response,
+        Err(infallible) => match infallible {},
+    }
+}
+
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let allow_origin = if config.allowed_origins.is_empty() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(config.allowed_origins.clone())
+    };
     CorsLayer::new()
-        .allow_origin(AllowOrigin::any())
+        .allow_origin(allow_origin)
         .allow_methods(AllowMethods::list([
             Method::GET,
             Method::POST,
@@ -23,20 +220,135 @@ pub fn cors_layer() -> CorsLayer {
         .allow_credentials(false)
 }
 
+/// gzip, brotli or zstd compression, whichever the client prefers, for the
+/// routes it is layered on. Apply it per router: byte-range downloads must
+/// stay uncompressed so offsets keep referring to the stored file.
+pub fn compression_layer(config: &CompressionConfig) -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new()
+        .gzip(config.enabled)
+        .br(config.enabled)
+        .zstd(config.enabled)
+        .compress_when(
+            SizeAbove::new(config.min_bytes)
+                .and(NotForContentType::GRPC)
+                .and(NotForContentType::IMAGES)
+                .and(NotForContentType::SSE),
+        )
+}
+
+/// Downgrades strong ETags on compressed responses to weak ones: the bytes
+/// differ per encoding, which a strong validator must not paper over. Layer
+/// it outside [`compression_layer`]; `If-None-Match` still matches either.
+pub async fn weaken_compressed_etag<B>(mut response: Response<B>) -> Response<B> {
+    if !response.headers().contains_key(header::CONTENT_ENCODING) {
+        return response;
+    }
+    let weak = response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .and_then(|etag| HeaderValue::from_str(&format!("W/{etag}")).ok());
+    if let Some(weak) = weak {
+        response.headers_mut().insert(header::ETAG, weak);
+    }
+    response
+}
+
+/// Answers `413` with the usual error body when the request body is over
+/// `limit` bytes: up front from `Content-Length`, otherwise once that many
+/// bytes have arrived. Pair with `DefaultBodyLimit::max(limit)` so extractors
+/// accept what this lets through.
+pub async fn body_limit_middleware(
+    State(limit): State<usize>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > limit as u64) {
+        return Err(AppError::PayloadTooLarge(limit));
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, limit).await {
+        Ok(bytes) => bytes,
+        Err(e) if is_length_limit(&e) => return Err(AppError::PayloadTooLarge(limit)),
+        Err(e) => {
+            return Err(AppError::Io(std::io::Error::other(format!(
+                "failed to read request body: {e}"
+            ))))
+        }
+    };
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await)
+}
+
+fn is_length_limit(error: &axum::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(e) = source {
+        if e.is::<LengthLimitError>() {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// Answers `504` with the usual error body when the handler has not produced
+/// a response within `budget`, dropping the handler and whatever query it
+/// was stuck on.
+pub async fn timeout_middleware(
+    State(budget): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    match tokio::time::timeout(budget, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!(path, budget_secs = budget.as_secs(), "Request timed out");
+            AppError::Timeout(budget.as_secs()).into_response()
+        }
+    }
+}
+
 pub async fn metrics_middleware(request: Request, next: Next) -> Response {
     let start = Instant::now();
     let method = request.method().clone();
     let uri = request.uri().path().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let labels = RouteLabels {
+        method: method.to_string(),
+        route,
+    };
+    let metrics = Metrics::global();
+    let in_flight = metrics.track_in_flight(&labels);
 
     let response = next.run(request).await;
 
+    drop(in_flight);
     let duration = start.elapsed();
-    let status = response.status().as_u16();
+    let status = response.status();
+
+    if let Some(ErrorKind(kind)) = response.extensions().get::<ErrorKind>() {
+        metrics.record_http_error(&labels, kind);
+    }
+    metrics.observe_http_request(labels, status, duration);
 
     info!(
         method = %method,
         uri = %uri,
-        status = status,
+        status = status.as_u16(),
         duration_ms = duration.as_millis(),
         "HTTP request processed"
     );
@@ -64,3 +376,83 @@ pub async fn security_headers_middleware(request: Request, next: Next) -> Respon
 
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        http::StatusCode,
+        middleware,
+        routing::{get, post},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeout_returns_gateway_timeout() {
+        let app = Router::new()
+            .route("/fast", get(|| async { "ok" }))
+            .route(
+                "/stalled",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    "late"
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Duration::from_secs(5),
+                timeout_middleware,
+            ));
+        let request = |path| Request::get(path).body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(request("/fast")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.oneshot(request("/stalled")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["status"], 504);
+        assert_eq!(body["error"], "Request timed out after 5s");
+    }
+
+    #[tokio::test]
+    async fn test_body_limit() {
+        let app = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .layer(middleware::from_fn_with_state(8, body_limit_middleware));
+        let post = |body: Body| Request::post("/echo").body(body).unwrap();
+        let chunked = |chunks: &'static [&'static str]| {
+            Body::from_stream(futures_util::stream::iter(
+                chunks.iter().map(|chunk| Ok::<_, std::io::Error>(*chunk)),
+            ))
+        };
+
+        let response = app
+            .clone()
+            .oneshot(post(Body::from("12345678")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .clone()
+            .oneshot(post(chunked(&["1234", "5678"])))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Rejected from Content-Length, and without one once the limit is passed
+        for body in [Body::from("123456789"), chunked(&["12345", "6789"])] {
+            let response = app.clone().oneshot(post(body)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["status"], 413);
+            assert_eq!(body["error"], "Request body exceeds the limit of 8 bytes");
+        }
+    }
+}
@@ -1,13 +1,81 @@
-use serde::{Deserialize, Serialize};
+use crate::{
+    error::{AppError, Result},
+    hash::{BlockHash, Txid, Wtxid},
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
 use utoipa::ToSchema;
 use validator::Validate;
 
+pub const SATS_PER_BTC: i64 = 100_000_000;
+
+/// Reads an amount in satoshis. Integers are satoshis; floats are BTC, as
+/// written before amounts became integers, and are rounded to the satoshi.
+pub fn parse_amount(value: &serde_json::Value) -> Option<i64> {
+    match value.as_i64() {
+        Some(sats) => Some(sats),
+        None => value.as_f64().map(btc_to_sats),
+    }
+}
+
+/// Converts a BTC amount to satoshis, rounding to the nearest one.
+pub fn btc_to_sats(btc: f64) -> i64 {
+    (btc * SATS_PER_BTC as f64).round() as i64
+}
+
+pub fn sats_to_btc(sats: i64) -> f64 {
+    sats as f64 / SATS_PER_BTC as f64
+}
+
+/// `/v1` reports amounts in BTC, as it did before they were stored as
+/// satoshis; `/v2` serves the satoshis.
+fn serialize_btc<S: Serializer>(sats: &i64, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(sats_to_btc(*sats))
+}
+
+fn serialize_btc_opt<S: Serializer>(
+    sats: &Option<i64>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match sats {
+        Some(sats) => serialize_btc(sats, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_amount_opt<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<i64>, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    if value.is_null() {
+        return Ok(None);
+    }
+    parse_amount(&value)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom("expected an amount in satoshis"))
+}
+
+/// Amounts stored as JSON, such as key-value store records, may predate
+/// integer satoshis.
+fn deserialize_amount<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<i64, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    parse_amount(&value).ok_or_else(|| serde::de::Error::custom("expected an amount in satoshis"))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct BlockSummary {
     pub height: u32,
-    pub hash: String,
+    pub hash: BlockHash,
     pub tx_count: u32,
-    pub total_fees: f64,
+    /// Fees paid by the block's transactions, in BTC. Held in satoshis.
+    #[serde(
+        serialize_with = "serialize_btc",
+        deserialize_with = "deserialize_amount"
+    )]
+    #[schema(value_type = f64)]
+    pub total_fees: i64,
     pub timestamp: i64,
     pub verified: bool,
 }
@@ -16,24 +84,234 @@ pub struct BlockSummary {
 pub struct BlockDetail {
     #[serde(flatten)]
     pub summary: BlockSummary,
-    pub prev_hash: String,
+    pub prev_hash: BlockHash,
     pub merkle_root: String,
+    /// Root of the witness tree, in display byte order like `merkle_root`;
+    /// null unless every transaction's raw bytes were ingested.
+    pub witness_merkle_root: Option<String>,
+    /// The coinbase's commitment to the witness tree (BIP 141) as hex; null
+    /// for blocks without one or without full transaction data.
+    pub witness_commitment: Option<String>,
+    pub version: i32,
     pub bits: u32,
     pub nonce: u32,
-    pub txids: Vec<String>,
+    /// Serialized size in bytes, with and without witness data; null when
+    /// the block was ingested without them or its transactions.
+    pub size: Option<u32>,
+    pub stripped_size: Option<u32>,
+    /// Weight units, at most 4,000,000.
+    pub weight: Option<u32>,
+    /// Relative to the difficulty-1 target, as `getblock` reports it.
+    pub difficulty: f64,
+    /// Cumulative work of the chain up to and including this block, as 64
+    /// hex digits. Counted from the first indexed block unless ingest
+    /// documents give it; null while a parent's is unknown.
+    pub chainwork: Option<String>,
+    /// Empty when requested with `include_txids=false`; page through
+    /// `/v1/blocks/{identifier}/txids` instead.
+    pub txids: Vec<Txid>,
+    /// Witness txids in block order, present under the same conditions as
+    /// `witness_merkle_root`; left out with `include_txids=false`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wtxids: Vec<Wtxid>,
+    /// Null for blocks ingested before coinbase details were recorded.
+    pub coinbase: Option<CoinbaseInfo>,
     pub proof_url: String,
+    /// True when the block's transactions were pruned; `txids` is then empty.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pruned: bool,
+    /// True when a reorg displaced the block from the canonical chain; it is
+    /// then served as it was when orphaned, from `/v1/blocks/orphans`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub orphaned: bool,
+}
+
+/// A block's coinbase transaction, as recorded at ingest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CoinbaseInfo {
+    pub txid: Txid,
+    /// New coins the block may create at its height, in BTC.
+    #[serde(
+        serialize_with = "serialize_btc",
+        deserialize_with = "deserialize_amount"
+    )]
+    #[schema(value_type = f64)]
+    pub subsidy: i64,
+    /// Coinbase outputs minus the subsidy, in BTC; null without the
+    /// coinbase's transaction details.
+    #[serde(
+        serialize_with = "serialize_btc_opt",
+        deserialize_with = "deserialize_amount_opt"
+    )]
+    #[schema(value_type = Option<f64>)]
+    pub claimed_fees: Option<i64>,
+    /// Whether `claimed_fees` equals the block's `total_fees`. Miners may
+    /// claim less than they could, but never more.
+    pub fees_match: Option<bool>,
+    /// The coinbase input's script as hex, when its raw bytes were ingested.
+    pub script_sig: Option<String>,
+    /// Printable text in the script, where pools sign their blocks.
+    pub miner_tag: Option<String>,
+}
+
+impl BlockDetail {
+    /// Serializes the 80-byte block header in wire format, so its double
+    /// SHA-256 is the block hash.
+    pub fn raw_header(&self) -> Result<[u8; 80]> {
+        let mut header = [0u8; 80];
+        header[0..4].copy_from_slice(&self.version.to_le_bytes());
+        header[4..36].copy_from_slice(&self.prev_hash.to_internal_bytes());
+        header[36..68].copy_from_slice(&internal_byte_order(&self.merkle_root)?);
+        header[68..72].copy_from_slice(&(self.summary.timestamp as u32).to_le_bytes());
+        header[72..76].copy_from_slice(&self.bits.to_le_bytes());
+        header[76..80].copy_from_slice(&self.nonce.to_le_bytes());
+        Ok(header)
+    }
+}
+
+// Hashes are displayed big-endian but serialized little-endian in headers.
+fn internal_byte_order(hash: &str) -> Result<[u8; 32]> {
+    let mut bytes: [u8; 32] = hex::decode(hash)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| AppError::Store(anyhow::anyhow!("Malformed stored hash: {}", hash)))?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BlockStats {
+    pub height: u32,
+    pub size: u32,
+    pub weight: u32,
+    /// Fee rates in sat/vB over non-coinbase transactions.
+    pub avg_fee_rate: f64,
+    pub median_fee_rate: f64,
+    pub input_count: u32,
+    pub output_count: u32,
+    /// Block subsidy in BTC.
+    #[serde(
+        serialize_with = "serialize_btc",
+        deserialize_with = "deserialize_amount"
+    )]
+    #[schema(value_type = f64)]
+    pub subsidy: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct TransactionStatus {
     pub included: bool,
     pub block_height: Option<u32>,
+    pub block_hash: Option<BlockHash>,
+    /// Blocks on top of and including the one containing the transaction,
+    /// as of the indexed tip.
+    pub confirmations: Option<u32>,
+    /// Proof of the containing block, present once one is available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_verified: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionInput {
+    /// Previous outpoint; both fields are null for coinbase inputs.
+    pub prev_txid: Option<Txid>,
+    pub prev_vout: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionOutput {
+    pub value: f64,
+    pub script_pubkey: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransactionDetail {
+    pub txid: Txid,
+    pub block_height: u32,
+    pub position_in_block: u32,
+    /// Detail fields are null when only the txid has been indexed.
+    pub fee: Option<f64>,
+    pub size: Option<u32>,
+    pub vsize: Option<u32>,
+    pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptType {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    OpReturn,
+    Nonstandard,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OutputSpend {
+    pub txid: Txid,
+    pub input: u32,
+    pub block_height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OutputStatus {
+    pub txid: Txid,
+    pub vout: u32,
+    pub value: f64,
+    pub script_pubkey: String,
+    pub script_type: ScriptType,
+    pub block_height: u32,
+    pub unspent: bool,
+    pub spent_by: Option<OutputSpend>,
+    /// Height of the indexed tip the spent status is evaluated against.
+    pub tip_height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressTransaction {
+    pub txid: Txid,
+    pub block_height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressTransactionsResponse {
+    pub address: String,
+    pub script_pubkey: String,
+    pub transactions: Vec<AddressTransaction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Utxo {
+    pub txid: Txid,
+    pub vout: u32,
+    pub value: f64,
+    pub block_height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressUtxosResponse {
+    pub address: String,
+    pub script_pubkey: String,
+    pub utxos: Vec<Utxo>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct HeaderStatus {
+    pub block_hash: BlockHash,
+    /// Whether the header is known, on the active chain or on a branch
+    /// orphaned by a reorg.
     pub in_chain: bool,
     pub block_height: Option<u32>,
+    /// Set only while the header is on the active chain.
+    pub confirmations: Option<u32>,
+    /// The block has a proof and it verified.
+    pub verified: bool,
+    pub on_active_chain: bool,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -41,6 +319,15 @@ pub struct BlocksQuery {
     #[validate(range(min = 1, max = 50))]
     pub limit: Option<u32>,
     pub cursor: Option<u32>,
+    /// `desc` (newest first, default) or `asc` to walk the chain forward.
+    pub order: Option<SortOrder>,
+    /// `next` (default) for the page after `cursor`, `prev` for the one before it.
+    pub direction: Option<PageDirection>,
+    pub verified: Option<bool>,
+    /// Inclusive lower bound on the block timestamp (unix seconds).
+    pub from_timestamp: Option<i64>,
+    /// Inclusive upper bound on the block timestamp (unix seconds).
+    pub to_timestamp: Option<i64>,
 }
 
 impl Default for BlocksQuery {
@@ -48,13 +335,990 @@ impl Default for BlocksQuery {
         Self {
             limit: Some(20),
             cursor: None,
+            order: None,
+            direction: None,
+            verified: None,
+            from_timestamp: None,
+            to_timestamp: None,
+        }
+    }
+}
+
+impl BlocksQuery {
+    pub fn filter(&self) -> BlockFilter {
+        BlockFilter {
+            verified: self.verified,
+            from_timestamp: self.from_timestamp,
+            to_timestamp: self.to_timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn reverse(self) -> Self {
+        match self {
+            SortOrder::Asc => SortOrder::Desc,
+            SortOrder::Desc => SortOrder::Asc,
+        }
+    }
+}
+
+/// Which side of the cursor a page is taken from, in the listing's order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PageDirection {
+    #[default]
+    Next,
+    Prev,
+}
+
+impl PageDirection {
+    /// The order rows are read in: away from the cursor, so backwards
+    /// through the listing for `prev`.
+    pub fn travel(self, order: SortOrder) -> SortOrder {
+        match self {
+            PageDirection::Next => order,
+            PageDirection::Prev => order.reverse(),
+        }
+    }
+}
+
+/// Optional constraints applied when listing blocks.
+#[derive(Debug, Clone, Default)]
+pub struct BlockFilter {
+    pub verified: Option<bool>,
+    pub from_timestamp: Option<i64>,
+    pub to_timestamp: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct BlockDetailQuery {
+    /// Set to `false` to leave `txids` empty for large blocks.
+    pub include_txids: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct BlockTxidsQuery {
+    #[validate(range(min = 1, max = 1000))]
+    pub limit: Option<u32>,
+    pub cursor: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlockTxidsResponse {
+    pub height: u32,
+    /// Transaction IDs in block order.
+    pub txids: Vec<Txid>,
+    pub total: u32,
+    pub has_next: bool,
+    /// Position in the block of the last txid returned.
+    pub next_cursor: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct OrphansQuery {
+    #[validate(range(min = 1, max = 50))]
+    pub limit: Option<u32>,
+    pub cursor: Option<i64>,
+}
+
+/// A block displaced from the canonical chain by a reorg.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlockTombstone {
+    pub height: u32,
+    pub hash: BlockHash,
+    /// Hash of the block that took this height, if the new branch reached it.
+    pub replaced_by: Option<BlockHash>,
+    /// Number of canonical blocks the reorg displaced.
+    pub reorg_depth: u32,
+    pub orphaned_at: i64,
+    /// The block as it was when it was orphaned.
+    pub block: BlockDetail,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrphansResponse {
+    pub orphans: Vec<BlockTombstone>,
+    pub has_next: bool,
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ProofsQuery {
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+    /// Continue after this height, as returned in `next_cursor`.
+    pub cursor: Option<u32>,
+    pub verified: Option<bool>,
+    /// Only return proofs produced by this prover version.
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofRecord {
+    pub height: u32,
+    pub version: String,
+    /// Proof file size in bytes, when recorded.
+    pub size: Option<i64>,
+    pub verified: bool,
+    pub generated_at: i64,
+    pub proof_url: String,
+}
+
+/// A proof accepted by `POST /v1/blocks/{height}/proof`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofUpload {
+    pub height: u32,
+    /// Proof file size in bytes.
+    pub size: i64,
+    /// SHA-256 of the proof as stored, hex encoded.
+    pub checksum: String,
+    pub proof_url: String,
+}
+
+/// A transaction relayed by `POST /v1/tx`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransactionBroadcast {
+    pub txid: Txid,
+    /// Where to follow the transaction until it is included and proved.
+    pub status_url: String,
+    /// Its status in the index when it was relayed.
+    pub status: TransactionStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofsResponse {
+    pub proofs: Vec<ProofRecord>,
+    pub has_next: bool,
+    pub next_cursor: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CoverageQuery {
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+    /// Height to resume from, as returned in `next_cursor`.
+    pub cursor: Option<u32>,
+}
+
+/// Why a range of indexed blocks has no proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofGapReason {
+    NeverAttempted,
+    Failed,
+    Archived,
+}
+
+impl ProofGapReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NeverAttempted => "never_attempted",
+            Self::Failed => "failed",
+            Self::Archived => "archived",
+        }
+    }
+}
+
+impl FromStr for ProofGapReason {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "never_attempted" => Ok(Self::NeverAttempted),
+            "failed" => Ok(Self::Failed),
+            "archived" => Ok(Self::Archived),
+            other => Err(AppError::Store(anyhow::anyhow!(
+                "Unknown proof status: {other}"
+            ))),
+        }
+    }
+}
+
+/// Contiguous heights lacking a proof for the same reason.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofGap {
+    pub start_height: u32,
+    pub end_height: u32,
+    pub count: u32,
+    pub reason: ProofGapReason,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofCoverageResponse {
+    pub gaps: Vec<ProofGap>,
+    pub has_next: bool,
+    pub next_cursor: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RawEncoding {
+    #[default]
+    Binary,
+    Hex,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RawQuery {
+    pub format: Option<RawEncoding>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NetworkStats {
+    /// Highest block height ingested by the service.
+    pub indexed_height: Option<u32>,
+    /// Highest block height with a proof available.
+    pub proved_height: Option<u32>,
+    /// Percentage of indexed blocks that have a proof.
+    pub proof_coverage: f64,
+    /// Average proof file size in bytes.
+    pub avg_proof_size: Option<f64>,
+    pub avg_proving_time_ms: Option<f64>,
+    /// Indexed blocks still waiting for a proof.
+    pub queue_depth: u32,
+    /// Whether the indexed tip is older than `STALE_THRESHOLD_SECS`, so
+    /// answers may be behind the real chain tip.
+    #[serde(default)]
+    pub stale: bool,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ProofLatencyQuery {
+    /// Number of most recent proofs to compute percentiles over.
+    #[validate(range(min = 1, max = 10000))]
+    pub window: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LatencyPercentiles {
+    pub p50_secs: Option<i64>,
+    pub p90_secs: Option<i64>,
+    pub p99_secs: Option<i64>,
+    pub max_secs: Option<i64>,
+}
+
+/// Time from block ingestion to proof availability.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofLatencyStats {
+    pub sample_count: u32,
+    #[serde(flatten)]
+    pub percentiles: LatencyPercentiles,
+    pub sla_secs: i64,
+    /// Proofs in the window that took longer than the SLA.
+    pub breaches: u32,
+    /// Blocks without a proof that were ingested longer ago than the SLA.
+    pub pending_over_sla: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChainAnchor {
+    pub height: u32,
+    pub hash: BlockHash,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConsensusParams {
+    pub halving_interval: u32,
+    /// Number of halvings that have occurred at the tip.
+    pub halving_epoch: u32,
+    pub blocks_until_halving: u32,
+    /// Block subsidy in BTC at the tip.
+    #[serde(
+        serialize_with = "serialize_btc",
+        deserialize_with = "deserialize_amount"
+    )]
+    #[schema(value_type = f64)]
+    pub subsidy: i64,
+    pub retarget_interval: u32,
+    pub blocks_until_retarget: u32,
+    pub target_block_time_secs: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VersionInfo {
+    pub version: String,
+    /// Commit the service was built from, or `unknown`.
+    pub git_commit: String,
+    /// RFC 3339 time of the build.
+    pub built_at: Option<String>,
+    /// API versions served, as in the `/v1` path prefix.
+    pub api_versions: Vec<String>,
+    /// Proof formats (`proof_version`) accepted and served.
+    pub proof_versions: Vec<String>,
+    /// Optional features turned on: `admin`, `api_keys_required`,
+    /// `encryption`, `jwt`, `pruning`, `read_replica`, `tls`.
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChainInfo {
+    pub network: String,
+    pub genesis_hash: String,
+    pub tip_height: Option<u32>,
+    /// Transaction data below this height has been pruned; headers and proofs
+    /// are kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pruned_below: Option<u32>,
+    pub consensus: ConsensusParams,
+    /// Trusted checkpoints the service is configured with.
+    pub anchors: Vec<ChainAnchor>,
+}
+
+/// Ordered from best to worst, so the overall verdict is the maximum over
+/// the components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    Up,
+    /// Serving, but something needs attention.
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ComponentHealth {
+    pub status: HealthState,
+    /// Why the component is not `up`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ComponentHealth {
+    pub fn up() -> Self {
+        Self {
+            status: HealthState::Up,
+            detail: None,
+        }
+    }
+
+    pub fn degraded(detail: impl Into<String>) -> Self {
+        Self {
+            status: HealthState::Degraded,
+            detail: Some(detail.into()),
         }
     }
+
+    pub fn down(detail: impl Into<String>) -> Self {
+        Self {
+            status: HealthState::Down,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HealthComponents {
+    pub database: ComponentHealth,
+    /// Free space on the proof disk.
+    pub proof_storage: ComponentHealth,
+    /// Whether proofs keep up with ingested blocks, per the proof SLA.
+    pub prover: ComponentHealth,
+    /// How far the read replica trails the primary.
+    pub sync: ComponentHealth,
+}
+
+impl HealthComponents {
+    pub fn overall(&self) -> HealthState {
+        [
+            &self.database,
+            &self.proof_storage,
+            &self.prover,
+            &self.sync,
+        ]
+        .into_iter()
+        .map(|component| component.status)
+        .max()
+        .unwrap_or(HealthState::Up)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct HealthStatus {
+    /// The worst state of any component.
+    pub status: HealthState,
+    pub timestamp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replica_lag_blocks: Option<u32>,
+    pub components: HealthComponents,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PruneRequest {
+    /// Prune transaction data of blocks below this height.
+    pub below_height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PruneResponse {
+    /// Pruned horizon after the run; it never moves down.
+    pub pruned_below: u32,
+    pub transactions_removed: u64,
+}
+
+/// A client API key as listed to admins; the key itself is never stored.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    /// Leading characters of the key, to tell keys apart.
+    pub prefix: String,
+    pub scope: KeyScope,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+/// What an API key may do. Write keys can also read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyScope {
+    #[default]
+    Read,
+    /// Also submit blocks and upload proofs.
+    Write,
+}
+
+impl KeyScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyScope::Read => "read",
+            KeyScope::Write => "write",
+        }
+    }
+}
+
+impl FromStr for KeyScope {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "read" => Ok(KeyScope::Read),
+            "write" => Ok(KeyScope::Write),
+            other => Err(AppError::Store(anyhow::anyhow!(
+                "Unknown API key scope: {other}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    /// Who or what the key is for.
+    pub name: String,
+    /// `read` unless the key is for a pipeline that submits blocks or
+    /// proofs.
+    #[serde(default)]
+    pub scope: KeyScope,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiKey {
+    /// The key to send as `Authorization: Bearer <key>`. Shown only once.
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeysResponse {
+    pub keys: Vec<ApiKey>,
+}
+
+/// Events a webhook can be notified of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// A block's proof was verified.
+    ProofVerified,
+    /// The indexed chain switched to another branch.
+    Reorg,
+    /// Proving a block failed.
+    JobFailed,
+}
+
+impl WebhookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::ProofVerified => "proof_verified",
+            WebhookEvent::Reorg => "reorg",
+            WebhookEvent::JobFailed => "job_failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "proof_verified" => Some(WebhookEvent::ProofVerified),
+            "reorg" => Some(WebhookEvent::Reorg),
+            "job_failed" => Some(WebhookEvent::JobFailed),
+            _ => None,
+        }
+    }
+}
+
+/// A webhook subscription. Its secret is only shown when it is set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    /// Inactive webhooks are kept but not notified.
+    pub active: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    /// `http` or `https` URL notifications are posted to.
+    pub url: String,
+    /// Secret deliveries are signed with; generated when omitted.
+    pub secret: Option<String>,
+    pub events: Vec<WebhookEvent>,
+    /// Defaults to `true`.
+    pub active: Option<bool>,
+}
+
+/// Changes to a webhook; omitted fields are left as they are.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub secret: Option<String>,
+    pub events: Option<Vec<WebhookEvent>>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedWebhook {
+    /// The secret deliveries are signed with. Shown only once.
+    pub secret: String,
+    #[serde(flatten)]
+    pub webhook: Webhook,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WebhooksResponse {
+    pub webhooks: Vec<Webhook>,
+}
+
+/// Where a webhook delivery stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for its first or next attempt.
+    Pending,
+    /// The endpoint accepted it.
+    Delivered,
+    /// Every attempt failed; it is not retried.
+    Dead,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Dead => "dead",
+        }
+    }
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "delivered" => Ok(DeliveryStatus::Delivered),
+            "dead" => Ok(DeliveryStatus::Dead),
+            other => Err(AppError::Store(anyhow::anyhow!(
+                "Unknown delivery status: {other}"
+            ))),
+        }
+    }
+}
+
+/// Where a proof job stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for a prover.
+    Queued,
+    /// A prover is working on it.
+    Running,
+    /// The proof is registered and verified against the block, which is
+    /// marked verified.
+    Completed,
+    /// The prover gave up; `error` says why.
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "completed" => Ok(JobStatus::Completed),
+            "failed" => Ok(JobStatus::Failed),
+            other => Err(AppError::Store(anyhow::anyhow!(
+                "Unknown job status: {other}"
+            ))),
+        }
+    }
+}
+
+/// A request to prove one block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ProofJob {
+    pub id: i64,
+    pub height: u32,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    /// When it completed or failed.
+    pub finished_at: Option<i64>,
+}
+
+/// An event sent, or to be sent, to a webhook, and how its attempts went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: WebhookEvent,
+    /// The JSON body posted.
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    /// When a pending delivery is next attempted.
+    pub next_attempt_at: i64,
+    /// HTTP status of the last attempt, if the endpoint answered.
+    pub last_status_code: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    /// When it was delivered or given up on.
+    pub finished_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct WebhookDeliveriesQuery {
+    pub status: Option<DeliveryStatus>,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+    /// Continue after this delivery, as returned in `next_cursor`.
+    pub cursor: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDelivery>,
+    pub has_next: bool,
+    pub next_cursor: Option<i64>,
+}
+
+/// Requests and proof bytes counted against an API key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct UsageCounts {
+    pub requests: u64,
+    pub proof_bytes: u64,
+}
+
+/// Usage over one quota window and the limits that apply to it; a missing
+/// limit means unlimited.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UsageWindow {
+    pub used: UsageCounts,
+    pub request_limit: Option<u64>,
+    pub proof_bytes_limit: Option<u64>,
+    /// When the window starts over (unix seconds, UTC).
+    pub resets_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UsageResponse {
+    pub api_key_id: i64,
+    pub name: String,
+    pub daily: UsageWindow,
+    pub monthly: UsageWindow,
+}
+
+/// One authenticated mutating action.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// `jwt:<subject>`, `key:<id>` or `admin-token`.
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>,
+    pub created_at: i64,
+}
+
+/// What became of an insert for a height that held a different block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// The incoming block took the height; the stored one was tombstoned.
+    Replaced,
+    /// The stored block stayed; the incoming one was only recorded.
+    Kept,
+    /// The insert failed with `BLOCK_CONFLICT`.
+    Rejected,
+}
+
+impl ConflictResolution {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConflictResolution::Replaced => "replaced",
+            ConflictResolution::Kept => "kept",
+            ConflictResolution::Rejected => "rejected",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlockConflict {
+    pub id: i64,
+    pub height: u32,
+    /// The block the height held when the insert arrived.
+    pub existing_hash: BlockHash,
+    pub incoming_hash: BlockHash,
+    pub resolution: ConflictResolution,
+    pub detected_at: i64,
+    /// The incoming block as it was received.
+    #[schema(value_type = Object)]
+    pub block: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct BlockConflictsQuery {
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+    /// Continue after this conflict, as returned in `next_cursor`.
+    pub cursor: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlockConflictsResponse {
+    pub conflicts: Vec<BlockConflict>,
+    pub has_next: bool,
+    pub next_cursor: Option<i64>,
+}
+
+/// A disagreement the consistency checker found between proof storage and
+/// `proof_files`, or between the blocks and transactions tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// A proof is recorded but its file is not in storage.
+    MissingProofFile,
+    /// Storage holds a proof that is not recorded.
+    OrphanedProofFile,
+    /// The stored proof's checksum differs from the one recorded with it.
+    ChecksumMismatch,
+    /// The stored proof's size differs from the recorded one, for proofs
+    /// recorded without a checksum.
+    SizeMismatch,
+    /// A proof is recorded for a height with no block.
+    ProofWithoutBlock,
+    /// Transactions reference a height with no block.
+    DanglingTransactions,
+    /// A transaction's position is past its block's `tx_count`.
+    TransactionOutOfRange,
+}
+
+impl FindingKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FindingKind::MissingProofFile => "missing_proof_file",
+            FindingKind::OrphanedProofFile => "orphaned_proof_file",
+            FindingKind::ChecksumMismatch => "checksum_mismatch",
+            FindingKind::SizeMismatch => "size_mismatch",
+            FindingKind::ProofWithoutBlock => "proof_without_block",
+            FindingKind::DanglingTransactions => "dangling_transactions",
+            FindingKind::TransactionOutOfRange => "transaction_out_of_range",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ConsistencyFinding {
+    pub kind: FindingKind,
+    pub height: Option<u32>,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConsistencyReport {
+    pub id: i64,
+    pub checked_at: i64,
+    /// Proofs recorded in `proof_files` when the check ran.
+    pub proofs_checked: u32,
+    /// Blocks indexed when the check ran.
+    pub blocks_checked: u32,
+    pub findings: Vec<ConsistencyFinding>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConsistencyReportsResponse {
+    /// Most recent runs first.
+    pub reports: Vec<ConsistencyReport>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ConsistencyReportsQuery {
+    #[validate(range(min = 1, max = 20))]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct AuditLogQuery {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+    /// Continue after this entry, as returned in `next_cursor`.
+    pub cursor: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditLogResponse {
+    pub entries: Vec<AuditEntry>,
+    pub has_next: bool,
+    pub next_cursor: Option<i64>,
+}
+
+/// State of the database circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReloadResponse {
+    /// Configuration file that was re-read, if one is set.
+    pub config_file: Option<String>,
+    /// Settings now in effect: `rate_limits`, `concurrency`, `cors`,
+    /// `maintenance`, `log_level` when logging was set up at startup, and
+    /// `tls` when serving HTTPS.
+    pub reloaded: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConfigSetting {
+    pub name: String,
+    /// Secrets read `<redacted>`.
+    pub value: String,
+    /// `environment`, `secret_file`, `vault` or `file`.
+    pub source: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConfigResponse {
+    pub config_file: Option<String>,
+    /// Settings as read at startup; reloadable ones may have changed since.
+    pub settings: Vec<ConfigSetting>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReseedResponse {
+    pub tip_height: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReindexRequest {
+    pub from_height: u32,
+    /// Inclusive.
+    pub to_height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReindexResponse {
+    /// Blocks rewritten; pruned blocks in the range are skipped.
+    pub reindexed: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RequeueResponse {
+    /// Failed proofs put back in the backlog.
+    pub requeued: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceMode {
+    #[default]
+    Off,
+    /// Reads are served; anything that writes is rejected.
+    ReadOnly,
+    /// Everything but health checks, metrics and the admin maintenance
+    /// controls is rejected.
+    Full,
+}
+
+impl std::str::FromStr for MaintenanceMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "off" => Ok(Self::Off),
+            "read_only" => Ok(Self::ReadOnly),
+            "full" => Ok(Self::Full),
+            other => Err(format!("unknown maintenance mode: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceStatus {
+    pub mode: MaintenanceMode,
+    /// `Retry-After` sent with rejected requests.
+    pub retry_after_secs: u64,
+    /// Shown to rejected clients in place of the default explanation.
+    pub message: Option<String>,
+    /// When the current mode was entered (unix seconds); null when off.
+    pub since: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MaintenanceRequest {
+    pub mode: MaintenanceMode,
+    /// Defaults to `MAINTENANCE_RETRY_AFTER_SECS`.
+    pub retry_after_secs: Option<u64>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessStatus {
+    /// `ready`, or `degraded` while the proof disk is low on space.
     pub status: String,
+    pub circuit: CircuitState,
+    /// Free space on the proof disk at the last capacity check.
+    pub storage_available_bytes: Option<u64>,
     pub timestamp: i64,
 }
 
@@ -64,4 +1328,47 @@ pub struct BlocksResponse {
     pub total: u32,
     pub has_next: bool,
     pub next_cursor: Option<u32>,
+    /// Pass with `direction=prev` to get the page before this one.
+    pub prev_cursor: Option<u32>,
+    /// True when the page was shrunk below the requested limit because the
+    /// service is under load.
+    #[serde(default)]
+    pub server_limited: bool,
+    /// Seconds to wait before asking for a full page again, set alongside
+    /// `server_limited`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u32>,
+}
+
+impl BlocksResponse {
+    /// Builds a page from up to `limit + 1` blocks read past `cursor` in
+    /// `direction`'s travel order; the extra block only tells whether more
+    /// follow. Coming from a cursor means there is a page back the other way.
+    pub fn page(
+        mut blocks: Vec<BlockSummary>,
+        limit: usize,
+        cursor: Option<u32>,
+        direction: PageDirection,
+        total: u32,
+    ) -> Self {
+        let more = blocks.len() > limit;
+        blocks.truncate(limit);
+        let (has_next, has_prev) = match direction {
+            PageDirection::Next => (more, cursor.is_some()),
+            PageDirection::Prev => {
+                blocks.reverse();
+                (cursor.is_some(), more)
+            }
+        };
+
+        Self {
+            next_cursor: blocks.last().filter(|_| has_next).map(|b| b.height),
+            prev_cursor: blocks.first().filter(|_| has_prev).map(|b| b.height),
+            blocks,
+            total,
+            has_next,
+            server_limited: false,
+            retry_after: None,
+        }
+    }
 }
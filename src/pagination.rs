@@ -0,0 +1,82 @@
+use axum::{
+    http::{header, HeaderValue, Uri},
+    response::{IntoResponse, Response},
+};
+use std::fmt::Display;
+
+/// Query parameters a link sets itself; any others the request had, such as
+/// `limit` and filters, carry over.
+const CURSOR_PARAMS: &[&str] = &["cursor", "direction"];
+
+/// `response` with an RFC 8288 `Link` header pointing at the pages next to
+/// it, so clients can page without reading the body. Each target is the
+/// request's URI with `cursor` swapped for the neighbour's; `prev` targets
+/// add `direction=prev`, which only `/v1/blocks` takes.
+pub fn with_links(
+    response: impl IntoResponse,
+    uri: &Uri,
+    next: Option<impl Display>,
+    prev: Option<impl Display>,
+) -> Response {
+    let mut response = response.into_response();
+    let links: Vec<String> = [
+        next.map(|cursor| (page_uri(uri, &cursor, None), "next")),
+        prev.map(|cursor| (page_uri(uri, &cursor, Some("prev")), "prev")),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(target, rel)| format!("<{target}>; rel=\"{rel}\""))
+    .collect();
+    if links.is_empty() {
+        return response;
+    }
+    if let Ok(value) = HeaderValue::from_str(&links.join(", ")) {
+        response.headers_mut().insert(header::LINK, value);
+    }
+    response
+}
+
+fn page_uri(uri: &Uri, cursor: &dyn Display, direction: Option<&str>) -> String {
+    let mut params: Vec<String> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or_default();
+            !param.is_empty() && !CURSOR_PARAMS.contains(&name)
+        })
+        .map(str::to_string)
+        .collect();
+    params.push(format!("cursor={cursor}"));
+    if let Some(direction) = direction {
+        params.push(format!("direction={direction}"));
+    }
+    format!("{}?{}", uri.path(), params.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links() {
+        let uri: Uri = "/v1/blocks?limit=2&cursor=869121&direction=prev&verified=true"
+            .parse()
+            .unwrap();
+        let response = with_links((), &uri, Some(869119), Some(869120));
+        assert_eq!(
+            response.headers()[header::LINK],
+            "</v1/blocks?limit=2&verified=true&cursor=869119>; rel=\"next\", \
+             </v1/blocks?limit=2&verified=true&cursor=869120&direction=prev>; rel=\"prev\""
+        );
+
+        let uri: Uri = "/v1/proofs".parse().unwrap();
+        let response = with_links((), &uri, Some(5), None::<u32>);
+        assert_eq!(
+            response.headers()[header::LINK],
+            "</v1/proofs?cursor=5>; rel=\"next\""
+        );
+        let response = with_links((), &uri, None::<u32>, None::<u32>);
+        assert!(!response.headers().contains_key(header::LINK));
+    }
+}
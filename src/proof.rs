@@ -0,0 +1,493 @@
+use crate::{
+    database::Database,
+    error::{AppError, Result},
+    model::BlockSummary,
+    storage::{ProofReader, ProofStorage},
+};
+use axum::http::{HeaderMap, HeaderValue};
+use bytes::Bytes;
+use chrono::Utc;
+use futures_util::Stream;
+use hmac::{Hmac, Mac};
+use http_body::{Body, Frame};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::{
+    io,
+    ops::RangeInclusive,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio_util::io::ReaderStream;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Proof format written for new proofs.
+pub const PROOF_VERSION: &str = "v1.0";
+/// Proof formats the service accepts and serves, oldest first.
+pub const SUPPORTED_PROOF_VERSIONS: &[&str] = &[PROOF_VERSION];
+
+pub const PROOF_CHECKSUM_HEADER: &str = "x-proof-checksum";
+pub const RESUME_TOKEN_HEADER: &str = "x-resume-token";
+
+// Tokens are signed with a per-process key unless a shared secret is configured,
+// so a restart (or a different replica) invalidates outstanding tokens.
+static RESUME_SECRET: Lazy<Vec<u8>> = Lazy::new(|| {
+    std::env::var("PROOF_RESUME_SECRET")
+        .map(String::into_bytes)
+        .unwrap_or_else(|_| uuid::Uuid::new_v4().as_bytes().to_vec())
+});
+
+/// Checksums hashed by [`stored_checksum`], by height.
+static CHECKSUMS: Lazy<Cache<u32, (usize, String)>> =
+    Lazy::new(|| Cache::builder().max_capacity(10_000).build());
+
+static RESUME_TOKEN_TTL_SECS: Lazy<i64> = Lazy::new(|| {
+    std::env::var("PROOF_RESUME_TOKEN_TTL_SECS")
+        .unwrap_or_else(|_| "3600".to_string())
+        .parse()
+        .unwrap_or(3600)
+});
+
+pub fn checksum(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Checksum of the stored proof of block `height`, for proofs recorded
+/// without one. Hashing a large proof would stall an async worker, so it runs
+/// on the blocking pool, once: the result is kept, with the size it was
+/// computed for, until the proof is registered again.
+pub async fn stored_checksum(height: u32, data: Bytes) -> Result<String> {
+    if let Some((size, checksum)) = CHECKSUMS.get(&height).await {
+        if size == data.len() {
+            return Ok(checksum);
+        }
+    }
+    let size = data.len();
+    let checksum = checksum_blocking(data).await?;
+    CHECKSUMS.insert(height, (size, checksum.clone())).await;
+    Ok(checksum)
+}
+
+async fn checksum_blocking(data: Bytes) -> Result<String> {
+    tokio::task::spawn_blocking(move || checksum(&data))
+        .await
+        .map_err(|_| AppError::Internal)
+}
+
+/// Checks a resume token against the checksum recorded with the proof and
+/// against the bytes about to be served. [`register`] replaces the file
+/// before recording its checksum, so for a moment the record still names the
+/// old proof while `served` is already the new one; a token bound to the old
+/// proof must not resume into it.
+pub async fn verify_resume(token: &str, recorded: &str, served: Bytes) -> Result<()> {
+    verify_resume_token(token, recorded)?;
+    verify_resume_token(token, &checksum_blocking(served).await?)
+}
+
+/// Checks that `data` is a proof document naming `block`'s height and hash,
+/// also in its public inputs when it has them, in a supported format when it
+/// names one. The STARK itself is left to the verifier; this catches a proof
+/// filed under the wrong block. Returns the reason it doesn't match.
+pub fn check_proof(data: &[u8], block: &BlockSummary) -> std::result::Result<(), String> {
+    let proof: serde_json::Value =
+        serde_json::from_slice(data).map_err(|e| format!("not a proof document: {e}"))?;
+    if proof["block_height"].as_u64() != Some(block.height as u64) {
+        return Err(format!(
+            "block_height is {}, expected {}",
+            proof["block_height"], block.height
+        ));
+    }
+    if !proof["block_hash"]
+        .as_str()
+        .is_some_and(|hash| block.hash == hash)
+    {
+        return Err(format!(
+            "block_hash is {}, expected {}",
+            proof["block_hash"], block.hash
+        ));
+    }
+    let public_hash = &proof["stark_proof"]["public_inputs"]["block_hash"];
+    if !public_hash.is_null() && !public_hash.as_str().is_some_and(|hash| block.hash == hash) {
+        return Err(format!(
+            "public inputs prove block_hash {public_hash}, expected {}",
+            block.hash
+        ));
+    }
+    if let Some(version) = proof.get("proof_version") {
+        if !version
+            .as_str()
+            .is_some_and(|v| SUPPORTED_PROOF_VERSIONS.contains(&v))
+        {
+            return Err(format!("unsupported proof_version {version}"));
+        }
+    }
+    Ok(())
+}
+
+/// Proving time a proof document reports in its `metadata`, if any.
+pub fn execution_time_ms(data: &[u8]) -> Option<i64> {
+    let proof: serde_json::Value = serde_json::from_slice(data).ok()?;
+    proof["metadata"]["execution_time_ms"].as_i64()
+}
+
+/// Stores `data` as the proof of block `height` and starts serving it.
+pub async fn register(
+    database: &Database,
+    storage: &dyn ProofStorage,
+    height: u32,
+    data: &[u8],
+    execution_time_ms: Option<i64>,
+) -> Result<()> {
+    let block = database.get_block_by_height(height).await?;
+    check_proof(data, &block.summary)
+        .map_err(|reason| AppError::InvalidProof(format!("block {height}: {reason}")))?;
+    storage
+        .write(height, data)
+        .await
+        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to write proof: {}", e)))?;
+    CHECKSUMS.invalidate(&height).await;
+    database
+        .record_proof(
+            height,
+            &format!("{}/{height}.json", storage.describe()),
+            data.len() as i64,
+            Some(&checksum(data)),
+            Utc::now().timestamp(),
+            execution_time_ms,
+        )
+        .await
+}
+
+/// Reads the stored proof of block `height` and checks it against the
+/// indexed block. Returns the proof's checksum.
+pub async fn verify(
+    database: &Database,
+    storage: &dyn ProofStorage,
+    height: u32,
+) -> Result<String> {
+    let block = database.get_block_by_height(height).await?;
+    let data = storage
+        .read(height)
+        .await
+        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to read proof: {}", e)))?
+        .ok_or_else(|| AppError::ProofNotFound(height.to_string()))?;
+    check_proof(&data, &block.summary).map_err(|reason| {
+        AppError::Store(anyhow::anyhow!(
+            "Proof does not match block {}: {}",
+            height,
+            reason
+        ))
+    })?;
+    Ok(checksum(&data))
+}
+
+fn sign(payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(&RESUME_SECRET).expect("HMAC accepts any key length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// Issues a resume token of the form `<expires_at>.<checksum>.<signature>`.
+pub fn issue_resume_token(checksum: &str) -> String {
+    let expires_at = Utc::now().timestamp() + *RESUME_TOKEN_TTL_SECS;
+    let payload = format!("{expires_at}.{checksum}");
+    let signature = hex::encode(sign(&payload).finalize().into_bytes());
+    format!("{payload}.{signature}")
+}
+
+/// Checks that `token` was issued by this service, has not expired, and was
+/// bound to the proof content identified by `checksum`.
+pub fn verify_resume_token(token: &str, checksum: &str) -> Result<()> {
+    let malformed = || AppError::InvalidResumeToken(token.to_string());
+
+    let (payload, signature) = token.rsplit_once('.').ok_or_else(malformed)?;
+    let (expires_at, token_checksum) = payload.split_once('.').ok_or_else(malformed)?;
+    let expires_at: i64 = expires_at.parse().map_err(|_| malformed())?;
+    let signature = hex::decode(signature).map_err(|_| malformed())?;
+
+    sign(payload)
+        .verify_slice(&signature)
+        .map_err(|_| malformed())?;
+
+    if expires_at < Utc::now().timestamp() {
+        return Err(AppError::ResumeConflict(
+            "resume token has expired".to_string(),
+        ));
+    }
+
+    if token_checksum != checksum {
+        return Err(AppError::ResumeConflict(
+            "proof has been superseded since the download started".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses a single `bytes=` range against a body of `len` bytes.
+///
+/// Returns `Ok(None)` for ranges this service does not handle (other units or
+/// multiple ranges), in which case the full body should be served.
+pub fn parse_range(header: &str, len: u64) -> Result<Option<RangeInclusive<u64>>> {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+
+    let unsatisfiable = || AppError::RangeNotSatisfiable(header.to_string());
+    let (start, end) = spec.split_once('-').ok_or_else(unsatisfiable)?;
+
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().map_err(|_| unsatisfiable())?;
+            if suffix == 0 || len == 0 {
+                return Err(unsatisfiable());
+            }
+            len.saturating_sub(suffix)..=len - 1
+        }
+        (start, "") => {
+            let start: u64 = start.parse().map_err(|_| unsatisfiable())?;
+            start..=len.saturating_sub(1)
+        }
+        (start, end) => {
+            let start: u64 = start.parse().map_err(|_| unsatisfiable())?;
+            let end: u64 = end.parse().map_err(|_| unsatisfiable())?;
+            if end < start {
+                return Err(unsatisfiable());
+            }
+            start..=end.min(len.saturating_sub(1))
+        }
+    };
+
+    if *range.start() >= len {
+        return Err(unsatisfiable());
+    }
+
+    Ok(Some(range))
+}
+
+/// Streams a proof file and appends its whole-file checksum (and a resume
+/// token bound to it) as HTTP trailers once the last chunk has been sent.
+pub struct ChecksumBody {
+    inner: ReaderStream<ProofReader>,
+    hasher: Option<Sha256>,
+}
+
+impl ChecksumBody {
+    pub const TRAILERS: &'static str = "x-proof-checksum, x-resume-token";
+
+    pub fn new(reader: ProofReader) -> Self {
+        Self {
+            inner: ReaderStream::new(reader),
+            hasher: Some(Sha256::new()),
+        }
+    }
+
+    fn trailers(hasher: Sha256) -> HeaderMap {
+        let checksum = hex::encode(hasher.finalize());
+        let mut trailers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&issue_resume_token(&checksum)) {
+            trailers.insert(RESUME_TOKEN_HEADER, value);
+        }
+        if let Ok(value) = HeaderValue::from_str(&format!("sha256={checksum}")) {
+            trailers.insert(PROOF_CHECKSUM_HEADER, value);
+        }
+        trailers
+    }
+}
+
+impl Body for ChecksumBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<Frame<Bytes>>>> {
+        let this = &mut *self;
+        match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                if let Some(hasher) = this.hasher.as_mut() {
+                    hasher.update(&chunk);
+                }
+                Poll::Ready(Some(Ok(Frame::data(chunk))))
+            }
+            Some(Err(e)) => Poll::Ready(Some(Err(e))),
+            None => Poll::Ready(
+                this.hasher
+                    .take()
+                    .map(|hasher| Ok(Frame::trailers(Self::trailers(hasher)))),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_token_roundtrip() {
+        let sum = checksum(b"proof");
+        let token = issue_resume_token(&sum);
+        assert!(verify_resume_token(&token, &sum).is_ok());
+    }
+
+    #[test]
+    fn test_resume_token_rejects_superseded_proof() {
+        let token = issue_resume_token(&checksum(b"old proof"));
+        let result = verify_resume_token(&token, &checksum(b"new proof"));
+        assert!(matches!(result, Err(AppError::ResumeConflict(_))));
+    }
+
+    #[test]
+    fn test_resume_token_rejects_tampering() {
+        let sum = checksum(b"proof");
+        let token = issue_resume_token(&sum);
+        let tampered = token.replacen('.', "9.", 1);
+        let result = verify_resume_token(&tampered, &sum);
+        assert!(matches!(result, Err(AppError::InvalidResumeToken(_))));
+    }
+
+    #[tokio::test]
+    async fn test_stored_checksum_is_cached() {
+        let height = u32::MAX;
+        let proof = Bytes::from_static(b"proof");
+        assert_eq!(
+            stored_checksum(height, proof).await.unwrap(),
+            checksum(b"proof")
+        );
+
+        // Served from the cache while the size matches, hashed again once it
+        // changes
+        let same_size = Bytes::from_static(b"other");
+        assert_eq!(
+            stored_checksum(height, same_size).await.unwrap(),
+            checksum(b"proof")
+        );
+        let resized = Bytes::from_static(b"longer proof");
+        assert_eq!(
+            stored_checksum(height, resized).await.unwrap(),
+            checksum(b"longer proof")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resume_rejects_proof_replaced_before_it_is_recorded() {
+        use crate::storage::FsProofStorage;
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FsProofStorage::new(dir.path());
+        storage.write(1, b"old proof").await.unwrap();
+        let recorded = checksum(b"old proof");
+        let token = issue_resume_token(&recorded);
+        let served = storage.map(1).await.unwrap().unwrap();
+        assert!(verify_resume(&token, &recorded, served).await.is_ok());
+
+        // The file is replaced while the record still names the old proof
+        storage.write(1, b"new proof").await.unwrap();
+        let served = storage.map(1).await.unwrap().unwrap();
+        let result = verify_resume(&token, &recorded, served.clone()).await;
+        assert!(matches!(result, Err(AppError::ResumeConflict(_))));
+
+        // And once the new checksum is recorded
+        let result = verify_resume(&token, &checksum(b"new proof"), served).await;
+        assert!(matches!(result, Err(AppError::ResumeConflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_checksum_body_trailers() {
+        use http_body_util::BodyExt;
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"streamed proof").unwrap();
+
+        let reader = tokio::fs::File::open(file.path()).await.unwrap();
+        let body = ChecksumBody::new(Box::new(reader));
+        let collected = body.collect().await.unwrap();
+        let trailers = collected.trailers().cloned().unwrap();
+
+        assert_eq!(collected.to_bytes().as_ref(), b"streamed proof");
+        assert_eq!(
+            trailers[PROOF_CHECKSUM_HEADER],
+            format!("sha256={}", checksum(b"streamed proof"))
+        );
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-9", 100).unwrap(), Some(0..=9));
+        assert_eq!(parse_range("bytes=90-", 100).unwrap(), Some(90..=99));
+        assert_eq!(parse_range("bytes=-10", 100).unwrap(), Some(90..=99));
+        assert_eq!(parse_range("bytes=50-500", 100).unwrap(), Some(50..=99));
+        assert_eq!(parse_range("bytes=0-1,5-6", 100).unwrap(), None);
+        assert!(parse_range("bytes=100-", 100).is_err());
+        assert!(parse_range("bytes=9-1", 100).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_register_and_verify_proof() {
+        use crate::{database::DatabaseConfig, storage::FsProofStorage};
+
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        database.seed_data().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FsProofStorage::new(dir.path());
+        let block = database.get_block_by_height(869121).await.unwrap();
+
+        assert!(matches!(
+            verify(&database, &storage, 869121).await,
+            Err(AppError::ProofNotFound(_))
+        ));
+
+        // A proof of another block is refused before anything is stored
+        let other = serde_json::json!({"block_height": 869121, "block_hash": "00ff"});
+        let err = register(
+            &database,
+            &storage,
+            869121,
+            other.to_string().as_bytes(),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AppError::InvalidProof(ref reason) if reason.contains("block_hash")));
+        assert!(!database.proof_file_exists(869121).await.unwrap());
+        let future = serde_json::json!({
+            "block_height": 869121,
+            "block_hash": block.summary.hash,
+            "proof_version": "v9.0",
+        });
+        assert!(check_proof(future.to_string().as_bytes(), &block.summary)
+            .unwrap_err()
+            .contains("unsupported proof_version"));
+        let forged = serde_json::json!({
+            "block_height": 869121,
+            "block_hash": block.summary.hash,
+            "stark_proof": {"public_inputs": {"block_hash": "00ff"}},
+        });
+        assert!(check_proof(forged.to_string().as_bytes(), &block.summary)
+            .unwrap_err()
+            .contains("public inputs"));
+
+        let proof = serde_json::json!({
+            "block_height": 869121,
+            "block_hash": block.summary.hash,
+            "stark_proof": {},
+        })
+        .to_string();
+        register(&database, &storage, 869121, proof.as_bytes(), None)
+            .await
+            .unwrap();
+        assert!(database.proof_file_exists(869121).await.unwrap());
+        assert_eq!(
+            verify(&database, &storage, 869121).await.unwrap(),
+            checksum(proof.as_bytes())
+        );
+    }
+}
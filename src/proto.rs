@@ -0,0 +1,259 @@
+//! Protobuf forms of the core resources, mirroring
+//! `proto/raito/v1/resources.proto` field for field. Keep the two in step:
+//! tags are the wire contract.
+
+use crate::{
+    encoding::ResponseEncoding,
+    model::{self, BlocksResponse},
+};
+use axum::{
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use prost::Message;
+
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BlockSummary {
+    #[prost(uint32, tag = "1")]
+    pub height: u32,
+    #[prost(string, tag = "2")]
+    pub hash: String,
+    #[prost(uint32, tag = "3")]
+    pub tx_count: u32,
+    #[prost(int64, tag = "4")]
+    pub total_fees: i64,
+    #[prost(int64, tag = "5")]
+    pub timestamp: i64,
+    #[prost(bool, tag = "6")]
+    pub verified: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct CoinbaseInfo {
+    #[prost(string, tag = "1")]
+    pub txid: String,
+    #[prost(int64, tag = "2")]
+    pub subsidy: i64,
+    #[prost(int64, optional, tag = "3")]
+    pub claimed_fees: Option<i64>,
+    #[prost(bool, optional, tag = "4")]
+    pub fees_match: Option<bool>,
+    #[prost(string, optional, tag = "5")]
+    pub script_sig: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub miner_tag: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BlockDetail {
+    #[prost(message, optional, tag = "1")]
+    pub summary: Option<BlockSummary>,
+    #[prost(string, tag = "2")]
+    pub prev_hash: String,
+    #[prost(string, tag = "3")]
+    pub merkle_root: String,
+    #[prost(string, optional, tag = "4")]
+    pub witness_merkle_root: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub witness_commitment: Option<String>,
+    #[prost(int32, tag = "6")]
+    pub version: i32,
+    #[prost(uint32, tag = "7")]
+    pub bits: u32,
+    #[prost(uint32, tag = "8")]
+    pub nonce: u32,
+    #[prost(uint32, optional, tag = "9")]
+    pub size: Option<u32>,
+    #[prost(uint32, optional, tag = "10")]
+    pub stripped_size: Option<u32>,
+    #[prost(uint32, optional, tag = "11")]
+    pub weight: Option<u32>,
+    #[prost(double, tag = "12")]
+    pub difficulty: f64,
+    #[prost(string, optional, tag = "13")]
+    pub chainwork: Option<String>,
+    #[prost(string, repeated, tag = "14")]
+    pub txids: Vec<String>,
+    #[prost(string, repeated, tag = "15")]
+    pub wtxids: Vec<String>,
+    #[prost(message, optional, tag = "16")]
+    pub coinbase: Option<CoinbaseInfo>,
+    #[prost(string, tag = "17")]
+    pub proof_url: String,
+    #[prost(bool, tag = "18")]
+    pub pruned: bool,
+    #[prost(bool, tag = "19")]
+    pub orphaned: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BlockSummaryPage {
+    #[prost(message, repeated, tag = "1")]
+    pub blocks: Vec<BlockSummary>,
+    #[prost(uint32, tag = "2")]
+    pub total: u32,
+    #[prost(bool, tag = "3")]
+    pub has_next: bool,
+    #[prost(uint32, optional, tag = "4")]
+    pub next_cursor: Option<u32>,
+    #[prost(uint32, optional, tag = "5")]
+    pub prev_cursor: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TransactionStatus {
+    #[prost(bool, tag = "1")]
+    pub included: bool,
+    #[prost(uint32, optional, tag = "2")]
+    pub block_height: Option<u32>,
+    #[prost(string, optional, tag = "3")]
+    pub block_hash: Option<String>,
+    #[prost(uint32, optional, tag = "4")]
+    pub confirmations: Option<u32>,
+    #[prost(string, optional, tag = "5")]
+    pub proof_url: Option<String>,
+    #[prost(bool, optional, tag = "6")]
+    pub proof_verified: Option<bool>,
+}
+
+impl From<&model::BlockSummary> for BlockSummary {
+    fn from(summary: &model::BlockSummary) -> Self {
+        Self {
+            height: summary.height,
+            hash: summary.hash.to_string(),
+            tx_count: summary.tx_count,
+            total_fees: summary.total_fees,
+            timestamp: summary.timestamp,
+            verified: summary.verified,
+        }
+    }
+}
+
+impl From<&model::CoinbaseInfo> for CoinbaseInfo {
+    fn from(coinbase: &model::CoinbaseInfo) -> Self {
+        Self {
+            txid: coinbase.txid.to_string(),
+            subsidy: coinbase.subsidy,
+            claimed_fees: coinbase.claimed_fees,
+            fees_match: coinbase.fees_match,
+            script_sig: coinbase.script_sig.clone(),
+            miner_tag: coinbase.miner_tag.clone(),
+        }
+    }
+}
+
+impl From<&model::BlockDetail> for BlockDetail {
+    fn from(block: &model::BlockDetail) -> Self {
+        Self {
+            summary: Some((&block.summary).into()),
+            prev_hash: block.prev_hash.to_string(),
+            merkle_root: block.merkle_root.clone(),
+            witness_merkle_root: block.witness_merkle_root.clone(),
+            witness_commitment: block.witness_commitment.clone(),
+            version: block.version,
+            bits: block.bits,
+            nonce: block.nonce,
+            size: block.size,
+            stripped_size: block.stripped_size,
+            weight: block.weight,
+            difficulty: block.difficulty,
+            chainwork: block.chainwork.clone(),
+            txids: block.txids.iter().map(ToString::to_string).collect(),
+            wtxids: block.wtxids.iter().map(ToString::to_string).collect(),
+            coinbase: block.coinbase.as_ref().map(Into::into),
+            proof_url: block.proof_url.clone(),
+            pruned: block.pruned,
+            orphaned: block.orphaned,
+        }
+    }
+}
+
+impl From<&BlocksResponse> for BlockSummaryPage {
+    fn from(page: &BlocksResponse) -> Self {
+        Self {
+            blocks: page.blocks.iter().map(Into::into).collect(),
+            total: page.total,
+            has_next: page.has_next,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+        }
+    }
+}
+
+impl From<&model::TransactionStatus> for TransactionStatus {
+    fn from(status: &model::TransactionStatus) -> Self {
+        Self {
+            included: status.included,
+            block_height: status.block_height,
+            block_hash: status.block_hash.map(|hash| hash.to_string()),
+            confirmations: status.confirmations,
+            proof_url: status.proof_url.clone(),
+            proof_verified: status.proof_verified,
+        }
+    }
+}
+
+/// Whether the client asked for protobuf over JSON.
+pub fn is_requested(headers: &axum::http::HeaderMap) -> bool {
+    ResponseEncoding::negotiate(headers) == ResponseEncoding::Protobuf
+}
+
+/// `message` as an `application/x-protobuf` response.
+pub fn response(message: &impl Message) -> Response {
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(PROTOBUF_CONTENT_TYPE),
+            ),
+            (header::VARY, HeaderValue::from_static("accept")),
+        ],
+        message.encode_to_vec(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MockStore;
+
+    #[test]
+    fn test_wire_format() {
+        // Field 1 as a varint, field 2 as length-delimited: the tags in the .proto
+        let summary = BlockSummary {
+            height: 1,
+            hash: "ab".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            summary.encode_to_vec(),
+            [0x08, 0x01, 0x12, 0x02, b'a', b'b']
+        );
+
+        let proto = include_str!("../proto/raito/v1/resources.proto");
+        for field in [
+            "uint32 height = 1;",
+            "string hash = 2;",
+            "CoinbaseInfo coinbase = 16;",
+        ] {
+            assert!(proto.contains(field), "{field}");
+        }
+    }
+
+    #[test]
+    fn test_block_round_trip() {
+        let store = MockStore::load_from_files().unwrap();
+        let block = store.get_block_by_height(869123).unwrap();
+        let message = BlockDetail::from(block);
+        let decoded = BlockDetail::decode(message.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(
+            decoded.summary.unwrap().hash,
+            block.summary.hash.to_string()
+        );
+        assert_eq!(decoded.txids.len(), block.txids.len());
+    }
+}
@@ -0,0 +1,84 @@
+use crate::database::Database;
+use std::{sync::Arc, time::Duration};
+use tracing::warn;
+
+#[derive(Debug, Clone)]
+pub struct PruneConfig {
+    /// Recent blocks whose transactions are kept; pruning is off when unset.
+    pub keep_blocks: Option<u32>,
+    /// How often the pruner runs.
+    pub interval_secs: u64,
+}
+
+impl Default for PruneConfig {
+    fn default() -> Self {
+        Self {
+            keep_blocks: None,
+            interval_secs: 3600,
+        }
+    }
+}
+
+impl PruneConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            keep_blocks: std::env::var("PRUNE_KEEP_BLOCKS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .or(defaults.keep_blocks),
+            interval_secs: std::env::var("PRUNE_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.interval_secs),
+        }
+    }
+
+    /// Height below which data may be pruned with the chain at `tip_height`.
+    pub fn horizon(&self, tip_height: u32) -> Option<u32> {
+        let keep_blocks = self.keep_blocks?;
+        Some((tip_height + 1).saturating_sub(keep_blocks))
+    }
+}
+
+/// Periodically prunes transaction data outside the retained window.
+pub fn spawn_pruner(database: Arc<Database>, config: PruneConfig) {
+    if config.keep_blocks.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        loop {
+            interval.tick().await;
+            let horizon = match database.tip_height().await {
+                Ok(Some(tip_height)) => config.horizon(tip_height),
+                Ok(None) => None,
+                Err(e) => {
+                    warn!(error = %e, "Failed to read tip height for pruning");
+                    None
+                }
+            };
+            if let Some(horizon) = horizon {
+                if let Err(e) = database.prune_below(horizon).await {
+                    warn!(error = %e, "Failed to prune transaction data");
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_horizon_keeps_recent_blocks() {
+        let config = PruneConfig {
+            keep_blocks: Some(100),
+            ..PruneConfig::default()
+        };
+        assert_eq!(config.horizon(869123), Some(869024));
+        assert_eq!(config.horizon(50), Some(0));
+        assert_eq!(PruneConfig::default().horizon(869123), None);
+    }
+}
@@ -0,0 +1,355 @@
+use crate::{
+    auth::ApiKeyIdentity,
+    config::{self, Lookup},
+    error::AppError,
+    jwt::Principal,
+    telemetry::Metrics,
+};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+    time::Instant,
+};
+
+pub const RATELIMIT_LIMIT_HEADER: &str = "ratelimit-limit";
+pub const RATELIMIT_REMAINING_HEADER: &str = "ratelimit-remaining";
+pub const RATELIMIT_RESET_HEADER: &str = "ratelimit-reset";
+
+/// Clients tracked per route group before idle ones are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Token bucket: `burst` requests at once, refilled at `per_sec`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_sec: f64,
+}
+
+impl RateLimit {
+    fn from_lookup(lookup: Lookup, prefix: &str, defaults: Self) -> Self {
+        Self {
+            burst: lookup(&format!("{prefix}_BURST"))
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.burst),
+            per_sec: lookup(&format!("{prefix}_PER_SEC"))
+                .and_then(|v| v.parse().ok())
+                .filter(|&rate: &f64| rate >= 0.0)
+                .unwrap_or(defaults.per_sec),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// JSON lookups under `/v1`.
+    pub api: RateLimit,
+    /// Proof downloads, which are far more expensive to serve.
+    pub proofs: RateLimit,
+    /// Admin endpoints.
+    pub admin: RateLimit,
+    /// Take the client address from the last `X-Forwarded-For` hop, the one
+    /// the proxy in front appended; only safe behind a proxy that sets it.
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            api: RateLimit {
+                burst: 120,
+                per_sec: 20.0,
+            },
+            proofs: RateLimit {
+                burst: 10,
+                per_sec: 0.5,
+            },
+            admin: RateLimit {
+                burst: 10,
+                per_sec: 0.2,
+            },
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(&config::env)
+    }
+
+    pub fn from_lookup(lookup: Lookup) -> Self {
+        let defaults = Self::default();
+        Self {
+            api: RateLimit::from_lookup(lookup, "RATE_LIMIT_API", defaults.api),
+            proofs: RateLimit::from_lookup(lookup, "RATE_LIMIT_PROOFS", defaults.proofs),
+            admin: RateLimit::from_lookup(lookup, "RATE_LIMIT_ADMIN", defaults.admin),
+            trust_forwarded_for: lookup("RATE_LIMIT_TRUST_FORWARDED_FOR")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.trust_forwarded_for),
+        }
+    }
+
+    pub fn limiter(&self, group: &'static str, limit: RateLimit) -> Arc<RateLimiter> {
+        Arc::new(RateLimiter::new(group, limit, self.trust_forwarded_for))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Outcome of taking a token, reported in the `RateLimit-*` headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until a token is available if none is left, otherwise until
+    /// the bucket is full again.
+    pub reset_secs: u64,
+}
+
+/// Per-client token buckets for one route group.
+#[derive(Debug)]
+pub struct RateLimiter {
+    group: &'static str,
+    /// Replaced on configuration reload; existing buckets keep their tokens.
+    limit: RwLock<RateLimit>,
+    trust_forwarded_for: bool,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(group: &'static str, limit: RateLimit, trust_forwarded_for: bool) -> Self {
+        Self {
+            group,
+            limit: RwLock::new(limit),
+            trust_forwarded_for,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        *self.limit.read().expect("rate limiter lock poisoned")
+    }
+
+    pub fn set_limit(&self, limit: RateLimit) {
+        *self.limit.write().expect("rate limiter lock poisoned") = limit;
+    }
+
+    /// Limiting is off when the burst is 0.
+    pub fn is_enabled(&self) -> bool {
+        self.limit().burst > 0
+    }
+
+    pub fn check(&self, client: &str, now: Instant) -> Decision {
+        let limit = self.limit();
+        let burst = limit.burst as f64;
+        let refill = |bucket: &mut Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * limit.per_sec).min(burst);
+            bucket.updated = now;
+        };
+
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(client) {
+            // Full buckets hold no state worth keeping
+            buckets.retain(|_, bucket| {
+                refill(bucket);
+                bucket.tokens < burst
+            });
+            // Clients cycling through addresses keep theirs partly spent, so
+            // the least recently seen tenth goes too
+            if buckets.len() >= MAX_TRACKED_CLIENTS {
+                let mut updated: Vec<Instant> = buckets.values().map(|b| b.updated).collect();
+                let (_, &mut cutoff, _) = updated.select_nth_unstable(MAX_TRACKED_CLIENTS / 10);
+                buckets.retain(|_, bucket| bucket.updated > cutoff);
+            }
+        }
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        refill(bucket);
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let missing = if allowed {
+            burst - bucket.tokens
+        } else {
+            1.0 - bucket.tokens
+        };
+        let reset_secs = if limit.per_sec > 0.0 {
+            (missing / limit.per_sec).ceil() as u64
+        } else {
+            u64::MAX
+        };
+
+        Decision {
+            allowed,
+            limit: limit.burst,
+            remaining: bucket.tokens.floor() as u32,
+            reset_secs,
+        }
+    }
+
+    /// What requests are counted against: the API key or JWT subject they
+    /// authenticated with, else the peer address, or the last
+    /// `X-Forwarded-For` hop when the proxy in front is trusted.
+    fn client_key(&self, request: &Request) -> String {
+        if let Some(identity) = request.extensions().get::<ApiKeyIdentity>() {
+            return format!("key:{}", identity.id);
+        }
+        if let Some(principal) = request.extensions().get::<Principal>() {
+            return format!("sub:{}", principal.subject);
+        }
+        match client_ip(request, self.trust_forwarded_for) {
+            Some(ip) => format!("ip:{ip}"),
+            None => "ip:unknown".to_string(),
+        }
+    }
+}
+
+/// The peer address of `request`, or its last `X-Forwarded-For` hop when
+/// the proxy in front is trusted to set it. Earlier hops come from the
+/// client, which can write anything there.
+pub fn client_ip(request: &Request, trust_forwarded_for: bool) -> Option<String> {
+    if trust_forwarded_for {
+        let forwarded = request
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty());
+        if let Some(ip) = forwarded {
+            return Some(ip.to_string());
+        }
+    }
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+}
+
+fn set_headers(headers: &mut HeaderMap, decision: &Decision) {
+    for (name, value) in [
+        (RATELIMIT_LIMIT_HEADER, decision.limit as u64),
+        (RATELIMIT_REMAINING_HEADER, decision.remaining as u64),
+        (RATELIMIT_RESET_HEADER, decision.reset_secs),
+    ] {
+        headers.insert(name, HeaderValue::from(value));
+    }
+}
+
+/// Rejects requests over the client's budget with 429 and reports the budget
+/// in `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers.
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !limiter.is_enabled() {
+        return next.run(request).await;
+    }
+    let decision = limiter.check(&limiter.client_key(&request), Instant::now());
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        Metrics::global().record_shed_request(&format!("rate_limit_{}", limiter.group));
+        AppError::RateLimited {
+            retry_after_secs: decision.reset_secs,
+        }
+        .into_response()
+    };
+    set_headers(response.headers_mut(), &decision);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(
+            "api",
+            RateLimit {
+                burst: 2,
+                per_sec: 0.5,
+            },
+            false,
+        );
+        let start = Instant::now();
+
+        let first = limiter.check("ip:1.1.1.1", start);
+        assert!(first.allowed);
+        assert_eq!((first.limit, first.remaining, first.reset_secs), (2, 1, 2));
+        assert!(limiter.check("ip:1.1.1.1", start).allowed);
+
+        let rejected = limiter.check("ip:1.1.1.1", start);
+        assert!(!rejected.allowed);
+        assert_eq!((rejected.remaining, rejected.reset_secs), (0, 2));
+
+        // Other clients have their own bucket
+        assert!(limiter.check("ip:2.2.2.2", start).allowed);
+
+        // One token is back after two seconds
+        assert!(
+            limiter
+                .check("ip:1.1.1.1", start + Duration::from_secs(2))
+                .allowed
+        );
+        assert!(
+            !limiter
+                .check("ip:1.1.1.1", start + Duration::from_secs(2))
+                .allowed
+        );
+    }
+
+    #[test]
+    fn test_tracked_clients_are_bounded() {
+        let limiter = RateLimiter::new(
+            "api",
+            RateLimit {
+                burst: 10,
+                per_sec: 0.0,
+            },
+            false,
+        );
+        let start = Instant::now();
+
+        // Spoofed addresses leave partly spent buckets that never refill
+        for i in 0..MAX_TRACKED_CLIENTS * 3 {
+            let at = start + Duration::from_millis(i as u64);
+            limiter.check(&format!("ip:{i}"), at);
+        }
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() <= MAX_TRACKED_CLIENTS);
+        // The most recent client is still tracked
+        assert!(buckets.contains_key(&format!("ip:{}", MAX_TRACKED_CLIENTS * 3 - 1)));
+    }
+
+    #[test]
+    fn test_client_ip_takes_last_forwarded_hop() {
+        let request = Request::builder()
+            .header("x-forwarded-for", "198.51.100.1, 203.0.113.7")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(client_ip(&request, true).as_deref(), Some("203.0.113.7"));
+        assert_eq!(client_ip(&request, false), None);
+    }
+}
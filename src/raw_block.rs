@@ -0,0 +1,367 @@
+use crate::{
+    hash::{sha256d, BlockHash, Txid},
+    stats,
+};
+use serde_json::{json, Value};
+
+/// Satoshis per bitcoin, for output values, which documents give in BTC.
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// A block decoded from its consensus (wire) serialization.
+#[derive(Debug, Clone)]
+pub struct RawBlock {
+    header: [u8; 80],
+    size: usize,
+    transactions: Vec<RawTransaction>,
+}
+
+#[derive(Debug, Clone)]
+struct RawTransaction {
+    txid: Txid,
+    raw: Vec<u8>,
+    stripped_size: usize,
+    /// Spent outpoints; `None` for the coinbase's null input.
+    inputs: Vec<Option<(Txid, u32)>>,
+    /// Values in satoshis with their scripts.
+    outputs: Vec<(u64, Vec<u8>)>,
+}
+
+impl RawTransaction {
+    fn weight(&self) -> usize {
+        self.stripped_size * 3 + self.raw.len()
+    }
+}
+
+/// Reads the primitives of Bitcoin's serialization off the front of a
+/// buffer.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| format!("truncated at byte {}", self.position))?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn compact_size(&mut self) -> Result<usize, String> {
+        let value = match self.take(1)?[0] {
+            0xfd => u64::from(u16::from_le_bytes(self.array()?)),
+            0xfe => u64::from(self.u32()?),
+            0xff => u64::from_le_bytes(self.array()?),
+            n => u64::from(n),
+        };
+        // Anything larger could not fit in what is left of the block
+        usize::try_from(value)
+            .ok()
+            .filter(|&count| count <= self.bytes.len() - self.position)
+            .ok_or_else(|| format!("implausible count {value} at byte {}", self.position))
+    }
+
+    fn var_bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.compact_size()?;
+        self.take(len)
+    }
+}
+
+fn read_transaction(reader: &mut Reader) -> Result<RawTransaction, String> {
+    let start = reader.position;
+    let version = reader.take(4)?;
+    let segwit = reader.bytes[reader.position..].starts_with(&[0x00, 0x01]);
+    if segwit {
+        reader.take(2)?;
+    }
+
+    let body_start = reader.position;
+    let mut inputs = Vec::new();
+    for _ in 0..reader.compact_size()? {
+        let prev_txid = reader.array::<32>()?;
+        let prev_vout = reader.u32()?;
+        reader.var_bytes()?;
+        reader.take(4)?;
+        let outpoint = (prev_txid != [0; 32] || prev_vout != u32::MAX)
+            .then(|| (Txid::from_internal_bytes(prev_txid), prev_vout));
+        inputs.push(outpoint);
+    }
+    let mut outputs = Vec::new();
+    for _ in 0..reader.compact_size()? {
+        let value = u64::from_le_bytes(reader.array()?);
+        outputs.push((value, reader.var_bytes()?.to_vec()));
+    }
+    let body = &reader.bytes[body_start..reader.position];
+
+    if segwit {
+        for _ in 0..inputs.len() {
+            for _ in 0..reader.compact_size()? {
+                reader.var_bytes()?;
+            }
+        }
+    }
+    let lock_time = reader.take(4)?;
+
+    // The txid commits to the serialization without the witness
+    let stripped = [version, body, lock_time].concat();
+    Ok(RawTransaction {
+        txid: Txid::from_internal_bytes(sha256d(&stripped)),
+        raw: reader.bytes[start..reader.position].to_vec(),
+        stripped_size: stripped.len(),
+        inputs,
+        outputs,
+    })
+}
+
+/// Decodes a standalone serialized transaction, as relayed for broadcast,
+/// and returns its txid. Returns why the bytes are not a transaction that
+/// could enter the mempool.
+pub fn decode_transaction(bytes: &[u8]) -> Result<Txid, String> {
+    let mut reader = Reader { bytes, position: 0 };
+    let tx = read_transaction(&mut reader)?;
+    if reader.position != bytes.len() {
+        return Err(format!(
+            "{} bytes left after the transaction",
+            bytes.len() - reader.position
+        ));
+    }
+    if tx.inputs.is_empty() || tx.outputs.is_empty() {
+        return Err("the transaction needs inputs and outputs".to_string());
+    }
+    if tx.inputs.contains(&None) {
+        return Err("a coinbase transaction cannot be broadcast".to_string());
+    }
+    Ok(tx.txid)
+}
+
+impl RawBlock {
+    /// Decodes a serialized block: the 80-byte header, then its
+    /// transactions, the first of which must be a coinbase. Returns why the
+    /// bytes are not a block.
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, position: 0 };
+        let header = reader.array::<80>()?;
+        let count = reader.compact_size()?;
+        let transactions = (0..count)
+            .map(|_| read_transaction(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        if reader.position != bytes.len() {
+            return Err(format!(
+                "{} bytes left after the last transaction",
+                bytes.len() - reader.position
+            ));
+        }
+        match transactions.first() {
+            Some(coinbase) if coinbase.inputs == [None] => {}
+            Some(_) => return Err("the first transaction is not a coinbase".to_string()),
+            None => return Err("the block has no transactions".to_string()),
+        }
+        Ok(Self {
+            header,
+            size: bytes.len(),
+            transactions,
+        })
+    }
+
+    pub fn hash(&self) -> BlockHash {
+        BlockHash::from_internal_bytes(sha256d(&self.header))
+    }
+
+    pub fn prev_hash(&self) -> BlockHash {
+        BlockHash::from_internal_bytes(self.header[4..36].try_into().expect("32 bytes"))
+    }
+
+    /// The height a version 2 or later block pushes first in its coinbase
+    /// script (BIP 34), if it does.
+    pub fn coinbase_height(&self) -> Option<u32> {
+        let version = i32::from_le_bytes(self.header[0..4].try_into().ok()?);
+        if version < 2 {
+            return None;
+        }
+        let coinbase = &self.transactions[0].raw;
+        let mut reader = Reader {
+            bytes: coinbase,
+            position: 4,
+        };
+        if coinbase[4..].starts_with(&[0x00, 0x01]) {
+            reader.take(2).ok()?;
+        }
+        reader.compact_size().ok()?;
+        reader.take(36).ok()?;
+        let script = reader.var_bytes().ok()?;
+        match *script.first()? {
+            // OP_0 and OP_1..OP_16 push small heights as opcodes
+            0x00 => Some(0),
+            op @ 0x51..=0x60 => Some(u32::from(op - 0x50)),
+            len @ 1..=4 => {
+                let pushed = script.get(1..=usize::from(len))?;
+                let mut height = [0u8; 4];
+                height[..pushed.len()].copy_from_slice(pushed);
+                Some(u32::from_le_bytes(height))
+            }
+            _ => None,
+        }
+    }
+
+    /// The block in the document shape blocks are ingested in, at `height`.
+    /// Total fees are what the coinbase claims beyond the subsidy, since the
+    /// values of the spent outputs are not at hand.
+    pub fn to_document(&self, height: u32) -> Value {
+        let header = &self.header;
+        let le_u32 = |range: std::ops::Range<usize>| {
+            u32::from_le_bytes(header[range].try_into().expect("4 bytes"))
+        };
+        let merkle_root =
+            BlockHash::from_internal_bytes(header[36..68].try_into().expect("32 bytes"));
+        let claimed: u64 = self.transactions[0]
+            .outputs
+            .iter()
+            .map(|(value, _)| value)
+            .sum();
+        let total_fees = (claimed as i64 - stats::block_subsidy(height)).max(0);
+        let stripped_size = 80
+            + (self.size
+                - 80
+                - self
+                    .transactions
+                    .iter()
+                    .map(|tx| tx.raw.len())
+                    .sum::<usize>())
+            + self
+                .transactions
+                .iter()
+                .map(|tx| tx.stripped_size)
+                .sum::<usize>();
+        let weight = self.size + 3 * stripped_size;
+
+        let transactions: Vec<Value> = self
+            .transactions
+            .iter()
+            .map(|tx| {
+                let inputs: Vec<Value> = tx
+                    .inputs
+                    .iter()
+                    .map(|outpoint| match outpoint {
+                        Some((txid, vout)) => json!({"prev_txid": txid, "prev_vout": vout}),
+                        None => json!({"prev_txid": null, "prev_vout": null}),
+                    })
+                    .collect();
+                let outputs: Vec<Value> = tx
+                    .outputs
+                    .iter()
+                    .map(|(value, script)| {
+                        json!({
+                            "value": *value as f64 / SATS_PER_BTC,
+                            "script_pubkey": hex::encode(script),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "txid": tx.txid,
+                    "size": tx.raw.len(),
+                    "vsize": tx.weight().div_ceil(4),
+                    "inputs": inputs,
+                    "outputs": outputs,
+                    "raw": hex::encode(&tx.raw),
+                })
+            })
+            .collect();
+
+        json!({
+            "height": height,
+            "hash": self.hash(),
+            "prev_hash": self.prev_hash(),
+            "merkle_root": merkle_root,
+            "version": i32::from_le_bytes(header[0..4].try_into().expect("4 bytes")),
+            "bits": le_u32(72..76),
+            "nonce": le_u32(76..80),
+            "tx_count": self.transactions.len(),
+            "total_fees": total_fees,
+            "timestamp": le_u32(68..72),
+            "verified": false,
+            "size": self.size,
+            "stripped_size": stripped_size,
+            "weight": weight,
+            "txids": self.transactions.iter().map(|tx| tx.txid).collect::<Vec<_>>(),
+            "transactions": transactions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation;
+
+    /// Bitcoin's genesis block.
+    const GENESIS: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    #[test]
+    fn test_decode_genesis() {
+        let bytes = hex::decode(GENESIS).unwrap();
+        let block = RawBlock::decode(&bytes).unwrap();
+        assert_eq!(
+            block.hash().to_string(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(block.coinbase_height(), None);
+
+        let document = block.to_document(0);
+        assert_eq!(document["tx_count"], 1);
+        assert_eq!(document["size"], 285);
+        assert_eq!(document["weight"], 1140);
+        assert_eq!(document["txids"], json!([document["merkle_root"]]));
+        assert_eq!(document["transactions"][0]["outputs"][0]["value"], 50.0);
+        assert_eq!(document["total_fees"], 0);
+        assert_eq!(validation::check_proof_of_work(&document), Ok(()));
+        assert_eq!(validation::check_merkle_root(&document), Ok(()));
+
+        assert!(RawBlock::decode(&bytes[..200])
+            .unwrap_err()
+            .contains("truncated"));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(RawBlock::decode(&trailing).unwrap_err().contains("left"));
+    }
+
+    #[test]
+    fn test_decode_segwit_transaction() {
+        // A BIP 143 example: one segwit input among two
+        let raw = hex::decode("01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000").unwrap();
+        let mut reader = Reader {
+            bytes: &raw,
+            position: 0,
+        };
+        let tx = read_transaction(&mut reader).unwrap();
+        assert_eq!(reader.position, raw.len());
+        assert_eq!(
+            tx.txid.to_string(),
+            "e8151a2af31c368a35053ddd4bdb285a8595c769a3ad83e0fa02314a602d4609"
+        );
+        assert_eq!(tx.inputs.len(), 2);
+        assert_eq!(tx.outputs[0].0, 112_340_000);
+        assert!(tx.stripped_size < tx.raw.len());
+
+        assert_eq!(decode_transaction(&raw), Ok(tx.txid));
+        assert!(decode_transaction(&raw[..raw.len() - 1])
+            .unwrap_err()
+            .contains("truncated"));
+        // The genesis coinbase, after the header and transaction count
+        let genesis = hex::decode(GENESIS).unwrap();
+        assert!(decode_transaction(&genesis[81..])
+            .unwrap_err()
+            .contains("coinbase"));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{
+    config::{self, ConfigError, Lookup},
+    load::{ConcurrencyConfig, ConcurrencyLimit},
+    logging::LogConfig,
+    maintenance::{Maintenance, MaintenanceConfig, SharedMaintenance},
+    middleware::{cors_layer, CorsConfig, SharedCors},
+    model::ReloadResponse,
+    rate_limit::{RateLimitConfig, RateLimiter},
+    tls::CertificateReloader,
+};
+use once_cell::sync::OnceCell;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+/// The live objects behind the settings that can change without a restart:
+/// rate limits, concurrency limits, CORS origins, the maintenance mode, the
+/// log level and the TLS certificate. Everything else is read once at
+/// startup.
+pub struct Reloader {
+    pub api_limiter: Arc<RateLimiter>,
+    pub proofs_limiter: Arc<RateLimiter>,
+    pub admin_limiter: Arc<RateLimiter>,
+    pub global_limit: ConcurrencyLimit,
+    pub proof_download_limit: ConcurrencyLimit,
+    pub cors: SharedCors,
+    pub maintenance: SharedMaintenance,
+    /// Set once the server is listening with TLS.
+    pub certificate: OnceCell<CertificateReloader>,
+}
+
+impl Reloader {
+    pub fn from_env() -> Self {
+        Self::from_lookup(&config::env)
+    }
+
+    pub fn from_lookup(lookup: Lookup) -> Self {
+        let rate_limits = RateLimitConfig::from_lookup(lookup);
+        let concurrency = ConcurrencyConfig::from_lookup(lookup);
+        Self {
+            api_limiter: rate_limits.limiter("api", rate_limits.api),
+            proofs_limiter: rate_limits.limiter("proofs", rate_limits.proofs),
+            admin_limiter: rate_limits.limiter("admin", rate_limits.admin),
+            global_limit: concurrency.global_limit(),
+            proof_download_limit: concurrency.proof_download_limit(),
+            cors: Arc::new(RwLock::new(cors_layer(&CorsConfig::from_lookup(lookup)))),
+            maintenance: Arc::new(Maintenance::new(&MaintenanceConfig::from_lookup(lookup))),
+            certificate: OnceCell::new(),
+        }
+    }
+
+    /// Puts the reloadable settings from `lookup` into effect and returns
+    /// the groups that were applied. Requests already running, such as proof
+    /// downloads, carry on under the limits they started with.
+    pub fn apply(&self, lookup: Lookup) -> Vec<String> {
+        let rate_limits = RateLimitConfig::from_lookup(lookup);
+        self.api_limiter.set_limit(rate_limits.api);
+        self.proofs_limiter.set_limit(rate_limits.proofs);
+        self.admin_limiter.set_limit(rate_limits.admin);
+
+        let concurrency = ConcurrencyConfig::from_lookup(lookup);
+        self.global_limit.resize(concurrency.max_requests);
+        self.proof_download_limit
+            .resize(concurrency.max_proof_downloads);
+
+        *self.cors.write().expect("CORS lock poisoned") =
+            cors_layer(&CorsConfig::from_lookup(lookup));
+
+        // A message set through the admin endpoint stays while the mode does
+        let maintenance = MaintenanceConfig::from_lookup(lookup);
+        let current = self.maintenance.status();
+        let message = (current.mode == maintenance.mode)
+            .then_some(current.message)
+            .flatten();
+        self.maintenance
+            .set(maintenance.mode, maintenance.retry_after_secs, message);
+
+        let mut reloaded = vec![
+            "rate_limits".to_string(),
+            "concurrency".to_string(),
+            "cors".to_string(),
+            "maintenance".to_string(),
+        ];
+        match LogConfig::from_lookup(lookup).reload_filter() {
+            Ok(true) => reloaded.push("log_level".to_string()),
+            Ok(false) => {}
+            Err(e) => warn!(error = %e, "Failed to reload the log level"),
+        }
+        // The certificate files are re-read from the paths given at startup
+        if let Some(certificate) = self.certificate.get() {
+            match certificate.reload() {
+                Ok(()) => reloaded.push("tls".to_string()),
+                Err(e) => warn!(error = %e, "Failed to reload TLS certificate"),
+            }
+        }
+        reloaded
+    }
+
+    /// Re-reads the configuration file and applies it. Nothing changes if
+    /// the file no longer validates.
+    pub fn reload(&self) -> Result<ReloadResponse, ConfigError> {
+        let settings = config::reread()?;
+        let reloaded = self.apply(&|name| settings.get(name).cloned());
+        Ok(ReloadResponse {
+            config_file: config::config_path().map(|path| path.display().to_string()),
+            reloaded,
+        })
+    }
+}
+
+/// Reloads the configuration whenever the process receives `SIGHUP`.
+#[cfg(unix)]
+pub fn spawn_sighup_handler(reloader: Arc<Reloader>) -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match reloader.reload() {
+                Ok(response) => info!(reloaded = ?response.reloaded, "Configuration reloaded"),
+                Err(e) => {
+                    warn!(error = %e, "Configuration reload failed; keeping current settings")
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_handler(_reloader: Arc<Reloader>) -> std::io::Result<()> {
+    Ok(())
+}
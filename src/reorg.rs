@@ -0,0 +1,87 @@
+use crate::database::Database;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::{sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+static TOMBSTONE_CONFIG: Lazy<TombstoneConfig> = Lazy::new(TombstoneConfig::from_env);
+
+#[derive(Debug, Clone)]
+pub struct TombstoneConfig {
+    /// How long tombstones are kept before being purged.
+    pub retention_days: i64,
+    /// How long orphaned blocks stay served; kept indefinitely when unset.
+    pub orphan_retention_days: Option<i64>,
+    /// How often the purge runs.
+    pub gc_interval_secs: u64,
+}
+
+impl Default for TombstoneConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            orphan_retention_days: None,
+            gc_interval_secs: 3600,
+        }
+    }
+}
+
+impl TombstoneConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            retention_days: std::env::var("TOMBSTONE_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.retention_days),
+            orphan_retention_days: std::env::var("ORPHAN_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .or(defaults.orphan_retention_days),
+            gc_interval_secs: std::env::var("TOMBSTONE_GC_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.gc_interval_secs),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &TOMBSTONE_CONFIG
+    }
+
+    /// Unix timestamp before which tombstones are eligible for purging.
+    pub fn cutoff(&self) -> i64 {
+        Utc::now().timestamp() - self.retention_days * 86_400
+    }
+
+    /// Unix timestamp before which orphaned blocks are eligible for purging.
+    pub fn orphan_cutoff(&self) -> Option<i64> {
+        self.orphan_retention_days
+            .map(|days| Utc::now().timestamp() - days * 86_400)
+    }
+}
+
+/// Periodically purges tombstones, and orphaned blocks, older than their
+/// retention windows.
+pub fn spawn_tombstone_gc(database: Arc<Database>) {
+    let config = TombstoneConfig::global();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.gc_interval_secs));
+        loop {
+            interval.tick().await;
+            match database.purge_tombstones(config.cutoff()).await {
+                Ok(0) => {}
+                Ok(purged) => info!(purged, "Purged expired block tombstones"),
+                Err(e) => warn!(error = %e, "Failed to purge block tombstones"),
+            }
+            let Some(cutoff) = config.orphan_cutoff() else {
+                continue;
+            };
+            match database.purge_orphans(cutoff).await {
+                Ok(0) => {}
+                Ok(purged) => info!(purged, "Purged expired orphaned blocks"),
+                Err(e) => warn!(error = %e, "Failed to purge orphaned blocks"),
+            }
+        }
+    });
+}
@@ -0,0 +1,473 @@
+//! A read-only subset of Bitcoin Core's JSON-RPC at `POST /v1/rpc`, so tools
+//! written against `bitcoind` can read indexed blocks and transactions
+//! without changes. Answers follow Core's shapes and error codes; fields the
+//! index doesn't hold are left out rather than made up.
+
+use crate::{
+    error::AppError,
+    hash::{BlockHash, BlockId, Txid},
+    model::BlockDetail,
+    store::BlockStore,
+};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+// Error codes from Bitcoin Core's `rpc/protocol.h`
+const RPC_MISC_ERROR: i64 = -1;
+const RPC_TYPE_ERROR: i64 = -3;
+const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+const RPC_INVALID_PARAMETER: i64 = -8;
+const RPC_INVALID_REQUEST: i64 = -32600;
+const RPC_METHOD_NOT_FOUND: i64 = -32601;
+const RPC_PARSE_ERROR: i64 = -32700;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The HTTP status Core answers a JSON-RPC 1.0 error with.
+    fn legacy_status(&self) -> StatusCode {
+        match self.code {
+            RPC_INVALID_REQUEST => StatusCode::BAD_REQUEST,
+            RPC_METHOD_NOT_FOUND => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(error: AppError) -> Self {
+        match error {
+            AppError::BlockNotFound(_) => Self::new(RPC_INVALID_ADDRESS_OR_KEY, "Block not found"),
+            AppError::TransactionNotFound(_) => Self::new(
+                RPC_INVALID_ADDRESS_OR_KEY,
+                "No such mempool or blockchain transaction",
+            ),
+            AppError::BlockPruned(_) => {
+                Self::new(RPC_MISC_ERROR, "Block not available (pruned data)")
+            }
+            AppError::RawTransactionUnavailable(_) => Self::new(
+                RPC_MISC_ERROR,
+                "Transaction not available (raw bytes not indexed)",
+            ),
+            error => {
+                warn!(error = %error, "JSON-RPC call failed");
+                Self::new(RPC_MISC_ERROR, error.to_string())
+            }
+        }
+    }
+}
+
+type CallResult = std::result::Result<Value, RpcError>;
+
+/// Positional or named parameters of a call; null counts as omitted, as in
+/// Core.
+struct Params<'a>(Option<&'a Value>);
+
+impl<'a> Params<'a> {
+    fn get(&self, index: usize, name: &str) -> Option<&'a Value> {
+        match self.0 {
+            Some(Value::Array(params)) => params.get(index),
+            Some(Value::Object(params)) => params.get(name),
+            _ => None,
+        }
+        .filter(|value| !value.is_null())
+    }
+
+    fn required(&self, index: usize, name: &str) -> std::result::Result<&'a Value, RpcError> {
+        self.get(index, name).ok_or_else(|| {
+            RpcError::new(
+                RPC_INVALID_PARAMETER,
+                format!("Missing required parameter: {name}"),
+            )
+        })
+    }
+
+    fn height(&self, index: usize, name: &str) -> std::result::Result<u32, RpcError> {
+        let value = self.required(index, name)?;
+        let height = value.as_i64().ok_or_else(|| type_error(value, "number"))?;
+        u32::try_from(height)
+            .map_err(|_| RpcError::new(RPC_INVALID_PARAMETER, "Block height out of range"))
+    }
+
+    fn hash<T: std::str::FromStr>(
+        &self,
+        index: usize,
+        name: &str,
+    ) -> std::result::Result<T, RpcError> {
+        let value = self.required(index, name)?;
+        let hex = value.as_str().ok_or_else(|| type_error(value, "string"))?;
+        if hex.len() != 64 {
+            return Err(RpcError::new(
+                RPC_INVALID_PARAMETER,
+                format!(
+                    "{name} must be of length 64 (not {}, for '{hex}')",
+                    hex.len()
+                ),
+            ));
+        }
+        hex.parse().map_err(|_| {
+            RpcError::new(
+                RPC_INVALID_PARAMETER,
+                format!("{name} must be hexadecimal string (not '{hex}')"),
+            )
+        })
+    }
+
+    /// A verbosity level, given as a number or, as older clients do, a bool.
+    fn verbosity(
+        &self,
+        index: usize,
+        name: &str,
+        default: u64,
+    ) -> std::result::Result<u64, RpcError> {
+        match self.get(index, name) {
+            None => Ok(default),
+            Some(Value::Bool(verbose)) => Ok(*verbose as u64),
+            Some(value) => value.as_u64().ok_or_else(|| type_error(value, "number")),
+        }
+    }
+}
+
+fn type_error(value: &Value, expected: &str) -> RpcError {
+    let actual = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    RpcError::new(
+        RPC_TYPE_ERROR,
+        format!("JSON value of type {actual} is not of expected type {expected}"),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/rpc",
+    tag = "rpc",
+    request_body(content = Object, description = "A JSON-RPC request or batch, as sent to `bitcoind`"),
+    responses(
+        (status = 200, description = "The call's result, or its error for JSON-RPC 2.0 requests and batches"),
+        (status = 404, description = "Unknown method, for JSON-RPC 1.0 requests"),
+        (status = 500, description = "The call's error, for JSON-RPC 1.0 requests"),
+    )
+)]
+pub async fn rpc_handler(State(db): State<Arc<dyn BlockStore>>, body: Bytes) -> Response {
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(_) => {
+            let error = RpcError::new(RPC_PARSE_ERROR, "Parse error");
+            return (error.legacy_status(), Json(reply(None, Err(error), None))).into_response();
+        }
+    };
+
+    match request {
+        Value::Array(requests) => {
+            let mut replies = Vec::with_capacity(requests.len());
+            for request in &requests {
+                replies.push(handle(&*db, request).await.1);
+            }
+            Json(Value::Array(replies)).into_response()
+        }
+        request => {
+            let (status, reply) = handle(&*db, &request).await;
+            (status, Json(reply)).into_response()
+        }
+    }
+}
+
+/// Runs one request and builds its reply, with the status Core would send
+/// were it alone.
+async fn handle(db: &dyn BlockStore, request: &Value) -> (StatusCode, Value) {
+    let Some(request) = request.as_object() else {
+        let error = RpcError::new(RPC_INVALID_REQUEST, "Invalid Request object");
+        return (error.legacy_status(), reply(None, Err(error), None));
+    };
+    let version = request.get("jsonrpc").and_then(Value::as_str);
+    let result = match request.get("method").and_then(Value::as_str) {
+        Some(method) => call(db, method, Params(request.get("params"))).await,
+        None => Err(RpcError::new(
+            RPC_INVALID_REQUEST,
+            "Method must be a string",
+        )),
+    };
+    let status = match &result {
+        Err(error) if version != Some("2.0") => error.legacy_status(),
+        _ => StatusCode::OK,
+    };
+    (status, reply(request.get("id"), result, version))
+}
+
+/// A 1.0 reply carries both `result` and `error`, a 2.0 one only the one
+/// that applies.
+fn reply(id: Option<&Value>, result: CallResult, version: Option<&str>) -> Value {
+    let id = id.cloned().unwrap_or(Value::Null);
+    let result = result.map_err(|error| json!({"code": error.code, "message": error.message}));
+    match (version == Some("2.0"), result) {
+        (true, Ok(result)) => json!({"jsonrpc": "2.0", "result": result, "id": id}),
+        (true, Err(error)) => json!({"jsonrpc": "2.0", "error": error, "id": id}),
+        (false, Ok(result)) => json!({"result": result, "error": null, "id": id}),
+        (false, Err(error)) => json!({"result": null, "error": error, "id": id}),
+    }
+}
+
+async fn call(db: &dyn BlockStore, method: &str, params: Params<'_>) -> CallResult {
+    match method {
+        "getblockcount" => Ok(json!(tip(db).await?)),
+        "getbestblockhash" => {
+            let block = db.get_block_by_height(tip(db).await?).await?;
+            Ok(json!(block.summary.hash))
+        }
+        "getblockhash" => {
+            let height = params.height(0, "height")?;
+            match db.get_block_by_height(height).await {
+                Ok(block) => Ok(json!(block.summary.hash)),
+                Err(AppError::BlockNotFound(_)) => Err(RpcError::new(
+                    RPC_INVALID_PARAMETER,
+                    "Block height out of range",
+                )),
+                Err(e) => Err(e.into()),
+            }
+        }
+        "getblockheader" => {
+            let hash: BlockHash = params.hash(0, "blockhash")?;
+            let verbose = params.verbosity(1, "verbose", 1)? > 0;
+            let block = db.get_block_by_hash(&hash).await?;
+            if !verbose {
+                return Ok(json!(hex::encode(block.raw_header()?)));
+            }
+            Ok(Value::Object(header_fields(db, &block).await?))
+        }
+        "getblock" => {
+            let hash: BlockHash = params.hash(0, "blockhash")?;
+            let verbosity = params.verbosity(1, "verbosity", 1)?;
+            let block = db.get_block_by_hash(&hash).await?;
+            if block.pruned {
+                return Err(AppError::BlockPruned(hash.to_string()).into());
+            }
+            match verbosity {
+                0 => Ok(json!(hex::encode(raw_block(db, &block).await?))),
+                1 => {
+                    let mut fields = header_fields(db, &block).await?;
+                    for (key, value) in [
+                        ("strippedsize", block.stripped_size),
+                        ("size", block.size),
+                        ("weight", block.weight),
+                    ] {
+                        if let Some(value) = value {
+                            fields.insert(key.to_string(), json!(value));
+                        }
+                    }
+                    fields.insert("tx".to_string(), json!(block.txids));
+                    Ok(Value::Object(fields))
+                }
+                _ => Err(RpcError::new(
+                    RPC_INVALID_PARAMETER,
+                    "Verbosity above 1 is not supported: decoded transactions are not indexed",
+                )),
+            }
+        }
+        "getrawtransaction" => {
+            let txid: Txid = params.hash(0, "txid")?;
+            let verbose = params.verbosity(1, "verbose", 0)? > 0;
+            let status = db.get_transaction_status(&txid).await?;
+            if params.get(2, "blockhash").is_some() {
+                let hash: BlockHash = params.hash(2, "blockhash")?;
+                if !db.block_exists_by_identifier(&BlockId::Hash(hash)).await? {
+                    return Err(RpcError::new(
+                        RPC_INVALID_ADDRESS_OR_KEY,
+                        "Block hash not found",
+                    ));
+                }
+                if status.block_hash != Some(hash) {
+                    return Err(RpcError::new(
+                        RPC_INVALID_ADDRESS_OR_KEY,
+                        "No such transaction found in the provided block",
+                    ));
+                }
+            }
+            let raw = db.get_raw_transaction(&txid).await?;
+            if !verbose {
+                return Ok(json!(hex::encode(raw)));
+            }
+            let mut fields = Map::new();
+            fields.insert("txid".to_string(), json!(txid));
+            fields.insert("hex".to_string(), json!(hex::encode(&raw)));
+            if let Ok(detail) = db.get_transaction_detail(&txid).await {
+                for (key, value) in [("size", detail.size), ("vsize", detail.vsize)] {
+                    if let Some(value) = value {
+                        fields.insert(key.to_string(), json!(value));
+                    }
+                }
+            }
+            if let (Some(height), Some(hash)) = (status.block_height, status.block_hash) {
+                let block = db.get_block_by_height(height).await?;
+                fields.insert("blockhash".to_string(), json!(hash));
+                fields.insert("confirmations".to_string(), json!(status.confirmations));
+                fields.insert("time".to_string(), json!(block.summary.timestamp));
+                fields.insert("blocktime".to_string(), json!(block.summary.timestamp));
+            }
+            Ok(Value::Object(fields))
+        }
+        _ => Err(RpcError::new(RPC_METHOD_NOT_FOUND, "Method not found")),
+    }
+}
+
+async fn tip(db: &dyn BlockStore) -> std::result::Result<u32, RpcError> {
+    db.tip_height()
+        .await?
+        .ok_or_else(|| RpcError::new(RPC_MISC_ERROR, "No blocks indexed yet"))
+}
+
+/// The fields `getblockheader` reports, which `getblock` starts from.
+async fn header_fields(
+    db: &dyn BlockStore,
+    block: &BlockDetail,
+) -> std::result::Result<Map<String, Value>, RpcError> {
+    let height = block.summary.height;
+    // As bitcoind reports blocks off the active chain
+    let confirmations = match block.orphaned {
+        true => -1,
+        false => i64::from(tip(db).await?.saturating_sub(height) + 1),
+    };
+    let mut fields = Map::new();
+    fields.insert("hash".to_string(), json!(block.summary.hash));
+    fields.insert("confirmations".to_string(), json!(confirmations));
+    fields.insert("height".to_string(), json!(height));
+    fields.insert("version".to_string(), json!(block.version));
+    fields.insert(
+        "versionHex".to_string(),
+        json!(format!("{:08x}", block.version)),
+    );
+    fields.insert("merkleroot".to_string(), json!(block.merkle_root));
+    fields.insert("time".to_string(), json!(block.summary.timestamp));
+    fields.insert("nonce".to_string(), json!(block.nonce));
+    fields.insert("bits".to_string(), json!(format!("{:08x}", block.bits)));
+    fields.insert("difficulty".to_string(), json!(block.difficulty));
+    if let Some(chainwork) = &block.chainwork {
+        fields.insert("chainwork".to_string(), json!(chainwork));
+    }
+    fields.insert("nTx".to_string(), json!(block.summary.tx_count));
+    if height > 0 {
+        fields.insert("previousblockhash".to_string(), json!(block.prev_hash));
+    }
+    match db.get_block_by_height(height + 1).await {
+        Ok(next) => {
+            fields.insert("nextblockhash".to_string(), json!(next.summary.hash));
+        }
+        Err(AppError::BlockNotFound(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(fields)
+}
+
+/// The serialized block, assembled from its header and every transaction's
+/// raw bytes; unavailable when any of them was not ingested.
+async fn raw_block(
+    db: &dyn BlockStore,
+    block: &BlockDetail,
+) -> std::result::Result<Vec<u8>, RpcError> {
+    let unavailable = || {
+        RpcError::new(
+            RPC_MISC_ERROR,
+            "Block not available (raw transactions not indexed)",
+        )
+    };
+    // As for `size`, the block is the transactions indexed under it
+    if block.txids.is_empty() {
+        return Err(unavailable());
+    }
+    let mut raw = block.raw_header()?.to_vec();
+    write_compact_size(&mut raw, block.txids.len() as u64);
+    for txid in &block.txids {
+        match db.get_raw_transaction(txid).await {
+            Ok(tx) => raw.extend_from_slice(&tx),
+            Err(AppError::RawTransactionUnavailable(_) | AppError::TransactionNotFound(_)) => {
+                return Err(unavailable())
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(raw)
+}
+
+fn write_compact_size(out: &mut Vec<u8>, n: u64) {
+    match n {
+        0..=0xfc => out.push(n as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xfe);
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xff);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params() {
+        let positional = json!([869123, null]);
+        let params = Params(Some(&positional));
+        assert_eq!(params.height(0, "height").unwrap(), 869123);
+        assert_eq!(params.verbosity(1, "verbose", 1).unwrap(), 1);
+
+        let named = json!({"height": "869123", "verbose": false});
+        let params = Params(Some(&named));
+        assert_eq!(params.height(0, "height").unwrap_err().code, RPC_TYPE_ERROR);
+        assert_eq!(params.verbosity(1, "verbose", 1).unwrap(), 0);
+        assert_eq!(
+            Params(None).height(0, "height").unwrap_err().code,
+            RPC_INVALID_PARAMETER
+        );
+        assert_eq!(
+            Params(Some(&json!(["abc"])))
+                .hash::<BlockHash>(0, "blockhash")
+                .unwrap_err()
+                .message,
+            "blockhash must be of length 64 (not 3, for 'abc')"
+        );
+    }
+
+    #[test]
+    fn test_compact_size() {
+        for (n, expected) in [
+            (0xfc, vec![0xfc]),
+            (0xfd, vec![0xfd, 0xfd, 0x00]),
+            (0x1_0000, vec![0xfe, 0x00, 0x00, 0x01, 0x00]),
+        ] {
+            let mut out = Vec::new();
+            write_compact_size(&mut out, n);
+            assert_eq!(out, expected);
+        }
+    }
+}
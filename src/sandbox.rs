@@ -0,0 +1,359 @@
+use crate::{
+    database::Database,
+    error::Result,
+    hash::{sha256d, BlockHash, Txid},
+    jobs::{self, Prover},
+    model::{BlockDetail, BlockSummary, ProofJob},
+    storage::ProofStorage,
+};
+use async_trait::async_trait;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    /// Seconds between simulated blocks.
+    pub block_interval_secs: u64,
+    /// Scratch directory for dummy proofs, kept apart from real proof storage.
+    pub proof_dir: PathBuf,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            block_interval_secs: 10,
+            proof_dir: std::env::temp_dir().join("raito-sandbox-proofs"),
+        }
+    }
+}
+
+impl SandboxConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            block_interval_secs: std::env::var("SANDBOX_BLOCK_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.block_interval_secs),
+            proof_dir: std::env::var("SANDBOX_PROOF_DIR")
+                .map(PathBuf::from)
+                .unwrap_or(defaults.proof_dir),
+        }
+    }
+}
+
+/// Builds the block following `tip`. Everything is derived from the tip, so
+/// the simulated chain is identical on every run. The block has a single
+/// transaction and its hash is the real double SHA-256 of its header.
+pub fn next_block(tip: &BlockDetail) -> serde_json::Value {
+    let height = tip.summary.height + 1;
+    let txid = Txid::from_internal_bytes(sha256d(
+        format!("raito-sandbox-coinbase-{height}").as_bytes(),
+    ));
+
+    let mut block = BlockDetail {
+        summary: BlockSummary {
+            height,
+            hash: BlockHash::from_byte_array([0; 32]),
+            tx_count: 1,
+            total_fees: 0,
+            timestamp: tip.summary.timestamp + 600,
+            // Verified once its proof job completes
+            verified: false,
+        },
+        prev_hash: tip.summary.hash,
+        // A single-transaction block's merkle root is its txid
+        merkle_root: txid.to_string(),
+        witness_merkle_root: None,
+        witness_commitment: None,
+        version: tip.version,
+        bits: tip.bits,
+        nonce: height,
+        size: None,
+        stripped_size: None,
+        weight: None,
+        difficulty: tip.difficulty,
+        chainwork: None,
+        txids: vec![txid],
+        wtxids: Vec::new(),
+        coinbase: None,
+        proof_url: String::new(),
+        pruned: false,
+        orphaned: false,
+    };
+    if let Ok(header) = block.raw_header() {
+        block.summary.hash = BlockHash::from_internal_bytes(sha256d(&header));
+    }
+
+    json!({
+        "height": height,
+        "hash": block.summary.hash,
+        "prev_hash": block.prev_hash,
+        "merkle_root": block.merkle_root,
+        "version": block.version,
+        "bits": block.bits,
+        "nonce": block.nonce,
+        "tx_count": block.summary.tx_count,
+        "total_fees": block.summary.total_fees,
+        "timestamp": block.summary.timestamp,
+        "verified": block.summary.verified,
+        "txids": block.txids,
+    })
+}
+
+/// Small deterministic stand-in for a STARK proof of `hash`.
+pub fn dummy_proof(height: u32, hash: &str) -> Vec<u8> {
+    let proof = json!({
+        "sandbox": true,
+        "block_height": height,
+        "block_hash": hash,
+        "proof": hex::encode(Sha256::digest(format!("raito-sandbox-proof-{hash}"))),
+    });
+    serde_json::to_vec_pretty(&proof).expect("proof JSON serializes")
+}
+
+/// Answers every proof job at once with [`dummy_proof`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DummyProver;
+
+#[async_trait]
+impl Prover for DummyProver {
+    async fn prove(&self, block: &BlockDetail) -> std::result::Result<Vec<u8>, String> {
+        Ok(dummy_proof(
+            block.summary.height,
+            &block.summary.hash.to_string(),
+        ))
+    }
+}
+
+/// Mines one simulated block on top of the current tip and runs its proof
+/// job with the [`DummyProver`], so it goes through the same job lifecycle,
+/// events and webhooks as a real one. Returns the job.
+pub async fn advance(database: &Database, storage: &dyn ProofStorage) -> Result<ProofJob> {
+    let tip_height = database.tip_height().await?.unwrap_or_default();
+    let tip = database.get_block_by_height(tip_height).await?;
+    database.insert_block(&next_block(&tip)).await?;
+
+    jobs::run(database, storage, &DummyProver, tip_height + 1).await
+}
+
+/// Extends the chain with a simulated block every `block_interval_secs`.
+pub fn spawn_block_generator(
+    database: Arc<Database>,
+    storage: &'static dyn ProofStorage,
+    config: &SandboxConfig,
+) {
+    let interval_secs = config.block_interval_secs;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        // The first tick fires immediately; let clients see the seeded chain first
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match advance(&database, storage).await {
+                Ok(job) => info!(
+                    height = job.height,
+                    job = job.id,
+                    "Sandbox mined and proved block"
+                ),
+                Err(e) => warn!(error = %e, "Sandbox block generation failed"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::DatabaseConfig,
+        events::{self, Event},
+        model::{CreateWebhookRequest, JobStatus, WebhookEvent},
+        storage::{FsProofStorage, ProofReader},
+        webhooks,
+    };
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    #[tokio::test]
+    async fn test_advance_is_deterministic_and_proved() {
+        let proofs = tempfile::tempdir().unwrap();
+        let storage = FsProofStorage::new(proofs.path());
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        database.seed_data().await.unwrap();
+
+        let tip = database.get_block_by_height(869123).await.unwrap();
+        let expected = next_block(&tip);
+        assert_eq!(next_block(&tip), expected);
+
+        let webhook = webhooks::create(
+            &database,
+            &CreateWebhookRequest {
+                url: "https://example.com/hook".to_string(),
+                secret: None,
+                events: vec![WebhookEvent::ProofVerified],
+                active: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut events = events::subscribe();
+        let job = advance(&database, &storage).await.unwrap();
+        let height = job.height;
+        assert_eq!(height, 869124);
+        assert_eq!(job.status, JobStatus::Completed);
+        assert!(job.started_at.is_some() && job.finished_at.is_some());
+        assert_eq!(database.get_proof_job(job.id).await.unwrap(), job);
+
+        let block = database.get_block_by_height(height).await.unwrap();
+        assert_eq!(block.summary.hash.to_string(), expected["hash"]);
+        assert_eq!(block.prev_hash, tip.summary.hash);
+        let header = block.raw_header().unwrap();
+        assert_eq!(
+            BlockHash::from_internal_bytes(sha256d(&header)),
+            block.summary.hash
+        );
+
+        assert!(block.summary.verified);
+        assert!(database.proof_file_exists(height).await.unwrap());
+        assert_eq!(
+            storage.read(height).await.unwrap().unwrap(),
+            dummy_proof(height, &block.summary.hash.to_string())
+        );
+
+        // Subscribers hear of the block, then of its proof
+        let mut published = Vec::new();
+        loop {
+            match events.try_recv() {
+                Ok(event) => published.push(event.event),
+                // Other tests publish too and may have overrun the buffer
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        let indexed = Event::BlockIndexed {
+            height,
+            hash: block.summary.hash,
+        };
+        let indexed_at = published.iter().position(|e| *e == indexed);
+        let proved_at = published
+            .iter()
+            .rposition(|e| *e == Event::ProofGenerated { height });
+        let verified_at = published
+            .iter()
+            .rposition(|e| *e == Event::ProofVerified { height });
+        assert!(indexed_at.is_some() && indexed_at < proved_at && proved_at < verified_at);
+
+        // The verification queued its delivery as it was committed
+        let deliveries = database
+            .list_webhook_deliveries(webhook.webhook.id, None, 10, None)
+            .await
+            .unwrap()
+            .deliveries;
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].event, WebhookEvent::ProofVerified);
+        assert_eq!(deliveries[0].payload["height"], height);
+    }
+
+    #[tokio::test]
+    async fn test_job_fails_when_stored_proof_does_not_verify() {
+        // Accepts proofs and loses them, so nothing can be read back
+        struct LosingStorage;
+
+        #[async_trait]
+        impl ProofStorage for LosingStorage {
+            fn describe(&self) -> String {
+                "nowhere".to_string()
+            }
+
+            async fn list(&self) -> std::io::Result<Vec<u32>> {
+                Ok(Vec::new())
+            }
+
+            async fn read(&self, _height: u32) -> std::io::Result<Option<Vec<u8>>> {
+                Ok(None)
+            }
+
+            async fn open(&self, _height: u32) -> std::io::Result<Option<ProofReader>> {
+                Ok(None)
+            }
+
+            async fn write(&self, _height: u32, _data: &[u8]) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        database.seed_data().await.unwrap();
+        let tip = database.get_block_by_height(869123).await.unwrap();
+        database.insert_block(&next_block(&tip)).await.unwrap();
+
+        let job = jobs::run(&database, &LosingStorage, &DummyProver, 869124)
+            .await
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert!(job.error.unwrap().contains("869124"));
+        let block = database.get_block_by_height(869124).await.unwrap();
+        assert!(!block.summary.verified);
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_queues_job_failed_webhook() {
+        struct FailingProver;
+
+        #[async_trait]
+        impl Prover for FailingProver {
+            async fn prove(&self, _block: &BlockDetail) -> std::result::Result<Vec<u8>, String> {
+                Err("prover crashed".to_string())
+            }
+        }
+
+        let proofs = tempfile::tempdir().unwrap();
+        let storage = FsProofStorage::new(proofs.path());
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        database.seed_data().await.unwrap();
+        let tip = database.get_block_by_height(869123).await.unwrap();
+        database.insert_block(&next_block(&tip)).await.unwrap();
+        let webhook = webhooks::create(
+            &database,
+            &CreateWebhookRequest {
+                url: "https://example.com/hook".to_string(),
+                secret: None,
+                events: vec![WebhookEvent::JobFailed],
+                active: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut events = events::subscribe();
+        let job = jobs::run(&database, &storage, &FailingProver, 869124)
+            .await
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.as_deref(), Some("prover crashed"));
+        assert!(!database.proof_file_exists(869124).await.unwrap());
+        let block = database.get_block_by_height(869124).await.unwrap();
+        assert!(!block.summary.verified);
+
+        let failed = Event::JobFailed { height: 869124 };
+        let mut heard = false;
+        loop {
+            match events.try_recv() {
+                Ok(event) => heard |= event.event == failed,
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        assert!(heard);
+        let deliveries = database
+            .list_webhook_deliveries(webhook.webhook.id, None, 10, None)
+            .await
+            .unwrap()
+            .deliveries;
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].event, WebhookEvent::JobFailed);
+    }
+}
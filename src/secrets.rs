@@ -0,0 +1,223 @@
+use crate::config::{ConfigError, Lookup};
+use serde_json::Value;
+use std::{collections::BTreeMap, time::Duration};
+
+/// Suffix of the variable naming a file to read a setting from, as in
+/// `DATABASE_URL_FILE` for `DATABASE_URL`.
+pub const FILE_SUFFIX: &str = "_FILE";
+
+/// Reads the `<NAME>_FILE` variant of each of `names` that `lookup` has,
+/// for secrets mounted as files (Docker and Kubernetes secrets). A trailing
+/// newline is dropped. `lookup` should only see settings that outrank a
+/// secret file, so that giving both is reported.
+pub fn read_files<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+    lookup: Lookup,
+) -> Result<BTreeMap<String, String>, ConfigError> {
+    let mut values = BTreeMap::new();
+    let mut errors = Vec::new();
+    for name in names {
+        let file_var = format!("{name}{FILE_SUFFIX}");
+        let Some(path) = lookup(&file_var).filter(|path| !path.is_empty()) else {
+            continue;
+        };
+        if lookup(name).is_some() {
+            errors.push(format!("set {name} or {file_var}, not both"));
+            continue;
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let value = contents.strip_suffix('\n').unwrap_or(&contents);
+                let value = value.strip_suffix('\r').unwrap_or(value);
+                values.insert(name.to_string(), value.to_string());
+            }
+            Err(e) => errors.push(format!("{file_var}: cannot read {path}: {e}")),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(ConfigError::Invalid(errors))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VaultConfig {
+    /// Vault server, such as `https://vault.internal:8200`; off when unset.
+    pub addr: Option<String>,
+    pub token: Option<String>,
+    /// Secret to read, as in the API path after `/v1/`: `secret/data/raito`
+    /// for a KV v2 mount named `secret`.
+    pub secret_path: Option<String>,
+}
+
+impl VaultConfig {
+    pub fn from_lookup(lookup: Lookup) -> Self {
+        let value = |name: &str| lookup(name).filter(|v| !v.is_empty());
+        Self {
+            addr: value("VAULT_ADDR"),
+            token: value("VAULT_TOKEN"),
+            secret_path: value("VAULT_SECRET_PATH"),
+        }
+    }
+}
+
+/// Reads the settings stored in the Vault secret, keyed by setting name
+/// (`ADMIN_TOKEN`, `ENCRYPTION_KEYS`, ...). Returns nothing when Vault is
+/// not configured. KV v1 and v2 mounts are both understood.
+pub async fn fetch_vault(config: &VaultConfig) -> Result<BTreeMap<String, String>, ConfigError> {
+    let (Some(addr), Some(secret_path)) = (&config.addr, &config.secret_path) else {
+        return Ok(BTreeMap::new());
+    };
+    let vault_error = |message: String| ConfigError::Vault(message);
+    let url = format!(
+        "{}/v1/{}",
+        addr.trim_end_matches('/'),
+        secret_path.trim_start_matches('/')
+    );
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| vault_error(e.to_string()))?;
+    let mut request = client.get(&url);
+    if let Some(token) = &config.token {
+        request = request.header("X-Vault-Token", token);
+    }
+    let response = request
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| vault_error(format!("{url}: {e}")))?;
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| vault_error(format!("{url}: {e}")))?;
+
+    // KV v2 nests the values under `data.data`, next to `data.metadata`
+    let data = match &body["data"] {
+        Value::Object(data) if data.contains_key("metadata") => &body["data"]["data"],
+        data => data,
+    };
+    let Value::Object(entries) = data else {
+        return Err(vault_error(format!("{url}: no secret data")));
+    };
+
+    let mut values = BTreeMap::new();
+    let mut errors = Vec::new();
+    for (name, value) in entries {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => {
+                errors.push(format!("`{name}`: expected a plain value"));
+                continue;
+            }
+        };
+        if !crate::config::is_setting(name) {
+            errors.push(format!("`{name}`: unknown setting"));
+            continue;
+        }
+        values.insert(name.clone(), value);
+    }
+
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(vault_error(format!("{url}: {}", errors.join(", "))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, routing::get, Json, Router};
+    use serde_json::json;
+
+    #[test]
+    fn test_read_secret_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("admin_token");
+        std::fs::write(&path, "s3cret\n").unwrap();
+        let path = path.display().to_string();
+
+        let vars = BTreeMap::from([("ADMIN_TOKEN_FILE", path.as_str())]);
+        let values = read_files(["ADMIN_TOKEN", "DATABASE_URL"], &|name| {
+            vars.get(name).map(|v| v.to_string())
+        })
+        .unwrap();
+        assert_eq!(
+            values,
+            BTreeMap::from([("ADMIN_TOKEN".to_string(), "s3cret".to_string())])
+        );
+
+        let vars = BTreeMap::from([
+            ("ADMIN_TOKEN", "plain"),
+            ("ADMIN_TOKEN_FILE", path.as_str()),
+            ("DATABASE_URL_FILE", "/nonexistent/raito"),
+        ]);
+        let Err(ConfigError::Invalid(errors)) =
+            read_files(["ADMIN_TOKEN", "DATABASE_URL"], &|name| {
+                vars.get(name).map(|v| v.to_string())
+            })
+        else {
+            panic!("expected errors");
+        };
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0], "set ADMIN_TOKEN or ADMIN_TOKEN_FILE, not both");
+        assert!(errors[1].starts_with("DATABASE_URL_FILE: cannot read /nonexistent/raito"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_vault_secrets() {
+        let app = Router::new().route(
+            "/v1/secret/data/raito",
+            get(|headers: HeaderMap| async move {
+                if headers
+                    .get("x-vault-token")
+                    .is_none_or(|token| token != "root")
+                {
+                    return Json(json!({ "errors": ["permission denied"] }));
+                }
+                Json(json!({
+                    "data": {
+                        "data": { "ADMIN_TOKEN": "from-vault", "PORT": 9090 },
+                        "metadata": { "version": 3 }
+                    }
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = VaultConfig {
+            addr: Some(format!("http://{addr}/")),
+            token: Some("root".to_string()),
+            secret_path: Some("secret/data/raito".to_string()),
+        };
+        let values = fetch_vault(&config).await.unwrap();
+        assert_eq!(values["ADMIN_TOKEN"], "from-vault");
+        assert_eq!(values["PORT"], "9090");
+
+        let denied = VaultConfig {
+            token: Some("wrong".to_string()),
+            ..config.clone()
+        };
+        assert!(matches!(
+            fetch_vault(&denied).await,
+            Err(ConfigError::Vault(_))
+        ));
+        let missing = VaultConfig {
+            secret_path: Some("secret/data/other".to_string()),
+            ..config
+        };
+        assert!(fetch_vault(&missing).await.is_err());
+        assert!(fetch_vault(&VaultConfig::default())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    model::LatencyPercentiles,
+    store::{BlockStore, ProofBacklog},
+    telemetry::Metrics,
+};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::{sync::Arc, time::Duration};
+use tracing::warn;
+
+static PROOF_SLA_CONFIG: Lazy<ProofSlaConfig> = Lazy::new(ProofSlaConfig::from_env);
+
+#[derive(Debug, Clone)]
+pub struct ProofSlaConfig {
+    /// Maximum time between ingesting a block and its proof becoming available.
+    pub target_secs: i64,
+    /// How often the proving backlog gauges are refreshed.
+    pub backlog_interval_secs: u64,
+}
+
+impl Default for ProofSlaConfig {
+    fn default() -> Self {
+        Self {
+            target_secs: 3600,
+            backlog_interval_secs: 30,
+        }
+    }
+}
+
+impl ProofSlaConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            target_secs: std::env::var("PROOF_SLA_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.target_secs),
+            backlog_interval_secs: std::env::var("PROOF_BACKLOG_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.backlog_interval_secs),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &PROOF_SLA_CONFIG
+    }
+
+    /// Records how long `milestone` took after ingestion. A proof that
+    /// became available later than the SLA is counted and logged as a breach.
+    pub fn observe(&self, milestone: ProofMilestone) {
+        let metrics = Metrics::global();
+        match milestone {
+            ProofMilestone::Proved {
+                height,
+                generated_secs,
+                available_secs,
+            } => {
+                metrics.observe_proof_generation_latency(generated_secs);
+                metrics.observe_proof_latency(available_secs);
+                if available_secs > self.target_secs {
+                    metrics.record_proof_sla_breach();
+                    warn!(
+                        height,
+                        latency_secs = available_secs,
+                        sla_secs = self.target_secs,
+                        "Proof SLA exceeded"
+                    );
+                }
+            }
+            ProofMilestone::Verified { secs, .. } => {
+                metrics.observe_proof_verification_latency(secs);
+            }
+        }
+    }
+}
+
+/// A step of a block's way to a verified proof, with its latency in seconds
+/// since the block was ingested. Stores report these once the write that
+/// reached them has committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofMilestone {
+    /// A proof was registered for the block.
+    Proved {
+        height: u32,
+        /// Until the prover finished, by the proof's `generated_at`.
+        generated_secs: i64,
+        /// Until the proof was servable.
+        available_secs: i64,
+    },
+    /// The block was marked verified.
+    Verified { height: u32, secs: i64 },
+}
+
+/// Periodically publishes how many blocks wait for a proof and how long the
+/// oldest has waited, so alerts fire while the prover falls behind rather
+/// than only once a late proof lands.
+pub fn spawn_backlog_monitor(store: Arc<dyn BlockStore>, config: &ProofSlaConfig) {
+    let period = Duration::from_secs(config.backlog_interval_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match store.proof_backlog().await {
+                Ok(backlog) => {
+                    Metrics::global().set_proof_backlog(
+                        backlog.blocks,
+                        backlog_age_secs(&backlog, Utc::now().timestamp()),
+                    );
+                }
+                Err(e) => warn!(error = %e, "Failed to read proof backlog"),
+            }
+        }
+    });
+}
+
+fn backlog_age_secs(backlog: &ProofBacklog, now: i64) -> i64 {
+    backlog
+        .oldest_ingested_at
+        .map_or(0, |ingested_at| (now - ingested_at).max(0))
+}
+
+/// Nearest-rank percentiles over `latencies`, which must be sorted ascending.
+pub fn percentiles(latencies: &[i64]) -> LatencyPercentiles {
+    let rank = |p: f64| -> Option<i64> {
+        if latencies.is_empty() {
+            return None;
+        }
+        let index = ((p / 100.0) * latencies.len() as f64).ceil() as usize;
+        latencies.get(index.saturating_sub(1)).copied()
+    };
+
+    LatencyPercentiles {
+        p50_secs: rank(50.0),
+        p90_secs: rank(90.0),
+        p99_secs: rank(99.0),
+        max_secs: latencies.last().copied(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let latencies: Vec<i64> = (1..=100).collect();
+        let p = percentiles(&latencies);
+        assert_eq!(p.p50_secs, Some(50));
+        assert_eq!(p.p90_secs, Some(90));
+        assert_eq!(p.p99_secs, Some(99));
+        assert_eq!(p.max_secs, Some(100));
+
+        let p = percentiles(&[7]);
+        assert_eq!((p.p50_secs, p.p99_secs), (Some(7), Some(7)));
+
+        assert_eq!(percentiles(&[]).p50_secs, None);
+    }
+
+    #[test]
+    fn test_backlog_age() {
+        assert_eq!(backlog_age_secs(&ProofBacklog::default(), 1_000), 0);
+        let backlog = ProofBacklog {
+            blocks: 3,
+            oldest_ingested_at: Some(400),
+        };
+        assert_eq!(backlog_age_secs(&backlog, 1_000), 600);
+        // Clock skew never reports a negative age
+        assert_eq!(backlog_age_secs(&backlog, 100), 0);
+    }
+}
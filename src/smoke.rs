@@ -0,0 +1,203 @@
+use anyhow::{ensure, Context};
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+
+/// Outcome of a single smoke check against a live deployment.
+#[derive(Debug)]
+pub struct SmokeCheck {
+    pub name: &'static str,
+    pub result: anyhow::Result<()>,
+}
+
+impl SmokeCheck {
+    pub fn passed(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Runs the scripted end-to-end checks against `base_url` and reports each
+/// one, so release pipelines and mirror operators can verify the API contract.
+pub async fn run(base_url: &str) -> Vec<SmokeCheck> {
+    let client = Client::new();
+    let base_url = base_url.trim_end_matches('/');
+    let mut checks = Vec::new();
+
+    checks.push(SmokeCheck {
+        name: "health",
+        result: check_health(&client, base_url).await,
+    });
+
+    let blocks = list_blocks(&client, base_url, None).await;
+    let latest = blocks
+        .as_ref()
+        .ok()
+        .and_then(|b| b["blocks"].get(0))
+        .cloned();
+
+    checks.push(SmokeCheck {
+        name: "pagination",
+        result: match blocks {
+            Ok(page) => check_pagination(&client, base_url, &page).await,
+            Err(e) => Err(e),
+        },
+    });
+
+    checks.push(SmokeCheck {
+        name: "block fetch",
+        result: match &latest {
+            Some(block) => check_block_fetch(&client, base_url, block).await,
+            None => Err(anyhow::anyhow!("no blocks available to fetch")),
+        },
+    });
+
+    checks.push(SmokeCheck {
+        name: "proof HEAD",
+        result: match &latest {
+            Some(block) => check_proof_head(&client, base_url, block).await,
+            None => Err(anyhow::anyhow!("no blocks available to check proofs")),
+        },
+    });
+
+    checks.push(SmokeCheck {
+        name: "error envelope",
+        result: check_error_envelope(&client, base_url).await,
+    });
+
+    checks
+}
+
+async fn get_json(client: &Client, url: &str) -> anyhow::Result<(StatusCode, Value)> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("GET {url} failed"))?;
+    let status = response.status();
+    let body = response
+        .json()
+        .await
+        .with_context(|| format!("GET {url} did not return JSON"))?;
+    Ok((status, body))
+}
+
+async fn check_health(client: &Client, base_url: &str) -> anyhow::Result<()> {
+    let (status, body) = get_json(client, &format!("{base_url}/healthz")).await?;
+    ensure!(status == StatusCode::OK, "expected 200, got {status}");
+    ensure!(
+        body["status"] == "up",
+        "expected status \"up\", got {}",
+        body["status"]
+    );
+    Ok(())
+}
+
+async fn list_blocks(
+    client: &Client,
+    base_url: &str,
+    cursor: Option<u64>,
+) -> anyhow::Result<Value> {
+    let url = match cursor {
+        Some(cursor) => format!("{base_url}/v1/blocks?limit=2&cursor={cursor}"),
+        None => format!("{base_url}/v1/blocks?limit=2"),
+    };
+    let (status, body) = get_json(client, &url).await?;
+    ensure!(status == StatusCode::OK, "expected 200, got {status}");
+    ensure!(body["blocks"].is_array(), "`blocks` is not an array");
+    ensure!(body["total"].is_u64(), "`total` is not a number");
+    ensure!(body["has_next"].is_boolean(), "`has_next` is not a boolean");
+    Ok(body)
+}
+
+async fn check_pagination(client: &Client, base_url: &str, page: &Value) -> anyhow::Result<()> {
+    let blocks = page["blocks"]
+        .as_array()
+        .context("`blocks` is not an array")?;
+    ensure!(blocks.len() <= 2, "page exceeds requested limit");
+
+    if page["has_next"] != true {
+        return Ok(());
+    }
+
+    let cursor = page["next_cursor"]
+        .as_u64()
+        .context("`has_next` is true but `next_cursor` is missing")?;
+    let next_page = list_blocks(client, base_url, Some(cursor)).await?;
+    let next_blocks = next_page["blocks"]
+        .as_array()
+        .context("`blocks` is not an array")?;
+    ensure!(
+        !next_blocks.is_empty(),
+        "next page is empty despite `has_next`"
+    );
+    ensure!(
+        next_blocks
+            .iter()
+            .all(|b| b["height"].as_u64().is_some_and(|h| h < cursor)),
+        "next page contains blocks at or above cursor {cursor}"
+    );
+    Ok(())
+}
+
+async fn check_block_fetch(client: &Client, base_url: &str, block: &Value) -> anyhow::Result<()> {
+    let height = block["height"].as_u64().context("block has no height")?;
+    let (status, detail) = get_json(client, &format!("{base_url}/v1/blocks/{height}")).await?;
+    ensure!(status == StatusCode::OK, "expected 200, got {status}");
+    ensure!(
+        detail["height"] == height,
+        "height mismatch in block detail"
+    );
+    ensure!(
+        detail["hash"] == block["hash"],
+        "hash mismatch in block detail"
+    );
+    ensure!(detail["txids"].is_array(), "`txids` is not an array");
+    Ok(())
+}
+
+async fn check_proof_head(client: &Client, base_url: &str, block: &Value) -> anyhow::Result<()> {
+    let height = block["height"].as_u64().context("block has no height")?;
+    let url = format!("{base_url}/v1/blocks/{height}/proof");
+    let response = client
+        .head(&url)
+        .send()
+        .await
+        .with_context(|| format!("HEAD {url} failed"))?;
+
+    match response.status() {
+        StatusCode::OK => {
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            ensure!(
+                content_type.starts_with("application/json"),
+                "unexpected proof content type: {content_type}"
+            );
+            Ok(())
+        }
+        StatusCode::NOT_FOUND => Ok(()),
+        status => Err(anyhow::anyhow!("expected 200 or 404, got {status}")),
+    }
+}
+
+async fn check_error_envelope(client: &Client, base_url: &str) -> anyhow::Result<()> {
+    let (status, body) = get_json(client, &format!("{base_url}/v1/blocks/not-a-block")).await?;
+    ensure!(
+        status == StatusCode::BAD_REQUEST,
+        "expected 400, got {status}"
+    );
+    ensure!(
+        body["code"] == "INVALID_BLOCK_IDENTIFIER",
+        "error envelope has no stable `code`"
+    );
+    ensure!(
+        body["error"].is_string(),
+        "error envelope has no `error` message"
+    );
+    ensure!(
+        body["status"] == status.as_u16(),
+        "error envelope `status` does not match HTTP status"
+    );
+    Ok(())
+}
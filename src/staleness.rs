@@ -0,0 +1,171 @@
+use crate::store::BlockStore;
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::warn;
+
+/// Set to `true` on data responses while the indexer is stale.
+pub const STALE_HEADER: &str = "x-raito-stale";
+
+static STALENESS_CONFIG: Lazy<StalenessConfig> = Lazy::new(StalenessConfig::from_env);
+
+/// Header time of the indexed tip as last read by the staleness monitor.
+static TIP_TIMESTAMP: Mutex<Option<i64>> = Mutex::new(None);
+
+#[derive(Debug, Clone)]
+pub struct StalenessConfig {
+    /// Responses are marked stale once the indexed tip's header time is
+    /// older than this; staleness is never signaled when unset.
+    pub threshold_secs: Option<i64>,
+    /// How often the indexed tip is read.
+    pub interval_secs: u64,
+}
+
+impl Default for StalenessConfig {
+    fn default() -> Self {
+        Self {
+            threshold_secs: None,
+            interval_secs: 30,
+        }
+    }
+}
+
+impl StalenessConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            threshold_secs: std::env::var("STALE_THRESHOLD_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .or(defaults.threshold_secs),
+            interval_secs: std::env::var("STALE_CHECK_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.interval_secs),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &STALENESS_CONFIG
+    }
+
+    /// Whether a tip mined at `tip_timestamp` is too old at `now`. A new
+    /// block is expected about every ten minutes, so a tip much older than
+    /// that means the indexer is missing blocks the network already has.
+    /// An unread or empty tip is not reported.
+    pub fn is_stale(&self, tip_timestamp: Option<i64>, now: i64) -> bool {
+        match (self.threshold_secs, tip_timestamp) {
+            (Some(threshold), Some(timestamp)) => now - timestamp > threshold,
+            _ => false,
+        }
+    }
+}
+
+/// Header time of the indexed tip, if the monitor has read one.
+pub fn tip_timestamp() -> Option<i64> {
+    *TIP_TIMESTAMP.lock().expect("staleness lock poisoned")
+}
+
+/// Records the indexed tip's header time, as the monitor does.
+pub fn observe_tip(timestamp: Option<i64>) {
+    *TIP_TIMESTAMP.lock().expect("staleness lock poisoned") = timestamp;
+}
+
+/// Whether responses are currently marked stale.
+pub fn is_stale() -> bool {
+    StalenessConfig::global().is_stale(tip_timestamp(), Utc::now().timestamp())
+}
+
+/// Reads the indexed tip's header time every `interval_secs`.
+pub fn spawn_staleness_monitor(store: Arc<dyn BlockStore>, config: &'static StalenessConfig) {
+    if config.threshold_secs.is_none() {
+        return;
+    }
+    let period = Duration::from_secs(config.interval_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            let tip = match store.tip_height().await {
+                Ok(Some(height)) => store
+                    .get_block_by_height(height)
+                    .await
+                    .map(|block| Some(block.summary.timestamp)),
+                Ok(None) => Ok(None),
+                Err(e) => Err(e),
+            };
+            match tip {
+                Ok(timestamp) => observe_tip(timestamp),
+                Err(e) => warn!(error = %e, "Failed to read the indexed tip"),
+            }
+        }
+    });
+}
+
+/// Adds `X-Raito-Stale: true` while the indexed tip is older than
+/// `config`'s threshold, so clients know answers may trail the chain.
+pub async fn staleness_middleware(
+    State(config): State<&'static StalenessConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if config.is_stale(tip_timestamp(), Utc::now().timestamp()) {
+        response
+            .headers_mut()
+            .insert(STALE_HEADER, HeaderValue::from_static("true"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_stale() {
+        let config = StalenessConfig {
+            threshold_secs: Some(3600),
+            ..StalenessConfig::default()
+        };
+        assert!(!config.is_stale(Some(10_000), 13_600));
+        assert!(config.is_stale(Some(10_000), 13_601));
+        assert!(!config.is_stale(None, 13_601));
+        assert!(!StalenessConfig::default().is_stale(Some(0), 13_601));
+    }
+
+    #[tokio::test]
+    async fn test_stale_header() {
+        use axum::{middleware, routing::get, Router};
+        use axum_test::TestServer;
+
+        static CONFIG: StalenessConfig = StalenessConfig {
+            threshold_secs: Some(3600),
+            interval_secs: 30,
+        };
+        let server = TestServer::new(Router::new().route("/", get(|| async { "ok" })).layer(
+            middleware::from_fn_with_state(&CONFIG, staleness_middleware),
+        ))
+        .unwrap();
+
+        let now = Utc::now().timestamp();
+        observe_tip(Some(now - 600));
+        let response = server.get("/").await;
+        assert!(response.headers().get(STALE_HEADER).is_none());
+
+        observe_tip(Some(now - 7200));
+        let response = server.get("/").await;
+        assert_eq!(response.headers()[STALE_HEADER], "true");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    chain::HALVING_INTERVAL,
+    model::{BlockStats, SATS_PER_BTC},
+};
+
+const INITIAL_SUBSIDY_SATS: i64 = 50 * SATS_PER_BTC;
+
+/// Block subsidy in satoshis at `height`.
+pub fn block_subsidy(height: u32) -> i64 {
+    let halvings = height / HALVING_INTERVAL;
+    if halvings >= 64 {
+        0
+    } else {
+        INITIAL_SUBSIDY_SATS >> halvings
+    }
+}
+
+fn compact_size_len(n: usize) -> u64 {
+    match n {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x10000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+/// Computes block statistics from the ingested transaction details of a block.
+pub fn compute_block_stats(height: u32, transactions: &[serde_json::Value]) -> BlockStats {
+    let header_and_count = 80 + compact_size_len(transactions.len());
+
+    let mut size = header_and_count;
+    let mut weight = header_and_count * 4;
+    let mut input_count = 0;
+    let mut output_count = 0;
+    let mut total_fee = 0.0;
+    let mut fee_paying_vsize = 0;
+    let mut fee_rates = Vec::new();
+
+    for (position, tx) in transactions.iter().enumerate() {
+        let tx_size = tx["size"].as_u64().unwrap_or_default();
+        let tx_vsize = tx["vsize"].as_u64().unwrap_or_default();
+        let fee = tx["fee"].as_f64().unwrap_or_default();
+
+        size += tx_size;
+        weight += tx_vsize * 4;
+        input_count += tx["inputs"].as_array().map_or(0, Vec::len) as u32;
+        output_count += tx["outputs"].as_array().map_or(0, Vec::len) as u32;
+
+        // The coinbase pays no fee and is excluded from fee rates
+        if position > 0 && tx_vsize > 0 {
+            total_fee += fee;
+            fee_paying_vsize += tx_vsize;
+            fee_rates.push(fee * 100_000_000.0 / tx_vsize as f64);
+        }
+    }
+
+    fee_rates.sort_by(f64::total_cmp);
+    let median_fee_rate = match fee_rates.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => fee_rates[n / 2],
+        n => (fee_rates[n / 2 - 1] + fee_rates[n / 2]) / 2.0,
+    };
+    let avg_fee_rate = if fee_paying_vsize > 0 {
+        total_fee * 100_000_000.0 / fee_paying_vsize as f64
+    } else {
+        0.0
+    };
+
+    BlockStats {
+        height,
+        size: size as u32,
+        weight: weight as u32,
+        avg_fee_rate,
+        median_fee_rate,
+        input_count,
+        output_count,
+        subsidy: block_subsidy(height),
+    }
+}
+
+/// Serialized size and weight of a block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockSize {
+    pub size: Option<u32>,
+    pub stripped_size: Option<u32>,
+    pub weight: Option<u32>,
+}
+
+/// Reads `size`, `stripped_size` and `weight` from a block document. Those it
+/// lacks are derived from its transactions when it carries them; the stripped
+/// size follows from weight = 3 * stripped size + size.
+pub fn block_size(block: &serde_json::Value) -> BlockSize {
+    let field = |name: &str| block[name].as_u64().map(|v| v as u32);
+    let computed = block["transactions"]
+        .as_array()
+        .filter(|transactions| !transactions.is_empty())
+        .map(|transactions| compute_block_stats(0, transactions));
+    let size = field("size").or(computed.as_ref().map(|stats| stats.size));
+    let weight = field("weight").or(computed.as_ref().map(|stats| stats.weight));
+    let stripped_size = field("stripped_size").or(match (size, weight) {
+        (Some(size), Some(weight)) => weight.checked_sub(size).map(|witness| witness / 3),
+        _ => None,
+    });
+    BlockSize {
+        size,
+        stripped_size,
+        weight,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_block_subsidy() {
+        assert_eq!(block_subsidy(0), 5_000_000_000);
+        assert_eq!(block_subsidy(210_000), 2_500_000_000);
+        assert_eq!(block_subsidy(869_123), 312_500_000);
+        assert_eq!(block_subsidy(64 * 210_000), 0);
+    }
+
+    #[test]
+    fn test_compute_block_stats() {
+        let transactions = vec![
+            json!({"fee": 0.0, "size": 100, "vsize": 100, "inputs": [{}], "outputs": [{}]}),
+            json!({"fee": 0.00001, "size": 100, "vsize": 100, "inputs": [{}], "outputs": [{}, {}]}),
+            json!({"fee": 0.00004, "size": 200, "vsize": 100, "inputs": [{}, {}], "outputs": [{}]}),
+        ];
+        let stats = compute_block_stats(869_123, &transactions);
+
+        assert_eq!(stats.size, 81 + 400);
+        assert_eq!(stats.weight, 81 * 4 + 1200);
+        assert_eq!(stats.input_count, 4);
+        assert_eq!(stats.output_count, 4);
+        assert!((stats.avg_fee_rate - 25.0).abs() < 1e-9);
+        assert!((stats.median_fee_rate - 25.0).abs() < 1e-9);
+
+        let block = json!({ "transactions": transactions });
+        assert_eq!(
+            block_size(&block),
+            BlockSize {
+                size: Some(481),
+                stripped_size: Some((1524 - 481) / 3),
+                weight: Some(1524),
+            }
+        );
+    }
+
+    #[test]
+    fn test_block_size_as_given() {
+        let block = json!({ "size": 1000, "stripped_size": 800, "weight": 3400 });
+        assert_eq!(
+            block_size(&block),
+            BlockSize {
+                size: Some(1000),
+                stripped_size: Some(800),
+                weight: Some(3400),
+            }
+        );
+        assert_eq!(block_size(&json!({})), BlockSize::default());
+    }
+}
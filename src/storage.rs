@@ -0,0 +1,400 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use once_cell::sync::OnceCell;
+use std::{collections::BTreeSet, io, path::PathBuf};
+use tokio::io::AsyncRead;
+
+pub type ProofReader = Box<dyn AsyncRead + Send + Unpin>;
+
+static PROOF_STORAGE: OnceCell<Box<dyn ProofStorage>> = OnceCell::new();
+
+/// Backend holding STARK proof files, keyed by block height.
+#[async_trait]
+pub trait ProofStorage: Send + Sync {
+    /// Human-readable location of the backend, used in migration reports.
+    fn describe(&self) -> String;
+
+    /// Heights of every proof held by the backend, in ascending order.
+    async fn list(&self) -> io::Result<Vec<u32>>;
+
+    async fn read(&self, height: u32) -> io::Result<Option<Vec<u8>>>;
+
+    /// Opens the proof for streaming without loading it into memory.
+    async fn open(&self, height: u32) -> io::Result<Option<ProofReader>>;
+
+    /// The whole proof as a buffer that can be sliced and sent without
+    /// copying. Backends that can share their storage with the process (such
+    /// as a memory-mapped file) should override this; the default reads it.
+    async fn map(&self, height: u32) -> io::Result<Option<Bytes>> {
+        Ok(self.read(height).await?.map(Bytes::from))
+    }
+
+    /// Reads the proof through once without keeping it, so the backend's
+    /// caches hold it for the first real download. Returns whether it exists.
+    async fn warm(&self, height: u32) -> io::Result<bool> {
+        let Some(mut reader) = self.open(height).await? else {
+            return Ok(false);
+        };
+        tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+        Ok(true)
+    }
+
+    async fn write(&self, height: u32, data: &[u8]) -> io::Result<()>;
+
+    /// Space taken by proofs and left on the disk holding them, for backends
+    /// where that is meaningful.
+    async fn usage(&self) -> io::Result<Option<StorageUsage>> {
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageUsage {
+    /// Total size of the stored proofs.
+    pub proof_bytes: u64,
+    /// Space still available to the service on the disk holding them.
+    pub available_bytes: u64,
+}
+
+/// Proofs stored as `<root>/<height>.json` on the local filesystem.
+#[derive(Debug, Clone)]
+pub struct FsProofStorage {
+    root: PathBuf,
+}
+
+impl FsProofStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, height: u32) -> PathBuf {
+        self.root.join(format!("{height}.json"))
+    }
+}
+
+fn not_found_as_none<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[async_trait]
+impl ProofStorage for FsProofStorage {
+    fn describe(&self) -> String {
+        format!("fs:{}", self.root.display())
+    }
+
+    async fn list(&self) -> io::Result<Vec<u32>> {
+        let Some(mut entries) = not_found_as_none(tokio::fs::read_dir(&self.root).await)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut heights = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(height) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse().ok())
+                {
+                    heights.push(height);
+                }
+            }
+        }
+        heights.sort_unstable();
+        Ok(heights)
+    }
+
+    async fn read(&self, height: u32) -> io::Result<Option<Vec<u8>>> {
+        not_found_as_none(tokio::fs::read(self.path(height)).await)
+    }
+
+    async fn open(&self, height: u32) -> io::Result<Option<ProofReader>> {
+        let file = not_found_as_none(tokio::fs::File::open(self.path(height)).await)?;
+        Ok(file.map(|f| Box::new(f) as ProofReader))
+    }
+
+    /// Memory-maps the proof, so serving it reads straight from the page
+    /// cache instead of copying the file onto the heap.
+    async fn map(&self, height: u32) -> io::Result<Option<Bytes>> {
+        let path = self.path(height);
+        let mapped = tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(path)?;
+            // SAFETY: proofs are never modified in place. `write` replaces a
+            // proof by renaming a new file over it, which leaves the inode
+            // mapped here untouched until the mapping is dropped.
+            unsafe { memmap2::Mmap::map(&file) }
+        })
+        .await
+        .map_err(io::Error::other)?;
+        Ok(not_found_as_none(mapped)?.map(Bytes::from_owner))
+    }
+
+    async fn write(&self, height: u32, data: &[u8]) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.root).await?;
+
+        // Write to a temporary file first so readers never observe a partial proof
+        let path = self.path(height);
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, &path).await
+    }
+
+    async fn usage(&self) -> io::Result<Option<StorageUsage>> {
+        let mut proof_bytes = 0;
+        if let Some(mut entries) = not_found_as_none(tokio::fs::read_dir(&self.root).await)? {
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+                if metadata.is_file() {
+                    proof_bytes += metadata.len();
+                }
+            }
+        }
+
+        // The directory is only created by the first write, so measure the
+        // disk it will end up on
+        let root = self.root.clone();
+        let available_bytes = tokio::task::spawn_blocking(move || {
+            let existing = root
+                .ancestors()
+                .find(|dir| dir.exists())
+                .unwrap_or(std::path::Path::new("."));
+            fs4::available_space(existing)
+        })
+        .await
+        .map_err(io::Error::other)??;
+
+        Ok(Some(StorageUsage {
+            proof_bytes,
+            available_bytes,
+        }))
+    }
+}
+
+/// Serves proofs from `primary`, falling back to `fallback` for proofs that
+/// have not been migrated yet. Writes only go to `primary`.
+pub struct DualReadStorage {
+    primary: Box<dyn ProofStorage>,
+    fallback: Box<dyn ProofStorage>,
+}
+
+impl DualReadStorage {
+    pub fn new(primary: Box<dyn ProofStorage>, fallback: Box<dyn ProofStorage>) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+#[async_trait]
+impl ProofStorage for DualReadStorage {
+    fn describe(&self) -> String {
+        format!(
+            "{} (falling back to {})",
+            self.primary.describe(),
+            self.fallback.describe()
+        )
+    }
+
+    async fn list(&self) -> io::Result<Vec<u32>> {
+        let mut heights: BTreeSet<u32> = self.primary.list().await?.into_iter().collect();
+        heights.extend(self.fallback.list().await?);
+        Ok(heights.into_iter().collect())
+    }
+
+    async fn read(&self, height: u32) -> io::Result<Option<Vec<u8>>> {
+        match self.primary.read(height).await? {
+            Some(data) => Ok(Some(data)),
+            None => self.fallback.read(height).await,
+        }
+    }
+
+    async fn open(&self, height: u32) -> io::Result<Option<ProofReader>> {
+        match self.primary.open(height).await? {
+            Some(reader) => Ok(Some(reader)),
+            None => self.fallback.open(height).await,
+        }
+    }
+
+    async fn map(&self, height: u32) -> io::Result<Option<Bytes>> {
+        match self.primary.map(height).await? {
+            Some(data) => Ok(Some(data)),
+            None => self.fallback.map(height).await,
+        }
+    }
+
+    async fn write(&self, height: u32, data: &[u8]) -> io::Result<()> {
+        self.primary.write(height, data).await
+    }
+
+    /// New proofs only land on the primary, so that is the disk that can
+    /// fill up.
+    async fn usage(&self) -> io::Result<Option<StorageUsage>> {
+        self.primary.usage().await
+    }
+}
+
+/// Builds the proof backend from `PROOF_STORAGE_DIR` (defaults to
+/// `data/proofs`). While a migration is in progress, `PROOF_STORAGE_FALLBACK_DIR`
+/// points at the old backend so proofs not yet copied are still served.
+pub fn from_env() -> Box<dyn ProofStorage> {
+    let primary = std::env::var("PROOF_STORAGE_DIR").unwrap_or_else(|_| "data/proofs".to_string());
+    let primary = Box::new(FsProofStorage::new(primary));
+
+    match std::env::var("PROOF_STORAGE_FALLBACK_DIR") {
+        Ok(fallback) if !fallback.is_empty() => Box::new(DualReadStorage::new(
+            primary,
+            Box::new(FsProofStorage::new(fallback)),
+        )),
+        _ => primary,
+    }
+}
+
+/// The backend proofs are served from; built from the environment unless
+/// [`set_global`] was called first.
+pub fn global() -> &'static dyn ProofStorage {
+    PROOF_STORAGE.get_or_init(from_env).as_ref()
+}
+
+/// Overrides the proof backend. Must be called before the first proof is
+/// served; returns `false` if the backend was already initialized.
+pub fn set_global(storage: Box<dyn ProofStorage>) -> bool {
+    PROOF_STORAGE.set(storage).is_ok()
+}
+
+/// Running totals for a proof migration, reported after every proof.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationProgress {
+    pub total: usize,
+    pub copied: usize,
+    /// Proofs already present in the target with identical content.
+    pub skipped: usize,
+    pub failed: Vec<(u32, String)>,
+}
+
+impl MigrationProgress {
+    pub fn processed(&self) -> usize {
+        self.copied + self.skipped + self.failed.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.processed() == self.total
+    }
+}
+
+/// Copies every proof from `source` to `target`, reading each one back from
+/// the target and comparing checksums before counting it as migrated.
+///
+/// Proofs already present in the target with the same content are skipped,
+/// so an interrupted migration can simply be re-run.
+pub async fn migrate(
+    source: &dyn ProofStorage,
+    target: &dyn ProofStorage,
+    mut on_progress: impl FnMut(u32, &MigrationProgress),
+) -> io::Result<MigrationProgress> {
+    let heights = source.list().await?;
+    let mut progress = MigrationProgress {
+        total: heights.len(),
+        ..Default::default()
+    };
+
+    for height in heights {
+        match migrate_one(source, target, height).await {
+            Ok(true) => progress.copied += 1,
+            Ok(false) => progress.skipped += 1,
+            Err(e) => progress.failed.push((height, e.to_string())),
+        }
+        on_progress(height, &progress);
+    }
+
+    Ok(progress)
+}
+
+/// Returns whether the proof had to be copied.
+async fn migrate_one(
+    source: &dyn ProofStorage,
+    target: &dyn ProofStorage,
+    height: u32,
+) -> io::Result<bool> {
+    let data = source
+        .read(height)
+        .await?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "proof vanished from source"))?;
+    let checksum = crate::proof::checksum(&data);
+
+    if let Some(existing) = target.read(height).await? {
+        if crate::proof::checksum(&existing) == checksum {
+            return Ok(false);
+        }
+    }
+
+    target.write(height, &data).await?;
+
+    let copied = target.read(height).await?.unwrap_or_default();
+    if crate::proof::checksum(&copied) != checksum {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "checksum mismatch after copy",
+        ));
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_migrate_copies_and_verifies() {
+        let old = tempfile::tempdir().unwrap();
+        let new = tempfile::tempdir().unwrap();
+        let source = FsProofStorage::new(old.path());
+        let target = FsProofStorage::new(new.path());
+
+        source.write(100, b"proof 100").await.unwrap();
+        source.write(101, b"proof 101").await.unwrap();
+        target.write(101, b"proof 101").await.unwrap();
+
+        let mut reported = Vec::new();
+        let progress = migrate(&source, &target, |height, p| {
+            reported.push((height, p.processed()))
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(reported, vec![(100, 1), (101, 2)]);
+        assert_eq!((progress.copied, progress.skipped), (1, 1));
+        assert!(progress.is_complete());
+        assert_eq!(target.read(100).await.unwrap().unwrap(), b"proof 100");
+
+        // Re-running is a no-op
+        let rerun = migrate(&source, &target, |_, _| {}).await.unwrap();
+        assert_eq!((rerun.copied, rerun.skipped), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_dual_read_falls_back_to_old_backend() {
+        let old = tempfile::tempdir().unwrap();
+        let new = tempfile::tempdir().unwrap();
+        FsProofStorage::new(old.path())
+            .write(100, b"old")
+            .await
+            .unwrap();
+
+        let storage = DualReadStorage::new(
+            Box::new(FsProofStorage::new(new.path())),
+            Box::new(FsProofStorage::new(old.path())),
+        );
+        assert_eq!(storage.read(100).await.unwrap().unwrap(), b"old");
+        assert!(storage.read(101).await.unwrap().is_none());
+
+        assert_eq!(storage.map(100).await.unwrap().unwrap(), &b"old"[..]);
+        assert!(storage.map(101).await.unwrap().is_none());
+
+        storage.write(100, b"new").await.unwrap();
+        assert_eq!(storage.read(100).await.unwrap().unwrap(), b"new");
+        assert_eq!(storage.list().await.unwrap(), vec![100]);
+    }
+}
@@ -1,16 +1,471 @@
 use crate::{
+    breaker::CircuitBreaker,
+    chain, coinbase,
     error::{AppError, Result},
-    model::{BlockDetail, BlockSummary, BlocksResponse, HeaderStatus, TransactionStatus},
+    hash::{BlockHash, BlockId, Txid},
+    idempotency::{IdempotencyClaim, IdempotencyConfig, StoredResponse},
+    model::{
+        parse_amount, AddressTransaction, ApiKey, AuditLogResponse, BlockConflictsResponse,
+        BlockDetail, BlockFilter, BlockStats, BlockSummary, BlockTxidsResponse, BlocksResponse,
+        ConsistencyReport, DeliveryStatus, HeaderStatus, KeyScope, NetworkStats, OrphansResponse,
+        OutputStatus, PageDirection, ProofCoverageResponse, ProofGap, ProofGapReason, ProofJob,
+        ProofRecord, ProofsResponse, PruneResponse, SortOrder, TransactionDetail,
+        TransactionStatus, UpdateWebhookRequest, UsageCounts, Utxo, Webhook,
+        WebhookDeliveriesResponse, WebhookEvent,
+    },
+    proof, stats, witness,
 };
+use async_trait::async_trait;
 use once_cell::sync::Lazy;
-use std::{collections::HashMap, fs, io::Read, path::Path};
+use std::{collections::HashMap, fs, io::Read, path::PathBuf, time::UNIX_EPOCH};
+use tracing::warn;
+
+/// Storage behind the HTTP handlers: reads of indexed blocks, transactions and
+/// proofs, plus the writes the API exposes (block submissions, proof uploads
+/// and the admin operations). Implemented by [`crate::database::Database`]
+/// and by [`MockStore`], so handlers can be exercised against either
+/// backend; writes default to refusing with `FeatureDisabled`.
+#[async_trait]
+pub trait BlockStore: Send + Sync {
+    async fn health_check(&self) -> Result<()>;
+
+    /// How far the read replica trails the primary, when reads go to one.
+    fn replica_lag(&self) -> Option<ReplicaLag> {
+        None
+    }
+
+    /// Breaker guarding reads, for backends that can fail transiently.
+    fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        None
+    }
+
+    /// Height of the highest indexed block, if any.
+    async fn tip_height(&self) -> Result<Option<u32>>;
+
+    async fn get_blocks(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        direction: PageDirection,
+        order: SortOrder,
+        filter: &BlockFilter,
+    ) -> Result<BlocksResponse>;
+
+    async fn get_orphans(&self, limit: u32, cursor: Option<i64>) -> Result<OrphansResponse>;
+
+    async fn block_exists_by_identifier(&self, id: &BlockId) -> Result<bool>;
+
+    async fn get_block_by_height(&self, height: u32) -> Result<BlockDetail>;
+
+    async fn get_block_by_hash(&self, hash: &BlockHash) -> Result<BlockDetail>;
+
+    async fn get_block_stats(&self, height: u32) -> Result<BlockStats>;
+
+    async fn get_block_txids(
+        &self,
+        height: u32,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<BlockTxidsResponse>;
+
+    async fn get_transaction_status(&self, txid: &Txid) -> Result<TransactionStatus>;
+
+    async fn get_transaction_detail(&self, txid: &Txid) -> Result<TransactionDetail>;
+
+    async fn get_raw_transaction(&self, txid: &Txid) -> Result<Vec<u8>>;
+
+    async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<OutputStatus>;
+
+    fn address_index_enabled(&self) -> bool;
+
+    async fn get_address_transactions(
+        &self,
+        script_pubkey: &str,
+    ) -> Result<Vec<AddressTransaction>>;
+
+    async fn get_address_utxos(&self, script_pubkey: &str) -> Result<Vec<Utxo>>;
+
+    async fn get_header_status(&self, hash: &BlockHash) -> Result<HeaderStatus>;
+
+    async fn get_network_stats(&self) -> Result<NetworkStats>;
+
+    async fn proof_file_exists(&self, height: u32) -> Result<bool>;
+
+    /// Checksum recorded when the proof of block `height` was stored. `None`
+    /// when the backend does not keep one, and the proof has to be hashed.
+    async fn proof_checksum(&self, _height: u32) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    async fn get_proofs(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        verified: Option<bool>,
+        version: Option<&str>,
+    ) -> Result<ProofsResponse>;
+
+    async fn get_proof_coverage(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<ProofCoverageResponse>;
+
+    /// Ingest-to-proof latencies in seconds of the `window` most recent proofs.
+    async fn get_proof_latencies(&self, window: u32) -> Result<Vec<i64>>;
+
+    /// Blocks ingested more than `secs` ago that still have no proof.
+    async fn count_unproved_older_than(&self, secs: i64) -> Result<u32>;
+
+    /// Blocks waiting for a proof and how long the oldest has waited.
+    async fn proof_backlog(&self) -> Result<ProofBacklog>;
+
+    /// Height below which transaction data has been pruned; 0 when nothing has.
+    async fn pruned_below(&self) -> Result<u32> {
+        Ok(0)
+    }
+
+    /// Prunes transaction data below `height`, for the admin API; backends
+    /// without pruning refuse it.
+    async fn prune_below(&self, _height: u32) -> Result<PruneResponse> {
+        Err(AppError::FeatureDisabled(
+            "pruning is not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Loads the bundled mock blocks again, over whatever is stored.
+    async fn reseed(&self) -> Result<()> {
+        Err(AppError::FeatureDisabled(
+            "reseeding is not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Rebuilds the data derived from blocks `from` through `to`; returns how
+    /// many blocks were rewritten.
+    async fn reindex(&self, _from: u32, _to: u32) -> Result<u32> {
+        Err(AppError::FeatureDisabled(
+            "reindexing is not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Indexes one block given in the mock-data JSON shape, as submitted to
+    /// `POST /v1/blocks`.
+    async fn submit_block(&self, _block: &serde_json::Value) -> Result<()> {
+        Err(AppError::FeatureDisabled(
+            "block submission is not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Stores an externally generated proof of block `height`, as uploaded to
+    /// `POST /v1/blocks/{height}/proof`, and starts serving it.
+    async fn upload_proof(&self, _height: u32, _data: &[u8]) -> Result<()> {
+        Err(AppError::FeatureDisabled(
+            "proof uploads are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Claims idempotency key `key` of client `scope` for the request with
+    /// `fingerprint`, unless a live claim already stands for it.
+    async fn claim_idempotency_key(
+        &self,
+        _scope: &str,
+        _key: &str,
+        _fingerprint: &str,
+        _now: i64,
+        _config: &IdempotencyConfig,
+    ) -> Result<IdempotencyClaim> {
+        Err(AppError::FeatureDisabled(
+            "idempotency keys are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Keeps the response the claimed key's request succeeded with.
+    async fn complete_idempotency_key(
+        &self,
+        _scope: &str,
+        _key: &str,
+        _response: &StoredResponse,
+    ) -> Result<()> {
+        Err(AppError::FeatureDisabled(
+            "idempotency keys are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Drops the claim of a request that failed, so it can be retried.
+    async fn release_idempotency_key(&self, _scope: &str, _key: &str) -> Result<()> {
+        Err(AppError::FeatureDisabled(
+            "idempotency keys are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Stores a webhook subscription; `secret` is its plaintext signing secret.
+    async fn create_webhook(
+        &self,
+        _url: &str,
+        _secret: &str,
+        _events: &[WebhookEvent],
+        _active: bool,
+    ) -> Result<Webhook> {
+        Err(AppError::FeatureDisabled(
+            "webhooks are not supported by this store backend".to_string(),
+        ))
+    }
+
+    async fn list_webhooks(&self) -> Result<Vec<Webhook>> {
+        Err(AppError::FeatureDisabled(
+            "webhooks are not supported by this store backend".to_string(),
+        ))
+    }
+
+    async fn get_webhook(&self, _id: i64) -> Result<Webhook> {
+        Err(AppError::FeatureDisabled(
+            "webhooks are not supported by this store backend".to_string(),
+        ))
+    }
+
+    async fn update_webhook(&self, _id: i64, _changes: &UpdateWebhookRequest) -> Result<Webhook> {
+        Err(AppError::FeatureDisabled(
+            "webhooks are not supported by this store backend".to_string(),
+        ))
+    }
+
+    async fn delete_webhook(&self, _id: i64) -> Result<Webhook> {
+        Err(AppError::FeatureDisabled(
+            "webhooks are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Webhook `webhook_id`'s deliveries, newest first.
+    async fn list_webhook_deliveries(
+        &self,
+        _webhook_id: i64,
+        _status: Option<DeliveryStatus>,
+        _limit: u32,
+        _cursor: Option<i64>,
+    ) -> Result<WebhookDeliveriesResponse> {
+        Err(AppError::FeatureDisabled(
+            "webhooks are not supported by this store backend".to_string(),
+        ))
+    }
+
+    async fn get_proof_job(&self, _id: i64) -> Result<ProofJob> {
+        Err(AppError::FeatureDisabled(
+            "proof jobs are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Drops any cached reads. Backends without a cache have nothing to do.
+    fn invalidate_cache(&self) {}
+
+    /// Puts proofs marked failed back in the backlog; returns how many.
+    async fn requeue_failed_proofs(&self) -> Result<u32> {
+        Err(AppError::FeatureDisabled(
+            "proof requeueing is not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Stores a new API key by the SHA-256 digest of its value.
+    async fn create_api_key(
+        &self,
+        _name: &str,
+        _scope: KeyScope,
+        _prefix: &str,
+        _key_hash: &str,
+    ) -> Result<ApiKey> {
+        Err(AppError::FeatureDisabled(
+            "API keys are not supported by this store backend".to_string(),
+        ))
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        Err(AppError::FeatureDisabled(
+            "API keys are not supported by this store backend".to_string(),
+        ))
+    }
+
+    async fn revoke_api_key(&self, _id: i64) -> Result<ApiKey> {
+        Err(AppError::FeatureDisabled(
+            "API keys are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Appends an entry to the audit log.
+    async fn record_audit(&self, _actor: &str, _action: &str, _target: Option<&str>) -> Result<()> {
+        Err(AppError::FeatureDisabled(
+            "audit logging is not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Audit entries, newest first, optionally for one actor or action.
+    async fn list_audit_log(
+        &self,
+        _actor: Option<&str>,
+        _action: Option<&str>,
+        _limit: u32,
+        _cursor: Option<i64>,
+    ) -> Result<AuditLogResponse> {
+        Err(AppError::FeatureDisabled(
+            "audit logging is not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Inserts that met a different block at their height, newest first.
+    async fn list_block_conflicts(
+        &self,
+        _limit: u32,
+        _cursor: Option<i64>,
+    ) -> Result<BlockConflictsResponse> {
+        Err(AppError::FeatureDisabled(
+            "conflict tracking is not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// The `limit` most recent consistency check reports, newest first.
+    async fn list_consistency_reports(&self, _limit: u32) -> Result<Vec<ConsistencyReport>> {
+        Err(AppError::FeatureDisabled(
+            "consistency checks are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Runs the consistency checks now and stores their report.
+    async fn run_consistency_check(&self) -> Result<ConsistencyReport> {
+        Err(AppError::FeatureDisabled(
+            "consistency checks are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Adds to key `id`'s usage on `day` (`YYYY-MM-DD`, UTC).
+    async fn record_api_key_usage(&self, _id: i64, _day: &str, _usage: UsageCounts) -> Result<()> {
+        Err(AppError::FeatureDisabled(
+            "API keys are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// Key `id`'s usage on `day` and from `month_start` through `day`.
+    async fn get_api_key_usage(
+        &self,
+        _id: i64,
+        _day: &str,
+        _month_start: &str,
+    ) -> Result<(UsageCounts, UsageCounts)> {
+        Err(AppError::FeatureDisabled(
+            "API keys are not supported by this store backend".to_string(),
+        ))
+    }
+
+    /// The unrevoked key whose digest is `key_hash`, noting its use. Backends
+    /// without API keys know no key.
+    async fn authenticate_api_key(&self, _key_hash: &str) -> Result<Option<ApiKey>> {
+        Ok(None)
+    }
+}
 
 #[derive(Debug)]
 pub struct MockStore {
     blocks_by_height: HashMap<u32, BlockDetail>,
-    blocks_by_hash: HashMap<String, BlockDetail>,
-    tx_index: HashMap<String, u32>,
-    header_index: HashMap<String, u32>,
+    blocks_by_hash: HashMap<BlockHash, BlockDetail>,
+    tx_index: HashMap<Txid, u32>,
+    header_index: HashMap<BlockHash, u32>,
+}
+
+/// Indexed blocks that still have no proof, leaving out ones whose proof was
+/// archived.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProofBacklog {
+    pub blocks: u32,
+    /// When the longest-waiting block was ingested (unix seconds).
+    pub oldest_ingested_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReplicaLag {
+    pub blocks: u32,
+    /// Lag beyond which reads are served by the primary instead.
+    pub max_blocks: u32,
+}
+
+impl ReplicaLag {
+    pub fn is_lagging(&self) -> bool {
+        self.blocks > self.max_blocks
+    }
+}
+
+/// Builds a [`BlockDetail`] from a block in the mock-data JSON shape.
+/// Chainwork not given in the document is added to that of `parent`, the
+/// stored block it builds on.
+pub(crate) fn parse_block(
+    block_data: &serde_json::Value,
+    parent: Option<&BlockDetail>,
+) -> BlockDetail {
+    let summary = BlockSummary {
+        height: block_data["height"].as_u64().unwrap() as u32,
+        hash: block_data["hash"].as_str().unwrap().parse().unwrap(),
+        tx_count: block_data["tx_count"].as_u64().unwrap() as u32,
+        total_fees: parse_amount(&block_data["total_fees"]).unwrap(),
+        timestamp: block_data["timestamp"].as_i64().unwrap(),
+        verified: block_data["verified"].as_bool().unwrap(),
+    };
+
+    let txids: Vec<Txid> = block_data["txids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_str().unwrap().parse().unwrap())
+        .collect();
+
+    let size = stats::block_size(block_data);
+    let witness = witness::block_witness(block_data);
+    let bits = block_data["bits"].as_u64().unwrap() as u32;
+    let chainwork = match block_data["chainwork"].as_str() {
+        Some(given) => chain::parse_chainwork(given),
+        None => chain::next_chainwork(
+            parent.map(|parent| parent.chainwork.as_deref().and_then(chain::parse_chainwork)),
+            bits,
+        ),
+    };
+    BlockDetail {
+        prev_hash: block_data["prev_hash"].as_str().unwrap().parse().unwrap(),
+        merkle_root: block_data["merkle_root"].as_str().unwrap().to_string(),
+        witness_merkle_root: witness.as_ref().map(|w| w.witness_merkle_root.clone()),
+        witness_commitment: witness.as_ref().and_then(|w| w.witness_commitment.clone()),
+        version: block_data["version"].as_i64().unwrap() as i32,
+        bits: block_data["bits"].as_u64().unwrap() as u32,
+        nonce: block_data["nonce"].as_u64().unwrap() as u32,
+        size: size.size,
+        stripped_size: size.stripped_size,
+        weight: size.weight,
+        difficulty: chain::difficulty(bits),
+        chainwork: chainwork.map(chain::format_chainwork),
+        proof_url: format!("/v1/blocks/{}/proof", summary.height),
+        txids,
+        wtxids: witness.map(|w| w.wtxids).unwrap_or_default(),
+        coinbase: coinbase::coinbase_info(block_data),
+        summary,
+        pruned: false,
+        orphaned: false,
+    }
+}
+
+/// Which [`BlockStore`] implementation the service runs on, from `STORE_BACKEND`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StoreBackend {
+    #[default]
+    Sqlite,
+    /// Embedded key-value store, see [`crate::kv::KvStore`].
+    Redb,
+}
+
+impl StoreBackend {
+    pub fn from_env() -> Self {
+        match std::env::var("STORE_BACKEND").as_deref() {
+            Ok("redb") => Self::Redb,
+            Ok("sqlite") | Err(_) => Self::Sqlite,
+            Ok(other) => {
+                warn!(backend = other, "Unknown STORE_BACKEND, using sqlite");
+                Self::Sqlite
+            }
+        }
+    }
 }
 
 static STORE: Lazy<MockStore> =
@@ -21,52 +476,36 @@ impl MockStore {
         &STORE
     }
 
-    fn load_from_files() -> Result<Self> {
+    pub fn load_from_files() -> Result<Self> {
         let blocks_data = fs::read_to_string("data/mock_blocks.json")
             .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to read blocks file: {}", e)))?;
 
-        let raw_blocks: Vec<serde_json::Value> = serde_json::from_str(&blocks_data)?;
+        let mut raw_blocks: Vec<serde_json::Value> = serde_json::from_str(&blocks_data)?;
+        // Parents first, so chainwork accumulates
+        raw_blocks.sort_by_key(|b| b["height"].as_u64());
 
-        let mut blocks_by_height = HashMap::new();
+        let mut blocks_by_height: HashMap<u32, BlockDetail> = HashMap::new();
         let mut blocks_by_hash = HashMap::new();
         let mut tx_index = HashMap::new();
         let mut header_index = HashMap::new();
 
         for block_data in raw_blocks {
-            let summary = BlockSummary {
-                height: block_data["height"].as_u64().unwrap() as u32,
-                hash: block_data["hash"].as_str().unwrap().to_string(),
-                tx_count: block_data["tx_count"].as_u64().unwrap() as u32,
-                total_fees: block_data["total_fees"].as_f64().unwrap(),
-                timestamp: block_data["timestamp"].as_i64().unwrap(),
-                verified: block_data["verified"].as_bool().unwrap(),
-            };
-
-            let txids: Vec<String> = block_data["txids"]
-                .as_array()
-                .unwrap()
-                .iter()
-                .map(|v| v.as_str().unwrap().to_string())
-                .collect();
-
-            let block_detail = BlockDetail {
-                prev_hash: block_data["prev_hash"].as_str().unwrap().to_string(),
-                merkle_root: block_data["merkle_root"].as_str().unwrap().to_string(),
-                bits: block_data["bits"].as_u64().unwrap() as u32,
-                nonce: block_data["nonce"].as_u64().unwrap() as u32,
-                proof_url: format!("/v1/blocks/{}/proof", summary.height),
-                txids: txids.clone(),
-                summary: summary.clone(),
-            };
-
-            for txid in &txids {
-                tx_index.insert(txid.clone(), summary.height);
+            let height = block_data["height"].as_u64().unwrap() as u32;
+            let parent = height
+                .checked_sub(1)
+                .and_then(|height| blocks_by_height.get(&height))
+                .filter(|parent| block_data["prev_hash"] == parent.summary.hash.to_string());
+            let block_detail = parse_block(&block_data, parent);
+            let summary = &block_detail.summary;
+
+            for txid in &block_detail.txids {
+                tx_index.insert(*txid, summary.height);
             }
 
-            header_index.insert(summary.hash.clone(), summary.height);
+            header_index.insert(summary.hash, summary.height);
 
             blocks_by_height.insert(summary.height, block_detail.clone());
-            blocks_by_hash.insert(summary.hash.clone(), block_detail);
+            blocks_by_hash.insert(summary.hash, block_detail);
         }
 
         Ok(Self {
@@ -78,37 +517,65 @@ impl MockStore {
     }
 
     pub fn get_blocks(&self, limit: u32, cursor: Option<u32>) -> BlocksResponse {
-        let mut blocks: Vec<_> = self.blocks_by_height.values().collect();
-        blocks.sort_by(|a, b| b.summary.height.cmp(&a.summary.height));
+        self.page_blocks(
+            limit,
+            cursor,
+            PageDirection::Next,
+            SortOrder::Desc,
+            &BlockFilter::default(),
+        )
+    }
+
+    fn page_blocks(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        direction: PageDirection,
+        order: SortOrder,
+        filter: &BlockFilter,
+    ) -> BlocksResponse {
+        let mut blocks: Vec<_> = self
+            .blocks_by_height
+            .values()
+            .filter(|b| filter.verified.is_none_or(|v| b.summary.verified == v))
+            .filter(|b| {
+                filter
+                    .from_timestamp
+                    .is_none_or(|t| b.summary.timestamp >= t)
+            })
+            .filter(|b| filter.to_timestamp.is_none_or(|t| b.summary.timestamp <= t))
+            .collect();
+        blocks.sort_by_key(|b| b.summary.height);
+        let travel = direction.travel(order);
+        if travel == SortOrder::Desc {
+            blocks.reverse();
+        }
 
         let start_idx = if let Some(cursor) = cursor {
             blocks
                 .iter()
-                .position(|b| b.summary.height < cursor)
+                .position(|b| match travel {
+                    SortOrder::Desc => b.summary.height < cursor,
+                    SortOrder::Asc => b.summary.height > cursor,
+                })
                 .unwrap_or(blocks.len())
         } else {
             0
         };
 
-        let end_idx = std::cmp::min(start_idx + limit as usize, blocks.len());
+        let end_idx = std::cmp::min(start_idx + limit as usize + 1, blocks.len());
         let selected_blocks: Vec<BlockSummary> = blocks[start_idx..end_idx]
             .iter()
             .map(|b| b.summary.clone())
             .collect();
 
-        let has_next = end_idx < blocks.len();
-        let next_cursor = if has_next {
-            selected_blocks.last().map(|b| b.height)
-        } else {
-            None
-        };
-
-        BlocksResponse {
-            blocks: selected_blocks,
-            total: self.blocks_by_height.len() as u32,
-            has_next,
-            next_cursor,
-        }
+        BlocksResponse::page(
+            selected_blocks,
+            limit as usize,
+            cursor,
+            direction,
+            blocks.len() as u32,
+        )
     }
 
     pub fn get_block_by_height(&self, height: u32) -> Result<&BlockDetail> {
@@ -117,7 +584,7 @@ impl MockStore {
             .ok_or_else(|| AppError::BlockNotFound(height.to_string()))
     }
 
-    pub fn get_block_by_hash(&self, hash: &str) -> Result<&BlockDetail> {
+    pub fn get_block_by_hash(&self, hash: &BlockHash) -> Result<&BlockDetail> {
         self.blocks_by_hash
             .get(hash)
             .ok_or_else(|| AppError::BlockNotFound(hash.to_string()))
@@ -128,8 +595,8 @@ impl MockStore {
             return Err(AppError::BlockNotFound(height.to_string()));
         }
 
-        let proof_path = format!("data/proofs/{height}.json");
-        if !Path::new(&proof_path).exists() {
+        let proof_path = Self::proof_path(height);
+        if !proof_path.exists() {
             return Err(AppError::ProofNotFound(height.to_string()));
         }
 
@@ -139,41 +606,353 @@ impl MockStore {
         Ok(contents)
     }
 
-    pub fn get_transaction_status(&self, txid: &str) -> Result<TransactionStatus> {
+    pub fn get_transaction_status(&self, txid: &Txid) -> Result<TransactionStatus> {
         if let Some(&block_height) = self.tx_index.get(txid) {
+            let tip = self
+                .blocks_by_height
+                .keys()
+                .max()
+                .copied()
+                .unwrap_or(block_height);
+            let block = self.blocks_by_height.get(&block_height);
+            let has_proof = Self::proof_path(block_height).exists();
             Ok(TransactionStatus {
                 included: true,
                 block_height: Some(block_height),
+                block_hash: block.map(|block| block.summary.hash),
+                confirmations: Some(tip - block_height + 1),
+                proof_url: has_proof.then(|| format!("/v1/blocks/{block_height}/proof")),
+                proof_verified: block
+                    .filter(|_| has_proof)
+                    .map(|block| block.summary.verified),
             })
         } else {
             Ok(TransactionStatus {
                 included: false,
                 block_height: None,
+                block_hash: None,
+                confirmations: None,
+                proof_url: None,
+                proof_verified: None,
             })
         }
     }
 
-    pub fn get_header_status(&self, hash: &str) -> Result<HeaderStatus> {
+    pub fn get_header_status(&self, hash: &BlockHash) -> Result<HeaderStatus> {
+        // The mock store has no reorgs, so every known header is active
         if let Some(&block_height) = self.header_index.get(hash) {
+            let tip = self
+                .blocks_by_height
+                .keys()
+                .max()
+                .copied()
+                .unwrap_or(block_height);
+            let has_proof = Self::proof_path(block_height).exists();
             Ok(HeaderStatus {
+                block_hash: *hash,
                 in_chain: true,
                 block_height: Some(block_height),
+                confirmations: Some(tip - block_height + 1),
+                verified: has_proof
+                    && self
+                        .blocks_by_height
+                        .get(&block_height)
+                        .is_some_and(|block| block.summary.verified),
+                on_active_chain: true,
             })
         } else {
             Ok(HeaderStatus {
+                block_hash: *hash,
                 in_chain: false,
                 block_height: None,
+                confirmations: None,
+                verified: false,
+                on_active_chain: false,
             })
         }
     }
 
-    pub fn block_exists_by_identifier(&self, identifier: &str) -> bool {
-        if let Ok(height) = identifier.parse::<u32>() {
-            self.blocks_by_height.contains_key(&height)
+    pub fn block_exists_by_identifier(&self, id: &BlockId) -> bool {
+        match id {
+            BlockId::Height(height) => self.blocks_by_height.contains_key(height),
+            BlockId::Hash(hash) => self.blocks_by_hash.contains_key(hash),
+        }
+    }
+
+    fn proof_path(height: u32) -> PathBuf {
+        PathBuf::from(format!("data/proofs/{height}.json"))
+    }
+
+    fn tip(&self) -> Option<u32> {
+        self.blocks_by_height.keys().max().copied()
+    }
+
+    fn proved_heights(&self) -> Vec<u32> {
+        let mut heights: Vec<u32> = self
+            .blocks_by_height
+            .keys()
+            .copied()
+            .filter(|&h| Self::proof_path(h).exists())
+            .collect();
+        heights.sort_unstable();
+        heights
+    }
+}
+
+// The fixtures carry blocks, txids and proof files only; lookups that need
+// transaction details, ingest times or an address index answer as a
+// database would when that data was never ingested.
+#[async_trait]
+impl BlockStore for MockStore {
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn tip_height(&self) -> Result<Option<u32>> {
+        Ok(self.tip())
+    }
+
+    async fn get_blocks(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        direction: PageDirection,
+        order: SortOrder,
+        filter: &BlockFilter,
+    ) -> Result<BlocksResponse> {
+        Ok(self.page_blocks(limit.min(50), cursor, direction, order, filter))
+    }
+
+    async fn get_orphans(&self, _limit: u32, _cursor: Option<i64>) -> Result<OrphansResponse> {
+        // Fixtures never reorg
+        Ok(OrphansResponse {
+            orphans: Vec::new(),
+            has_next: false,
+            next_cursor: None,
+        })
+    }
+
+    async fn block_exists_by_identifier(&self, id: &BlockId) -> Result<bool> {
+        Ok(MockStore::block_exists_by_identifier(self, id))
+    }
+
+    async fn get_block_by_height(&self, height: u32) -> Result<BlockDetail> {
+        MockStore::get_block_by_height(self, height).cloned()
+    }
+
+    async fn get_block_by_hash(&self, hash: &BlockHash) -> Result<BlockDetail> {
+        MockStore::get_block_by_hash(self, hash).cloned()
+    }
+
+    async fn get_block_stats(&self, height: u32) -> Result<BlockStats> {
+        MockStore::get_block_by_height(self, height)?;
+        Err(AppError::BlockStatsUnavailable(height.to_string()))
+    }
+
+    async fn get_block_txids(
+        &self,
+        height: u32,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<BlockTxidsResponse> {
+        let block = MockStore::get_block_by_height(self, height)?;
+        let start = cursor.map_or(0, |c| c as usize + 1);
+        let txids: Vec<Txid> = block
+            .txids
+            .iter()
+            .skip(start)
+            .take(limit.min(1000) as usize)
+            .copied()
+            .collect();
+        let end = start + txids.len();
+        let has_next = end < block.txids.len();
+
+        Ok(BlockTxidsResponse {
+            height,
+            txids,
+            total: block.txids.len() as u32,
+            has_next,
+            next_cursor: has_next.then(|| end as u32 - 1),
+        })
+    }
+
+    async fn get_transaction_status(&self, txid: &Txid) -> Result<TransactionStatus> {
+        MockStore::get_transaction_status(self, txid)
+    }
+
+    async fn get_transaction_detail(&self, txid: &Txid) -> Result<TransactionDetail> {
+        Err(AppError::TransactionNotFound(txid.to_string()))
+    }
+
+    async fn get_raw_transaction(&self, txid: &Txid) -> Result<Vec<u8>> {
+        if self.tx_index.contains_key(txid) {
+            Err(AppError::RawTransactionUnavailable(txid.to_string()))
         } else {
-            self.blocks_by_hash.contains_key(identifier)
+            Err(AppError::TransactionNotFound(txid.to_string()))
         }
     }
+
+    async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<OutputStatus> {
+        Err(AppError::OutputNotFound(format!("{txid}:{vout}")))
+    }
+
+    fn address_index_enabled(&self) -> bool {
+        false
+    }
+
+    async fn get_address_transactions(
+        &self,
+        _script_pubkey: &str,
+    ) -> Result<Vec<AddressTransaction>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_address_utxos(&self, _script_pubkey: &str) -> Result<Vec<Utxo>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_header_status(&self, hash: &BlockHash) -> Result<HeaderStatus> {
+        MockStore::get_header_status(self, hash)
+    }
+
+    async fn get_network_stats(&self) -> Result<NetworkStats> {
+        let proved = self.proved_heights();
+        let block_count = self.blocks_by_height.len();
+        let sizes: Vec<f64> = proved
+            .iter()
+            .filter_map(|&h| fs::metadata(Self::proof_path(h)).ok())
+            .map(|m| m.len() as f64)
+            .collect();
+
+        Ok(NetworkStats {
+            indexed_height: self.tip(),
+            proved_height: proved.last().copied(),
+            proof_coverage: if block_count > 0 {
+                proved.len() as f64 * 100.0 / block_count as f64
+            } else {
+                0.0
+            },
+            avg_proof_size: (!sizes.is_empty())
+                .then(|| sizes.iter().sum::<f64>() / sizes.len() as f64),
+            avg_proving_time_ms: None,
+            queue_depth: (block_count - proved.len()) as u32,
+            stale: false,
+        })
+    }
+
+    async fn proof_file_exists(&self, height: u32) -> Result<bool> {
+        Ok(self.blocks_by_height.contains_key(&height) && Self::proof_path(height).exists())
+    }
+
+    async fn get_proofs(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+        verified: Option<bool>,
+        version: Option<&str>,
+    ) -> Result<ProofsResponse> {
+        // Fixture proofs all carry the default prover version
+        const FIXTURE_VERSION: &str = proof::PROOF_VERSION;
+        let limit = limit.min(100) as usize;
+
+        let mut records: Vec<ProofRecord> = self
+            .proved_heights()
+            .into_iter()
+            .filter(|&h| cursor.is_none_or(|c| h > c))
+            .filter(|_| version.is_none_or(|v| v == FIXTURE_VERSION))
+            .filter_map(|height| {
+                let block = self.blocks_by_height.get(&height)?;
+                if verified.is_some_and(|v| block.summary.verified != v) {
+                    return None;
+                }
+                let metadata = fs::metadata(Self::proof_path(height)).ok()?;
+                let generated_at = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_secs() as i64);
+                Some(ProofRecord {
+                    height,
+                    version: FIXTURE_VERSION.to_string(),
+                    size: Some(metadata.len() as i64),
+                    verified: block.summary.verified,
+                    generated_at,
+                    proof_url: block.proof_url.clone(),
+                })
+            })
+            .take(limit + 1)
+            .collect();
+
+        let has_next = records.len() > limit;
+        records.truncate(limit);
+        let next_cursor = if has_next {
+            records.last().map(|r| r.height)
+        } else {
+            None
+        };
+
+        Ok(ProofsResponse {
+            proofs: records,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    async fn get_proof_coverage(
+        &self,
+        limit: u32,
+        cursor: Option<u32>,
+    ) -> Result<ProofCoverageResponse> {
+        let mut heights: Vec<u32> = self
+            .blocks_by_height
+            .keys()
+            .copied()
+            .filter(|&h| h >= cursor.unwrap_or(0) && !Self::proof_path(h).exists())
+            .collect();
+        heights.sort_unstable();
+
+        let mut gaps: Vec<ProofGap> = Vec::new();
+        for height in heights {
+            match gaps.last_mut() {
+                Some(gap) if gap.end_height + 1 == height => {
+                    gap.end_height = height;
+                    gap.count += 1;
+                }
+                _ => gaps.push(ProofGap {
+                    start_height: height,
+                    end_height: height,
+                    count: 1,
+                    reason: ProofGapReason::NeverAttempted,
+                }),
+            }
+        }
+
+        gaps.truncate(limit as usize + 1);
+        let has_next = gaps.len() > limit as usize;
+        let next_cursor = if has_next {
+            gaps.pop().map(|gap| gap.start_height)
+        } else {
+            None
+        };
+
+        Ok(ProofCoverageResponse {
+            gaps,
+            has_next,
+            next_cursor,
+        })
+    }
+
+    async fn get_proof_latencies(&self, _window: u32) -> Result<Vec<i64>> {
+        Ok(Vec::new())
+    }
+
+    async fn count_unproved_older_than(&self, _secs: i64) -> Result<u32> {
+        Ok(0)
+    }
+
+    async fn proof_backlog(&self) -> Result<ProofBacklog> {
+        Ok(ProofBacklog::default())
+    }
 }
 
 #[cfg(test)]
@@ -212,6 +991,8 @@ mod tests {
             let status = store.get_transaction_status(txid).unwrap();
             assert!(status.included);
             assert!(status.block_height.is_some());
+            assert!(status.block_hash.is_some());
+            assert!(status.confirmations.unwrap() >= 1);
         }
     }
 }
@@ -0,0 +1,534 @@
+use crate::storage::StorageUsage;
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use once_cell::sync::Lazy;
+use prometheus_client::{
+    encoding::{text::encode, EncodeLabelSet},
+    metrics::{
+        counter::Counter,
+        exemplar::HistogramWithExemplars,
+        family::Family,
+        gauge::Gauge,
+        histogram::{exponential_buckets, Histogram},
+    },
+    registry::Registry,
+};
+use std::time::Duration;
+use tracing::{info_span, Instrument};
+
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+tokio::task_local! {
+    static TRACE_ID: TraceId;
+}
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// W3C trace id of the request being served, taken from an incoming
+/// `traceparent` header or generated when the caller did not send one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceId(pub String);
+
+impl TraceId {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get("traceparent")
+            .and_then(|v| v.to_str().ok())
+            .and_then(Self::from_traceparent)
+            .unwrap_or_else(Self::generate)
+    }
+
+    fn from_traceparent(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.split('-');
+        let _version = parts.next()?;
+        let trace_id = parts.next()?;
+        let valid = trace_id.len() == 32
+            && trace_id.chars().all(|c| c.is_ascii_hexdigit())
+            && trace_id.chars().any(|c| c != '0');
+        valid.then(|| Self(trace_id.to_ascii_lowercase()))
+    }
+
+    fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().simple().to_string())
+    }
+
+    /// Trace id of the request currently being served on this task, if any.
+    pub fn current() -> Option<Self> {
+        TRACE_ID.try_with(Clone::clone).ok()
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct RouteLabels {
+    pub method: String,
+    pub route: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct LatencyLabels {
+    pub method: String,
+    pub route: String,
+    /// `2xx`, `4xx`, ...
+    pub status_class: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ErrorLabels {
+    pub method: String,
+    pub route: String,
+    /// [`AppError::kind`](crate::error::AppError::kind) of the response.
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ResponseLabels {
+    pub method: String,
+    pub route: String,
+    /// Numeric status code, e.g. `200`.
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct QueryLabels {
+    /// Name the database layer gives the query, e.g. `list_blocks`.
+    pub query: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct DeprecationLabels {
+    pub feature: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct RejectionLabels {
+    /// [`Rejection::reason`](crate::validation::Rejection::reason).
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct CacheLabels {
+    /// `hit` or `miss`.
+    pub result: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ShedLabels {
+    /// The concurrency limit that turned the request away.
+    pub limit: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct TraceExemplar {
+    pub trace_id: String,
+}
+
+type LatencyHistogram = HistogramWithExemplars<TraceExemplar>;
+
+fn latency_histogram() -> LatencyHistogram {
+    // 1ms .. ~16s
+    HistogramWithExemplars::new(exponential_buckets(0.001, 2.0, 15))
+}
+
+fn query_histogram() -> LatencyHistogram {
+    // 0.1ms .. ~3s
+    HistogramWithExemplars::new(exponential_buckets(0.0001, 2.0, 16))
+}
+
+/// Process-wide metrics registry, exposed in OpenMetrics format so latency
+/// buckets carry trace-id exemplars that link to the slow request's trace.
+pub struct Metrics {
+    registry: Registry,
+    http_request_duration: Family<LatencyLabels, LatencyHistogram, fn() -> LatencyHistogram>,
+    http_requests: Family<ResponseLabels, Counter>,
+    http_requests_in_flight: Family<RouteLabels, Gauge>,
+    http_errors: Family<ErrorLabels, Counter>,
+    db_query_duration: Family<QueryLabels, LatencyHistogram, fn() -> LatencyHistogram>,
+    db_query_rows: Family<QueryLabels, Counter>,
+    db_query_errors: Family<QueryLabels, Counter>,
+    deprecated_requests: Family<DeprecationLabels, Counter>,
+    rejected_headers: Family<RejectionLabels, Counter>,
+    proof_latency: Histogram,
+    proof_generation_latency: Histogram,
+    proof_verification_latency: Histogram,
+    proof_backlog_blocks: Gauge,
+    proof_backlog_age: Gauge,
+    proof_storage_bytes: Gauge,
+    storage_available_bytes: Gauge,
+    proof_sla_breaches: Counter,
+    block_cache_requests: Family<CacheLabels, Counter>,
+    shed_requests: Family<ShedLabels, Counter>,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let mut registry = Registry::with_prefix("raito");
+
+        let http_request_duration = Family::<
+            LatencyLabels,
+            LatencyHistogram,
+            fn() -> LatencyHistogram,
+        >::new_with_constructor(latency_histogram);
+        registry.register(
+            "http_request_duration_seconds",
+            "HTTP request latency by route and status class",
+            http_request_duration.clone(),
+        );
+
+        let http_requests = Family::<ResponseLabels, Counter>::default();
+        registry.register(
+            "http_requests",
+            "HTTP requests served by route and response status",
+            http_requests.clone(),
+        );
+
+        let http_requests_in_flight = Family::<RouteLabels, Gauge>::default();
+        registry.register(
+            "http_requests_in_flight",
+            "HTTP requests currently being served by route",
+            http_requests_in_flight.clone(),
+        );
+
+        let http_errors = Family::<ErrorLabels, Counter>::default();
+        registry.register(
+            "http_errors",
+            "Error responses by route and error kind",
+            http_errors.clone(),
+        );
+
+        let db_query_duration =
+            Family::<QueryLabels, LatencyHistogram, fn() -> LatencyHistogram>::new_with_constructor(
+                query_histogram,
+            );
+        registry.register(
+            "db_query_duration_seconds",
+            "Database query latency by query",
+            db_query_duration.clone(),
+        );
+
+        let db_query_rows = Family::<QueryLabels, Counter>::default();
+        registry.register(
+            "db_query_rows",
+            "Rows returned or changed by database queries",
+            db_query_rows.clone(),
+        );
+
+        let db_query_errors = Family::<QueryLabels, Counter>::default();
+        registry.register(
+            "db_query_errors",
+            "Database queries that failed",
+            db_query_errors.clone(),
+        );
+
+        let deprecated_requests = Family::<DeprecationLabels, Counter>::default();
+        registry.register(
+            "deprecated_feature_requests",
+            "Requests served by a deprecated endpoint or field",
+            deprecated_requests.clone(),
+        );
+
+        let rejected_headers = Family::<RejectionLabels, Counter>::default();
+        registry.register(
+            "rejected_headers",
+            "Ingested block headers refused by validation, by reason",
+            rejected_headers.clone(),
+        );
+
+        // 1min .. ~34h
+        let proof_latency = Histogram::new(exponential_buckets(60.0, 2.0, 12));
+        registry.register(
+            "proof_latency_seconds",
+            "Time from block ingestion to proof availability",
+            proof_latency.clone(),
+        );
+
+        let proof_generation_latency = Histogram::new(exponential_buckets(60.0, 2.0, 12));
+        registry.register(
+            "proof_generation_latency_seconds",
+            "Time from block ingestion to the prover finishing its proof",
+            proof_generation_latency.clone(),
+        );
+
+        let proof_verification_latency = Histogram::new(exponential_buckets(60.0, 2.0, 12));
+        registry.register(
+            "proof_verification_latency_seconds",
+            "Time from block ingestion to the block being marked verified",
+            proof_verification_latency.clone(),
+        );
+
+        let proof_backlog_blocks = Gauge::default();
+        registry.register(
+            "proof_backlog_blocks",
+            "Indexed blocks still waiting for a proof",
+            proof_backlog_blocks.clone(),
+        );
+
+        let proof_backlog_age = Gauge::default();
+        registry.register(
+            "proof_backlog_age_seconds",
+            "How long the oldest block waiting for a proof has waited",
+            proof_backlog_age.clone(),
+        );
+
+        let proof_storage_bytes = Gauge::default();
+        registry.register(
+            "proof_storage_bytes",
+            "Total size of the stored proof files",
+            proof_storage_bytes.clone(),
+        );
+
+        let storage_available_bytes = Gauge::default();
+        registry.register(
+            "storage_available_bytes",
+            "Free space left on the disk holding the proofs",
+            storage_available_bytes.clone(),
+        );
+
+        let proof_sla_breaches = Counter::default();
+        registry.register(
+            "proof_sla_breaches",
+            "Proofs that became available later than the configured SLA",
+            proof_sla_breaches.clone(),
+        );
+
+        let block_cache_requests = Family::<CacheLabels, Counter>::default();
+        registry.register(
+            "block_cache_requests",
+            "Block lookups answered from the in-process cache (hit) or the store (miss)",
+            block_cache_requests.clone(),
+        );
+
+        let shed_requests = Family::<ShedLabels, Counter>::default();
+        registry.register(
+            "shed_requests",
+            "Requests rejected because a concurrency limit was reached",
+            shed_requests.clone(),
+        );
+
+        Self {
+            registry,
+            http_request_duration,
+            http_requests,
+            http_requests_in_flight,
+            http_errors,
+            db_query_duration,
+            db_query_rows,
+            db_query_errors,
+            deprecated_requests,
+            rejected_headers,
+            proof_latency,
+            proof_generation_latency,
+            proof_verification_latency,
+            proof_backlog_blocks,
+            proof_backlog_age,
+            proof_storage_bytes,
+            storage_available_bytes,
+            proof_sla_breaches,
+            block_cache_requests,
+            shed_requests,
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &METRICS
+    }
+
+    pub fn observe_http_request(
+        &self,
+        labels: RouteLabels,
+        status: StatusCode,
+        duration: Duration,
+    ) {
+        self.http_requests
+            .get_or_create(&ResponseLabels {
+                method: labels.method.clone(),
+                route: labels.route.clone(),
+                status: status.as_u16().to_string(),
+            })
+            .inc();
+        self.http_request_duration
+            .get_or_create(&LatencyLabels {
+                method: labels.method,
+                route: labels.route,
+                status_class: format!("{}xx", status.as_u16() / 100),
+            })
+            .observe(duration.as_secs_f64(), exemplar());
+    }
+
+    /// Counts the request as in flight on its route until the returned
+    /// guard is dropped, including when the request is cancelled.
+    pub fn track_in_flight(&self, labels: &RouteLabels) -> InFlightRequest {
+        let gauge = self.http_requests_in_flight.get_or_create(labels).clone();
+        gauge.inc();
+        InFlightRequest(gauge)
+    }
+
+    pub fn record_http_error(&self, labels: &RouteLabels, kind: &str) {
+        self.http_errors
+            .get_or_create(&ErrorLabels {
+                method: labels.method.clone(),
+                route: labels.route.clone(),
+                error: kind.to_string(),
+            })
+            .inc();
+    }
+
+    /// `rows` is the number of rows the query returned or changed, or `Err`
+    /// when it failed.
+    pub fn observe_db_query(
+        &self,
+        query: &str,
+        duration: Duration,
+        rows: std::result::Result<u64, ()>,
+    ) {
+        let labels = QueryLabels {
+            query: query.to_string(),
+        };
+        self.db_query_duration
+            .get_or_create(&labels)
+            .observe(duration.as_secs_f64(), exemplar());
+        match rows {
+            Ok(rows) => {
+                self.db_query_rows.get_or_create(&labels).inc_by(rows);
+            }
+            Err(()) => {
+                self.db_query_errors.get_or_create(&labels).inc();
+            }
+        }
+    }
+
+    pub fn record_deprecated_use(&self, feature: &str) {
+        self.deprecated_requests
+            .get_or_create(&DeprecationLabels {
+                feature: feature.to_string(),
+            })
+            .inc();
+    }
+
+    pub fn record_rejected_header(&self, reason: &str) {
+        self.rejected_headers
+            .get_or_create(&RejectionLabels {
+                reason: reason.to_string(),
+            })
+            .inc();
+    }
+
+    pub fn observe_proof_latency(&self, latency_secs: i64) {
+        self.proof_latency.observe(latency_secs.max(0) as f64);
+    }
+
+    pub fn observe_proof_generation_latency(&self, latency_secs: i64) {
+        self.proof_generation_latency
+            .observe(latency_secs.max(0) as f64);
+    }
+
+    pub fn observe_proof_verification_latency(&self, latency_secs: i64) {
+        self.proof_verification_latency
+            .observe(latency_secs.max(0) as f64);
+    }
+
+    pub fn set_proof_backlog(&self, blocks: u32, oldest_age_secs: i64) {
+        self.proof_backlog_blocks.set(blocks as i64);
+        self.proof_backlog_age.set(oldest_age_secs);
+    }
+
+    pub fn set_storage_usage(&self, usage: &StorageUsage) {
+        self.proof_storage_bytes
+            .set(i64::try_from(usage.proof_bytes).unwrap_or(i64::MAX));
+        self.storage_available_bytes
+            .set(i64::try_from(usage.available_bytes).unwrap_or(i64::MAX));
+    }
+
+    pub fn record_proof_sla_breach(&self) {
+        self.proof_sla_breaches.inc();
+    }
+
+    pub fn record_block_cache(&self, hit: bool) {
+        self.block_cache_requests
+            .get_or_create(&CacheLabels {
+                result: if hit { "hit" } else { "miss" }.to_string(),
+            })
+            .inc();
+    }
+
+    pub fn record_shed_request(&self, limit: &str) {
+        self.shed_requests
+            .get_or_create(&ShedLabels {
+                limit: limit.to_string(),
+            })
+            .inc();
+    }
+
+    pub fn render(&self) -> String {
+        let mut buffer = String::new();
+        encode(&mut buffer, &self.registry).expect("writing to a String cannot fail");
+        buffer
+    }
+}
+
+pub struct InFlightRequest(Gauge);
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+fn exemplar() -> Option<TraceExemplar> {
+    TraceId::current().map(|TraceId(trace_id)| TraceExemplar { trace_id })
+}
+
+/// Establishes the trace id for the request: it is recorded on the request
+/// span, made available to metrics as an exemplar, and echoed back to the
+/// caller in `X-Trace-Id`.
+pub async fn trace_context_middleware(mut request: Request, next: Next) -> Response {
+    let trace_id = TraceId::from_headers(request.headers());
+    request.extensions_mut().insert(trace_id.clone());
+
+    let span = info_span!("request", trace_id = %trace_id.0);
+    let mut response = TRACE_ID
+        .scope(trace_id.clone(), next.run(request).instrument(span))
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&trace_id.0) {
+        response.headers_mut().insert(TRACE_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_id_from_traceparent() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            TraceId::from_headers(&headers).0,
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+
+        headers.insert(
+            "traceparent",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(TraceId::from_headers(&headers).0.len(), 32);
+        assert_ne!(
+            TraceId::from_headers(&headers).0,
+            "00000000000000000000000000000000"
+        );
+    }
+}
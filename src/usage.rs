@@ -0,0 +1,357 @@
+use crate::{
+    auth::ApiKeyIdentity,
+    error::{AppError, Result},
+    model::{UsageCounts, UsageResponse, UsageWindow},
+    store::BlockStore,
+};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use futures_util::future::BoxFuture;
+use http_body::{Frame, SizeHint};
+use once_cell::sync::Lazy;
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+use tracing::warn;
+
+static QUOTA_CONFIG: Lazy<QuotaConfig> = Lazy::new(QuotaConfig::from_env);
+
+/// Per-key allowances; `None` is unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct QuotaConfig {
+    pub daily_requests: Option<u64>,
+    pub monthly_requests: Option<u64>,
+    pub daily_proof_bytes: Option<u64>,
+    pub monthly_proof_bytes: Option<u64>,
+}
+
+impl QuotaConfig {
+    pub fn from_env() -> Self {
+        // Unset and 0 both mean unlimited
+        let limit = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&limit: &u64| limit > 0)
+        };
+        Self {
+            daily_requests: limit("QUOTA_DAILY_REQUESTS"),
+            monthly_requests: limit("QUOTA_MONTHLY_REQUESTS"),
+            daily_proof_bytes: limit("QUOTA_DAILY_PROOF_BYTES"),
+            monthly_proof_bytes: limit("QUOTA_MONTHLY_PROOF_BYTES"),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &QUOTA_CONFIG
+    }
+
+    fn is_limited(&self) -> bool {
+        self.daily_requests.is_some()
+            || self.monthly_requests.is_some()
+            || self.daily_proof_bytes.is_some()
+            || self.monthly_proof_bytes.is_some()
+    }
+
+    /// Refuses a request once a window's allowance is spent. Proof byte
+    /// allowances only hold back proof downloads.
+    pub fn check(
+        &self,
+        daily: UsageCounts,
+        monthly: UsageCounts,
+        proof_download: bool,
+        period: &Period,
+        now: i64,
+    ) -> Result<()> {
+        let windows = [
+            (
+                "daily",
+                daily,
+                self.daily_requests,
+                self.daily_proof_bytes,
+                period.day_resets_at,
+            ),
+            (
+                "monthly",
+                monthly,
+                self.monthly_requests,
+                self.monthly_proof_bytes,
+                period.month_resets_at,
+            ),
+        ];
+        for (window, used, requests, proof_bytes, resets_at) in windows {
+            let exceeded = if requests.is_some_and(|limit| used.requests >= limit) {
+                Some("requests")
+            } else if proof_download && proof_bytes.is_some_and(|limit| used.proof_bytes >= limit) {
+                Some("proof bytes")
+            } else {
+                None
+            };
+            if let Some(resource) = exceeded {
+                return Err(AppError::QuotaExceeded {
+                    quota: format!("{window} {resource}"),
+                    retry_after_secs: (resets_at - now).max(1) as u64,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn report(
+        &self,
+        identity: &ApiKeyIdentity,
+        daily: UsageCounts,
+        monthly: UsageCounts,
+        period: &Period,
+    ) -> UsageResponse {
+        UsageResponse {
+            api_key_id: identity.id,
+            name: identity.name.clone(),
+            daily: UsageWindow {
+                used: daily,
+                request_limit: self.daily_requests,
+                proof_bytes_limit: self.daily_proof_bytes,
+                resets_at: period.day_resets_at,
+            },
+            monthly: UsageWindow {
+                used: monthly,
+                request_limit: self.monthly_requests,
+                proof_bytes_limit: self.monthly_proof_bytes,
+                resets_at: period.month_resets_at,
+            },
+        }
+    }
+}
+
+/// The UTC day and month usage is counted in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Period {
+    /// `YYYY-MM-DD`.
+    pub day: String,
+    /// First day of the month, `YYYY-MM-01`.
+    pub month_start: String,
+    pub day_resets_at: i64,
+    pub month_resets_at: i64,
+}
+
+impl Period {
+    pub fn at(now: DateTime<Utc>) -> Self {
+        let today = now.date_naive();
+        let first = NaiveDate::from_ymd_opt(today.year(), today.month(), 1)
+            .expect("first of the month is a valid date");
+        let midnight = |date: NaiveDate| {
+            date.and_hms_opt(0, 0, 0)
+                .expect("midnight is a valid time")
+                .and_utc()
+                .timestamp()
+        };
+        Self {
+            day: today.format("%Y-%m-%d").to_string(),
+            month_start: first.format("%Y-%m-%d").to_string(),
+            day_resets_at: midnight(today + Days::new(1)),
+            month_resets_at: midnight(first + Months::new(1)),
+        }
+    }
+}
+
+/// Usage of the key a request authenticated with.
+pub async fn current_usage(
+    db: &dyn BlockStore,
+    identity: &ApiKeyIdentity,
+) -> Result<UsageResponse> {
+    let period = Period::at(Utc::now());
+    let (daily, monthly) = db
+        .get_api_key_usage(identity.id, &period.day, &period.month_start)
+        .await?;
+    Ok(QuotaConfig::global().report(identity, daily, monthly, &period))
+}
+
+/// Enforces the quotas of requests made with an API key and counts them,
+/// along with the proof bytes each download serves. `/usage` itself is
+/// neither limited nor counted, so integrators can always check it.
+pub async fn usage_middleware(
+    State(db): State<Arc<dyn BlockStore>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let Some(identity) = request.extensions().get::<ApiKeyIdentity>().cloned() else {
+        return Ok(next.run(request).await);
+    };
+    let path = request.uri().path();
+    if path == "/usage" {
+        return Ok(next.run(request).await);
+    }
+    let proof_download = request.method() == Method::GET && path.ends_with("/proof");
+
+    let now = Utc::now();
+    let period = Period::at(now);
+    let quotas = QuotaConfig::global();
+    if quotas.is_limited() {
+        let (daily, monthly) = db
+            .get_api_key_usage(identity.id, &period.day, &period.month_start)
+            .await?;
+        quotas.check(daily, monthly, proof_download, &period, now.timestamp())?;
+    }
+
+    let response = next.run(request).await;
+    let recorder = Recorder {
+        db,
+        api_key_id: identity.id,
+        day: period.day,
+    };
+    if proof_download && response.status().is_success() {
+        // Streamed proofs have no length up front, so the bytes are counted
+        // as they are sent and the download recorded once they are
+        return Ok(response.map(|body| Body::new(MeteredBody::new(body, recorder))));
+    }
+    recorder.record(0).await;
+    Ok(response)
+}
+
+/// Records one request made with a key, and the proof bytes it served.
+struct Recorder {
+    db: Arc<dyn BlockStore>,
+    api_key_id: i64,
+    day: String,
+}
+
+impl Recorder {
+    async fn record(self, proof_bytes: u64) {
+        let usage = UsageCounts {
+            requests: 1,
+            proof_bytes,
+        };
+        if let Err(e) = self
+            .db
+            .record_api_key_usage(self.api_key_id, &self.day, usage)
+            .await
+        {
+            warn!(api_key_id = self.api_key_id, error = %e, "Failed to record API key usage");
+        }
+    }
+}
+
+/// A proof download that counts the bytes it sends. The usage is recorded
+/// before the body ends, so it is in place by the time the client has the
+/// whole proof, or when the body is dropped early because the client went
+/// away, counting what was sent until then.
+struct MeteredBody {
+    inner: Body,
+    sent: u64,
+    recorder: Option<Recorder>,
+    recording: Option<BoxFuture<'static, ()>>,
+}
+
+impl MeteredBody {
+    fn new(inner: Body, recorder: Recorder) -> Self {
+        Self {
+            inner,
+            sent: 0,
+            recorder: Some(recorder),
+            recording: None,
+        }
+    }
+}
+
+impl http_body::Body for MeteredBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Bytes>, axum::Error>>> {
+        let this = &mut *self;
+        loop {
+            if let Some(recording) = this.recording.as_mut() {
+                ready!(recording.as_mut().poll(cx));
+                this.recording = None;
+                return Poll::Ready(None);
+            }
+            match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => {
+                    if let Some(data) = frame.data_ref() {
+                        this.sent += data.len() as u64;
+                    }
+                    return Poll::Ready(Some(Ok(frame)));
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => match this.recorder.take() {
+                    Some(recorder) => this.recording = Some(Box::pin(recorder.record(this.sent))),
+                    None => return Poll::Ready(None),
+                },
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.recorder.is_none() && self.recording.is_none() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for MeteredBody {
+    fn drop(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(recorder.record(self.sent));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_quota_windows() {
+        let now = Utc.with_ymd_and_hms(2026, 12, 31, 23, 0, 0).unwrap();
+        let period = Period::at(now);
+        assert_eq!(period.day, "2026-12-31");
+        assert_eq!(period.month_start, "2026-12-01");
+        assert_eq!(period.day_resets_at - now.timestamp(), 3600);
+        assert_eq!(period.month_resets_at, period.day_resets_at);
+
+        let config = QuotaConfig {
+            daily_requests: Some(100),
+            monthly_proof_bytes: Some(1000),
+            ..QuotaConfig::default()
+        };
+        let used = |requests, proof_bytes| UsageCounts {
+            requests,
+            proof_bytes,
+        };
+        let check =
+            |daily, monthly, proof| config.check(daily, monthly, proof, &period, now.timestamp());
+
+        assert!(check(used(99, 0), used(99, 999), true).is_ok());
+        match check(used(100, 0), used(100, 0), false) {
+            Err(AppError::QuotaExceeded {
+                quota,
+                retry_after_secs,
+            }) => assert_eq!((quota.as_str(), retry_after_secs), ("daily requests", 3600)),
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // Spent proof bytes only hold back downloads
+        assert!(check(used(1, 0), used(1, 1000), false).is_ok());
+        assert!(matches!(
+            check(used(1, 0), used(1, 1000), true),
+            Err(AppError::QuotaExceeded { .. })
+        ));
+    }
+}
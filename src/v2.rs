@@ -0,0 +1,678 @@
+//! The `/v2` resources: `/v1` models with their breaking fixes applied.
+//! Amounts are integer satoshis throughout, booleans tracking a block
+//! through indexing and proving are folded into [`PipelineStatus`], and
+//! problem documents drop the legacy `error` field. Handlers read the same
+//! store as `/v1` and map its models through the `From` impls here.
+
+use crate::{
+    cache_control::CacheControlConfig,
+    error::Result,
+    etag,
+    handlers::{
+        address_script_pubkey, check_transaction_miss, find_block, list_blocks, list_orphans,
+        list_proofs, resolve_txid,
+    },
+    hash::{BlockHash, BlockId, Txid, Wtxid},
+    model::{
+        self, btc_to_sats, BlockDetailQuery, BlocksQuery, OrphansQuery, ProofsQuery,
+        TransactionInput,
+    },
+    pagination,
+    store::BlockStore,
+};
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// How far a block, or the block containing a transaction, has made it
+/// through the service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStatus {
+    /// Not in an indexed block yet.
+    Pending,
+    /// Indexed without a verified proof. Blocks stay `indexed` until their
+    /// proof verifies.
+    Indexed,
+    /// A proof is available but has not verified.
+    Proved,
+    /// The block's proof verified.
+    Verified,
+}
+
+impl PipelineStatus {
+    fn of_block(verified: bool) -> Self {
+        if verified {
+            Self::Verified
+        } else {
+            Self::Indexed
+        }
+    }
+
+    fn of_proof(verified: bool) -> Self {
+        if verified {
+            Self::Verified
+        } else {
+            Self::Proved
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BlockSummaryV2 {
+    pub height: u32,
+    pub hash: BlockHash,
+    pub tx_count: u32,
+    /// Fees paid by the block's transactions, in satoshis.
+    pub total_fees: i64,
+    pub timestamp: i64,
+    pub status: PipelineStatus,
+}
+
+impl From<model::BlockSummary> for BlockSummaryV2 {
+    fn from(summary: model::BlockSummary) -> Self {
+        Self {
+            height: summary.height,
+            hash: summary.hash,
+            tx_count: summary.tx_count,
+            total_fees: summary.total_fees,
+            timestamp: summary.timestamp,
+            status: PipelineStatus::of_block(summary.verified),
+        }
+    }
+}
+
+/// `/v1`'s `BlockDetail` with the summary's `verified` replaced by `status`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BlockDetailV2 {
+    #[serde(flatten)]
+    pub summary: BlockSummaryV2,
+    pub prev_hash: BlockHash,
+    pub merkle_root: String,
+    pub witness_merkle_root: Option<String>,
+    pub witness_commitment: Option<String>,
+    pub version: i32,
+    pub bits: u32,
+    pub nonce: u32,
+    pub size: Option<u32>,
+    pub stripped_size: Option<u32>,
+    pub weight: Option<u32>,
+    pub difficulty: f64,
+    pub chainwork: Option<String>,
+    /// Empty when requested with `include_txids=false`.
+    pub txids: Vec<Txid>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wtxids: Vec<Wtxid>,
+    pub coinbase: Option<CoinbaseInfoV2>,
+    pub proof_url: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pruned: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub orphaned: bool,
+}
+
+impl From<model::BlockDetail> for BlockDetailV2 {
+    fn from(block: model::BlockDetail) -> Self {
+        Self {
+            summary: block.summary.into(),
+            prev_hash: block.prev_hash,
+            merkle_root: block.merkle_root,
+            witness_merkle_root: block.witness_merkle_root,
+            witness_commitment: block.witness_commitment,
+            version: block.version,
+            bits: block.bits,
+            nonce: block.nonce,
+            size: block.size,
+            stripped_size: block.stripped_size,
+            weight: block.weight,
+            difficulty: block.difficulty,
+            chainwork: block.chainwork,
+            txids: block.txids,
+            wtxids: block.wtxids,
+            coinbase: block.coinbase.map(Into::into),
+            proof_url: block.proof_url,
+            pruned: block.pruned,
+            orphaned: block.orphaned,
+        }
+    }
+}
+
+/// `/v1`'s `CoinbaseInfo` with amounts in satoshis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CoinbaseInfoV2 {
+    pub txid: Txid,
+    /// In satoshis.
+    pub subsidy: i64,
+    /// In satoshis.
+    pub claimed_fees: Option<i64>,
+    pub fees_match: Option<bool>,
+    pub script_sig: Option<String>,
+    pub miner_tag: Option<String>,
+}
+
+impl From<model::CoinbaseInfo> for CoinbaseInfoV2 {
+    fn from(coinbase: model::CoinbaseInfo) -> Self {
+        Self {
+            txid: coinbase.txid,
+            subsidy: coinbase.subsidy,
+            claimed_fees: coinbase.claimed_fees,
+            fees_match: coinbase.fees_match,
+            script_sig: coinbase.script_sig,
+            miner_tag: coinbase.miner_tag,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlocksResponseV2 {
+    pub blocks: Vec<BlockSummaryV2>,
+    pub total: u32,
+    pub has_next: bool,
+    pub next_cursor: Option<u32>,
+    /// Pass with `direction=prev` to get the page before this one.
+    pub prev_cursor: Option<u32>,
+    /// True when the page was shrunk below the requested limit because the
+    /// service is under load.
+    pub server_limited: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u32>,
+}
+
+impl From<model::BlocksResponse> for BlocksResponseV2 {
+    fn from(page: model::BlocksResponse) -> Self {
+        Self {
+            blocks: page.blocks.into_iter().map(Into::into).collect(),
+            total: page.total,
+            has_next: page.has_next,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+            server_limited: page.server_limited,
+            retry_after: page.retry_after,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlockTombstoneV2 {
+    pub height: u32,
+    pub hash: BlockHash,
+    pub replaced_by: Option<BlockHash>,
+    pub reorg_depth: u32,
+    pub orphaned_at: i64,
+    pub block: BlockDetailV2,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrphansResponseV2 {
+    pub orphans: Vec<BlockTombstoneV2>,
+    pub has_next: bool,
+    pub next_cursor: Option<i64>,
+}
+
+impl From<model::OrphansResponse> for OrphansResponseV2 {
+    fn from(page: model::OrphansResponse) -> Self {
+        Self {
+            orphans: page
+                .orphans
+                .into_iter()
+                .map(|orphan| BlockTombstoneV2 {
+                    height: orphan.height,
+                    hash: orphan.hash,
+                    replaced_by: orphan.replaced_by,
+                    reorg_depth: orphan.reorg_depth,
+                    orphaned_at: orphan.orphaned_at,
+                    block: orphan.block.into(),
+                })
+                .collect(),
+            has_next: page.has_next,
+            next_cursor: page.next_cursor,
+        }
+    }
+}
+
+/// `/v1`'s `TransactionStatus` with `included` and `proof_verified` folded
+/// into `status`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransactionStatusV2 {
+    pub status: PipelineStatus,
+    pub block_height: Option<u32>,
+    pub block_hash: Option<BlockHash>,
+    pub confirmations: Option<u32>,
+    /// Proof of the containing block, present once one is available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_url: Option<String>,
+}
+
+impl From<model::TransactionStatus> for TransactionStatusV2 {
+    fn from(status: model::TransactionStatus) -> Self {
+        Self {
+            status: match (status.included, status.proof_verified) {
+                (false, _) => PipelineStatus::Pending,
+                (true, None) => PipelineStatus::Indexed,
+                (true, Some(verified)) => PipelineStatus::of_proof(verified),
+            },
+            block_height: status.block_height,
+            block_hash: status.block_hash,
+            confirmations: status.confirmations,
+            proof_url: status.proof_url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionOutputV2 {
+    /// In satoshis.
+    pub value: i64,
+    pub script_pubkey: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransactionDetailV2 {
+    pub txid: Txid,
+    pub block_height: u32,
+    pub position_in_block: u32,
+    /// In satoshis; detail fields are null when only the txid has been
+    /// indexed.
+    pub fee: Option<i64>,
+    pub size: Option<u32>,
+    pub vsize: Option<u32>,
+    pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutputV2>,
+}
+
+impl From<model::TransactionDetail> for TransactionDetailV2 {
+    fn from(detail: model::TransactionDetail) -> Self {
+        Self {
+            txid: detail.txid,
+            block_height: detail.block_height,
+            position_in_block: detail.position_in_block,
+            fee: detail.fee.map(btc_to_sats),
+            size: detail.size,
+            vsize: detail.vsize,
+            inputs: detail.inputs,
+            outputs: detail
+                .outputs
+                .into_iter()
+                .map(|output| TransactionOutputV2 {
+                    value: btc_to_sats(output.value),
+                    script_pubkey: output.script_pubkey,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OutputStatusV2 {
+    pub txid: Txid,
+    pub vout: u32,
+    /// In satoshis.
+    pub value: i64,
+    pub script_pubkey: String,
+    pub script_type: model::ScriptType,
+    pub block_height: u32,
+    pub unspent: bool,
+    pub spent_by: Option<model::OutputSpend>,
+    /// Height of the indexed tip the spent status is evaluated against.
+    pub tip_height: u32,
+}
+
+impl From<model::OutputStatus> for OutputStatusV2 {
+    fn from(status: model::OutputStatus) -> Self {
+        Self {
+            txid: status.txid,
+            vout: status.vout,
+            value: btc_to_sats(status.value),
+            script_pubkey: status.script_pubkey,
+            script_type: status.script_type,
+            block_height: status.block_height,
+            unspent: status.unspent,
+            spent_by: status.spent_by,
+            tip_height: status.tip_height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UtxoV2 {
+    pub txid: Txid,
+    pub vout: u32,
+    /// In satoshis.
+    pub value: i64,
+    pub block_height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressUtxosResponseV2 {
+    pub address: String,
+    pub script_pubkey: String,
+    pub utxos: Vec<UtxoV2>,
+}
+
+impl From<model::Utxo> for UtxoV2 {
+    fn from(utxo: model::Utxo) -> Self {
+        Self {
+            txid: utxo.txid,
+            vout: utxo.vout,
+            value: btc_to_sats(utxo.value),
+            block_height: utxo.block_height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofRecordV2 {
+    pub height: u32,
+    pub version: String,
+    /// Proof file size in bytes, when recorded.
+    pub size: Option<i64>,
+    /// `proved` or `verified`.
+    pub status: PipelineStatus,
+    pub generated_at: i64,
+    pub proof_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofsResponseV2 {
+    pub proofs: Vec<ProofRecordV2>,
+    pub has_next: bool,
+    pub next_cursor: Option<u32>,
+}
+
+impl From<model::ProofsResponse> for ProofsResponseV2 {
+    fn from(page: model::ProofsResponse) -> Self {
+        Self {
+            proofs: page
+                .proofs
+                .into_iter()
+                .map(|proof| ProofRecordV2 {
+                    height: proof.height,
+                    version: proof.version,
+                    size: proof.size,
+                    status: PipelineStatus::of_proof(proof.verified),
+                    generated_at: proof.generated_at,
+                    proof_url: proof.proof_url,
+                })
+                .collect(),
+            has_next: page.has_next,
+            next_cursor: page.next_cursor,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/blocks",
+    operation_id = "get_blocks_v2",
+    tag = "v2",
+    params(
+        ("limit" = Option<u32>, Query, description = "Number of blocks to return (1-50, default 20)"),
+        ("cursor" = Option<u32>, Query, description = "Continue after this height, from `next_cursor`"),
+        ("direction" = Option<model::PageDirection>, Query, description = "`next` (default) or `prev` with `prev_cursor`"),
+        ("order" = Option<model::SortOrder>, Query, description = "`desc` (default) or `asc`"),
+        ("verified" = Option<bool>, Query, description = "Only return `verified` blocks, or only the others"),
+        ("from_timestamp" = Option<i64>, Query, description = "Only return blocks at or after this unix timestamp"),
+        ("to_timestamp" = Option<i64>, Query, description = "Only return blocks at or before this unix timestamp"),
+    ),
+    responses(
+        (status = 200, description = "List of blocks", body = BlocksResponseV2),
+        (status = 400, description = "Invalid query parameters"),
+    )
+)]
+pub async fn get_blocks(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<BlocksQuery>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response> {
+    let page = list_blocks(&*db, &query).await?;
+    let (next, prev) = (page.next_cursor, page.prev_cursor);
+    Ok(pagination::with_links(
+        Json(BlocksResponseV2::from(page)),
+        &uri,
+        next,
+        prev,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/blocks/orphans",
+    operation_id = "get_orphans_v2",
+    tag = "v2",
+    params(
+        ("limit" = Option<u32>, Query, description = "Number of orphans to return (1-50, default 20)"),
+        ("cursor" = Option<i64>, Query, description = "Resume after this position, from `next_cursor`"),
+    ),
+    responses(
+        (status = 200, description = "Blocks displaced by reorgs, most recent first", body = OrphansResponseV2),
+        (status = 400, description = "Invalid query parameters"),
+    )
+)]
+pub async fn get_orphans(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<OrphansQuery>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response> {
+    let page = list_orphans(&*db, &query).await?;
+    let next = page.next_cursor.filter(|_| page.has_next);
+    Ok(pagination::with_links(
+        Json(OrphansResponseV2::from(page)),
+        &uri,
+        next,
+        None::<i64>,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/blocks/{identifier}",
+    operation_id = "get_block_by_identifier_v2",
+    tag = "v2",
+    params(
+        ("include_txids" = Option<bool>, Query, description = "Set to `false` to omit `txids` (default true)"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy; answered with 304 while it is current"),
+    ),
+    responses(
+        (status = 200, description = "Block details", body = BlockDetailV2),
+        (status = 304, description = "The cached copy named by `If-None-Match` is current"),
+        (status = 400, description = "Invalid block identifier"),
+        (status = 404, description = "Block not found, or past the indexed tip (`code` is `NOT_INDEXED`)"),
+    )
+)]
+pub async fn get_block_by_identifier(
+    State(db): State<Arc<dyn BlockStore>>,
+    id: BlockId,
+    Query(query): Query<BlockDetailQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let mut block = find_block(&*db, id).await?;
+    let include_txids = query.include_txids.unwrap_or(true);
+    let cache_control = CacheControlConfig::global();
+    let immutable = cache_control.is_block_final(&block, db.tip_height().await?);
+    let etag = etag::block_etag(&block, include_txids);
+
+    let mut response = if etag::is_fresh(&headers, &etag) {
+        etag::not_modified(&etag)
+    } else {
+        if !include_txids {
+            block.txids.clear();
+            block.wtxids.clear();
+        }
+        ([(header::ETAG, etag)], Json(BlockDetailV2::from(block))).into_response()
+    };
+    cache_control.apply(&mut response, immutable);
+    Ok(response)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/tx/{txid}",
+    operation_id = "get_transaction_status_v2",
+    tag = "v2",
+    responses(
+        (status = 200, description = "Transaction status", body = TransactionStatusV2),
+        (status = 400, description = "Invalid transaction ID"),
+    )
+)]
+pub async fn get_transaction_status(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(txid): Path<String>,
+) -> Result<Json<TransactionStatusV2>> {
+    let txid = resolve_txid(&*db, &txid).await?;
+
+    let status = db.get_transaction_status(&txid).await?;
+
+    Ok(Json(status.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/tx/{txid}/detail",
+    operation_id = "get_transaction_detail_v2",
+    tag = "v2",
+    responses(
+        (status = 200, description = "Transaction details", body = TransactionDetailV2),
+        (status = 400, description = "Invalid transaction ID"),
+        (status = 404, description = "Transaction not found, or not indexed yet (`code` is `NOT_INDEXED`)"),
+    )
+)]
+pub async fn get_transaction_detail(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(txid): Path<String>,
+) -> Result<Json<TransactionDetailV2>> {
+    let txid = resolve_txid(&*db, &txid).await?;
+
+    let detail =
+        check_transaction_miss(&*db, &txid, db.get_transaction_detail(&txid).await).await?;
+
+    Ok(Json(detail.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/utxo/{txid}/{vout}",
+    operation_id = "get_output_status_v2",
+    tag = "v2",
+    responses(
+        (status = 200, description = "Spent status of the output as of the indexed tip", body = OutputStatusV2),
+        (status = 400, description = "Invalid transaction ID"),
+        (status = 404, description = "Output not found, or its transaction not indexed yet (`code` is `NOT_INDEXED`)"),
+    )
+)]
+pub async fn get_output_status(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path((txid, vout)): Path<(String, u32)>,
+) -> Result<Json<OutputStatusV2>> {
+    let txid = resolve_txid(&*db, &txid).await?;
+
+    let status =
+        check_transaction_miss(&*db, &txid, db.get_output_status(&txid, vout).await).await?;
+
+    Ok(Json(status.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/address/{address}/utxos",
+    operation_id = "get_address_utxos_v2",
+    tag = "v2",
+    responses(
+        (status = 200, description = "Unspent outputs locked to the address", body = AddressUtxosResponseV2),
+        (status = 400, description = "Invalid address"),
+        (status = 501, description = "Address index is disabled"),
+    )
+)]
+pub async fn get_address_utxos(
+    State(db): State<Arc<dyn BlockStore>>,
+    Path(address): Path<String>,
+) -> Result<Json<AddressUtxosResponseV2>> {
+    let script_pubkey = address_script_pubkey(&*db, &address)?;
+    let utxos = db.get_address_utxos(&script_pubkey).await?;
+
+    Ok(Json(AddressUtxosResponseV2 {
+        address,
+        script_pubkey,
+        utxos: utxos.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/proofs",
+    operation_id = "get_proofs_v2",
+    tag = "v2",
+    params(
+        ("limit" = Option<u32>, Query, description = "Number of proofs to return (1-100, default 50)"),
+        ("cursor" = Option<u32>, Query, description = "Continue after this height, from `next_cursor`"),
+        ("verified" = Option<bool>, Query, description = "Only return `verified` proofs, or only the others"),
+        ("version" = Option<String>, Query, description = "Only return proofs produced by this prover version"),
+    ),
+    responses(
+        (status = 200, description = "Available proofs in ascending height order", body = ProofsResponseV2),
+        (status = 400, description = "Invalid query parameters"),
+    )
+)]
+pub async fn get_proofs(
+    State(db): State<Arc<dyn BlockStore>>,
+    Query(query): Query<ProofsQuery>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response> {
+    let page = list_proofs(&*db, &query).await?;
+    let next = page.next_cursor.filter(|_| page.has_next);
+    Ok(pagination::with_links(
+        Json(ProofsResponseV2::from(page)),
+        &uri,
+        next,
+        None::<u32>,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{TransactionOutput, TransactionStatus};
+
+    #[test]
+    fn test_transaction_status_mapping() {
+        let status = |included, proof_verified| {
+            TransactionStatusV2::from(TransactionStatus {
+                included,
+                block_height: None,
+                block_hash: None,
+                confirmations: None,
+                proof_url: None,
+                proof_verified,
+            })
+            .status
+        };
+        assert_eq!(status(false, None), PipelineStatus::Pending);
+        assert_eq!(status(true, None), PipelineStatus::Indexed);
+        assert_eq!(status(true, Some(false)), PipelineStatus::Proved);
+        assert_eq!(status(true, Some(true)), PipelineStatus::Verified);
+    }
+
+    #[test]
+    fn test_amounts_in_satoshis() {
+        let detail = TransactionDetailV2::from(model::TransactionDetail {
+            txid: Txid::from_internal_bytes([1; 32]),
+            block_height: 1,
+            position_in_block: 1,
+            fee: Some(0.00012345),
+            size: None,
+            vsize: None,
+            inputs: Vec::new(),
+            outputs: vec![TransactionOutput {
+                value: 6.25,
+                script_pubkey: String::new(),
+            }],
+        });
+        assert_eq!(detail.fee, Some(12_345));
+        assert_eq!(detail.outputs[0].value, 625_000_000);
+    }
+}
@@ -0,0 +1,648 @@
+use crate::{
+    chain,
+    hash::{sha256d, BlockHash, Txid},
+    model::ChainAnchor,
+    telemetry::Metrics,
+    witness,
+};
+use serde_json::Value;
+use std::fmt;
+use tracing::warn;
+
+/// What ingest does with blocks whose header fails validation, from
+/// `HEADER_VALIDATION`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeaderValidation {
+    /// Stop the ingest with an error, as for a malformed file.
+    #[default]
+    Reject,
+    /// Set the block aside in `quarantined_blocks` and carry on without it.
+    Quarantine,
+    /// Trust the source; only for synthetic data such as the mock blocks.
+    Off,
+}
+
+impl HeaderValidation {
+    pub fn from_env() -> Self {
+        match std::env::var("HEADER_VALIDATION").as_deref() {
+            Ok("reject") | Err(_) => Self::Reject,
+            Ok("quarantine") => Self::Quarantine,
+            Ok("off") => Self::Off,
+            Ok(other) => {
+                warn!(
+                    mode = other,
+                    "Unknown HEADER_VALIDATION, rejecting invalid headers"
+                );
+                Self::Reject
+            }
+        }
+    }
+}
+
+/// A block trusted to be valid together with every block below it, from
+/// `ASSUME_VALID` (`height:hash`), like Bitcoin Core's `-assumevalid`.
+/// Ingest skips the expensive header checks up to it and runs them all above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssumeValid {
+    pub height: u32,
+    pub hash: BlockHash,
+}
+
+impl AssumeValid {
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var("ASSUME_VALID").ok()?;
+        let assume_valid = Self::parse(&spec);
+        if assume_valid.is_none() {
+            warn!(
+                spec,
+                "Ignoring malformed ASSUME_VALID, validating every block"
+            );
+        }
+        assume_valid
+    }
+
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (height, hash) = spec.trim().split_once(':')?;
+        Some(Self {
+            height: height.parse().ok()?,
+            hash: hash.parse().ok()?,
+        })
+    }
+
+    /// Whether `block` is at or below the trusted height.
+    pub fn covers(&self, block: &Value) -> bool {
+        block["height"]
+            .as_u64()
+            .is_some_and(|height| height <= u64::from(self.height))
+    }
+}
+
+/// Why a block's header was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    /// Stable `snake_case` reason, used as a metric label and stored with
+    /// quarantined blocks.
+    pub reason: &'static str,
+    pub detail: String,
+}
+
+impl Rejection {
+    fn new(reason: &'static str, detail: String) -> Self {
+        Metrics::global().record_rejected_header(reason);
+        Self { reason, detail }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.detail, self.reason)
+    }
+}
+
+/// The 80-byte header of a block in the mock-data shape, in wire format.
+/// Only call on a block whose fields have been checked.
+fn raw_header(block: &Value) -> Option<[u8; 80]> {
+    let hash_bytes = |field: &str| {
+        block[field]
+            .as_str()
+            .and_then(|s| s.parse::<BlockHash>().ok())
+            .map(BlockHash::to_internal_bytes)
+    };
+    let mut header = [0u8; 80];
+    header[0..4].copy_from_slice(&(block["version"].as_i64()? as i32).to_le_bytes());
+    header[4..36].copy_from_slice(&hash_bytes("prev_hash")?);
+    header[36..68].copy_from_slice(&hash_bytes("merkle_root")?);
+    header[68..72].copy_from_slice(&(block["timestamp"].as_i64()? as u32).to_le_bytes());
+    header[72..76].copy_from_slice(&u32::try_from(block["bits"].as_u64()?).ok()?.to_le_bytes());
+    header[76..80].copy_from_slice(&u32::try_from(block["nonce"].as_u64()?).ok()?.to_le_bytes());
+    Some(header)
+}
+
+/// Checks that the block's hash is the double SHA-256 of its header and
+/// meets the target its `bits` encode, so a source cannot make up blocks
+/// without doing the work. Counts each failure in
+/// `raito_rejected_headers_total`.
+pub fn check_proof_of_work(block: &Value) -> Result<(), Rejection> {
+    let height = &block["height"];
+    let Some(header) = raw_header(block) else {
+        return Err(Rejection::new(
+            "malformed_header",
+            format!("block {height} has a header that cannot be serialized"),
+        ));
+    };
+    let computed = BlockHash::from_internal_bytes(sha256d(&header));
+    if block["hash"].as_str().and_then(|s| s.parse().ok()) != Some(computed) {
+        return Err(Rejection::new(
+            "hash_mismatch",
+            format!(
+                "block {height} claims hash {} but its header hashes to {computed}",
+                block["hash"]
+            ),
+        ));
+    }
+    let bits = block["bits"].as_u64().unwrap_or_default() as u32;
+    let Some(target) = chain::target(bits) else {
+        return Err(Rejection::new(
+            "invalid_target",
+            format!("block {height} has invalid bits {bits:#010x}"),
+        ));
+    };
+    if computed.to_internal_bytes().iter().rev().cmp(target.iter()) == std::cmp::Ordering::Greater {
+        return Err(Rejection::new(
+            "insufficient_work",
+            format!("block {height} hash {computed} is above the target of bits {bits:#010x}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the block's `merkle_root` against its txids when it lists all
+/// `tx_count` of them. A block carrying only some of its txids cannot be
+/// checked and passes.
+pub fn check_merkle_root(block: &Value) -> Result<(), Rejection> {
+    let height = &block["height"];
+    let Some(txids) = block["txids"].as_array() else {
+        return Ok(());
+    };
+    if txids.is_empty() || Some(txids.len() as u64) != block["tx_count"].as_u64() {
+        return Ok(());
+    }
+    let leaves = txids
+        .iter()
+        .map(|txid| {
+            txid.as_str()
+                .and_then(|s| s.parse::<Txid>().ok())
+                .map(Txid::to_internal_bytes)
+        })
+        .collect::<Option<Vec<_>>>();
+    let Some(leaves) = leaves else {
+        return Err(Rejection::new(
+            "merkle_mismatch",
+            format!("block {height} lists a malformed txid"),
+        ));
+    };
+    let computed = BlockHash::from_internal_bytes(witness::merkle_root(&leaves));
+    if block["merkle_root"].as_str().and_then(|s| s.parse().ok()) != Some(computed) {
+        return Err(Rejection::new(
+            "merkle_mismatch",
+            format!(
+                "block {height} claims merkle root {} but its txids give {computed}",
+                block["merkle_root"]
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Runs the expensive header checks, proof of work and the merkle root,
+/// unless `assume_valid` covers the block: blocks below the trusted height
+/// are taken as they are, and the block at it only has to be the trusted
+/// one.
+pub fn check_header(block: &Value, assume_valid: Option<&AssumeValid>) -> Result<(), Rejection> {
+    match assume_valid {
+        Some(trusted) if trusted.covers(block) => {
+            let height = block["height"].as_u64().unwrap_or_default();
+            let hash = block["hash"].as_str().and_then(|s| s.parse().ok());
+            if height < u64::from(trusted.height) || hash == Some(trusted.hash) {
+                return Ok(());
+            }
+            Err(Rejection::new(
+                "assume_valid_mismatch",
+                format!(
+                    "block {height} is not the assumed-valid block {}",
+                    trusted.hash
+                ),
+            ))
+        }
+        _ => {
+            check_proof_of_work(block)?;
+            check_merkle_root(block)
+        }
+    }
+}
+
+/// Checks that a block extends the indexed chain: its `prev_hash` must name
+/// the block indexed below it (`parent_indexed`) or an anchor, unless nothing
+/// is indexed below it yet (`first`) and it starts the chain. A block at an
+/// anchored height must be the anchored one.
+pub fn check_continuity(
+    block: &Value,
+    parent_indexed: bool,
+    first: bool,
+    anchors: &[ChainAnchor],
+) -> Result<(), Rejection> {
+    let height = block["height"].as_u64().unwrap_or_default();
+    let hash = |field: &str| {
+        block[field]
+            .as_str()
+            .and_then(|s| s.parse::<BlockHash>().ok())
+    };
+    if let Some(anchor) = anchors
+        .iter()
+        .find(|anchor| u64::from(anchor.height) == height)
+        .filter(|anchor| hash("hash") != Some(anchor.hash))
+    {
+        return Err(Rejection::new(
+            "anchor_mismatch",
+            format!("block {height} is not the anchored block {}", anchor.hash),
+        ));
+    }
+    let prev_hash = hash("prev_hash");
+    let anchored = anchors
+        .iter()
+        .any(|anchor| u64::from(anchor.height) + 1 == height && Some(anchor.hash) == prev_hash);
+    if parent_indexed || anchored || first || height == 0 {
+        return Ok(());
+    }
+    Err(Rejection::new(
+        "unlinked_parent",
+        format!(
+            "block {height} builds on {}, which is neither indexed at height {} nor an anchor",
+            block["prev_hash"],
+            height - 1
+        ),
+    ))
+}
+
+/// What the indexed chain knows about the difficulty a block must have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetargetContext {
+    /// Compact target of the block's parent.
+    pub parent_bits: u32,
+    pub parent_timestamp: i64,
+    /// Timestamp of the block opening the period the parent closes, for a
+    /// block at a retarget height when that block is indexed.
+    pub period_start: Option<i64>,
+    /// Compact target of the block opening the block's own period, when the
+    /// network allows min-difficulty blocks, the parent is one and that block
+    /// is indexed.
+    pub period_bits: Option<u32>,
+}
+
+/// Checks the block's `bits` against `network`'s retarget rules: unchanged
+/// within a 2016-block period, and at a retarget height exactly what the
+/// closing period's timespan gives. Without the period's first block only the
+/// bounds are checked: at most four times harder or easier, and never easier
+/// than the proof-of-work limit. Networks without retargeting keep the
+/// parent's target at retarget heights too. On networks allowing
+/// min-difficulty blocks, a block more than 20 minutes after its parent must
+/// have the proof-of-work limit, and the block after it returns to the
+/// period's target.
+pub fn check_retarget(
+    block: &Value,
+    context: &RetargetContext,
+    network: &chain::NetworkParams,
+) -> Result<(), Rejection> {
+    let height = block["height"].as_u64().unwrap_or_default();
+    let bits = block["bits"].as_u64().unwrap_or_default() as u32;
+    let parent_bits = context.parent_bits;
+    if !height.is_multiple_of(u64::from(chain::RETARGET_INTERVAL)) {
+        let expected = if network.allow_min_difficulty_blocks {
+            min_difficulty_bits(block, context, network)
+        } else {
+            Some(parent_bits)
+        };
+        if expected.is_none_or(|expected| bits == expected) {
+            return Ok(());
+        }
+        return Err(Rejection::new(
+            "unexpected_retarget",
+            format!("block {height} changes bits from {parent_bits:#010x} to {bits:#010x} within a retarget period"),
+        ));
+    }
+    if network.no_retargeting {
+        if bits == parent_bits {
+            return Ok(());
+        }
+        return Err(Rejection::new(
+            "bad_retarget",
+            format!(
+                "block {height} has bits {bits:#010x} on a network that keeps {parent_bits:#010x}"
+            ),
+        ));
+    }
+    let limit = network.pow_limit_bits;
+    if let Some(period_start) = context.period_start {
+        let expected =
+            chain::next_bits(parent_bits, context.parent_timestamp - period_start, limit);
+        if bits == expected {
+            return Ok(());
+        }
+        return Err(Rejection::new(
+            "bad_retarget",
+            format!(
+                "block {height} has bits {bits:#010x} where the retarget gives {expected:#010x}"
+            ),
+        ));
+    }
+    let hardest = chain::target(chain::next_bits(parent_bits, 0, limit));
+    let easiest = chain::target(chain::next_bits(parent_bits, i64::MAX, limit));
+    match (chain::target(bits), hardest, easiest) {
+        (Some(target), Some(hardest), Some(easiest)) if hardest <= target && target <= easiest => {
+            Ok(())
+        }
+        _ => Err(Rejection::new(
+            "implausible_retarget",
+            format!("block {height} retargets from {parent_bits:#010x} to {bits:#010x}, beyond what one period allows"),
+        )),
+    }
+}
+
+/// The bits a block within a period must have on a network allowing
+/// min-difficulty blocks, as Bitcoin Core's `GetNextWorkRequired` gives them:
+/// the proof-of-work limit more than twice the target spacing after the
+/// parent, otherwise the target of the last block that was not a
+/// min-difficulty one. `None` when that block is not indexed.
+fn min_difficulty_bits(
+    block: &Value,
+    context: &RetargetContext,
+    network: &chain::NetworkParams,
+) -> Option<u32> {
+    let timestamp = block["timestamp"].as_i64().unwrap_or_default();
+    let spacing = i64::from(chain::TARGET_BLOCK_TIME_SECS);
+    if timestamp > context.parent_timestamp + 2 * spacing {
+        return Some(network.pow_limit_bits);
+    }
+    if context.parent_bits != network.pow_limit_bits {
+        return Some(context.parent_bits);
+    }
+    context.period_bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A block meeting regtest's target, which about every other hash does.
+    fn mined_block(height: u64, prev_hash: &str) -> Value {
+        let mut block = json!({
+            "height": height,
+            "hash": "",
+            "prev_hash": prev_hash,
+            "merkle_root": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+            "version": 0x2000_0000,
+            "bits": 0x207fffff,
+            "nonce": 0,
+            "tx_count": 1,
+            "total_fees": 0,
+            "timestamp": 1_700_000_000,
+            "verified": false,
+        });
+        for nonce in 0u32.. {
+            block["nonce"] = json!(nonce);
+            let hash = BlockHash::from_internal_bytes(sha256d(&raw_header(&block).unwrap()));
+            block["hash"] = json!(hash.to_string());
+            if check_proof_of_work(&block).is_ok() {
+                break;
+            }
+        }
+        block
+    }
+
+    #[test]
+    fn test_check_proof_of_work() {
+        let block = mined_block(1, &"0".repeat(64));
+        assert_eq!(check_proof_of_work(&block), Ok(()));
+
+        let mut forged = block.clone();
+        forged["hash"] = json!(format!("{}1", "0".repeat(63)));
+        assert_eq!(
+            check_proof_of_work(&forged).unwrap_err().reason,
+            "hash_mismatch"
+        );
+
+        // The right hash for a header claiming far more work than was done
+        let mut hard = block.clone();
+        hard["bits"] = json!(0x1d00ffff);
+        let hash = BlockHash::from_internal_bytes(sha256d(&raw_header(&hard).unwrap()));
+        hard["hash"] = json!(hash.to_string());
+        assert_eq!(
+            check_proof_of_work(&hard).unwrap_err().reason,
+            "insufficient_work"
+        );
+
+        let mock: Vec<Value> =
+            serde_json::from_str(include_str!("../data/mock_blocks.json")).unwrap();
+        assert!(check_proof_of_work(&mock[0]).is_err());
+        assert!(Metrics::global()
+            .render()
+            .contains("raito_rejected_headers_total{reason=\"hash_mismatch\"}"));
+    }
+
+    #[test]
+    fn test_check_merkle_root() {
+        // The genesis block, whose only transaction's txid is its merkle root
+        let mut block = mined_block(0, &"0".repeat(64));
+        assert_eq!(check_merkle_root(&block), Ok(()));
+        block["txids"] = json!([block["merkle_root"]]);
+        assert_eq!(check_merkle_root(&block), Ok(()));
+
+        block["txids"] = json!(["11".repeat(32)]);
+        assert_eq!(
+            check_merkle_root(&block).unwrap_err().reason,
+            "merkle_mismatch"
+        );
+        // Only some of the block's txids: nothing to check against
+        block["tx_count"] = json!(2);
+        assert_eq!(check_merkle_root(&block), Ok(()));
+
+        let mock: Vec<Value> =
+            serde_json::from_str(include_str!("../data/mock_blocks.json")).unwrap();
+        assert!(mock.iter().all(|block| check_merkle_root(block).is_ok()));
+    }
+
+    #[test]
+    fn test_check_header_assume_valid() {
+        let mock: Vec<Value> =
+            serde_json::from_str(include_str!("../data/mock_blocks.json")).unwrap();
+        let at = |height: u64| mock.iter().find(|b| b["height"] == height).unwrap();
+        let trusted =
+            AssumeValid::parse(&format!("869122:{}", at(869122)["hash"].as_str().unwrap()))
+                .unwrap();
+        assert_eq!(AssumeValid::parse("869122"), None);
+
+        // The mock hashes fail proof of work, which only matters above
+        assert_eq!(check_header(at(869121), Some(&trusted)), Ok(()));
+        assert_eq!(check_header(at(869122), Some(&trusted)), Ok(()));
+        assert_eq!(
+            check_header(at(869123), Some(&trusted)).unwrap_err().reason,
+            "hash_mismatch"
+        );
+        assert_eq!(
+            check_header(at(869121), None).unwrap_err().reason,
+            "hash_mismatch"
+        );
+
+        let mut impostor = at(869122).clone();
+        impostor["hash"] = json!("7".repeat(64));
+        assert_eq!(
+            check_header(&impostor, Some(&trusted)).unwrap_err().reason,
+            "assume_valid_mismatch"
+        );
+
+        let block = mined_block(1, &"0".repeat(64));
+        assert_eq!(check_header(&block, None), Ok(()));
+    }
+
+    #[test]
+    fn test_check_continuity() {
+        let parent = mined_block(1, &"0".repeat(64));
+        let block = mined_block(2, parent["hash"].as_str().unwrap());
+        let reason = |result: Result<(), Rejection>| result.err().map(|r| r.reason);
+
+        assert_eq!(reason(check_continuity(&block, true, false, &[])), None);
+        assert_eq!(reason(check_continuity(&block, false, true, &[])), None);
+        assert_eq!(
+            reason(check_continuity(&block, false, false, &[])),
+            Some("unlinked_parent")
+        );
+
+        // An anchor stands in for the missing parent, and pins its own height
+        let anchor = |height, block: &Value| ChainAnchor {
+            height,
+            hash: block["hash"].as_str().unwrap().parse().unwrap(),
+        };
+        assert_eq!(
+            reason(check_continuity(
+                &block,
+                false,
+                false,
+                &[anchor(1, &parent)]
+            )),
+            None
+        );
+        assert_eq!(
+            reason(check_continuity(&block, true, false, &[anchor(2, &parent)])),
+            Some("anchor_mismatch")
+        );
+    }
+
+    #[test]
+    fn test_check_retarget() {
+        let reason = |height: u64, bits: u32, period_start| {
+            let block = json!({ "height": height, "bits": bits });
+            let context = RetargetContext {
+                parent_bits: 0x1d00ffff,
+                parent_timestamp: 1262152739,
+                period_start,
+                period_bits: None,
+            };
+            check_retarget(&block, &context, &chain::MAINNET)
+                .err()
+                .map(|r| r.reason)
+        };
+
+        assert_eq!(reason(32255, 0x1d00ffff, None), None);
+        assert_eq!(reason(32255, 0x1d00d86a, None), Some("unexpected_retarget"));
+
+        // Block 32256, mainnet's first retarget to a harder target
+        assert_eq!(reason(32256, 0x1d00d86a, Some(1261130161)), None);
+        assert_eq!(
+            reason(32256, 0x1d00ffff, Some(1261130161)),
+            Some("bad_retarget")
+        );
+
+        // Without the period's first block, anything within a factor of four
+        assert_eq!(reason(32256, 0x1d00d86a, None), None);
+        assert_eq!(reason(32256, 0x1c3fffc0, None), None);
+        assert_eq!(
+            reason(32256, 0x1c3fffbf, None),
+            Some("implausible_retarget")
+        );
+        assert_eq!(
+            reason(32256, 0x1d01fffe, None),
+            Some("implausible_retarget")
+        );
+    }
+
+    #[test]
+    fn test_check_retarget_across_regtest_period() {
+        // A regtest chain mined a second apart, through the 2016 boundary
+        let bits = chain::REGTEST.pow_limit_bits;
+        let timestamp = |height: u64| 1_700_000_000 + height as i64;
+        let check = |height: u64, network: &chain::NetworkParams| {
+            let block = json!({ "height": height, "bits": bits, "timestamp": timestamp(height) });
+            let context = RetargetContext {
+                parent_bits: bits,
+                parent_timestamp: timestamp(height - 1),
+                period_start: height
+                    .is_multiple_of(2016)
+                    .then(|| timestamp(height - 2016)),
+                period_bits: Some(bits),
+            };
+            check_retarget(&block, &context, network)
+                .err()
+                .map(|r| r.reason)
+        };
+
+        for height in 2014..=2018 {
+            assert_eq!(check(height, &chain::REGTEST), None);
+        }
+        // Without the period's first block regtest keeps its target too
+        let block = json!({ "height": 4032, "bits": bits, "timestamp": timestamp(4032) });
+        let context = RetargetContext {
+            parent_bits: bits,
+            parent_timestamp: timestamp(4031),
+            period_start: None,
+            period_bits: None,
+        };
+        assert!(check_retarget(&block, &context, &chain::REGTEST).is_ok());
+        let mut retargeted = block.clone();
+        retargeted["bits"] = json!(0x1f7fffff);
+        assert_eq!(
+            check_retarget(&retargeted, &context, &chain::REGTEST)
+                .err()
+                .map(|r| r.reason),
+            Some("bad_retarget")
+        );
+
+        // Mainnet rules would have asked for a harder target at the boundary
+        assert_eq!(check(2016, &chain::MAINNET), Some("bad_retarget"));
+    }
+
+    #[test]
+    fn test_check_retarget_min_difficulty_blocks() {
+        let limit = chain::TESTNET.pow_limit_bits;
+        let period_bits = 0x1c0ffff0;
+        let reason = |bits: u32, parent_bits: u32, delay: i64, known_period: bool| {
+            let block =
+                json!({ "height": 100_001, "bits": bits, "timestamp": 1_700_000_000 + delay });
+            let context = RetargetContext {
+                parent_bits,
+                parent_timestamp: 1_700_000_000,
+                period_start: None,
+                period_bits: known_period.then_some(period_bits),
+            };
+            check_retarget(&block, &context, &chain::TESTNET)
+                .err()
+                .map(|r| r.reason)
+        };
+
+        assert_eq!(reason(period_bits, period_bits, 600, true), None);
+        // More than 20 minutes after its parent, a block must drop to the limit
+        assert_eq!(reason(limit, period_bits, 1201, true), None);
+        assert_eq!(
+            reason(period_bits, period_bits, 1201, true),
+            Some("unexpected_retarget")
+        );
+        assert_eq!(
+            reason(limit, period_bits, 1200, true),
+            Some("unexpected_retarget")
+        );
+        // and the block after it returns to the period's target
+        assert_eq!(reason(period_bits, limit, 600, true), None);
+        assert_eq!(reason(limit, limit, 600, true), Some("unexpected_retarget"));
+        assert_eq!(reason(limit, limit, 600, false), None);
+
+        // Mainnet has no such exception
+        let block = json!({ "height": 100_001, "bits": limit, "timestamp": 1_700_003_600 });
+        let context = RetargetContext {
+            parent_bits: period_bits,
+            parent_timestamp: 1_700_000_000,
+            period_start: None,
+            period_bits: None,
+        };
+        assert!(check_retarget(&block, &context, &chain::MAINNET).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{config::Lookup, model::VersionInfo, proof};
+use axum::{extract::Request, middleware::Next, response::Response};
+use chrono::DateTime;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Commit the binary was built from, or `unknown`.
+pub const GIT_COMMIT: &str = env!("RAITO_GIT_COMMIT");
+/// Unix time of the build, set by `build.rs`.
+const BUILD_TIMESTAMP: &str = env!("RAITO_BUILD_TIMESTAMP");
+/// Major versions of the HTTP API served, as in the `/v1` prefix.
+pub const API_VERSIONS: &[&str] = &["v1", "v2"];
+
+tokio::task_local! {
+    static API_VERSION: ApiVersion;
+}
+
+/// Major version of the API a request came in under, for code that renders
+/// responses differently per version without seeing the request, such as
+/// error bodies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApiVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    /// The version of the request being served; `V1` outside of a request
+    /// and for routes not scoped by [`v2_scope_middleware`].
+    pub fn current() -> Self {
+        API_VERSION.try_with(|version| *version).unwrap_or_default()
+    }
+}
+
+/// Serves the request as a `/v2` one.
+pub async fn v2_scope_middleware(request: Request, next: Next) -> Response {
+    API_VERSION.scope(ApiVersion::V2, next.run(request)).await
+}
+
+/// The optional parts of the service that `lookup` turns on, for clients
+/// to check before relying on them.
+pub fn enabled_features(lookup: Lookup) -> Vec<String> {
+    let set = |name: &str| lookup(name).is_some_and(|v| !v.is_empty());
+    let flag = |name: &str| lookup(name).and_then(|v| v.parse().ok()) == Some(true);
+    [
+        ("admin", set("ADMIN_TOKEN")),
+        ("api_keys_required", flag("API_KEYS_REQUIRED_FOR_READS")),
+        ("encryption", set("ENCRYPTION_KEYS")),
+        ("jwt", set("JWT_JWKS_URL") || set("JWT_HS256_SECRET")),
+        ("pruning", set("PRUNE_KEEP_BLOCKS")),
+        ("read_replica", set("DATABASE_REPLICA_URL")),
+        ("tls", set("TLS_CERT_PATH") && set("TLS_KEY_PATH")),
+    ]
+    .into_iter()
+    .filter(|&(_, enabled)| enabled)
+    .map(|(feature, _)| feature.to_string())
+    .collect()
+}
+
+/// Build and compatibility information for `GET /v1/version`.
+pub fn version_info(lookup: Lookup) -> VersionInfo {
+    let built_at = BUILD_TIMESTAMP
+        .parse()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|built_at| built_at.to_rfc3339());
+    VersionInfo {
+        version: VERSION.to_string(),
+        git_commit: GIT_COMMIT.to_string(),
+        built_at,
+        api_versions: API_VERSIONS.iter().map(|v| v.to_string()).collect(),
+        proof_versions: proof::SUPPORTED_PROOF_VERSIONS
+            .iter()
+            .map(|v| v.to_string())
+            .collect(),
+        features: enabled_features(lookup),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_enabled_features() {
+        let vars = BTreeMap::from([
+            ("ADMIN_TOKEN", "secret"),
+            ("API_KEYS_REQUIRED_FOR_READS", "false"),
+            ("JWT_HS256_SECRET", "key"),
+            ("TLS_CERT_PATH", "cert.pem"),
+            ("PRUNE_KEEP_BLOCKS", ""),
+        ]);
+        let features = enabled_features(&|name| vars.get(name).map(|v| v.to_string()));
+        assert_eq!(features, ["admin", "jwt"]);
+        assert!(enabled_features(&|_| None).is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{database::Database, error::Result, storage::ProofStorage};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone)]
+pub struct ProofWarmConfig {
+    /// Most recent proofs kept warm; warming is off when 0.
+    pub recent: u32,
+    /// How often new proofs are looked for.
+    pub interval_secs: u64,
+}
+
+impl Default for ProofWarmConfig {
+    fn default() -> Self {
+        Self {
+            recent: 10,
+            interval_secs: 30,
+        }
+    }
+}
+
+impl ProofWarmConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            recent: std::env::var("PROOF_WARM_RECENT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.recent),
+            interval_secs: std::env::var("PROOF_WARM_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.interval_secs),
+        }
+    }
+}
+
+/// Reads the most recent proofs not warmed yet through `storage`, and
+/// returns their heights. `warmed` carries what earlier passes did; heights
+/// that fell out of the recent window are forgotten.
+pub async fn warm_recent(
+    database: &Database,
+    storage: &dyn ProofStorage,
+    recent: u32,
+    warmed: &mut HashSet<u32>,
+) -> Result<Vec<u32>> {
+    let heights = database.recent_proof_heights(recent).await?;
+    warmed.retain(|height| heights.contains(height));
+
+    let mut newly_warmed = Vec::new();
+    for height in heights {
+        if warmed.contains(&height) {
+            continue;
+        }
+        match storage.warm(height).await {
+            Ok(true) => {
+                warmed.insert(height);
+                newly_warmed.push(height);
+            }
+            Ok(false) => debug!(height, "Proof not in storage yet, not warming"),
+            Err(e) => warn!(height, error = %e, "Failed to warm proof"),
+        }
+    }
+    Ok(newly_warmed)
+}
+
+/// Periodically warms newly generated proofs, so the first download of a
+/// fresh proof is served from the page cache instead of a cold disk read.
+pub fn spawn_proof_warmer(
+    database: Arc<Database>,
+    storage: &'static dyn ProofStorage,
+    config: ProofWarmConfig,
+) {
+    if config.recent == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        let mut warmed = HashSet::new();
+        loop {
+            interval.tick().await;
+            match warm_recent(&database, storage, config.recent, &mut warmed).await {
+                Ok(heights) if !heights.is_empty() => debug!(?heights, "Warmed proofs"),
+                Ok(_) => {}
+                Err(e) => warn!(error = %e, "Failed to list proofs to warm"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{database::DatabaseConfig, storage::FsProofStorage};
+
+    #[tokio::test]
+    async fn test_warm_recent_proofs_once() {
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        database.seed_data().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FsProofStorage::new(dir.path());
+        storage.write(869123, b"proof").await.unwrap();
+
+        // 869122 has a proof record but no file yet
+        let mut warmed = HashSet::new();
+        let heights = warm_recent(&database, &storage, 2, &mut warmed)
+            .await
+            .unwrap();
+        assert_eq!(heights, vec![869123]);
+
+        storage.write(869122, b"proof").await.unwrap();
+        let heights = warm_recent(&database, &storage, 2, &mut warmed)
+            .await
+            .unwrap();
+        assert_eq!(heights, vec![869122]);
+
+        let heights = warm_recent(&database, &storage, 1, &mut warmed)
+            .await
+            .unwrap();
+        assert!(heights.is_empty());
+        assert_eq!(warmed, HashSet::from([869123]));
+    }
+}
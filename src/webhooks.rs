@@ -0,0 +1,600 @@
+use crate::{
+    database::{Database, DueDelivery},
+    error::{AppError, Result},
+    events::Event,
+    model::{
+        CreateWebhookRequest, CreatedWebhook, DeliveryStatus, UpdateWebhookRequest, WebhookEvent,
+    },
+    store::BlockStore,
+};
+use chrono::Utc;
+use futures_util::{stream, StreamExt};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{sync::Arc, time::Duration};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Names the event a delivery carries.
+pub const EVENT_HEADER: &str = "x-raito-event";
+/// The delivery's id, the same on every attempt, so consumers can skip
+/// deliveries they already processed.
+pub const DELIVERY_HEADER: &str = "x-raito-delivery";
+/// `t=<unix time>,v1=<hex HMAC-SHA256 of "<t>.<body>">`, keyed with the
+/// webhook's secret.
+pub const SIGNATURE_HEADER: &str = "x-raito-signature";
+
+/// Due deliveries fetched per pass.
+const DELIVERY_BATCH: u32 = 100;
+/// Deliveries attempted at once.
+const DELIVERY_CONCURRENCY: usize = 8;
+
+const SECRET_PREFIX: &str = "whsec_";
+/// Shortest secret accepted from a client; generated ones are longer.
+const MIN_SECRET_LEN: usize = 16;
+
+/// A new random signing secret.
+pub fn generate_secret() -> String {
+    format!(
+        "{SECRET_PREFIX}{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+fn invalid(message: impl Into<String>) -> AppError {
+    AppError::InvalidQueryParameter(message.into())
+}
+
+/// Checks `url` is an absolute `http` or `https` URL.
+pub fn validate_url(url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| invalid(format!("url: {e}")))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(invalid("url must be an http or https URL"));
+    }
+    Ok(())
+}
+
+fn validate_secret(secret: &str) -> Result<()> {
+    if secret.len() < MIN_SECRET_LEN {
+        return Err(invalid(format!(
+            "secret must be at least {MIN_SECRET_LEN} characters"
+        )));
+    }
+    Ok(())
+}
+
+/// The subscribed events without repeats, refusing an empty list.
+fn normalize_events(events: &[WebhookEvent]) -> Result<Vec<WebhookEvent>> {
+    let mut normalized = Vec::with_capacity(events.len());
+    for &event in events {
+        if !normalized.contains(&event) {
+            normalized.push(event);
+        }
+    }
+    if normalized.is_empty() {
+        return Err(invalid("events must name at least one event type"));
+    }
+    Ok(normalized)
+}
+
+/// Validates and stores a new subscription; the result is the only place
+/// its secret is shown.
+pub async fn create(
+    store: &dyn BlockStore,
+    request: &CreateWebhookRequest,
+) -> Result<CreatedWebhook> {
+    validate_url(&request.url)?;
+    let events = normalize_events(&request.events)?;
+    let secret = match &request.secret {
+        Some(secret) => {
+            validate_secret(secret)?;
+            secret.clone()
+        }
+        None => generate_secret(),
+    };
+    let webhook = store
+        .create_webhook(
+            &request.url,
+            &secret,
+            &events,
+            request.active.unwrap_or(true),
+        )
+        .await?;
+    Ok(CreatedWebhook { secret, webhook })
+}
+
+/// Validates the fields `request` changes; returns it with events normalized.
+pub fn validate_update(request: UpdateWebhookRequest) -> Result<UpdateWebhookRequest> {
+    if let Some(url) = &request.url {
+        validate_url(url)?;
+    }
+    if let Some(secret) = &request.secret {
+        validate_secret(secret)?;
+    }
+    let events = request
+        .events
+        .as_deref()
+        .map(normalize_events)
+        .transpose()?;
+    Ok(UpdateWebhookRequest { events, ..request })
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// Failed attempts after which a delivery is dead-lettered.
+    pub max_attempts: u32,
+    /// Wait before the first retry; it doubles with every further failure.
+    pub backoff_base_secs: u64,
+    /// Longest wait between retries.
+    pub backoff_max_secs: u64,
+    /// How long an endpoint may take to answer.
+    pub timeout_secs: u64,
+    /// How often due deliveries are looked for.
+    pub poll_interval_secs: u64,
+    /// How long delivered and dead deliveries stay in the delivery log.
+    pub retention_secs: i64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            backoff_base_secs: 10,
+            backoff_max_secs: 3600,
+            timeout_secs: 10,
+            poll_interval_secs: 5,
+            retention_secs: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl WebhookConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_attempts: std::env::var("WEBHOOK_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&attempts| attempts > 0)
+                .unwrap_or(defaults.max_attempts),
+            backoff_base_secs: std::env::var("WEBHOOK_BACKOFF_BASE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.backoff_base_secs),
+            backoff_max_secs: std::env::var("WEBHOOK_BACKOFF_MAX_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.backoff_max_secs),
+            timeout_secs: std::env::var("WEBHOOK_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.timeout_secs),
+            poll_interval_secs: std::env::var("WEBHOOK_POLL_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.poll_interval_secs),
+            retention_secs: std::env::var("WEBHOOK_DELIVERY_RETENTION_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.retention_secs),
+        }
+    }
+
+    /// Wait before retrying after the `failures`th failed attempt.
+    pub fn backoff_secs(&self, failures: u32) -> u64 {
+        let factor = 1u64
+            .checked_shl(failures.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        self.backoff_base_secs
+            .saturating_mul(factor)
+            .min(self.backoff_max_secs)
+    }
+
+    /// How long claimed deliveries are held by one pass: long enough for a
+    /// full batch to time out at [`DELIVERY_CONCURRENCY`] at a time.
+    pub fn lease_secs(&self) -> u64 {
+        let rounds = (DELIVERY_BATCH as usize).div_ceil(DELIVERY_CONCURRENCY) as u64;
+        (rounds + 1).saturating_mul(self.timeout_secs)
+    }
+}
+
+/// The webhook event `event` is delivered as, if webhooks can subscribe to it.
+pub fn webhook_event(event: &Event) -> Option<WebhookEvent> {
+    match event {
+        Event::ProofVerified { .. } => Some(WebhookEvent::ProofVerified),
+        Event::Reorg { .. } => Some(WebhookEvent::Reorg),
+        Event::JobFailed { .. } => Some(WebhookEvent::JobFailed),
+        Event::BlockIndexed { .. } | Event::ProofGenerated { .. } => None,
+    }
+}
+
+/// The `X-Raito-Signature` value for `body` sent at `timestamp`.
+pub fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!(
+        "t={timestamp},v1={}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// Queues `event` for the webhooks subscribed to it. The database queues
+/// the events its own writes announce as it commits them; this is for
+/// events recorded anywhere else.
+pub async fn enqueue(database: &Database, event: &Event, now: i64) -> Result<u64> {
+    database.enqueue_webhook_deliveries(event, now).await
+}
+
+pub fn client(config: &WebhookConfig) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        // A redirect would resend the signed payload somewhere not configured
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| AppError::Store(anyhow::anyhow!("Failed to build webhook client: {}", e)))
+}
+
+/// Posts `delivery` once; returns the status code the endpoint answered with,
+/// if any, and why the attempt failed, if it did.
+async fn attempt(
+    client: &reqwest::Client,
+    delivery: &DueDelivery,
+    now: i64,
+) -> (Option<u16>, Option<String>) {
+    let body = delivery.payload.as_bytes().to_vec();
+    let result = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.event)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .header(SIGNATURE_HEADER, signature(&delivery.secret, now, &body))
+        .body(body)
+        .send()
+        .await;
+    match result {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+        Ok(response) => {
+            let status = response.status();
+            (
+                Some(status.as_u16()),
+                Some(format!("endpoint answered {status}")),
+            )
+        }
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+/// Attempts every delivery due at `now` once, scheduling a retry for those
+/// that fail or dead-lettering them once they have failed
+/// [`WebhookConfig::max_attempts`] times. Deliveries are claimed before they
+/// are sent, so concurrent passes, in this process or another replica, never
+/// send the same one twice. Returns the deliveries attempted.
+pub async fn deliver_due(
+    database: &Database,
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    now: i64,
+) -> Result<usize> {
+    let lease_until = now + config.lease_secs() as i64;
+    let due = database
+        .claim_webhook_deliveries(now, lease_until, DELIVERY_BATCH)
+        .await?;
+    let attempted = due.len();
+    stream::iter(due)
+        .for_each_concurrent(DELIVERY_CONCURRENCY, |delivery| async move {
+            let (status_code, error) = attempt(client, &delivery, now).await;
+            let failures = delivery.attempts as u32 + 1;
+            let (status, next_attempt_at) = match &error {
+                None => (DeliveryStatus::Delivered, now),
+                Some(_) if failures >= config.max_attempts => (DeliveryStatus::Dead, now),
+                Some(_) => (
+                    DeliveryStatus::Pending,
+                    now + config.backoff_secs(failures) as i64,
+                ),
+            };
+            match status {
+                DeliveryStatus::Dead => warn!(
+                    delivery = delivery.id,
+                    webhook = delivery.webhook_id,
+                    attempts = failures,
+                    error = error.as_deref(),
+                    "Webhook delivery dead-lettered"
+                ),
+                DeliveryStatus::Pending => debug!(
+                    delivery = delivery.id,
+                    webhook = delivery.webhook_id,
+                    error = error.as_deref(),
+                    "Webhook delivery failed, will retry"
+                ),
+                DeliveryStatus::Delivered => {}
+            }
+            if let Err(e) = database
+                .record_webhook_attempt(
+                    delivery.id,
+                    status,
+                    status_code,
+                    error.as_deref(),
+                    next_attempt_at,
+                    now,
+                )
+                .await
+            {
+                warn!(delivery = delivery.id, error = %e, "Failed to record webhook attempt");
+            }
+        })
+        .await;
+    Ok(attempted)
+}
+
+/// Delivers the queued deliveries as they come due, retrying failed ones
+/// with exponential backoff. Deliveries are queued with the writes that
+/// announce their events rather than from the event bus, which drops events
+/// for subscribers that fall behind.
+pub fn spawn_webhook_dispatcher(database: Arc<Database>, config: WebhookConfig) -> Result<()> {
+    let client = client(&config)?;
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.poll_interval_secs));
+        loop {
+            interval.tick().await;
+            let now = Utc::now().timestamp();
+            if let Err(e) = deliver_due(&database, &client, &config, now).await {
+                warn!(error = %e, "Failed to deliver webhooks");
+            }
+            if let Err(e) = database
+                .purge_webhook_deliveries(now - config.retention_secs)
+                .await
+            {
+                warn!(error = %e, "Failed to purge webhook deliveries");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation() {
+        assert!(validate_url("https://hooks.example.com/raito").is_ok());
+        assert!(validate_url("http://10.0.0.5:8080/events").is_ok());
+        assert!(validate_url("ftp://hooks.example.com").is_err());
+        assert!(validate_url("hooks.example.com/raito").is_err());
+
+        assert!(validate_secret(&generate_secret()).is_ok());
+        assert!(validate_secret("short").is_err());
+
+        let events = [
+            WebhookEvent::Reorg,
+            WebhookEvent::JobFailed,
+            WebhookEvent::Reorg,
+        ];
+        assert_eq!(
+            normalize_events(&events).unwrap(),
+            [WebhookEvent::Reorg, WebhookEvent::JobFailed]
+        );
+        assert!(normalize_events(&[]).is_err());
+    }
+
+    #[test]
+    fn test_backoff_and_signature() {
+        let config = WebhookConfig::default();
+        let waits: Vec<_> = (1..=4)
+            .map(|failures| config.backoff_secs(failures))
+            .collect();
+        assert_eq!(waits, [10, 20, 40, 80]);
+        assert_eq!(config.backoff_secs(12), 3600);
+        assert_eq!(config.backoff_secs(200), 3600);
+
+        // Checked against `printf '1700000000.{}' | openssl dgst -sha256 -hmac whsec_test`
+        assert_eq!(
+            signature("whsec_test", 1_700_000_000, b"{}"),
+            "t=1700000000,v1=35495024f4ef3f94e5a93e22221544c4b75e9a42300cd965ab81cb85cd994e91"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_due() {
+        use crate::database::DatabaseConfig;
+        use axum::{
+            body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post, Router,
+        };
+        use std::sync::Mutex;
+
+        type Received = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+        async fn receive(
+            State(received): State<Received>,
+            headers: HeaderMap,
+            body: Bytes,
+        ) -> StatusCode {
+            received.lock().unwrap().push((headers, body));
+            StatusCode::NO_CONTENT
+        }
+        let received = Received::default();
+        let app = Router::new()
+            .route("/ok", post(receive))
+            .route("/down", post(|| async { StatusCode::SERVICE_UNAVAILABLE }))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        let subscribe = |path: &str, events: Vec<WebhookEvent>| CreateWebhookRequest {
+            url: format!("http://{address}{path}"),
+            secret: None,
+            events,
+            active: None,
+        };
+        let ok = create(&database, &subscribe("/ok", vec![WebhookEvent::JobFailed]))
+            .await
+            .unwrap();
+        let down = create(
+            &database,
+            &subscribe("/down", vec![WebhookEvent::JobFailed, WebhookEvent::Reorg]),
+        )
+        .await
+        .unwrap();
+
+        let config = WebhookConfig {
+            max_attempts: 2,
+            ..WebhookConfig::default()
+        };
+        let client = client(&config).unwrap();
+        let now = 1_700_000_000;
+        let event = Event::JobFailed { height: 7 };
+        assert_eq!(enqueue(&database, &event, now).await.unwrap(), 2);
+        let block = Event::ProofGenerated { height: 7 };
+        assert_eq!(enqueue(&database, &block, now).await.unwrap(), 0);
+
+        assert_eq!(
+            deliver_due(&database, &client, &config, now).await.unwrap(),
+            2
+        );
+        {
+            let received = received.lock().unwrap();
+            let (headers, body) = &received[0];
+            assert_eq!(headers[EVENT_HEADER], "job_failed");
+            assert_eq!(
+                headers[SIGNATURE_HEADER].to_str().unwrap(),
+                signature(&ok.secret, now, body)
+            );
+            let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+            assert_eq!(
+                payload,
+                serde_json::json!({ "type": "job_failed", "height": 7 })
+            );
+        }
+
+        let log = |id| database.list_webhook_deliveries(id, None, 10, None);
+        let delivered = log(ok.webhook.id).await.unwrap().deliveries;
+        assert_eq!(delivered[0].status, DeliveryStatus::Delivered);
+        assert_eq!(delivered[0].last_status_code, Some(204));
+
+        // The failed delivery waits out its backoff, then is dead-lettered
+        let failed = &log(down.webhook.id).await.unwrap().deliveries[0];
+        assert_eq!(failed.status, DeliveryStatus::Pending);
+        assert_eq!(failed.last_status_code, Some(503));
+        assert_eq!(failed.next_attempt_at, now + 10);
+        assert_eq!(
+            deliver_due(&database, &client, &config, now + 9)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            deliver_due(&database, &client, &config, now + 10)
+                .await
+                .unwrap(),
+            1
+        );
+        let dead = &log(down.webhook.id).await.unwrap().deliveries[0];
+        assert_eq!(dead.status, DeliveryStatus::Dead);
+        assert_eq!(dead.attempts, 2);
+        assert_eq!(dead.finished_at, Some(now + 10));
+        assert_eq!(
+            deliver_due(&database, &client, &config, now + 3600)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        let pending = database
+            .list_webhook_deliveries(down.webhook.id, Some(DeliveryStatus::Pending), 10, None)
+            .await
+            .unwrap();
+        assert!(pending.deliveries.is_empty());
+        assert_eq!(
+            database.purge_webhook_deliveries(now + 11).await.unwrap(),
+            2
+        );
+        assert!(matches!(
+            database.list_webhook_deliveries(999, None, 10, None).await,
+            Err(AppError::WebhookNotFound(999))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_deliver_due() {
+        use crate::database::DatabaseConfig;
+        use axum::{extract::State, http::StatusCode, routing::post, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let received = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/ok",
+                post(|State(received): State<Arc<AtomicUsize>>| async move {
+                    received.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::NO_CONTENT
+                }),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let database = Database::new(DatabaseConfig::test_config()).await.unwrap();
+        create(
+            &database,
+            &CreateWebhookRequest {
+                url: format!("http://{address}/ok"),
+                secret: None,
+                events: vec![WebhookEvent::JobFailed],
+                active: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let config = WebhookConfig::default();
+        let client = client(&config).unwrap();
+        let now = 1_700_000_000;
+        for height in 0..20 {
+            enqueue(&database, &Event::JobFailed { height }, now)
+                .await
+                .unwrap();
+        }
+
+        let (first, second) = tokio::join!(
+            deliver_due(&database, &client, &config, now),
+            deliver_due(&database, &client, &config, now),
+        );
+        assert_eq!(first.unwrap() + second.unwrap(), 20);
+        assert_eq!(received.load(Ordering::SeqCst), 20);
+
+        // A claim that is never recorded comes due again once its lease ends
+        enqueue(&database, &Event::JobFailed { height: 20 }, now)
+            .await
+            .unwrap();
+        let lease_until = now + config.lease_secs() as i64;
+        let claimed = database
+            .claim_webhook_deliveries(now, lease_until, 10)
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert!(database
+            .claim_webhook_deliveries(lease_until - 1, lease_until, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            deliver_due(&database, &client, &config, lease_until)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(received.load(Ordering::SeqCst), 21);
+    }
+}
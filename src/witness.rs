@@ -0,0 +1,150 @@
+use crate::hash::{sha256d, Wtxid};
+use serde_json::Value;
+
+/// Script of the coinbase output committing to the witness tree (BIP 141):
+/// `OP_RETURN`, a 36-byte push, and the `aa21a9ed` header.
+const COMMITMENT_HEADER: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// The segwit side of a block, available when the block document carries
+/// every transaction's raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockWitness {
+    /// In block order.
+    pub wtxids: Vec<Wtxid>,
+    /// Root of the tree over `wtxids` with the coinbase's taken as zero, in
+    /// display byte order like `merkle_root`.
+    pub witness_merkle_root: String,
+    /// The 32 bytes committed to in the coinbase, as hex in script order;
+    /// `None` for blocks without segwit transactions.
+    pub witness_commitment: Option<String>,
+}
+
+/// Computes a block's wtxids and witness tree from its document, or `None`
+/// when some transaction's raw bytes are missing.
+pub fn block_witness(block: &Value) -> Option<BlockWitness> {
+    let txids = block["txids"].as_array()?;
+    let transactions = block["transactions"].as_array()?;
+    let wtxids = txids
+        .iter()
+        .map(|txid| {
+            let tx = transactions.iter().find(|tx| tx["txid"] == *txid)?;
+            let raw = hex::decode(tx["raw"].as_str()?).ok()?;
+            Some(Wtxid::from_internal_bytes(sha256d(&raw)))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if wtxids.is_empty() {
+        return None;
+    }
+
+    // The coinbase can't commit to itself, so it stands in as zero
+    let leaves: Vec<[u8; 32]> = std::iter::once([0; 32])
+        .chain(wtxids[1..].iter().map(|wtxid| wtxid.to_internal_bytes()))
+        .collect();
+    let mut root = merkle_root(&leaves);
+    root.reverse();
+
+    let coinbase = transactions.iter().find(|tx| tx["txid"] == txids[0]);
+    let witness_commitment = coinbase
+        .and_then(|tx| tx["outputs"].as_array())
+        .and_then(|outputs| {
+            outputs
+                .iter()
+                .rev()
+                .filter_map(|output| hex::decode(output["script_pubkey"].as_str()?).ok())
+                .find_map(|script| commitment(&script))
+        })
+        .map(hex::encode);
+
+    Some(BlockWitness {
+        wtxids,
+        witness_merkle_root: hex::encode(root),
+        witness_commitment,
+    })
+}
+
+/// The 32-byte commitment in a coinbase output script, if it is one. When
+/// several outputs match, consensus takes the last.
+fn commitment(script: &[u8]) -> Option<[u8; 32]> {
+    script
+        .strip_prefix(&COMMITMENT_HEADER)?
+        .get(..32)?
+        .try_into()
+        .ok()
+}
+
+/// Bitcoin's merkle root over `leaves` in internal byte order, pairing the
+/// last node with itself on odd levels.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let mut concat = [0u8; 64];
+                concat[..32].copy_from_slice(&pair[0]);
+                concat[32..].copy_from_slice(pair.get(1).unwrap_or(&pair[0]));
+                sha256d(&concat)
+            })
+            .collect();
+    }
+    level.first().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merkle_root_matches_block_header() {
+        let blocks: Vec<Value> =
+            serde_json::from_str(include_str!("../data/mock_blocks.json")).unwrap();
+        let block = &blocks[0];
+        let leaves: Vec<[u8; 32]> = block["txids"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|txid| {
+                let txid: crate::hash::Txid = txid.as_str().unwrap().parse().unwrap();
+                txid.to_internal_bytes()
+            })
+            .collect();
+        let mut root = merkle_root(&leaves);
+        root.reverse();
+        assert_eq!(hex::encode(root), block["merkle_root"]);
+    }
+
+    #[test]
+    fn test_block_witness() {
+        let commitment = [7u8; 32];
+        let script = [&COMMITMENT_HEADER[..], &commitment].concat();
+        let block = json!({
+            "txids": ["aa".repeat(32), "bb".repeat(32)],
+            "transactions": [
+                {
+                    "txid": "aa".repeat(32),
+                    "raw": "01",
+                    "outputs": [{ "script_pubkey": "51" }, { "script_pubkey": hex::encode(&script) }],
+                },
+                { "txid": "bb".repeat(32), "raw": "02" },
+            ],
+        });
+        let witness = block_witness(&block).unwrap();
+        assert_eq!(
+            witness.wtxids,
+            [
+                Wtxid::from_internal_bytes(sha256d(&[1])),
+                Wtxid::from_internal_bytes(sha256d(&[2]))
+            ]
+        );
+        let mut root = merkle_root(&[[0; 32], sha256d(&[2])]);
+        root.reverse();
+        assert_eq!(witness.witness_merkle_root, hex::encode(root));
+        assert_eq!(witness.witness_commitment, Some(hex::encode(commitment)));
+
+        // Missing raw bytes leave the witness tree unknown
+        let mut partial = block.clone();
+        partial["transactions"][1]["raw"] = Value::Null;
+        assert_eq!(block_witness(&partial), None);
+    }
+}